@@ -0,0 +1,121 @@
+# English messages (fallback for every other locale).
+
+## Shared
+
+auth-required = No API key found. Please run '{ $cli } auth' first
+init-required = Project not initialized. Please run '{ $cli } init <repo_id>' first
+
+## auth
+
+auth-prompt = Please enter your Verilib API key:
+auth-key-label = API Key:
+auth-read-failed = Failed to read API key from input
+auth-empty = API key cannot be empty
+auth-storing = Attempting to store API key...
+auth-stored = API key successfully stored.
+auth-stored-keyring = Your API key is securely stored in the system keyring.
+auth-verified = Storage verified successfully.
+auth-verify-mismatch = Warning: Storage verification failed - keys don't match
+auth-verify-failed = Warning: Could not verify storage: { $error }
+auth-store-failed = Failed to store API key: { $error }
+auth-platform-help = Platform-specific help:
+auth-store-failed-short = Failed to store API key
+auth-key-rejected = API key invalid or revoked. Run '{ $cli } auth' to store a new one
+auth-key-rejected-prompt = The server rejected the stored API key. Enter a new one now?
+
+## init
+
+init-with-id = Initializing project with repository ID: { $id }
+init-creating = Creating new repository from git URL: { $url }
+init-created = Repository created successfully!
+init-repo-id = Repository ID: { $id }
+init-mkdir-failed = Failed to create .verilib directory
+init-select-mode = Select execution mode
+init-mode-local = Local (Default)
+init-mode-docker = Docker
+init-mode-failed = Failed to select execution mode
+init-url-options = Repository URL Options:
+init-url-full = • Full repository: https://github.com/user/repo
+init-url-branch = • Specific branch: https://github.com/user/repo@branch-name
+init-url-folder = • Folder only: https://github.com/user/repo/tree/main/folder-name
+init-url-folder-branch = • Folder from branch: https://github.com/user/repo/tree/main/folder-name@branch-name
+init-url-prompt = Enter repository URL
+init-url-input-failed = Failed to get git URL input
+init-url-empty = Repository URL cannot be empty
+init-collecting = Collecting repository information...
+init-conflict-repo = This directory is already initialized for repository { $id }.
+init-conflict-uncommitted = .verilib has { $count } uncommitted changes:
+init-conflict-prompt = Existing local state would be overwritten
+init-conflict-snapshot = Snapshot .verilib, then continue
+init-conflict-overwrite = Continue without a snapshot
+init-conflict-cancel = Cancel
+init-conflict-prompt-failed = Failed to read selection
+init-conflict-cancelled = Init cancelled; nothing was changed.
+init-conflict-abort = Refusing to overwrite existing local state. Re-run with --force (optionally with --snapshot) to continue.
+init-snapshot-saved = Saved a snapshot of .verilib to { $path }
+
+## freshness
+
+freshness-structure = the structure files
+freshness-stale = { $artifact } is older than { $input }; run 'verilib-cli { $stage }' to update it.
+freshness-stale-rerun = { $artifact } is older than { $input }. Run 'verilib-cli { $stage }' first, or pass --auto to run it now.
+freshness-auto = Running '{ $stage }' first...
+
+## verify
+
+verify-stubs-missing = { $path } not found. Run 'verilib-cli atomize' first.
+verify-no-owned-stubs = No stubs are owned by '{ $owner }'. Check the 'owners' rules in config.json and re-run 'atomize'.
+verify-scoping = Scoping to { $count } stubs owned by '{ $owner }'
+verify-checking = Checking stubs for verification failures...
+verify-updated = Updated { $path }
+verify-all-passed = All { $count } stubs passed verification.
+verify-found-failures = Found { $count } stubs with status "failure":
+verify-failed = { $count } stubs failed verification. Run 'verify' to update verification status.
+verify-passed-except-expected = No unexpected failures among { $count } stubs ({ $expected } expected failures).
+verify-expected-now-passing = { $count } functions listed as expected failures no longer fail; remove them from the list:
+verify-expected-unknown = { $count } functions listed as expected failures have no stub; remove them from the list.
+verify-changes-header = VERIFICATION STATUS CHANGES
+verify-newly-verified = Newly verified ({ $count }):
+verify-none-verified = No newly verified items
+verify-newly-unverified = Newly unverified ({ $count }):
+verify-none-unverified = No newly unverified items
+verify-total-verified = Newly verified: +{ $count }
+verify-total-unverified = Newly unverified: -{ $count }
+verify-proofs-missing = proofs.json not found at { $path }. Run without --no-probe first to generate it.
+verify-loading-proofs = Loading proofs from { $path }...
+verify-loaded-proofs = Loaded { $count } proofs
+verify-running = Running probe-verus verify on { $path }...
+verify-running-module = Running probe-verus verify on { $path } (module: { $module })...
+verify-running-function = Running probe-verus verify on { $function } in { $path } (module: { $module })...
+verify-probe-failed = probe-verus verify failed (exit code: { $code }) and no results were produced
+verify-saved = Verification results saved to { $path }
+verify-unknown-profile = Unknown verify profile '{ $profile }'. Profiles in config.json: { $available }
+verify-using-profile = Using verify profile '{ $profile }'
+verify-profile-mismatch = proofs.json was produced with profile { $recorded }, not { $requested }. Re-run without --no-probe to regenerate it.
+verify-env-captured = Environment recorded to { $path }
+verify-env-matches = Environment matches { $path }
+verify-env-diverged = Environment differs from { $path } in { $count } fields (baseline -> current):
+verify-env-absent = (absent)
+verify-function-not-found = No stub has the code-name or display-name '{ $name }'. Run 'verilib-cli atomize' if the function is new.
+verify-function-ambiguous = Several stubs have the display-name '{ $name }'; pass one of their code-names instead: { $candidates }
+verify-function-no-module = Stub { $name } has no code-module. Run 'verilib-cli atomize' first.
+verify-function-verified = { $name }: verified ({ $elapsed })
+verify-function-failed = { $name }: not verified, status { $status } ({ $elapsed })
+verify-function-no-result = { $name }: no result in proofs.json
+verify-function-no-timing = from proofs.json
+verify-function-cached = from cache
+verify-cache-hit = All { $count } functions in scope have cached results; skipping probe-verus
+verify-cache-stats = Cache: { $hits } hits, { $misses } misses, { $saved } saved
+verify-cache-store-failed = failed to update the verification cache: { $error }
+verify-diff-no-atoms = { $path } not found. --modules-from-diff maps changed lines to modules with atoms.json; run 'verilib-cli atomize' first.
+verify-diff-changed = Modules changed by { $range } ({ $count }): { $modules }
+verify-diff-dependents = Dependent modules, up to depth { $depth } ({ $count }): { $modules }
+verify-diff-skipped = Skipped modules ({ $count }): { $modules }
+verify-diff-nothing = No module is changed by { $range }; nothing to verify.
+verify-import-loaded = Imported { $count } Verus results from { $path }
+verify-import-unmatched = { $count } Verus functions match no stub and were ignored: { $functions }
+verify-matrix-running = Running { $count } verify profiles, { $jobs } at a time...
+verify-matrix-header = PROFILE MATRIX
+verify-matrix-profile = { $profile }: { $verified } of { $total } verified
+verify-matrix-agree = All profiles agree on every function.
+verify-matrix-differences = { $count } functions differ between profiles:
@@ -0,0 +1,121 @@
+# Mensajes en español.
+
+## Shared
+
+auth-required = No se encontró ninguna clave de API. Ejecuta primero '{ $cli } auth'
+init-required = El proyecto no está inicializado. Ejecuta primero '{ $cli } init <repo_id>'
+
+## auth
+
+auth-prompt = Introduce tu clave de API de Verilib:
+auth-key-label = Clave de API:
+auth-read-failed = No se pudo leer la clave de API
+auth-empty = La clave de API no puede estar vacía
+auth-storing = Guardando la clave de API...
+auth-stored = Clave de API guardada correctamente.
+auth-stored-keyring = Tu clave de API está guardada de forma segura en el llavero del sistema.
+auth-verified = Almacenamiento verificado correctamente.
+auth-verify-mismatch = Aviso: la verificación del almacenamiento falló: las claves no coinciden
+auth-verify-failed = Aviso: no se pudo verificar el almacenamiento: { $error }
+auth-store-failed = No se pudo guardar la clave de API: { $error }
+auth-platform-help = Ayuda específica de la plataforma:
+auth-store-failed-short = No se pudo guardar la clave de API
+auth-key-rejected = La clave de API no es válida o fue revocada. Ejecuta '{ $cli } auth' para guardar una nueva
+auth-key-rejected-prompt = El servidor rechazó la clave de API guardada. ¿Introducir una nueva ahora?
+
+## init
+
+init-with-id = Inicializando el proyecto con el ID de repositorio: { $id }
+init-creating = Creando un repositorio nuevo desde la URL de git: { $url }
+init-created = ¡Repositorio creado correctamente!
+init-repo-id = ID de repositorio: { $id }
+init-mkdir-failed = No se pudo crear el directorio .verilib
+init-select-mode = Selecciona el modo de ejecución
+init-mode-local = Local (predeterminado)
+init-mode-docker = Docker
+init-mode-failed = No se pudo seleccionar el modo de ejecución
+init-url-options = Opciones de URL del repositorio:
+init-url-full = • Repositorio completo: https://github.com/user/repo
+init-url-branch = • Rama concreta: https://github.com/user/repo@branch-name
+init-url-folder = • Solo una carpeta: https://github.com/user/repo/tree/main/folder-name
+init-url-folder-branch = • Carpeta de una rama: https://github.com/user/repo/tree/main/folder-name@branch-name
+init-url-prompt = Introduce la URL del repositorio
+init-url-input-failed = No se pudo leer la URL de git
+init-url-empty = La URL del repositorio no puede estar vacía
+init-collecting = Recopilando información del repositorio...
+init-conflict-repo = Este directorio ya está inicializado para el repositorio { $id }.
+init-conflict-uncommitted = .verilib tiene { $count } cambios sin confirmar:
+init-conflict-prompt = Se sobrescribiría el estado local existente
+init-conflict-snapshot = Guardar una copia de .verilib y continuar
+init-conflict-overwrite = Continuar sin copia
+init-conflict-cancel = Cancelar
+init-conflict-prompt-failed = No se pudo leer la selección
+init-conflict-cancelled = Init cancelado; no se cambió nada.
+init-conflict-abort = No se sobrescribirá el estado local existente. Vuelve a ejecutar con --force (y opcionalmente --snapshot) para continuar.
+init-snapshot-saved = Copia de .verilib guardada en { $path }
+
+## freshness
+
+freshness-structure = los archivos de estructura
+freshness-stale = { $artifact } es anterior a { $input }; ejecuta 'verilib-cli { $stage }' para actualizarlo.
+freshness-stale-rerun = { $artifact } es anterior a { $input }. Ejecuta primero 'verilib-cli { $stage }', o usa --auto para ejecutarlo ahora.
+freshness-auto = Ejecutando primero '{ $stage }'...
+
+## verify
+
+verify-stubs-missing = No se encontró { $path }. Ejecuta primero 'verilib-cli atomize'.
+verify-no-owned-stubs = Ningún stub pertenece a '{ $owner }'. Revisa las reglas 'owners' de config.json y vuelve a ejecutar 'atomize'.
+verify-scoping = Limitando a { $count } stubs de '{ $owner }'
+verify-checking = Buscando stubs con fallos de verificación...
+verify-updated = Actualizado { $path }
+verify-all-passed = Los { $count } stubs pasaron la verificación.
+verify-found-failures = Se encontraron { $count } stubs con estado "failure":
+verify-failed = { $count } stubs fallaron la verificación. Ejecuta 'verify' para actualizar el estado.
+verify-passed-except-expected = Ningún fallo inesperado entre { $count } stubs ({ $expected } fallos esperados).
+verify-expected-now-passing = { $count } funciones listadas como fallos esperados ya no fallan; quítalas de la lista:
+verify-expected-unknown = { $count } funciones listadas como fallos esperados no tienen stub; quítalas de la lista.
+verify-changes-header = CAMBIOS EN EL ESTADO DE VERIFICACIÓN
+verify-newly-verified = Verificados ahora ({ $count }):
+verify-none-verified = Ningún elemento verificado nuevo
+verify-newly-unverified = Ya no verificados ({ $count }):
+verify-none-unverified = Ningún elemento ha dejado de estar verificado
+verify-total-verified = Verificados ahora: +{ $count }
+verify-total-unverified = Ya no verificados: -{ $count }
+verify-proofs-missing = No se encontró proofs.json en { $path }. Ejecuta primero sin --no-probe para generarlo.
+verify-loading-proofs = Cargando pruebas desde { $path }...
+verify-loaded-proofs = { $count } pruebas cargadas
+verify-running = Ejecutando probe-verus verify en { $path }...
+verify-running-module = Ejecutando probe-verus verify en { $path } (módulo: { $module })...
+verify-running-function = Ejecutando probe-verus verify sobre { $function } en { $path } (módulo: { $module })...
+verify-probe-failed = probe-verus verify falló (código de salida: { $code }) y no produjo resultados
+verify-saved = Resultados de verificación guardados en { $path }
+verify-unknown-profile = Perfil de verificación desconocido '{ $profile }'. Perfiles en config.json: { $available }
+verify-using-profile = Usando el perfil de verificación '{ $profile }'
+verify-profile-mismatch = proofs.json se generó con el perfil { $recorded }, no { $requested }. Vuelve a ejecutar sin --no-probe para regenerarlo.
+verify-env-captured = Entorno registrado en { $path }
+verify-env-matches = El entorno coincide con { $path }
+verify-env-diverged = El entorno difiere de { $path } en { $count } campos (referencia -> actual):
+verify-env-absent = (ausente)
+verify-function-not-found = Ningún stub tiene el code-name o display-name '{ $name }'. Ejecuta 'verilib-cli atomize' si la función es nueva.
+verify-function-ambiguous = Varios stubs tienen el display-name '{ $name }'; indica uno de sus code-names: { $candidates }
+verify-function-no-module = El stub { $name } no tiene code-module. Ejecuta primero 'verilib-cli atomize'.
+verify-function-verified = { $name }: verificado ({ $elapsed })
+verify-function-failed = { $name }: no verificado, estado { $status } ({ $elapsed })
+verify-function-no-result = { $name }: sin resultado en proofs.json
+verify-function-no-timing = desde proofs.json
+verify-function-cached = desde la caché
+verify-cache-hit = Las { $count } funciones del alcance tienen resultados en caché; se omite probe-verus
+verify-cache-stats = Caché: { $hits } aciertos, { $misses } fallos, { $saved } ahorrados
+verify-cache-store-failed = no se pudo actualizar la caché de verificación: { $error }
+verify-diff-no-atoms = No se encontró { $path }. --modules-from-diff asigna las líneas cambiadas a módulos con atoms.json; ejecute primero 'verilib-cli atomize'.
+verify-diff-changed = Módulos cambiados por { $range } ({ $count }): { $modules }
+verify-diff-dependents = Módulos dependientes, hasta la profundidad { $depth } ({ $count }): { $modules }
+verify-diff-skipped = Módulos omitidos ({ $count }): { $modules }
+verify-diff-nothing = Ningún módulo cambia en { $range }; no hay nada que verificar.
+verify-import-loaded = Se importaron { $count } resultados de Verus de { $path }
+verify-import-unmatched = { $count } funciones de Verus no corresponden a ningún stub y se ignoraron: { $functions }
+verify-matrix-running = Ejecutando { $count } perfiles de verificación, { $jobs } a la vez...
+verify-matrix-header = MATRIZ DE PERFILES
+verify-matrix-profile = { $profile }: { $verified } de { $total } verificadas
+verify-matrix-agree = Todos los perfiles coinciden en todas las funciones.
+verify-matrix-differences = { $count } funciones difieren entre perfiles:
@@ -1,4 +1,15 @@
-use clap::{Parser, Subcommand};
+use crate::commands::api::ListSort;
+use crate::commands::atomize::Analyzer;
+use crate::commands::functions::FunctionSort;
+use crate::commands::grep::{GrepScope, GrepStatus};
+use crate::commands::schema::SchemaArtifact;
+use crate::commands::types::Visibility;
+use crate::commands::ExportFormat;
+use crate::config::PipelineStage;
+use crate::output::ColorChoice;
+use crate::query::Query;
+use crate::structure::{CertStoreKind, FrontmatterFormat, LayoutKind, ReviewStatus};
+use clap::{ArgGroup, Parser, Subcommand};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -18,27 +29,152 @@ pub struct Cli {
     #[arg(long, global = true)]
     pub dry_run: bool,
 
+    /// When to color output: auto (terminals only, honoring NO_COLOR), always, or never
+    #[arg(long, global = true, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Server URL (default: $VERILIB_BASE_URL, then the URL saved by init, then production)
+    #[arg(long, global = true, alias = "url", value_name = "URL")]
+    pub base_url: Option<String>,
+
+    /// Use the secure file storage for credentials if the keyring is locked or unavailable
+    #[arg(long, global = true)]
+    pub fallback_file_storage: bool,
+
+    /// Keep probe-verus intermediate files under .verilib/intermediates/ instead of deleting them
+    #[arg(long, global = true)]
+    pub keep_intermediates: bool,
+
+    /// Project root directory (default: the nearest directory from here up that has a .verilib)
+    #[arg(long = "project-root", global = true, value_name = "DIR")]
+    pub project_dir: Option<PathBuf>,
+
+    /// Ignore the `defaults` flags of the user and project configs
+    #[arg(long, global = true)]
+    pub no_defaults: bool,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
+// Parsed once per run, so the size of the largest variant doesn't matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 pub enum Commands {
     /// Authenticate with API key (interactive prompt)
     Auth,
     /// Show current authentication status
-    Status,
+    Status {
+        /// Compare the local repository tree with the server and suggest pull or deploy
+        #[arg(long)]
+        remote: bool,
+        /// Stable tab-separated output for scripts
+        #[arg(long)]
+        porcelain: bool,
+    },
     /// Initialize project with repository tree
     Init {
         /// Repository ID to fetch
         #[arg(long)]
         id: Option<String>,
-        /// API base URL (defaults to production)
+        /// Overwrite local state from another repository or with uncommitted changes
+        #[arg(long)]
+        force: bool,
+        /// Copy .verilib to .verilib.snapshot-<timestamp> before changing it
+        #[arg(long)]
+        snapshot: bool,
+        /// Team or organization that owns a new repository
+        #[arg(long, conflicts_with = "id")]
+        team: Option<String>,
+        /// Visibility of a new repository (prompted for if not given)
+        #[arg(long, value_enum, conflicts_with = "id")]
+        visibility: Option<Visibility>,
+        /// Topic tag of a new repository; repeat or separate with commas
+        #[arg(long = "topic", value_name = "TOPIC", conflicts_with = "id")]
+        topics: Vec<String>,
+    },
+    /// Deploy repository changes to the server
+    Deploy {
+        /// Release note sent with this deployment
+        #[arg(long, conflicts_with = "changelog_file")]
+        message: Option<String>,
+        /// Read the release note from a file
+        #[arg(long, value_name = "PATH")]
+        changelog_file: Option<PathBuf>,
+        /// Deploy only this subtree of .verilib to its own server repository
+        #[arg(long, value_name = "DIR")]
+        subdir: Option<String>,
+        /// Team or organization that owns a new repository
+        #[arg(long)]
+        team: Option<String>,
+        /// Visibility of a new repository (prompted for if not given)
+        #[arg(long, value_enum)]
+        visibility: Option<Visibility>,
+        /// Topic tag of a new repository; repeat or separate with commas
+        #[arg(long = "topic", value_name = "TOPIC")]
+        topics: Vec<String>,
+        /// Register a new repository and save its ID without uploading the tree
         #[arg(long)]
-        url: Option<String>,
+        create_only: bool,
     },
     /// Reclone repository after checking for uncommitted changes
     Reclone,
+    /// Compare specified flags with the server and resolve the differences
+    SyncFlags {
+        /// Take the server's flags
+        #[arg(long, group = "direction")]
+        pull: bool,
+        /// Deploy the local flags
+        #[arg(long, group = "direction")]
+        push: bool,
+        /// Choose a side for each atom that differs
+        #[arg(long, group = "direction")]
+        interactive: bool,
+    },
+    /// Renumber atom indices in a .verilib directory and fix references
+    Reindex {
+        /// Directory to renumber, relative to .verilib or, inside it, to the current directory (default: the .verilib root)
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+        /// Also renumber every subdirectory
+        #[arg(short, long)]
+        recursive: bool,
+    },
+    /// Show server-side atomization status for the current repository
+    AtomizationStatus {
+        /// Poll until atomization completes
+        #[arg(long)]
+        wait: bool,
+        /// Give up waiting after this many seconds (requires --wait)
+        #[arg(long, requires = "wait")]
+        timeout: Option<u64>,
+    },
+    /// Show who ran which mutating commands in this checkout
+    History {
+        /// Number of most recent entries to show
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Show who changed which meta file flags, and when
+    FlagLog {
+        #[command(subcommand)]
+        command: FlagLogCommands,
+    },
+    /// Summarize recorded command timings (enable with "metrics": true in config.json)
+    Metrics {
+        #[command(subcommand)]
+        command: MetricsCommands,
+    },
+    /// Manage encryption keys for atom contents
+    Keys {
+        #[command(subcommand)]
+        command: KeysCommands,
+    },
+    /// Read and edit the .meta.verilib files of atoms
+    Api {
+        #[command(subcommand)]
+        command: ApiCommands,
+    },
     // ===== Structure Commands (merged from verilib-structure) =====
     /// Initialize structure files from source analysis
     Create {
@@ -49,6 +185,55 @@ pub enum Commands {
         /// Root directory for structure files (default: .verilib/structure)
         #[arg(long)]
         root: Option<PathBuf>,
+
+        /// Naming scheme for structure files, saved to config.json (default: path)
+        #[arg(long, value_enum)]
+        layout: Option<LayoutKind>,
+
+        /// Seed config.json, .verilibignore, spec templates and hooks from a template
+        /// directory or git URL (URL#REF for a branch or tag)
+        #[arg(long, value_name = "PATH|URL")]
+        template: Option<String>,
+    },
+
+    /// Move structure files to another naming scheme and rekey stubs.json
+    MigrateLayout {
+        /// Project root directory (default: current working directory)
+        #[arg(default_value = ".")]
+        project_root: PathBuf,
+
+        /// Layout to migrate to
+        #[arg(long, value_enum)]
+        to: LayoutKind,
+    },
+
+    /// Move spec certs to another storage backend
+    MigrateCerts {
+        /// Project root directory (default: current working directory)
+        #[arg(default_value = ".")]
+        project_root: PathBuf,
+
+        /// Backend to migrate to
+        #[arg(long, value_enum)]
+        to: CertStoreKind,
+    },
+
+    /// Rewrite structure file frontmatter as YAML or TOML
+    MigrateFrontmatter {
+        /// Project root directory (default: current working directory)
+        #[arg(default_value = ".")]
+        project_root: PathBuf,
+
+        /// Frontmatter format to migrate to
+        #[arg(long, value_enum)]
+        to: FrontmatterFormat,
+    },
+
+    /// Rewrite backslash-separated paths in meta files with forward slashes
+    MigratePaths {
+        /// Project root directory (default: current working directory)
+        #[arg(default_value = ".")]
+        project_root: PathBuf,
     },
 
     /// Enrich structure files with metadata from SCIP atoms
@@ -73,9 +258,31 @@ pub enum Commands {
         #[arg(long)]
         atoms_only: bool,
 
-        /// Use rust-analyzer instead of verus-analyzer for SCIP generation
-        #[arg(long)]
+        /// SCIP indexer for probe-verus: auto (from the crate and installed tools), rust, or verus
+        #[arg(long, value_enum, default_value_t = Analyzer::Auto)]
+        analyzer: Analyzer,
+
+        /// Same as --analyzer rust
+        #[arg(long, hide = true, conflicts_with = "analyzer")]
         rust_analyzer: bool,
+
+        /// Build atoms.json from an existing SCIP index instead of running probe-verus
+        #[arg(long, value_name = "INDEX", conflicts_with_all = ["no_probe", "analyzer", "rust_analyzer"])]
+        from_scip: Option<PathBuf>,
+
+        /// Write every stub that could not be matched to an atom, with the reason, to this file
+        #[arg(long, value_name = "PATH", conflicts_with = "atoms_only")]
+        report_skips: Option<PathBuf>,
+
+        /// Match a stub to the atom starting nearest its code-line within N lines
+        /// (default: line-tolerance in config.json, or 5; 0 disables)
+        #[arg(long, value_name = "N", conflicts_with = "atoms_only")]
+        line_tolerance: Option<u32>,
+
+        /// Point skipped stubs at the function they most likely became (a confident,
+        /// unambiguous possible rename) and move their .md files to match
+        #[arg(long, conflicts_with_all = ["atoms_only", "check_only"])]
+        apply_renames: bool,
     },
 
     /// Check specification status and manage spec certs
@@ -88,9 +295,283 @@ pub enum Commands {
         #[arg(short = 'n', long)]
         no_probe: bool,
 
-        /// Check if all stubs with specs have certs, error if any are missing
+        /// Check if all stubs with specs have certs, error if any are missing.
+        /// With --json, print the spec and cert status of every stub
         #[arg(short = 'c', long)]
         check_only: bool,
+
+        /// Pick functions to certify in a full-screen list with search and spec preview
+        #[arg(short = 'i', long, conflicts_with = "check_only")]
+        interactive: bool,
+
+        /// Re-run atomize first when stubs.json is older than the structure files
+        #[arg(long, conflicts_with = "check_only")]
+        auto: bool,
+
+        /// Certify the functions listed in a reviewed CSV with a code-name or
+        /// path column, instead of showing the menu
+        #[arg(long, value_name = "CSV", conflicts_with_all = ["check_only", "interactive"])]
+        approve_from: Option<PathBuf>,
+    },
+
+    /// Export a SCIP index for code-intelligence tools, or a bill of verification
+    Export {
+        /// Project root directory (default: current working directory)
+        #[arg(default_value = ".")]
+        project_root: PathBuf,
+
+        /// What to write
+        #[arg(long, value_enum, default_value_t = ExportFormat::Scip)]
+        format: ExportFormat,
+
+        /// File to write
+        #[arg(
+            short,
+            long,
+            value_name = "PATH",
+            required_unless_present = "scip",
+            conflicts_with = "scip"
+        )]
+        output: Option<PathBuf>,
+
+        /// Write a SCIP index to this path (same as --format scip --output PATH)
+        #[arg(long, value_name = "PATH", conflicts_with = "format")]
+        scip: Option<PathBuf>,
+
+        /// Sign the output with this SSH key, writing PATH.sig (ssh-keygen -Y sign)
+        #[arg(long, value_name = "KEY")]
+        sign_key: Option<PathBuf>,
+
+        /// Only export functions matching this filter expression, e.g. 'verified && module:crypto'
+        #[arg(long, value_name = "EXPR")]
+        filter: Option<Query>,
+    },
+
+    /// Write the .verilib directory to one reproducible .tar.zst archive
+    Pack {
+        /// Project root directory (default: current working directory)
+        #[arg(default_value = ".")]
+        project_root: PathBuf,
+
+        /// Archive to write
+        #[arg(short, long, value_name = "PATH", default_value = "verilib.tar.zst")]
+        output: PathBuf,
+
+        /// Leave out paths under .verilib matching this glob (repeatable), e.g. 'logs'
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+    },
+
+    /// Restore a .verilib directory from an archive written by 'pack'
+    Unpack {
+        /// Archive to read
+        archive: PathBuf,
+
+        /// Project root directory (default: current working directory)
+        #[arg(default_value = ".")]
+        project_root: PathBuf,
+
+        /// Skip paths under .verilib matching this glob (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+
+        /// Overwrite files under .verilib that the archive also contains
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Summarize atoms.json and, for full projects, stubs.json
+    Stats {
+        /// Project root directory (default: current working directory)
+        #[arg(default_value = ".")]
+        project_root: PathBuf,
+
+        /// Only report on atoms.json (implied in atoms-only projects)
+        #[arg(long, conflicts_with = "since")]
+        atoms: bool,
+
+        /// Show per-module progress since a stubs.json, snapshot directory,
+        /// pack archive, date (YYYY-MM-DD) or git ref
+        #[arg(long, value_name = "SNAPSHOT|REF|DATE")]
+        since: Option<String>,
+
+        /// Print the --since report as a Markdown table
+        #[arg(long, requires = "since")]
+        markdown: bool,
+
+        /// Also count stubs, specified and verified functions per owner
+        #[arg(long, conflicts_with_all = ["atoms", "since"])]
+        by_owner: bool,
+    },
+
+    /// Write the call graph between atoms as Graphviz DOT (JSON with --json)
+    Graph {
+        /// Project root directory (default: current working directory)
+        #[arg(default_value = ".")]
+        project_root: PathBuf,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+
+    /// Check spec certs against current stubs and spec contents
+    VerifyCerts {
+        /// Project root directory (default: current working directory)
+        #[arg(default_value = ".")]
+        project_root: PathBuf,
+
+        /// Remove malformed certs and certs whose function or spec is gone
+        #[arg(long)]
+        clean: bool,
+
+        /// Remove certs whose spec changed so that 'specify' asks to re-certify them
+        #[arg(long)]
+        requeue: bool,
+    },
+
+    /// List functions whose code changed since they were verified or certified
+    Drift {
+        /// Project root directory (default: current working directory)
+        #[arg(default_value = ".")]
+        project_root: PathBuf,
+
+        /// Fail if any function drifted (for CI)
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Remove stubs.json entries and spec certs left behind by deleted functions
+    Prune {
+        /// Project root directory (default: current working directory)
+        #[arg(default_value = ".")]
+        project_root: PathBuf,
+
+        /// Remove without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Convert stubs.json to CSV for spreadsheet triage, or apply an edited CSV
+    #[command(group(ArgGroup::new("direction").required(true)))]
+    Convert {
+        /// Project root directory (default: current working directory)
+        #[arg(default_value = ".")]
+        project_root: PathBuf,
+
+        /// Write stubs.json as CSV to this file ('-' for stdout)
+        #[arg(long, value_name = "CSV", group = "direction")]
+        to_csv: Option<PathBuf>,
+
+        /// Apply the specified, owner and ignore-reason columns of this CSV
+        #[arg(long, value_name = "CSV", group = "direction")]
+        from_csv: Option<PathBuf>,
+
+        /// Apply without asking for confirmation
+        #[arg(short, long, requires = "from_csv")]
+        yes: bool,
+    },
+
+    /// Search atom contents and spec bodies with a regex
+    Grep {
+        /// Regular expression to search for
+        pattern: String,
+
+        /// Set from the global --project-root
+        #[arg(skip = PathBuf::from("."))]
+        project_root: PathBuf,
+
+        /// Match case-insensitively
+        #[arg(short, long)]
+        ignore_case: bool,
+
+        /// Search only atoms or only specs
+        #[arg(long = "in", value_enum, default_value_t = GrepScope::All)]
+        scope: GrepScope,
+
+        /// Only show entries with this status
+        #[arg(long, value_enum)]
+        status: Option<GrepStatus>,
+    },
+
+    /// Print the atom containing a line of a source file (locations from stdin
+    /// as PATH:LINE when PATH is omitted)
+    CodeLocate {
+        /// Source file, relative to the project root
+        #[arg(requires = "line")]
+        path: Option<String>,
+
+        /// Line number in the file
+        line: Option<u32>,
+
+        /// Set from the global --project-root
+        #[arg(skip = PathBuf::from("."))]
+        project_root: PathBuf,
+
+        /// Match the atom starting nearest the line within N lines when none
+        /// contains it (default: line-tolerance in config.json, or 5; 0 disables)
+        #[arg(long, value_name = "N")]
+        line_tolerance: Option<u32>,
+    },
+
+    /// Query the functions in stubs.json
+    Functions {
+        #[command(subcommand)]
+        command: FunctionsCommands,
+    },
+
+    /// Check the layout.verilib graph layouts in .verilib
+    Layouts {
+        #[command(subcommand)]
+        command: LayoutsCommands,
+    },
+
+    /// Print the JSON Schema of a file written by this tool
+    Schema {
+        /// Which file's schema to print
+        #[arg(value_enum)]
+        artifact: SchemaArtifact,
+    },
+
+    /// Check tools, project files and credentials; --json adds stable check and fix IDs
+    Doctor {
+        /// Project root directory (default: current working directory)
+        #[arg(default_value = ".")]
+        project_root: PathBuf,
+    },
+
+    /// Check the installation with a miniature project and a mock server
+    Selftest {
+        /// Keep the temporary project for inspection
+        #[arg(long)]
+        keep: bool,
+    },
+
+    /// Generate a synthetic .verilib tree for use as a test fixture
+    GenerateFixture {
+        /// Directory to write the fixture into (default: ./fixture)
+        #[arg(default_value = "fixture")]
+        output: PathBuf,
+
+        /// Number of atoms to generate
+        #[arg(long, default_value_t = 50)]
+        atoms: usize,
+
+        /// Number of source modules to spread atoms across
+        #[arg(long, default_value_t = 5)]
+        modules: usize,
+
+        /// Fraction of atoms whose verification fails (0.0 - 1.0)
+        #[arg(long, default_value_t = 0.1)]
+        failure_rate: f64,
+
+        /// Seed for the deterministic generator
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+
+        /// Overwrite an existing .verilib directory in the output
+        #[arg(long)]
+        force: bool,
     },
 
     /// Run verification and update stubs with verification status
@@ -108,6 +589,10 @@ pub enum Commands {
         #[arg(long)]
         verify_only_module: Option<String>,
 
+        /// Only update and check stubs owned by this person or team
+        #[arg(long)]
+        owner: Option<String>,
+
         /// Skip running probe-verus verify and read proofs.json from disk
         #[arg(short = 'n', long)]
         no_probe: bool,
@@ -115,28 +600,250 @@ pub enum Commands {
         /// Check if any stub has status "failure", error if any are found
         #[arg(short = 'c', long)]
         check_only: bool,
+
+        /// With --check-only, tolerate failures of the code-names listed in this file
+        #[arg(long, value_name = "PATH", requires = "check_only")]
+        expect_failures: Option<PathBuf>,
+
+        /// Verify only this function, given by display-name or code-name
+        #[arg(
+            long,
+            value_name = "NAME",
+            conflicts_with_all = ["verify_only_module", "check_only", "owner"]
+        )]
+        function: Option<String>,
+
+        /// Named verifier profile from config.json whose args are passed to probe-verus
+        #[arg(long, conflicts_with = "check_only")]
+        profile: Option<String>,
+
+        /// Record tool versions, relevant env vars and platform to this file
+        #[arg(long, value_name = "PATH")]
+        capture_env: Option<PathBuf>,
+
+        /// Warn where the environment differs from one recorded with --capture-env
+        #[arg(long, value_name = "PATH")]
+        check_env: Option<PathBuf>,
+
+        /// Always run probe-verus, even when every result in scope is cached
+        #[arg(long, conflicts_with_all = ["no_probe", "check_only"])]
+        no_cache: bool,
+
+        /// Only verify modules this git diff range changes (e.g. origin/main...HEAD), and their dependents
+        #[arg(
+            long,
+            value_name = "RANGE",
+            conflicts_with_all = ["verify_only_module", "function", "check_only"]
+        )]
+        modules_from_diff: Option<String>,
+
+        /// With --modules-from-diff, add dependents up to this many calls away
+        #[arg(
+            long,
+            value_name = "N",
+            default_value_t = 1,
+            requires = "modules_from_diff"
+        )]
+        diff_depth: usize,
+
+        /// Take results from Verus JSON output (--output-json, --error-format=json) instead of probe-verus
+        #[arg(
+            long,
+            value_name = "PATH",
+            conflicts_with_all = [
+                "package",
+                "verify_only_module",
+                "no_probe",
+                "check_only",
+                "function",
+                "profile",
+                "no_cache",
+                "modules_from_diff"
+            ]
+        )]
+        import: Option<PathBuf>,
+
+        /// Run each of these config.json profiles (e.g. quick,full) and compare their results
+        #[arg(
+            long,
+            value_name = "PROFILES",
+            value_delimiter = ',',
+            num_args = 1..,
+            conflicts_with_all = [
+                "check_only",
+                "function",
+                "profile",
+                "modules_from_diff",
+                "import",
+                "owner",
+                "capture_env",
+                "check_env"
+            ]
+        )]
+        matrix: Vec<String>,
+
+        /// With --matrix, run up to this many profiles at once
+        #[arg(long, value_name = "N", default_value_t = 1, requires = "matrix")]
+        jobs: usize,
+
+        /// Re-run atomize first when stubs.json is older than the structure files
+        #[arg(long, conflicts_with = "check_only")]
+        auto: bool,
+    },
+
+    /// Run the pipeline stages in order, skipping stages whose inputs haven't changed
+    Run {
+        /// Project root directory (default: current working directory)
+        #[arg(default_value = ".")]
+        project_root: PathBuf,
+
+        /// Stages to run, in pipeline order (default: "pipeline" in config.json,
+        /// else atomize,specify,verify)
+        #[arg(long, value_enum, value_delimiter = ',', value_name = "STAGES")]
+        stages: Vec<PipelineStage>,
+
+        /// Read probe-verus outputs from disk in every stage
+        #[arg(short = 'n', long)]
+        no_probe: bool,
+
+        /// Verify with a named profile from config.json
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+
+        /// Run stages even when their inputs haven't changed
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum KeysCommands {
+    /// Generate a new local identity and print its public key
+    Generate {
+        /// Overwrite an existing identity file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Show the local public key and the project's encryption recipient
+    Show,
+    /// Encrypt atom contents on deploy to this team public key (age1...)
+    SetRecipient {
+        /// Team public key
+        recipient: String,
+    },
+    /// Stop encrypting atom contents on deploy
+    UnsetRecipient,
+}
+
+#[derive(Subcommand)]
+pub enum MetricsCommands {
+    /// Show recent runs and per-command totals from .verilib/metrics.jsonl
+    Show {
+        /// Project root directory (default: current working directory)
+        #[arg(default_value = ".")]
+        project_root: PathBuf,
+        /// Number of most recent runs to show and summarize
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum FlagLogCommands {
+    /// Show recent changes from .verilib/flag_changes.jsonl
+    Show {
+        /// Only changes to this meta file, or to the atom with this identifier
+        #[arg(long)]
+        file: Option<String>,
+        /// Number of most recent changes to show
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum LayoutsCommands {
+    /// Report folder children without coordinates and nodes matching no atom or folder
+    Check {
+        /// Project root directory (default: current working directory)
+        #[arg(default_value = ".")]
+        project_root: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum FunctionsCommands {
+    /// List functions, filtered by module, path and spec and proof status
+    List {
+        /// Project root directory (default: current working directory)
+        #[arg(default_value = ".")]
+        project_root: PathBuf,
+        /// Only functions in this code-module or its submodules
+        #[arg(long)]
+        module: Option<String>,
+        /// Only functions whose code-path matches this glob
+        #[arg(long, value_name = "GLOB")]
+        path_glob: Option<String>,
+        /// Only specified functions (--specified=false: only unspecified ones)
+        #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+        specified: Option<bool>,
+        /// Only verified functions (--verified=false: only unverified ones)
+        #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+        verified: Option<bool>,
+        /// Only functions whose last verification failed
+        #[arg(long)]
+        failed: bool,
+        /// Only functions matching this filter expression, e.g. 'specified && !verified && path:rsa/**'
+        #[arg(long, value_name = "EXPR")]
+        filter: Option<Query>,
+        /// Order of the list
+        #[arg(long, value_enum, default_value_t = FunctionSort::Path)]
+        sort: FunctionSort,
     },
 }
 
 #[derive(Subcommand)]
 pub enum ApiCommands {
     /// Get metadata for a specific file
+    #[command(group(ArgGroup::new("target").required(true)))]
     Get {
         /// Path to the .meta.verilib file
-        #[arg(long)]
-        file: String,
+        #[arg(long, group = "target")]
+        file: Option<String>,
+        /// Look up the meta file by its identifier
+        #[arg(long, group = "target")]
+        id: Option<String>,
+        /// Look up the meta file by its code name (e.g. probe:crate/mod#func())
+        #[arg(long, group = "target")]
+        code_name: Option<String>,
     },
-    /// List all files, optionally filtered by status
+    /// List all files, optionally filtered
     List {
-        /// Filter by status: specified, ignored, or verified
+        /// Only files matching this filter expression, e.g. 'specified && !verified && path:rsa/**'
+        #[arg(long, value_name = "EXPR")]
+        filter: Option<Query>,
+        /// Filter by spec review status: draft, in-review, or approved
         #[arg(long)]
-        filter: Option<String>,
+        review_status: Option<ReviewStatus>,
+        /// Order of the list
+        #[arg(long, value_enum, default_value_t = ListSort::Path)]
+        sort: ListSort,
+        /// Stable tab-separated output for scripts
+        #[arg(long)]
+        porcelain: bool,
     },
     /// Set metadata fields for a file
+    #[command(group(ArgGroup::new("target").required(true)))]
     Set {
         /// Path to the .meta.verilib file
-        #[arg(long)]
-        file: String,
+        #[arg(long, group = "target")]
+        file: Option<String>,
+        /// Look up the meta file by its identifier
+        #[arg(long, group = "target")]
+        id: Option<String>,
+        /// Look up the meta file by its code name (e.g. probe:crate/mod#func())
+        #[arg(long, group = "target")]
+        code_name: Option<String>,
         /// Set specified status
         #[arg(long)]
         specified: Option<bool>,
@@ -146,24 +853,27 @@ pub enum ApiCommands {
         /// Set verified status (admin only)
         #[arg(long)]
         verified: Option<bool>,
+        /// Set spec review status: draft, in-review, or approved
+        #[arg(long)]
+        review_status: Option<ReviewStatus>,
     },
     /// Batch update multiple files from JSON input
     Batch {
         /// Path to JSON file with batch operations
         #[arg(long)]
-        input: String,
+        input: PathBuf,
     },
     /// Create a new file with content from string, file, or stdin
     CreateFile {
         /// Destination path for the new file
         #[arg(long)]
-        path: String,
+        path: PathBuf,
         /// Content string to write to the file
         #[arg(long, group = "source")]
         content: Option<String>,
         /// Path to a source file to read content from
         #[arg(long, group = "source")]
-        from_file: Option<String>,
+        from_file: Option<PathBuf>,
         /// Set disabled status
         #[arg(long, default_value_t = false)]
         disabled: bool,
@@ -179,5 +889,32 @@ pub enum ApiCommands {
         /// Set code name (defaults to parent directory name)
         #[arg(long)]
         code_name: Option<String>,
+        /// Add a typed snippet read from a file (doc, code, spec, or proof); repeatable, in order
+        #[arg(long = "snippet", value_name = "KIND=FILE", group = "source", value_parser = crate::commands::api::parse_snippet_arg)]
+        snippets: Vec<(crate::commands::types::SnippetKind, PathBuf)>,
+        /// Read snippets from a JSON array of {"type": KIND, "text": TEXT} objects
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["content", "from_file"])]
+        snippets_json: Option<PathBuf>,
+    },
+    /// Check .verilib atoms, meta files, indices, and layouts for consistency
+    Validate {
+        /// Renumber duplicate indices and rewrite mismatched meta index/path fields
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Add, remove, or list reviewer notes on an atom
+    #[command(group(ArgGroup::new("action").required(true).multiple(true)))]
+    Annotate {
+        /// Path to the .meta.verilib file
+        file: String,
+        /// Add a note; repeatable
+        #[arg(long, value_name = "NOTE", group = "action")]
+        add: Vec<String>,
+        /// Remove a note, by its text or its number in --list; repeatable
+        #[arg(long, value_name = "NOTE", group = "action")]
+        remove: Vec<String>,
+        /// List the notes
+        #[arg(long, group = "action")]
+        list: bool,
     },
 }
@@ -1,24 +1,39 @@
-#![allow(dead_code)] // WIP: not yet wired into CLI — see https://github.com/Beneficial-AI-Foundation/verilib-cli/issues/36
-
 use anyhow::{Context, Result};
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::{self, IsTerminal, Read};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::deploy::validate_snippets;
+use super::flag_log::{self, FlagChange};
+use super::reindex::{apply_renumbering, plan_renumbering, scan_indices};
+use super::types::{SnippetInput, SnippetKind};
+use crate::exit::fail;
+use crate::output::{self, Table};
+use crate::paths::{check_segment, join_inside, slash};
+use crate::query::{Flag, Query, Subject};
+use crate::structure::ReviewStatus;
+use crate::walk;
 
 #[derive(Debug, Clone)]
 pub enum ApiSubcommand {
     Get {
-        file: PathBuf,
+        target: MetaTarget,
     },
     List {
-        filter: Option<StatusFilter>,
+        filter: Option<Query>,
+        review_status: Option<ReviewStatus>,
+        sort: ListSort,
+        porcelain: bool,
     },
     Set {
-        file: PathBuf,
+        target: MetaTarget,
         specified: Option<bool>,
         ignored: Option<bool>,
         verified: Option<bool>,
+        review_status: Option<ReviewStatus>,
     },
     Batch {
         input: PathBuf,
@@ -32,14 +47,43 @@ pub enum ApiSubcommand {
         status_id: u32,
         statement_type: Option<String>,
         code_name: Option<String>,
+        snippet_files: Vec<(SnippetKind, PathBuf)>,
+        snippets_json: Option<PathBuf>,
+    },
+    Validate {
+        fix: bool,
+    },
+    Annotate {
+        target: MetaTarget,
+        add: Vec<String>,
+        remove: Vec<String>,
     },
 }
 
+/// How a meta file is addressed: by path, or by a field stored inside it.
+/// Identifier and code-name lookups are independent of the `[N] - ` index
+/// prefix and directory of the file.
 #[derive(Debug, Clone)]
-pub enum StatusFilter {
+pub enum MetaTarget {
+    File(PathBuf),
+    Identifier(String),
+    CodeName(String),
+}
+
+/// Order of `api list`, for working through files by priority.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ListSort {
+    /// By path
+    #[default]
+    Path,
+    /// By name, without the `[N] - ` index prefix
+    Name,
+    /// By status_id, lowest first
+    Status,
+    /// Unspecified files first
     Specified,
-    Ignored,
-    Verified,
+    /// Most recently modified first
+    Modified,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -50,10 +94,30 @@ struct MetaFile {
     pub disabled: bool,
     #[serde(default)]
     pub status_id: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review_status: Option<ReviewStatus>,
+    /// Reviewer notes, see [`crate::structure::annotations`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<String>,
     #[serde(flatten)]
     pub other: serde_json::Value,
 }
 
+/// Entry of the meta file lookup index cached in `.verilib/meta_index.json`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct MetaIndexEntry {
+    path: PathBuf,
+    /// Modification time of the file when it was indexed.
+    #[serde(default)]
+    modified: Option<SystemTime>,
+    #[serde(default)]
+    identifier: Option<String>,
+    #[serde(default)]
+    code_name: Option<String>,
+}
+
+const META_INDEX_FILE: &str = "meta_index.json";
+
 #[derive(Serialize, Debug)]
 struct GetOutput {
     file: String,
@@ -61,6 +125,15 @@ struct GetOutput {
     ignored: bool,
     verified: bool,
     status_id: u32,
+    review_status: Option<ReviewStatus>,
+}
+
+#[derive(Serialize, Debug)]
+struct AnnotateOutput {
+    file: String,
+    annotations: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    changes: Vec<String>,
 }
 
 #[derive(Serialize, Debug)]
@@ -74,8 +147,75 @@ struct FileInfo {
     specified: bool,
     ignored: bool,
     verified: bool,
+    status_id: u32,
+    review_status: Option<ReviewStatus>,
+    #[serde(skip)]
+    modified: Option<SystemTime>,
+}
+
+/// Inconsistencies `api validate` looks for in the .verilib tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum TreeProblem {
+    /// An atom file without a meta file.
+    MissingMeta,
+    /// A meta file that is not a JSON object.
+    InvalidMeta,
+    /// A meta file without a `code_name` or `identifier`.
+    MissingField,
+    /// Snippets that `deploy` would reject.
+    InvalidSnippets,
+    /// Atoms in one directory sharing an index.
+    DuplicateIndex,
+    /// A meta `index` that differs from the file name's.
+    IndexMismatch,
+    /// A meta `path` that differs from the file's location.
+    PathMismatch,
+    /// A layout.verilib that is not valid JSON.
+    InvalidLayout,
+}
+
+impl TreeProblem {
+    fn as_str(self) -> &'static str {
+        match self {
+            TreeProblem::MissingMeta => "missing-meta",
+            TreeProblem::InvalidMeta => "invalid-meta",
+            TreeProblem::MissingField => "missing-field",
+            TreeProblem::InvalidSnippets => "invalid-snippets",
+            TreeProblem::DuplicateIndex => "duplicate-index",
+            TreeProblem::IndexMismatch => "index-mismatch",
+            TreeProblem::PathMismatch => "path-mismatch",
+            TreeProblem::InvalidLayout => "invalid-layout",
+        }
+    }
+
+    /// Whether `--fix` repairs this problem.
+    fn is_fixable(self) -> bool {
+        matches!(
+            self,
+            TreeProblem::DuplicateIndex | TreeProblem::IndexMismatch | TreeProblem::PathMismatch
+        )
+    }
 }
 
+#[derive(Serialize, Debug)]
+struct Violation {
+    file: String,
+    problem: TreeProblem,
+    detail: String,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+#[derive(Serialize, Debug)]
+struct ValidateOutput {
+    violations: Vec<Violation>,
+    fixed: usize,
+}
+
+/// Meta fields that must be present as strings.
+const REQUIRED_META_FIELDS: [&str; 2] = ["code_name", "identifier"];
+
 #[derive(Deserialize, Debug)]
 struct BatchInput {
     operations: Vec<BatchOperation>,
@@ -90,6 +230,8 @@ struct BatchOperation {
     ignored: Option<bool>,
     #[serde(default)]
     verified: Option<bool>,
+    #[serde(default)]
+    review_status: Option<ReviewStatus>,
 }
 
 #[derive(Serialize, Debug)]
@@ -107,16 +249,51 @@ struct BatchResult {
     error: Option<String>,
 }
 
+impl ApiSubcommand {
+    /// Whether the subcommand writes .verilib files when not in dry-run mode.
+    fn is_mutating(&self) -> bool {
+        match self {
+            ApiSubcommand::Get { .. }
+            | ApiSubcommand::List { .. }
+            | ApiSubcommand::Validate { fix: false } => false,
+            ApiSubcommand::Annotate { add, remove, .. } => !add.is_empty() || !remove.is_empty(),
+            _ => true,
+        }
+    }
+}
+
 pub async fn handle_api(subcommand: ApiSubcommand, json_output: bool, dry_run: bool) -> Result<()> {
+    if subcommand.is_mutating() && !dry_run {
+        check_writable(Path::new("."))?;
+    }
+
     match subcommand {
-        ApiSubcommand::Get { file } => handle_get(file, json_output).await,
-        ApiSubcommand::List { filter } => handle_list(filter, json_output).await,
+        ApiSubcommand::Get { target } => handle_get(target, json_output).await,
+        ApiSubcommand::List {
+            filter,
+            review_status,
+            sort,
+            porcelain,
+        } => handle_list(filter, review_status, sort, json_output, porcelain).await,
         ApiSubcommand::Set {
-            file,
+            target,
             specified,
             ignored,
             verified,
-        } => handle_set(file, specified, ignored, verified, json_output, dry_run).await,
+            review_status,
+        } => {
+            handle_set(
+                target,
+                specified,
+                ignored,
+                verified,
+                review_status,
+                "api set",
+                json_output,
+                dry_run,
+            )
+            .await
+        }
         ApiSubcommand::Batch { input } => handle_batch(input, json_output, dry_run).await,
         ApiSubcommand::CreateFile {
             path,
@@ -127,6 +304,8 @@ pub async fn handle_api(subcommand: ApiSubcommand, json_output: bool, dry_run: b
             status_id,
             statement_type,
             code_name,
+            snippet_files,
+            snippets_json,
         } => {
             handle_create_file(
                 path,
@@ -137,11 +316,19 @@ pub async fn handle_api(subcommand: ApiSubcommand, json_output: bool, dry_run: b
                 status_id,
                 statement_type,
                 code_name,
+                snippet_files,
+                snippets_json,
                 json_output,
                 dry_run,
             )
             .await
         }
+        ApiSubcommand::Validate { fix } => handle_validate(fix, json_output, dry_run).await,
+        ApiSubcommand::Annotate {
+            target,
+            add,
+            remove,
+        } => handle_annotate(target, add, remove, json_output, dry_run).await,
     }
 }
 
@@ -155,10 +342,20 @@ async fn handle_create_file(
     status_id: u32,
     statement_type: Option<String>,
     code_name: Option<String>,
+    snippet_files: Vec<(SnippetKind, PathBuf)>,
+    snippets_json: Option<PathBuf>,
     json_output: bool,
     dry_run: bool,
 ) -> Result<()> {
-    let (final_content, source_desc) = if let Some(c) = content {
+    let snippets = read_snippets(snippet_files, snippets_json)?;
+    if !snippets.is_empty() && (content.is_some() || from_file.is_some()) {
+        anyhow::bail!("Snippets replace --content and --from-file; pass only one kind of source.");
+    }
+
+    let (final_content, source_desc) = if !snippets.is_empty() {
+        let text: String = snippets.iter().map(|s| s.text.as_str()).collect();
+        (text, format!("{} snippets", snippets.len()))
+    } else if let Some(c) = content {
         (c, "argument string".to_string())
     } else if let Some(p) = from_file {
         let content = fs::read_to_string(&p)
@@ -179,15 +376,19 @@ async fn handle_create_file(
         .ok_or_else(|| anyhow::anyhow!("Invalid path: no filename"))?
         .to_string_lossy()
         .to_string();
+    check_segment(&identifier).context("Invalid atom name")?;
 
     let logical_parent = path.parent().unwrap_or_else(|| std::path::Path::new(""));
     let verilib_root = PathBuf::from(".verilib");
 
-    let physical_parent = if logical_parent.starts_with(&verilib_root) {
-        logical_parent.to_path_buf()
-    } else {
-        verilib_root.join(logical_parent)
-    };
+    let physical_parent = join_inside(
+        &verilib_root,
+        &logical_parent
+            .strip_prefix(&verilib_root)
+            .unwrap_or(logical_parent)
+            .to_string_lossy(),
+    )
+    .context("Invalid atom path")?;
 
     if !dry_run {
         fs::create_dir_all(&physical_parent).with_context(|| {
@@ -198,21 +399,15 @@ async fn handle_create_file(
         })?;
     }
 
-    let mut next_index = 0;
-    if physical_parent.exists() {
-        let re = regex::Regex::new(r"^\[(\d+)\]\s*-\s*").unwrap();
-        for entry in fs::read_dir(&physical_parent)? {
-            let entry = entry?;
-            let file_name = entry.file_name().to_string_lossy().to_string();
-            if let Some(caps) = re.captures(&file_name) {
-                if let Ok(idx) = caps[1].parse::<u32>() {
-                    if idx >= next_index {
-                        next_index = idx + 1;
-                    }
-                }
-            }
-        }
+    let indices = scan_indices(&physical_parent)?;
+    if !indices.duplicates.is_empty() && !json_output {
+        output::warn(format!(
+            "{:?} has duplicate atom indices; run 'verilib-cli reindex {}' to fix them.",
+            physical_parent,
+            logical_parent.display()
+        ));
     }
+    let next_index = indices.next_free;
 
     let atom_filename = format!("[{}] - {}.atom.verilib", next_index, identifier);
     let meta_filename = format!("[{}] - {}.meta.verilib", next_index, identifier);
@@ -226,20 +421,10 @@ async fn handle_create_file(
             .unwrap_or_else(|| "unknown".to_string())
     });
 
-    let path_str = path.to_string_lossy();
-    let clean_path = if path_str.starts_with(".verilib/") {
-        path_str.strip_prefix(".verilib/").unwrap()
-    } else if path_str.starts_with(".verilib\\") {
-        path_str.strip_prefix(".verilib\\").unwrap()
-    } else {
-        &path_str
-    };
-
-    let json_path = if clean_path.starts_with('/') {
-        clean_path.to_string()
-    } else {
-        format!("/{}", clean_path)
-    };
+    let json_path = format!(
+        "/{}",
+        slash(path.strip_prefix(&verilib_root).unwrap_or(&path))?
+    );
 
     let meta_json = serde_json::json!({
         "code_name": final_code_name,
@@ -247,13 +432,7 @@ async fn handle_create_file(
         "identifier": identifier,
         "index": next_index,
         "path": json_path,
-        "snippets": [
-            {
-                "sortorder": 0,
-                "text": final_content,
-                "type_id": 2
-            }
-        ],
+        "snippets": snippets_to_json(&snippets, &final_content),
         "specified": specified,
         "status_id": status_id,
         "statement_type": statement_type
@@ -304,8 +483,8 @@ async fn handle_create_file(
     Ok(())
 }
 
-async fn handle_get(file: PathBuf, json_output: bool) -> Result<()> {
-    let resolved_path = resolve_file_path(&file)?;
+async fn handle_get(target: MetaTarget, json_output: bool) -> Result<()> {
+    let resolved_path = resolve_target(&target)?;
     validate_meta_file(&resolved_path)?;
 
     let content = fs::read_to_string(&resolved_path)
@@ -319,6 +498,7 @@ async fn handle_get(file: PathBuf, json_output: bool) -> Result<()> {
         ignored: meta.disabled,
         verified: meta.status_id == 2,
         status_id: meta.status_id,
+        review_status: meta.review_status,
     };
 
     if json_output {
@@ -329,43 +509,61 @@ async fn handle_get(file: PathBuf, json_output: bool) -> Result<()> {
         println!("  Ignored:   {}", output.ignored);
         println!("  Verified:  {}", output.verified);
         println!("  Status ID: {}", output.status_id);
+        if let Some(review_status) = output.review_status {
+            println!("  Review:    {}", review_status);
+        }
     }
 
     Ok(())
 }
 
-async fn handle_list(filter: Option<StatusFilter>, json_output: bool) -> Result<()> {
+async fn handle_list(
+    filter: Option<Query>,
+    review_status: Option<ReviewStatus>,
+    sort: ListSort,
+    json_output: bool,
+    porcelain: bool,
+) -> Result<()> {
     let verilib_dir = PathBuf::from(".verilib");
 
     if !verilib_dir.exists() {
-        anyhow::bail!("No .verilib directory found. Please run 'init' first.");
+        fail!(
+            MissingPrerequisite,
+            "No .verilib directory found. Please run 'init' first."
+        );
     }
 
     let mut files = Vec::new();
 
-    for entry in walkdir::WalkDir::new(&verilib_dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        let path = entry.path();
-        if path.is_file() && path.extension().is_some_and(|ext| ext == "verilib") {
+    for path in walk::verilib_files(&verilib_dir) {
+        let path = path.as_path();
+        if path.extension().is_some_and(|ext| ext == "verilib") {
             let file_name = path.file_name().unwrap_or_default().to_string_lossy();
             if file_name.contains(".meta.") {
-                if let Ok(content) = fs::read_to_string(path) {
-                    if let Ok(meta) = serde_json::from_str::<MetaFile>(&content) {
-                        let matches_filter = match &filter {
-                            None => true,
-                            Some(StatusFilter::Specified) => meta.specified,
-                            Some(StatusFilter::Ignored) => meta.disabled,
-                            Some(StatusFilter::Verified) => meta.status_id == 2,
-                        };
-
-                        if matches_filter {
+                let meta = fs::read_to_string(path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|content| Ok(serde_json::from_str::<MetaFile>(&content)?));
+                match meta {
+                    Err(e) => output::warn(format!("skipping {}: {:#}", path.display(), e)),
+                    Ok(meta) => {
+                        let matches_filter = filter.as_ref().is_none_or(|query| {
+                            query.matches(&MetaSubject {
+                                path: &path.to_string_lossy(),
+                                meta: &meta,
+                            })
+                        });
+                        let matches_review =
+                            review_status.is_none() || meta.review_status == review_status;
+
+                        if matches_filter && matches_review {
                             files.push(FileInfo {
                                 path: path.to_string_lossy().to_string(),
                                 specified: meta.specified,
                                 ignored: meta.disabled,
                                 verified: meta.status_id == 2,
+                                status_id: meta.status_id,
+                                review_status: meta.review_status,
+                                modified: fs::metadata(path).and_then(|m| m.modified()).ok(),
                             });
                         }
                     }
@@ -374,31 +572,123 @@ async fn handle_list(filter: Option<StatusFilter>, json_output: bool) -> Result<
         }
     }
 
+    sort_files(&mut files, sort);
+
     if json_output {
         let output = ListOutput { files };
         println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if porcelain {
+        for file in &files {
+            output::porcelain([
+                file.path.as_str(),
+                flag(file.specified),
+                flag(file.ignored),
+                flag(file.verified),
+                file.review_status.map(|r| r.as_str()).unwrap_or("-"),
+            ]);
+        }
     } else {
         println!("Found {} files", files.len());
+        let mut table = Table::new([
+            "Path",
+            "Specified",
+            "Ignored",
+            "Verified",
+            "Status",
+            "Review",
+        ]);
         for file in files {
-            println!(
-                "  {} [Spec: {} | Ign: {} | Ver: {}]",
-                file.path, file.specified, file.ignored, file.verified
-            );
+            table.row([
+                file.path,
+                file.specified.to_string(),
+                file.ignored.to_string(),
+                file.verified.to_string(),
+                file.status_id.to_string(),
+                file.review_status
+                    .map(|r| r.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+            ]);
         }
+        table.print();
     }
 
     Ok(())
 }
 
+/// A meta file as filter expressions see it.
+struct MetaSubject<'a> {
+    path: &'a str,
+    meta: &'a MetaFile,
+}
+
+impl Subject for MetaSubject<'_> {
+    fn flag(&self, flag: Flag) -> Option<bool> {
+        match flag {
+            Flag::Specified => Some(self.meta.specified),
+            Flag::Verified => Some(self.meta.status_id == 2),
+            Flag::Ignored => Some(self.meta.disabled),
+            Flag::Failed => None,
+        }
+    }
+
+    fn path(&self) -> Option<&str> {
+        Some(self.path)
+    }
+
+    fn names(&self) -> Vec<&str> {
+        ["identifier", "code_name"]
+            .iter()
+            .filter_map(|field| self.meta.other.get(field).and_then(|v| v.as_str()))
+            .collect()
+    }
+
+    fn review_status(&self) -> Option<ReviewStatus> {
+        self.meta.review_status
+    }
+}
+
+/// Order `files` by `sort`, then by path. They arrive in path order.
+fn sort_files(files: &mut [FileInfo], sort: ListSort) {
+    let name = |file: &FileInfo| {
+        let stem = Path::new(&file.path)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .trim_end_matches(".meta.verilib")
+            .to_string();
+        split_index(&stem).1.to_string()
+    };
+    match sort {
+        ListSort::Path => {}
+        ListSort::Name => files.sort_by_cached_key(name),
+        ListSort::Status => files.sort_by_key(|file| file.status_id),
+        ListSort::Specified => files.sort_by_key(|file| file.specified),
+        ListSort::Modified => files.sort_by_key(|file| std::cmp::Reverse(file.modified)),
+    }
+}
+
+fn flag(value: bool) -> &'static str {
+    if value {
+        "true"
+    } else {
+        "false"
+    }
+}
+
+/// Set flags of a meta file; each change is recorded in the flag log as
+/// made by `command`.
+#[allow(clippy::too_many_arguments)]
 async fn handle_set(
-    file: PathBuf,
+    target: MetaTarget,
     specified: Option<bool>,
     ignored: Option<bool>,
     verified: Option<bool>,
+    review_status: Option<ReviewStatus>,
+    command: &str,
     json_output: bool,
     dry_run: bool,
 ) -> Result<()> {
-    let resolved_path = resolve_file_path(&file)?;
+    let resolved_path = resolve_target(&target)?;
     validate_meta_file(&resolved_path)?;
 
     if verified.is_some() {
@@ -411,10 +701,12 @@ async fn handle_set(
     let mut meta: MetaFile = serde_json::from_str(&content).context("Failed to parse meta file")?;
 
     let mut changes = Vec::new();
+    let mut logged = Vec::new();
 
     if let Some(val) = specified {
         if meta.specified != val {
             changes.push(format!("specified: {} -> {}", meta.specified, val));
+            logged.push(FlagChange::new(command, "specified", meta.specified, val));
             meta.specified = val;
         }
     }
@@ -422,6 +714,7 @@ async fn handle_set(
     if let Some(val) = ignored {
         if meta.disabled != val {
             changes.push(format!("ignored: {} -> {}", meta.disabled, val));
+            logged.push(FlagChange::new(command, "ignored", meta.disabled, val));
             meta.disabled = val;
         }
     }
@@ -430,10 +723,35 @@ async fn handle_set(
         let new_status = if val { 2 } else { 0 };
         if meta.status_id != new_status {
             changes.push(format!("verified: {} -> {}", meta.status_id == 2, val));
+            logged.push(FlagChange::new(
+                command,
+                "verified",
+                meta.status_id == 2,
+                val,
+            ));
             meta.status_id = new_status;
         }
     }
 
+    if let Some(val) = review_status {
+        if meta.review_status != Some(val) {
+            changes.push(format!(
+                "review_status: {} -> {}",
+                meta.review_status
+                    .map(|r| r.to_string())
+                    .unwrap_or_else(|| "none".to_string()),
+                val
+            ));
+            logged.push(FlagChange::new(
+                command,
+                "review_status",
+                meta.review_status.map(|r| r.to_string()),
+                val.to_string(),
+            ));
+            meta.review_status = Some(val);
+        }
+    }
+
     if changes.is_empty() {
         if !json_output {
             println!("No changes needed for: {}", resolved_path.display());
@@ -462,6 +780,18 @@ async fn handle_set(
     fs::write(&resolved_path, new_content)
         .with_context(|| format!("Failed to write file: {:?}", resolved_path))?;
 
+    let file = resolved_path.display().to_string();
+    let identifier = meta.other.get("identifier").and_then(|v| v.as_str());
+    let code_name = meta.other.get("code_name").and_then(|v| v.as_str());
+    for change in &mut logged {
+        change.file = Some(file.clone());
+        change.identifier = identifier.map(str::to_string);
+        change.code_name = code_name.map(str::to_string);
+    }
+    if let Err(e) = flag_log::append(Path::new(".verilib"), &logged) {
+        output::warn(format!("failed to record flag changes: {:#}", e));
+    }
+
     if json_output {
         println!(
             "{{\"success\": true, \"file\": \"{}\", \"changes\": {}}}",
@@ -469,7 +799,7 @@ async fn handle_set(
             changes.len()
         );
     } else {
-        println!("Successfully updated: {}", resolved_path.display());
+        output::success(format!("updated {}", resolved_path.display()));
         for change in changes {
             println!("  - {}", change);
         }
@@ -478,6 +808,93 @@ async fn handle_set(
     Ok(())
 }
 
+/// Add and remove notes on a meta file, then list its notes. A note to
+/// remove is given by its text or by its number in the list.
+async fn handle_annotate(
+    target: MetaTarget,
+    add: Vec<String>,
+    remove: Vec<String>,
+    json_output: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let resolved_path = resolve_target(&target)?;
+    validate_meta_file(&resolved_path)?;
+
+    let content = fs::read_to_string(&resolved_path)
+        .with_context(|| format!("Failed to read file: {:?}", resolved_path))?;
+    let mut meta: MetaFile = serde_json::from_str(&content).context("Failed to parse meta file")?;
+
+    let mut changes = Vec::new();
+    let mut removed = Vec::new();
+    for note in &remove {
+        let position = match note.parse::<usize>() {
+            Ok(number) if (1..=meta.annotations.len()).contains(&number) => number - 1,
+            _ => meta
+                .annotations
+                .iter()
+                .position(|a| a == note)
+                .ok_or_else(|| {
+                    anyhow::anyhow!("No annotation '{}' on {:?}", note, resolved_path)
+                })?,
+        };
+        removed.push(position);
+    }
+    removed.sort_unstable();
+    removed.dedup();
+    for position in removed.into_iter().rev() {
+        changes.push(format!("removed: {}", meta.annotations.remove(position)));
+    }
+    for note in add {
+        let note = note.trim().to_string();
+        if note.is_empty() {
+            anyhow::bail!("Annotations can't be empty");
+        }
+        if !meta.annotations.contains(&note) {
+            changes.push(format!("added: {}", note));
+            meta.annotations.push(note);
+        }
+    }
+
+    if !changes.is_empty() && !dry_run {
+        let new_content =
+            serde_json::to_string_pretty(&meta).context("Failed to serialize meta file")?;
+        fs::write(&resolved_path, new_content)
+            .with_context(|| format!("Failed to write file: {:?}", resolved_path))?;
+    }
+
+    if json_output {
+        let output = AnnotateOutput {
+            file: resolved_path.to_string_lossy().to_string(),
+            annotations: meta.annotations,
+            changes,
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    if !changes.is_empty() {
+        if dry_run {
+            println!(
+                "DRY RUN - Would make the following changes to {}:",
+                resolved_path.display()
+            );
+        } else {
+            output::success(format!("updated {}", resolved_path.display()));
+        }
+        for change in &changes {
+            println!("  - {}", change);
+        }
+    }
+    println!("File: {}", resolved_path.display());
+    if meta.annotations.is_empty() {
+        println!("  No annotations");
+    }
+    for (i, note) in meta.annotations.iter().enumerate() {
+        println!("  [{}] {}", i + 1, note);
+    }
+    Ok(())
+}
+
 async fn handle_batch(input: PathBuf, json_output: bool, dry_run: bool) -> Result<()> {
     let content = fs::read_to_string(&input)
         .with_context(|| format!("Failed to read batch input file: {:?}", input))?;
@@ -490,12 +907,14 @@ async fn handle_batch(input: PathBuf, json_output: bool, dry_run: bool) -> Resul
     let mut error_count = 0;
 
     for op in batch.operations {
-        let file_path = PathBuf::from(&op.file);
+        let target = MetaTarget::File(PathBuf::from(&op.file));
         let result = handle_set(
-            file_path,
+            target,
             op.specified,
             op.ignored,
             op.verified,
+            op.review_status,
+            "api batch",
             false,
             dry_run,
         )
@@ -532,13 +951,17 @@ async fn handle_batch(input: PathBuf, json_output: bool, dry_run: bool) -> Resul
         println!("Batch operation completed:");
         println!("  Success: {}", success_count);
         println!("  Errors:  {}", error_count);
+        println!();
+        let mut table = Table::new(["File", "Result", "Error"]);
         for result in results {
-            if result.success {
-                println!("  ✓ {}", result.file);
-            } else {
-                println!("  ✗ {} - {}", result.file, result.error.unwrap_or_default());
-            }
+            let outcome = if result.success { "ok" } else { "failed" };
+            table.row([
+                result.file,
+                outcome.to_string(),
+                result.error.unwrap_or_default(),
+            ]);
         }
+        table.print();
     }
 
     if error_count > 0 {
@@ -548,6 +971,239 @@ async fn handle_batch(input: PathBuf, json_output: bool, dry_run: bool) -> Resul
     Ok(())
 }
 
+async fn handle_validate(fix: bool, json_output: bool, dry_run: bool) -> Result<()> {
+    let verilib_dir = PathBuf::from(".verilib");
+    if !verilib_dir.exists() {
+        fail!(
+            MissingPrerequisite,
+            "No .verilib directory found. Please run 'init' first."
+        );
+    }
+
+    let mut violations = validate_tree(&verilib_dir)?;
+    let mut fixed = 0;
+    if fix && !dry_run && violations.iter().any(|v| v.problem.is_fixable()) {
+        fix_tree(&verilib_dir, &violations)?;
+        let before = violations.len();
+        violations = validate_tree(&verilib_dir)?;
+        fixed = before.saturating_sub(violations.len());
+    }
+
+    let remaining = violations.len();
+    let fixable = violations.iter().filter(|v| v.problem.is_fixable()).count();
+    if json_output {
+        let output = ValidateOutput { violations, fixed };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        if fixed > 0 {
+            output::success(format!("Fixed {} violations.", fixed));
+        }
+        if violations.is_empty() {
+            output::success(".verilib tree is consistent.");
+        } else {
+            let mut table = Table::new(["Problem", "File", "Detail"]);
+            for violation in &violations {
+                table.row([
+                    violation.problem.as_str(),
+                    violation.file.as_str(),
+                    violation.detail.as_str(),
+                ]);
+            }
+            table.print();
+        }
+    }
+
+    if remaining > 0 {
+        let hint = if fixable > 0 && !fix {
+            format!(" {} can be repaired with --fix.", fixable)
+        } else {
+            String::new()
+        };
+        fail!(CheckFailed, "{} violations found.{}", remaining, hint);
+    }
+    Ok(())
+}
+
+/// Check every atom, meta, and layout file under `verilib_dir`.
+fn validate_tree(verilib_dir: &Path) -> Result<Vec<Violation>> {
+    let mut violations = Vec::new();
+
+    for entry in walkdir::WalkDir::new(verilib_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        let path = entry.path();
+        let violation = |problem, detail: String| Violation {
+            file: path.display().to_string(),
+            problem,
+            detail,
+            path: path.to_path_buf(),
+        };
+
+        if entry.file_type().is_dir() {
+            for (index, identifiers) in scan_indices(path)?.duplicates {
+                violations.push(violation(
+                    TreeProblem::DuplicateIndex,
+                    format!("[{}] is used by {}", index, identifiers.join(", ")),
+                ));
+            }
+            continue;
+        }
+
+        let file_name = entry.file_name().to_string_lossy();
+        if file_name == "layout.verilib" {
+            if let Err(e) = read_json(path) {
+                violations.push(violation(TreeProblem::InvalidLayout, e.to_string()));
+            }
+        } else if let Some(stem) = file_name.strip_suffix(".atom.verilib") {
+            let meta_name = format!("{}.meta.verilib", stem);
+            if !path.with_file_name(&meta_name).exists() {
+                violations.push(violation(
+                    TreeProblem::MissingMeta,
+                    format!("no {}", meta_name),
+                ));
+            }
+        } else if let Some(stem) = file_name.strip_suffix(".meta.verilib") {
+            let meta = match read_json(path) {
+                Ok(serde_json::Value::Object(meta)) => meta,
+                Ok(_) => {
+                    violations.push(violation(
+                        TreeProblem::InvalidMeta,
+                        "not a JSON object".to_string(),
+                    ));
+                    continue;
+                }
+                Err(e) => {
+                    violations.push(violation(TreeProblem::InvalidMeta, e.to_string()));
+                    continue;
+                }
+            };
+
+            for field in REQUIRED_META_FIELDS {
+                if !meta.get(field).is_some_and(|v| v.is_string()) {
+                    violations.push(violation(
+                        TreeProblem::MissingField,
+                        format!("no '{}'", field),
+                    ));
+                }
+            }
+            if let Some(snippets) = meta.get("snippets") {
+                if let Err(e) = validate_snippets(snippets, stem) {
+                    violations.push(violation(TreeProblem::InvalidSnippets, e.to_string()));
+                }
+            }
+
+            let (index, identifier) = split_index(stem);
+            let field = |name: &str| meta.get(name).unwrap_or(&serde_json::Value::Null);
+            if index.is_some_and(|index| field("index").as_u64() != Some(index)) {
+                violations.push(violation(
+                    TreeProblem::IndexMismatch,
+                    format!(
+                        "index is {}; the file name says {}",
+                        field("index"),
+                        index.unwrap_or_default()
+                    ),
+                ));
+            }
+            let expected = meta_location(verilib_dir, path, identifier);
+            if field("path").as_str().map(normalize_meta_path) != Some(expected.clone()) {
+                violations.push(violation(
+                    TreeProblem::PathMismatch,
+                    format!("path is {}; expected \"{}\"", field("path"), expected),
+                ));
+            }
+        }
+    }
+
+    Ok(violations)
+}
+
+/// Renumber directories with duplicate indices, then rewrite the `index` and
+/// `path` of meta files that disagree with their location.
+fn fix_tree(verilib_dir: &Path, violations: &[Violation]) -> Result<()> {
+    let dirs: Vec<PathBuf> = violations
+        .iter()
+        .filter(|v| v.problem == TreeProblem::DuplicateIndex)
+        .map(|v| v.path.clone())
+        .collect();
+    if !dirs.is_empty() {
+        let plan = plan_renumbering(verilib_dir, &dirs)?;
+        apply_renumbering(verilib_dir, &dirs, &plan)?;
+    }
+
+    // Renumbering renames files, so find the remaining mismatches afresh.
+    let mut mismatched: Vec<PathBuf> = validate_tree(verilib_dir)?
+        .into_iter()
+        .filter(|v| {
+            matches!(
+                v.problem,
+                TreeProblem::IndexMismatch | TreeProblem::PathMismatch
+            )
+        })
+        .map(|v| v.path)
+        .collect();
+    mismatched.dedup();
+
+    for path in mismatched {
+        let stem = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .trim_end_matches(".meta.verilib")
+            .to_string();
+        let (index, identifier) = split_index(&stem);
+        let mut meta = read_json(&path)?;
+        if let Some(obj) = meta.as_object_mut() {
+            if let Some(index) = index {
+                obj.insert("index".to_string(), serde_json::Value::from(index));
+            }
+            obj.insert(
+                "path".to_string(),
+                serde_json::Value::from(meta_location(verilib_dir, &path, identifier)),
+            );
+        }
+        fs::write(&path, serde_json::to_string_pretty(&meta)?)
+            .with_context(|| format!("Failed to write file: {:?}", path))?;
+    }
+    Ok(())
+}
+
+fn read_json(path: &Path) -> Result<serde_json::Value> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read file: {:?}", path))?;
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse {:?}", path))
+}
+
+/// The `[N] - ` index of a file stem, if any, and the identifier after it.
+fn split_index(stem: &str) -> (Option<u64>, &str) {
+    let re = regex::Regex::new(r"^\[(\d+)\]\s*-\s*").unwrap();
+    match re.captures(stem) {
+        Some(caps) => (caps[1].parse().ok(), &stem[caps[0].len()..]),
+        None => (None, stem),
+    }
+}
+
+/// The `path` a meta file at `path` should record: `/<dir>/<identifier>`,
+/// with `<dir>` relative to `.verilib`.
+fn meta_location(verilib_dir: &Path, path: &Path, identifier: &str) -> String {
+    let dir = path
+        .parent()
+        .and_then(|p| p.strip_prefix(verilib_dir).ok())
+        .unwrap_or(Path::new(""));
+    normalize_meta_path(&format!("{}/{}", dir.to_string_lossy(), identifier))
+}
+
+/// `path` with `/` separators, no `.` components or empty segments, and a
+/// single leading `/`.
+fn normalize_meta_path(path: &str) -> String {
+    let parts: Vec<&str> = path
+        .split(['/', '\\'])
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect();
+    format!("/{}", parts.join("/"))
+}
+
 fn validate_meta_file(file: &PathBuf) -> Result<()> {
     if !file.exists() {
         anyhow::bail!("File not found: {:?}", file);
@@ -578,6 +1234,195 @@ fn check_admin_status() -> Result<()> {
     Ok(())
 }
 
+/// Parse a `--snippet KIND=FILE` argument.
+pub fn parse_snippet_arg(arg: &str) -> Result<(SnippetKind, PathBuf), String> {
+    let (kind, file) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected KIND=FILE, got '{}'", arg))?;
+    let kind = SnippetKind::from_str(kind, true).map_err(|_| {
+        format!(
+            "unknown snippet kind '{}' (expected doc, code, spec, or proof)",
+            kind
+        )
+    })?;
+    Ok((kind, PathBuf::from(file)))
+}
+
+/// Collect snippets from `--snippet` files, in order, followed by a `--snippets-json` array.
+fn read_snippets(
+    snippet_files: Vec<(SnippetKind, PathBuf)>,
+    snippets_json: Option<PathBuf>,
+) -> Result<Vec<SnippetInput>> {
+    let mut snippets = Vec::new();
+    for (kind, file) in snippet_files {
+        let text = fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read snippet file: {:?}", file))?;
+        snippets.push(SnippetInput { kind, text });
+    }
+    if let Some(file) = snippets_json {
+        let content = fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read snippets file: {:?}", file))?;
+        let parsed: Vec<SnippetInput> = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse snippets file: {:?}", file))?;
+        snippets.extend(parsed);
+    }
+    Ok(snippets)
+}
+
+/// Meta-file snippets: the given ones in order, or the whole content as one code snippet.
+fn snippets_to_json(snippets: &[SnippetInput], content: &str) -> serde_json::Value {
+    if snippets.is_empty() {
+        return serde_json::json!([{
+            "sortorder": 0,
+            "text": content,
+            "type_id": SnippetKind::Code.type_id()
+        }]);
+    }
+    snippets
+        .iter()
+        .enumerate()
+        .map(|(i, snippet)| {
+            serde_json::json!({
+                "sortorder": i,
+                "text": snippet.text,
+                "type_id": snippet.kind.type_id()
+            })
+        })
+        .collect()
+}
+
+/// Refuse mutating operations in checkouts marked `read-only` in config.json.
+fn check_writable(project_root: &Path) -> Result<()> {
+    let config = crate::config::ProjectConfig::load(project_root)?;
+
+    if config.read_only {
+        anyhow::bail!(
+            "This checkout is read-only ('read-only' in .verilib/config.json); use --dry-run to preview changes"
+        );
+    }
+
+    Ok(())
+}
+
+fn resolve_target(target: &MetaTarget) -> Result<PathBuf> {
+    match target {
+        MetaTarget::File(file) => resolve_file_path(file),
+        MetaTarget::Identifier(id) => {
+            lookup_meta_file(Path::new(".verilib"), "identifier", id, |e| {
+                e.identifier.as_deref()
+            })
+        }
+        MetaTarget::CodeName(name) => {
+            lookup_meta_file(Path::new(".verilib"), "code-name", name, |e| {
+                e.code_name.as_deref()
+            })
+        }
+    }
+}
+
+/// Find the meta file whose `key` field equals `value`. The cached index is
+/// used while it lists exactly the meta files in the tree with their current
+/// modification times; otherwise it is rebuilt first, so files added, moved,
+/// or edited since are found and duplicates are reported.
+fn lookup_meta_file(
+    verilib_dir: &Path,
+    label: &str,
+    value: &str,
+    key: impl Fn(&MetaIndexEntry) -> Option<&str>,
+) -> Result<PathBuf> {
+    if !verilib_dir.exists() {
+        fail!(
+            MissingPrerequisite,
+            "No .verilib directory found. Please run 'init' first."
+        );
+    }
+
+    let stamps = meta_file_stamps(verilib_dir);
+    let mut index = load_meta_index(verilib_dir);
+    let fresh = index.len() == stamps.len()
+        && index.iter().zip(&stamps).all(|(entry, (path, modified))| {
+            entry.path == *path && modified.is_some() && entry.modified == *modified
+        });
+    if !fresh {
+        index = build_meta_index(stamps);
+        save_meta_index(verilib_dir, &index);
+    }
+
+    let mut matches: Vec<PathBuf> = index
+        .iter()
+        .filter(|e| key(e) == Some(value))
+        .map(|e| e.path.clone())
+        .collect();
+
+    match matches.len() {
+        0 => anyhow::bail!("No meta file found with {} '{}'", label, value),
+        1 => Ok(matches.remove(0)),
+        _ => {
+            let paths: Vec<String> = matches.iter().map(|p| p.display().to_string()).collect();
+            anyhow::bail!(
+                "{} '{}' is ambiguous; matching files:\n  {}\nUse --file to pick one.",
+                label,
+                value,
+                paths.join("\n  ")
+            )
+        }
+    }
+}
+
+/// Every meta file under `verilib_dir` with its modification time, in path
+/// order. Only the directories are read, not the files.
+fn meta_file_stamps(verilib_dir: &Path) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let mut stamps: Vec<_> = walk::verilib_files(verilib_dir)
+        .filter(|path| path.to_string_lossy().ends_with(".meta.verilib"))
+        .map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            (path, modified)
+        })
+        .collect();
+    stamps.sort();
+    stamps
+}
+
+/// Read the meta files in `stamps`. Unreadable ones are kept without fields,
+/// so that the index still lists every file.
+fn build_meta_index(stamps: Vec<(PathBuf, Option<SystemTime>)>) -> Vec<MetaIndexEntry> {
+    stamps
+        .into_iter()
+        .map(|(path, modified)| {
+            let value: Option<serde_json::Value> = fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| serde_json::from_str(&content).ok());
+            let field = |name: &str| {
+                value
+                    .as_ref()
+                    .and_then(|v| v.get(name))
+                    .and_then(|v| v.as_str())
+                    .map(String::from)
+            };
+            MetaIndexEntry {
+                identifier: field("identifier"),
+                code_name: field("code_name"),
+                path,
+                modified,
+            }
+        })
+        .collect()
+}
+
+fn load_meta_index(verilib_dir: &Path) -> Vec<MetaIndexEntry> {
+    fs::read_to_string(verilib_dir.join(META_INDEX_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Best effort: a missing cache only costs a rebuild on the next lookup.
+fn save_meta_index(verilib_dir: &Path, index: &[MetaIndexEntry]) {
+    if let Ok(content) = serde_json::to_string_pretty(index) {
+        let _ = fs::write(verilib_dir.join(META_INDEX_FILE), content);
+    }
+}
+
 fn resolve_file_path(input: &Path) -> Result<PathBuf> {
     use regex::Regex;
 
@@ -590,6 +1435,7 @@ fn resolve_file_path(input: &Path) -> Result<PathBuf> {
         path = path.strip_prefix(".verilib\\").unwrap().to_string();
     }
 
+    join_inside(Path::new(".verilib"), &path).context("Invalid atom path")?;
     let path_buf = PathBuf::from(&path);
     let parent = path_buf.parent();
     let filename = path_buf.file_name().unwrap_or_default().to_string_lossy();
@@ -629,3 +1475,247 @@ fn resolve_file_path(input: &Path) -> Result<PathBuf> {
 
     Ok(resolved)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_meta(dir: &Path, name: &str, identifier: &str, code_name: &str) -> PathBuf {
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join(name);
+        let meta = serde_json::json!({ "identifier": identifier, "code_name": code_name });
+        fs::write(&path, meta.to_string()).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_snippets_keep_order_and_type_ids() {
+        assert_eq!(
+            parse_snippet_arg("spec=ensures.rs").unwrap(),
+            (SnippetKind::Spec, PathBuf::from("ensures.rs"))
+        );
+        assert!(parse_snippet_arg("tests=x.rs").is_err());
+        assert!(parse_snippet_arg("x.rs").is_err());
+
+        let snippets = vec![
+            SnippetInput {
+                kind: SnippetKind::Spec,
+                text: "ensures true\n".to_string(),
+            },
+            SnippetInput {
+                kind: SnippetKind::Code,
+                text: "fn f() {}\n".to_string(),
+            },
+        ];
+        let json = snippets_to_json(&snippets, "");
+        assert_eq!(json[0]["type_id"], 3);
+        assert_eq!(json[1]["type_id"], 2);
+        assert_eq!(json[1]["sortorder"], 1);
+
+        assert_eq!(snippets_to_json(&[], "fn g() {}")[0]["type_id"], 2);
+    }
+
+    #[test]
+    fn test_read_only_config_blocks_writes() {
+        let dir = TempDir::new().unwrap();
+        assert!(check_writable(dir.path()).is_ok());
+
+        let config = crate::config::ProjectConfig {
+            read_only: true,
+            ..Default::default()
+        };
+        config.save(dir.path()).unwrap();
+        assert!(check_writable(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_lookup_survives_index_renumbering() {
+        let dir = TempDir::new().unwrap();
+        let verilib = dir.path().join(".verilib");
+        let old = write_meta(
+            &verilib.join("src"),
+            "[3] - lib.rs.meta.verilib",
+            "lib.rs",
+            "probe:crate/lib",
+        );
+
+        let by_id =
+            |id: &str| lookup_meta_file(&verilib, "identifier", id, |e| e.identifier.as_deref());
+        assert_eq!(by_id("lib.rs").unwrap(), old);
+
+        let renamed = verilib.join("src").join("[0] - lib.rs.meta.verilib");
+        fs::rename(&old, &renamed).unwrap();
+        assert_eq!(by_id("lib.rs").unwrap(), renamed);
+
+        let by_code = lookup_meta_file(&verilib, "code-name", "probe:crate/lib", |e| {
+            e.code_name.as_deref()
+        });
+        assert_eq!(by_code.unwrap(), renamed);
+    }
+
+    #[test]
+    fn test_lookup_notices_meta_files_added_or_edited_later() {
+        let dir = TempDir::new().unwrap();
+        let verilib = dir.path().join(".verilib");
+        let first = write_meta(
+            &verilib.join("a"),
+            "[0] - mod.rs.meta.verilib",
+            "mod.rs",
+            "probe:a",
+        );
+        let by_id =
+            |id: &str| lookup_meta_file(&verilib, "identifier", id, |e| e.identifier.as_deref());
+        assert_eq!(by_id("mod.rs").unwrap(), first);
+
+        let second = write_meta(
+            &verilib.join("b"),
+            "[0] - other.rs.meta.verilib",
+            "other.rs",
+            "probe:b",
+        );
+        assert_eq!(by_id("other.rs").unwrap(), second);
+
+        // Edited in place to collide with the cached entry of the first file.
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        write_meta(
+            &verilib.join("b"),
+            "[0] - other.rs.meta.verilib",
+            "mod.rs",
+            "probe:b",
+        );
+        let err = by_id("mod.rs").unwrap_err();
+        assert!(err.to_string().contains("ambiguous"));
+    }
+
+    #[test]
+    fn test_lookup_reports_ambiguous_identifier() {
+        let dir = TempDir::new().unwrap();
+        let verilib = dir.path().join(".verilib");
+        write_meta(
+            &verilib.join("a"),
+            "[0] - mod.rs.meta.verilib",
+            "mod.rs",
+            "probe:a",
+        );
+        write_meta(
+            &verilib.join("b"),
+            "[0] - mod.rs.meta.verilib",
+            "mod.rs",
+            "probe:b",
+        );
+
+        let err = lookup_meta_file(&verilib, "identifier", "mod.rs", |e| {
+            e.identifier.as_deref()
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("ambiguous"));
+        assert!(lookup_meta_file(&verilib, "identifier", "missing.rs", |e| {
+            e.identifier.as_deref()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_validate_finds_and_fixes_tree_problems() {
+        let dir = TempDir::new().unwrap();
+        let verilib = dir.path().join(".verilib");
+        let src = verilib.join("src");
+        fs::create_dir_all(&src).unwrap();
+        let atom = |name: &str, meta: Option<serde_json::Value>| {
+            fs::write(src.join(format!("{}.atom.verilib", name)), "fn f() {}").unwrap();
+            if let Some(meta) = meta {
+                fs::write(src.join(format!("{}.meta.verilib", name)), meta.to_string()).unwrap();
+            }
+        };
+        let meta = |identifier: &str, index: u32, path: &str| {
+            serde_json::json!({
+                "code_name": format!("probe:{}", identifier),
+                "identifier": identifier,
+                "index": index,
+                "path": path,
+            })
+        };
+        atom("[0] - a.rs", Some(meta("a.rs", 0, "/./src/a.rs")));
+        atom("[0] - b.rs", Some(meta("b.rs", 0, "/src/b.rs")));
+        atom("[1] - c.rs", Some(meta("c.rs", 7, "/elsewhere/c.rs")));
+        atom("[2] - d.rs", None);
+        fs::write(src.join("layout.verilib"), "{").unwrap();
+
+        let problems = |violations: &[Violation]| -> Vec<(TreeProblem, String)> {
+            violations
+                .iter()
+                .map(|v| {
+                    let name = v.path.file_name().unwrap().to_string_lossy().to_string();
+                    (v.problem, name)
+                })
+                .collect()
+        };
+        let found = validate_tree(&verilib).unwrap();
+        assert_eq!(
+            problems(&found),
+            [
+                (TreeProblem::DuplicateIndex, "src".to_string()),
+                (
+                    TreeProblem::IndexMismatch,
+                    "[1] - c.rs.meta.verilib".to_string()
+                ),
+                (
+                    TreeProblem::PathMismatch,
+                    "[1] - c.rs.meta.verilib".to_string()
+                ),
+                (
+                    TreeProblem::MissingMeta,
+                    "[2] - d.rs.atom.verilib".to_string()
+                ),
+                (TreeProblem::InvalidLayout, "layout.verilib".to_string()),
+            ]
+        );
+
+        fix_tree(&verilib, &found).unwrap();
+        assert_eq!(
+            problems(&validate_tree(&verilib).unwrap()),
+            [
+                (
+                    TreeProblem::MissingMeta,
+                    "[3] - d.rs.atom.verilib".to_string()
+                ),
+                (TreeProblem::InvalidLayout, "layout.verilib".to_string()),
+            ]
+        );
+        let c = read_json(&src.join("[2] - c.rs.meta.verilib")).unwrap();
+        assert_eq!(c["index"], 2);
+        assert_eq!(c["path"], "/src/c.rs");
+    }
+
+    #[test]
+    fn test_sort_files_keeps_path_order_on_ties() {
+        let file = |path: &str, status_id, specified, modified_secs| FileInfo {
+            path: path.to_string(),
+            specified,
+            ignored: false,
+            verified: status_id == 2,
+            status_id,
+            review_status: None,
+            modified: Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(modified_secs)),
+        };
+        let order = |sort| {
+            let mut files = vec![
+                file(".verilib/a/[2] - zeta.meta.verilib", 2, true, 30),
+                file(".verilib/b/[1] - alpha.meta.verilib", 0, false, 10),
+                file(".verilib/c/[3] - mid.meta.verilib", 0, true, 20),
+            ];
+            sort_files(&mut files, sort);
+            files
+                .into_iter()
+                .map(|f| f.path[9..10].to_string())
+                .collect::<String>()
+        };
+
+        assert_eq!(order(ListSort::Path), "abc");
+        assert_eq!(order(ListSort::Name), "bca");
+        assert_eq!(order(ListSort::Status), "bca");
+        assert_eq!(order(ListSort::Specified), "bac");
+        assert_eq!(order(ListSort::Modified), "acb");
+    }
+}
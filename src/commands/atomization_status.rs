@@ -0,0 +1,45 @@
+use anyhow::Result;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::commands::auth_check::stored_api_key;
+use crate::config::{resolve_base_url, ProjectConfig};
+use crate::constants::init_required_msg;
+use crate::download::{fetch_atomization_status, wait_for_atomization};
+use crate::exit::{ExitContext, ExitKind};
+
+pub async fn handle_atomization_status(
+    wait: bool,
+    timeout: Option<u64>,
+    base_url: Option<String>,
+    debug: bool,
+) -> Result<()> {
+    let project_root = PathBuf::from(".");
+    let config = ProjectConfig::load(&project_root)?;
+
+    let repo = config
+        .repo
+        .ok_or_else(|| anyhow::anyhow!(init_required_msg()))
+        .exit_kind(ExitKind::MissingPrerequisite)?;
+    let url = resolve_base_url(base_url.as_deref(), Some(&repo));
+    let api_key = stored_api_key()?;
+
+    if debug {
+        println!(
+            "Debug: Checking atomization status for repository {}",
+            repo.id
+        );
+        println!("Debug: Using URL: {}", url);
+    }
+
+    if wait {
+        wait_for_atomization(&repo.id, &url, &api_key, timeout.map(Duration::from_secs)).await?;
+        println!("Atomization complete.");
+        return Ok(());
+    }
+
+    let status = fetch_atomization_status(&repo.id, &url, &api_key).await?;
+    println!("Atomization status: {}", status.description());
+
+    Ok(())
+}
@@ -2,26 +2,44 @@
 //!
 //! Enrich structure files with metadata from SCIP atoms.
 
-use crate::config::ProjectConfig;
+use crate::commands::atomize_renames;
+use crate::config::{resolve_source_roots, ProjectConfig, ProjectMode, SourceRoot};
+use crate::exit::{fail, ExitContext, ExitKind};
+use crate::freshness::{self, Artifact};
+use crate::metrics;
+use crate::output;
+use crate::paths::relative_arg;
+use crate::structure::layout::relocations;
+use crate::structure::probe_output::{self, load_probe_map};
+use crate::structure::schema::{load_map, save_map};
 use crate::structure::{
-    cleanup_intermediate_files, parse_frontmatter, run_command, write_frontmatter, CommandConfig,
-    ExternalTool, ATOMIZE_INTERMEDIATE_FILES,
+    assign_owners, cleanup_intermediate_files, parse_frontmatter, run_command, scip,
+    update_frontmatter, Atom, CommandConfig, ExecutionMode, ExternalTool, FrontmatterFormat,
+    LayoutKind, ReviewStatus, StubEntry, ATOMIZE_INTERMEDIATE_FILES,
 };
+use crate::walk;
 use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
 use intervaltree::IntervalTree;
+use serde::Serialize;
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
 
 /// Run the atomize subcommand.
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_atomize(
     project_root: PathBuf,
     update_stubs: bool,
     no_probe: bool,
     check_only: bool,
     atoms_only: bool,
-    rust_analyzer: bool,
+    analyzer: Analyzer,
+    from_scip: Option<PathBuf>,
+    report_skips: Option<PathBuf>,
+    line_tolerance: Option<u32>,
+    apply_renames: bool,
 ) -> Result<()> {
     let project_root = project_root
         .canonicalize()
@@ -29,24 +47,26 @@ pub async fn handle_atomize(
 
     // Decide whether to use atoms-only mode:
     //   1. Explicit --atoms-only flag always wins
-    //   2. Cargo.toml has no Verus deps -> pure Rust -> atoms-only + rust-analyzer
-    //   3. Verus project with config.json -> full pipeline
-    //   4. Verus project without config.json -> error (need create first)
+    //   2. project-mode in config.json
+    //   3. Cargo.toml has no Verus deps -> pure Rust -> atoms-only, recorded as
+    //      project-mode so later commands know
+    //   4. Verus project with config.json -> full pipeline
+    //   5. Verus project without config.json -> error (need create first)
+    let mut project_config = ProjectConfig::open(&project_root)?;
     let is_pure_rust = !is_verus_project(&project_root);
-    let use_atoms_only = if atoms_only {
+    let use_atoms_only = if atoms_only || project_config.is_atoms_only() {
         true
-    } else if is_pure_rust {
+    } else if is_pure_rust && project_config.project_mode.is_none() {
         println!("No Verus dependencies detected in Cargo.toml.");
-        println!("Auto-enabling atoms-only mode for pure Rust project.\n");
+        println!("Auto-enabling atoms-only mode for pure Rust project.");
+        project_config.project_mode = Some(ProjectMode::AtomsOnly);
+        project_config.save(&project_root)?;
+        println!("Recorded project-mode \"atoms-only\" in .verilib/config.json.\n");
         true
     } else {
-        ProjectConfig::init(&project_root)?;
-        if ProjectConfig::global()
-            .unwrap()
-            .structure_root_path()
-            .is_err()
-        {
-            bail!(
+        if project_config.structure_root_path().is_err() {
+            fail!(
+                MissingPrerequisite,
                 "Verus project detected but no .verilib/config.json found. \
                  Run 'verilib-cli create' first."
             );
@@ -54,76 +74,180 @@ pub async fn handle_atomize(
         false
     };
 
-    let use_rust_analyzer = rust_analyzer || is_pure_rust;
+    let source_roots = resolve_source_roots(&project_root, &project_config.source_roots);
+
+    let atoms_source = if let Some(path) = from_scip {
+        AtomsSource::Scip(path)
+    } else if no_probe {
+        AtomsSource::File
+    } else {
+        AtomsSource::Probe {
+            analyzer,
+            is_verus: !is_pure_rust,
+            source_roots: source_roots.clone(),
+        }
+    };
 
     if use_atoms_only {
-        return handle_atoms_only(&project_root, no_probe, use_rust_analyzer);
+        return handle_atoms_only(&project_root, &atoms_source);
     }
 
-    // init already called when checking structure_root above
-    let config = ProjectConfig::global().unwrap();
+    let config = &project_config;
     let structure_root = config.structure_root_path()?;
     let stubs_path = config.stubs_path();
     let atoms_path = config.atoms_path();
     let cmd_config = config.command_config();
 
-    // Step 1: Generate stubs from .md files
-    let stubs = if no_probe {
-        load_stubs_from_md_files(&structure_root)?
+    // Enrichment from the last run, for scoring possible renames; stubify
+    // overwrites stubs.json below.
+    let previous: HashMap<String, StubEntry> = if stubs_path.exists() {
+        load_map(&stubs_path).unwrap_or_default()
     } else {
-        generate_stubs(&project_root, &structure_root, &stubs_path, &cmd_config)?
+        HashMap::new()
     };
+
+    // Step 1: Generate stubs from .md files (SCIP import never runs probe-verus,
+    // and probe-verus stubify only reads YAML frontmatter)
+    let stubs = {
+        let _stage = metrics::stage("stubs");
+        if !matches!(atoms_source, AtomsSource::Probe { .. })
+            || config.frontmatter_format == Some(FrontmatterFormat::Toml)
+        {
+            load_stubs_from_md_files(&structure_root)?
+        } else {
+            generate_stubs(&project_root, &structure_root, &stubs_path, &cmd_config)?
+        }
+    };
+    metrics::count("stubs", stubs.len());
     println!("Loaded {} stubs", stubs.len());
+    warn_misplaced_stubs(&stubs, config.structure_layout.unwrap_or_default());
 
     // Step 2: Generate or load atoms.json
-    let probe_atoms = if no_probe {
-        load_atoms_from_file(&atoms_path)?
-    } else {
-        generate_probe_atoms(&project_root, &atoms_path, &cmd_config, use_rust_analyzer)?
+    let probe_atoms = {
+        let _stage = metrics::stage("atoms");
+        load_atoms(&project_root, &atoms_path, &cmd_config, &atoms_source)?
     };
+    metrics::count("atoms", probe_atoms.len());
     println!("Loaded {} atoms", probe_atoms.len());
 
     // Step 3: Build probe index for fast lookups
-    let probe_index = ProbeIndex::build(&probe_atoms, project_root);
+    let line_tolerance = line_tolerance
+        .or(config.line_tolerance)
+        .unwrap_or(DEFAULT_LINE_TOLERANCE);
+    let probe_index = ProbeIndex::build(&probe_atoms, project_root, source_roots)
+        .with_line_tolerance(line_tolerance);
 
     // Step 4: Enrich stubs with code-name and all atom metadata
     println!("Enriching stubs with atom metadata...");
-    let enriched = probe_index.enrich_stubs(&stubs, &probe_atoms)?;
+    let mut enriched = {
+        let _stage = metrics::stage("enrich");
+        probe_index.enrich_stubs(&stubs, &probe_atoms)?
+    };
+
+    handle_renames(
+        &stubs,
+        &mut enriched,
+        &previous,
+        &probe_atoms,
+        &structure_root,
+        config.structure_layout.unwrap_or_default(),
+        apply_renames,
+    )?;
+
+    if let Some(report_path) = &report_skips {
+        write_skip_report(&enriched, report_path)?;
+    }
+
+    if !config.owners.is_empty() {
+        let assigned = assign_owners(&mut enriched, &config.owners);
+        println!("Owners assigned: {}", assigned);
+    }
 
     // If check_only, compare .md stubs against enriched and report mismatches
     if check_only {
         println!("Checking .md stub files against enriched stubs...");
-        return check_stubs_match(&stubs, &enriched);
+        return check_stubs_match(&stubs, &enriched).exit_kind(ExitKind::CheckFailed);
     }
 
     // Step 5: Save enriched stubs.json
     println!("Saving enriched stubs to {}...", stubs_path.display());
-    let content = serde_json::to_string_pretty(&enriched)?;
-    std::fs::write(&stubs_path, content)?;
+    let _stage = metrics::stage("save");
+    save_map(&stubs_path, &enriched)?;
 
     // Optionally update .md files with code-name
     if update_stubs {
         println!("Updating structure files with code-names...");
-        update_structure_files(&enriched, &structure_root)?;
+        update_structure_files(&stubs, &enriched, &structure_root)?;
     }
+    freshness::record(config, Artifact::Stubs)?;
 
     println!("Done.");
     Ok(())
 }
 
+/// Where atoms.json comes from.
+enum AtomsSource {
+    /// Run probe-verus atomize on the project and every extra source root.
+    Probe {
+        /// As requested; `Auto` is resolved by [`select_analyzer`].
+        analyzer: Analyzer,
+        /// Whether Cargo.toml has Verus dependencies.
+        is_verus: bool,
+        source_roots: Vec<SourceRoot>,
+    },
+    /// Read an existing atoms.json (`--no-probe`).
+    File,
+    /// Convert a prebuilt SCIP index (`--from-scip`).
+    Scip(PathBuf),
+}
+
+fn load_atoms(
+    project_root: &Path,
+    atoms_path: &Path,
+    config: &CommandConfig,
+    source: &AtomsSource,
+) -> Result<HashMap<String, Atom>> {
+    match source {
+        AtomsSource::Probe {
+            analyzer,
+            is_verus,
+            source_roots,
+        } => {
+            let choice = select_analyzer(project_root, *analyzer, *is_verus, config);
+            generate_probe_atoms(project_root, atoms_path, config, &choice, source_roots)
+        }
+        AtomsSource::File => load_atoms_from_file(atoms_path),
+        AtomsSource::Scip(scip_path) => import_scip_atoms(scip_path, atoms_path),
+    }
+}
+
+/// Convert a SCIP index to atoms and save them to atoms.json.
+fn import_scip_atoms(scip_path: &Path, atoms_path: &Path) -> Result<HashMap<String, Atom>> {
+    println!("Importing atoms from SCIP index {}...", scip_path.display());
+    let index = scip::load_index(scip_path)?;
+    let atoms = scip::atoms_from_index(&index);
+
+    if let Some(parent) = atoms_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    save_map(atoms_path, &atoms)?;
+
+    Ok(atoms)
+}
+
 /// Atoms-only mode: just produce atoms.json without stubs enrichment.
-fn handle_atoms_only(project_root: &Path, no_probe: bool, rust_analyzer: bool) -> Result<()> {
+fn handle_atoms_only(project_root: &Path, source: &AtomsSource) -> Result<()> {
     let verilib_path = project_root.join(".verilib");
     std::fs::create_dir_all(&verilib_path).context("Failed to create .verilib directory")?;
 
     let atoms_path = verilib_path.join("atoms.json");
     let config = CommandConfig::default();
 
-    let atoms = if no_probe {
-        load_atoms_from_file(&atoms_path)?
-    } else {
-        generate_probe_atoms(project_root, &atoms_path, &config, rust_analyzer)?
+    let atoms = {
+        let _stage = metrics::stage("atoms");
+        load_atoms(project_root, &atoms_path, &config, source)?
     };
+    metrics::count("atoms", atoms.len());
 
     println!("Atoms-only mode: generated {} atoms.", atoms.len());
     println!("Output: {}", atoms_path.display());
@@ -173,34 +297,142 @@ fn has_verus_indicators(parsed: &toml::Value) -> bool {
     false
 }
 
-const SKIP_DIRS: &[&str] = &["target", ".git", "node_modules"];
+/// Records how atoms.json was generated, next to it.
+const PROVENANCE_FILE: &str = "atoms.provenance.json";
+
+/// SCIP indexer probe-verus atomizes with (`atomize --analyzer`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Analyzer {
+    /// Choose from the crate and the installed analyzers.
+    #[default]
+    Auto,
+    /// rust-analyzer: plain Rust, no `verus!` blocks.
+    Rust,
+    /// verus-analyzer: understands `verus!` blocks.
+    Verus,
+}
 
-/// Check if a project uses Verus by scanning all Cargo.toml files under the
-/// project root. Skips `target/`, `.git/`, and `node_modules/` directories.
-fn is_verus_project(project_root: &Path) -> bool {
-    for entry in WalkDir::new(project_root).into_iter().filter_entry(|e| {
-        !e.file_type().is_dir() || !SKIP_DIRS.contains(&e.file_name().to_str().unwrap_or(""))
-    }) {
-        let entry = match entry {
-            Ok(e) => e,
-            Err(_) => continue,
-        };
-        if entry.file_name() != "Cargo.toml" || !entry.file_type().is_file() {
-            continue;
+impl Analyzer {
+    fn label(self) -> &'static str {
+        match self {
+            Analyzer::Auto => "auto",
+            Analyzer::Rust => "rust-analyzer",
+            Analyzer::Verus => "verus-analyzer",
         }
-        let content = match std::fs::read_to_string(entry.path()) {
-            Ok(c) => c,
-            Err(_) => continue,
-        };
-        let parsed: toml::Value = match content.parse() {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
-        if has_verus_indicators(&parsed) {
-            return true;
+    }
+}
+
+/// The analyzer to run and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AnalyzerChoice {
+    /// `Rust` or `Verus`, never `Auto`.
+    analyzer: Analyzer,
+    reason: &'static str,
+}
+
+/// Resolve `requested`: pure Rust crates and crates without `verus!` blocks
+/// get rust-analyzer, as do Verus crates when only rust-analyzer is installed
+/// locally (with a warning, since `verus!` blocks are then skipped).
+fn select_analyzer(
+    project_root: &Path,
+    requested: Analyzer,
+    is_verus: bool,
+    config: &CommandConfig,
+) -> AnalyzerChoice {
+    let choice = |analyzer, reason| AnalyzerChoice { analyzer, reason };
+    match requested {
+        Analyzer::Rust | Analyzer::Verus => return choice(requested, "requested"),
+        Analyzer::Auto => {}
+    }
+    if !is_verus {
+        return choice(Analyzer::Rust, "no Verus dependencies in Cargo.toml");
+    }
+    if !uses_verus_macro(project_root) {
+        return choice(Analyzer::Rust, "no verus! blocks in the sources");
+    }
+    if config.execution_mode == ExecutionMode::Local
+        && which::which("verus-analyzer").is_err()
+        && which::which("rust-analyzer").is_ok()
+    {
+        output::warn(
+            "verus-analyzer is not installed; falling back to rust-analyzer, \
+             which skips functions inside verus! blocks",
+        );
+        return choice(Analyzer::Rust, "verus-analyzer not installed");
+    }
+    choice(Analyzer::Verus, "verus! blocks in the sources")
+}
+
+/// Whether any `.rs` file under the project root uses the `verus!` macro.
+fn uses_verus_macro(project_root: &Path) -> bool {
+    walk::any_source_path(project_root, |path| {
+        path.extension().is_some_and(|x| x == "rs")
+            && std::fs::read_to_string(path).is_ok_and(|content| content.contains("verus!"))
+    })
+}
+
+/// How atoms.json was generated (`atoms.provenance.json`).
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct AtomsProvenance {
+    generated_at: DateTime<Utc>,
+    verilib_cli: &'static str,
+    execution_mode: ExecutionMode,
+    analyzer: Analyzer,
+    analyzer_reason: &'static str,
+    /// The analyzer each source directory was indexed with.
+    sources: Vec<SourceAnalyzer>,
+    /// The analyzer that produced each atom, by code-name.
+    atom_analyzers: BTreeMap<String, Analyzer>,
+}
+
+/// How one source directory was indexed.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct SourceAnalyzer {
+    /// Relative to the project root.
+    source: String,
+    analyzer: Analyzer,
+    /// Why verus-analyzer was given up on, when it was.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fallback_reason: Option<String>,
+}
+
+impl AtomsProvenance {
+    fn new(
+        choice: &AnalyzerChoice,
+        config: &CommandConfig,
+        sources: Vec<SourceAnalyzer>,
+        atom_analyzers: BTreeMap<String, Analyzer>,
+    ) -> Self {
+        AtomsProvenance {
+            generated_at: Utc::now(),
+            verilib_cli: env!("CARGO_PKG_VERSION"),
+            execution_mode: config.execution_mode.clone(),
+            analyzer: choice.analyzer,
+            analyzer_reason: choice.reason,
+            sources,
+            atom_analyzers,
         }
     }
-    false
+
+    fn save(&self, path: &Path) -> Result<()> {
+        crate::interrupt::write_atomic(path, serde_json::to_string_pretty(self)? + "\n")
+    }
+}
+
+/// Check if a project uses Verus by scanning all Cargo.toml files under the
+/// project root, skipping ignored and build directories (see [`walk`]).
+fn is_verus_project(project_root: &Path) -> bool {
+    walk::any_source_path(project_root, |path| {
+        path.file_name().is_some_and(|name| name == "Cargo.toml")
+            && path.is_file()
+            && std::fs::read_to_string(path)
+                .ok()
+                .and_then(|content| content.parse::<toml::Value>().ok())
+                .is_some_and(|parsed| has_verus_indicators(&parsed))
+    })
 }
 
 /// Run probe-verus stubify to generate stubs.json from .md files.
@@ -209,7 +441,7 @@ fn generate_stubs(
     structure_root: &Path,
     stubs_path: &Path,
     config: &CommandConfig,
-) -> Result<HashMap<String, Value>> {
+) -> Result<HashMap<String, StubEntry>> {
     if let Some(parent) = stubs_path.parent() {
         std::fs::create_dir_all(parent)?;
     }
@@ -223,17 +455,9 @@ fn generate_stubs(
         &ExternalTool::Probe,
         &[
             "stubify",
-            structure_root
-                .strip_prefix(project_root)
-                .unwrap_or(structure_root)
-                .to_str()
-                .unwrap(),
+            relative_arg(structure_root, project_root)?,
             "-o",
-            stubs_path
-                .strip_prefix(project_root)
-                .unwrap_or(stubs_path)
-                .to_str()
-                .unwrap(),
+            relative_arg(stubs_path, project_root)?,
         ],
         Some(project_root),
         config,
@@ -245,22 +469,25 @@ fn generate_stubs(
         if !stderr.is_empty() {
             eprintln!("{}", stderr);
         }
-        cleanup_intermediate_files(project_root, ATOMIZE_INTERMEDIATE_FILES);
-        bail!("probe-verus stubify failed");
+        cleanup_intermediate_files(project_root, ATOMIZE_INTERMEDIATE_FILES, config, true);
+        fail!(
+            ExternalTool,
+            "probe-verus stubify failed{}",
+            output.log_hint()
+        );
     }
 
     println!("Stubs saved to {}", stubs_path.display());
 
-    let content = std::fs::read_to_string(stubs_path)?;
-    let stubs: HashMap<String, Value> = serde_json::from_str(&content)?;
-    Ok(stubs)
+    load_map(stubs_path)
 }
 
 /// Walk the structure directory and parse .md frontmatter to build stubs
 /// without requiring probe-verus. This mirrors what `probe-verus stubify` does.
-fn load_stubs_from_md_files(structure_root: &Path) -> Result<HashMap<String, Value>> {
+fn load_stubs_from_md_files(structure_root: &Path) -> Result<HashMap<String, StubEntry>> {
     if !structure_root.exists() {
-        bail!(
+        fail!(
+            MissingPrerequisite,
             "Structure directory not found at {}. Run 'verilib-cli create' first.",
             structure_root.display()
         );
@@ -271,26 +498,25 @@ fn load_stubs_from_md_files(structure_root: &Path) -> Result<HashMap<String, Val
         structure_root.display()
     );
 
-    let mut stubs: HashMap<String, Value> = HashMap::new();
-    for entry in WalkDir::new(structure_root)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        let path = entry.path();
+    let mut stubs: HashMap<String, StubEntry> = HashMap::new();
+    for path in walk::verilib_files(structure_root) {
+        let path = path.as_path();
         if path.extension().and_then(|e| e.to_str()) != Some("md") {
             continue;
         }
-        let rel_path = path
-            .strip_prefix(structure_root)
-            .unwrap_or(path)
-            .to_string_lossy()
-            .to_string();
-        match parse_frontmatter(path) {
-            Ok(fm) => {
-                stubs.insert(rel_path, serde_json::to_value(fm)?);
+        let rel_path = match relative_arg(path, structure_root) {
+            Ok(rel_path) => rel_path.to_string(),
+            Err(e) => {
+                output::warn(format!("skipping: {:#}", e));
+                continue;
+            }
+        };
+        match parse_frontmatter(path).and_then(StubEntry::from_frontmatter) {
+            Ok(stub) => {
+                stubs.insert(rel_path, stub);
             }
             Err(e) => {
-                eprintln!("Warning: skipping {}: {}", rel_path, e);
+                output::warn(format!("skipping {}: {:#}", rel_path, e));
             }
         }
     }
@@ -298,52 +524,164 @@ fn load_stubs_from_md_files(structure_root: &Path) -> Result<HashMap<String, Val
     Ok(stubs)
 }
 
+/// Warn when structure files don't follow the configured layout, e.g. after
+/// `structure-layout` was edited by hand instead of running `migrate-layout`.
+fn warn_misplaced_stubs(stubs: &HashMap<String, StubEntry>, layout: LayoutKind) {
+    let misplaced = relocations(stubs, layout.layout(), layout.layout());
+    if !misplaced.is_empty() {
+        output::warn(format!(
+            "{} structure files are not where the '{}' layout puts them; run 'verilib-cli migrate-layout --to {}' to move them",
+            misplaced.len(),
+            layout.as_str(),
+            layout.as_str()
+        ));
+    }
+}
+
 /// Load atoms from an existing atoms.json file.
-fn load_atoms_from_file(atoms_path: &Path) -> Result<HashMap<String, Value>> {
+fn load_atoms_from_file(atoms_path: &Path) -> Result<HashMap<String, Atom>> {
     if !atoms_path.exists() {
-        bail!(
+        fail!(
+            MissingPrerequisite,
             "atoms.json not found at {}. Run without --no-probe first to generate it.",
             atoms_path.display()
         );
     }
 
     println!("Loading atoms from {}...", atoms_path.display());
-    let content = std::fs::read_to_string(atoms_path)
-        .with_context(|| format!("Failed to read {}", atoms_path.display()))?;
-    let atoms: HashMap<String, Value> = serde_json::from_str(&content)
-        .with_context(|| format!("Failed to parse {}", atoms_path.display()))?;
-    Ok(atoms)
+    load_probe_map(atoms_path)
 }
 
 /// Run probe-verus atomize on the project and save results to atoms.json.
+///
+/// Each extra source root is atomized separately and its atoms are merged in
+/// with code-paths prefixed by the root's label. A source directory that
+/// verus-analyzer fails on is atomized again with rust-analyzer.
 fn generate_probe_atoms(
     project_root: &Path,
     atoms_path: &Path,
     config: &CommandConfig,
-    use_rust_analyzer: bool,
-) -> Result<HashMap<String, Value>> {
+    choice: &AnalyzerChoice,
+    source_roots: &[SourceRoot],
+) -> Result<HashMap<String, Atom>> {
     if let Some(parent) = atoms_path.parent() {
         std::fs::create_dir_all(parent)?;
     }
+    println!("Using {} ({}).", choice.analyzer.label(), choice.reason);
+
+    let (mut atoms, source) =
+        atomize_with_fallback(project_root, ".", atoms_path, config, choice.analyzer)?;
+    let mut atom_analyzers: BTreeMap<String, Analyzer> = atoms
+        .keys()
+        .map(|code_name| (code_name.clone(), source.analyzer))
+        .collect();
+    let mut sources = vec![source];
+
+    for (i, root) in source_roots.iter().enumerate() {
+        let root_atoms_path = atoms_path.with_extension(format!("root{}.json", i));
+        let root_atoms = atomize_with_fallback(
+            project_root,
+            &root.relative,
+            &root_atoms_path,
+            config,
+            choice.analyzer,
+        );
+        let _ = std::fs::remove_file(&root_atoms_path);
+        let (root_atoms, source) = root_atoms?;
 
-    let analyzer_label = if use_rust_analyzer {
-        "rust-analyzer"
-    } else {
-        "verus-analyzer"
+        for (code_name, mut atom) in root_atoms {
+            if let Some(code_path) = atom.code_path.as_mut() {
+                if Path::new(code_path.as_str()).is_relative() {
+                    *code_path = root.prefix(code_path);
+                }
+            }
+            if atoms.contains_key(&code_name) {
+                output::warn(format!(
+                    "atom '{}' from source root '{}' shadows an existing atom",
+                    code_name, root.relative
+                ));
+            }
+            atom_analyzers.insert(code_name.clone(), source.analyzer);
+            atoms.insert(code_name, atom);
+        }
+        sources.push(source);
+    }
+
+    let fallbacks = sources
+        .iter()
+        .filter(|source| source.fallback_reason.is_some())
+        .count();
+    if !source_roots.is_empty() {
+        save_map(atoms_path, &atoms)?;
+    }
+    if fallbacks > 0 {
+        output::warn(format!(
+            "{} of {} source directories were atomized with rust-analyzer after verus-analyzer failed; \
+             their functions inside verus! blocks are missing",
+            fallbacks,
+            sources.len()
+        ));
+    }
+    AtomsProvenance::new(choice, config, sources, atom_analyzers)
+        .save(&atoms_path.with_file_name(PROVENANCE_FILE))?;
+
+    println!("Atoms saved to {}", atoms_path.display());
+    Ok(atoms)
+}
+
+/// Atomize `source_dir` with `analyzer`. When verus-analyzer fails, retry
+/// with rust-analyzer rather than losing the whole run.
+fn atomize_with_fallback(
+    project_root: &Path,
+    source_dir: &str,
+    atoms_path: &Path,
+    config: &CommandConfig,
+    analyzer: Analyzer,
+) -> Result<(HashMap<String, Atom>, SourceAnalyzer)> {
+    let source = |analyzer, fallback_reason| SourceAnalyzer {
+        source: source_dir.to_string(),
+        analyzer,
+        fallback_reason,
     };
+    let use_rust_analyzer = analyzer == Analyzer::Rust;
+    match run_probe_atomize(
+        project_root,
+        source_dir,
+        atoms_path,
+        config,
+        use_rust_analyzer,
+    ) {
+        Ok(atoms) => Ok((atoms, source(analyzer, None))),
+        Err(e) if analyzer == Analyzer::Verus && !crate::interrupt::requested() => {
+            output::warn(format!(
+                "verus-analyzer failed on {}; retrying with rust-analyzer",
+                source_dir
+            ));
+            let atoms = run_probe_atomize(project_root, source_dir, atoms_path, config, true)
+                .context("rust-analyzer failed as well")?;
+            let reason = format!("{:#}", e);
+            Ok((atoms, source(Analyzer::Rust, Some(reason))))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Run probe-verus atomize on `source_dir` (relative to the project root).
+fn run_probe_atomize(
+    project_root: &Path,
+    source_dir: &str,
+    atoms_path: &Path,
+    config: &CommandConfig,
+    use_rust_analyzer: bool,
+) -> Result<HashMap<String, Atom>> {
     println!(
-        "Running probe-verus atomize ({}) on {}...",
-        analyzer_label,
-        project_root.display()
+        "Running probe-verus atomize on {}...",
+        project_root.join(source_dir).display()
     );
 
-    let atoms_path_str = atoms_path
-        .strip_prefix(project_root)
-        .unwrap_or(atoms_path)
-        .to_str()
-        .unwrap();
+    let atoms_path_str = relative_arg(atoms_path, project_root)?;
 
-    let mut args = vec!["atomize", ".", "-o", atoms_path_str, "-r"];
+    let mut args = vec!["atomize", source_dir, "-o", atoms_path_str, "-r"];
     if use_rust_analyzer {
         args.push("--rust-analyzer");
     }
@@ -356,57 +694,52 @@ fn generate_probe_atoms(
         if !stderr.is_empty() {
             eprintln!("{}", stderr);
         }
-        cleanup_intermediate_files(project_root, ATOMIZE_INTERMEDIATE_FILES);
-        bail!("probe-verus atomize failed");
+        cleanup_intermediate_files(project_root, ATOMIZE_INTERMEDIATE_FILES, config, true);
+        fail!(
+            ExternalTool,
+            "probe-verus atomize failed{}",
+            output.log_hint()
+        );
     }
 
-    cleanup_intermediate_files(project_root, ATOMIZE_INTERMEDIATE_FILES);
-
-    println!("Atoms saved to {}", atoms_path.display());
+    cleanup_intermediate_files(project_root, ATOMIZE_INTERMEDIATE_FILES, config, false);
 
-    let content = std::fs::read_to_string(atoms_path)?;
-    let atoms: HashMap<String, Value> = serde_json::from_str(&content)?;
-    Ok(atoms)
+    probe_output::canonicalize(atoms_path)?;
+    load_probe_map(atoms_path)
 }
 
+/// Lines a drifted code-line may be from an atom's start and still match it.
+pub const DEFAULT_LINE_TOLERANCE: u32 = 5;
+
 /// Interval-tree index for fast line-based atom lookups, bundled with the
 /// project root used to canonicalize code-paths (resolving symlinks).
-struct ProbeIndex {
+pub struct ProbeIndex {
     trees: HashMap<String, IntervalTree<u32, String>>,
     project_root: PathBuf,
+    source_roots: Vec<SourceRoot>,
+    line_tolerance: u32,
 }
 
 impl ProbeIndex {
     /// Build the index from parsed atoms, canonicalizing every code-path
-    /// relative to `project_root` so that symlinks are transparent.
-    fn build(atoms: &HashMap<String, Value>, project_root: PathBuf) -> Self {
+    /// relative to `project_root` (or a source root) so that symlinks are transparent.
+    pub fn build(
+        atoms: &HashMap<String, Atom>,
+        project_root: PathBuf,
+        source_roots: Vec<SourceRoot>,
+    ) -> Self {
         let mut trees: HashMap<String, Vec<(std::ops::Range<u32>, String)>> = HashMap::new();
 
-        for (probe_name, atom_data) in atoms {
-            let code_path = match atom_data.get("code-path").and_then(|v| v.as_str()) {
-                Some(p) => canonicalize_code_path(&project_root, p),
-                None => continue,
-            };
-
-            let code_text = match atom_data.get("code-text") {
-                Some(ct) => ct,
-                None => continue,
-            };
-
-            let lines_start = match code_text.get("lines-start").and_then(|v| v.as_u64()) {
-                Some(l) => l as u32,
-                None => continue,
-            };
-
-            let lines_end = match code_text.get("lines-end").and_then(|v| v.as_u64()) {
-                Some(l) => l as u32,
-                None => continue,
+        for (probe_name, atom) in atoms {
+            let (Some(code_path), Some(code_text)) = (&atom.code_path, &atom.code_text) else {
+                continue;
             };
+            let code_path = canonicalize_code_path(&project_root, &source_roots, code_path);
 
-            trees
-                .entry(code_path)
-                .or_default()
-                .push((lines_start..lines_end + 1, probe_name.clone()));
+            trees.entry(code_path).or_default().push((
+                code_text.lines_start..code_text.lines_end + 1,
+                probe_name.clone(),
+            ));
         }
 
         Self {
@@ -415,19 +748,40 @@ impl ProbeIndex {
                 .map(|(k, v)| (k, v.into_iter().collect()))
                 .collect(),
             project_root,
+            source_roots,
+            line_tolerance: 0,
         }
     }
 
-    /// Look up code-name from code-path and code-line.
+    /// Also match atoms that start within `tolerance` lines of a code-line
+    /// that no atom contains, e.g. after edits above the function.
+    pub fn with_line_tolerance(mut self, tolerance: u32) -> Self {
+        self.line_tolerance = tolerance;
+        self
+    }
+
+    /// Look up code-name from code-path and code-line, explaining why none matched.
     /// Canonicalizes the code-path to resolve symlinks before lookup.
-    fn lookup_code_name(&self, code_path: &str, code_line: u32) -> Option<String> {
-        let canonical = canonicalize_code_path(&self.project_root, code_path);
-        let tree = self.trees.get(&canonical)?;
+    pub fn lookup_code_name(
+        &self,
+        code_path: &str,
+        code_line: u32,
+    ) -> Result<(String, MatchConfidence), SkipReason> {
+        let resolved = resolve_code_path(&self.project_root, &self.source_roots, code_path);
+        let canonical = resolved.as_deref().unwrap_or(code_path);
+        let tree = match self.trees.get(canonical) {
+            Some(tree) => tree,
+            None if resolved.is_none() => return Err(SkipReason::CanonicalizationFailed),
+            None => return Err(SkipReason::NoAtomsInFile),
+        };
 
         let matching: Vec<_> = tree.query(code_line..code_line + 1).collect();
 
         if matching.is_empty() {
-            return None;
+            return self
+                .nearest_atom_start(tree, code_line)
+                .map(|name| (name, MatchConfidence::Fuzzy))
+                .ok_or(SkipReason::NoAtomAtLine);
         }
 
         let exact: Vec<_> = matching
@@ -436,139 +790,397 @@ impl ProbeIndex {
             .collect();
 
         if !exact.is_empty() {
-            return Some(exact[0].value.clone());
+            return Ok((exact[0].value.clone(), MatchConfidence::Exact));
         }
 
-        Some(matching[0].value.clone())
+        Ok((matching[0].value.clone(), MatchConfidence::Contained))
+    }
+
+    /// Find the atom whose start is nearest to `code_line`, within the line tolerance.
+    /// Ties are broken by code-name so that the result is deterministic.
+    fn nearest_atom_start(
+        &self,
+        tree: &IntervalTree<u32, String>,
+        code_line: u32,
+    ) -> Option<String> {
+        if self.line_tolerance == 0 {
+            return None;
+        }
+        let window = code_line.saturating_sub(self.line_tolerance)
+            ..code_line.saturating_add(self.line_tolerance + 1);
+
+        tree.query(window)
+            .filter(|iv| iv.range.start.abs_diff(code_line) <= self.line_tolerance)
+            .min_by(|a, b| {
+                (a.range.start.abs_diff(code_line), &a.value)
+                    .cmp(&(b.range.start.abs_diff(code_line), &b.value))
+            })
+            .map(|iv| iv.value.clone())
     }
 
     /// Resolve code-name and atom for an entry.
-    /// First tries existing code-name, then falls back to inference from code-path/code-line.
+    /// An `atom-override` pin wins unconditionally; otherwise tries the existing code-name,
+    /// then falls back to inference from code-path/code-line.
     fn resolve_code_name_and_atom<'a>(
         &self,
-        entry: &Value,
+        entry: &StubEntry,
         file_path: &str,
-        atoms: &'a HashMap<String, Value>,
-    ) -> Option<(String, &'a Value)> {
-        if let Some(name) = entry.get("code-name").and_then(|v| v.as_str()) {
-            if let Some(atom) = atoms.get(name) {
-                return Some((name.to_string(), atom));
-            }
+        atoms: &'a HashMap<String, Atom>,
+    ) -> Result<(String, &'a Atom, MatchConfidence), SkipReason> {
+        if let Some(pinned) = entry.atom_override.as_deref() {
+            return match atoms.get(pinned) {
+                Some(atom) => Ok((pinned.to_string(), atom, MatchConfidence::Override)),
+                None => {
+                    output::warn(format!(
+                        "{}: {} '{}' is not in atoms.json",
+                        file_path, ATOM_OVERRIDE_KEY, pinned
+                    ));
+                    Err(SkipReason::OverrideNotInAtoms)
+                }
+            };
         }
 
-        let code_path = entry.get("code-path").and_then(|v| v.as_str());
-        let code_line = entry
-            .get("code-line")
-            .and_then(|v| v.as_u64())
-            .map(|l| l as u32);
+        let stale_code_name = match entry.code_name.as_deref() {
+            Some(name) => match atoms.get(name) {
+                Some(atom) => return Ok((name.to_string(), atom, MatchConfidence::CodeName)),
+                None => true,
+            },
+            None => false,
+        };
 
-        let (code_path, code_line) = match (code_path, code_line) {
+        let (code_path, code_line) = match (entry.code_path.as_deref(), entry.code_line) {
             (Some(p), Some(l)) => (p, l),
+            _ if stale_code_name => return Err(SkipReason::CodeNameNotInAtoms),
             _ => {
-                eprintln!("WARNING: Missing code-path or code-line for {}", file_path);
-                return None;
+                output::warn(format!("missing code-path or code-line for {}", file_path));
+                return Err(SkipReason::MissingCodePath);
             }
         };
 
-        let code_name = self.lookup_code_name(code_path, code_line)?;
-        let atom = atoms.get(&code_name)?;
+        let (code_name, confidence) = self.lookup_code_name(code_path, code_line)?;
+        let atom = atoms
+            .get(&code_name)
+            .ok_or(SkipReason::CodeNameNotInAtoms)?;
 
-        Some((code_name, atom))
+        Ok((code_name, atom, confidence))
     }
 
     /// Enrich stubs with code-name and all metadata from atoms.
     fn enrich_stubs(
         &self,
-        stubs: &HashMap<String, Value>,
-        atoms: &HashMap<String, Value>,
-    ) -> Result<HashMap<String, Value>> {
+        stubs: &HashMap<String, StubEntry>,
+        atoms: &HashMap<String, Atom>,
+    ) -> Result<HashMap<String, StubEntry>> {
         let mut result = HashMap::new();
         let mut enriched_count = 0;
         let mut skipped_count = 0;
+        let mut skipped_by_reason: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut fuzzy_count = 0;
+        let mut pinned: Vec<(&str, String)> = Vec::new();
 
         for (file_path, entry) in stubs {
-            let (code_name, atom) = match self.resolve_code_name_and_atom(entry, file_path, atoms) {
-                Some(r) => r,
-                None => {
-                    skipped_count += 1;
-                    result.insert(file_path.clone(), entry.clone());
-                    continue;
-                }
-            };
-
-            let enriched_entry = build_enriched_entry(&code_name, atom);
+            let (code_name, atom, confidence) =
+                match self.resolve_code_name_and_atom(entry, file_path, atoms) {
+                    Ok(r) => r,
+                    Err(reason) => {
+                        skipped_count += 1;
+                        *skipped_by_reason.entry(reason.as_str()).or_default() += 1;
+                        let mut skipped_entry = entry.clone();
+                        skipped_entry.skip_reason = Some(reason.as_str().to_string());
+                        result.insert(file_path.clone(), skipped_entry);
+                        continue;
+                    }
+                };
+
+            match confidence {
+                MatchConfidence::Fuzzy => fuzzy_count += 1,
+                MatchConfidence::Override => pinned.push((file_path, code_name.clone())),
+                _ => {}
+            }
+            let enriched_entry = enrich_entry(entry, file_path, &code_name, atom, confidence);
             result.insert(file_path.clone(), enriched_entry);
             enriched_count += 1;
         }
 
         println!("Entries enriched: {}", enriched_count);
+        if fuzzy_count > 0 {
+            println!(
+                "  Matched within ±{} lines: {} (run with -s to update code-line)",
+                self.line_tolerance, fuzzy_count
+            );
+        }
+        if !pinned.is_empty() {
+            pinned.sort();
+            println!("  Pinned by {}: {}", ATOM_OVERRIDE_KEY, pinned.len());
+            for (file_path, code_name) in &pinned {
+                println!("    {} -> {}", file_path, code_name);
+            }
+        }
         println!("Skipped: {}", skipped_count);
+        for (reason, count) in &skipped_by_reason {
+            println!("  {}: {}", reason, count);
+        }
 
         Ok(result)
     }
 }
 
+/// The enriched entry for a stub matched to `atom`, keeping the stub's
+/// review status, owner and ignore reason.
+fn enrich_entry(
+    entry: &StubEntry,
+    file_path: &str,
+    code_name: &str,
+    atom: &Atom,
+    confidence: MatchConfidence,
+) -> StubEntry {
+    let mut enriched_entry = build_enriched_entry(code_name, atom);
+    enriched_entry.match_confidence = Some(confidence.as_str().to_string());
+    if confidence == MatchConfidence::Override {
+        enriched_entry.atom_override = Some(code_name.to_string());
+    }
+    carry_review_status(entry, &mut enriched_entry, file_path);
+    enriched_entry.owner = entry.owner.clone();
+    enriched_entry.ignore_reason = entry.ignore_reason.clone();
+    enriched_entry
+}
+
+/// Report the functions skipped stubs may have become and, with
+/// `--apply-renames`, point the confident ones at their atoms.
+fn handle_renames(
+    stubs: &HashMap<String, StubEntry>,
+    enriched: &mut HashMap<String, StubEntry>,
+    previous: &HashMap<String, StubEntry>,
+    atoms: &HashMap<String, Atom>,
+    structure_root: &Path,
+    layout: LayoutKind,
+    apply: bool,
+) -> Result<()> {
+    let renames = atomize_renames::detect(enriched, previous, atoms, layout.layout());
+    if renames.is_empty() {
+        return Ok(());
+    }
+    println!("Possible renames: {}", renames.len());
+    for rename in &renames {
+        println!(
+            "  {} -> {} ({:.0}%{})",
+            rename.stub,
+            rename.code_name,
+            rename.confidence * 100.0,
+            if rename.ambiguous { ", ambiguous" } else { "" }
+        );
+    }
+
+    let applicable: Vec<_> = renames.iter().filter(|r| r.applicable()).collect();
+    if !apply {
+        if !applicable.is_empty() {
+            println!(
+                "  Run with --apply-renames to apply the {} unambiguous renames scoring at least {:.0}%",
+                applicable.len(),
+                atomize_renames::APPLY_THRESHOLD * 100.0
+            );
+        }
+        return Ok(());
+    }
+
+    let mut applied = 0;
+    for rename in applicable {
+        let atom = &atoms[&rename.code_name];
+        let target = match atomize_renames::apply(rename, atom, structure_root, layout.layout()) {
+            Ok(target) => target,
+            Err(e) => {
+                output::warn(format!("{:#}", e));
+                continue;
+            }
+        };
+        let entry = stubs.get(&rename.stub).cloned().unwrap_or_default();
+        enriched.remove(&rename.stub);
+        enriched.insert(
+            target.clone(),
+            enrich_entry(
+                &entry,
+                &target,
+                &rename.code_name,
+                atom,
+                MatchConfidence::Renamed,
+            ),
+        );
+        println!("  Moved {} -> {}", rename.stub, target);
+        applied += 1;
+    }
+    println!("Renames applied: {}", applied);
+    Ok(())
+}
+
+/// Frontmatter key that pins a stub to an atom by code-name, bypassing matching.
+const ATOM_OVERRIDE_KEY: &str = "atom-override";
+
+/// How a stub was matched to its atom, from most to least certain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchConfidence {
+    /// The stub is pinned to the atom with `atom-override`.
+    Override,
+    /// The stub's code-name is in atoms.json.
+    CodeName,
+    /// code-line is the first line of the atom.
+    Exact,
+    /// code-line falls inside the atom's line range.
+    Contained,
+    /// No atom contains code-line; the atom starting nearest to it was used.
+    Fuzzy,
+    /// The stub was skipped and `--apply-renames` pointed it at the atom it
+    /// most likely became.
+    Renamed,
+}
+
+impl MatchConfidence {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchConfidence::Override => "override",
+            MatchConfidence::CodeName => "code-name",
+            MatchConfidence::Exact => "exact",
+            MatchConfidence::Contained => "contained",
+            MatchConfidence::Fuzzy => "fuzzy",
+            MatchConfidence::Renamed => "renamed",
+        }
+    }
+}
+
+/// Why a stub could not be matched to an atom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Frontmatter has neither a known code-name nor both code-path and code-line.
+    MissingCodePath,
+    /// The code-path does not resolve to a file under the project root.
+    CanonicalizationFailed,
+    /// The file exists but no atom was recorded for it.
+    NoAtomsInFile,
+    /// No atom's line range contains code-line.
+    NoAtomAtLine,
+    /// The stub names a code-name that atoms.json does not contain.
+    CodeNameNotInAtoms,
+    /// The stub's `atom-override` names a code-name that atoms.json does not contain.
+    OverrideNotInAtoms,
+}
+
+impl SkipReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkipReason::MissingCodePath => "missing-code-path",
+            SkipReason::CanonicalizationFailed => "canonicalization-failed",
+            SkipReason::NoAtomsInFile => "no-atoms-in-file",
+            SkipReason::NoAtomAtLine => "no-atom-at-line",
+            SkipReason::CodeNameNotInAtoms => "code-name-not-in-atoms",
+            SkipReason::OverrideNotInAtoms => "override-not-in-atoms",
+        }
+    }
+}
+
+/// Write every skipped stub with its reason and lookup keys to `report_path`.
+fn write_skip_report(enriched: &HashMap<String, StubEntry>, report_path: &Path) -> Result<()> {
+    let mut skipped: Vec<Value> = enriched
+        .iter()
+        .filter_map(|(file_path, entry)| {
+            let reason = entry.skip_reason.as_ref()?;
+            Some(json!({
+                "stub": file_path,
+                "reason": reason,
+                "code-name": entry.code_name,
+                "code-path": entry.code_path,
+                "code-line": entry.code_line,
+            }))
+        })
+        .collect();
+    skipped.sort_by(|a, b| a["stub"].as_str().cmp(&b["stub"].as_str()));
+
+    std::fs::write(report_path, serde_json::to_string_pretty(&skipped)?)
+        .with_context(|| format!("Failed to write {}", report_path.display()))?;
+    println!(
+        "Wrote {} skipped entries to {}",
+        skipped.len(),
+        report_path.display()
+    );
+    Ok(())
+}
+
 /// Canonicalize a code-path relative to the project root, resolving symlinks.
 /// Falls back to the original path if the file doesn't exist or canonicalization fails.
-fn canonicalize_code_path(project_root: &Path, code_path: &str) -> String {
-    project_root
+fn canonicalize_code_path(
+    project_root: &Path,
+    source_roots: &[SourceRoot],
+    code_path: &str,
+) -> String {
+    resolve_code_path(project_root, source_roots, code_path)
+        .unwrap_or_else(|| code_path.to_string())
+}
+
+/// Resolve a code-path to its canonical form: relative to the project root, or
+/// prefixed with a source root's label when the file lives in that root.
+/// Returns `None` if the file cannot be found under any root.
+fn resolve_code_path(
+    project_root: &Path,
+    source_roots: &[SourceRoot],
+    code_path: &str,
+) -> Option<String> {
+    let absolute = project_root
         .join(code_path)
         .canonicalize()
         .ok()
-        .and_then(|p| p.strip_prefix(project_root).ok().map(|r| r.to_path_buf()))
-        .map(|p| p.to_string_lossy().to_string())
-        .unwrap_or_else(|| code_path.to_string())
+        .or_else(|| {
+            source_roots.iter().find_map(|root| {
+                let rest = code_path.strip_prefix(&format!("{}/", root.label))?;
+                root.path.join(rest).canonicalize().ok()
+            })
+        })?;
+
+    if let Ok(relative) = absolute.strip_prefix(project_root) {
+        return Some(relative.to_string_lossy().to_string());
+    }
+    source_roots
+        .iter()
+        .find_map(|root| {
+            let relative = absolute.strip_prefix(&root.path).ok()?;
+            Some(root.prefix(&relative.to_string_lossy()))
+        })
+        .or_else(|| Some(code_path.to_string()))
 }
 
 /// Build an enriched entry from atom data.
-fn build_enriched_entry(code_name: &str, atom: &Value) -> Value {
-    let code_path = atom.get("code-path").and_then(|v| v.as_str()).unwrap_or("");
-
-    let code_text = atom.get("code-text");
-
-    let lines_start = code_text
-        .and_then(|ct| ct.get("lines-start"))
-        .and_then(|v| v.as_u64())
-        .unwrap_or(0);
-
-    let lines_end = code_text
-        .and_then(|ct| ct.get("lines-end"))
-        .and_then(|v| v.as_u64())
-        .unwrap_or(0);
-
-    let code_module = atom
-        .get("code-module")
-        .and_then(|v| v.as_str())
-        .unwrap_or("");
-
-    let dependencies = atom
-        .get("dependencies")
-        .cloned()
-        .unwrap_or_else(|| json!([]));
-
-    let display_name = atom
-        .get("display-name")
-        .and_then(|v| v.as_str())
-        .unwrap_or("");
-
-    json!({
-        "code-path": code_path,
-        "code-text": {
-            "lines-start": lines_start,
-            "lines-end": lines_end,
-        },
-        "code-name": code_name,
-        "code-module": code_module,
-        "dependencies": dependencies,
-        "display-name": display_name,
-    })
+fn build_enriched_entry(code_name: &str, atom: &Atom) -> StubEntry {
+    StubEntry {
+        code_path: Some(atom.code_path.clone().unwrap_or_default()),
+        code_text: Some(atom.code_text.clone().unwrap_or_default()),
+        code_name: Some(code_name.to_string()),
+        code_module: Some(atom.code_module.clone().unwrap_or_default()),
+        dependencies: Some(atom.dependencies.clone()),
+        display_name: Some(atom.display_name.clone().unwrap_or_default()),
+        ..Default::default()
+    }
+}
+
+/// Copy a valid `review-status` from the .md frontmatter into the enriched entry.
+/// Unknown values are reported and dropped rather than propagated.
+fn carry_review_status(stub: &StubEntry, enriched: &mut StubEntry, file_path: &str) {
+    let raw = match stub.review_status.as_deref() {
+        Some(r) => r,
+        None => return,
+    };
+
+    match raw.parse::<ReviewStatus>() {
+        Ok(status) => {
+            enriched.review_status = Some(status.as_str().to_string());
+        }
+        Err(e) => {
+            output::warn(format!("{}: {}", file_path, e));
+        }
+    }
 }
 
 /// Check if .md stub files match the enriched stubs.
 /// Compares code-name, code-path, and code-line fields.
 fn check_stubs_match(
-    stubs: &HashMap<String, Value>,
-    enriched: &HashMap<String, Value>,
+    stubs: &HashMap<String, StubEntry>,
+    enriched: &HashMap<String, StubEntry>,
 ) -> Result<()> {
     use std::collections::HashSet;
 
@@ -586,8 +1198,8 @@ fn check_stubs_match(
         };
 
         // Compare code-name
-        let stub_code_name = stub_entry.get("code-name").and_then(|v| v.as_str());
-        let enriched_code_name = enriched_entry.get("code-name").and_then(|v| v.as_str());
+        let stub_code_name = stub_entry.code_name.as_deref();
+        let enriched_code_name = enriched_entry.code_name.as_deref();
         if stub_code_name != enriched_code_name {
             mismatches.push(format!(
                 "{}: code-name mismatch: .md has {:?}, enriched has {:?}",
@@ -597,8 +1209,8 @@ fn check_stubs_match(
         }
 
         // Compare code-path
-        let stub_code_path = stub_entry.get("code-path").and_then(|v| v.as_str());
-        let enriched_code_path = enriched_entry.get("code-path").and_then(|v| v.as_str());
+        let stub_code_path = stub_entry.code_path.as_deref();
+        let enriched_code_path = enriched_entry.code_path.as_deref();
         if stub_code_path != enriched_code_path {
             mismatches.push(format!(
                 "{}: code-path mismatch: .md has {:?}, enriched has {:?}",
@@ -608,11 +1220,8 @@ fn check_stubs_match(
         }
 
         // Compare code-line (from stub) vs lines-start (from enriched code-text)
-        let stub_code_line = stub_entry.get("code-line").and_then(|v| v.as_u64());
-        let enriched_code_line = enriched_entry
-            .get("code-text")
-            .and_then(|ct| ct.get("lines-start"))
-            .and_then(|v| v.as_u64());
+        let stub_code_line = stub_entry.code_line;
+        let enriched_code_line = enriched_entry.code_text.as_ref().map(|ct| ct.lines_start);
         if stub_code_line != enriched_code_line {
             mismatches.push(format!(
                 "{}: code-line mismatch: .md has {:?}, enriched has {:?}",
@@ -647,70 +1256,120 @@ fn check_stubs_match(
     }
 }
 
+/// What to do with one frontmatter field atomize owns.
+#[derive(Debug, PartialEq, Eq)]
+enum FieldMerge {
+    /// The file already has the new value, or the user changed the field
+    /// and atomize's value did not.
+    Keep,
+    Write,
+    /// Both the user and atomize changed the field.
+    Conflict,
+}
+
+/// Three-way merge of a field: `base` is the value read when the run
+/// started, `current` what the file holds now, `new` what atomize found.
+fn merge_field(base: Option<&Value>, current: Option<&Value>, new: &Value) -> FieldMerge {
+    if current == Some(new) || (base == Some(new) && current != base) {
+        FieldMerge::Keep
+    } else if current == base {
+        FieldMerge::Write
+    } else {
+        FieldMerge::Conflict
+    }
+}
+
+/// The code-name, code-path and code-line frontmatter of a stub.
+fn owned_fields(entry: &StubEntry, code_line: Option<u32>) -> [(&'static str, Option<Value>); 3] {
+    [
+        ("code-name", entry.code_name.as_ref().map(|v| json!(v))),
+        ("code-path", entry.code_path.as_ref().map(|v| json!(v))),
+        ("code-line", code_line.map(|v| json!(v))),
+    ]
+}
+
 /// Update structure .md files with code-name field from enriched data.
-fn update_structure_files(enriched: &HashMap<String, Value>, structure_root: &Path) -> Result<()> {
+///
+/// `stubs` is the frontmatter as read at the start of the run. A field the
+/// user edited since is kept when atomize's value did not change, and
+/// reported as a conflict, not overwritten, when it did.
+fn update_structure_files(
+    stubs: &HashMap<String, StubEntry>,
+    enriched: &HashMap<String, StubEntry>,
+    structure_root: &Path,
+) -> Result<()> {
     let mut updated_count = 0;
     let mut skipped_count = 0;
+    let mut kept_count = 0;
+    let mut conflicts = Vec::new();
 
     for (file_path, entry) in enriched {
         let path = structure_root.join(file_path);
-        if !path.exists() {
+        if !path.exists() || entry.code_name.is_none() {
             skipped_count += 1;
             continue;
         }
 
-        let code_name = match entry.get("code-name").and_then(|v| v.as_str()) {
-            Some(name) => name,
-            None => {
-                skipped_count += 1;
-                continue;
-            }
-        };
+        let new = owned_fields(entry, entry.code_text.as_ref().map(|t| t.lines_start));
+        let base = stubs
+            .get(file_path)
+            .map(|stub| owned_fields(stub, stub.code_line));
 
-        let fm = match parse_frontmatter(&path) {
-            Ok(fm) => fm,
-            Err(_) => {
-                skipped_count += 1;
-                continue;
+        // Keeps the body and the frontmatter format.
+        let updated = update_frontmatter(&path, |metadata| {
+            for (i, (key, new_value)) in new.iter().enumerate() {
+                let Some(new_value) = new_value else {
+                    continue;
+                };
+                // A null value reads back the same as an absent key.
+                let current = metadata.get(*key).filter(|value| !value.is_null());
+                // No base when the stub was renamed this run; its
+                // frontmatter was just written, so it counts as unchanged.
+                let base_value = match &base {
+                    Some(base) => base[i].1.as_ref(),
+                    None => current,
+                };
+                match merge_field(base_value, current, new_value) {
+                    FieldMerge::Keep => {
+                        if current != Some(new_value) {
+                            kept_count += 1;
+                        }
+                    }
+                    FieldMerge::Write => {
+                        metadata.insert(key.to_string(), new_value.clone());
+                    }
+                    FieldMerge::Conflict => conflicts.push(format!(
+                        "{}: {} is {} in the file, atomize found {}",
+                        file_path,
+                        key,
+                        current.map_or("unset".to_string(), Value::to_string),
+                        new_value
+                    )),
+                }
             }
-        };
-
-        // Read original file content to preserve body
-        let original_content = std::fs::read_to_string(&path)?;
-        let body_start = original_content
-            .find("\n---\n")
-            .map(|pos| pos + 5)
-            .and_then(|start| {
-                original_content[start..]
-                    .find("\n---\n")
-                    .map(|p| start + p + 5)
-            });
-
-        let body = body_start.map(|start| original_content[start..].to_string());
-
-        // Build updated frontmatter
-        let mut metadata: HashMap<String, Value> =
-            fm.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
-        metadata.insert("code-name".to_string(), json!(code_name));
-
-        // Update code-path and code-line to be consistent with enriched data
-        if let Some(code_path) = entry.get("code-path").and_then(|v| v.as_str()) {
-            metadata.insert("code-path".to_string(), json!(code_path));
-        }
-        if let Some(code_line) = entry
-            .get("code-text")
-            .and_then(|ct| ct.get("lines-start"))
-            .and_then(|v| v.as_u64())
-        {
-            metadata.insert("code-line".to_string(), json!(code_line));
+        });
+        if updated.is_err() {
+            skipped_count += 1;
+            continue;
         }
-
-        write_frontmatter(&path, &metadata, body.as_deref())?;
         updated_count += 1;
     }
 
     println!("Structure files updated: {}", updated_count);
     println!("Skipped: {}", skipped_count);
+    if kept_count > 0 {
+        println!("Manual edits kept: {}", kept_count);
+    }
+    if !conflicts.is_empty() {
+        conflicts.sort();
+        output::warn(format!(
+            "{} fields were edited in the file and changed by atomize; left as they are:",
+            conflicts.len()
+        ));
+        for conflict in &conflicts {
+            eprintln!("  {}", conflict);
+        }
+    }
 
     Ok(())
 }
@@ -720,6 +1379,10 @@ mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    fn from_json<T: serde::de::DeserializeOwned>(value: Value) -> T {
+        serde_json::from_value(value).unwrap()
+    }
+
     #[cfg(unix)]
     #[test]
     fn test_canonicalize_code_path_resolves_symlinks() {
@@ -733,8 +1396,9 @@ mod tests {
         std::os::unix::fs::symlink(real_dir.to_str().unwrap(), project_root.join("my-crate"))
             .unwrap();
 
-        let via_symlink = canonicalize_code_path(&project_root, "my-crate/src/lib.rs");
-        let via_real = canonicalize_code_path(&project_root, "deps/my-crate/my-crate/src/lib.rs");
+        let via_symlink = canonicalize_code_path(&project_root, &[], "my-crate/src/lib.rs");
+        let via_real =
+            canonicalize_code_path(&project_root, &[], "deps/my-crate/my-crate/src/lib.rs");
 
         assert_eq!(via_symlink, via_real);
         assert_eq!(via_real, "deps/my-crate/my-crate/src/lib.rs");
@@ -745,7 +1409,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         let project_root = dir.path().canonicalize().unwrap();
 
-        let result = canonicalize_code_path(&project_root, "nonexistent/src/lib.rs");
+        let result = canonicalize_code_path(&project_root, &[], "nonexistent/src/lib.rs");
         assert_eq!(result, "nonexistent/src/lib.rs");
     }
 
@@ -762,33 +1426,33 @@ mod tests {
         std::os::unix::fs::symlink(real_dir.to_str().unwrap(), project_root.join("my-crate"))
             .unwrap();
 
-        let mut atoms = HashMap::new();
+        let mut atoms: HashMap<String, Atom> = HashMap::new();
         atoms.insert(
             "probe:my-crate/0.1.0/func_a()".to_string(),
-            json!({
+            from_json(json!({
                 "code-path": "my-crate/src/lib.rs",
                 "code-text": { "lines-start": 10, "lines-end": 20 },
                 "code-module": "my_crate",
                 "dependencies": [],
                 "display-name": "func_a",
-            }),
+            })),
         );
 
-        let mut stubs = HashMap::new();
+        let mut stubs: HashMap<String, StubEntry> = HashMap::new();
         stubs.insert(
             "deps/my-crate/my-crate/src/lib.rs/func_a.md".to_string(),
-            json!({
+            from_json(json!({
                 "code-path": "deps/my-crate/my-crate/src/lib.rs",
                 "code-line": 10,
-            }),
+            })),
         );
 
-        let index = ProbeIndex::build(&atoms, project_root);
+        let index = ProbeIndex::build(&atoms, project_root, Vec::new());
         let enriched = index.enrich_stubs(&stubs, &atoms).unwrap();
 
         let entry = &enriched["deps/my-crate/my-crate/src/lib.rs/func_a.md"];
         assert_eq!(
-            entry.get("code-name").and_then(|v| v.as_str()).unwrap(),
+            entry.code_name.as_deref().unwrap(),
             "probe:my-crate/0.1.0/func_a()"
         );
     }
@@ -801,37 +1465,190 @@ mod tests {
         std::fs::create_dir_all(project_root.join("src")).unwrap();
         std::fs::write(project_root.join("src").join("lib.rs"), "").unwrap();
 
-        let mut atoms = HashMap::new();
+        let mut atoms: HashMap<String, Atom> = HashMap::new();
         atoms.insert(
             "probe:test/0.1.0/func_a()".to_string(),
-            json!({
+            from_json(json!({
                 "code-path": "src/lib.rs",
                 "code-text": { "lines-start": 5, "lines-end": 15 },
                 "code-module": "test",
                 "dependencies": [],
                 "display-name": "func_a",
-            }),
+            })),
         );
 
-        let mut stubs = HashMap::new();
+        let mut stubs: HashMap<String, StubEntry> = HashMap::new();
         stubs.insert(
             "src/lib.rs/func_a.md".to_string(),
-            json!({
+            from_json(json!({
                 "code-path": "src/lib.rs",
                 "code-line": 5,
-            }),
+            })),
         );
 
-        let index = ProbeIndex::build(&atoms, project_root);
+        let index = ProbeIndex::build(&atoms, project_root, Vec::new());
         let enriched = index.enrich_stubs(&stubs, &atoms).unwrap();
 
         let entry = &enriched["src/lib.rs/func_a.md"];
         assert_eq!(
-            entry.get("code-name").and_then(|v| v.as_str()).unwrap(),
+            entry.code_name.as_deref().unwrap(),
             "probe:test/0.1.0/func_a()"
         );
     }
 
+    #[test]
+    fn test_lookup_code_name_skip_reasons() {
+        let dir = TempDir::new().unwrap();
+        let project_root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(project_root.join("src")).unwrap();
+        std::fs::write(project_root.join("src").join("lib.rs"), "").unwrap();
+        std::fs::write(project_root.join("src").join("empty.rs"), "").unwrap();
+
+        let mut atoms: HashMap<String, Atom> = HashMap::new();
+        atoms.insert(
+            "probe:test/0.1.0/func_a()".to_string(),
+            from_json(json!({
+                "code-path": "src/lib.rs",
+                "code-text": { "lines-start": 5, "lines-end": 15 },
+            })),
+        );
+
+        let index = ProbeIndex::build(&atoms, project_root, Vec::new());
+        assert_eq!(
+            index.lookup_code_name("src/lib.rs", 10),
+            Ok((
+                "probe:test/0.1.0/func_a()".to_string(),
+                MatchConfidence::Contained
+            ))
+        );
+        assert_eq!(
+            index.lookup_code_name("src/lib.rs", 20),
+            Err(SkipReason::NoAtomAtLine)
+        );
+        assert_eq!(
+            index.lookup_code_name("src/empty.rs", 1),
+            Err(SkipReason::NoAtomsInFile)
+        );
+        assert_eq!(
+            index.lookup_code_name("src/missing.rs", 1),
+            Err(SkipReason::CanonicalizationFailed)
+        );
+
+        let stale: StubEntry = from_json(json!({ "code-name": "probe:test/0.1.0/gone()" }));
+        assert_eq!(
+            index
+                .resolve_code_name_and_atom(&stale, "gone.md", &atoms)
+                .unwrap_err(),
+            SkipReason::CodeNameNotInAtoms
+        );
+    }
+
+    #[test]
+    fn test_update_structure_files_merges_manual_edits() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        // Since the run started, the user moved code-line and set code-path;
+        // atomize found a new code-path and the same code-line.
+        std::fs::write(
+            root.join("a.md"),
+            "---\ncode-name: null\ncode-path: src/mine.rs\ncode-line: 12\nnote: kept\n---\nBody\n",
+        )
+        .unwrap();
+        let stubs: HashMap<String, StubEntry> = HashMap::from([(
+            "a.md".to_string(),
+            from_json(json!({ "code-path": "src/lib.rs", "code-line": 10 })),
+        )]);
+        let enriched: HashMap<String, StubEntry> = HashMap::from([(
+            "a.md".to_string(),
+            from_json(json!({
+                "code-name": "probe:a()",
+                "code-path": "src/new.rs",
+                "code-text": { "lines-start": 10, "lines-end": 20 },
+            })),
+        )]);
+
+        update_structure_files(&stubs, &enriched, root).unwrap();
+
+        let content = std::fs::read_to_string(root.join("a.md")).unwrap();
+        assert!(content.contains("code-name: \"probe:a()\""), "{}", content);
+        assert!(content.contains("code-path: src/mine.rs"), "conflict kept");
+        assert!(content.contains("code-line: 12"), "manual edit kept");
+        assert!(content.contains("note: kept"));
+        assert!(content.ends_with("Body\n"));
+
+        assert_eq!(
+            merge_field(Some(&json!(1)), Some(&json!(1)), &json!(2)),
+            FieldMerge::Write
+        );
+        assert_eq!(
+            merge_field(Some(&json!(1)), Some(&json!(3)), &json!(1)),
+            FieldMerge::Keep
+        );
+        assert_eq!(
+            merge_field(Some(&json!(1)), Some(&json!(3)), &json!(2)),
+            FieldMerge::Conflict
+        );
+    }
+
+    #[test]
+    fn test_lookup_code_name_prefers_nearest_atom_start() {
+        let dir = TempDir::new().unwrap();
+        let project_root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(project_root.join("src")).unwrap();
+        std::fs::write(project_root.join("src").join("lib.rs"), "").unwrap();
+
+        let mut atoms: HashMap<String, Atom> = HashMap::new();
+        for (name, start, end) in [("a", 1, 10), ("b", 20, 30)] {
+            atoms.insert(
+                format!("probe:test/0.1.0/{}()", name),
+                from_json(json!({
+                    "code-path": "src/lib.rs",
+                    "code-text": { "lines-start": start, "lines-end": end },
+                })),
+            );
+        }
+
+        let index = ProbeIndex::build(&atoms, project_root, Vec::new()).with_line_tolerance(5);
+        assert_eq!(
+            index.lookup_code_name("src/lib.rs", 17),
+            Ok(("probe:test/0.1.0/b()".to_string(), MatchConfidence::Fuzzy))
+        );
+        assert_eq!(
+            index.lookup_code_name("src/lib.rs", 12),
+            Err(SkipReason::NoAtomAtLine)
+        );
+        assert_eq!(
+            index.lookup_code_name("src/lib.rs", 35),
+            Err(SkipReason::NoAtomAtLine)
+        );
+    }
+
+    #[test]
+    fn test_resolve_code_path_in_source_root() {
+        let dir = TempDir::new().unwrap();
+        let base = dir.path().canonicalize().unwrap();
+        let project_root = base.join("project");
+        std::fs::create_dir_all(project_root.join("src")).unwrap();
+        std::fs::create_dir_all(base.join("vendored-lib").join("src")).unwrap();
+        std::fs::write(base.join("vendored-lib").join("src").join("lib.rs"), "").unwrap();
+
+        let roots = resolve_source_roots(&project_root, &["../vendored-lib".to_string()]);
+        assert_eq!(roots[0].label, "vendored-lib");
+
+        for code_path in ["vendored-lib/src/lib.rs", "../vendored-lib/src/lib.rs"] {
+            assert_eq!(
+                resolve_code_path(&project_root, &roots, code_path).as_deref(),
+                Some("vendored-lib/src/lib.rs"),
+                "{}",
+                code_path
+            );
+        }
+        assert_eq!(
+            resolve_code_path(&project_root, &[], "vendored-lib/src/lib.rs"),
+            None
+        );
+    }
+
     #[test]
     fn test_is_verus_project_with_vstd_dep() {
         let dir = TempDir::new().unwrap();
@@ -849,6 +1666,32 @@ vstd = { git = "https://github.com/verus-lang/verus" }
         assert!(is_verus_project(dir.path()));
     }
 
+    #[test]
+    fn test_auto_analyzer_follows_verus_usage() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "fn f() {}\n").unwrap();
+        let docker = CommandConfig {
+            execution_mode: ExecutionMode::Docker,
+            ..CommandConfig::default()
+        };
+        let select = |requested, is_verus| {
+            select_analyzer(dir.path(), requested, is_verus, &docker).analyzer
+        };
+
+        assert_eq!(select(Analyzer::Auto, false), Analyzer::Rust);
+        assert_eq!(select(Analyzer::Auto, true), Analyzer::Rust);
+        assert_eq!(select(Analyzer::Verus, false), Analyzer::Verus);
+
+        std::fs::write(
+            dir.path().join("src/lib.rs"),
+            "verus! {\n    fn f() {}\n}\n",
+        )
+        .unwrap();
+        assert_eq!(select(Analyzer::Auto, true), Analyzer::Verus);
+        assert_eq!(select(Analyzer::Rust, true), Analyzer::Rust);
+    }
+
     #[test]
     fn test_is_verus_project_with_builtin_dep() {
         let dir = TempDir::new().unwrap();
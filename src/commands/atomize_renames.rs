@@ -0,0 +1,322 @@
+//! Rename detection, for `atomize --apply-renames`.
+//!
+//! A function that moves to another file, or is renamed, leaves its stub
+//! pointing at a code-path and code-line where no atom is, so enrichment
+//! skips it. Each skipped stub is compared with the atoms no stub matched: by
+//! display-name, and by the dependencies and length stubs.json recorded for it
+//! at the last atomize. Candidates scoring at least [`REPORT_THRESHOLD`] are
+//! reported as possible renames; `--apply-renames` rewrites the stubs whose
+//! best candidate scores at least [`APPLY_THRESHOLD`] and clearly beats the
+//! runner-up.
+
+use crate::structure::layout::StructureLayout;
+use crate::structure::{update_frontmatter, Atom, StubEntry};
+use anyhow::{bail, Context, Result};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Lowest confidence reported as a possible rename.
+pub const REPORT_THRESHOLD: f64 = 0.5;
+
+/// Lowest confidence `--apply-renames` acts on.
+pub const APPLY_THRESHOLD: f64 = 0.8;
+
+/// Candidates this close to the best one make a rename ambiguous.
+const AMBIGUITY_MARGIN: f64 = 0.05;
+
+/// Weights of the display-name, dependencies and length scores.
+const NAME_WEIGHT: f64 = 0.6;
+const DEPENDENCIES_WEIGHT: f64 = 0.25;
+const LENGTH_WEIGHT: f64 = 0.15;
+
+/// A skipped stub and the unmatched atom it most likely became.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rename {
+    /// The stub's structure file, relative to the structure root.
+    pub stub: String,
+    pub code_name: String,
+    /// From 0 to 1.
+    pub confidence: f64,
+    /// Another candidate scored within [`AMBIGUITY_MARGIN`] of this one.
+    pub ambiguous: bool,
+}
+
+impl Rename {
+    /// Whether `--apply-renames` rewrites the stub.
+    pub fn applicable(&self) -> bool {
+        !self.ambiguous && self.confidence >= APPLY_THRESHOLD
+    }
+}
+
+/// Possible renames for the stubs enrichment skipped, sorted by stub.
+/// `previous` is stubs.json as the last atomize left it.
+pub fn detect(
+    enriched: &HashMap<String, StubEntry>,
+    previous: &HashMap<String, StubEntry>,
+    atoms: &HashMap<String, Atom>,
+    layout: &dyn StructureLayout,
+) -> Vec<Rename> {
+    let claimed: HashSet<&str> = enriched
+        .values()
+        .filter(|entry| entry.skip_reason.is_none())
+        .filter_map(|entry| entry.code_name.as_deref())
+        .collect();
+    let candidates: Vec<(&String, &Atom, &str)> = atoms
+        .iter()
+        .filter(|(code_name, _)| !claimed.contains(code_name.as_str()))
+        .filter_map(|(code_name, atom)| Some((code_name, atom, atom.display_name.as_deref()?)))
+        .collect();
+
+    let mut renames = Vec::new();
+    for (file_path, entry) in enriched {
+        // A pinned stub names its atom on purpose.
+        if entry.skip_reason.is_none() || entry.atom_override.is_some() {
+            continue;
+        }
+        let old = previous.get(file_path);
+        let Some(name) = old
+            .and_then(|old| old.display_name.clone())
+            .or_else(|| entry.display_name.clone())
+            .or_else(|| Some(last_segment(&layout.function_name(file_path)?).to_string()))
+        else {
+            continue;
+        };
+
+        let mut scored: Vec<(f64, &String)> = candidates
+            .iter()
+            .map(|(code_name, atom, atom_name)| (score(&name, old, atom, atom_name), *code_name))
+            .filter(|(confidence, _)| *confidence >= REPORT_THRESHOLD)
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        let Some(&(confidence, code_name)) = scored.first() else {
+            continue;
+        };
+        renames.push(Rename {
+            stub: file_path.clone(),
+            code_name: code_name.clone(),
+            confidence,
+            ambiguous: scored
+                .get(1)
+                .is_some_and(|(second, _)| confidence - second < AMBIGUITY_MARGIN),
+        });
+    }
+    renames.sort_by(|a, b| a.stub.cmp(&b.stub));
+    renames
+}
+
+/// How likely the function a stub last matched (`old`, called `name`) is now
+/// `atom`. Dependencies and length only count when stubs.json recorded them.
+fn score(name: &str, old: Option<&StubEntry>, atom: &Atom, atom_name: &str) -> f64 {
+    let mut total = NAME_WEIGHT * similarity(name, atom_name);
+    let mut weight = NAME_WEIGHT;
+    if let Some(dependencies) = old.and_then(|old| old.dependencies.as_ref()) {
+        let before: HashSet<&String> = dependencies.iter().collect();
+        let after: HashSet<&String> = atom.dependencies.iter().collect();
+        let union = before.union(&after).count();
+        let jaccard = if union == 0 {
+            1.0
+        } else {
+            before.intersection(&after).count() as f64 / union as f64
+        };
+        total += DEPENDENCIES_WEIGHT * jaccard;
+        weight += DEPENDENCIES_WEIGHT;
+    }
+    if let (Some(before), Some(after)) = (
+        old.and_then(|old| old.code_text.as_ref()),
+        atom.code_text.as_ref(),
+    ) {
+        let length = |start: u32, end: u32| f64::from(end.saturating_sub(start) + 1);
+        let (a, b) = (
+            length(before.lines_start, before.lines_end),
+            length(after.lines_start, after.lines_end),
+        );
+        total += LENGTH_WEIGHT * a.min(b) / a.max(b);
+        weight += LENGTH_WEIGHT;
+    }
+    total / weight
+}
+
+/// 1 minus the edit distance of `a` and `b` over the longer one's length.
+fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    1.0 - row[b.len()] as f64 / longest as f64
+}
+
+/// The function name without its type, e.g. `new` for `Parser.new()`.
+fn last_segment(name: &str) -> &str {
+    let name = name.trim_end_matches("()");
+    name.rsplit('.').next().unwrap_or(name)
+}
+
+/// Point the stub's frontmatter at the atom `rename` names and move its file
+/// to where the layout puts the atom. Returns the new structure file path.
+pub fn apply(
+    rename: &Rename,
+    atom: &Atom,
+    structure_root: &Path,
+    layout: &dyn StructureLayout,
+) -> Result<String> {
+    let code_path = atom
+        .code_path
+        .as_deref()
+        .with_context(|| format!("{} has no code-path", rename.code_name))?;
+    // Keep the file's `Type.` prefix and `()` suffix, renaming what's between.
+    let name = layout.function_name(&rename.stub).unwrap_or_default();
+    let (base, parens) = match name.strip_suffix("()") {
+        Some(base) => (base, "()"),
+        None => (name.as_str(), ""),
+    };
+    let base = match (atom.display_name.as_deref(), base.rsplit_once('.')) {
+        (Some(display_name), Some((owner, _))) => format!("{}.{}", owner, display_name),
+        (Some(display_name), None) => display_name.to_string(),
+        (None, _) => base.to_string(),
+    };
+    let name = format!("{}{}", base, parens);
+    let target = layout.file_path(code_path, &name);
+    let source = structure_root.join(&rename.stub);
+    if target != rename.stub && structure_root.join(&target).exists() {
+        bail!(
+            "Cannot move {} to {}: another structure file already uses that path",
+            rename.stub,
+            target
+        );
+    }
+
+    update_frontmatter(&source, |metadata| {
+        metadata.insert("code-name".to_string(), json!(rename.code_name));
+        metadata.insert("code-path".to_string(), json!(code_path));
+        if let Some(code_text) = &atom.code_text {
+            metadata.insert("code-line".to_string(), json!(code_text.lines_start));
+        }
+    })
+    .with_context(|| format!("Failed to update {}", source.display()))?;
+
+    if target != rename.stub {
+        let destination = structure_root.join(&target);
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::rename(&source, &destination)
+            .with_context(|| format!("Failed to move {}", source.display()))?;
+        // Drop the directory the file leaves empty; remove_dir fails otherwise.
+        if let Some(parent) = source.parent().filter(|p| *p != structure_root) {
+            let _ = std::fs::remove_dir(parent);
+        }
+    }
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::schema::LineRange;
+    use crate::structure::LayoutKind;
+
+    fn atom(name: &str, path: &str, lines: (u32, u32), dependencies: &[&str]) -> Atom {
+        Atom {
+            display_name: Some(name.to_string()),
+            code_path: Some(path.to_string()),
+            code_text: Some(LineRange::new(lines.0, lines.1)),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_detect_scores_moved_and_renamed_functions() {
+        let atoms = HashMap::from([
+            (
+                "a".to_string(),
+                atom("parse", "src/new.rs", (1, 10), &["lex"]),
+            ),
+            (
+                "b".to_string(),
+                atom("parse_all", "src/new.rs", (20, 40), &[]),
+            ),
+            ("c".to_string(), atom("render", "src/old.rs", (1, 5), &[])),
+        ]);
+        let skipped = |path: &str| StubEntry {
+            code_path: Some(path.to_string()),
+            code_line: Some(1),
+            skip_reason: Some("no-atom-at-line".to_string()),
+            ..Default::default()
+        };
+        let enriched = HashMap::from([
+            ("src/old.rs/parse.md".to_string(), skipped("src/old.rs")),
+            ("src/old.rs/unrelated.md".to_string(), skipped("src/old.rs")),
+            (
+                "src/old.rs/render.md".to_string(),
+                StubEntry {
+                    code_name: Some("c".to_string()),
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let previous = HashMap::from([(
+            "src/old.rs/parse.md".to_string(),
+            StubEntry {
+                display_name: Some("parse".to_string()),
+                dependencies: Some(vec!["lex".to_string()]),
+                code_text: Some(LineRange::new(3, 12)),
+                ..Default::default()
+            },
+        )]);
+
+        let renames = detect(&enriched, &previous, &atoms, LayoutKind::Path.layout());
+        assert_eq!(renames.len(), 1);
+        assert_eq!(renames[0].stub, "src/old.rs/parse.md");
+        assert_eq!(renames[0].code_name, "a");
+        assert!((renames[0].confidence - 1.0).abs() < 1e-9);
+        assert!(renames[0].applicable());
+
+        assert_eq!(similarity("parse", "parse"), 1.0);
+        assert!((similarity("parse", "parse_all") - 5.0 / 9.0).abs() < 1e-9);
+        assert_eq!(similarity("", ""), 1.0);
+    }
+
+    #[test]
+    fn test_equally_likely_candidates_are_ambiguous() {
+        let atoms = HashMap::from([
+            ("a".to_string(), atom("new", "src/a.rs", (1, 5), &[])),
+            ("b".to_string(), atom("new", "src/b.rs", (1, 5), &[])),
+        ]);
+        let enriched = HashMap::from([(
+            "src/c.rs/new.md".to_string(),
+            StubEntry {
+                skip_reason: Some("no-atoms-in-file".to_string()),
+                ..Default::default()
+            },
+        )]);
+
+        let renames = detect(
+            &enriched,
+            &HashMap::new(),
+            &atoms,
+            LayoutKind::Path.layout(),
+        );
+        assert_eq!(renames.len(), 1);
+        assert_eq!(renames[0].code_name, "a");
+        assert!(renames[0].ambiguous);
+        assert!(!renames[0].applicable());
+    }
+}
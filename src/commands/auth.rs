@@ -1,44 +1,46 @@
 use anyhow::{Context, Result};
 use rpassword::prompt_password;
 
+use crate::i18n::t;
 use crate::storage::{get_credential_storage, print_platform_help};
 
 pub async fn handle_auth() -> Result<()> {
-    println!("Please enter your Verilib API key:");
+    println!("{}", t!("auth-prompt"));
 
-    let key = prompt_password("API Key: ").context("Failed to read API key from input")?;
+    let key = prompt_password(format!("{} ", t!("auth-key-label")))
+        .with_context(|| t!("auth-read-failed"))?;
 
     if key.trim().is_empty() {
-        anyhow::bail!("API key cannot be empty");
+        anyhow::bail!(t!("auth-empty"));
     }
 
-    println!("Attempting to store API key...");
+    println!("{}", t!("auth-storing"));
 
     let entry = get_credential_storage()?;
 
     match entry.set_password(key.trim()) {
         Ok(()) => {
-            println!("API key successfully stored.");
-            println!("Your API key is securely stored in the system keyring.");
+            println!("{}", t!("auth-stored"));
+            println!("{}", t!("auth-stored-keyring"));
 
             match entry.get_password() {
                 Ok(stored_key) => {
                     if stored_key == key.trim() {
-                        println!("Storage verified successfully.");
+                        println!("{}", t!("auth-verified"));
                     } else {
-                        println!("Warning: Storage verification failed - keys don't match");
+                        println!("{}", t!("auth-verify-mismatch"));
                     }
                 }
                 Err(e) => {
-                    println!("Warning: Could not verify storage: {}", e);
+                    println!("{}", t!("auth-verify-failed", error = e.to_string()));
                 }
             }
         }
         Err(err) => {
-            eprintln!("Failed to store API key: {}", err);
-            eprintln!("Platform-specific help:");
+            eprintln!("{}", t!("auth-store-failed", error = err.to_string()));
+            eprintln!("{}", t!("auth-platform-help"));
             print_platform_help();
-            anyhow::bail!("Failed to store API key");
+            anyhow::bail!(t!("auth-store-failed-short"));
         }
     }
 
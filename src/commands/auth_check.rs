@@ -0,0 +1,196 @@
+//! Early check that the stored API key is still accepted.
+//!
+//! A key revoked on the server used to surface as a raw 401 late in a
+//! command, after local work was done. Commands that talk to the server ask
+//! it first with one cheap request, the repository's atomization status.
+//! Commands whose first request is that status anyway skip the check and
+//! take the key from [`stored_api_key`]; a 401 there gives the same advice. A
+//! key the server accepted is not asked about again for [`CACHE_TTL`]; the
+//! cache in `~/.verilib_auth_check.json` holds a fingerprint of the key and
+//! server, never the key. When the server is unreachable or answers with
+//! anything but 401, the command goes ahead and reports its own errors.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use dialoguer::Confirm;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+use crate::commands::auth::handle_auth;
+use crate::commands::status::get_stored_api_key;
+use crate::constants::{auth_required_msg, CLI_NAME};
+use crate::download::ApiClient;
+use crate::exit::{tag, ExitContext, ExitKind};
+use crate::i18n::t;
+
+const CACHE_FILE: &str = ".verilib_auth_check.json";
+
+/// How long a key the server accepted is trusted without asking again.
+const CACHE_TTL: Duration = Duration::minutes(5);
+
+/// The check request gives up after this long; the command then goes ahead.
+const CHECK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AuthCache {
+    /// When each key fingerprint was last accepted.
+    #[serde(default)]
+    accepted: BTreeMap<String, DateTime<Utc>>,
+}
+
+impl AuthCache {
+    fn path() -> Result<PathBuf> {
+        let home_dir = dirs::home_dir().context("Failed to get home directory")?;
+        Ok(home_dir.join(CACHE_FILE))
+    }
+
+    fn load() -> Self {
+        Self::path()
+            .ok()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Best effort: a cache that cannot be written only costs a request.
+    /// Owner-only on unix, like the credentials it vouches for.
+    fn save(&self) {
+        let (Ok(path), Ok(content)) = (Self::path(), serde_json::to_string_pretty(self)) else {
+            return;
+        };
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        if let Ok(mut file) = options.open(path) {
+            #[cfg(unix)]
+            let _ = file.set_permissions(std::fs::Permissions::from_mode(0o600));
+            let _ = file.write_all(content.as_bytes());
+        }
+    }
+
+    fn is_fresh(&self, fingerprint: &str, now: DateTime<Utc>) -> bool {
+        self.accepted
+            .get(fingerprint)
+            .is_some_and(|&accepted| accepted <= now && now - accepted < CACHE_TTL)
+    }
+
+    fn record(&mut self, fingerprint: String, now: DateTime<Utc>) {
+        self.accepted
+            .retain(|_, &mut accepted| now - accepted < CACHE_TTL);
+        self.accepted.insert(fingerprint, now);
+    }
+}
+
+fn fingerprint(base_url: &str, api_key: &str) -> String {
+    let digest = Sha256::digest(format!("{}\n{}", base_url, api_key));
+    digest[..16].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// What the server said about a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyCheck {
+    Accepted,
+    Rejected,
+    /// No answer, or one that says nothing about the key.
+    Unknown,
+}
+
+async fn check_key(base_url: &str, repo_id: &str, api_key: &str) -> KeyCheck {
+    let fingerprint = fingerprint(base_url, api_key);
+    let mut cache = AuthCache::load();
+    if cache.is_fresh(&fingerprint, Utc::now()) {
+        return KeyCheck::Accepted;
+    }
+
+    let Ok(client) = ApiClient::for_project(api_key) else {
+        return KeyCheck::Unknown;
+    };
+    let endpoint = format!("{}/api/atomization-status?id={}", base_url, repo_id);
+    let request = client
+        .get(&endpoint)
+        .header("Accept", "application/json")
+        .timeout(CHECK_TIMEOUT);
+    match client.send(request).await.map(|r| r.status()) {
+        Ok(StatusCode::UNAUTHORIZED) => KeyCheck::Rejected,
+        Ok(status) if status.is_success() => {
+            cache.record(fingerprint, Utc::now());
+            cache.save();
+            KeyCheck::Accepted
+        }
+        _ => KeyCheck::Unknown,
+    }
+}
+
+/// The stored API key, unchecked. A missing key fails with advice to run
+/// `auth`.
+pub fn stored_api_key() -> Result<String> {
+    get_stored_api_key()
+        .context(auth_required_msg())
+        .exit_kind(ExitKind::Network)
+}
+
+/// The stored API key, once the server at `base_url` has not rejected it
+/// for repository `repo_id`. A rejected key fails with advice to run `auth`,
+/// or, on a terminal, offers to store a new key and checks that one.
+pub async fn checked_api_key(base_url: &str, repo_id: &str) -> Result<String> {
+    let api_key = stored_api_key()?;
+    if check_key(base_url, repo_id, &api_key).await != KeyCheck::Rejected {
+        return Ok(api_key);
+    }
+
+    let rejected = || {
+        tag(
+            ExitKind::Network,
+            anyhow::anyhow!(t!("auth-key-rejected", cli = CLI_NAME)),
+        )
+    };
+    let interactive = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+    if !interactive {
+        return Err(rejected());
+    }
+    let reauth = Confirm::new()
+        .with_prompt(t!("auth-key-rejected-prompt"))
+        .default(true)
+        .interact()
+        .unwrap_or(false);
+    if !reauth {
+        return Err(rejected());
+    }
+
+    handle_auth().await?;
+    let api_key = get_stored_api_key().context(auth_required_msg())?;
+    match check_key(base_url, repo_id, &api_key).await {
+        KeyCheck::Rejected => Err(rejected()),
+        _ => Ok(api_key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_expires_and_is_per_key() {
+        let now = Utc::now();
+        let key = fingerprint("https://verilib.org", "key-1");
+        assert_ne!(key, fingerprint("https://verilib.org", "key-2"));
+        assert_ne!(key, fingerprint("https://staging.verilib.org", "key-1"));
+
+        let mut cache = AuthCache::default();
+        cache.record("old".to_string(), now - CACHE_TTL - Duration::seconds(1));
+        cache.record(key.clone(), now);
+        assert!(cache.is_fresh(&key, now + Duration::minutes(1)));
+        assert!(!cache.is_fresh(&key, now + CACHE_TTL));
+        assert!(!cache.is_fresh("old", now));
+        assert!(!cache.accepted.contains_key("old"));
+    }
+}
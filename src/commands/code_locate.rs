@@ -0,0 +1,182 @@
+//! Code-locate subcommand implementation.
+//!
+//! Answer "which atom contains line N of this file" for other tools, with the
+//! interval-tree [`ProbeIndex`] that atomize matches stubs with. Locations
+//! come from the command line or, one per line as `path:line` or
+//! `path line`, from stdin.
+
+use crate::commands::atomize::{ProbeIndex, DEFAULT_LINE_TOLERANCE};
+use crate::commands::stats::read_atoms;
+use crate::config::{resolve_source_roots, ProjectConfig};
+use crate::exit::fail;
+use crate::output::Table;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::io::BufRead;
+use std::path::PathBuf;
+
+/// One queried location and the atom containing it.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Located {
+    pub path: String,
+    pub line: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_module: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lines_start: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lines_end: Option<u32>,
+    /// How the line matched: exact, contained or fuzzy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<&'static str>,
+    /// Why no atom matched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'static str>,
+}
+
+/// Run the code-locate subcommand. Without `location`, reads locations from
+/// stdin. Fails if any location matched no atom.
+pub async fn handle_code_locate(
+    project_root: PathBuf,
+    location: Option<(String, u32)>,
+    line_tolerance: Option<u32>,
+    json: bool,
+) -> Result<()> {
+    let project_root = project_root
+        .canonicalize()
+        .context("Failed to resolve project root")?;
+    let config = ProjectConfig::open(&project_root)?;
+    let atoms = read_atoms(&config.atoms_path())?;
+    let source_roots = resolve_source_roots(&project_root, &config.source_roots);
+    let index = ProbeIndex::build(&atoms, project_root, source_roots).with_line_tolerance(
+        line_tolerance
+            .or(config.line_tolerance)
+            .unwrap_or(DEFAULT_LINE_TOLERANCE),
+    );
+
+    let queries = match location {
+        Some(location) => vec![location],
+        None => read_locations(std::io::stdin().lock())?,
+    };
+    let results: Vec<Located> = queries
+        .into_iter()
+        .map(|(path, line)| match index.lookup_code_name(&path, line) {
+            Ok((code_name, confidence)) => {
+                let atom = &atoms[&code_name];
+                Located {
+                    code_module: atom.code_module.clone(),
+                    lines_start: atom.code_text.as_ref().map(|text| text.lines_start),
+                    lines_end: atom.code_text.as_ref().map(|text| text.lines_end),
+                    confidence: Some(confidence.as_str()),
+                    reason: None,
+                    code_name: Some(code_name),
+                    path,
+                    line,
+                }
+            }
+            Err(reason) => Located {
+                path,
+                line,
+                code_name: None,
+                code_module: None,
+                lines_start: None,
+                lines_end: None,
+                confidence: None,
+                reason: Some(reason.as_str()),
+            },
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+    } else {
+        let mut table = Table::new(["Location", "Code-name", "Module", "Lines", "Match"]);
+        for result in &results {
+            let lines = match (result.lines_start, result.lines_end) {
+                (Some(start), Some(end)) => format!("{}-{}", start, end),
+                _ => String::new(),
+            };
+            table.row([
+                format!("{}:{}", result.path, result.line),
+                result.code_name.clone().unwrap_or_else(|| "-".to_string()),
+                result.code_module.clone().unwrap_or_default(),
+                lines,
+                result
+                    .confidence
+                    .or(result.reason)
+                    .unwrap_or_default()
+                    .to_string(),
+            ]);
+        }
+        table.print();
+    }
+
+    let unmatched = results.iter().filter(|r| r.code_name.is_none()).count();
+    if unmatched > 0 {
+        fail!(
+            CheckFailed,
+            "{} of {} locations matched no atom",
+            unmatched,
+            results.len()
+        );
+    }
+    Ok(())
+}
+
+/// Parse `path:line` or `path line` locations, one per line, skipping blank
+/// lines.
+fn read_locations(reader: impl BufRead) -> Result<Vec<(String, u32)>> {
+    let mut locations = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line.context("Failed to read locations from stdin")?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some(location) = parse_location(line) else {
+            bail!(
+                "Invalid location on line {} of stdin: '{}' (expected PATH:LINE or PATH LINE)",
+                number + 1,
+                line
+            );
+        };
+        locations.push(location);
+    }
+    Ok(locations)
+}
+
+fn parse_location(location: &str) -> Option<(String, u32)> {
+    let (path, line) = location
+        .rsplit_once(char::is_whitespace)
+        .or_else(|| location.rsplit_once(':'))?;
+    let path = path.trim();
+    if path.is_empty() {
+        return None;
+    }
+    Some((path.to_string(), line.trim().parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_locations_accepts_colon_and_space() {
+        let input = "src/lib.rs:12\n\n  src/a b.rs 7  \nsrc/main.rs\t3\n";
+        assert_eq!(
+            read_locations(input.as_bytes()).unwrap(),
+            vec![
+                ("src/lib.rs".to_string(), 12),
+                ("src/a b.rs".to_string(), 7),
+                ("src/main.rs".to_string(), 3),
+            ]
+        );
+
+        let error = read_locations("src/lib.rs:1\nsrc/lib.rs\n".as_bytes()).unwrap_err();
+        assert!(error.to_string().contains("line 2"));
+        assert_eq!(parse_location(":4"), None);
+    }
+}
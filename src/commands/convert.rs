@@ -0,0 +1,383 @@
+//! Convert subcommand implementation.
+//!
+//! Write stubs.json as CSV for triage in a spreadsheet, and apply the edited
+//! `specified`, `owner` and `ignore-reason` columns back. Owners and ignore
+//! reasons are also written to the structure files, so `atomize` keeps them;
+//! `specified` only changes in stubs.json, as `specify` recomputes it from
+//! the spec certs. Reviewer notes from `api annotate` are exported in a
+//! read-only `annotations` column, one note per line.
+
+use crate::config::ProjectConfig;
+use crate::exit::fail;
+use crate::output;
+use crate::structure::schema::{load_map, save_map};
+use crate::structure::{annotations, update_frontmatter, StubEntry};
+use anyhow::{bail, Context, Result};
+use dialoguer::Confirm;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Columns of the exported CSV; only [`Field`] columns are read back.
+const COLUMNS: [&str; 11] = [
+    "file",
+    "code-name",
+    "display-name",
+    "code-module",
+    "code-path",
+    "verified",
+    "review-status",
+    "specified",
+    "owner",
+    "ignore-reason",
+    "annotations",
+];
+
+/// A column that can be edited in the spreadsheet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Field {
+    Specified,
+    Owner,
+    IgnoreReason,
+}
+
+const FIELDS: [Field; 3] = [Field::Specified, Field::Owner, Field::IgnoreReason];
+
+impl Field {
+    fn as_str(self) -> &'static str {
+        match self {
+            Field::Specified => "specified",
+            Field::Owner => "owner",
+            Field::IgnoreReason => "ignore-reason",
+        }
+    }
+
+    fn get(self, stub: &StubEntry) -> Option<String> {
+        match self {
+            Field::Specified => stub.specified.map(|specified| specified.to_string()),
+            Field::Owner => stub.owner.clone(),
+            Field::IgnoreReason => stub.ignore_reason.clone(),
+        }
+    }
+
+    fn set(self, stub: &mut StubEntry, value: Option<&str>) {
+        match self {
+            Field::Specified => stub.specified = value.map(|v| v == "true"),
+            Field::Owner => stub.owner = value.map(str::to_string),
+            Field::IgnoreReason => stub.ignore_reason = value.map(str::to_string),
+        }
+    }
+
+    /// The value of a CSV cell; an empty cell clears the field.
+    fn parse(self, cell: &str) -> Result<Option<String>> {
+        if cell.is_empty() {
+            return Ok(None);
+        }
+        match self {
+            Field::Specified => match cell.to_lowercase().as_str() {
+                "true" | "yes" | "y" | "1" => Ok(Some("true".to_string())),
+                "false" | "no" | "n" | "0" => Ok(Some("false".to_string())),
+                _ => bail!("expected true or false, got '{}'", cell),
+            },
+            Field::Owner | Field::IgnoreReason => Ok(Some(cell.to_string())),
+        }
+    }
+}
+
+/// One field of one stub that the CSV changes.
+#[derive(Debug, PartialEq, Serialize)]
+struct Change {
+    file: String,
+    field: Field,
+    old: Option<String>,
+    new: Option<String>,
+}
+
+/// Run the convert subcommand.
+pub async fn handle_convert(
+    project_root: PathBuf,
+    to_csv: Option<PathBuf>,
+    from_csv: Option<PathBuf>,
+    yes: bool,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    let project_root = project_root
+        .canonicalize()
+        .context("Failed to resolve project root")?;
+    let config = ProjectConfig::open(&project_root)?;
+
+    let stubs_path = config.stubs_path();
+    if !stubs_path.exists() {
+        fail!(
+            MissingPrerequisite,
+            "stubs.json not found at {}. Run 'verilib-cli atomize' first.",
+            stubs_path.display()
+        );
+    }
+    let mut stubs: HashMap<String, StubEntry> = load_map(&stubs_path)?;
+
+    match (to_csv, from_csv) {
+        (Some(path), _) => {
+            let notes = annotations::load(&config.verilib_path());
+            export_csv(&stubs, &notes, &path)
+        }
+        (None, Some(path)) => {
+            let file = std::fs::File::open(&path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            let changes = read_changes(&stubs, file)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&changes)?);
+            } else {
+                print_changes(&changes);
+            }
+            if changes.is_empty() || dry_run {
+                return Ok(());
+            }
+            if !yes && !confirm(changes.len())? {
+                bail!("Import cancelled; nothing was changed.");
+            }
+
+            apply(&config, &mut stubs, &changes)?;
+            if !json {
+                println!("Applied {} changes.", changes.len());
+            }
+            Ok(())
+        }
+        (None, None) => bail!("Pass --to-csv or --from-csv."),
+    }
+}
+
+/// Write `stubs` as CSV to `path`, or to stdout for `-`.
+fn export_csv(
+    stubs: &HashMap<String, StubEntry>,
+    notes: &HashMap<String, Vec<String>>,
+    path: &Path,
+) -> Result<()> {
+    if path == Path::new("-") {
+        return write_csv(stubs, notes, std::io::stdout().lock());
+    }
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    write_csv(stubs, notes, file)?;
+    eprintln!("Wrote {} stubs to {}", stubs.len(), path.display());
+    Ok(())
+}
+
+fn write_csv(
+    stubs: &HashMap<String, StubEntry>,
+    notes: &HashMap<String, Vec<String>>,
+    writer: impl Write,
+) -> Result<()> {
+    let mut csv = csv::Writer::from_writer(writer);
+    csv.write_record(COLUMNS)?;
+    let sorted: BTreeMap<&String, &StubEntry> = stubs.iter().collect();
+    for (file, stub) in sorted {
+        let text = |value: &Option<String>| value.clone().unwrap_or_default();
+        csv.write_record([
+            file.clone(),
+            text(&stub.code_name),
+            text(&stub.display_name),
+            text(&stub.code_module),
+            text(&stub.code_path),
+            stub.verified.map(|v| v.to_string()).unwrap_or_default(),
+            text(&stub.review_status),
+            Field::Specified.get(stub).unwrap_or_default(),
+            Field::Owner.get(stub).unwrap_or_default(),
+            Field::IgnoreReason.get(stub).unwrap_or_default(),
+            stub.code_name
+                .as_ref()
+                .and_then(|name| notes.get(name))
+                .map(|notes| notes.join("\n"))
+                .unwrap_or_default(),
+        ])?;
+    }
+    csv.flush()?;
+    Ok(())
+}
+
+/// Compare the editable columns of a CSV with `stubs`. Every row is checked
+/// first; any invalid row fails the whole import.
+fn read_changes(stubs: &HashMap<String, StubEntry>, reader: impl Read) -> Result<Vec<Change>> {
+    let mut csv = csv::Reader::from_reader(reader);
+    let headers = csv.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|header| header.trim() == name);
+    let Some(file_column) = column("file") else {
+        bail!("no 'file' column; start from a CSV written by 'convert --to-csv'");
+    };
+    let editable: Vec<(Field, usize)> = FIELDS
+        .iter()
+        .filter_map(|&field| column(field.as_str()).map(|i| (field, i)))
+        .collect();
+    if editable.is_empty() {
+        bail!("none of the editable columns (specified, owner, ignore-reason) is present");
+    }
+
+    let mut changes = Vec::new();
+    let mut errors = Vec::new();
+    let mut seen = HashSet::new();
+    for (i, record) in csv.records().enumerate() {
+        // Row 1 is the header.
+        let row = i + 2;
+        let record = record?;
+        let file = record.get(file_column).unwrap_or("").trim();
+        let Some(stub) = stubs.get(file) else {
+            errors.push(format!("row {}: no stub '{}' in stubs.json", row, file));
+            continue;
+        };
+        if !seen.insert(file.to_string()) {
+            errors.push(format!("row {}: '{}' appears more than once", row, file));
+            continue;
+        }
+        for &(field, index) in &editable {
+            let cell = record.get(index).unwrap_or("").trim();
+            match field.parse(cell) {
+                Ok(new) if new != field.get(stub) => changes.push(Change {
+                    file: file.to_string(),
+                    field,
+                    old: field.get(stub),
+                    new,
+                }),
+                Ok(_) => {}
+                Err(e) => errors.push(format!("row {}, {}: {}", row, field.as_str(), e)),
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        bail!(
+            "{} problems; nothing was changed:\n  {}",
+            errors.len(),
+            errors.join("\n  ")
+        );
+    }
+    changes.sort_by(|a, b| (&a.file, a.field).cmp(&(&b.file, b.field)));
+    Ok(changes)
+}
+
+fn print_changes(changes: &[Change]) {
+    if changes.is_empty() {
+        println!("No changes.");
+        return;
+    }
+    let mut current = None;
+    for change in changes {
+        if current != Some(&change.file) {
+            println!("{}", change.file);
+            current = Some(&change.file);
+        }
+        println!(
+            "  {}: {} -> {}",
+            change.field.as_str(),
+            change.old.as_deref().unwrap_or("(none)"),
+            change.new.as_deref().unwrap_or("(none)")
+        );
+    }
+}
+
+fn confirm(count: usize) -> Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        bail!("Refusing to import without confirmation; pass --yes to apply these changes.");
+    }
+    Confirm::new()
+        .with_prompt(format!("Apply {} changes?", count))
+        .default(false)
+        .interact()
+        .context("Failed to read confirmation")
+}
+
+/// Write `changes` to stubs.json, and owners and ignore reasons to the
+/// structure files as well.
+fn apply(
+    config: &ProjectConfig,
+    stubs: &mut HashMap<String, StubEntry>,
+    changes: &[Change],
+) -> Result<()> {
+    for change in changes {
+        let stub = stubs
+            .get_mut(&change.file)
+            .expect("changes name known stubs");
+        change.field.set(stub, change.new.as_deref());
+    }
+    save_map(&config.stubs_path(), stubs)?;
+
+    let structure_root = config.structure_root_path()?;
+    let mut by_file: BTreeMap<&str, Vec<&Change>> = BTreeMap::new();
+    for change in changes.iter().filter(|c| c.field != Field::Specified) {
+        by_file.entry(&change.file).or_default().push(change);
+    }
+    for (file, changes) in by_file {
+        let path = structure_root.join(file);
+        if !path.exists() {
+            output::warn(format!(
+                "{}: structure file not found; changed in stubs.json only",
+                file
+            ));
+            continue;
+        }
+        update_frontmatter(&path, |metadata| {
+            for change in changes {
+                match &change.new {
+                    Some(value) => metadata.insert(change.field.as_str().to_string(), json!(value)),
+                    None => metadata.remove(change.field.as_str()),
+                };
+            }
+        })
+        .with_context(|| format!("Failed to update {}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_round_trip_and_validation() {
+        let stubs = HashMap::from([
+            (
+                "a.md".to_string(),
+                StubEntry {
+                    code_name: Some("probe:a()".to_string()),
+                    specified: Some(false),
+                    owner: Some("alice".to_string()),
+                    ..Default::default()
+                },
+            ),
+            ("b.md".to_string(), StubEntry::default()),
+        ]);
+        let mut exported = Vec::new();
+        write_csv(&stubs, &HashMap::new(), &mut exported).unwrap();
+        assert_eq!(read_changes(&stubs, exported.as_slice()).unwrap(), vec![]);
+
+        let edited = "file,owner,specified,ignore-reason\n\
+                      a.md,,yes,\n\
+                      b.md,bob,,generated code\n";
+        let changes = read_changes(&stubs, edited.as_bytes()).unwrap();
+        let summary: Vec<(&str, Field, Option<&str>)> = changes
+            .iter()
+            .map(|c| (c.file.as_str(), c.field, c.new.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("a.md", Field::Specified, Some("true")),
+                ("a.md", Field::Owner, None),
+                ("b.md", Field::Owner, Some("bob")),
+                ("b.md", Field::IgnoreReason, Some("generated code")),
+            ]
+        );
+
+        let invalid = "file,specified\na.md,maybe\nc.md,true\na.md,true\n";
+        let message = format!(
+            "{:#}",
+            read_changes(&stubs, invalid.as_bytes()).unwrap_err()
+        );
+        assert!(message.starts_with("3 problems"), "{}", message);
+    }
+}
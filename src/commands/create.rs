@@ -1,53 +1,113 @@
 //! Create subcommand implementation.
 //!
-//! Initialize structure files from source analysis using probe-verus.
-
-use crate::config::ProjectConfig;
-use crate::structure::{run_command, write_frontmatter, CommandConfig, ExternalTool};
-use anyhow::{bail, Context, Result};
+//! Initialize structure files from source analysis using probe-verus,
+//! optionally starting from a project template (see [`super::template`]).
+
+use crate::commands::template::Template;
+use crate::config::{resolve_source_roots, ProjectConfig};
+use crate::exit::fail;
+use crate::output;
+use crate::paths::{relative_arg, utf8};
+use crate::structure::{
+    run_command, write_frontmatter, CommandConfig, ExternalTool, FrontmatterFormat, LayoutKind,
+    StructureLayout,
+};
+use anyhow::{Context, Result};
 use serde_json::{json, Value};
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
 /// Run the create subcommand.
-pub async fn handle_create(project_root: PathBuf, root: Option<PathBuf>) -> Result<()> {
+pub async fn handle_create(
+    project_root: PathBuf,
+    root: Option<PathBuf>,
+    layout: Option<LayoutKind>,
+    template: Option<String>,
+) -> Result<()> {
     let project_root = project_root
         .canonicalize()
         .context("Failed to resolve project root")?;
     let verilib_path = project_root.join(".verilib");
     std::fs::create_dir_all(&verilib_path).context("Failed to create .verilib directory")?;
 
-    let structure_root_relative = root
-        .map(|r| r.to_string_lossy().to_string())
-        .unwrap_or_else(|| ".verilib/structure".to_string());
-
     let mut config = ProjectConfig::load(&project_root)?;
+    let mut template_root = None;
+    if let Some(source) = template.as_deref() {
+        let template = Template::fetch(source)?;
+        let applied = template.apply_config(&mut config)?;
+        if applied.iter().any(|key| key == "structure-root") {
+            template_root = config.structure_root.clone();
+        }
+        let seeded = template.seed(&project_root)?;
+        println!("Applied template {}:", source);
+        if !applied.is_empty() {
+            println!("  config.json: {}", applied.join(", "));
+        }
+        for path in &seeded {
+            println!("  {}", path);
+        }
+    }
+
+    // --root wins over the template's structure-root.
+    let structure_root_relative = match (root, template_root) {
+        (Some(root), _) => utf8(&root)?.to_string(),
+        (None, Some(root)) => root,
+        (None, None) => ".verilib/structure".to_string(),
+    };
     config.structure_root = Some(structure_root_relative.clone());
+    if layout.is_some() {
+        config.structure_layout = layout;
+    }
     let config_path = config.save(&project_root)?;
     println!("Wrote config to {}", config_path.display());
 
     let tracked_output_path = verilib_path.join("tracked_functions.csv");
 
     let cmd_config = config.command_config();
-    run_probe_verus_tracked_csv(&project_root, &tracked_output_path, &cmd_config)?;
-
-    let tracked = read_tracked_csv(&tracked_output_path)?;
+    run_probe_verus_tracked_csv(&project_root, ".", &tracked_output_path, &cmd_config)?;
+
+    let mut tracked = read_tracked_csv(&tracked_output_path)?;
+    for (i, root) in resolve_source_roots(&project_root, &config.source_roots)
+        .iter()
+        .enumerate()
+    {
+        let root_output_path = tracked_output_path.with_extension(format!("root{}.csv", i));
+        run_probe_verus_tracked_csv(
+            &project_root,
+            &root.relative,
+            &root_output_path,
+            &cmd_config,
+        )?;
+        let root_tracked = read_tracked_csv(&root_output_path);
+        let _ = std::fs::remove_file(&root_output_path);
+        for (key, mut func) in root_tracked? {
+            func.link = root.prefix(&func.link);
+            tracked.insert(root.prefix(&key), func);
+        }
+    }
     let tracked = disambiguate_names(tracked);
-    let structure = tracked_to_structure(&tracked);
+    let layout = config.structure_layout.unwrap_or_default();
+    let structure = tracked_to_structure(&tracked, layout.layout());
 
     println!("\nGenerating structure files...");
     let structure_root = project_root.join(&structure_root_relative);
-    generate_structure_files(&structure, &structure_root)?;
+    generate_structure_files(
+        &structure,
+        &structure_root,
+        config.frontmatter_format.unwrap_or_default(),
+    )?;
 
     Ok(())
 }
 
-/// Run `probe-verus tracked-csv` to generate the tracked functions CSV.
+/// Run `probe-verus tracked-csv` on `source_dir` (relative to the project root)
+/// to generate the tracked functions CSV.
 ///
 /// Called without `--github-base-url` so the link column contains bare
 /// `file_path#Lline` values that `parse_tracked_link` can parse directly.
 fn run_probe_verus_tracked_csv(
     project_root: &Path,
+    source_dir: &str,
     output_path: &Path,
     config: &CommandConfig,
 ) -> Result<()> {
@@ -55,19 +115,13 @@ fn run_probe_verus_tracked_csv(
         std::fs::create_dir_all(parent)?;
     }
 
-    println!("Running probe-verus tracked-csv...");
+    println!("Running probe-verus tracked-csv on {}...", source_dir);
 
-    let output_relative = output_path
-        .strip_prefix(project_root)
-        .unwrap_or(output_path);
-
-    let output_str = output_relative
-        .to_str()
-        .context("Output path contains non-UTF-8 characters")?;
+    let output_str = relative_arg(output_path, project_root)?;
 
     let output = run_command(
         &ExternalTool::Probe,
-        &["tracked-csv", ".", "--output", output_str],
+        &["tracked-csv", source_dir, "--output", output_str],
         Some(project_root),
         config,
     )?;
@@ -78,7 +132,11 @@ fn run_probe_verus_tracked_csv(
         if !stderr.is_empty() {
             eprintln!("{}", stderr);
         }
-        bail!("probe-verus tracked-csv failed");
+        fail!(
+            ExternalTool,
+            "probe-verus tracked-csv failed{}",
+            output.log_hint()
+        );
     }
 
     println!(
@@ -113,10 +171,10 @@ fn read_tracked_csv(csv_path: &Path) -> Result<HashMap<String, TrackedFunction>>
 
         let result_key = format!("{}::{}", function, module);
         if results.contains_key(&result_key) {
-            eprintln!(
-                "Warning: duplicate CSV entry for '{}', later row overwrites earlier",
+            output::warn(format!(
+                "duplicate CSV entry for '{}', later row overwrites earlier",
                 result_key
-            );
+            ));
         }
         results.insert(
             result_key,
@@ -193,8 +251,11 @@ fn parse_tracked_link(link: &str) -> Option<(String, u32)> {
     }
 }
 
-/// Convert tracked functions to a structure dictionary.
-fn tracked_to_structure(tracked: &HashMap<String, TrackedFunction>) -> HashMap<String, Value> {
+/// Convert tracked functions to a structure dictionary, with file paths chosen by `layout`.
+fn tracked_to_structure(
+    tracked: &HashMap<String, TrackedFunction>,
+    layout: &dyn StructureLayout,
+) -> HashMap<String, Value> {
     let mut result = HashMap::new();
 
     for func in tracked.values() {
@@ -204,7 +265,7 @@ fn tracked_to_structure(tracked: &HashMap<String, TrackedFunction>) -> HashMap<S
             }
 
             let func_name = func.qualified_name.replace("::", ".");
-            let file_path = format!("{}/{}.md", code_path, func_name);
+            let file_path = layout.file_path(&code_path, &func_name);
 
             result.insert(
                 file_path,
@@ -224,6 +285,7 @@ fn tracked_to_structure(tracked: &HashMap<String, TrackedFunction>) -> HashMap<S
 fn generate_structure_files(
     structure: &HashMap<String, Value>,
     structure_root: &Path,
+    format: FrontmatterFormat,
 ) -> Result<()> {
     let mut created_count = 0;
 
@@ -231,10 +293,10 @@ fn generate_structure_files(
         let file_path = structure_root.join(relative_path_str);
 
         if file_path.exists() {
-            eprintln!(
-                "WARNING: File already exists, overwriting: {}",
+            output::warn(format!(
+                "file already exists, overwriting: {}",
                 file_path.display()
-            );
+            ));
         }
 
         let mut metadata_map: HashMap<String, Value> = if let Some(obj) = metadata.as_object() {
@@ -246,7 +308,7 @@ fn generate_structure_files(
         let body_content = metadata_map.remove("content");
         let body = body_content.as_ref().and_then(|v| v.as_str());
 
-        write_frontmatter(&file_path, &metadata_map, body)?;
+        write_frontmatter(&file_path, &metadata_map, body, format)?;
         created_count += 1;
     }
 
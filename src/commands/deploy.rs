@@ -1,22 +1,44 @@
-#![allow(dead_code)] // WIP: not yet wired into CLI — see https://github.com/Beneficial-AI-Foundation/verilib-cli/issues/36
-
+use age::x25519::Recipient;
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use dialoguer::Select;
+use indicatif::{HumanBytes, HumanDuration, ProgressBar};
 use regex::Regex;
-use reqwest::Client;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::OsStr;
-use std::fs;
+use std::fs::{self, OpenOptions};
 use std::io::{self, Write};
-use std::path::{Path, PathBuf};
-
-use super::types::{DeployNode, DeployResponse, VerifierVersionsResponse, LANGUAGES, TYPES};
+use std::path::{Component, Path, PathBuf};
+use std::time::Instant;
+use tempfile::NamedTempFile;
+use walkdir::WalkDir;
+
+use super::types::{
+    DeployNode, DeployPayload, DeployResponse, NodeError, SnippetKind, VerifierVersionsResponse,
+    Visibility, LANGUAGES, SNIPPET_TYPES, TYPES,
+};
+use crate::commands::deploy_secrets::{SecretFinding, SecretScanner, ALLOW_MARKER};
+use crate::commands::layouts::check_layouts;
 use crate::commands::status::get_stored_api_key;
-use crate::config::{ProjectConfig, RepoConfig};
-use crate::constants::{auth_required_msg, DEFAULT_BASE_URL};
-use crate::download::handle_api_error;
+use crate::config::{
+    resolve_base_url, AtomSizeLimit, OversizedAtoms, ProjectConfig, RepoConfig, SecretScan,
+};
+use crate::constants::auth_required_msg;
+use crate::download::{api_error_from_parts, handle_api_error, ApiClient, ApiOperation, Layout};
+use crate::encryption::{encrypt_content, parse_recipient};
+use crate::exit::{fail, ExitContext, ExitKind};
+use crate::output::{self, Table};
+use crate::paths::slash;
+use crate::structure::ReviewStatus;
+
+/// Local log of deployments, one JSON object per line.
+const DEPLOY_LOG: &str = "deploys.jsonl";
+
+/// Report of the nodes the server rejected in the last deploy.
+const DEPLOY_ERRORS: &str = "deploy_errors.json";
 
 #[derive(Debug, Clone, Copy)]
 enum ChangeDecision {
@@ -25,22 +47,132 @@ enum ChangeDecision {
     NoToAll,
 }
 
-pub async fn handle_deploy(url: Option<String>, debug: bool) -> Result<()> {
+/// Options of `deploy`.
+#[derive(Debug, Default)]
+pub struct DeployOptions {
+    /// The `--base-url` flag; see [`resolve_base_url`].
+    pub url: Option<String>,
+    /// Deploy only this subtree of `.verilib` to its own server repository.
+    pub subdir: Option<String>,
+    /// Release note for this deployment.
+    pub message: Option<String>,
+    /// Read the release note from this file instead.
+    pub changelog_file: Option<PathBuf>,
+    /// Settings of a new repository; see [`RepoOptions`].
+    pub repo: RepoOptions,
+    /// Register a new repository and save its ID without uploading the tree.
+    pub create_only: bool,
+}
+
+/// Settings of a new server repository given on the command line. Unset ones
+/// are prompted for.
+#[derive(Debug, Clone, Default)]
+pub struct RepoOptions {
+    /// Team or organization that owns the repository.
+    pub team: Option<String>,
+    pub visibility: Option<Visibility>,
+    /// Topic tags.
+    pub topics: Vec<String>,
+}
+
+/// Everything the server is told about a new repository.
+#[derive(Debug)]
+pub struct RepoInfo {
+    pub language_id: u32,
+    pub proof_id: u32,
+    pub verifierversion_id: Option<u32>,
+    pub summary: String,
+    pub description: Option<String>,
+    pub type_id: u32,
+    pub team: Option<String>,
+    pub visibility: Visibility,
+    pub topics: Vec<String>,
+}
+
+/// One deployment recorded in `.verilib/deploys.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct DeployLogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub repo_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subdir: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// SHA-256 of the request body that was uploaded.
+    pub payload_sha256: String,
+    /// Version the server assigned, when it reports one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<Value>,
+    /// Atoms changed, so the server re-snippetizes them asynchronously.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub has_changes: bool,
+    /// The server's snippetization job for the changes, when it reports one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<Value>,
+}
+
+/// Deploy `.verilib`, or with a subdir only that subtree, to its own server repository.
+pub async fn handle_deploy(options: DeployOptions, debug: bool) -> Result<()> {
+    let api_key = get_stored_api_key()
+        .context(auth_required_msg())
+        .exit_kind(ExitKind::Network)?;
+    deploy_with_key(options, &api_key, debug).await
+}
+
+/// [`handle_deploy`] with a given API key instead of the stored one.
+pub async fn deploy_with_key(options: DeployOptions, api_key: &str, debug: bool) -> Result<()> {
     println!("Preparing deployment...");
     if debug {
         println!("Debug mode: {}", debug);
     }
 
-    let api_key = get_stored_api_key().context(auth_required_msg())?;
+    let message = release_note(options.message, options.changelog_file.as_deref())?;
+    let subdir = options
+        .subdir
+        .as_deref()
+        .map(normalize_subdir)
+        .transpose()?;
+    let subdir = subdir.as_deref();
 
-    let url_base = url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+    let verilib_path = PathBuf::from(".verilib");
+    if !verilib_path.exists() {
+        fail!(
+            MissingPrerequisite,
+            "No .verilib directory found. Please run 'init' first."
+        );
+    }
+    let scope_path = match subdir {
+        Some(subdir) => verilib_path.join(subdir),
+        None => verilib_path.clone(),
+    };
+    if !scope_path.is_dir() {
+        anyhow::bail!("Subdir {} not found under .verilib", scope_path.display());
+    }
 
-    let repo_id = read_repo_id_from_config()?;
+    let repo = read_repo_from_config(subdir)?;
+    let url_base = resolve_base_url(options.url.as_deref(), repo.as_ref());
+    let repo_id = repo.map(|repo| repo.id);
 
     let deploy_info = match &repo_id {
         None => {
             println!("New repository - collecting deployment information...");
-            Some(collect_deploy_info(&url_base, &api_key, debug).await?)
+            Some(
+                collect_deploy_info_with_path(
+                    &url_base,
+                    api_key,
+                    &scope_path,
+                    &options.repo,
+                    debug,
+                )
+                .await?,
+            )
+        }
+        Some(id) if options.create_only => {
+            anyhow::bail!(
+                "Repository already registered (ID: {}); deploy without --create-only to upload.",
+                id
+            )
         }
         Some(id) => {
             println!("Updating existing repository (ID: {})...", id);
@@ -48,22 +180,275 @@ pub async fn handle_deploy(url: Option<String>, debug: bool) -> Result<()> {
         }
     };
 
-    println!("\nScanning .verilib directory...");
+    let (tree, layouts, has_changes) = if options.create_only {
+        (Vec::new(), BTreeMap::new(), false)
+    } else {
+        scan(&scope_path, debug)?
+    };
 
-    let verilib_path = PathBuf::from(".verilib");
-    if !verilib_path.exists() {
-        anyhow::bail!("No .verilib directory found. Please run 'init' first.");
+    let mut payload = DeployPayload {
+        tree,
+        layouts,
+        has_changes,
+        language_id: None,
+        proof_id: None,
+        summary: None,
+        type_id: None,
+        description: None,
+        verifierversion_id: None,
+        repo_id: repo_id.clone(),
+        message: message.clone(),
+        team: None,
+        visibility: None,
+        topics: Vec::new(),
+    };
+
+    if let Some(info) = deploy_info {
+        payload.language_id = Some(info.language_id);
+        payload.proof_id = Some(info.proof_id);
+        payload.summary = Some(info.summary);
+        payload.type_id = Some(info.type_id);
+        payload.description = info.description;
+        payload.verifierversion_id = info.verifierversion_id;
+        payload.team = info.team;
+        payload.visibility = Some(info.visibility);
+        payload.topics = info.topics;
     }
 
-    let mut decision = ChangeDecision::Ask;
-    let mut has_changes = false;
-    let tree = build_tree(
-        &verilib_path,
-        &verilib_path,
-        &mut decision,
-        &mut has_changes,
-    )?;
-    let layouts = build_layouts(&verilib_path, &verilib_path)?;
+    let endpoint = match &repo_id {
+        Some(id) => format!("{}/v2/repo/deploy/{}", url_base, id),
+        None => format!("{}/v2/repo/deploy", url_base),
+    };
+
+    let body = write_payload(&payload)?;
+    let payload_size = body.size;
+    let payload_sha256 = body.sha256.clone();
+    let (files, folders) = count_nodes(&payload.tree);
+
+    println!("\nDeploying to {}...", endpoint);
+
+    let client = ApiClient::for_project(api_key)?;
+    let spinner = output::spinner(format!("Uploading {}", HumanBytes(payload_size)));
+    let started = Instant::now();
+    let response = client
+        .send_with(|| {
+            let file = body
+                .file
+                .reopen()
+                .context("Failed to reopen the deploy payload")?;
+            Ok(client
+                .post(&endpoint)
+                .header("Content-Type", "application/json")
+                .header(reqwest::header::CONTENT_LENGTH, payload_size)
+                .body(tokio::fs::File::from_std(file)))
+        })
+        .await
+        .context("Failed to send deploy request");
+    spinner.finish_and_clear();
+    let response = response?;
+    let elapsed = started.elapsed();
+    println!(
+        "Uploaded {} in {} ({}/s)",
+        HumanBytes(payload_size),
+        HumanDuration(elapsed),
+        HumanBytes((payload_size as f64 / elapsed.as_secs_f64().max(0.001)) as u64)
+    );
+
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let response_text = response
+        .text()
+        .await
+        .context("Failed to read response body")?;
+
+    if debug {
+        println!("Debug: API response: {}", response_text);
+    }
+
+    let errors_path = verilib_path.join(DEPLOY_ERRORS);
+    let node_errors = node_errors(&response_text);
+    if !status.is_success() {
+        if !node_errors.is_empty() {
+            return Err(report_node_errors(&node_errors, &scope_path, &errors_path));
+        }
+        return Err(api_error_from_parts(
+            status.as_u16(),
+            &response_text,
+            retry_after.as_deref(),
+            ApiOperation::Deploy,
+        ));
+    }
+
+    let deploy_response: DeployResponse =
+        serde_json::from_str(&response_text).context("Failed to parse deploy response")?;
+
+    save_config_from_response(&deploy_response, &url_base, subdir)
+        .context("Failed to save config file")?;
+    if options.create_only {
+        println!("Repository registered. Run deploy again to upload the tree.");
+        return Ok(());
+    }
+
+    let entry = DeployLogEntry {
+        timestamp: Utc::now(),
+        repo_id: deploy_response.data.id.to_string(),
+        subdir: subdir.map(str::to_string),
+        message,
+        payload_sha256,
+        version: deploy_response.data.version.clone(),
+        has_changes,
+        job_id: deploy_response.data.job_id.clone(),
+    };
+    if let Err(e) = append_deploy_log(&verilib_path, &entry) {
+        output::warn(format!("failed to record deployment: {:#}", e));
+    }
+
+    if !node_errors.is_empty() {
+        return Err(report_node_errors(&node_errors, &scope_path, &errors_path));
+    }
+    if errors_path.exists() {
+        fs::remove_file(&errors_path)
+            .with_context(|| format!("Failed to remove stale {}", errors_path.display()))?;
+    }
+
+    println!("Deployment successful!");
+    println!(
+        "Deployed {} atoms in {} folders with {} layouts ({} payload)",
+        files,
+        folders,
+        payload.layouts.len(),
+        HumanBytes(payload_size)
+    );
+    if has_changes {
+        println!(
+            "The server is re-snippetizing the changes{}. Run 'verilib-cli atomization-status --wait' \
+             before pulling.",
+            job_suffix(entry.job_id.as_ref())
+        );
+    }
+
+    Ok(())
+}
+
+/// The serialized deploy request body, in a temporary file.
+struct PayloadFile {
+    file: NamedTempFile,
+    size: u64,
+    sha256: String,
+}
+
+/// Serialize `payload` to a temporary file, hashing it as it is written, so
+/// that the body is never held in memory as a whole.
+fn write_payload(payload: &DeployPayload) -> Result<PayloadFile> {
+    let file = NamedTempFile::new().context("Failed to create the deploy payload file")?;
+    let (size, hasher) = {
+        let mut writer = HashingWriter {
+            inner: io::BufWriter::new(file.as_file()),
+            hasher: Sha256::new(),
+            size: 0,
+        };
+        serde_json::to_writer(&mut writer, payload)
+            .context("Failed to serialize deploy payload")?;
+        writer
+            .flush()
+            .context("Failed to write the deploy payload file")?;
+        (writer.size, writer.hasher)
+    };
+    Ok(PayloadFile {
+        file,
+        size,
+        sha256: format!("{:x}", hasher.finalize()),
+    })
+}
+
+/// Passes writes through to `inner`, hashing and counting the bytes.
+struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// ` (job <id>)`, or nothing without a job ID.
+pub fn job_suffix(job_id: Option<&Value>) -> String {
+    match job_id {
+        Some(Value::String(id)) => format!(" (job {})", id),
+        Some(id) => format!(" (job {})", id),
+        None => String::new(),
+    }
+}
+
+/// Hash the atoms under `scope_path` into the deploy tree, and read its layouts.
+fn scan(
+    scope_path: &Path,
+    debug: bool,
+) -> Result<(Vec<DeployNode>, BTreeMap<String, Value>, bool)> {
+    println!("\nScanning {} directory...", scope_path.display());
+
+    let config = ProjectConfig::load(Path::new("."))?;
+    let recipient = config
+        .encryption_recipient
+        .as_deref()
+        .map(parse_recipient)
+        .transpose()?;
+    if recipient.is_some() {
+        println!("Encrypting atom contents before upload.");
+    }
+
+    let atom_count = count_atom_files(scope_path);
+    println!("Found {} atom files", atom_count);
+
+    let secret_scan = config.secret_scan.unwrap_or_default();
+    let scanner = (secret_scan != SecretScan::Off).then(SecretScanner::new);
+    let progress = output::progress_bar(atom_count, "Hashing atoms");
+    let mut state = ScanState {
+        decision: ChangeDecision::Ask,
+        has_changes: false,
+        recipient: recipient.as_ref(),
+        progress: &progress,
+        size_limit: config.max_atom_size,
+        oversized: Vec::new(),
+        scanner: scanner.as_ref(),
+        secrets: Vec::new(),
+    };
+    let tree = build_tree(scope_path, scope_path, &mut state)?;
+    progress.finish_and_clear();
+    report_secrets(&state.secrets, secret_scan)?;
+    let has_changes = state.has_changes;
+    if !state.oversized.is_empty() {
+        output::warn(format!(
+            "left {} atoms larger than max-atom-size out of the upload:",
+            state.oversized.len()
+        ));
+        for (identifier, size) in &state.oversized {
+            eprintln!("  {} ({})", identifier, HumanBytes(*size));
+        }
+    }
+    let layouts = build_layouts(scope_path, scope_path)?;
+    let layout_issues = check_layouts(scope_path)?.len();
+    if layout_issues > 0 {
+        output::warn(format!(
+            "{} layout problems; run 'verilib-cli layouts check' for details.",
+            layout_issues
+        ));
+    }
 
     if debug {
         let tree_json = serde_json::to_string_pretty(&tree)
@@ -79,101 +464,232 @@ pub async fn handle_deploy(url: Option<String>, debug: bool) -> Result<()> {
         println!("Debug: Layouts saved to .verilib/debug_deploy_layouts.json");
     }
 
-    let mut payload = serde_json::json!({
-        "tree": tree,
-        "layouts": layouts,
-    });
+    Ok((tree, layouts, has_changes))
+}
 
-    if has_changes {
-        payload["has_changes"] = Value::Bool(true);
+/// List the likely secrets in the upload, and fail if `policy` blocks them.
+fn report_secrets(secrets: &[SecretFinding], policy: SecretScan) -> Result<()> {
+    if secrets.is_empty() {
+        return Ok(());
     }
-
-    if let Some((language_id, proof_id, verifierversion_id, summary, description, type_id)) =
-        deploy_info
-    {
-        payload["language_id"] = Value::Number(language_id.into());
-        payload["proof_id"] = Value::Number(proof_id.into());
-        payload["summary"] = Value::String(summary);
-        payload["type_id"] = Value::Number(type_id.into());
-
-        if let Some(desc) = description {
-            payload["description"] = Value::String(desc);
-        }
-
-        if let Some(version_id) = verifierversion_id {
-            payload["verifierversion_id"] = Value::Number(version_id.into());
-        }
+    output::warn(format!(
+        "{} lines of the upload look like they contain secrets:",
+        secrets.len()
+    ));
+    let mut table = Table::new(["File", "Line", "Rule"]);
+    for secret in secrets {
+        table.row([
+            secret.path.display().to_string(),
+            secret.line.to_string(),
+            secret.rule.to_string(),
+        ]);
     }
+    table.print();
+    if policy == SecretScan::Block {
+        fail!(
+            CheckFailed,
+            "Deploy blocked by secret-scan in .verilib/config.json. Remove the secrets, \
+             or mark false positives with '{}' on their line.",
+            ALLOW_MARKER
+        );
+    }
+    eprintln!(
+        "Mark false positives with '{}' on their line, or set \"secret-scan\": \"block\" \
+         to stop such deploys.",
+        ALLOW_MARKER
+    );
+    Ok(())
+}
 
-    let endpoint = if let Some(ref id) = repo_id {
-        payload["repo_id"] = Value::String(id.clone());
-        format!("{}/v2/repo/deploy/{}", url_base, id)
-    } else {
-        format!("{}/v2/repo/deploy", url_base)
+/// The per-node errors in a deploy response body, from `data.errors`. A body
+/// without them, or that isn't JSON, has none.
+fn node_errors(body: &str) -> Vec<NodeError> {
+    let Ok(value) = serde_json::from_str::<Value>(body) else {
+        return Vec::new();
     };
-
-    println!("\nDeploying to {}...", endpoint);
-
-    let client = Client::new();
-    let response = client
-        .post(&endpoint)
-        .header("Authorization", format!("ApiKey {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(&payload)
-        .send()
-        .await
-        .context("Failed to send deploy request")?;
-
-    let status = response.status();
-
-    if !status.is_success() {
-        let error_msg = handle_api_error(response).await?;
-        anyhow::bail!(error_msg);
+    match value.pointer("/data/errors") {
+        Some(errors) => serde_json::from_value(errors.clone()).unwrap_or_default(),
+        None => Vec::new(),
     }
+}
 
-    let response_text = response
-        .text()
-        .await
-        .context("Failed to read response body")?;
+/// One rejected node in `deploy_errors.json`.
+#[derive(Debug, PartialEq, Serialize)]
+struct NodeErrorReport {
+    identifier: String,
+    /// The local atom file or folder, when the identifier matches one.
+    path: Option<String>,
+    message: String,
+}
 
-    if debug {
-        println!("Debug: API response: {}", response_text);
+/// The local atom file or folder of each identifier under `scope_path`.
+fn local_paths(scope_path: &Path) -> Result<HashMap<String, PathBuf>> {
+    let mut paths = HashMap::new();
+    for entry in WalkDir::new(scope_path).min_depth(1) {
+        let entry = entry.context("Failed to read .verilib")?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy();
+        if entry.file_type().is_dir() {
+            paths.insert(slash(path.strip_prefix(scope_path)?)?, path.to_path_buf());
+        } else if name.ends_with(".atom.verilib") {
+            paths.insert(atom_identifier(scope_path, path)?, path.to_path_buf());
+        }
     }
+    Ok(paths)
+}
 
-    let deploy_response: DeployResponse =
-        serde_json::from_str(&response_text).context("Failed to parse deploy response")?;
+/// Match `errors` to local files, in identifier order.
+fn match_node_errors(
+    errors: &[NodeError],
+    paths: &HashMap<String, PathBuf>,
+) -> Vec<NodeErrorReport> {
+    let mut reports: Vec<NodeErrorReport> = errors
+        .iter()
+        .map(|error| NodeErrorReport {
+            identifier: error.identifier.clone(),
+            path: paths
+                .get(&error.identifier)
+                .map(|path| path.display().to_string()),
+            message: error.message.clone(),
+        })
+        .collect();
+    reports.sort_by(|a, b| a.identifier.cmp(&b.identifier));
+    reports
+}
 
-    save_config_from_response(&deploy_response, &url_base).context("Failed to save config file")?;
+/// Write `errors` to `deploy_errors.json`, print them as a table, and return
+/// the error that fails the deploy.
+fn report_node_errors(
+    errors: &[NodeError],
+    scope_path: &Path,
+    errors_path: &Path,
+) -> anyhow::Error {
+    let paths = local_paths(scope_path).unwrap_or_else(|e| {
+        output::warn(format!("could not map rejected nodes to files: {:#}", e));
+        HashMap::new()
+    });
+    let reports = match_node_errors(errors, &paths);
+
+    let mut table = Table::new(["Atom", "File", "Error"]);
+    for report in &reports {
+        table.row([
+            report.identifier.as_str(),
+            report.path.as_deref().unwrap_or("-"),
+            report.message.as_str(),
+        ]);
+    }
+    table.print();
+
+    let written = serde_json::to_vec_pretty(&reports)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| crate::interrupt::write_atomic(errors_path, json));
+    match written {
+        Ok(()) => anyhow::anyhow!(
+            "The server rejected {} nodes; details in {}",
+            reports.len(),
+            errors_path.display()
+        ),
+        Err(e) => anyhow::anyhow!(
+            "The server rejected {} nodes, and the report could not be written: {:#}",
+            reports.len(),
+            e
+        ),
+    }
+}
 
-    println!("Deployment successful!");
+/// The release note from `--message` or `--changelog-file`, if either is given.
+fn release_note(message: Option<String>, changelog_file: Option<&Path>) -> Result<Option<String>> {
+    let note = match (message, changelog_file) {
+        (Some(_), Some(_)) => {
+            anyhow::bail!("Use either a deploy message or a changelog file, not both")
+        }
+        (Some(message), None) => message,
+        (None, Some(path)) => fs::read_to_string(path)
+            .with_context(|| format!("Failed to read changelog file {}", path.display()))?,
+        (None, None) => return Ok(None),
+    };
+    let note = note.trim();
+    if note.is_empty() {
+        anyhow::bail!("Deploy message is empty");
+    }
+    Ok(Some(note.to_string()))
+}
 
+fn append_deploy_log(verilib_path: &Path, entry: &DeployLogEntry) -> Result<()> {
+    let path = verilib_path.join(DEPLOY_LOG);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
     Ok(())
 }
 
-fn read_repo_id_from_config() -> Result<Option<String>> {
+/// The latest deployment of `repo_id` recorded in `.verilib/deploys.jsonl`.
+pub fn last_deploy(verilib_path: &Path, repo_id: &str) -> Option<DeployLogEntry> {
+    let content = fs::read_to_string(verilib_path.join(DEPLOY_LOG)).ok()?;
+    content
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str::<DeployLogEntry>(line).ok())
+        .find(|entry| entry.repo_id == repo_id)
+}
+
+fn read_repo_from_config(subdir: Option<&str>) -> Result<Option<RepoConfig>> {
     let project_root = PathBuf::from(".");
     let config = ProjectConfig::load(&project_root)?;
 
-    Ok(config.repo.map(|r| r.id))
+    Ok(config.repo_for(subdir).cloned())
 }
 
-fn save_config_from_response(response_data: &DeployResponse, base_url: &str) -> Result<()> {
+/// Normalize a `--subdir` to `a/b` form, relative to `.verilib`.
+fn normalize_subdir(subdir: &str) -> Result<String> {
+    let mut parts = Vec::new();
+    for component in Path::new(subdir).components() {
+        match component {
+            Component::Normal(part) => parts.push(
+                part.to_str()
+                    .with_context(|| format!("Subdir is not valid UTF-8: {}", subdir))?,
+            ),
+            Component::CurDir => {}
+            _ => anyhow::bail!("Subdir must be a relative path inside .verilib: {}", subdir),
+        }
+    }
+    if parts.is_empty() {
+        anyhow::bail!("Subdir must name a directory inside .verilib: {}", subdir);
+    }
+    Ok(parts.join("/"))
+}
+
+fn save_config_from_response(
+    response_data: &DeployResponse,
+    base_url: &str,
+    subdir: Option<&str>,
+) -> Result<()> {
     let repo_id_str = response_data.data.id.to_string();
 
     let project_root = PathBuf::from(".");
     let mut config = ProjectConfig::load(&project_root)?;
 
-    let is_admin = config.repo.as_ref().map(|r| r.is_admin).unwrap_or(false);
+    let is_admin = config.repo_for(subdir).map(|r| r.is_admin).unwrap_or(false);
 
-    config.repo = Some(RepoConfig {
-        id: repo_id_str.clone(),
-        url: base_url.to_string(),
-        is_admin,
-    });
+    config.set_repo_for(
+        subdir,
+        RepoConfig {
+            id: repo_id_str.clone(),
+            url: base_url.to_string(),
+            is_admin,
+        },
+    );
 
     config.save(&project_root)?;
 
     println!("Config saved to .verilib/config.json");
+    if let Some(subdir) = subdir {
+        println!("Subdir: {}", subdir);
+    }
     println!("Repository ID: {}", response_data.data.id);
     println!("Repository URL: {}", base_url);
     Ok(())
@@ -196,9 +712,9 @@ fn detect_language_in_path(search_path: &PathBuf, debug: bool) -> Option<u32> {
             );
         }
         for ext in language.extensions {
-            if find_files_with_extension(search_path, ext, debug) {
+            if let Some(found) = find_path_with_extension(search_path, ext) {
                 if debug {
-                    println!("Debug: Found {} file with extension {}", language.name, ext);
+                    println!("Debug: Found {} with extension {}", found.display(), ext);
                 }
                 return Some(language.id);
             }
@@ -211,54 +727,22 @@ fn detect_language_in_path(search_path: &PathBuf, debug: bool) -> Option<u32> {
     None
 }
 
-fn find_files_with_extension(dir: &Path, extension: &str, debug: bool) -> bool {
-    if let Ok(entries) = fs::read_dir(dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
-
-            if file_name == "config.json" || file_name == "debug_response.json" {
-                continue;
-            }
-
-            if path.is_dir() {
-                let dir_name = file_name.to_string();
-                let ext_without_dot = extension.trim_start_matches('.');
-
-                if dir_name == format!("mod{}", extension)
-                    || dir_name.ends_with(&format!(".{}", ext_without_dot))
-                {
-                    if debug {
-                        println!(
-                            "Debug: Found matching directory: {} with extension {}",
-                            dir_name, extension
-                        );
-                    }
-                    return true;
-                }
-
-                if find_files_with_extension(&path, extension, debug) {
-                    return true;
-                }
-            } else if path.is_file() {
-                if let Some(file_ext) = path.extension() {
-                    let file_ext_str = file_ext.to_string_lossy();
-                    let ext_without_dot = extension.trim_start_matches('.');
-                    if file_ext_str == ext_without_dot {
-                        if debug {
-                            println!(
-                                "Debug: Found matching file: {} with extension {}",
-                                file_name, extension
-                            );
-                        }
-                        return true;
-                    }
-                }
-            }
+/// A file with `extension` (e.g. `.rs`) under `dir`, or a directory named
+/// like one, as `.verilib` has for each source file.
+fn find_path_with_extension(dir: &Path, extension: &str) -> Option<PathBuf> {
+    let ext_without_dot = extension.trim_start_matches('.');
+    let found = std::sync::Mutex::new(None);
+    crate::walk::any_source_path(dir, |path| {
+        let matches = path.extension().is_some_and(|ext| ext == ext_without_dot)
+            && path
+                .file_name()
+                .is_some_and(|name| name != "config.json" && name != "debug_response.json");
+        if matches {
+            *found.lock().unwrap() = Some(path.to_path_buf());
         }
-    }
-
-    false
+        matches
+    });
+    found.into_inner().unwrap()
 }
 
 fn prompt_language(default_id: Option<u32>, prompt_text: &str) -> Result<u32> {
@@ -301,12 +785,9 @@ async fn fetch_verifier_versions(
         println!("Debug: Fetching verifier versions from: {}", endpoint);
     }
 
-    let client = Client::new();
+    let client = ApiClient::for_project(api_key)?;
     let response = client
-        .get(&endpoint)
-        .header("Authorization", format!("ApiKey {}", api_key))
-        .header("Accept", "application/json")
-        .send()
+        .send(client.get(&endpoint).header("Accept", "application/json"))
         .await
         .context("Failed to fetch verifier versions")?;
 
@@ -407,7 +888,12 @@ fn prompt_summary() -> Result<String> {
 }
 
 fn prompt_description() -> Result<Option<String>> {
-    println!("\nEnter description (optional, press Enter to skip):");
+    prompt_optional("description")
+}
+
+/// A line of input, or `None` if it is left empty.
+fn prompt_optional(what: &str) -> Result<Option<String>> {
+    println!("\nEnter {} (optional, press Enter to skip):", what);
     print!("> ");
     io::stdout().flush()?;
 
@@ -422,20 +908,40 @@ fn prompt_description() -> Result<Option<String>> {
     }
 }
 
-pub async fn collect_deploy_info(
-    base_url: &str,
-    api_key: &str,
-    debug: bool,
-) -> Result<(u32, u32, Option<u32>, String, Option<String>, u32)> {
-    collect_deploy_info_with_path(base_url, api_key, &PathBuf::from(".verilib"), debug).await
+fn prompt_visibility() -> Result<Visibility> {
+    let items: Vec<&str> = Visibility::ALL.iter().map(|v| v.as_str()).collect();
+
+    let selection = Select::new()
+        .with_prompt("Select Visibility")
+        .items(&items)
+        .default(0)
+        .interact()
+        .context("Failed to get visibility selection")?;
+
+    Ok(Visibility::ALL[selection])
+}
+
+/// Topic tags from `--topic` flags or a comma-separated answer: trimmed,
+/// without empty or repeated ones.
+pub fn parse_topics<'a>(topics: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut parsed: Vec<String> = Vec::new();
+    for topic in topics.into_iter().flat_map(|t| t.split(',')) {
+        let topic = topic.trim();
+        if !topic.is_empty() && !parsed.iter().any(|t| t == topic) {
+            parsed.push(topic.to_string());
+        }
+    }
+    parsed
 }
 
+/// Prompt for the settings of a new repository, except those given in `repo`.
 pub async fn collect_deploy_info_with_path(
     base_url: &str,
     api_key: &str,
     search_path: &PathBuf,
+    repo: &RepoOptions,
     debug: bool,
-) -> Result<(u32, u32, Option<u32>, String, Option<String>, u32)> {
+) -> Result<RepoInfo> {
     let detected_language = detect_language_in_path(search_path, debug);
 
     let language_id = prompt_language(detected_language, "Select Language:")?;
@@ -447,29 +953,85 @@ pub async fn collect_deploy_info_with_path(
     let description = prompt_description()?;
     let type_id = prompt_type()?;
 
-    Ok((
+    let team = match &repo.team {
+        Some(team) => Some(team.clone()),
+        None => prompt_optional("team or organization")?,
+    };
+    let visibility = match repo.visibility {
+        Some(visibility) => visibility,
+        None => prompt_visibility()?,
+    };
+    let topics = if repo.topics.is_empty() {
+        let answer = prompt_optional("topic tags, separated by commas")?;
+        parse_topics(answer.as_deref())
+    } else {
+        parse_topics(repo.topics.iter().map(String::as_str))
+    };
+
+    Ok(RepoInfo {
         language_id,
         proof_id,
         verifierversion_id,
         summary,
         description,
         type_id,
-    ))
+        team,
+        visibility,
+        topics,
+    })
+}
+
+/// Number of `.atom.verilib` files under `dir`, for sizing the progress bar.
+fn count_atom_files(dir: &Path) -> u64 {
+    crate::walk::verilib_files(dir)
+        .filter(|path| path.to_string_lossy().ends_with(".atom.verilib"))
+        .count() as u64
+}
+
+/// Number of file and folder nodes in `nodes`, recursively.
+fn count_nodes(nodes: &[DeployNode]) -> (usize, usize) {
+    nodes.iter().fold((0, 0), |(files, folders), node| {
+        let (child_files, child_folders) = count_nodes(&node.children);
+        if node.file_type == "folder" {
+            (files + child_files, folders + child_folders + 1)
+        } else {
+            (files + child_files + 1, folders + child_folders)
+        }
+    })
+}
+
+/// The identifier of the atom file at `path`: its path under `base_path`
+/// without the extension or `[N] - ` index prefixes.
+pub fn atom_identifier(base_path: &Path, path: &Path) -> Result<String> {
+    let re = Regex::new(r"\[\d*\]\s-\s").unwrap();
+    let identifier_base = slash(path.strip_prefix(base_path).unwrap())?
+        .trim_end_matches(".atom.verilib")
+        .to_string();
+    Ok(re.replace(&identifier_base, "").to_string())
+}
+
+/// What [`build_tree`] carries through the directories it walks.
+struct ScanState<'a> {
+    decision: ChangeDecision,
+    has_changes: bool,
+    recipient: Option<&'a Recipient>,
+    progress: &'a ProgressBar,
+    size_limit: Option<AtomSizeLimit>,
+    /// Atoms left out for exceeding `max-atom-size`, with their sizes.
+    oversized: Vec<(String, u64)>,
+    /// Checks uploaded atoms for secrets, unless `secret-scan` is off.
+    scanner: Option<&'a SecretScanner>,
+    secrets: Vec<SecretFinding>,
 }
 
 fn build_tree(
     base_path: &Path,
     current_path: &Path,
-    decision: &mut ChangeDecision,
-    has_changes: &mut bool,
+    state: &mut ScanState,
 ) -> Result<Vec<DeployNode>> {
     let mut nodes = Vec::new();
 
-    let entries = fs::read_dir(current_path)
-        .with_context(|| format!("Failed to read directory: {:?}", current_path))?;
-
-    for entry in entries {
-        let entry = entry?;
+    for entry in sorted_entries(current_path)? {
         let path = entry.path();
         let extension = path.extension();
         let file_name = entry.file_name();
@@ -480,13 +1042,9 @@ fn build_tree(
         }
 
         if path.is_dir() {
-            let relative_path = path
-                .strip_prefix(base_path)
-                .unwrap()
-                .to_string_lossy()
-                .to_string();
+            let relative_path = slash(path.strip_prefix(base_path).unwrap())?;
 
-            let children = build_tree(base_path, &path, decision, has_changes)?;
+            let children = build_tree(base_path, &path, state)?;
 
             nodes.push(DeployNode {
                 identifier: relative_path,
@@ -499,20 +1057,39 @@ fn build_tree(
                 snippets: None,
                 specified: false,
                 disabled: false,
+                review_status: None,
             });
         } else if file_name_str.ends_with(".atom.verilib") {
-            let content = fs::read_to_string(&path)
-                .with_context(|| format!("Failed to read file: {:?}", path))?;
-            let regex_pattern = r"\[\d*\]\s-\s";
-            let re = Regex::new(regex_pattern).unwrap();
-            let identifier_base = path
-                .strip_prefix(base_path)
-                .unwrap()
-                .to_string_lossy()
-                .to_string()
-                .trim_end_matches(".atom.verilib")
-                .to_string();
-            let identifier = re.replace(&identifier_base, "").to_string();
+            state.progress.inc(1);
+            let identifier = atom_identifier(base_path, &path)?;
+            let size = fs::metadata(&path)
+                .with_context(|| format!("Failed to read file: {:?}", path))?
+                .len();
+            if let Some(limit) = state.size_limit.filter(|limit| size > limit.bytes) {
+                match limit.oversized {
+                    OversizedAtoms::Error => anyhow::bail!(
+                        "{} is {}, larger than max-atom-size ({}) in .verilib/config.json. \
+                         Raise the limit, or set \"oversized\": \"skip\" to deploy without it.",
+                        identifier,
+                        HumanBytes(size),
+                        HumanBytes(limit.bytes)
+                    ),
+                    OversizedAtoms::Skip => {
+                        state.oversized.push((identifier, size));
+                        continue;
+                    }
+                }
+            }
+
+            // Read once: the same bytes are fingerprinted, scanned and uploaded.
+            let contents =
+                fs::read(&path).with_context(|| format!("Failed to read file: {:?}", path))?;
+            let current_fingerprint = fingerprint(&contents);
+            let text = String::from_utf8(contents)
+                .with_context(|| format!("{} is not valid UTF-8", path.display()))?;
+            if let Some(scanner) = state.scanner {
+                state.secrets.extend(scanner.scan(&path, &text));
+            }
 
             let meta_file_name =
                 file_name_str.trim_end_matches(".atom.verilib").to_string() + ".meta.verilib";
@@ -526,9 +1103,12 @@ fn build_tree(
                 snippets_value,
                 specified,
                 disabled,
+                review_status,
             ) = if meta_path.exists() {
-                let meta_content = fs::read_to_string(&meta_path)?;
-                let meta_value: Value = serde_json::from_str(&meta_content)?;
+                let meta_content = fs::read_to_string(&meta_path)
+                    .with_context(|| format!("Failed to read file: {:?}", meta_path))?;
+                let meta_value: Value = serde_json::from_str(&meta_content)
+                    .with_context(|| format!("Invalid meta file {}", meta_path.display()))?;
 
                 let deps = if let Some(deps) = meta_value.get("dependencies") {
                     serde_json::from_value(deps.clone()).unwrap_or_default()
@@ -551,6 +1131,9 @@ fn build_tree(
                     .and_then(|v| v.as_str())
                     .map(|s| s.to_string());
                 let snippets = meta_value.get("snippets").cloned();
+                if let Some(snippets) = &snippets {
+                    validate_snippets(snippets, &identifier)?;
+                }
                 let specified = meta_value
                     .get("specified")
                     .and_then(|v| v.as_bool())
@@ -559,6 +1142,18 @@ fn build_tree(
                     .get("disabled")
                     .and_then(|v| v.as_bool())
                     .unwrap_or_default();
+                let review_status = match meta_value.get("review_status") {
+                    None | Some(Value::Null) => None,
+                    Some(value) => Some(
+                        value
+                            .as_str()
+                            .context("review_status is not a string")
+                            .and_then(str::parse::<ReviewStatus>)
+                            .with_context(|| {
+                                format!("Invalid meta file {}", meta_path.display())
+                            })?,
+                    ),
+                };
 
                 (
                     deps,
@@ -568,45 +1163,52 @@ fn build_tree(
                     snippets,
                     specified,
                     disabled,
+                    review_status,
                 )
             } else {
-                (Vec::new(), String::new(), None, None, None, false, false)
+                (
+                    Vec::new(),
+                    String::new(),
+                    None,
+                    None,
+                    None,
+                    false,
+                    false,
+                    None,
+                )
             };
 
-            let mut hasher = Sha256::new();
-            hasher.update(&content);
-            let hash_result = hasher.finalize();
-            let current_fingerprint = format!("{:x}", hash_result);
-
-            let (final_content, snippets) = if let Some(stored_fp) = stored_fingerprint {
+            let snippets = if let Some(stored_fp) = stored_fingerprint {
                 if stored_fp != current_fingerprint {
-                    let use_new_content = match *decision {
+                    let use_new_content = match state.decision {
                         ChangeDecision::YesToAll => true,
                         ChangeDecision::NoToAll => false,
                         ChangeDecision::Ask => {
-                            println!("\nFile has been modified: {}", identifier);
-                            println!("   Current file differs from the stored version.");
-
-                            let options = vec![
-                                "Yes - Deploy edited content (triggers re-snippetization for entire repository)",
-                                "No - Keep original snippets for this file",
-                                "No to all - Skip all edited files"
-                            ];
-
-                            let selection = Select::new()
-                                .with_prompt("Would you like to deploy the edited content?")
-                                .items(&options)
-                                .default(0)
-                                .interact()?;
+                            let selection = state.progress.suspend(|| {
+                                println!("\nFile has been modified: {}", identifier);
+                                println!("   Current file differs from the stored version.");
+
+                                let options = vec![
+                                    "Yes - Deploy edited content (triggers re-snippetization for entire repository)",
+                                    "No - Keep original snippets for this file",
+                                    "No to all - Skip all edited files"
+                                ];
+
+                                Select::new()
+                                    .with_prompt("Would you like to deploy the edited content?")
+                                    .items(&options)
+                                    .default(0)
+                                    .interact()
+                            })?;
 
                             match selection {
                                 0 => {
-                                    *decision = ChangeDecision::YesToAll;
+                                    state.decision = ChangeDecision::YesToAll;
                                     true
                                 }
                                 1 => false,
                                 2 => {
-                                    *decision = ChangeDecision::NoToAll;
+                                    state.decision = ChangeDecision::NoToAll;
                                     false
                                 }
                                 _ => false,
@@ -614,18 +1216,26 @@ fn build_tree(
                         }
                     };
                     if use_new_content {
-                        *has_changes = true;
+                        state.has_changes = true;
                     }
                 }
-                (content.clone(), snippets_value)
+                snippets_value
             } else {
-                *has_changes = true;
-                (content.clone(), None)
+                state.has_changes = true;
+                None
+            };
+
+            let (content, snippets) = match state.recipient {
+                Some(r) => (
+                    encrypt_content(r, &text)?,
+                    snippets.map(|s| encrypt_snippets(s, r)).transpose()?,
+                ),
+                None => (text, snippets),
             };
 
             nodes.push(DeployNode {
                 identifier,
-                content: final_content,
+                content,
                 dependencies,
                 code_name,
                 file_type: "file".to_string(),
@@ -634,6 +1244,7 @@ fn build_tree(
                 snippets,
                 specified,
                 disabled,
+                review_status,
             });
         }
     }
@@ -641,30 +1252,73 @@ fn build_tree(
     Ok(nodes)
 }
 
-fn build_layouts(base_path: &Path, current_path: &Path) -> Result<HashMap<String, Value>> {
-    let mut layouts = HashMap::new();
+/// The entries of `dir` by file name, so that the payload doesn't depend on
+/// the order the filesystem lists them in.
+fn sorted_entries(dir: &Path) -> Result<Vec<fs::DirEntry>> {
+    let mut entries = fs::read_dir(dir)
+        .and_then(|entries| entries.collect::<io::Result<Vec<_>>>())
+        .with_context(|| format!("Failed to read directory: {:?}", dir))?;
+    entries.sort_by_key(|entry| entry.file_name());
+    Ok(entries)
+}
 
-    let entries = fs::read_dir(current_path)
-        .with_context(|| format!("Failed to read directory: {:?}", current_path))?;
+/// SHA-256 of an atom's contents, as stored in its meta file's `fingerprint`.
+fn fingerprint(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
 
-    for entry in entries {
-        let entry = entry?;
-        let path = entry.path();
+/// Reject snippets whose `type_id` is not in the server's taxonomy before anything is sent.
+pub fn validate_snippets(snippets: &Value, identifier: &str) -> Result<()> {
+    let items = snippets
+        .as_array()
+        .with_context(|| format!("snippets of {} must be an array", identifier))?;
+
+    for (i, item) in items.iter().enumerate() {
+        let type_id = item.get("type_id").and_then(|v| v.as_u64());
+        if type_id
+            .and_then(|id| SnippetKind::from_type_id(id as u32))
+            .is_none()
+        {
+            let expected: Vec<String> = SNIPPET_TYPES
+                .iter()
+                .map(|(kind, id)| format!("{} ({})", id, kind.as_str()))
+                .collect();
+            anyhow::bail!(
+                "Snippet {} of {} has type_id {}; expected one of {}",
+                i,
+                identifier,
+                item.get("type_id").unwrap_or(&Value::Null),
+                expected.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
 
-        if path.is_dir() {
-            let layout_file = path.join("layout.verilib");
+/// Encrypt the `text` of every snippet, leaving ordering and type metadata in the clear.
+fn encrypt_snippets(mut snippets: Value, recipient: &Recipient) -> Result<Value> {
+    if let Some(items) = snippets.as_array_mut() {
+        for item in items {
+            if let Some(text) = item.get("text").and_then(|v| v.as_str()) {
+                item["text"] = Value::String(encrypt_content(recipient, text)?);
+            }
+        }
+    }
+    Ok(snippets)
+}
 
-            if layout_file.exists() {
-                let layout_content = fs::read_to_string(&layout_file)?;
-                let layout_value: Value = serde_json::from_str(&layout_content)?;
+/// Every layout.verilib under `current_path`, keyed by folder, as whole
+/// [`Layout`]s so fields this version doesn't use survive the upload.
+fn build_layouts(base_path: &Path, current_path: &Path) -> Result<BTreeMap<String, Value>> {
+    let mut layouts = BTreeMap::new();
 
-                let relative_path = path
-                    .strip_prefix(base_path)
-                    .unwrap()
-                    .to_string_lossy()
-                    .to_string();
+    for entry in sorted_entries(current_path)? {
+        let path = entry.path();
 
-                layouts.insert(relative_path, layout_value);
+        if path.is_dir() {
+            if let Some(layout) = Layout::read(&path)? {
+                let relative_path = slash(path.strip_prefix(base_path).unwrap())?;
+                layouts.insert(relative_path, serde_json::to_value(&layout)?);
             }
 
             let child_layouts = build_layouts(base_path, &path)?;
@@ -674,3 +1328,225 @@ fn build_layouts(base_path: &Path, current_path: &Path) -> Result<HashMap<String
 
     Ok(layouts)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_snippets_rejects_unknown_types() {
+        let valid = json!([
+            { "sortorder": 0, "text": "ensures x", "type_id": 3 },
+            { "sortorder": 1, "text": "fn f() {}", "type_id": 2 },
+        ]);
+        assert!(validate_snippets(&valid, "f").is_ok());
+
+        let unknown = json!([{ "sortorder": 0, "text": "?", "type_id": 99 }]);
+        assert!(validate_snippets(&unknown, "f").is_err());
+        let missing = json!([{ "sortorder": 0, "text": "?" }]);
+        assert!(validate_snippets(&missing, "f").is_err());
+    }
+
+    #[test]
+    fn test_release_note_from_message_or_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let changelog = dir.path().join("CHANGES.md");
+        fs::write(&changelog, "\n- Prove len bound\n\n").unwrap();
+
+        assert_eq!(release_note(None, None).unwrap(), None);
+        assert_eq!(
+            release_note(Some(" Fix specs ".to_string()), None).unwrap(),
+            Some("Fix specs".to_string())
+        );
+        assert_eq!(
+            release_note(None, Some(&changelog)).unwrap(),
+            Some("- Prove len bound".to_string())
+        );
+        assert!(release_note(Some("x".to_string()), Some(&changelog)).is_err());
+        assert!(release_note(Some("  ".to_string()), None).is_err());
+    }
+
+    #[test]
+    fn test_normalize_subdir() {
+        assert_eq!(normalize_subdir("services/auth").unwrap(), "services/auth");
+        assert_eq!(
+            normalize_subdir("./services/auth/").unwrap(),
+            "services/auth"
+        );
+        assert!(normalize_subdir("../other").is_err());
+        assert!(normalize_subdir("/services").is_err());
+        assert!(normalize_subdir(".").is_err());
+    }
+
+    #[test]
+    fn test_node_errors_map_to_local_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        let atom = dir.path().join("src/[1] - lib.rs.atom.verilib");
+        fs::write(&atom, "").unwrap();
+
+        let body = json!({
+            "status": "error",
+            "data": {
+                "errors": [
+                    {"identifier": "src/missing.rs", "message": "unknown parent"},
+                    {"identifier": "src/lib.rs", "message": "empty content"}
+                ]
+            }
+        })
+        .to_string();
+        let errors = node_errors(&body);
+        let reports = match_node_errors(&errors, &local_paths(dir.path()).unwrap());
+        assert_eq!(
+            reports,
+            vec![
+                NodeErrorReport {
+                    identifier: "src/lib.rs".to_string(),
+                    path: Some(atom.display().to_string()),
+                    message: "empty content".to_string(),
+                },
+                NodeErrorReport {
+                    identifier: "src/missing.rs".to_string(),
+                    path: None,
+                    message: "unknown parent".to_string(),
+                },
+            ]
+        );
+        assert!(node_errors(r#"{"status": "ok", "data": {"id": 1}}"#).is_empty());
+        assert!(node_errors("Bad Gateway").is_empty());
+    }
+
+    #[test]
+    fn test_last_deploy_of_repo() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert!(last_deploy(dir.path(), "1").is_none());
+
+        let entry = |repo_id: &str, has_changes: bool| DeployLogEntry {
+            timestamp: Utc::now(),
+            repo_id: repo_id.to_string(),
+            subdir: None,
+            message: None,
+            payload_sha256: String::new(),
+            version: None,
+            has_changes,
+            job_id: has_changes.then(|| serde_json::json!(7)),
+        };
+        append_deploy_log(dir.path(), &entry("1", true)).unwrap();
+        append_deploy_log(dir.path(), &entry("2", false)).unwrap();
+
+        let last = last_deploy(dir.path(), "1").unwrap();
+        assert!(last.has_changes);
+        assert_eq!(job_suffix(last.job_id.as_ref()), " (job 7)");
+        let last = last_deploy(dir.path(), "2").unwrap();
+        assert!(!last.has_changes);
+        assert_eq!(job_suffix(last.job_id.as_ref()), "");
+    }
+
+    #[test]
+    fn test_parse_topics() {
+        assert_eq!(
+            parse_topics(["crypto, verus", "crypto", " ,ntt"]),
+            vec!["crypto", "verus", "ntt"]
+        );
+        assert!(parse_topics(None).is_empty());
+    }
+
+    #[test]
+    fn test_oversized_atoms_are_skipped_or_refused() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("small().atom.verilib"), "fn small() {}").unwrap();
+        fs::write(src.join("[1] - big().atom.verilib"), "x".repeat(100)).unwrap();
+
+        let progress = ProgressBar::hidden();
+        let scan = |oversized| {
+            let mut state = ScanState {
+                decision: ChangeDecision::Ask,
+                has_changes: false,
+                recipient: None,
+                progress: &progress,
+                size_limit: Some(AtomSizeLimit {
+                    bytes: 50,
+                    oversized,
+                }),
+                oversized: Vec::new(),
+                scanner: None,
+                secrets: Vec::new(),
+            };
+            build_tree(dir.path(), dir.path(), &mut state).map(|tree| (tree, state.oversized))
+        };
+
+        let (tree, skipped) = scan(OversizedAtoms::Skip).unwrap();
+        assert_eq!(skipped, [("src/big()".to_string(), 100)]);
+        let tree = serde_json::to_value(&tree).unwrap();
+        let atoms = tree[0]["children"].as_array().unwrap();
+        assert_eq!(atoms.len(), 1);
+        assert_eq!(atoms[0]["identifier"], "src/small()");
+        assert_eq!(atoms[0]["content"], "fn small() {}");
+
+        let error = scan(OversizedAtoms::Error).unwrap_err();
+        assert!(error.to_string().contains("src/big()"), "{}", error);
+    }
+
+    /// Each atom is read and encrypted once, in `build_tree`: the payload
+    /// serializes the same content every time.
+    #[test]
+    fn test_atom_is_encrypted_once_while_building_the_tree() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("f().atom.verilib");
+        fs::write(&path, "fn f() {}").unwrap();
+        let identity = age::x25519::Identity::generate();
+        let recipient = identity.to_public();
+
+        let progress = ProgressBar::hidden();
+        let mut state = ScanState {
+            decision: ChangeDecision::Ask,
+            has_changes: false,
+            recipient: Some(&recipient),
+            progress: &progress,
+            size_limit: None,
+            oversized: Vec::new(),
+            scanner: None,
+            secrets: Vec::new(),
+        };
+        let tree = build_tree(dir.path(), dir.path(), &mut state).unwrap();
+        fs::write(&path, "fn changed() {}").unwrap();
+
+        let first = serde_json::to_string(&tree).unwrap();
+        assert_eq!(serde_json::to_string(&tree).unwrap(), first);
+        let decrypted = crate::encryption::decrypt_content(&identity, &tree[0].content).unwrap();
+        assert_eq!(decrypted, "fn f() {}");
+        assert_eq!(
+            fingerprint(b"fn f() {}"),
+            format!("{:x}", Sha256::digest(b"fn f() {}"))
+        );
+    }
+
+    #[test]
+    fn test_invalid_review_status_fails_with_the_meta_path() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(dir.path().join("f().atom.verilib"), "fn f() {}").unwrap();
+        let meta_path = dir.path().join("f().meta.verilib");
+        fs::write(&meta_path, r#"{"review_status": "done"}"#).unwrap();
+
+        let progress = ProgressBar::hidden();
+        let mut state = ScanState {
+            decision: ChangeDecision::Ask,
+            has_changes: false,
+            recipient: None,
+            progress: &progress,
+            size_limit: None,
+            oversized: Vec::new(),
+            scanner: None,
+            secrets: Vec::new(),
+        };
+        let err = build_tree(dir.path(), dir.path(), &mut state).unwrap_err();
+        assert!(err.to_string().contains(&meta_path.display().to_string()));
+
+        fs::write(&meta_path, r#"{"review_status": "in-review"}"#).unwrap();
+        let tree = build_tree(dir.path(), dir.path(), &mut state).unwrap();
+        assert_eq!(tree[0].review_status, Some(ReviewStatus::InReview));
+    }
+}
@@ -0,0 +1,412 @@
+//! Doctor subcommand implementation.
+//!
+//! Check that the tools, project files and credentials the other commands
+//! need are in place. Every check has a stable ID and a severity, and a
+//! failed check names a remediation by ID, so CI wrappers can act on the
+//! `--json` report (e.g. pull the Docker image) without parsing prose.
+
+use crate::commands::status::get_stored_api_key;
+use crate::config::ProjectConfig;
+use crate::constants::{PROBE_VERUS_MIN_VERSION, PROBE_VERUS_TESTED_MAX_VERSION};
+use crate::executor::{
+    image_present, probe_verus_version, CommandConfig, ExecutionMode, PROBE_REPO_URL,
+};
+use crate::exit::fail;
+use crate::output::{self, Table};
+use anyhow::{Context, Result};
+use semver::VersionReq;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// How much a failed check matters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    /// Commands that need this will fail; `doctor` exits non-zero.
+    Error,
+    /// Commands work, but something is missing or untested.
+    Warning,
+}
+
+/// How to fix a failed check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Remediation {
+    /// Stable ID to branch on, e.g. `pull-docker-image`.
+    pub id: &'static str,
+    pub hint: String,
+    /// A command that applies the fix, when there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Check {
+    /// Stable ID, e.g. `probe-verus-installed`.
+    pub id: &'static str,
+    pub severity: Severity,
+    pub passed: bool,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remediation: Option<Remediation>,
+}
+
+impl Check {
+    fn pass(id: &'static str, severity: Severity, message: impl Into<String>) -> Self {
+        Check {
+            id,
+            severity,
+            passed: true,
+            message: message.into(),
+            remediation: None,
+        }
+    }
+
+    fn fail(
+        id: &'static str,
+        severity: Severity,
+        message: impl Into<String>,
+        remediation: Remediation,
+    ) -> Self {
+        Check {
+            id,
+            severity,
+            passed: false,
+            message: message.into(),
+            remediation: Some(remediation),
+        }
+    }
+
+    /// A failed error-severity check.
+    fn is_error(&self) -> bool {
+        !self.passed && self.severity == Severity::Error
+    }
+}
+
+fn remediation(id: &'static str, hint: impl Into<String>, command: Option<String>) -> Remediation {
+    Remediation {
+        id,
+        hint: hint.into(),
+        command,
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct DoctorReport<'a> {
+    /// No error-severity check failed.
+    ok: bool,
+    checks: &'a [Check],
+}
+
+/// Run the doctor subcommand.
+pub async fn handle_doctor(project_root: PathBuf, json: bool) -> Result<()> {
+    let project_root = project_root
+        .canonicalize()
+        .context("Failed to resolve project root")?;
+
+    let mut project = Vec::new();
+    let config = project_checks(&project_root, &mut project);
+    let cmd_config = config
+        .as_ref()
+        .map(ProjectConfig::command_config)
+        .unwrap_or_default();
+    let mut checks = tool_checks(&cmd_config);
+    checks.extend(project);
+    checks.push(api_key_check());
+
+    let errors = checks.iter().filter(|check| check.is_error()).count();
+    if json {
+        let report = DoctorReport {
+            ok: errors == 0,
+            checks: &checks,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_checks(&checks);
+    }
+
+    if errors > 0 {
+        fail!(
+            CheckFailed,
+            "{} checks failed. See the fixes above.",
+            errors
+        );
+    }
+    Ok(())
+}
+
+/// probe-verus in local mode, Docker and its image in Docker mode.
+fn tool_checks(config: &CommandConfig) -> Vec<Check> {
+    match config.execution_mode {
+        ExecutionMode::Local => probe_verus_checks(),
+        ExecutionMode::Docker => docker_checks(&config.docker_image),
+    }
+}
+
+fn probe_verus_checks() -> Vec<Check> {
+    let install = remediation(
+        "install-probe-verus",
+        format!("Install probe-verus from {}", PROBE_REPO_URL),
+        Some(format!("cargo install --locked --git {}", PROBE_REPO_URL)),
+    );
+    if which::which("probe-verus").is_err() {
+        return vec![Check::fail(
+            "probe-verus-installed",
+            Severity::Error,
+            "probe-verus is not in PATH",
+            install,
+        )];
+    }
+    let installed = Check::pass(
+        "probe-verus-installed",
+        Severity::Error,
+        "probe-verus is in PATH",
+    );
+    let version = match probe_verus_version() {
+        Ok(version) => version,
+        Err(e) => {
+            return vec![
+                installed,
+                Check::fail(
+                    "probe-verus-version",
+                    Severity::Error,
+                    format!("{:#}", e),
+                    install,
+                ),
+            ]
+        }
+    };
+
+    let update = remediation(
+        "update-probe-verus",
+        format!(
+            "Install a probe-verus version matching {} from {}",
+            PROBE_VERUS_MIN_VERSION, PROBE_REPO_URL
+        ),
+        Some(format!(
+            "cargo install --locked --force --git {}",
+            PROBE_REPO_URL
+        )),
+    );
+    let min_req = VersionReq::parse(PROBE_VERUS_MIN_VERSION)
+        .expect("PROBE_VERUS_MIN_VERSION is a valid semver requirement");
+    let tested_req = VersionReq::parse(PROBE_VERUS_TESTED_MAX_VERSION)
+        .expect("PROBE_VERUS_TESTED_MAX_VERSION is a valid semver requirement");
+    let supported = if min_req.matches(&version) {
+        Check::pass(
+            "probe-verus-version",
+            Severity::Error,
+            format!(
+                "probe-verus {} matches {}",
+                version, PROBE_VERUS_MIN_VERSION
+            ),
+        )
+    } else {
+        Check::fail(
+            "probe-verus-version",
+            Severity::Error,
+            format!(
+                "probe-verus {} is older than required ({})",
+                version, PROBE_VERUS_MIN_VERSION
+            ),
+            update,
+        )
+    };
+    let tested = if tested_req.matches(&version) {
+        Check::pass(
+            "probe-verus-tested",
+            Severity::Warning,
+            format!("probe-verus {} has been tested", version),
+        )
+    } else {
+        Check::fail(
+            "probe-verus-tested",
+            Severity::Warning,
+            format!(
+                "probe-verus {} has not been tested with this version of verilib-cli ({})",
+                version, PROBE_VERUS_TESTED_MAX_VERSION
+            ),
+            remediation(
+                "report-untested-probe-verus",
+                format!("If you hit problems, file an issue at {}", PROBE_REPO_URL),
+                None,
+            ),
+        )
+    };
+    vec![installed, supported, tested]
+}
+
+fn docker_checks(image: &str) -> Vec<Check> {
+    if which::which("docker").is_err() {
+        return vec![Check::fail(
+            "docker-installed",
+            Severity::Error,
+            "docker is not in PATH, but execution-mode is docker",
+            remediation(
+                "install-docker",
+                "Install Docker: https://docs.docker.com/get-docker/",
+                None,
+            ),
+        )];
+    }
+    let installed = Check::pass("docker-installed", Severity::Error, "docker is in PATH");
+    // Commands pull a missing image themselves, so this is only a warning.
+    let pulled = if image_present(image) {
+        Check::pass(
+            "docker-image",
+            Severity::Warning,
+            format!("{} is available locally", image),
+        )
+    } else {
+        Check::fail(
+            "docker-image",
+            Severity::Warning,
+            format!(
+                "{} has not been pulled; the next command will pull it",
+                image
+            ),
+            remediation(
+                "pull-docker-image",
+                format!("Pull {}", image),
+                Some(format!("docker pull --platform linux/amd64 {}", image)),
+            ),
+        )
+    };
+    vec![installed, pulled]
+}
+
+/// Check config.json and atoms.json, returning the config when it parses.
+fn project_checks(project_root: &Path, checks: &mut Vec<Check>) -> Option<ProjectConfig> {
+    let config_path = project_root.join(".verilib").join("config.json");
+    if !config_path.exists() {
+        checks.push(Check::fail(
+            "project-config",
+            Severity::Warning,
+            format!("{} not found", config_path.display()),
+            remediation(
+                "create-project",
+                "Run 'verilib-cli create', or 'verilib-cli atomize' in a project without Verus",
+                Some("verilib-cli create".to_string()),
+            ),
+        ));
+        return None;
+    }
+    let config = match ProjectConfig::open(project_root) {
+        Ok(config) => config,
+        Err(e) => {
+            checks.push(Check::fail(
+                "project-config",
+                Severity::Error,
+                format!("{:#}", e),
+                remediation(
+                    "fix-config",
+                    format!("Fix or remove {}", config_path.display()),
+                    None,
+                ),
+            ));
+            return None;
+        }
+    };
+    checks.push(Check::pass(
+        "project-config",
+        Severity::Error,
+        format!("{} is valid", config_path.display()),
+    ));
+
+    let atoms_path = config.atoms_path();
+    checks.push(if atoms_path.exists() {
+        Check::pass(
+            "atoms-json",
+            Severity::Warning,
+            format!("{} exists", atoms_path.display()),
+        )
+    } else {
+        Check::fail(
+            "atoms-json",
+            Severity::Warning,
+            format!("{} not found", atoms_path.display()),
+            remediation(
+                "run-atomize",
+                "Run 'verilib-cli atomize'",
+                Some("verilib-cli atomize".to_string()),
+            ),
+        )
+    });
+    Some(config)
+}
+
+fn api_key_check() -> Check {
+    match get_stored_api_key() {
+        Ok(_) => Check::pass("api-key", Severity::Warning, "An API key is stored"),
+        Err(e) => Check::fail(
+            "api-key",
+            Severity::Warning,
+            format!("No API key is stored: {:#}", e),
+            remediation(
+                "run-auth",
+                "Run 'verilib-cli auth' before deploy and api commands",
+                Some("verilib-cli auth".to_string()),
+            ),
+        ),
+    }
+}
+
+fn print_checks(checks: &[Check]) {
+    let mut table = Table::new(["Check", "Result", "Detail"]);
+    for check in checks {
+        let result = match (check.passed, check.severity) {
+            (true, _) => "ok",
+            (false, Severity::Error) => "error",
+            (false, Severity::Warning) => "warning",
+        };
+        table.row([check.id, result, check.message.as_str()]);
+    }
+    table.print();
+
+    for check in checks.iter().filter(|check| !check.passed) {
+        let Some(fix) = &check.remediation else {
+            continue;
+        };
+        match &fix.command {
+            Some(command) => println!("{}: {} ({})", check.id, fix.hint, command),
+            None => println!("{}: {}", check.id, fix.hint),
+        }
+    }
+    if checks.iter().all(|check| check.passed) {
+        output::success("All checks passed.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_has_stable_ids_and_remediations() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut checks = Vec::new();
+        assert!(project_checks(dir.path(), &mut checks).is_none());
+        assert_eq!(checks.len(), 1);
+        assert!(!checks[0].is_error());
+
+        std::fs::create_dir(dir.path().join(".verilib")).unwrap();
+        std::fs::write(dir.path().join(".verilib/config.json"), "{}").unwrap();
+        let mut checks = Vec::new();
+        assert!(project_checks(dir.path(), &mut checks).is_some());
+        let report = serde_json::to_value(&checks).unwrap();
+        assert_eq!(report[0]["id"], "project-config");
+        assert_eq!(report[0]["passed"], true);
+        assert!(report[0].get("remediation").is_none());
+        assert_eq!(report[1]["id"], "atoms-json");
+        assert_eq!(report[1]["severity"], "warning");
+        assert_eq!(report[1]["remediation"]["id"], "run-atomize");
+        assert_eq!(report[1]["remediation"]["command"], "verilib-cli atomize");
+
+        std::fs::write(dir.path().join(".verilib/config.json"), "{").unwrap();
+        let mut checks = Vec::new();
+        assert!(project_checks(dir.path(), &mut checks).is_none());
+        assert!(checks[0].is_error());
+        assert_eq!(checks[0].remediation.as_ref().unwrap().id, "fix-config");
+    }
+}
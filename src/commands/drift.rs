@@ -0,0 +1,240 @@
+//! Drift subcommand implementation.
+//!
+//! List functions whose code changed after they last verified or after their
+//! spec was certified, by comparing the current code-text with the hash
+//! `verify` recorded in verified.json and the hash in the spec cert. Their
+//! proofs and certs may be stale. `--check` fails when any are found, to gate
+//! pull requests in CI.
+
+use crate::config::{resolve_source_roots, ProjectConfig};
+use crate::exit::fail;
+use crate::output::{self, Table};
+use crate::structure::certs::SpecSource;
+use crate::structure::schema::load_map;
+use crate::structure::verified::load_verified;
+use crate::structure::StubEntry;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// What a function's code changed since.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DriftKind {
+    /// The hash `verify` recorded in verified.json.
+    Verified,
+    /// The hash recorded in the spec cert.
+    Certified,
+}
+
+impl DriftKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            DriftKind::Verified => "verified",
+            DriftKind::Certified => "certified",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Drift {
+    pub code_name: String,
+    /// The stub's structure file.
+    pub file: String,
+    pub since: DriftKind,
+    /// When the function verified or was certified with the old code.
+    pub recorded_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+struct DriftReport<'a> {
+    drift: &'a [Drift],
+    /// Recorded hashes whose function's code could not be read.
+    unchecked: usize,
+}
+
+/// Run the drift subcommand.
+pub async fn handle_drift(project_root: PathBuf, check: bool, json: bool) -> Result<()> {
+    let project_root = project_root
+        .canonicalize()
+        .context("Failed to resolve project root")?;
+    let config = ProjectConfig::open(&project_root)?;
+
+    let stubs_path = config.stubs_path();
+    if !stubs_path.exists() {
+        fail!(
+            MissingPrerequisite,
+            "{} not found. Run 'verilib-cli atomize' first.",
+            stubs_path.display()
+        );
+    }
+    let stubs: HashMap<String, StubEntry> = load_map(&stubs_path)?;
+    let source_roots = resolve_source_roots(&project_root, &config.source_roots);
+    let source = SpecSource {
+        project_root: &project_root,
+        source_roots: &source_roots,
+    };
+
+    let mut recorded: Vec<(&str, DriftKind, DateTime<Utc>, String)> = Vec::new();
+    let verified = load_verified(&config.verified_path())?;
+    for (code_name, entry) in &verified {
+        recorded.push((
+            code_name,
+            DriftKind::Verified,
+            entry.verified_at,
+            entry.code_hash.clone(),
+        ));
+    }
+    let certs = config.spec_certs().entries()?;
+    for entry in &certs {
+        if let (Some(code_name), Some(cert)) = (&entry.code_name, &entry.cert) {
+            if let Some(code_hash) = &cert.code_hash {
+                recorded.push((
+                    code_name,
+                    DriftKind::Certified,
+                    cert.timestamp,
+                    code_hash.clone(),
+                ));
+            }
+        }
+    }
+
+    let (drift, unchecked) = find_drift(&stubs, &recorded, &source);
+    if json {
+        let report = DriftReport {
+            drift: &drift,
+            unchecked,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_drift(&drift, unchecked);
+    }
+
+    if check && !drift.is_empty() {
+        fail!(
+            CheckFailed,
+            "{} functions changed since they were verified or certified. Re-run 'verify', \
+             and review the specs of changed functions and certify them again with 'specify'.",
+            drift.len()
+        );
+    }
+    Ok(())
+}
+
+/// Compare each recorded `(code-name, kind, when, hash)` with the current
+/// code of the stub of that code-name. Records of functions that are gone are
+/// left to `prune` and `verify-certs`. Returns the drift, sorted, and the
+/// number of records whose code could not be read.
+fn find_drift(
+    stubs: &HashMap<String, StubEntry>,
+    recorded: &[(&str, DriftKind, DateTime<Utc>, String)],
+    source: &SpecSource,
+) -> (Vec<Drift>, usize) {
+    let by_code_name: HashMap<&str, (&String, &StubEntry)> = stubs
+        .iter()
+        .filter_map(|(file, stub)| Some((stub.code_name.as_deref()?, (file, stub))))
+        .collect();
+
+    let mut drift = Vec::new();
+    let mut unchecked = 0;
+    let mut current: HashMap<&str, Option<String>> = HashMap::new();
+    for (code_name, since, recorded_at, code_hash) in recorded {
+        let Some((file, stub)) = by_code_name.get(code_name) else {
+            continue;
+        };
+        let hash = current
+            .entry(code_name)
+            .or_insert_with(|| source.code_hash(stub));
+        match hash {
+            Some(hash) if hash != code_hash => drift.push(Drift {
+                code_name: code_name.to_string(),
+                file: file.to_string(),
+                since: *since,
+                recorded_at: *recorded_at,
+            }),
+            Some(_) => {}
+            None => unchecked += 1,
+        }
+    }
+
+    drift.sort_by(|a, b| (&a.code_name, a.since).cmp(&(&b.code_name, b.since)));
+    (drift, unchecked)
+}
+
+fn print_drift(drift: &[Drift], unchecked: usize) {
+    if drift.is_empty() {
+        output::success("No function changed since it was verified or certified.");
+    } else {
+        let mut table = Table::new(["Function", "Changed since", "Recorded", "File"]);
+        for entry in drift {
+            let recorded_at = entry.recorded_at.format("%Y-%m-%d %H:%M").to_string();
+            table.row([
+                entry.code_name.as_str(),
+                entry.since.as_str(),
+                recorded_at.as_str(),
+                entry.file.as_str(),
+            ]);
+        }
+        table.print();
+    }
+    if unchecked > 0 {
+        output::warn(format!(
+            "{} recorded functions have an unreadable source and were not compared.",
+            unchecked
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::schema::LineRange;
+
+    #[test]
+    fn test_drift_compares_recorded_hashes_with_current_code() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+        let source = SpecSource {
+            project_root: dir.path(),
+            source_roots: &[],
+        };
+        let stub = |name: &str, line: u32| StubEntry {
+            code_name: Some(name.to_string()),
+            code_path: Some("lib.rs".to_string()),
+            code_text: Some(LineRange {
+                lines_start: line,
+                lines_end: line,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let stubs = HashMap::from([
+            ("a.md".to_string(), stub("a", 1)),
+            ("b.md".to_string(), stub("b", 2)),
+        ]);
+        let hash = |name: &str| source.code_hash(&stubs[&format!("{}.md", name)]).unwrap();
+        let now = Utc::now();
+        let recorded = vec![
+            ("a", DriftKind::Verified, now, hash("a")),
+            ("b", DriftKind::Certified, now, hash("b")),
+            ("b", DriftKind::Verified, now, hash("b")),
+            ("gone", DriftKind::Verified, now, "x".to_string()),
+        ];
+        assert_eq!(find_drift(&stubs, &recorded, &source), (vec![], 0));
+
+        std::fs::write(dir.path().join("lib.rs"), "fn a() {}\nfn b() { 1 }\n").unwrap();
+        let (drift, unchecked) = find_drift(&stubs, &recorded, &source);
+        let found: Vec<(&str, DriftKind)> = drift
+            .iter()
+            .map(|d| (d.code_name.as_str(), d.since))
+            .collect();
+        assert_eq!(
+            found,
+            vec![("b", DriftKind::Verified), ("b", DriftKind::Certified)]
+        );
+        assert_eq!(unchecked, 0);
+    }
+}
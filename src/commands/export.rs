@@ -0,0 +1,180 @@
+//! Export subcommand implementation.
+//!
+//! Every format exports from the same [`ExportContext`]: the project config
+//! and its enriched stubs, or its atoms in atoms-only projects. `scip`
+//! writes stubs and their dependency edges for code-intelligence tools, with
+//! reviewer notes from `api annotate` in each symbol's hover text; `vbom`
+//! writes a bill of verification (see [`export_vbom`]). A new format is a
+//! module with a `write` function and an [`ExportFormat`] variant.
+//!
+//! With a signing key, the written file gets a detached SSH signature next
+//! to it, made by `ssh-keygen -Y sign`.
+
+use crate::commands::export_vbom;
+use crate::commands::stats::read_atoms;
+use crate::config::ProjectConfig;
+use crate::exit::{fail, ExitContext, ExitKind};
+use crate::freshness::{self, Artifact};
+use crate::output;
+use crate::query::Query;
+use crate::structure::schema::load_map;
+use crate::structure::{annotations, scip, Atom, StubEntry};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Namespace of export signatures, for `ssh-keygen -Y verify -n`.
+pub const SIGNATURE_NAMESPACE: &str = "verilib-export";
+
+/// What `export` writes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// SCIP index of the stubs and their dependency edges.
+    #[default]
+    Scip,
+    /// Bill of verification: functions, spec hashes, proof results, tool
+    /// versions and source commit, as JSON.
+    Vbom,
+}
+
+/// Options for the export subcommand.
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub output: PathBuf,
+    /// SSH key to sign the output with.
+    pub sign_key: Option<PathBuf>,
+    /// Only functions matching this expression are exported.
+    pub filter: Option<Query>,
+}
+
+/// What every format exports from.
+pub struct ExportContext {
+    pub project_root: PathBuf,
+    pub config: ProjectConfig,
+    /// stubs.json, or each atom as the stub it would match in atoms-only
+    /// projects.
+    pub stubs: HashMap<String, StubEntry>,
+    /// "stubs" or "atoms", for messages.
+    pub kind: &'static str,
+}
+
+impl ExportContext {
+    pub fn load(project_root: &Path) -> Result<Self> {
+        let project_root = project_root
+            .canonicalize()
+            .context("Failed to resolve project root")?;
+        let config = ProjectConfig::open(&project_root)?;
+        freshness::warn_stale(&config, &Artifact::ALL);
+
+        let (stubs, kind) = if config.is_atoms_only() {
+            let stubs = read_atoms(&config.atoms_path())?
+                .into_iter()
+                .map(|(name, atom)| {
+                    let stub = stub_from_atom(&name, atom);
+                    (name, stub)
+                })
+                .collect();
+            (stubs, "atoms")
+        } else {
+            let stubs_path = config.stubs_path();
+            if !stubs_path.exists() {
+                fail!(
+                    MissingPrerequisite,
+                    "stubs.json not found at {}. Run 'verilib-cli atomize' first.",
+                    stubs_path.display()
+                );
+            }
+            let stubs: HashMap<String, StubEntry> = load_map(&stubs_path)?;
+            (stubs, "stubs")
+        };
+        Ok(Self {
+            project_root,
+            config,
+            stubs,
+            kind,
+        })
+    }
+}
+
+/// Run the export subcommand.
+pub async fn handle_export(project_root: PathBuf, options: ExportOptions) -> Result<()> {
+    let mut context = ExportContext::load(&project_root)?;
+    if let Some(query) = &options.filter {
+        context.stubs.retain(|_, stub| query.matches(stub));
+    }
+    match options.format {
+        ExportFormat::Scip => write_scip(&context, &options.output)?,
+        ExportFormat::Vbom => export_vbom::write(&context, &options.output)?,
+    }
+
+    match &options.sign_key {
+        Some(key) => {
+            let signature = sign(&options.output, key)?;
+            println!("Signed with {} to {}", key.display(), signature.display());
+        }
+        None if options.format == ExportFormat::Vbom => {
+            output::warn("the bill of verification is unsigned; pass --sign-key to sign it");
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+fn write_scip(context: &ExportContext, scip_path: &Path) -> Result<()> {
+    let stubs = &context.stubs;
+    let notes = annotations::load(&context.config.verilib_path());
+    let (index, exported) = scip::index_from_stubs(stubs, &notes, &context.project_root);
+    scip::write_index(&index, scip_path)?;
+
+    println!(
+        "Exported {} of {} {} to {}",
+        exported,
+        stubs.len(),
+        context.kind,
+        scip_path.display()
+    );
+    if exported < stubs.len() && context.config.is_atoms_only() {
+        println!("Atoms without a code-path or code-text were skipped.");
+    } else if exported < stubs.len() {
+        println!("Stubs without a code-name or code-text were skipped; run 'verilib-cli atomize' to enrich them.");
+    }
+    Ok(())
+}
+
+/// Sign `path` with the SSH key `key`, writing `<path>.sig`.
+fn sign(path: &Path, key: &Path) -> Result<PathBuf> {
+    let output = Command::new("ssh-keygen")
+        .args(["-Y", "sign", "-n", SIGNATURE_NAMESPACE, "-f"])
+        .arg(key)
+        .arg(path)
+        .output()
+        .context("Failed to run ssh-keygen; it is needed for --sign-key")
+        .exit_kind(ExitKind::MissingPrerequisite)?;
+    if !output.status.success() {
+        fail!(
+            ExternalTool,
+            "ssh-keygen could not sign {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let mut signature = path.as_os_str().to_owned();
+    signature.push(".sig");
+    Ok(PathBuf::from(signature))
+}
+
+/// An atom as the enriched stub it would match.
+fn stub_from_atom(code_name: &str, atom: Atom) -> StubEntry {
+    StubEntry {
+        code_name: Some(code_name.to_string()),
+        code_path: atom.code_path,
+        code_text: atom.code_text,
+        code_module: atom.code_module,
+        dependencies: Some(atom.dependencies),
+        display_name: atom.display_name,
+        ..StubEntry::default()
+    }
+}
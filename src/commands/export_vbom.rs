@@ -0,0 +1,243 @@
+//! Bill of verification (`export --format vbom`).
+//!
+//! A CycloneDX-style JSON manifest of what is verified at which commit with
+//! which tools: one component per function with its spec hash, cert and
+//! proof result, the source commit, the tool versions and the SHA-256 of
+//! each artifact it was built from.
+
+use crate::commands::export::ExportContext;
+use crate::commands::specify::{spec_statuses, SpecHashStatus};
+use crate::config::resolve_source_roots;
+use crate::environment::Environment;
+use crate::output;
+use crate::structure::certs::SpecSource;
+use crate::structure::schema::load_map;
+use crate::structure::{ExecutionMode, ProofResult};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+/// `bomFormat` of every bill of verification.
+pub const BOM_FORMAT: &str = "VeriLib-VBOM";
+
+/// Bumped when a field changes meaning or goes away.
+pub const SPEC_VERSION: &str = "1.0";
+
+/// Artifacts whose hashes are recorded, under `.verilib`.
+const ARTIFACTS: &[&str] = &["atoms.json", "stubs.json", "specs.json", "proofs.json"];
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Vbom {
+    pub bom_format: &'static str,
+    pub spec_version: &'static str,
+    pub metadata: Metadata,
+    pub components: Vec<Component>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Metadata {
+    pub timestamp: DateTime<Utc>,
+    pub tools: Vec<Tool>,
+    pub execution_mode: ExecutionMode,
+    pub source: Source,
+    pub artifacts: Vec<ArtifactHash>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Tool {
+    pub name: String,
+    /// First line of its `--version`, or the image digest in docker mode.
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Source {
+    /// Project directory name.
+    pub name: String,
+    /// `HEAD` of the project's git repository; absent outside one.
+    pub commit: Option<String>,
+    /// Whether the working tree had uncommitted changes.
+    pub dirty: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArtifactHash {
+    pub name: String,
+    pub sha256: String,
+}
+
+/// One function.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Component {
+    /// The function's code-name, or its stub when it has none.
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: String,
+    pub name: Option<String>,
+    pub stub: String,
+    pub code_path: Option<String>,
+    pub specified: bool,
+    /// SHA-256 of the spec lines as they are now.
+    pub spec_hash: Option<String>,
+    pub certified: bool,
+    pub cert_timestamp: Option<DateTime<Utc>>,
+    /// How `specHash` compares with the hash the cert recorded.
+    pub cert_spec_hash: Option<SpecHashStatus>,
+    /// From proofs.json; absent when verify has no result for it.
+    pub proof: Option<Proof>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Proof {
+    pub verified: bool,
+    pub status: Option<String>,
+    pub verify_profile: Option<String>,
+}
+
+/// Write the bill of verification of `context` to `path`.
+pub fn write(context: &ExportContext, path: &Path) -> Result<()> {
+    let vbom = build(context)?;
+    let content = serde_json::to_string_pretty(&vbom)? + "\n";
+    crate::interrupt::write_atomic(path, content)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let verified = vbom
+        .components
+        .iter()
+        .filter(|c| c.proof.as_ref().is_some_and(|p| p.verified))
+        .count();
+    println!(
+        "Exported {} functions ({} verified) to {}",
+        vbom.components.len(),
+        verified,
+        path.display()
+    );
+    if vbom.metadata.source.dirty {
+        output::warn(
+            "the working tree has uncommitted changes; the commit alone doesn't reproduce this export",
+        );
+    }
+    Ok(())
+}
+
+pub fn build(context: &ExportContext) -> Result<Vbom> {
+    let config = &context.config;
+    let source_roots = resolve_source_roots(&context.project_root, &config.source_roots);
+    let spec_source = SpecSource {
+        project_root: &context.project_root,
+        source_roots: &source_roots,
+    };
+    let proofs_path = config.verilib_path().join("proofs.json");
+    let proofs: HashMap<String, ProofResult> = if proofs_path.exists() {
+        load_map(&proofs_path)?
+    } else {
+        HashMap::new()
+    };
+
+    let cert_store = config.spec_certs();
+    let components = spec_statuses(&context.stubs, cert_store.as_ref(), &spec_source)?
+        .into_iter()
+        .map(|status| {
+            let stub = &context.stubs[&status.stub];
+            let proof = status
+                .code_name
+                .as_deref()
+                .and_then(|name| proofs.get(name))
+                .map(|proof| Proof {
+                    verified: proof.verified,
+                    status: proof.status.clone(),
+                    verify_profile: proof.verify_profile.clone(),
+                });
+            Component {
+                bom_ref: status.code_name.clone().unwrap_or(status.stub.clone()),
+                name: status.display_name,
+                code_path: stub.code_path.clone(),
+                specified: status.specified,
+                spec_hash: spec_source.hash(stub),
+                certified: status.certified,
+                cert_timestamp: status.cert_timestamp,
+                cert_spec_hash: status.spec_hash,
+                proof,
+                stub: status.stub,
+            }
+        })
+        .collect();
+
+    let environment = Environment::capture(&config.command_config());
+    let mut tools = vec![Tool {
+        name: "verilib-cli".to_string(),
+        version: Some(environment.verilib_cli.clone()),
+    }];
+    tools.extend(
+        environment
+            .tools
+            .iter()
+            .filter(|(_, version)| version.is_some())
+            .map(|(name, version)| Tool {
+                name: name.clone(),
+                version: version.clone(),
+            }),
+    );
+    if let Some(image) = &environment.docker_image {
+        tools.push(Tool {
+            name: image.clone(),
+            version: environment.docker_digest.clone(),
+        });
+    }
+
+    let artifacts = ARTIFACTS
+        .iter()
+        .filter_map(|name| {
+            let content = std::fs::read(config.verilib_path().join(name)).ok()?;
+            Some(ArtifactHash {
+                name: name.to_string(),
+                sha256: format!("{:x}", Sha256::digest(content)),
+            })
+        })
+        .collect();
+
+    Ok(Vbom {
+        bom_format: BOM_FORMAT,
+        spec_version: SPEC_VERSION,
+        metadata: Metadata {
+            timestamp: Utc::now(),
+            tools,
+            execution_mode: environment.execution_mode.clone(),
+            source: source(&context.project_root),
+            artifacts,
+        },
+        components,
+    })
+}
+
+fn source(project_root: &Path) -> Source {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .arg("-C")
+            .arg(project_root)
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let commit = git(&["rev-parse", "HEAD"]);
+    let dirty = commit.is_some()
+        && git(&["status", "--porcelain"]).is_some_and(|status| !status.is_empty());
+    Source {
+        name: project_root
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        commit,
+        dirty,
+    }
+}
@@ -0,0 +1,200 @@
+//! Flag-log subcommand implementation.
+//!
+//! Every change to a meta file flag (`specified`, `ignored`, `verified`,
+//! `review_status`) made by `api set`, `api batch` or `sync-flags` is
+//! appended to `.verilib/flag_changes.jsonl` with who made it, when, and
+//! with which command, so audits can tell when `verified` was toggled and by
+//! whom. `flag-log show` reads it back.
+
+use crate::commands::history::current_user;
+use crate::output::{self, Table};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+/// Local log of flag changes, one JSON object per line.
+pub const FLAG_LOG: &str = "flag_changes.jsonl";
+
+/// Where `sync-flags` changed a flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FlagSide {
+    Local,
+    Server,
+}
+
+/// One change recorded in `.verilib/flag_changes.jsonl`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FlagChange {
+    pub timestamp: DateTime<Utc>,
+    /// Empty in entries written before users were recorded.
+    #[serde(default)]
+    pub user: String,
+    /// The command that made the change, e.g. `api set`.
+    #[serde(default)]
+    pub command: String,
+    /// The meta file, as the command was given or found it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_name: Option<String>,
+    pub flag: String,
+    pub from: Value,
+    pub to: Value,
+    /// The side `sync-flags` changed; absent for local edits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub side: Option<FlagSide>,
+}
+
+impl FlagChange {
+    /// A change of `flag` made now by the current user with `command`.
+    pub fn new(command: &str, flag: &str, from: impl Into<Value>, to: impl Into<Value>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            user: current_user(),
+            command: command.to_string(),
+            file: None,
+            identifier: None,
+            code_name: None,
+            flag: flag.to_string(),
+            from: from.into(),
+            to: to.into(),
+            side: None,
+        }
+    }
+
+    /// Whether this change is to the meta file `file`, or to the atom with
+    /// that identifier.
+    fn concerns(&self, file: &str) -> bool {
+        let file = normalize(file);
+        self.file.as_deref().map(normalize) == Some(file)
+            || self.identifier.as_deref() == Some(file)
+    }
+}
+
+fn normalize(path: &str) -> &str {
+    path.trim_start_matches("./").trim_start_matches('/')
+}
+
+/// Append `changes` to the flag log of the `.verilib` directory at `verilib_path`.
+pub fn append(verilib_path: &Path, changes: &[FlagChange]) -> Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let path = verilib_path.join(FLAG_LOG);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    for change in changes {
+        writeln!(file, "{}", serde_json::to_string(change)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Read all entries, oldest first. Malformed lines are skipped with a warning.
+pub fn read(verilib_path: &Path) -> Result<Vec<FlagChange>> {
+    let path = verilib_path.join(FLAG_LOG);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(i, line)| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                output::warn(format!("{}:{}: {}", path.display(), i + 1, e));
+                None
+            }
+        })
+        .collect())
+}
+
+/// Run `flag-log show`: the latest `limit` changes, of `file` only if given.
+pub async fn handle_flag_log_show(file: Option<String>, limit: usize, json: bool) -> Result<()> {
+    let entries: Vec<FlagChange> = read(Path::new(".verilib"))?
+        .into_iter()
+        .filter(|entry| file.as_deref().is_none_or(|file| entry.concerns(file)))
+        .collect();
+    let shown = &entries[entries.len().saturating_sub(limit)..];
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(shown)?);
+        return Ok(());
+    }
+
+    if shown.is_empty() {
+        println!("No flag changes recorded in .verilib/{}", FLAG_LOG);
+        return Ok(());
+    }
+
+    let mut table = Table::new(["Time", "User", "Command", "File", "Flag", "Change"]);
+    for entry in shown {
+        table.row([
+            entry.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+            if entry.user.is_empty() {
+                "-".to_string()
+            } else {
+                entry.user.clone()
+            },
+            match entry.side {
+                Some(FlagSide::Server) => format!("{} (server)", entry.command),
+                _ => entry.command.clone(),
+            },
+            entry
+                .file
+                .clone()
+                .or_else(|| entry.identifier.clone())
+                .unwrap_or_default(),
+            entry.flag.clone(),
+            format!("{} -> {}", entry.from, entry.to),
+        ]);
+    }
+    table.print();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_back_old_and_new_entries() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join(FLAG_LOG),
+            r#"{"timestamp":"2026-01-02T03:04:05Z","identifier":"src/f()","flag":"specified","from":false,"to":true,"side":"local"}
+not json
+"#,
+        )
+        .unwrap();
+        let mut change = FlagChange::new("api set", "review_status", Value::Null, "approved");
+        change.file = Some(".verilib/src/[1] - f().meta.verilib".to_string());
+        append(dir.path(), &[change.clone()]).unwrap();
+
+        let entries = read(dir.path()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].user, "");
+        assert_eq!(entries[0].side, Some(FlagSide::Local));
+        assert_eq!(entries[1], change);
+
+        assert!(entries[0].concerns("src/f()"));
+        assert!(entries[1].concerns("./.verilib/src/[1] - f().meta.verilib"));
+        assert!(!entries[1].concerns("src/f()"));
+    }
+}
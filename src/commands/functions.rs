@@ -0,0 +1,291 @@
+//! Functions subcommand implementation.
+//!
+//! `functions list` answers questions such as "which functions in module X
+//! are specified but not verified" from stubs.json, without a server or a
+//! probe-verus run. Filters combine; `--json` prints the matching entries
+//! as they are in stubs.json, with the stub path added.
+
+use crate::config::ProjectConfig;
+use crate::exit::fail;
+use crate::freshness::{self, Artifact};
+use crate::output::Table;
+use crate::query::Query;
+use crate::structure::schema::load_map;
+use crate::structure::StubEntry;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use glob::Pattern;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Order of the listed functions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum FunctionSort {
+    /// By code-path, then code-line
+    #[default]
+    Path,
+    /// By display name
+    Name,
+    /// By code-module, then code-path
+    Module,
+}
+
+/// Filters and order for `functions list`.
+#[derive(Debug, Clone, Default)]
+pub struct FunctionsListOptions {
+    /// A code-module; its submodules match too.
+    pub module: Option<String>,
+    pub path_glob: Option<String>,
+    pub specified: Option<bool>,
+    pub verified: Option<bool>,
+    /// Only functions `verify` reported a failure for.
+    pub failed: bool,
+    pub filter: Option<Query>,
+    pub sort: FunctionSort,
+}
+
+#[derive(Debug, Serialize)]
+struct Function<'a> {
+    stub: &'a str,
+    #[serde(flatten)]
+    entry: &'a StubEntry,
+}
+
+/// Run `functions list`.
+pub async fn handle_functions_list(
+    project_root: PathBuf,
+    options: FunctionsListOptions,
+    json: bool,
+) -> Result<()> {
+    let project_root = project_root
+        .canonicalize()
+        .context("Failed to resolve project root")?;
+    let config = ProjectConfig::open(&project_root)?;
+    freshness::warn_stale(&config, &Artifact::ALL);
+
+    let stubs_path = config.stubs_path();
+    if !stubs_path.exists() {
+        fail!(
+            MissingPrerequisite,
+            "stubs.json not found at {}. Run 'verilib-cli atomize' first.",
+            stubs_path.display()
+        );
+    }
+    let stubs: HashMap<String, StubEntry> = load_map(&stubs_path)?;
+    let functions = list(&stubs, &options)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&functions)?);
+        return Ok(());
+    }
+    if functions.is_empty() {
+        println!("No functions match.");
+        return Ok(());
+    }
+
+    let mut table = Table::new(["FUNCTION", "MODULE", "PATH", "SPECIFIED", "VERIFIED"]);
+    for function in &functions {
+        let entry = function.entry;
+        let path = match (&entry.code_path, line(entry)) {
+            (Some(path), Some(line)) => format!("{}:{}", path, line),
+            (Some(path), None) => path.clone(),
+            (None, _) => "-".to_string(),
+        };
+        table.row([
+            name(function).to_string(),
+            entry.code_module.clone().unwrap_or_else(|| "-".to_string()),
+            path,
+            yes_no(entry.specified).to_string(),
+            verified(entry).to_string(),
+        ]);
+    }
+    table.print();
+    println!("{} of {} functions listed.", functions.len(), stubs.len());
+    Ok(())
+}
+
+/// The stubs matching `options`, in its order.
+fn list<'a>(
+    stubs: &'a HashMap<String, StubEntry>,
+    options: &FunctionsListOptions,
+) -> Result<Vec<Function<'a>>> {
+    let path_glob = options
+        .path_glob
+        .as_deref()
+        .map(Pattern::new)
+        .transpose()
+        .context("Invalid --path-glob")?;
+
+    let mut functions: Vec<Function> = stubs
+        .iter()
+        .filter(|(_, entry)| {
+            options
+                .module
+                .as_deref()
+                .is_none_or(|module| in_module(entry, module))
+                && path_glob.as_ref().is_none_or(|glob| {
+                    entry
+                        .code_path
+                        .as_deref()
+                        .is_some_and(|path| glob.matches(path))
+                })
+                && options
+                    .specified
+                    .is_none_or(|specified| entry.specified.unwrap_or(false) == specified)
+                && options
+                    .verified
+                    .is_none_or(|verified| entry.verified.unwrap_or(false) == verified)
+                && (!options.failed || entry.status.as_deref() == Some("failure"))
+                && options
+                    .filter
+                    .as_ref()
+                    .is_none_or(|query| query.matches(*entry))
+        })
+        .map(|(stub, entry)| Function { stub, entry })
+        .collect();
+
+    match options.sort {
+        FunctionSort::Path => functions.sort_by_key(|f| location(f)),
+        FunctionSort::Name => functions.sort_by_key(|f| (name(f), f.stub)),
+        FunctionSort::Module => {
+            functions.sort_by_key(|f| (f.entry.code_module.as_deref(), location(f)))
+        }
+    }
+    Ok(functions)
+}
+
+fn location<'a>(function: &Function<'a>) -> (Option<&'a str>, Option<u32>, &'a str) {
+    let entry = function.entry;
+    (entry.code_path.as_deref(), line(entry), function.stub)
+}
+
+/// First line of the function: from atoms.json once enriched, else from
+/// the frontmatter.
+fn line(entry: &StubEntry) -> Option<u32> {
+    entry
+        .code_text
+        .as_ref()
+        .map(|text| text.lines_start)
+        .or(entry.code_line)
+}
+
+fn in_module(entry: &StubEntry, module: &str) -> bool {
+    entry.code_module.as_deref().is_some_and(|m| {
+        m == module
+            || m.strip_prefix(module)
+                .is_some_and(|rest| rest.starts_with("::"))
+    })
+}
+
+/// Display name, falling back to the code-name and then the stub path.
+fn name<'a>(function: &Function<'a>) -> &'a str {
+    let entry = function.entry;
+    entry
+        .display_name
+        .as_deref()
+        .or(entry.code_name.as_deref())
+        .unwrap_or(function.stub)
+}
+
+fn yes_no(flag: Option<bool>) -> &'static str {
+    if flag == Some(true) {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+fn verified(entry: &StubEntry) -> &'static str {
+    if entry.status.as_deref() == Some("failure") {
+        "failed"
+    } else {
+        yes_no(entry.verified)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn stub(module: &str, path: &str, line: u32, specified: bool, verified: bool) -> StubEntry {
+        serde_json::from_value(json!({
+            "code-module": module,
+            "code-path": path,
+            "code-line": line,
+            "specified": specified,
+            "verified": verified,
+        }))
+        .unwrap()
+    }
+
+    fn stubs_of(functions: &[Function]) -> Vec<String> {
+        functions.iter().map(|f| f.stub.to_string()).collect()
+    }
+
+    #[test]
+    fn test_filters_combine_and_sort() {
+        let mut failed = stub("core::field", "src/core/field.rs", 9, true, false);
+        failed.status = Some("failure".to_string());
+        let stubs = HashMap::from([
+            (
+                "b.md".to_string(),
+                stub("core", "src/core/mod.rs", 5, true, true),
+            ),
+            ("a.md".to_string(), failed),
+            (
+                "c.md".to_string(),
+                stub("core::field", "src/core/field.rs", 1, false, false),
+            ),
+            (
+                "d.md".to_string(),
+                stub("coreutils", "src/utils.rs", 1, true, false),
+            ),
+        ]);
+
+        let options = FunctionsListOptions {
+            module: Some("core".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            stubs_of(&list(&stubs, &options).unwrap()),
+            ["c.md", "a.md", "b.md"]
+        );
+
+        let options = FunctionsListOptions {
+            module: Some("core".to_string()),
+            specified: Some(true),
+            verified: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(stubs_of(&list(&stubs, &options).unwrap()), ["a.md"]);
+
+        let options = FunctionsListOptions {
+            path_glob: Some("src/u*.rs".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(stubs_of(&list(&stubs, &options).unwrap()), ["d.md"]);
+
+        let options = FunctionsListOptions {
+            failed: true,
+            ..Default::default()
+        };
+        assert_eq!(stubs_of(&list(&stubs, &options).unwrap()), ["a.md"]);
+
+        let options = FunctionsListOptions {
+            sort: FunctionSort::Name,
+            ..Default::default()
+        };
+        assert_eq!(
+            stubs_of(&list(&stubs, &options).unwrap()),
+            ["a.md", "b.md", "c.md", "d.md"]
+        );
+
+        let options = FunctionsListOptions {
+            path_glob: Some("[".to_string()),
+            ..Default::default()
+        };
+        assert!(list(&stubs, &options).is_err());
+    }
+}
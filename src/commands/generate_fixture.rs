@@ -0,0 +1,402 @@
+//! Generate-fixture subcommand implementation.
+//!
+//! Writes a synthetic but internally consistent `.verilib` tree for use in
+//! downstream test suites: atoms.json, specs.json, proofs.json, stubs.json,
+//! structure files, spec certs, and the atom/meta repository tree. Output is
+//! deterministic for a given seed and includes unicode identifiers, deeply
+//! nested modules, and one very large atom.
+
+use crate::commands::types::SnippetKind;
+use crate::config::ProjectConfig;
+use crate::structure::certs::{encode_name, Cert};
+use crate::structure::{write_frontmatter, FrontmatterFormat};
+use anyhow::{bail, Context, Result};
+use chrono::DateTime;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Lines of code in the single oversized atom.
+const BIG_ATOM_LINES: u32 = 5000;
+
+/// Lines of code in every other atom.
+const ATOM_LINES: u32 = 10;
+
+/// Directories used to nest modules; module `m` is nested `m % 5` levels deep.
+const NESTING: &[&str] = &["nested", "deeper", "deepest", "abyss"];
+
+/// Fixed cert timestamp so that fixtures are byte-for-byte reproducible.
+const CERT_TIMESTAMP: i64 = 1_767_225_600; // 2026-01-01T00:00:00Z
+
+/// SplitMix64; small and deterministic, which is all a fixture needs.
+struct FixtureRng(u64);
+
+impl FixtureRng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+struct FixtureModule {
+    name: String,
+    code_path: String,
+    next_line: u32,
+    atom_count: u32,
+}
+
+struct FixtureAtom {
+    display_name: String,
+    code_name: String,
+    module: usize,
+    index: u32,
+    lines_start: u32,
+    lines_end: u32,
+    dependencies: Vec<String>,
+    specified: bool,
+    verified: bool,
+}
+
+/// Options for fixture generation.
+#[derive(Debug, Clone)]
+pub struct FixtureOptions {
+    pub atoms: usize,
+    pub modules: usize,
+    pub failure_rate: f64,
+    pub seed: u64,
+}
+
+/// Run the generate-fixture subcommand.
+pub async fn handle_generate_fixture(
+    output: PathBuf,
+    options: FixtureOptions,
+    force: bool,
+) -> Result<()> {
+    let verilib_path = output.join(".verilib");
+    if verilib_path.exists() && !force {
+        bail!(
+            "{} already exists. Use --force to overwrite it.",
+            verilib_path.display()
+        );
+    }
+
+    let counts = generate_fixture(&output, &options)?;
+
+    println!("Generated fixture in {}", verilib_path.display());
+    println!("  Atoms:     {}", options.atoms);
+    println!("  Modules:   {}", options.modules);
+    println!("  Specified: {}", counts.specified);
+    println!("  Failing:   {}", counts.failing);
+
+    Ok(())
+}
+
+/// Summary of a generated fixture.
+#[derive(Debug, PartialEq, Eq)]
+pub struct FixtureCounts {
+    pub specified: usize,
+    pub failing: usize,
+}
+
+/// Write the fixture under `output/.verilib`.
+pub fn generate_fixture(output: &Path, options: &FixtureOptions) -> Result<FixtureCounts> {
+    if options.atoms == 0 || options.modules == 0 {
+        bail!("--atoms and --modules must be at least 1");
+    }
+    if !(0.0..=1.0).contains(&options.failure_rate) {
+        bail!("--failure-rate must be between 0.0 and 1.0");
+    }
+
+    let mut rng = FixtureRng(options.seed);
+    let mut modules = build_modules(options.modules);
+    let atoms = build_atoms(&mut rng, &mut modules, options);
+
+    let verilib_path = output.join(".verilib");
+    if verilib_path.exists() {
+        std::fs::remove_dir_all(&verilib_path)
+            .with_context(|| format!("Failed to remove {}", verilib_path.display()))?;
+    }
+    std::fs::create_dir_all(&verilib_path).context("Failed to create .verilib directory")?;
+
+    let config = ProjectConfig {
+        structure_root: Some(".verilib/structure".to_string()),
+        ..ProjectConfig::default()
+    };
+    config.save(output)?;
+
+    let mut atoms_json = Map::new();
+    let mut specs_json = Map::new();
+    let mut proofs_json = Map::new();
+    let mut stubs_json = Map::new();
+
+    let certs_dir = verilib_path.join("certs").join("specs");
+    std::fs::create_dir_all(&certs_dir)?;
+    let cert_content = serde_json::to_string_pretty(&Cert {
+        timestamp: DateTime::from_timestamp(CERT_TIMESTAMP, 0).unwrap(),
+        spec_hash: None,
+        code_hash: None,
+    })?;
+
+    for atom in &atoms {
+        let module = &modules[atom.module];
+        let code_text = json!({
+            "lines-start": atom.lines_start,
+            "lines-end": atom.lines_end,
+        });
+
+        atoms_json.insert(
+            atom.code_name.clone(),
+            json!({
+                "display-name": atom.display_name,
+                "dependencies": atom.dependencies,
+                "code-module": module.name,
+                "code-path": module.code_path,
+                "code-text": code_text,
+                "mode": "exec",
+            }),
+        );
+
+        proofs_json.insert(
+            atom.code_name.clone(),
+            json!({
+                "code-path": module.code_path,
+                "code-line": atom.lines_start,
+                "verified": atom.verified,
+                "status": if atom.verified { "success" } else { "failure" },
+            }),
+        );
+
+        if atom.specified {
+            specs_json.insert(
+                atom.code_name.clone(),
+                json!({
+                    "code-path": module.code_path,
+                    "spec-text": {
+                        "lines-start": atom.lines_start.saturating_sub(2).max(1),
+                        "lines-end": atom.lines_end,
+                    },
+                    "context": "standalone",
+                    "specified": true,
+                    "has_requires": atom.index % 2 == 0,
+                    "has_ensures": true,
+                }),
+            );
+            std::fs::write(
+                certs_dir.join(format!("{}.json", encode_name(&atom.code_name))),
+                &cert_content,
+            )?;
+        }
+
+        let stub_key = format!("{}/{}().md", module.code_path, atom.display_name);
+        let mut stub = json!({
+            "code-module": module.name,
+            "code-name": atom.code_name,
+            "code-path": module.code_path,
+            "code-text": code_text,
+            "dependencies": atom.dependencies,
+            "display-name": atom.display_name,
+            "specified": atom.specified,
+            "verified": atom.verified,
+        });
+        if !atom.verified {
+            stub["status"] = json!("failure");
+        }
+        stubs_json.insert(stub_key.clone(), stub);
+
+        let mut frontmatter = HashMap::new();
+        frontmatter.insert("code-name".to_string(), json!(atom.code_name));
+        frontmatter.insert("code-path".to_string(), json!(module.code_path));
+        frontmatter.insert("code-line".to_string(), json!(atom.lines_start));
+        write_frontmatter(
+            &verilib_path.join("structure").join(&stub_key),
+            &frontmatter,
+            None,
+            FrontmatterFormat::default(),
+        )?;
+
+        write_atom_files(&verilib_path, module, atom)?;
+    }
+
+    for (name, value) in [
+        ("atoms.json", atoms_json),
+        ("specs.json", specs_json),
+        ("proofs.json", proofs_json),
+        ("stubs.json", stubs_json),
+    ] {
+        std::fs::write(
+            verilib_path.join(name),
+            serde_json::to_string_pretty(&Value::Object(value))?,
+        )
+        .with_context(|| format!("Failed to write {}", name))?;
+    }
+
+    Ok(FixtureCounts {
+        specified: atoms.iter().filter(|a| a.specified).count(),
+        failing: atoms.iter().filter(|a| !a.verified).count(),
+    })
+}
+
+fn build_modules(count: usize) -> Vec<FixtureModule> {
+    (0..count)
+        .map(|m| {
+            let name = if m % 4 == 3 {
+                format!("módulo_{}", m)
+            } else {
+                format!("mod_{}", m)
+            };
+            let mut segments = vec!["src".to_string()];
+            segments.extend(NESTING[..m % 5].iter().map(|s| s.to_string()));
+            segments.push(format!("{}.rs", name));
+
+            FixtureModule {
+                name,
+                code_path: segments.join("/"),
+                next_line: 1,
+                atom_count: 0,
+            }
+        })
+        .collect()
+}
+
+fn build_atoms(
+    rng: &mut FixtureRng,
+    modules: &mut [FixtureModule],
+    options: &FixtureOptions,
+) -> Vec<FixtureAtom> {
+    let mut atoms: Vec<FixtureAtom> = Vec::with_capacity(options.atoms);
+
+    for i in 0..options.atoms {
+        let module_index = i % modules.len();
+        let module = &mut modules[module_index];
+
+        let display_name = match i % 7 {
+            3 => format!("función_{}", i),
+            5 => format!("計算_{}", i),
+            _ => format!("func_{}", i),
+        };
+        let code_name = format!(
+            "probe:fixture/0.1.0/{}/{}()",
+            module.code_path.trim_end_matches(".rs"),
+            display_name
+        );
+
+        let length = if i == 0 { BIG_ATOM_LINES } else { ATOM_LINES };
+        let lines_start = module.next_line;
+        let lines_end = lines_start + length - 1;
+        module.next_line = lines_end + 3;
+
+        let index = module.atom_count;
+        module.atom_count += 1;
+
+        let mut dependencies = Vec::new();
+        if !atoms.is_empty() {
+            for _ in 0..rng.below(3) {
+                let dep = atoms[rng.below(atoms.len())].code_name.clone();
+                if !dependencies.contains(&dep) {
+                    dependencies.push(dep);
+                }
+            }
+        }
+
+        atoms.push(FixtureAtom {
+            display_name,
+            code_name,
+            module: module_index,
+            index,
+            lines_start,
+            lines_end,
+            dependencies,
+            specified: i % 3 != 0,
+            verified: rng.next_f64() >= options.failure_rate,
+        });
+    }
+
+    atoms
+}
+
+fn write_atom_files(verilib_path: &Path, module: &FixtureModule, atom: &FixtureAtom) -> Result<()> {
+    let dir = verilib_path.join(&module.code_path);
+    std::fs::create_dir_all(&dir)?;
+
+    let mut code = format!("pub fn {}() {{\n", atom.display_name);
+    for line in 1..atom.lines_end - atom.lines_start {
+        code.push_str(&format!("    let _x{} = {};\n", line, line));
+    }
+    code.push_str("}\n");
+
+    let identifier = format!("{}()", atom.display_name);
+    let stem = format!("[{}] - {}", atom.index, identifier);
+    std::fs::write(dir.join(format!("{}.atom.verilib", stem)), &code)?;
+
+    let meta = json!({
+        "code_name": atom.code_name,
+        "dependencies": atom.dependencies,
+        "disabled": false,
+        "identifier": identifier,
+        "index": atom.index,
+        "path": format!("/{}/{}", module.code_path, identifier),
+        "snippets": [{ "sortorder": 0, "text": code, "type_id": SnippetKind::Code.type_id() }],
+        "specified": atom.specified,
+        "statement_type": "function",
+        "status_id": if atom.verified { 2 } else { 0 },
+    });
+    std::fs::write(
+        dir.join(format!("{}.meta.verilib", stem)),
+        serde_json::to_string_pretty(&meta)?,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn options(failure_rate: f64) -> FixtureOptions {
+        FixtureOptions {
+            atoms: 20,
+            modules: 5,
+            failure_rate,
+            seed: 7,
+        }
+    }
+
+    #[test]
+    fn test_failure_rate_bounds() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(
+            generate_fixture(dir.path(), &options(0.0)).unwrap().failing,
+            0
+        );
+        assert_eq!(
+            generate_fixture(dir.path(), &options(1.0)).unwrap().failing,
+            20
+        );
+        assert!(generate_fixture(dir.path(), &options(1.5)).is_err());
+    }
+
+    #[test]
+    fn test_same_seed_is_reproducible() {
+        let a = TempDir::new().unwrap();
+        let b = TempDir::new().unwrap();
+        generate_fixture(a.path(), &options(0.3)).unwrap();
+        generate_fixture(b.path(), &options(0.3)).unwrap();
+
+        for name in ["atoms.json", "proofs.json", "stubs.json"] {
+            let read = |dir: &TempDir| std::fs::read(dir.path().join(".verilib").join(name));
+            assert_eq!(read(&a).unwrap(), read(&b).unwrap(), "{} differs", name);
+        }
+    }
+}
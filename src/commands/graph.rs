@@ -0,0 +1,152 @@
+//! Graph subcommand implementation.
+//!
+//! Write the call graph between atoms in atoms.json as Graphviz DOT, or as
+//! JSON nodes and edges with `--json`. Dependencies on code outside
+//! atoms.json are left out.
+
+use crate::commands::stats::read_atoms;
+use crate::config::ProjectConfig;
+use crate::structure::Atom;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Node<'a> {
+    id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code_module: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code_path: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+struct Edge<'a> {
+    from: &'a str,
+    to: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct Graph<'a> {
+    nodes: Vec<Node<'a>>,
+    edges: Vec<Edge<'a>>,
+}
+
+/// Run the graph subcommand.
+pub async fn handle_graph(
+    project_root: PathBuf,
+    output: Option<PathBuf>,
+    json: bool,
+) -> Result<()> {
+    let project_root = project_root
+        .canonicalize()
+        .context("Failed to resolve project root")?;
+    let config = ProjectConfig::open(&project_root)?;
+
+    let atoms = read_atoms(&config.atoms_path())?;
+    let graph = build_graph(&atoms);
+    let rendered = if json {
+        serde_json::to_string_pretty(&graph)? + "\n"
+    } else {
+        render_dot(&graph)
+    };
+
+    match output {
+        Some(path) => {
+            std::fs::write(&path, rendered)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            eprintln!(
+                "Wrote {} atoms and {} edges to {}",
+                graph.nodes.len(),
+                graph.edges.len(),
+                path.display()
+            );
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+/// Nodes and edges sorted by code-name, for stable output.
+fn build_graph(atoms: &HashMap<String, Atom>) -> Graph<'_> {
+    let sorted: BTreeMap<&str, &Atom> = atoms.iter().map(|(k, v)| (k.as_str(), v)).collect();
+    let nodes = sorted
+        .iter()
+        .map(|(&id, atom)| Node {
+            id,
+            display_name: atom.display_name.as_deref(),
+            code_module: atom.code_module.as_deref(),
+            code_path: atom.code_path.as_deref(),
+        })
+        .collect();
+    let mut edges: Vec<Edge> = sorted
+        .iter()
+        .flat_map(|(&from, atom)| {
+            atom.dependencies
+                .iter()
+                .filter(|to| atoms.contains_key(*to))
+                .map(move |to| Edge {
+                    from,
+                    to: to.as_str(),
+                })
+        })
+        .collect();
+    edges.sort_by(|a, b| (a.from, a.to).cmp(&(b.from, b.to)));
+    edges.dedup_by(|a, b| (a.from, a.to) == (b.from, b.to));
+    Graph { nodes, edges }
+}
+
+fn render_dot(graph: &Graph) -> String {
+    let mut dot = String::from("digraph atoms {\n    node [shape=box];\n");
+    for node in &graph.nodes {
+        dot.push_str(&format!(
+            "    {} [label={}];\n",
+            quote(node.id),
+            quote(node.display_name.unwrap_or(node.id))
+        ));
+    }
+    for edge in &graph.edges {
+        dot.push_str(&format!(
+            "    {} -> {};\n",
+            quote(edge.from),
+            quote(edge.to)
+        ));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// A DOT string literal.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dot_keeps_only_edges_between_atoms() {
+        let atoms = HashMap::from([
+            (
+                "probe:a()".to_string(),
+                Atom {
+                    display_name: Some("a".to_string()),
+                    dependencies: vec!["probe:b()".to_string(), "vstd::seq".to_string()],
+                    ..Atom::default()
+                },
+            ),
+            ("probe:b()".to_string(), Atom::default()),
+        ]);
+
+        assert_eq!(
+            render_dot(&build_graph(&atoms)),
+            "digraph atoms {\n    node [shape=box];\n    \"probe:a()\" [label=\"a\"];\n    \
+             \"probe:b()\" [label=\"probe:b()\"];\n    \"probe:a()\" -> \"probe:b()\";\n}\n"
+        );
+    }
+}
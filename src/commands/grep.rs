@@ -0,0 +1,303 @@
+//! Grep subcommand implementation.
+//!
+//! Search atom contents (`*.atom.verilib`) and spec bodies (the `spec-text`
+//! ranges of specs.json, read from the source files) with a regex, skipping
+//! the meta, layout and index files that make plain ripgrep over `.verilib` noisy.
+
+use crate::config::{resolve_source_roots, source_file, ProjectConfig};
+use crate::exit::fail;
+use crate::structure::probe_output::load_probe_map;
+use crate::structure::{ProofResult, SpecEntry};
+use crate::walk;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use regex::{Regex, RegexBuilder};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Meta `status_id` of a verified atom.
+const VERIFIED_STATUS_ID: u64 = 2;
+
+/// Which results to keep, by verification state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GrepStatus {
+    Verified,
+    Unverified,
+    Specified,
+    Unspecified,
+}
+
+impl GrepStatus {
+    fn matches(self, verified: bool, specified: bool) -> bool {
+        match self {
+            GrepStatus::Verified => verified,
+            GrepStatus::Unverified => !verified,
+            GrepStatus::Specified => specified,
+            GrepStatus::Unspecified => !specified,
+        }
+    }
+}
+
+/// Where to search.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum GrepScope {
+    #[default]
+    All,
+    Atoms,
+    Specs,
+}
+
+/// Options for the grep subcommand.
+#[derive(Debug, Clone)]
+pub struct GrepOptions {
+    pub pattern: String,
+    pub ignore_case: bool,
+    pub scope: GrepScope,
+    pub status: Option<GrepStatus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GrepHit {
+    pub kind: &'static str,
+    pub identifier: String,
+    #[serde(rename = "code-name", skip_serializing_if = "Option::is_none")]
+    pub code_name: Option<String>,
+    pub path: String,
+    pub verified: bool,
+    pub specified: bool,
+    pub lines: Vec<GrepLine>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GrepLine {
+    pub line: usize,
+    pub text: String,
+}
+
+/// Run the grep subcommand.
+pub async fn handle_grep(project_root: PathBuf, options: GrepOptions, json: bool) -> Result<()> {
+    let hits = search(&project_root, &options)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&hits)?);
+        return Ok(());
+    }
+
+    for hit in &hits {
+        println!("{} ({}, {})", hit.identifier, hit.kind, hit.path);
+        for line in &hit.lines {
+            println!("  {}: {}", line.line, line.text);
+        }
+    }
+    let matched: usize = hits.iter().map(|h| h.lines.len()).sum();
+    println!("{} matching lines in {} entries", matched, hits.len());
+    Ok(())
+}
+
+/// Search atoms and/or specs under `project_root`, sorted by kind and identifier.
+pub fn search(project_root: &Path, options: &GrepOptions) -> Result<Vec<GrepHit>> {
+    let verilib_path = project_root.join(".verilib");
+    if !verilib_path.exists() {
+        fail!(
+            MissingPrerequisite,
+            "No .verilib directory found. Please run 'init' or 'create' first."
+        );
+    }
+
+    let regex = RegexBuilder::new(&options.pattern)
+        .case_insensitive(options.ignore_case)
+        .build()
+        .with_context(|| format!("Invalid pattern '{}'", options.pattern))?;
+
+    let mut hits = Vec::new();
+    if options.scope != GrepScope::Specs {
+        hits.extend(search_atoms(project_root, &verilib_path, &regex)?);
+    }
+    if options.scope != GrepScope::Atoms {
+        hits.extend(search_specs(project_root, &verilib_path, &regex)?);
+    }
+
+    hits.retain(|hit| {
+        options
+            .status
+            .is_none_or(|status| status.matches(hit.verified, hit.specified))
+    });
+    hits.sort_by(|a, b| (a.kind, &a.identifier).cmp(&(b.kind, &b.identifier)));
+    Ok(hits)
+}
+
+fn search_atoms(project_root: &Path, verilib_path: &Path, regex: &Regex) -> Result<Vec<GrepHit>> {
+    let mut hits = Vec::new();
+
+    for path in walk::verilib_files(verilib_path) {
+        let path = path.as_path();
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let Some(stem) = file_name.strip_suffix(".atom.verilib") else {
+            continue;
+        };
+
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let lines = matching_lines(content.lines(), 1, regex);
+        if lines.is_empty() {
+            continue;
+        }
+
+        let meta_path = path.with_file_name(format!("{}.meta.verilib", stem));
+        let meta: Value = std::fs::read_to_string(&meta_path)
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or(Value::Null);
+        let field = |key: &str| meta.get(key).and_then(|v| v.as_str()).map(str::to_string);
+
+        hits.push(GrepHit {
+            kind: "atom",
+            identifier: field("identifier").unwrap_or_else(|| stem.to_string()),
+            code_name: field("code_name").filter(|n| !n.is_empty()),
+            path: path
+                .strip_prefix(project_root)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .to_string(),
+            verified: meta.get("status_id").and_then(|v| v.as_u64()) == Some(VERIFIED_STATUS_ID),
+            specified: meta
+                .get("specified")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            lines,
+        });
+    }
+
+    Ok(hits)
+}
+
+fn search_specs(project_root: &Path, verilib_path: &Path, regex: &Regex) -> Result<Vec<GrepHit>> {
+    let specs: HashMap<String, SpecEntry> = read_json_map(&verilib_path.join("specs.json"))?;
+    let proofs: HashMap<String, ProofResult> = read_json_map(&verilib_path.join("proofs.json"))?;
+    let source_roots = resolve_source_roots(
+        project_root,
+        &ProjectConfig::load(project_root)?.source_roots,
+    );
+
+    let mut sources: HashMap<String, Option<Vec<String>>> = HashMap::new();
+    let mut hits = Vec::new();
+
+    for (code_name, spec) in &specs {
+        let (Some(code_path), Some(spec_text)) = (&spec.code_path, &spec.spec_text) else {
+            continue;
+        };
+        let (start, end) = (spec_text.lines_start as usize, spec_text.lines_end as usize);
+
+        let source = sources.entry(code_path.clone()).or_insert_with(|| {
+            std::fs::read_to_string(source_file(project_root, &source_roots, code_path))
+                .ok()
+                .map(|c| c.lines().map(str::to_string).collect())
+        });
+        let Some(source) = source else {
+            continue;
+        };
+
+        let body = source
+            .iter()
+            .skip(start.saturating_sub(1))
+            .take((end + 1).saturating_sub(start.max(1)))
+            .map(String::as_str);
+        let lines = matching_lines(body, start.max(1), regex);
+        if lines.is_empty() {
+            continue;
+        }
+
+        hits.push(GrepHit {
+            kind: "spec",
+            identifier: code_name.clone(),
+            code_name: Some(code_name.clone()),
+            path: code_path.clone(),
+            verified: proofs.get(code_name).is_some_and(|p| p.verified),
+            specified: spec.specified.unwrap_or(true),
+            lines,
+        });
+    }
+
+    Ok(hits)
+}
+
+fn matching_lines<'a>(
+    lines: impl Iterator<Item = &'a str>,
+    first_line: usize,
+    regex: &Regex,
+) -> Vec<GrepLine> {
+    lines
+        .enumerate()
+        .filter(|(_, text)| regex.is_match(text))
+        .map(|(i, text)| GrepLine {
+            line: first_line + i,
+            text: text.to_string(),
+        })
+        .collect()
+}
+
+/// Read probe-verus output, treating a missing file as empty.
+fn read_json_map<T: DeserializeOwned>(path: &Path) -> Result<HashMap<String, T>> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    load_probe_map(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn options(pattern: &str, status: Option<GrepStatus>) -> GrepOptions {
+        GrepOptions {
+            pattern: pattern.to_string(),
+            ignore_case: false,
+            scope: GrepScope::All,
+            status,
+        }
+    }
+
+    #[test]
+    fn test_search_atoms_and_specs_with_status() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        let atoms = root.join(".verilib/src/lib.rs");
+        std::fs::create_dir_all(&atoms).unwrap();
+        std::fs::write(
+            atoms.join("[0] - add().atom.verilib"),
+            "fn add() {\n    a + b\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            atoms.join("[0] - add().meta.verilib"),
+            r#"{"identifier": "add()", "status_id": 2, "specified": true}"#,
+        )
+        .unwrap();
+        std::fs::write(atoms.join("layout.json"), r#"{"a + b": 1}"#).unwrap();
+
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(
+            root.join("src/lib.rs"),
+            "fn add()\n    ensures r == a + b\n{\n    a + b\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join(".verilib/specs.json"),
+            r#"{"probe:c/add()": {"code-path": "src/lib.rs", "spec-text": {"lines-start": 1, "lines-end": 2}}}"#,
+        )
+        .unwrap();
+
+        let hits = search(root, &options(r"a \+ b", None)).unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!((hits[0].kind, hits[0].lines[0].line), ("atom", 2));
+        assert_eq!((hits[1].kind, hits[1].lines[0].line), ("spec", 2));
+
+        let unverified = search(root, &options(r"a \+ b", Some(GrepStatus::Unverified))).unwrap();
+        assert_eq!(unverified.len(), 1);
+        assert_eq!(unverified[0].kind, "spec");
+    }
+}
@@ -0,0 +1,161 @@
+//! History subcommand implementation.
+//!
+//! Every mutating command appends one JSON line to `.verilib/history.jsonl`
+//! recording who ran what, when, which artifacts it touched, and whether it
+//! succeeded, so that shared checkouts (e.g. CI workspaces) stay auditable.
+
+use crate::output::{self, Table};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const HISTORY_FILE: &str = "history.jsonl";
+
+/// One recorded command invocation.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HistoryEntry {
+    pub timestamp: DateTime<Utc>,
+    pub user: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub artifacts: Vec<String>,
+    pub result: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+fn history_path(project_root: &Path) -> PathBuf {
+    project_root.join(".verilib").join(HISTORY_FILE)
+}
+
+pub fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Append an entry for a finished command. Only projects that have a
+/// `.verilib` directory get a history; failures to write are reported
+/// but never fail the command itself.
+pub fn record(project_root: &Path, command: &str, artifacts: Vec<String>, outcome: &Result<()>) {
+    if !project_root.join(".verilib").is_dir() {
+        return;
+    }
+
+    let entry = HistoryEntry {
+        timestamp: Utc::now(),
+        user: current_user(),
+        command: command.to_string(),
+        args: std::env::args().skip(1).collect(),
+        artifacts,
+        result: if outcome.is_ok() {
+            "success"
+        } else {
+            "failure"
+        }
+        .to_string(),
+        error: outcome.as_ref().err().map(|e| format!("{:#}", e)),
+    };
+
+    if let Err(e) = append(project_root, &entry) {
+        output::warn(format!("failed to record command history: {:#}", e));
+    }
+}
+
+fn append(project_root: &Path, entry: &HistoryEntry) -> Result<()> {
+    let path = history_path(project_root);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// Read all entries, oldest first. Malformed lines are skipped with a warning.
+fn read_history(project_root: &Path) -> Result<Vec<HistoryEntry>> {
+    let path = history_path(project_root);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(i, line)| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                output::warn(format!("{}:{}: {}", path.display(), i + 1, e));
+                None
+            }
+        })
+        .collect())
+}
+
+/// Run the history subcommand.
+pub async fn handle_history(limit: usize, json: bool) -> Result<()> {
+    let entries = read_history(Path::new("."))?;
+    let shown = &entries[entries.len().saturating_sub(limit)..];
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(shown)?);
+        return Ok(());
+    }
+
+    if shown.is_empty() {
+        println!("No history recorded in .verilib/{}", HISTORY_FILE);
+        return Ok(());
+    }
+
+    let mut table = Table::new(["Time", "User", "Result", "Command", "Touched", "Error"]);
+    for entry in shown {
+        table.row([
+            entry.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+            entry.user.clone(),
+            entry.result.clone(),
+            entry.args.join(" "),
+            entry.artifacts.join(", "),
+            entry.error.clone().unwrap_or_default(),
+        ]);
+    }
+    table.print();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_appends_only_inside_projects() {
+        let dir = TempDir::new().unwrap();
+        record(dir.path(), "atomize", vec![], &Ok(()));
+        assert!(!history_path(dir.path()).exists());
+
+        std::fs::create_dir(dir.path().join(".verilib")).unwrap();
+        record(
+            dir.path(),
+            "atomize",
+            vec![".verilib/stubs.json".to_string()],
+            &Ok(()),
+        );
+        record(dir.path(), "verify", vec![], &Err(anyhow::anyhow!("boom")));
+
+        let entries = read_history(dir.path()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].command, "atomize");
+        assert_eq!(entries[0].result, "success");
+        assert_eq!(entries[1].result, "failure");
+        assert_eq!(entries[1].error.as_deref(), Some("boom"));
+    }
+}
@@ -1,15 +1,21 @@
 use anyhow::{Context, Result};
+use chrono::Utc;
 use dialoguer::{Input, Select};
-use reqwest::Client;
 use serde_json::Value;
 use std::fs;
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use walkdir::WalkDir;
 
-use crate::commands::deploy::collect_deploy_info_with_path;
+use crate::commands::deploy::{collect_deploy_info_with_path, RepoOptions};
 use crate::commands::status::get_stored_api_key;
-use crate::constants::{auth_required_msg, DEFAULT_BASE_URL};
-use crate::download::handle_api_error;
+use crate::config::{resolve_base_url, ProjectConfig};
+use crate::constants::auth_required_msg;
+use crate::download::{api_error, ApiClient, ApiOperation};
+use crate::exit::{ExitContext, ExitKind};
+use crate::i18n::t;
+use crate::output;
 use crate::structure::{create_gitignore, ExecutionMode};
 
 #[derive(serde::Deserialize, Debug)]
@@ -22,44 +28,185 @@ struct CreateRepoData {
     id: u32,
 }
 
-pub async fn handle_init(id: Option<String>, url: Option<String>, debug: bool) -> Result<()> {
-    let api_key = get_stored_api_key().context(auth_required_msg())?;
+/// Local state that `init` would overwrite.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct InitConflicts {
+    /// ID of the repository this checkout currently belongs to, when it differs
+    /// from the one being initialized.
+    other_repo: Option<String>,
+    /// Paths under .verilib with uncommitted git changes.
+    uncommitted: Vec<String>,
+}
 
-    let url_base = url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+impl InitConflicts {
+    fn is_empty(&self) -> bool {
+        self.other_repo.is_none() && self.uncommitted.is_empty()
+    }
+}
+
+pub async fn handle_init(
+    id: Option<String>,
+    base_url: Option<String>,
+    force: bool,
+    snapshot: bool,
+    repo: RepoOptions,
+    debug: bool,
+) -> Result<()> {
+    let api_key = get_stored_api_key()
+        .context(auth_required_msg())
+        .exit_kind(ExitKind::Network)?;
+
+    let project_root = PathBuf::from(".");
+    let conflicts = detect_conflicts(&project_root, id.as_deref())?;
+    if !conflicts.is_empty() {
+        resolve_conflicts(&project_root, &conflicts, force, snapshot)?;
+    } else if snapshot && project_root.join(".verilib").exists() {
+        take_snapshot(&project_root)?;
+    }
+
+    let saved_repo = ProjectConfig::load(&project_root)?.repo;
+    let url_base = resolve_base_url(base_url.as_deref(), saved_repo.as_ref());
 
     let repo_id = if let Some(repo_id) = id {
-        println!("Initializing project with repository ID: {}", repo_id);
+        println!("{}", t!("init-with-id", id = repo_id.as_str()));
         repo_id
     } else {
         let git_url = prompt_git_url()?;
 
-        println!("Creating new repository from git URL: {}", git_url);
+        println!("{}", t!("init-creating", url = git_url.as_str()));
 
-        let repo_id = create_repo_from_git_url(&git_url, &url_base, &api_key, debug).await?;
+        let repo_id = create_repo_from_git_url(&git_url, &url_base, &api_key, &repo, debug).await?;
 
-        println!("Repository created successfully!");
-        println!("Repository ID: {}", repo_id);
+        println!("{}", t!("init-created"));
+        println!("{}", t!("init-repo-id", id = repo_id.as_str()));
 
         repo_id
     };
 
     let execution_mode = prompt_execution_mode()?;
 
-    fs::create_dir_all(".verilib").context("Failed to create .verilib directory")?;
+    fs::create_dir_all(".verilib").with_context(|| t!("init-mkdir-failed"))?;
 
     save_config(&repo_id, &url_base, true, execution_mode)?;
 
     Ok(())
 }
 
+/// Compare the existing .verilib with the repository being initialized.
+/// `repo_id` is `None` when a new repository is about to be created.
+fn detect_conflicts(project_root: &Path, repo_id: Option<&str>) -> Result<InitConflicts> {
+    let existing = ProjectConfig::load(project_root)?.repo.map(|repo| repo.id);
+    let other_repo = existing.filter(|existing| Some(existing.as_str()) != repo_id);
+
+    // Outside a git checkout (or without git) there is nothing to compare against.
+    let uncommitted = Command::new("git")
+        .args(["status", "--porcelain", "--", ".verilib"])
+        .current_dir(project_root)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|line| line.get(3..))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(InitConflicts {
+        other_repo,
+        uncommitted,
+    })
+}
+
+/// Report conflicts and decide whether to continue: `--force` continues, an
+/// interactive terminal is asked, and anything else aborts.
+fn resolve_conflicts(
+    project_root: &Path,
+    conflicts: &InitConflicts,
+    force: bool,
+    snapshot: bool,
+) -> Result<()> {
+    if let Some(other) = &conflicts.other_repo {
+        output::warn(t!("init-conflict-repo", id = other.as_str()));
+    }
+    if !conflicts.uncommitted.is_empty() {
+        output::warn(t!(
+            "init-conflict-uncommitted",
+            count = conflicts.uncommitted.len()
+        ));
+        for path in &conflicts.uncommitted {
+            eprintln!("  {}", path);
+        }
+    }
+
+    if force {
+        if snapshot {
+            take_snapshot(project_root)?;
+        }
+        return Ok(());
+    }
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!(t!("init-conflict-abort"));
+    }
+
+    let choices = vec![
+        t!("init-conflict-snapshot"),
+        t!("init-conflict-overwrite"),
+        t!("init-conflict-cancel"),
+    ];
+    let selection = Select::new()
+        .with_prompt(t!("init-conflict-prompt"))
+        .items(&choices)
+        .default(0)
+        .interact()
+        .with_context(|| t!("init-conflict-prompt-failed"))?;
+
+    match selection {
+        0 => {
+            take_snapshot(project_root)?;
+            Ok(())
+        }
+        1 => Ok(()),
+        _ => anyhow::bail!(t!("init-conflict-cancelled")),
+    }
+}
+
+/// Copy .verilib to `.verilib.snapshot-<timestamp>` next to it.
+fn take_snapshot(project_root: &Path) -> Result<PathBuf> {
+    let source = project_root.join(".verilib");
+    let target = project_root.join(format!(
+        ".verilib.snapshot-{}",
+        Utc::now().format("%Y%m%dT%H%M%SZ")
+    ));
+
+    for entry in WalkDir::new(&source) {
+        let entry = entry?;
+        let dest = target.join(entry.path().strip_prefix(&source)?);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&dest)?;
+        } else {
+            fs::copy(entry.path(), &dest)
+                .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+        }
+    }
+
+    println!(
+        "{}",
+        t!("init-snapshot-saved", path = target.display().to_string())
+    );
+    Ok(target)
+}
+
 fn prompt_execution_mode() -> Result<ExecutionMode> {
-    let modes = vec!["Local (Default)", "Docker"];
+    let modes = vec![t!("init-mode-local"), t!("init-mode-docker")];
     let selection = Select::new()
-        .with_prompt("Select execution mode")
+        .with_prompt(t!("init-select-mode"))
         .items(&modes)
         .default(0)
         .interact()
-        .context("Failed to select execution mode")?;
+        .with_context(|| t!("init-mode-failed"))?;
 
     match selection {
         0 => Ok(ExecutionMode::Local),
@@ -130,34 +277,32 @@ fn normalize_git_url(url: &str) -> String {
 }
 
 fn prompt_git_url() -> Result<String> {
-    println!("\nRepository URL Options:");
-    println!("• Full repository: https://github.com/user/repo");
-    println!("• Specific branch: https://github.com/user/repo@branch-name");
-    println!("• Folder only: https://github.com/user/repo/tree/main/folder-name");
-    println!(
-        "• Folder from branch: https://github.com/user/repo/tree/main/folder-name@branch-name"
-    );
+    println!("\n{}", t!("init-url-options"));
+    println!("{}", t!("init-url-full"));
+    println!("{}", t!("init-url-branch"));
+    println!("{}", t!("init-url-folder"));
+    println!("{}", t!("init-url-folder-branch"));
     println!();
 
     let detected_url = detect_git_url();
 
     let git_url = if let Some(default_url) = detected_url {
         Input::<String>::new()
-            .with_prompt("Enter repository URL")
+            .with_prompt(t!("init-url-prompt"))
             .default(default_url)
             .interact_text()
-            .context("Failed to get git URL input")?
+            .with_context(|| t!("init-url-input-failed"))?
     } else {
         Input::<String>::new()
-            .with_prompt("Enter repository URL")
+            .with_prompt(t!("init-url-prompt"))
             .interact_text()
-            .context("Failed to get git URL input")?
+            .with_context(|| t!("init-url-input-failed"))?
     };
 
     let git_url = git_url.trim().to_string();
 
     if git_url.is_empty() {
-        anyhow::bail!("Repository URL cannot be empty");
+        anyhow::bail!(t!("init-url-empty"));
     }
 
     Ok(git_url)
@@ -167,46 +312,56 @@ async fn create_repo_from_git_url(
     git_url: &str,
     base_url: &str,
     api_key: &str,
+    repo: &RepoOptions,
     debug: bool,
 ) -> Result<String> {
-    println!("\nCollecting repository information...");
+    println!("\n{}", t!("init-collecting"));
 
-    let (language_id, proof_id, verifierversion_id, summary, description, type_id) =
-        collect_deploy_info_with_path(base_url, api_key, &PathBuf::from("."), debug).await?;
+    let info =
+        collect_deploy_info_with_path(base_url, api_key, &PathBuf::from("."), repo, debug).await?;
 
     let mut payload = serde_json::json!({
         "url": git_url,
-        "language_id": language_id,
-        "prooflanguage_id": proof_id,
-        "summary": summary,
-        "type_id": type_id,
+        "language_id": info.language_id,
+        "prooflanguage_id": info.proof_id,
+        "summary": info.summary,
+        "type_id": info.type_id,
+        "visibility": info.visibility,
     });
 
-    if let Some(desc) = description {
+    if let Some(desc) = info.description {
         payload["description"] = Value::String(desc);
     }
 
-    if let Some(version_id) = verifierversion_id {
+    if let Some(version_id) = info.verifierversion_id {
         payload["verifierversion_id"] = Value::Number(version_id.into());
     }
 
+    if let Some(team) = info.team {
+        payload["team"] = Value::String(team);
+    }
+
+    if !info.topics.is_empty() {
+        payload["topics"] = serde_json::json!(info.topics);
+    }
+
     let endpoint = format!("{}/v2/repo/create", base_url);
 
-    let client = Client::new();
+    let client = ApiClient::for_project(api_key)?;
     let response = client
-        .post(&endpoint)
-        .header("Authorization", format!("ApiKey {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(&payload)
-        .send()
+        .send(
+            client
+                .post(&endpoint)
+                .header("Content-Type", "application/json")
+                .json(&payload),
+        )
         .await
         .context("Failed to send create repository request")?;
 
     let status = response.status();
 
     if !status.is_success() {
-        let error_msg = handle_api_error(response).await?;
-        anyhow::bail!(error_msg);
+        return Err(api_error(response, ApiOperation::Init).await);
     }
 
     let response_text = response
@@ -220,7 +375,9 @@ async fn create_repo_from_git_url(
     Ok(create_response.data.id.to_string())
 }
 
-fn save_config(
+/// Record the repository and execution mode in `.verilib/config.json` of the
+/// current directory, and write `.verilib/.gitignore`.
+pub fn save_config(
     repo_id: &str,
     base_url: &str,
     is_admin: bool,
@@ -244,3 +401,52 @@ fn save_config(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::DEFAULT_BASE_URL;
+    use tempfile::TempDir;
+
+    fn project_with_repo(id: &str) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let config = ProjectConfig {
+            repo: Some(crate::config::RepoConfig {
+                id: id.to_string(),
+                url: DEFAULT_BASE_URL.to_string(),
+                is_admin: true,
+            }),
+            ..ProjectConfig::default()
+        };
+        config.save(dir.path()).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_other_repo_is_a_conflict() {
+        let dir = project_with_repo("42");
+
+        assert!(detect_conflicts(dir.path(), Some("42")).unwrap().is_empty());
+        assert_eq!(
+            detect_conflicts(dir.path(), Some("7")).unwrap().other_repo,
+            Some("42".to_string())
+        );
+        assert_eq!(
+            detect_conflicts(dir.path(), None).unwrap().other_repo,
+            Some("42".to_string())
+        );
+        assert!(detect_conflicts(TempDir::new().unwrap().path(), None)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_copies_verilib() {
+        let dir = project_with_repo("42");
+        let snapshot = take_snapshot(dir.path()).unwrap();
+        assert_eq!(
+            fs::read(snapshot.join("config.json")).unwrap(),
+            fs::read(dir.path().join(".verilib/config.json")).unwrap()
+        );
+    }
+}
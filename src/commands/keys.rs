@@ -0,0 +1,56 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use crate::config::ProjectConfig;
+use crate::encryption::{generate_identity, identity_path, load_identity, parse_recipient};
+
+#[derive(Debug, Clone)]
+pub enum KeysSubcommand {
+    Generate { force: bool },
+    Show,
+    SetRecipient { recipient: String },
+    UnsetRecipient,
+}
+
+pub async fn handle_keys(subcommand: KeysSubcommand) -> Result<()> {
+    match subcommand {
+        KeysSubcommand::Generate { force } => {
+            let (path, recipient) = generate_identity(force)?;
+            println!("Identity written to {}", path.display());
+            println!("Public key: {}", recipient);
+            println!("Share the public key with your team and run:");
+            println!("  verilib-cli keys set-recipient {}", recipient);
+        }
+        KeysSubcommand::Show => {
+            let identity = load_identity()?;
+            println!("Identity file: {}", identity_path()?.display());
+            println!("Public key: {}", identity.to_public());
+
+            let config = ProjectConfig::load(&PathBuf::from("."))?;
+            match config.encryption_recipient {
+                Some(recipient) => println!("Project encrypts deploys to: {}", recipient),
+                None => println!("Project encryption: disabled"),
+            }
+        }
+        KeysSubcommand::SetRecipient { recipient } => {
+            parse_recipient(&recipient)?;
+
+            let project_root = PathBuf::from(".");
+            let mut config = ProjectConfig::load(&project_root)?;
+            config.encryption_recipient = Some(recipient.trim().to_string());
+            config.save(&project_root)?;
+
+            println!("Atom contents will be encrypted on deploy.");
+        }
+        KeysSubcommand::UnsetRecipient => {
+            let project_root = PathBuf::from(".");
+            let mut config = ProjectConfig::load(&project_root)?;
+            config.encryption_recipient = None;
+            config.save(&project_root)?;
+
+            println!("Encryption disabled; atom contents will be deployed in the clear.");
+        }
+    }
+
+    Ok(())
+}
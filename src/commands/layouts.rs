@@ -0,0 +1,236 @@
+//! Layouts subcommand implementation.
+//!
+//! A folder of the `.verilib` tree may hold a `layout.verilib` with the graph
+//! positions of its children, as arranged on the server. `layouts check`
+//! reports children without coordinates and layout nodes that match nothing
+//! in the folder; `deploy` warns when there are any.
+
+use crate::commands::deploy::atom_identifier;
+use crate::download::Layout;
+use crate::exit::fail;
+use crate::output::{self, Table};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// What is wrong with a layout node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LayoutProblem {
+    /// The layout.verilib could not be read.
+    Invalid,
+    /// A child of the folder has no `fx` and `fy`, or no node at all.
+    MissingCoordinates,
+    /// A node matches no atom or subfolder of the folder.
+    UnknownNode,
+}
+
+impl LayoutProblem {
+    fn as_str(self) -> &'static str {
+        match self {
+            LayoutProblem::Invalid => "invalid",
+            LayoutProblem::MissingCoordinates => "missing-coordinates",
+            LayoutProblem::UnknownNode => "unknown-node",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LayoutIssue {
+    /// Relative to the checked directory; `.` for the directory itself.
+    pub folder: String,
+    pub node: String,
+    pub problem: LayoutProblem,
+    pub detail: String,
+}
+
+/// Run `layouts check`.
+pub async fn handle_layouts_check(project_root: PathBuf, json: bool) -> Result<()> {
+    let verilib_path = project_root.join(".verilib");
+    if !verilib_path.is_dir() {
+        fail!(
+            MissingPrerequisite,
+            "No .verilib directory found. Please run 'init' first."
+        );
+    }
+    let issues = check_layouts(&verilib_path)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&issues)?);
+    } else if !issues.is_empty() {
+        let mut table = Table::new(["FOLDER", "NODE", "PROBLEM", "DETAIL"]);
+        for issue in &issues {
+            table.row([
+                issue.folder.as_str(),
+                issue.node.as_str(),
+                issue.problem.as_str(),
+                issue.detail.as_str(),
+            ]);
+        }
+        table.print();
+    }
+
+    if !issues.is_empty() {
+        fail!(CheckFailed, "{} layout problems found.", issues.len());
+    }
+    if !json {
+        output::success("Every layout places all of its folder's children.");
+    }
+    Ok(())
+}
+
+/// Check every layout.verilib under `scope_path` against its folder.
+pub fn check_layouts(scope_path: &Path) -> Result<Vec<LayoutIssue>> {
+    let mut issues = Vec::new();
+    for entry in WalkDir::new(scope_path).sort_by_file_name() {
+        let entry = entry.context("Failed to read .verilib")?;
+        if entry.file_type().is_dir() {
+            issues.extend(check_folder(scope_path, entry.path())?);
+        }
+    }
+    Ok(issues)
+}
+
+fn check_folder(scope_path: &Path, folder: &Path) -> Result<Vec<LayoutIssue>> {
+    let name = match folder.strip_prefix(scope_path)?.to_string_lossy() {
+        name if name.is_empty() => ".".to_string(),
+        name => name.replace('\\', "/"),
+    };
+    let issue = |node: &str, problem, detail: &str| LayoutIssue {
+        folder: name.clone(),
+        node: node.to_string(),
+        problem,
+        detail: detail.to_string(),
+    };
+
+    let layout = match Layout::read(folder) {
+        Ok(Some(layout)) => layout,
+        Ok(None) => return Ok(Vec::new()),
+        Err(e) => {
+            let detail = format!("{:#}", e);
+            return Ok(vec![issue(Layout::FILE, LayoutProblem::Invalid, &detail)]);
+        }
+    };
+
+    let children = children(folder)?;
+    let mut issues = Vec::new();
+    let mut placed = BTreeSet::new();
+    for node in &layout.nodes {
+        if !children.contains(&node.identifier) {
+            issues.push(issue(
+                &node.identifier,
+                LayoutProblem::UnknownNode,
+                "no such atom or folder",
+            ));
+        } else if !node.has_coordinates() {
+            issues.push(issue(
+                &node.identifier,
+                LayoutProblem::MissingCoordinates,
+                "no fx/fy",
+            ));
+        }
+        placed.insert(node.identifier.as_str());
+    }
+    for child in &children {
+        if !placed.contains(child.as_str()) {
+            issues.push(issue(
+                child,
+                LayoutProblem::MissingCoordinates,
+                "no layout node",
+            ));
+        }
+    }
+    Ok(issues)
+}
+
+/// Identifiers of the atoms and subfolders directly in `folder`.
+fn children(folder: &Path) -> Result<BTreeSet<String>> {
+    let mut children = BTreeSet::new();
+    for entry in std::fs::read_dir(folder)
+        .with_context(|| format!("Failed to read directory: {:?}", folder))?
+    {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if path.is_dir() {
+            children.insert(name.into_owned());
+        } else if name.ends_with(".atom.verilib") {
+            children.insert(atom_identifier(folder, &path)?);
+        }
+    }
+    Ok(children)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn test_layout_round_trips_unknown_fields() {
+        let value = json!({
+            "nodes": [{ "identifier": "f", "fx": 1.5, "fy": null, "path": "/src/f", "pinned": true }],
+            "zoom": { "k": 0.8, "x": 10, "y": -4 },
+            "repositioned": true,
+            "version": 2,
+        });
+        let layout: Layout = serde_json::from_value(value).unwrap();
+        assert!(!layout.nodes[0].has_coordinates());
+
+        let dir = tempfile::TempDir::new().unwrap();
+        layout.write(dir.path()).unwrap();
+        let written: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.path().join(Layout::FILE)).unwrap())
+                .unwrap();
+        assert_eq!(written["zoom"]["k"], 0.8);
+        assert_eq!(written["repositioned"], true);
+        assert_eq!(written["version"], 2);
+        assert_eq!(written["nodes"][0]["pinned"], true);
+        assert_eq!(Layout::read(dir.path()).unwrap(), Some(layout));
+    }
+
+    #[test]
+    fn test_check_reports_unplaced_and_unknown_nodes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        for atom in [
+            "[1] - a.atom.verilib",
+            "[2] - b.atom.verilib",
+            "[3] - c.atom.verilib",
+        ] {
+            fs::write(src.join(atom), "").unwrap();
+        }
+        let layout = json!({
+            "nodes": [
+                { "identifier": "a", "fx": 0.0, "fy": 1.0, "path": "/src/a" },
+                { "identifier": "b", "fx": 2.0, "path": "/src/b" },
+                { "identifier": "gone", "fx": 3.0, "fy": 3.0, "path": "/src/gone" },
+                { "identifier": "sub", "fx": 4.0, "fy": 4.0, "path": "/src/sub" },
+            ]
+        });
+        fs::write(src.join(Layout::FILE), layout.to_string()).unwrap();
+        fs::write(src.join("sub").join(Layout::FILE), "{").unwrap();
+
+        let found: Vec<(String, String, LayoutProblem)> = check_layouts(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|issue| (issue.folder, issue.node, issue.problem))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("src".into(), "b".into(), LayoutProblem::MissingCoordinates),
+                ("src".into(), "gone".into(), LayoutProblem::UnknownNode),
+                ("src".into(), "c".into(), LayoutProblem::MissingCoordinates),
+                (
+                    "src/sub".into(),
+                    Layout::FILE.into(),
+                    LayoutProblem::Invalid
+                ),
+            ]
+        );
+    }
+}
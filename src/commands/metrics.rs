@@ -0,0 +1,196 @@
+//! Metrics subcommand implementation.
+//!
+//! Summarize the runs recorded in `.verilib/metrics.jsonl`: the most recent
+//! ones with where their time went, and per-command totals over them.
+
+use crate::metrics::{read_metrics, MetricsEntry};
+use crate::output::{self, Table};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Totals over the shown runs of one command.
+#[derive(Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+struct CommandSummary {
+    runs: usize,
+    failures: usize,
+    total_ms: u64,
+    max_ms: u64,
+    /// Time spent in external tools such as probe-verus.
+    subprocess_ms: u64,
+    cache_hits: u64,
+    cache_misses: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct Summary<'a> {
+    runs: &'a [MetricsEntry],
+    commands: BTreeMap<&'a str, CommandSummary>,
+}
+
+/// Run `metrics show`.
+pub async fn handle_metrics_show(project_root: PathBuf, limit: usize, json: bool) -> Result<()> {
+    let project_root = project_root
+        .canonicalize()
+        .context("Failed to resolve project root")?;
+    let entries = read_metrics(&project_root)?;
+    let shown = &entries[entries.len().saturating_sub(limit)..];
+    let summary = Summary {
+        runs: shown,
+        commands: summarize(shown),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+
+    if shown.is_empty() {
+        println!(
+            "No metrics recorded. Set \"metrics\": true in .verilib/config.json \
+             (or VERILIB_METRICS=1) to record command timings."
+        );
+        return Ok(());
+    }
+
+    output::heading("Recent runs");
+    let mut table = Table::new([
+        "Time",
+        "Command",
+        "Result",
+        "Total",
+        "Tools",
+        "Slowest stage",
+    ]);
+    for entry in shown {
+        let slowest = entry
+            .stages
+            .iter()
+            .max_by_key(|stage| stage.duration_ms)
+            .map(|stage| format!("{} ({})", stage.name, seconds(stage.duration_ms)))
+            .unwrap_or_else(|| "-".to_string());
+        table.row([
+            entry.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+            entry.command.clone(),
+            entry.result.clone(),
+            seconds(entry.duration_ms),
+            seconds(subprocess_ms(entry)),
+            slowest,
+        ]);
+    }
+    table.print();
+
+    println!();
+    output::heading("By command");
+    let mut table = Table::new([
+        "Command",
+        "Runs",
+        "Failed",
+        "Avg",
+        "Max",
+        "In tools",
+        "Cache hits",
+    ]);
+    for (command, totals) in &summary.commands {
+        let lookups = totals.cache_hits + totals.cache_misses;
+        table.row([
+            command.to_string(),
+            totals.runs.to_string(),
+            totals.failures.to_string(),
+            seconds(totals.total_ms / totals.runs as u64),
+            seconds(totals.max_ms),
+            percent(totals.subprocess_ms, totals.total_ms),
+            if lookups == 0 {
+                "-".to_string()
+            } else {
+                percent(totals.cache_hits, lookups)
+            },
+        ]);
+    }
+    table.print();
+
+    Ok(())
+}
+
+fn subprocess_ms(entry: &MetricsEntry) -> u64 {
+    entry.subprocesses.iter().map(|sub| sub.duration_ms).sum()
+}
+
+fn summarize(entries: &[MetricsEntry]) -> BTreeMap<&str, CommandSummary> {
+    let mut commands: BTreeMap<&str, CommandSummary> = BTreeMap::new();
+    for entry in entries {
+        let totals = commands.entry(entry.command.as_str()).or_default();
+        totals.runs += 1;
+        if entry.result != "success" {
+            totals.failures += 1;
+        }
+        totals.total_ms += entry.duration_ms;
+        totals.max_ms = totals.max_ms.max(entry.duration_ms);
+        totals.subprocess_ms += subprocess_ms(entry);
+        let count = |name: &str| entry.counts.get(name).copied().unwrap_or(0);
+        totals.cache_hits += count("cache-hits");
+        totals.cache_misses += count("cache-misses");
+    }
+    commands
+}
+
+fn seconds(ms: u64) -> String {
+    format!("{:.1}s", ms as f64 / 1000.0)
+}
+
+fn percent(part: u64, whole: u64) -> String {
+    if whole == 0 {
+        return "-".to_string();
+    }
+    format!("{:.0}%", part as f64 * 100.0 / whole as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Subprocess;
+
+    fn entry(command: &str, result: &str, duration_ms: u64, tool_ms: u64) -> MetricsEntry {
+        MetricsEntry {
+            timestamp: chrono::Utc::now(),
+            command: command.to_string(),
+            args: Vec::new(),
+            result: result.to_string(),
+            duration_ms,
+            stages: Vec::new(),
+            subprocesses: vec![Subprocess {
+                command: "probe-verus verify".to_string(),
+                duration_ms: tool_ms,
+                exit_code: Some(0),
+            }],
+            counts: BTreeMap::from([("cache-hits".to_string(), 3)]),
+        }
+    }
+
+    #[test]
+    fn test_summarize_groups_by_command() {
+        let entries = [
+            entry("verify", "success", 1000, 800),
+            entry("verify", "failure", 3000, 2000),
+            entry("atomize", "success", 500, 0),
+        ];
+
+        let summary = summarize(&entries);
+
+        assert_eq!(
+            summary["verify"],
+            CommandSummary {
+                runs: 2,
+                failures: 1,
+                total_ms: 4000,
+                max_ms: 3000,
+                subprocess_ms: 2800,
+                cache_hits: 6,
+                cache_misses: 0,
+            }
+        );
+        assert_eq!(summary["atomize"].runs, 1);
+    }
+}
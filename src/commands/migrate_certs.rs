@@ -0,0 +1,75 @@
+//! Migrate-certs subcommand implementation.
+//!
+//! Copy every spec cert from the configured store to another one, record the
+//! new `cert-store` in config.json, and remove the old copies.
+
+use crate::config::ProjectConfig;
+use crate::structure::CertStoreKind;
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+
+/// Run the migrate-certs subcommand.
+pub async fn handle_migrate_certs(
+    project_root: PathBuf,
+    to: CertStoreKind,
+    dry_run: bool,
+) -> Result<()> {
+    let project_root = project_root
+        .canonicalize()
+        .context("Failed to resolve project root")?;
+    let mut config = ProjectConfig::open(&project_root)?;
+    let from = config.cert_store.unwrap_or_default();
+    if from == to {
+        println!("Certs already use the '{}' store.", to.as_str());
+        return Ok(());
+    }
+
+    let source = from.open(config.certs_specify_dir());
+    let target = to.open(config.certs_specify_dir());
+    let mut entries = source.entries()?;
+    let unreadable: Vec<&str> = entries
+        .iter()
+        .filter(|entry| entry.code_name.is_none() || entry.cert.is_none())
+        .map(|entry| entry.location.as_str())
+        .collect();
+    if !unreadable.is_empty() {
+        bail!(
+            "Cannot migrate {} unreadable certs ({}). Run 'verify-certs --clean' to remove them first.",
+            unreadable.len(),
+            unreadable.join(", ")
+        );
+    }
+    entries.sort_by(|a, b| a.code_name.cmp(&b.code_name));
+
+    if dry_run {
+        println!(
+            "Would move {} certs from the '{}' to the '{}' store ({}).",
+            entries.len(),
+            from.as_str(),
+            to.as_str(),
+            target.path().display()
+        );
+        return Ok(());
+    }
+
+    for entry in &entries {
+        if let (Some(code_name), Some(cert)) = (&entry.code_name, &entry.cert) {
+            target.put(code_name, cert)?;
+        }
+    }
+    config.cert_store = Some(to);
+    config.save(&project_root)?;
+    source.remove(&entries.iter().collect::<Vec<_>>())?;
+    // Drop the certs directory the directory store leaves empty; fails if not empty.
+    if from == CertStoreKind::Directory {
+        let _ = std::fs::remove_dir(source.path());
+    }
+
+    println!(
+        "Moved {} certs from the '{}' to the '{}' store.",
+        entries.len(),
+        from.as_str(),
+        to.as_str()
+    );
+    Ok(())
+}
@@ -0,0 +1,62 @@
+//! Migrate-frontmatter subcommand implementation.
+//!
+//! Rewrite the frontmatter of every structure .md file as YAML (`---`) or
+//! TOML (`+++`), keeping the bodies, and record the new `frontmatter-format`
+//! in config.json so that files written later match.
+
+use crate::config::ProjectConfig;
+use crate::structure::frontmatter::{convert, detect};
+use crate::structure::{read_structure_files, FrontmatterFormat};
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+/// Run the migrate-frontmatter subcommand.
+pub async fn handle_migrate_frontmatter(
+    project_root: PathBuf,
+    to: FrontmatterFormat,
+    dry_run: bool,
+) -> Result<()> {
+    let project_root = project_root
+        .canonicalize()
+        .context("Failed to resolve project root")?;
+    let mut config = ProjectConfig::open(&project_root)?;
+    let structure_root = config.structure_root_path()?;
+
+    let mut files: Vec<String> = read_structure_files(&structure_root)?.into_keys().collect();
+    files.sort();
+
+    let mut changed = Vec::new();
+    for file in &files {
+        let path = structure_root.join(file);
+        let rewritten = if dry_run {
+            detect(&path)? != to
+        } else {
+            convert(&path, to).with_context(|| format!("Failed to convert {}", file))?
+        };
+        if rewritten {
+            changed.push(file);
+        }
+    }
+
+    if dry_run {
+        println!(
+            "Would rewrite {} structure files with {} frontmatter:",
+            changed.len(),
+            to.as_str()
+        );
+        for file in &changed {
+            println!("  {}", file);
+        }
+        return Ok(());
+    }
+
+    config.frontmatter_format = Some(to);
+    config.save(&project_root)?;
+
+    println!(
+        "Rewrote {} structure files with {} frontmatter.",
+        changed.len(),
+        to.as_str()
+    );
+    Ok(())
+}
@@ -0,0 +1,132 @@
+//! Migrate-layout subcommand implementation.
+//!
+//! Move structure .md files from the configured naming scheme to another one,
+//! rekey stubs.json to match, and record the new `structure-layout` in config.json.
+
+use crate::config::ProjectConfig;
+use crate::structure::layout::relocations;
+use crate::structure::schema::{load_map, save_map};
+use crate::structure::{read_structure_files, LayoutKind, StubEntry};
+use anyhow::{bail, Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Run the migrate-layout subcommand.
+pub async fn handle_migrate_layout(
+    project_root: PathBuf,
+    to: LayoutKind,
+    dry_run: bool,
+) -> Result<()> {
+    let project_root = project_root
+        .canonicalize()
+        .context("Failed to resolve project root")?;
+    let mut config = ProjectConfig::open(&project_root)?;
+    let structure_root = config.structure_root_path()?;
+    let from = config.structure_layout.unwrap_or_default();
+
+    let files = read_structure_files(&structure_root)?;
+    let moves = relocations(&files, from.layout(), to.layout());
+    check_targets(&files, &moves)?;
+
+    if dry_run {
+        println!(
+            "Would move {} structure files from the '{}' to the '{}' layout:",
+            moves.len(),
+            from.as_str(),
+            to.as_str()
+        );
+        for (current, target) in &moves {
+            println!("  {} -> {}", current, target);
+        }
+        return Ok(());
+    }
+
+    move_files(&structure_root, &moves)?;
+
+    let stubs_path = config.stubs_path();
+    if stubs_path.exists() {
+        rekey_stubs(&stubs_path, &moves)?;
+    }
+
+    config.structure_layout = Some(to);
+    config.save(&project_root)?;
+
+    println!(
+        "Moved {} structure files from the '{}' to the '{}' layout.",
+        moves.len(),
+        from.as_str(),
+        to.as_str()
+    );
+    Ok(())
+}
+
+/// Refuse to migrate if two files would land on the same path.
+fn check_targets(files: &HashMap<String, StubEntry>, moves: &[(String, String)]) -> Result<()> {
+    let moved: HashSet<&str> = moves.iter().map(|(current, _)| current.as_str()).collect();
+    let mut taken: HashSet<&str> = files
+        .keys()
+        .map(String::as_str)
+        .filter(|path| !moved.contains(path))
+        .collect();
+
+    for (current, target) in moves {
+        if !taken.insert(target) {
+            bail!(
+                "Cannot move {} to {}: another structure file already uses that path",
+                current,
+                target
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Move files in two passes so that a target may reuse another file's old path.
+fn move_files(structure_root: &Path, moves: &[(String, String)]) -> Result<()> {
+    let mut contents = Vec::with_capacity(moves.len());
+    for (current, target) in moves {
+        let path = structure_root.join(current);
+        let content =
+            std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        std::fs::remove_file(&path)?;
+        contents.push((target, content));
+    }
+
+    for (target, content) in contents {
+        let path = structure_root.join(target);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, content)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+
+    // Remove directories the old layout leaves empty; remove_dir fails on non-empty ones.
+    for entry in WalkDir::new(structure_root)
+        .min_depth(1)
+        .contents_first(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        if entry.file_type().is_dir() {
+            let _ = std::fs::remove_dir(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// Rename stubs.json keys so enrichment survives without re-running atomize.
+fn rekey_stubs(stubs_path: &Path, moves: &[(String, String)]) -> Result<()> {
+    let mut stubs: HashMap<String, StubEntry> = load_map(stubs_path)?;
+
+    let entries: Vec<(&String, StubEntry)> = moves
+        .iter()
+        .filter_map(|(current, target)| stubs.remove(current).map(|stub| (target, stub)))
+        .collect();
+    for (target, stub) in entries {
+        stubs.insert(target.clone(), stub);
+    }
+
+    save_map(stubs_path, &stubs)
+}
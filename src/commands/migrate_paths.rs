@@ -0,0 +1,85 @@
+//! Migrate-paths subcommand implementation.
+//!
+//! Checkouts written on Windows can store backslash-separated `path` and
+//! `dependencies` entries in meta files, which the server and other platforms
+//! treat as different nodes. Rewrite them with forward slashes.
+
+use crate::exit::fail;
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+const META_SUFFIX: &str = ".meta.verilib";
+
+/// Run the migrate-paths subcommand.
+pub async fn handle_migrate_paths(project_root: PathBuf, dry_run: bool) -> Result<()> {
+    let verilib_dir = project_root.join(".verilib");
+    if !verilib_dir.exists() {
+        fail!(
+            MissingPrerequisite,
+            "No .verilib directory found. Please run 'init' first."
+        );
+    }
+
+    let mut changed = Vec::new();
+    for entry in WalkDir::new(&verilib_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        let path = entry.path();
+        if !entry.file_type().is_file() || !path.to_string_lossy().ends_with(META_SUFFIX) {
+            continue;
+        }
+        if let Some(meta) = normalized_meta(path)? {
+            if !dry_run {
+                fs::write(path, serde_json::to_string_pretty(&meta)?)
+                    .with_context(|| format!("Failed to write file: {:?}", path))?;
+            }
+            changed.push(
+                path.strip_prefix(&verilib_dir)
+                    .unwrap_or(path)
+                    .to_path_buf(),
+            );
+        }
+    }
+
+    if changed.is_empty() {
+        println!("All meta paths already use forward slashes.");
+        return Ok(());
+    }
+    let verb = if dry_run { "Would rewrite" } else { "Rewrote" };
+    println!("{} paths in {} meta files:", verb, changed.len());
+    for path in &changed {
+        println!("  {}", path.display());
+    }
+    Ok(())
+}
+
+/// The meta file at `path` with forward slashes in `path` and `dependencies`,
+/// or `None` if nothing changes. Files that don't parse are left alone.
+fn normalized_meta(path: &Path) -> Result<Option<Value>> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read file: {:?}", path))?;
+    let Ok(mut meta) = serde_json::from_str::<Value>(&content) else {
+        return Ok(None);
+    };
+
+    let mut changed = false;
+    let mut fix = |value: &mut Value| {
+        if let Some(text) = value.as_str().filter(|text| text.contains('\\')) {
+            *value = Value::String(text.replace('\\', "/"));
+            changed = true;
+        }
+    };
+    if let Some(path) = meta.get_mut("path") {
+        fix(path);
+    }
+    if let Some(dependencies) = meta.get_mut("dependencies").and_then(|d| d.as_array_mut()) {
+        dependencies.iter_mut().for_each(&mut fix);
+    }
+
+    Ok(changed.then_some(meta))
+}
@@ -1,20 +1,86 @@
 pub mod api;
+pub mod atomization_status;
 pub mod atomize;
+pub mod atomize_renames;
 pub mod auth;
+pub mod auth_check;
+pub mod code_locate;
+pub mod convert;
 pub mod create;
 pub mod deploy;
+pub mod deploy_secrets;
+pub mod doctor;
+pub mod drift;
+pub mod export;
+pub mod export_vbom;
+pub mod flag_log;
+pub mod functions;
+pub mod generate_fixture;
+pub mod graph;
+pub mod grep;
+pub mod history;
 pub mod init;
+pub mod keys;
+pub mod layouts;
+pub mod metrics;
+pub mod migrate_certs;
+pub mod migrate_frontmatter;
+pub mod migrate_layout;
+pub mod migrate_paths;
+pub mod pack;
+pub mod prune;
 pub mod reclone;
+pub mod reindex;
+pub mod run;
+pub mod schema;
+pub mod selftest;
 pub mod specify;
+pub mod stats;
+pub mod stats_diff;
 pub mod status;
+pub mod sync_flags;
+pub mod template;
 pub mod types;
 pub mod verify;
+pub mod verify_certs;
+pub mod verify_diff;
+pub mod verify_import;
+pub mod verify_matrix;
 
+pub use atomization_status::handle_atomization_status;
 pub use atomize::handle_atomize;
 pub use auth::handle_auth;
+pub use code_locate::handle_code_locate;
+pub use convert::handle_convert;
 pub use create::handle_create;
+pub use deploy::handle_deploy;
+pub use doctor::handle_doctor;
+pub use drift::handle_drift;
+pub use export::{handle_export, ExportFormat, ExportOptions};
+pub use flag_log::handle_flag_log_show;
+pub use functions::{handle_functions_list, FunctionsListOptions};
+pub use generate_fixture::{handle_generate_fixture, FixtureOptions};
+pub use graph::handle_graph;
+pub use grep::{handle_grep, GrepOptions};
+pub use history::handle_history;
 pub use init::handle_init;
+pub use keys::{handle_keys, KeysSubcommand};
+pub use layouts::handle_layouts_check;
+pub use metrics::handle_metrics_show;
+pub use migrate_certs::handle_migrate_certs;
+pub use migrate_frontmatter::handle_migrate_frontmatter;
+pub use migrate_layout::handle_migrate_layout;
+pub use migrate_paths::handle_migrate_paths;
+pub use pack::{handle_pack, handle_unpack};
+pub use prune::handle_prune;
 pub use reclone::handle_reclone;
+pub use reindex::handle_reindex;
+pub use run::{handle_run, RunOptions};
+pub use schema::handle_schema;
+pub use selftest::handle_selftest;
 pub use specify::handle_specify;
+pub use stats::handle_stats;
 pub use status::handle_status;
-pub use verify::handle_verify;
+pub use sync_flags::{handle_sync_flags, SyncDirection};
+pub use verify::{handle_verify, VerifyOptions};
+pub use verify_certs::handle_verify_certs;
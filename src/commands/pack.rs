@@ -0,0 +1,369 @@
+//! Pack and unpack subcommand implementations.
+//!
+//! Store the `.verilib` directory as a single zstd-compressed tar archive,
+//! e.g. to upload it as one CI artifact instead of thousands of small files
+//! with percent-encoded names. The archive is canonical: entries are sorted,
+//! paths use `/`, and timestamps, owners and permissions are fixed, so packing
+//! the same tree twice gives the same bytes.
+
+use crate::output;
+use crate::paths::{join_inside, slash};
+use anyhow::{bail, Context, Result};
+use glob::Pattern;
+use serde::Serialize;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Top-level directory of every entry.
+const ROOT: &str = ".verilib";
+
+/// zstd level; part of what makes archives reproducible.
+const COMPRESSION_LEVEL: i32 = 19;
+
+#[derive(Debug, Serialize)]
+struct PackReport<'a> {
+    archive: String,
+    files: &'a [String],
+    bytes: u64,
+    /// Files of a dry-run unpack that already exist and need `--force`.
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    existing: &'a [String],
+}
+
+/// Run the pack subcommand.
+pub async fn handle_pack(
+    project_root: PathBuf,
+    output: PathBuf,
+    exclude: Vec<String>,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    let verilib = project_root.join(ROOT);
+    if !verilib.is_dir() {
+        bail!("{} not found; nothing to pack.", verilib.display());
+    }
+    let exclude = patterns(&exclude)?;
+    let skip = output.canonicalize().ok();
+
+    let files = collect(&verilib, &exclude, skip.as_deref())?;
+    let names: Vec<String> = files.iter().map(|(name, _)| name.clone()).collect();
+    let mut bytes = 0;
+    if !dry_run {
+        bytes = crate::interrupt::write_atomic_with(&output, |file| {
+            let file = pack(&files, io::BufWriter::new(file))?
+                .into_inner()
+                .map_err(io::IntoInnerError::into_error)?;
+            Ok(file.metadata()?.len())
+        })?;
+    }
+
+    if json {
+        let report = PackReport {
+            archive: output.display().to_string(),
+            files: &names,
+            bytes,
+            existing: &[],
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else if dry_run {
+        for name in &names {
+            println!("{}", name);
+        }
+        println!("Would pack {} files into {}", names.len(), output.display());
+    } else {
+        println!(
+            "Packed {} files into {} ({} bytes)",
+            names.len(),
+            output.display(),
+            bytes
+        );
+    }
+    Ok(())
+}
+
+/// Run the unpack subcommand.
+pub async fn handle_unpack(
+    archive: PathBuf,
+    project_root: PathBuf,
+    exclude: Vec<String>,
+    force: bool,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    let exclude = patterns(&exclude)?;
+    let open = || {
+        std::fs::File::open(&archive)
+            .with_context(|| format!("Failed to open {}", archive.display()))
+    };
+    let entries = list_entries(open()?, &project_root, &exclude)
+        .with_context(|| format!("Failed to read {}", archive.display()))?;
+
+    let relative = |path: &PathBuf| slash(path.strip_prefix(&project_root).unwrap_or(path));
+    let existing: Vec<String> = if force {
+        Vec::new()
+    } else {
+        entries
+            .iter()
+            .map(|(path, _)| path)
+            .filter(|path| path.exists())
+            .map(relative)
+            .collect::<Result<_>>()?
+    };
+    if !existing.is_empty() && !dry_run {
+        bail!(
+            "{} files in the archive already exist, e.g. {}. Use --force to overwrite them.",
+            existing.len(),
+            existing[0]
+        );
+    }
+    if !dry_run {
+        extract(open()?, &project_root, &exclude)
+            .with_context(|| format!("Failed to unpack {}", archive.display()))?;
+    }
+
+    let names: Vec<String> = entries
+        .iter()
+        .map(|(path, _)| relative(path))
+        .collect::<Result<_>>()?;
+    if json {
+        let report = PackReport {
+            archive: archive.display().to_string(),
+            files: &names,
+            bytes: entries.iter().map(|(_, size)| size).sum(),
+            existing: &existing,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else if dry_run {
+        for name in &names {
+            println!("{}", name);
+        }
+        println!("Would unpack {} files", names.len());
+        if !existing.is_empty() {
+            output::warn(format!(
+                "{} of them already exist; unpack needs --force to overwrite:",
+                existing.len()
+            ));
+            for name in &existing {
+                eprintln!("  {}", name);
+            }
+        }
+    } else {
+        println!("Unpacked {} files from {}", names.len(), archive.display());
+    }
+    Ok(())
+}
+
+/// The contents of `name` (relative to `.verilib`, e.g. `stubs.json`) in an
+/// archive written by `pack`, or `None` if the archive has no such file.
+pub fn read_packed_file(archive: &Path, name: &str) -> Result<Option<Vec<u8>>> {
+    let file = std::fs::File::open(archive)
+        .with_context(|| format!("Failed to open {}", archive.display()))?;
+    let mut tar = tar::Archive::new(zstd::Decoder::new(file)?);
+    let wanted = format!("{}/{}", ROOT, name);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        if *entry.path_bytes() == *wanted.as_bytes() {
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents)?;
+            return Ok(Some(contents));
+        }
+    }
+    Ok(None)
+}
+
+fn patterns(globs: &[String]) -> Result<Vec<Pattern>> {
+    globs
+        .iter()
+        .map(|glob| Pattern::new(glob).with_context(|| format!("Invalid --exclude '{}'", glob)))
+        .collect()
+}
+
+/// Whether `name` (relative to `.verilib`, with `/`) or one of its parent
+/// directories matches an exclude pattern.
+fn excluded(name: &str, exclude: &[Pattern]) -> bool {
+    let mut prefix = String::new();
+    name.split('/').any(|segment| {
+        if !prefix.is_empty() {
+            prefix.push('/');
+        }
+        prefix.push_str(segment);
+        exclude.iter().any(|pattern| pattern.matches(&prefix))
+    })
+}
+
+/// The regular files under `verilib`, as sorted `(entry name, path)` pairs.
+/// Symlinks are skipped, as the archive only carries file contents.
+fn collect(
+    verilib: &Path,
+    exclude: &[Pattern],
+    skip: Option<&Path>,
+) -> Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(verilib).sort_by_file_name() {
+        let entry = entry.context("Failed to read .verilib")?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        if skip.is_some_and(|skip| entry.path().canonicalize().ok().as_deref() == Some(skip)) {
+            continue;
+        }
+        let relative = slash(entry.path().strip_prefix(verilib)?)?;
+        if excluded(&relative, exclude) {
+            continue;
+        }
+        files.push((format!("{}/{}", ROOT, relative), entry.path().to_path_buf()));
+    }
+    // walkdir sorts siblings by OS file name; sort the full names so the
+    // order doesn't depend on the platform.
+    files.sort();
+    Ok(files)
+}
+
+/// Write the compressed archive of `files` to `out` and return it. Only one
+/// file is held in memory at a time.
+fn pack<W: Write>(files: &[(String, PathBuf)], out: W) -> Result<W> {
+    let encoder = zstd::Encoder::new(out, COMPRESSION_LEVEL)?;
+    let mut builder = tar::Builder::new(encoder);
+    for (name, path) in files {
+        let contents =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        header.set_uid(0);
+        header.set_gid(0);
+        builder
+            .append_data(&mut header, name, contents.as_slice())
+            .with_context(|| format!("Failed to add {}", name))?;
+    }
+    Ok(builder.into_inner()?.finish()?)
+}
+
+/// The `(destination, size)` of every file in an archive made by [`pack`],
+/// under `project_root`. Every entry is checked without writing anything:
+/// only regular files under `.verilib/` with safe path segments are accepted.
+fn list_entries(
+    archive: impl Read,
+    project_root: &Path,
+    exclude: &[Pattern],
+) -> Result<Vec<(PathBuf, u64)>> {
+    let mut tar = tar::Archive::new(zstd::Decoder::new(archive)?);
+    let mut files = Vec::new();
+    for entry in tar.entries()? {
+        let entry = entry?;
+        if let Some(path) = destination(&entry, project_root, exclude)? {
+            files.push((path, entry.header().size()?));
+        }
+    }
+    Ok(files)
+}
+
+/// Write every file of an archive already checked by [`list_entries`],
+/// streaming each entry to disk. Returns the paths written.
+fn extract(archive: impl Read, project_root: &Path, exclude: &[Pattern]) -> Result<Vec<PathBuf>> {
+    let mut tar = tar::Archive::new(zstd::Decoder::new(archive)?);
+    let mut written = Vec::new();
+    for entry in tar.entries()? {
+        let entry = entry?;
+        let Some(path) = destination(&entry, project_root, exclude)? else {
+            continue;
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        crate::interrupt::write_atomic_from(&path, entry)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Where `entry` goes under `project_root`, `None` for directories and
+/// excluded files, or an error for anything `pack` does not write.
+fn destination(
+    entry: &tar::Entry<'_, impl Read>,
+    project_root: &Path,
+    exclude: &[Pattern],
+) -> Result<Option<PathBuf>> {
+    let name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+    match entry.header().entry_type() {
+        tar::EntryType::Regular => {}
+        tar::EntryType::Directory => return Ok(None),
+        other => bail!("entry '{}' is a {:?}, not a file", name, other),
+    }
+    let Some(relative) = name.strip_prefix(&format!("{}/", ROOT)) else {
+        bail!("entry '{}' is not under {}/", name, ROOT);
+    };
+    let path = join_inside(&project_root.join(ROOT), relative)
+        .with_context(|| format!("entry '{}'", name))?;
+    if excluded(relative, exclude) {
+        return Ok(None);
+    }
+    Ok(Some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_pack_is_deterministic_and_round_trips() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let verilib = dir.path().join(ROOT);
+        fs::create_dir_all(verilib.join("certs/specs")).unwrap();
+        fs::create_dir_all(verilib.join("logs")).unwrap();
+        fs::write(verilib.join("config.json"), "{}").unwrap();
+        fs::write(verilib.join("certs/specs/probe%3Aa%28%29.json"), "{}").unwrap();
+        fs::write(verilib.join("logs/run.log"), "noise").unwrap();
+
+        let exclude = patterns(&["logs".to_string()]).unwrap();
+        let files = collect(&verilib, &exclude, None).unwrap();
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                ".verilib/certs/specs/probe%3Aa%28%29.json",
+                ".verilib/config.json"
+            ]
+        );
+
+        let first = pack(&files, Vec::new()).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        fs::write(verilib.join("config.json"), "{}").unwrap();
+        assert_eq!(pack(&files, Vec::new()).unwrap(), first);
+
+        let target = tempfile::TempDir::new().unwrap();
+        let listed = list_entries(first.as_slice(), target.path(), &[]).unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[1], (target.path().join(".verilib/config.json"), 2));
+        let written = extract(first.as_slice(), target.path(), &[]).unwrap();
+        assert_eq!(written[1], target.path().join(".verilib/config.json"));
+        assert_eq!(fs::read(&written[1]).unwrap(), b"{}");
+    }
+
+    #[test]
+    fn test_unpack_rejects_entries_outside_verilib() {
+        for name in [".verilib/../escape", "elsewhere/file", ".verilib/CON"] {
+            let mut builder = tar::Builder::new(zstd::Encoder::new(Vec::new(), 1).unwrap());
+            let mut header = tar::Header::new_gnu();
+            header.set_size(1);
+            header.set_entry_type(tar::EntryType::Regular);
+            // Set the raw name: set_path refuses `..`.
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_cksum();
+            builder.append(&header, &b"x"[..]).unwrap();
+            let archive = builder.into_inner().unwrap().finish().unwrap();
+
+            let dir = tempfile::TempDir::new().unwrap();
+            assert!(
+                list_entries(archive.as_slice(), dir.path(), &[]).is_err(),
+                "{}",
+                name
+            );
+        }
+    }
+}
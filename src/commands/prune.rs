@@ -0,0 +1,161 @@
+//! Prune subcommand implementation.
+//!
+//! Cross-reference structure files, atoms.json, stubs.json and spec certs, and
+//! remove what deleted functions left behind: stubs entries whose structure
+//! file is gone, and certs for functions that no longer have a structure file
+//! or an atom.
+
+use crate::config::ProjectConfig;
+use crate::structure::probe_output::load_probe_map;
+use crate::structure::schema::{load_map, save_map};
+use crate::structure::{read_structure_files, Atom, StubEntry};
+use anyhow::{bail, Context, Result};
+use dialoguer::Confirm;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+/// Entries `prune` would remove.
+#[derive(Debug, Default, Serialize)]
+pub struct PruneReport {
+    /// stubs.json keys without a structure file.
+    pub stubs: Vec<String>,
+    /// Code-names of orphaned spec certs.
+    pub certs: Vec<String>,
+}
+
+impl PruneReport {
+    fn is_empty(&self) -> bool {
+        self.stubs.is_empty() && self.certs.is_empty()
+    }
+}
+
+/// Run the prune subcommand.
+pub async fn handle_prune(
+    project_root: PathBuf,
+    yes: bool,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    let project_root = project_root
+        .canonicalize()
+        .context("Failed to resolve project root")?;
+    let config = ProjectConfig::open(&project_root)?;
+
+    let stubs_path = config.stubs_path();
+    let mut stubs: HashMap<String, StubEntry> = if stubs_path.exists() {
+        load_map(&stubs_path)?
+    } else {
+        HashMap::new()
+    };
+    let report = find_orphans(&config, &stubs)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+    if report.is_empty() || dry_run {
+        return Ok(());
+    }
+
+    if !yes && !confirm(&report)? {
+        bail!("Prune cancelled; nothing was removed.");
+    }
+
+    for key in &report.stubs {
+        stubs.remove(key);
+    }
+    if !report.stubs.is_empty() {
+        save_map(&stubs_path, &stubs)?;
+    }
+    let orphans: HashSet<&str> = report.certs.iter().map(String::as_str).collect();
+    config.spec_certs().remove_names(&orphans)?;
+
+    if !json {
+        println!(
+            "Removed {} stubs.json entries and {} certs.",
+            report.stubs.len(),
+            report.certs.len()
+        );
+    }
+    Ok(())
+}
+
+/// Stubs entries whose structure file is gone, and certs whose function is not
+/// named by any remaining structure file or stub, or is missing from atoms.json.
+fn find_orphans(config: &ProjectConfig, stubs: &HashMap<String, StubEntry>) -> Result<PruneReport> {
+    let files = read_structure_files(&config.structure_root_path()?)?;
+
+    let mut stale: Vec<String> = stubs
+        .keys()
+        .filter(|key| !files.contains_key(*key))
+        .cloned()
+        .collect();
+    stale.sort();
+
+    let mut live: HashSet<&str> = files
+        .values()
+        .chain(
+            stubs
+                .iter()
+                .filter(|(key, _)| files.contains_key(*key))
+                .map(|(_, stub)| stub),
+        )
+        .filter_map(|stub| stub.code_name.as_deref())
+        .collect();
+
+    let atoms_path = config.atoms_path();
+    if atoms_path.exists() {
+        let atoms: HashMap<String, Atom> = load_probe_map(&atoms_path)?;
+        live.retain(|name| atoms.contains_key(*name));
+    }
+
+    let mut certs: Vec<String> = config
+        .spec_certs()
+        .names()?
+        .into_iter()
+        .filter(|name| !live.contains(name.as_str()))
+        .collect();
+    certs.sort();
+
+    Ok(PruneReport {
+        stubs: stale,
+        certs,
+    })
+}
+
+fn print_report(report: &PruneReport) {
+    if report.is_empty() {
+        println!("Nothing to prune.");
+        return;
+    }
+    if !report.stubs.is_empty() {
+        println!("Stale stubs.json entries (no structure file):");
+        for key in &report.stubs {
+            println!("  - {}", key);
+        }
+    }
+    if !report.certs.is_empty() {
+        println!("Orphaned certs (function no longer exists):");
+        for name in &report.certs {
+            println!("  - {}", name);
+        }
+    }
+}
+
+fn confirm(report: &PruneReport) -> Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        bail!("Refusing to prune without confirmation; pass --yes to remove these entries.");
+    }
+    Confirm::new()
+        .with_prompt(format!(
+            "Remove {} stubs.json entries and {} certs?",
+            report.stubs.len(),
+            report.certs.len()
+        ))
+        .default(false)
+        .interact()
+        .context("Failed to read confirmation")
+}
@@ -1,33 +1,33 @@
 use anyhow::{Context, Result};
-use reqwest::Client;
 use serde_json::Value;
 use std::path::PathBuf;
 use std::process::Command;
 
-use crate::commands::status::get_stored_api_key;
-use crate::config::ProjectConfig;
-use crate::constants::{auth_required_msg, init_required_msg};
-use crate::download::handle_api_error;
+use crate::commands::auth_check::checked_api_key;
+use crate::config::{resolve_base_url, ProjectConfig};
+use crate::constants::init_required_msg;
+use crate::download::{api_error, ApiClient, ApiOperation};
+use crate::exit::{ExitContext, ExitKind};
+use crate::output;
 
-pub async fn handle_reclone(debug: bool) -> Result<()> {
+pub async fn handle_reclone(base_url: Option<String>, debug: bool) -> Result<()> {
     if debug {
         println!("Debug: Starting reclone process...");
     } else {
         println!("Starting reclone process...");
     }
 
-    // Check if authentication exists
-    get_stored_api_key().context(auth_required_msg())?;
-
     let project_root = PathBuf::from(".");
     let config = ProjectConfig::load(&project_root)?;
 
     let repo = config
         .repo
-        .ok_or_else(|| anyhow::anyhow!(init_required_msg()))?;
+        .ok_or_else(|| anyhow::anyhow!(init_required_msg()))
+        .exit_kind(ExitKind::MissingPrerequisite)?;
 
+    let url_base = resolve_base_url(base_url.as_deref(), Some(&repo));
     let repo_id = repo.id;
-    let url_base = repo.url;
+    let api_key = checked_api_key(&url_base, &repo_id).await?;
 
     println!("Found repository ID: {}", repo_id);
     if debug {
@@ -41,30 +41,26 @@ pub async fn handle_reclone(debug: bool) -> Result<()> {
 
     // Check for uncommitted changes
     if has_uncommitted_changes()? {
-        println!("Warning: You have uncommitted changes in your git repository.");
-        println!("Please commit or stash your changes before running reclone.");
+        output::warn("you have uncommitted changes in your git repository.");
+        eprintln!("Please commit or stash your changes before running reclone.");
         anyhow::bail!("Uncommitted changes detected");
     }
 
     // Check for unpushed commits
     if has_unpushed_commits()? {
-        println!("Warning: You have unpushed commits in your git repository.");
-        println!("Please push your changes before running reclone.");
+        output::warn("you have unpushed commits in your git repository.");
+        eprintln!("Please push your changes before running reclone.");
         anyhow::bail!("Unpushed commits detected");
     }
 
     // Perform the reclone API call
-    let api_key = get_stored_api_key()?;
     let endpoint = format!("{}/v2/repo/reclone/{}", url_base, repo_id);
 
     println!("Calling reclone endpoint: {}", endpoint);
 
-    let client = Client::new();
+    let client = ApiClient::for_project(&api_key)?;
     let response = client
-        .post(&endpoint)
-        .header("Authorization", format!("ApiKey {}", api_key))
-        .header("Accept", "application/json")
-        .send()
+        .send(client.post(&endpoint).header("Accept", "application/json"))
         .await
         .context("Failed to send reclone request")?;
 
@@ -75,8 +71,7 @@ pub async fn handle_reclone(debug: bool) -> Result<()> {
     }
 
     if !status.is_success() {
-        let error_msg = handle_api_error(response).await?;
-        anyhow::bail!(error_msg);
+        return Err(api_error(response, ApiOperation::Reclone).await);
     }
 
     let response_text = response
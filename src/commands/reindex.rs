@@ -0,0 +1,488 @@
+//! Atom index allocation and renumbering.
+//!
+//! Atom and meta files are named `[N] - <identifier>.{atom,meta}.verilib`,
+//! with `N` mirrored in the meta `index` field. Indices come both from the
+//! server (on pull) and from local allocation (on `api create-file`), so
+//! after merges two atoms in a directory can end up sharing an index.
+
+use crate::exit::fail;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const ATOM_SUFFIX: &str = ".atom.verilib";
+const META_SUFFIX: &str = ".meta.verilib";
+
+fn index_prefix_re() -> Regex {
+    Regex::new(r"^\[(\d+)\]\s*-\s*").unwrap()
+}
+
+/// One atom in a directory: its current index and identifier.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct IndexedAtom {
+    index: u32,
+    identifier: String,
+}
+
+/// Index usage of a single directory.
+#[derive(Debug, Default)]
+pub struct DirIndices {
+    /// Lowest index greater than every index in use.
+    pub next_free: u32,
+    /// Indices shared by more than one atom, with the identifiers using them.
+    pub duplicates: BTreeMap<u32, Vec<String>>,
+}
+
+fn scan_atoms(dir: &Path) -> Result<Vec<IndexedAtom>> {
+    let re = index_prefix_re();
+    let mut atoms = Vec::new();
+
+    if !dir.exists() {
+        return Ok(atoms);
+    }
+
+    for entry in
+        fs::read_dir(dir).with_context(|| format!("Failed to read directory: {:?}", dir))?
+    {
+        let file_name = entry?.file_name().to_string_lossy().to_string();
+        let Some(stem) = file_name
+            .strip_suffix(ATOM_SUFFIX)
+            .or_else(|| file_name.strip_suffix(META_SUFFIX))
+        else {
+            continue;
+        };
+        let Some(caps) = re.captures(stem) else {
+            continue;
+        };
+        let Ok(index) = caps[1].parse::<u32>() else {
+            continue;
+        };
+        let atom = IndexedAtom {
+            index,
+            identifier: stem[caps[0].len()..].to_string(),
+        };
+        if !atoms.contains(&atom) {
+            atoms.push(atom);
+        }
+    }
+
+    atoms.sort();
+    Ok(atoms)
+}
+
+/// Scan a directory for allocated indices and collisions.
+pub fn scan_indices(dir: &Path) -> Result<DirIndices> {
+    let atoms = scan_atoms(dir)?;
+
+    let mut by_index: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+    for atom in &atoms {
+        by_index
+            .entry(atom.index)
+            .or_default()
+            .push(atom.identifier.clone());
+    }
+
+    Ok(DirIndices {
+        next_free: atoms.iter().map(|a| a.index + 1).max().unwrap_or(0),
+        duplicates: by_index
+            .into_iter()
+            .filter(|(_, ids)| ids.len() > 1)
+            .collect(),
+    })
+}
+
+fn file_stem(index: u32, identifier: &str) -> String {
+    format!("[{}] - {}", index, identifier)
+}
+
+/// Planned renumbering of one atom, as paths relative to `.verilib`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Renumber {
+    pub dir: PathBuf,
+    pub identifier: String,
+    pub from: u32,
+    pub to: u32,
+}
+
+impl Renumber {
+    fn old_stem(&self) -> String {
+        file_stem(self.from, &self.identifier)
+    }
+
+    fn new_stem(&self) -> String {
+        file_stem(self.to, &self.identifier)
+    }
+}
+
+/// Plan a dense `0..n` renumbering of a directory, keeping the current order
+/// (by index, then identifier). Only atoms whose index changes are returned.
+fn plan_directory(verilib_dir: &Path, dir: &Path) -> Result<Vec<Renumber>> {
+    let relative = dir.strip_prefix(verilib_dir).unwrap_or(dir).to_path_buf();
+
+    Ok(scan_atoms(dir)?
+        .into_iter()
+        .enumerate()
+        .filter(|(new_index, atom)| atom.index != *new_index as u32)
+        .map(|(new_index, atom)| Renumber {
+            dir: relative.clone(),
+            identifier: atom.identifier,
+            from: atom.index,
+            to: new_index as u32,
+        })
+        .collect())
+}
+
+/// Rename files in two passes through temporary names so that swapped
+/// indices never overwrite each other, and rewrite the meta `index` field.
+/// If any step fails, the renames and rewrites done so far are undone.
+fn apply_directory(verilib_dir: &Path, plan: &[Renumber]) -> Result<()> {
+    let mut journal = Journal::default();
+    match rename_directory(verilib_dir, plan, &mut journal) {
+        Ok(()) => Ok(()),
+        Err(err) => Err(journal.roll_back(err)),
+    }
+}
+
+/// Completed steps of [`apply_directory`], in order, so they can be undone.
+#[derive(Debug, Default)]
+struct Journal {
+    renames: Vec<(PathBuf, PathBuf)>,
+    rewrites: Vec<(PathBuf, String)>,
+}
+
+impl Journal {
+    fn rename(&mut self, from: &Path, to: &Path) -> Result<()> {
+        fs::rename(from, to).with_context(|| format!("Failed to rename {:?}", from))?;
+        self.renames.push((from.to_path_buf(), to.to_path_buf()));
+        Ok(())
+    }
+
+    /// Undo the recorded steps, newest first. Files that cannot be restored
+    /// are listed in the returned error with the name they were left under.
+    fn roll_back(self, err: anyhow::Error) -> anyhow::Error {
+        let mut stranded = Vec::new();
+        for (path, content) in self.rewrites.into_iter().rev() {
+            if fs::write(&path, content).is_err() {
+                stranded.push(format!("{:?} (index not restored)", path));
+            }
+        }
+        for (from, to) in self.renames.into_iter().rev() {
+            if fs::rename(&to, &from).is_err() {
+                stranded.push(format!("{:?} (was {:?})", to, from));
+            }
+        }
+        if stranded.is_empty() {
+            err.context("Reindex rolled back")
+        } else {
+            err.context(format!(
+                "Reindex could not be fully rolled back; restore these by hand: {}",
+                stranded.join(", ")
+            ))
+        }
+    }
+}
+
+fn rename_directory(verilib_dir: &Path, plan: &[Renumber], journal: &mut Journal) -> Result<()> {
+    let mut staged = Vec::new();
+
+    for (i, item) in plan.iter().enumerate() {
+        let dir = verilib_dir.join(&item.dir);
+        for suffix in [ATOM_SUFFIX, META_SUFFIX] {
+            let from = dir.join(format!("{}{}", item.old_stem(), suffix));
+            if !from.exists() {
+                continue;
+            }
+            let temp = dir.join(format!(".reindex-{}{}", i, suffix));
+            let to = dir.join(format!("{}{}", item.new_stem(), suffix));
+            journal.rename(&from, &temp)?;
+            staged.push((temp, to, item.to));
+        }
+    }
+
+    for (temp, to, _) in &staged {
+        if to.exists() {
+            anyhow::bail!("Refusing to overwrite existing file {:?}", to);
+        }
+        journal.rename(temp, to)?;
+    }
+
+    for (_, to, index) in staged {
+        if !to.to_string_lossy().ends_with(META_SUFFIX) {
+            continue;
+        }
+        let content =
+            fs::read_to_string(&to).with_context(|| format!("Failed to read file: {:?}", to))?;
+        let mut meta: Value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse meta file: {:?}", to))?;
+        if let Some(obj) = meta.as_object_mut() {
+            obj.insert("index".to_string(), Value::from(index));
+        }
+        fs::write(&to, serde_json::to_string_pretty(&meta)?)
+            .with_context(|| format!("Failed to write file: {:?}", to))?;
+        journal.rewrites.push((to, content));
+    }
+
+    Ok(())
+}
+
+/// Rewrite `dependencies` entries that point at a renamed atom. Entries are
+/// paths relative to `.verilib`, optionally with a leading `/` and a file
+/// suffix. Returns the number of meta files that changed.
+fn update_dependencies(verilib_dir: &Path, plan: &[Renumber]) -> Result<usize> {
+    let renames: HashMap<String, String> = plan
+        .iter()
+        .map(|item| {
+            let path = |stem: String| item.dir.join(stem).to_string_lossy().replace('\\', "/");
+            (path(item.old_stem()), path(item.new_stem()))
+        })
+        .collect();
+
+    let mut updated = 0;
+
+    for entry in walkdir::WalkDir::new(verilib_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        let path = entry.path();
+        if !path.is_file() || !path.to_string_lossy().ends_with(META_SUFFIX) {
+            continue;
+        }
+
+        let content = fs::read_to_string(path)?;
+        let Ok(mut meta) = serde_json::from_str::<Value>(&content) else {
+            continue;
+        };
+        let Some(deps) = meta.get_mut("dependencies").and_then(|d| d.as_array_mut()) else {
+            continue;
+        };
+
+        let mut changed = false;
+        for dep in deps.iter_mut() {
+            let Some(text) = dep.as_str() else {
+                continue;
+            };
+            if let Some(rewritten) = rewrite_reference(text, &renames) {
+                *dep = Value::String(rewritten);
+                changed = true;
+            }
+        }
+
+        if changed {
+            fs::write(path, serde_json::to_string_pretty(&meta)?)
+                .with_context(|| format!("Failed to write file: {:?}", path))?;
+            updated += 1;
+        }
+    }
+
+    Ok(updated)
+}
+
+fn rewrite_reference(text: &str, renames: &HashMap<String, String>) -> Option<String> {
+    let (lead, rest) = match text.strip_prefix('/') {
+        Some(rest) => ("/", rest),
+        None => ("", text),
+    };
+
+    for suffix in ["", ATOM_SUFFIX, META_SUFFIX] {
+        let Some(stem) = rest.strip_suffix(suffix) else {
+            continue;
+        };
+        if let Some(new_stem) = renames.get(stem) {
+            return Some(format!("{}{}{}", lead, new_stem, suffix));
+        }
+    }
+
+    None
+}
+
+/// Plan a dense `0..n` renumbering of each of `dirs`.
+pub fn plan_renumbering(verilib_dir: &Path, dirs: &[PathBuf]) -> Result<Vec<Renumber>> {
+    let mut plan = Vec::new();
+    for dir in dirs {
+        plan.extend(plan_directory(verilib_dir, dir)?);
+    }
+    Ok(plan)
+}
+
+/// Rename the files of `plan` and rewrite dependency references to them.
+/// Returns the number of meta files whose dependencies changed.
+pub fn apply_renumbering(verilib_dir: &Path, dirs: &[PathBuf], plan: &[Renumber]) -> Result<usize> {
+    for dir in dirs {
+        let relative = dir.strip_prefix(verilib_dir).unwrap_or(dir);
+        let dir_plan: Vec<Renumber> = plan
+            .iter()
+            .filter(|item| item.dir == relative)
+            .cloned()
+            .collect();
+        apply_directory(verilib_dir, &dir_plan)?;
+    }
+    update_dependencies(verilib_dir, plan)
+}
+
+pub async fn handle_reindex(dir: PathBuf, recursive: bool, dry_run: bool) -> Result<()> {
+    let verilib_dir = PathBuf::from(".verilib");
+    if !verilib_dir.exists() {
+        fail!(
+            MissingPrerequisite,
+            "No .verilib directory found. Please run 'init' first."
+        );
+    }
+
+    let target = if dir.starts_with(&verilib_dir) {
+        dir
+    } else {
+        verilib_dir.join(dir)
+    };
+    if !target.is_dir() {
+        anyhow::bail!("Not a directory: {:?}", target);
+    }
+
+    let dirs: Vec<PathBuf> = if recursive {
+        walkdir::WalkDir::new(&target)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_dir())
+            .map(|e| e.path().to_path_buf())
+            .collect()
+    } else {
+        vec![target.clone()]
+    };
+
+    for dir in &dirs {
+        for (index, identifiers) in scan_indices(dir)?.duplicates {
+            println!(
+                "Duplicate index [{}] in {}: {}",
+                index,
+                dir.display(),
+                identifiers.join(", ")
+            );
+        }
+    }
+    let plan = plan_renumbering(&verilib_dir, &dirs)?;
+
+    if plan.is_empty() {
+        println!("Indices in {} are already contiguous.", target.display());
+        return Ok(());
+    }
+
+    for item in &plan {
+        println!(
+            "  {}: [{}] -> [{}]",
+            item.dir.join(&item.identifier).display(),
+            item.from,
+            item.to
+        );
+    }
+
+    if dry_run {
+        println!("DRY RUN - {} atoms would be renumbered.", plan.len());
+        return Ok(());
+    }
+
+    let updated = apply_renumbering(&verilib_dir, &dirs, &plan)?;
+
+    println!("Renumbered {} atoms.", plan.len());
+    if updated > 0 {
+        println!("Updated dependency references in {} meta files.", updated);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn touch_atom(dir: &Path, index: u32, identifier: &str, deps: &[&str]) {
+        let stem = file_stem(index, identifier);
+        fs::write(dir.join(format!("{}{}", stem, ATOM_SUFFIX)), "fn f() {}").unwrap();
+        let meta = serde_json::json!({
+            "identifier": identifier,
+            "index": index,
+            "dependencies": deps,
+        });
+        fs::write(
+            dir.join(format!("{}{}", stem, META_SUFFIX)),
+            meta.to_string(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_scan_detects_duplicates() {
+        let dir = TempDir::new().unwrap();
+        touch_atom(dir.path(), 0, "a.rs", &[]);
+        touch_atom(dir.path(), 2, "b.rs", &[]);
+        touch_atom(dir.path(), 2, "c.rs", &[]);
+
+        let indices = scan_indices(dir.path()).unwrap();
+        assert_eq!(indices.next_free, 3);
+        assert_eq!(
+            indices.duplicates.get(&2),
+            Some(&vec!["b.rs".to_string(), "c.rs".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_reindex_renumbers_and_rewrites_references() {
+        let root = TempDir::new().unwrap();
+        let verilib = root.path().join(".verilib");
+        let src = verilib.join("src");
+        fs::create_dir_all(&src).unwrap();
+        touch_atom(&src, 2, "b.rs", &[]);
+        touch_atom(&src, 2, "c.rs", &["/src/[2] - b.rs"]);
+        touch_atom(&src, 7, "d.rs", &["src/[2] - c.rs.atom.verilib"]);
+
+        let plan = plan_directory(&verilib, &src).unwrap();
+        apply_directory(&verilib, &plan).unwrap();
+        assert_eq!(update_dependencies(&verilib, &plan).unwrap(), 2);
+
+        assert!(src.join("[0] - b.rs.atom.verilib").exists());
+        assert!(src.join("[1] - c.rs.atom.verilib").exists());
+        assert!(src.join("[2] - d.rs.meta.verilib").exists());
+
+        let read = |name: &str| -> Value {
+            serde_json::from_str(&fs::read_to_string(src.join(name)).unwrap()).unwrap()
+        };
+        let c = read("[1] - c.rs.meta.verilib");
+        assert_eq!(c["index"], 1);
+        assert_eq!(c["dependencies"][0], "/src/[0] - b.rs");
+        let d = read("[2] - d.rs.meta.verilib");
+        assert_eq!(d["dependencies"][0], "src/[1] - c.rs.atom.verilib");
+    }
+
+    #[test]
+    fn test_failed_reindex_rolls_back_renames() {
+        let root = TempDir::new().unwrap();
+        let verilib = root.path().join(".verilib");
+        let src = verilib.join("src");
+        fs::create_dir_all(&src).unwrap();
+        touch_atom(&src, 2, "a.rs", &[]);
+        touch_atom(&src, 3, "b.rs", &[]);
+        fs::write(src.join("[3] - b.rs.meta.verilib"), "not json").unwrap();
+        let listing = || {
+            let mut names: Vec<_> = fs::read_dir(&src)
+                .unwrap()
+                .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+                .collect();
+            names.sort();
+            names
+        };
+        let before = listing();
+        let a_meta = fs::read_to_string(src.join("[2] - a.rs.meta.verilib")).unwrap();
+
+        let plan = plan_directory(&verilib, &src).unwrap();
+        let err = apply_directory(&verilib, &plan).unwrap_err();
+
+        assert!(format!("{:#}", err).contains("rolled back"));
+        assert_eq!(listing(), before);
+        assert_eq!(
+            fs::read_to_string(src.join("[2] - a.rs.meta.verilib")).unwrap(),
+            a_meta
+        );
+    }
+}
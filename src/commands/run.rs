@@ -0,0 +1,275 @@
+//! Run subcommand implementation.
+//!
+//! Run the pipeline stages (`create`, `atomize`, `specify`, `verify`) in
+//! order with shared flags, then print how each went. After a stage
+//! succeeds, a fingerprint of the project's inputs (source files, structure
+//! files and config.json) is stored in `.verilib/run.json`. A stage whose
+//! fingerprint still matches is skipped, unless an earlier stage ran in the
+//! same invocation or `--force` is given.
+
+use crate::commands::atomize::Analyzer;
+use crate::commands::{
+    handle_atomize, handle_create, handle_specify, handle_verify, VerifyOptions,
+};
+use crate::config::{PipelineStage, ProjectConfig};
+use crate::exit::fail;
+use crate::freshness::{self, Artifact};
+use crate::i18n::t;
+use crate::output::{self, Table};
+use crate::walk;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Input fingerprints of the stages that last succeeded, under `.verilib`.
+const RUN_RECORD: &str = "run.json";
+
+/// Options for the run subcommand.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Stages to run; `pipeline` in config.json, then the default, when empty.
+    pub stages: Vec<PipelineStage>,
+    pub no_probe: bool,
+    /// Verify profile from config.json.
+    pub profile: Option<String>,
+    /// Run stages whose inputs haven't changed.
+    pub force: bool,
+}
+
+/// What happened to a stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Ran,
+    /// Its inputs were unchanged since it last succeeded.
+    Fresh,
+    Failed,
+    /// An earlier stage failed.
+    NotRun,
+}
+
+impl Outcome {
+    fn label(self) -> &'static str {
+        match self {
+            Outcome::Ran => "ran",
+            Outcome::Fresh => "skipped (fresh)",
+            Outcome::Failed => "failed",
+            Outcome::NotRun => "not run",
+        }
+    }
+}
+
+/// Run the run subcommand.
+pub async fn handle_run(project_root: PathBuf, options: RunOptions) -> Result<()> {
+    let project_root = project_root
+        .canonicalize()
+        .context("Failed to resolve project root")?;
+    let config = ProjectConfig::load(&project_root)?;
+    let stages = stages(&options.stages, &config.pipeline);
+    let record_path = project_root.join(".verilib").join(RUN_RECORD);
+    let mut record = read_record(&record_path);
+
+    let mut results: Vec<(PipelineStage, Outcome, Duration)> = Vec::new();
+    let mut ran_earlier = false;
+    let mut failure = None;
+    for stage in stages {
+        if failure.is_some() {
+            results.push((stage, Outcome::NotRun, Duration::ZERO));
+            continue;
+        }
+        if !options.force && !ran_earlier {
+            let stamp = input_stamp(&project_root);
+            if record.get(stage.name()) == Some(&stamp) {
+                results.push((stage, Outcome::Fresh, Duration::ZERO));
+                continue;
+            }
+        }
+
+        output::heading(stage.name());
+        let started = Instant::now();
+        let outcome = run_stage(stage, &project_root, &options).await;
+        let elapsed = started.elapsed();
+        match outcome {
+            Ok(()) => {
+                ran_earlier = true;
+                record.insert(stage.name().to_string(), input_stamp(&project_root));
+                write_record(&record_path, &record)?;
+                results.push((stage, Outcome::Ran, elapsed));
+            }
+            Err(e) => {
+                results.push((stage, Outcome::Failed, elapsed));
+                failure = Some(e.context(format!("Stage '{}' failed", stage.name())));
+            }
+        }
+        println!();
+    }
+
+    let mut table = Table::new(["STAGE", "RESULT", "TIME"]);
+    for (stage, outcome, elapsed) in &results {
+        let time = match outcome {
+            Outcome::Ran | Outcome::Failed => format!("{:.1}s", elapsed.as_secs_f64()),
+            Outcome::Fresh | Outcome::NotRun => "-".to_string(),
+        };
+        table.row([stage.name(), outcome.label(), time.as_str()]);
+    }
+    table.print();
+
+    match failure {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// The requested stages, else the configured ones, else the default, in
+/// pipeline order and without repeats.
+fn stages(requested: &[PipelineStage], configured: &[PipelineStage]) -> Vec<PipelineStage> {
+    let mut stages = if !requested.is_empty() {
+        requested.to_vec()
+    } else if !configured.is_empty() {
+        configured.to_vec()
+    } else {
+        PipelineStage::DEFAULT.to_vec()
+    };
+    stages.sort();
+    stages.dedup();
+    stages
+}
+
+async fn run_stage(stage: PipelineStage, project_root: &Path, options: &RunOptions) -> Result<()> {
+    let project_root = project_root.to_path_buf();
+    match stage {
+        PipelineStage::Create => handle_create(project_root, None, None, None).await,
+        PipelineStage::Atomize => {
+            handle_atomize(
+                project_root,
+                false,
+                options.no_probe,
+                false,
+                false,
+                Analyzer::default(),
+                None,
+                None,
+                None,
+                false,
+            )
+            .await
+        }
+        PipelineStage::Specify => {
+            handle_specify(
+                project_root,
+                options.no_probe,
+                false,
+                false,
+                false,
+                None,
+                false,
+            )
+            .await
+        }
+        PipelineStage::Verify => {
+            let verify = VerifyOptions {
+                no_probe: options.no_probe,
+                profile: options.profile.clone(),
+                jobs: 1,
+                ..Default::default()
+            };
+            handle_verify(project_root, verify).await
+        }
+    }
+}
+
+/// Check that `artifacts` are up to date before a command reads them. A
+/// stale one fails the command with the stage to re-run; with `auto`, that
+/// stage runs first instead.
+pub async fn preflight(
+    config: &ProjectConfig,
+    artifacts: &[Artifact],
+    auto: bool,
+    no_probe: bool,
+) -> Result<()> {
+    let stale = freshness::stale(config, artifacts);
+    if stale.is_empty() {
+        return Ok(());
+    }
+    if !auto {
+        let hints: Vec<String> = stale
+            .iter()
+            .map(|artifact| artifact.message("freshness-stale-rerun"))
+            .collect();
+        fail!(MissingPrerequisite, hints.join("\n"));
+    }
+
+    let mut stages: Vec<PipelineStage> = stale.iter().map(|artifact| artifact.stage()).collect();
+    stages.sort();
+    stages.dedup();
+    for artifact in &stale {
+        output::warn(artifact.message("freshness-stale"));
+    }
+    let options = RunOptions {
+        no_probe,
+        ..Default::default()
+    };
+    for stage in stages {
+        println!("{}", t!("freshness-auto", stage = stage.name()));
+        // Boxed: the stage may be the command that called this.
+        Box::pin(run_stage(stage, &config.project_root, &options)).await?;
+        println!();
+    }
+    Ok(())
+}
+
+/// Fingerprint of the files the stages read: path, size and modification
+/// time of every source file, structure file and config.json.
+fn input_stamp(project_root: &Path) -> String {
+    let mut files = walk::source_files(project_root);
+    let structure_root = ProjectConfig::open(project_root)
+        .ok()
+        .and_then(|config| config.structure_root_path().ok())
+        .filter(|root| root.is_dir());
+    if let Some(root) = structure_root {
+        files.extend(walk::verilib_files(&root));
+    }
+    files.push(project_root.join(".verilib").join("config.json"));
+    freshness::stamp(project_root, &files)
+}
+
+fn read_record(path: &Path) -> BTreeMap<String, String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_record(path: &Path, record: &BTreeMap<String, String>) -> Result<()> {
+    let content = serde_json::to_string_pretty(record)? + "\n";
+    crate::interrupt::write_atomic(path, content)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stages_follow_pipeline_order() {
+        use PipelineStage::*;
+        assert_eq!(stages(&[], &[]), PipelineStage::DEFAULT);
+        assert_eq!(stages(&[], &[Verify, Atomize]), [Atomize, Verify]);
+        assert_eq!(
+            stages(&[Verify, Create, Verify], &[Atomize]),
+            [Create, Verify]
+        );
+    }
+
+    #[test]
+    fn test_input_stamp_changes_with_sources() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "fn f() {}\n").unwrap();
+        let before = input_stamp(dir.path());
+        assert_eq!(input_stamp(dir.path()), before);
+
+        std::fs::write(dir.path().join("src/lib.rs"), "fn f() { g() }\n").unwrap();
+        assert_ne!(input_stamp(dir.path()), before);
+    }
+}
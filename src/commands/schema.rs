@@ -0,0 +1,96 @@
+//! Schema subcommand implementation.
+//!
+//! Print the JSON Schema of a file this tool writes, so other tools can
+//! validate against it. The same schemas are checked in under `schemas/`.
+
+use crate::commands::types::DeployPayload;
+use crate::config::ProjectConfig;
+use crate::structure::cert_store::CertRecord;
+use crate::structure::certs::Cert;
+use crate::structure::verified::VerifiedCode;
+use crate::structure::{Atom, ProofResult, SpecEntry, StubEntry};
+use anyhow::Result;
+use clap::ValueEnum;
+use schemars::{schema_for, Schema};
+use std::collections::HashMap;
+
+/// Files with a published schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SchemaArtifact {
+    /// .verilib/stubs.json
+    Stubs,
+    /// .verilib/atoms.json
+    Atoms,
+    /// .verilib/specs.json
+    Specs,
+    /// .verilib/proofs.json
+    Proofs,
+    /// .verilib/verified.json
+    Verified,
+    /// .verilib/certs/specs/*.json
+    Cert,
+    /// Lines of .verilib/certs/specs.jsonl
+    CertRecord,
+    /// .verilib/config.json
+    Config,
+    /// Body of the deploy request
+    DeployPayload,
+}
+
+impl SchemaArtifact {
+    pub fn schema(self) -> Schema {
+        match self {
+            SchemaArtifact::Stubs => schema_for!(HashMap<String, StubEntry>),
+            SchemaArtifact::Atoms => schema_for!(HashMap<String, Atom>),
+            SchemaArtifact::Specs => schema_for!(HashMap<String, SpecEntry>),
+            SchemaArtifact::Proofs => schema_for!(HashMap<String, ProofResult>),
+            SchemaArtifact::Verified => schema_for!(HashMap<String, VerifiedCode>),
+            SchemaArtifact::Cert => schema_for!(Cert),
+            SchemaArtifact::CertRecord => schema_for!(CertRecord),
+            SchemaArtifact::Config => schema_for!(ProjectConfig),
+            SchemaArtifact::DeployPayload => schema_for!(DeployPayload),
+        }
+    }
+
+    /// The schema as written to `schemas/` and printed by the command.
+    pub fn render(self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.schema())? + "\n")
+    }
+}
+
+/// Run the schema subcommand.
+pub async fn handle_schema(artifact: SchemaArtifact) -> Result<()> {
+    print!("{}", artifact.render()?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// The checked-in schemas must match the types. Run with
+    /// `VERILIB_UPDATE_SCHEMAS=1` to rewrite them after changing a type.
+    #[test]
+    fn test_checked_in_schemas_are_current() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("schemas");
+        let update = std::env::var_os("VERILIB_UPDATE_SCHEMAS").is_some();
+
+        for artifact in SchemaArtifact::value_variants() {
+            let name = artifact.to_possible_value().unwrap();
+            let path = dir.join(format!("{}.schema.json", name.get_name()));
+            let rendered = artifact.render().unwrap();
+            if update {
+                std::fs::create_dir_all(&dir).unwrap();
+                std::fs::write(&path, &rendered).unwrap();
+                continue;
+            }
+            let checked_in = std::fs::read_to_string(&path).unwrap_or_default();
+            assert!(
+                checked_in == rendered,
+                "{} is out of date; rerun with VERILIB_UPDATE_SCHEMAS=1",
+                path.display()
+            );
+        }
+    }
+}
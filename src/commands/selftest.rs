@@ -0,0 +1,474 @@
+//! Selftest subcommand implementation.
+//!
+//! Check an installation end to end without touching the current project or
+//! the real server. A miniature project is generated in a temporary
+//! directory and taken through `init`, `atomize --no-probe`,
+//! `verify --no-probe` and `deploy`, against a mock server on the loopback
+//! interface. Every check is reported with the subsystem it exercises, so a
+//! failure points at the network, credential storage, the filesystem or a
+//! missing external tool.
+
+use crate::commands::atomize::Analyzer;
+use crate::commands::deploy::{deploy_with_key, DeployOptions};
+use crate::commands::generate_fixture::{generate_fixture, FixtureOptions};
+use crate::commands::init::save_config;
+use crate::commands::{handle_atomize, handle_verify, VerifyOptions};
+use crate::output::{self, Table};
+use crate::storage::{get_credential_storage, get_platform_info};
+use crate::structure::ExecutionMode;
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use std::future::Future;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// Repository ID the miniature project is deployed to.
+const REPO_ID: u64 = 1;
+
+/// API key sent to the mock server; the stored key is never used.
+const API_KEY: &str = "selftest";
+
+/// What a check exercises.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Subsystem {
+    Filesystem,
+    Storage,
+    ExternalTools,
+    Network,
+}
+
+impl Subsystem {
+    fn as_str(self) -> &'static str {
+        match self {
+            Subsystem::Filesystem => "filesystem",
+            Subsystem::Storage => "storage",
+            Subsystem::ExternalTools => "external tools",
+            Subsystem::Network => "network",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Ok,
+    /// Works for the selftest, but something users need may be missing.
+    Warn,
+    Fail,
+    /// Not run because an earlier step failed.
+    Skipped,
+}
+
+impl Outcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Ok => "ok",
+            Outcome::Warn => "warn",
+            Outcome::Fail => "FAIL",
+            Outcome::Skipped => "skipped",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Check {
+    subsystem: Subsystem,
+    name: &'static str,
+    outcome: Outcome,
+    detail: String,
+}
+
+impl Check {
+    fn new(
+        subsystem: Subsystem,
+        name: &'static str,
+        outcome: Outcome,
+        detail: impl Into<String>,
+    ) -> Self {
+        Self {
+            subsystem,
+            name,
+            outcome,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Run the selftest subcommand.
+pub async fn handle_selftest(keep: bool, debug: bool) -> Result<()> {
+    let mut checks = vec![check_storage()];
+    checks.extend(check_tools());
+
+    let dir = std::env::temp_dir().join(format!("verilib-selftest-{}", std::process::id()));
+    let server = MockServer::start().await;
+    match &server {
+        Ok(server) => checks.push(Check::new(
+            Subsystem::Network,
+            "mock server",
+            Outcome::Ok,
+            format!("listening on {}", server.url),
+        )),
+        Err(e) => checks.push(Check::new(
+            Subsystem::Network,
+            "mock server",
+            Outcome::Fail,
+            format!("{:#}", e),
+        )),
+    }
+
+    let previous = std::env::current_dir().context("Failed to read the current directory")?;
+    let mut cycle = Cycle {
+        checks: &mut checks,
+        failed: false,
+    };
+    cycle
+        .step(Subsystem::Filesystem, "create project", async {
+            write_project(&dir)
+        })
+        .await;
+    // init and deploy read and write `.verilib` in the current directory.
+    cycle
+        .step(Subsystem::Filesystem, "enter project", async {
+            std::env::set_current_dir(&dir)
+                .with_context(|| format!("Failed to enter {}", dir.display()))
+        })
+        .await;
+    cycle
+        .step(Subsystem::Filesystem, "init", async {
+            let url = &server
+                .as_ref()
+                .map_err(|_| anyhow::anyhow!("no mock server"))?
+                .url;
+            save_config(&REPO_ID.to_string(), url, false, ExecutionMode::Local)
+        })
+        .await;
+    cycle
+        .step(Subsystem::Filesystem, "atomize --no-probe", async {
+            handle_atomize(
+                dir.clone(),
+                false,
+                true,
+                false,
+                false,
+                Analyzer::Auto,
+                None,
+                None,
+                None,
+                false,
+            )
+            .await
+        })
+        .await;
+    cycle
+        .step(Subsystem::Filesystem, "verify --no-probe", async {
+            let options = VerifyOptions {
+                no_probe: true,
+                ..Default::default()
+            };
+            handle_verify(dir.clone(), options).await
+        })
+        .await;
+    cycle
+        .step(Subsystem::Network, "deploy", async {
+            let server = server
+                .as_ref()
+                .map_err(|_| anyhow::anyhow!("no mock server"))?;
+            let options = DeployOptions {
+                url: Some(server.url.clone()),
+                message: Some("selftest".to_string()),
+                ..Default::default()
+            };
+            deploy_with_key(options, API_KEY, debug).await?;
+            server.check_deploy()
+        })
+        .await;
+    std::env::set_current_dir(&previous).context("Failed to return to the original directory")?;
+
+    if keep {
+        println!("\nKept the selftest project in {}", dir.display());
+    } else if dir.exists() {
+        if let Err(e) = std::fs::remove_dir_all(&dir) {
+            output::warn(format!("Failed to remove {}: {}", dir.display(), e));
+        }
+    }
+
+    output::heading("Selftest");
+    let mut table = Table::new(["SUBSYSTEM", "CHECK", "RESULT", "DETAIL"]);
+    for check in &checks {
+        table.row([
+            check.subsystem.as_str(),
+            check.name,
+            check.outcome.as_str(),
+            check.detail.as_str(),
+        ]);
+    }
+    table.print();
+
+    let mut failed: Vec<&str> = checks
+        .iter()
+        .filter(|check| check.outcome == Outcome::Fail)
+        .map(|check| check.subsystem.as_str())
+        .collect();
+    failed.sort();
+    failed.dedup();
+    if !failed.is_empty() {
+        bail!("Selftest failed: {}", failed.join(", "));
+    }
+    output::success("Selftest passed.");
+    Ok(())
+}
+
+/// The `init`, `atomize`, `verify`, `deploy` steps, each skipped once one fails.
+struct Cycle<'a> {
+    checks: &'a mut Vec<Check>,
+    failed: bool,
+}
+
+impl Cycle<'_> {
+    async fn step(
+        &mut self,
+        subsystem: Subsystem,
+        name: &'static str,
+        run: impl Future<Output = Result<()>>,
+    ) {
+        if self.failed {
+            self.checks.push(Check::new(
+                subsystem,
+                name,
+                Outcome::Skipped,
+                "an earlier step failed",
+            ));
+            return;
+        }
+        output::heading(format!("selftest: {}", name));
+        let check = match run.await {
+            Ok(()) => Check::new(subsystem, name, Outcome::Ok, ""),
+            Err(e) => {
+                self.failed = true;
+                Check::new(subsystem, name, Outcome::Fail, format!("{:#}", e))
+            }
+        };
+        self.checks.push(check);
+    }
+}
+
+/// The credential backend opens. A missing API key only warns: the selftest
+/// deploys with its own.
+fn check_storage() -> Check {
+    let name = "credential storage";
+    let storage = match get_credential_storage() {
+        Ok(storage) => storage,
+        Err(e) => return Check::new(Subsystem::Storage, name, Outcome::Fail, format!("{:#}", e)),
+    };
+    match storage.get_password() {
+        Ok(_) => Check::new(
+            Subsystem::Storage,
+            name,
+            Outcome::Ok,
+            format!("API key stored in {}", get_platform_info()),
+        ),
+        Err(e) => Check::new(
+            Subsystem::Storage,
+            name,
+            Outcome::Warn,
+            format!(
+                "no API key read from {} ({:#}); run 'verilib-cli auth'",
+                get_platform_info(),
+                e
+            ),
+        ),
+    }
+}
+
+/// External tools are found on PATH. The selftest runs without them, so a
+/// missing one only warns.
+fn check_tools() -> Vec<Check> {
+    [
+        ("git", "needed by init, reclone and stats --since"),
+        ("probe-verus", "needed by create, atomize and verify"),
+        ("docker", "needed in docker execution mode"),
+    ]
+    .into_iter()
+    .map(|(tool, needed_by)| match which::which(tool) {
+        Ok(path) => Check::new(
+            Subsystem::ExternalTools,
+            tool,
+            Outcome::Ok,
+            path.display().to_string(),
+        ),
+        Err(_) => Check::new(
+            Subsystem::ExternalTools,
+            tool,
+            Outcome::Warn,
+            format!("not found on PATH; {}", needed_by),
+        ),
+    })
+    .collect()
+}
+
+/// Write a miniature Verus project to `dir`, and check that files written
+/// atomically read back.
+fn write_project(dir: &Path) -> Result<()> {
+    if dir.exists() {
+        std::fs::remove_dir_all(dir)
+            .with_context(|| format!("Failed to remove {}", dir.display()))?;
+    }
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let manifest = "[package]\nname = \"selftest\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+                    [dependencies]\nvstd = { git = \"https://github.com/verus-lang/verus\" }\n";
+    let manifest_path = dir.join("Cargo.toml");
+    crate::interrupt::write_atomic(&manifest_path, manifest)?;
+    if std::fs::read_to_string(&manifest_path)? != manifest {
+        bail!("{} did not read back as written", manifest_path.display());
+    }
+
+    let options = FixtureOptions {
+        atoms: 5,
+        modules: 2,
+        failure_rate: 0.2,
+        seed: 0,
+    };
+    generate_fixture(dir, &options)?;
+    Ok(())
+}
+
+/// A request the mock server received.
+#[derive(Debug)]
+struct MockRequest {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+/// A minimal HTTP/1.1 server on the loopback interface that accepts deploys.
+struct MockServer {
+    url: String,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    async fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .context("Failed to listen on the loopback interface")?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let requests: Arc<Mutex<Vec<MockRequest>>> = Arc::default();
+        let recorded = Arc::clone(&requests);
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let recorded = Arc::clone(&recorded);
+                tokio::spawn(async move {
+                    let _ = serve(stream, &recorded).await;
+                });
+            }
+        });
+        Ok(Self {
+            url,
+            requests,
+            task,
+        })
+    }
+
+    /// The server got the deploy with the tree and the API key.
+    fn check_deploy(&self) -> Result<()> {
+        let requests = self.requests.lock().unwrap();
+        let path = format!("/v2/repo/deploy/{}", REPO_ID);
+        let Some(request) = requests
+            .iter()
+            .find(|r| r.method == "POST" && r.path == path)
+        else {
+            bail!("the mock server received no deploy request");
+        };
+        if request.authorization.as_deref() != Some(&format!("ApiKey {}", API_KEY)) {
+            bail!("the deploy request had no API key");
+        }
+        let payload: Value =
+            serde_json::from_slice(&request.body).context("the deploy request body is not JSON")?;
+        if payload["tree"].as_array().is_none_or(Vec::is_empty) {
+            bail!("the deploy request had an empty tree");
+        }
+        Ok(())
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Answer the requests of one connection until the client closes it.
+async fn serve(stream: TcpStream, recorded: &Mutex<Vec<MockRequest>>) -> Result<()> {
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).await? == 0 {
+            return Ok(());
+        }
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let path = parts.next().unwrap_or_default().to_string();
+
+        let mut content_length = 0;
+        let mut authorization = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).await? == 0 {
+                return Ok(());
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                let value = value.trim();
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.parse().context("Invalid Content-Length")?;
+                } else if name.eq_ignore_ascii_case("authorization") {
+                    authorization = Some(value.to_string());
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).await?;
+
+        let (status, response) = respond(&method, &path);
+        recorded.lock().unwrap().push(MockRequest {
+            method,
+            path,
+            authorization,
+            body,
+        });
+        let response = response.to_string();
+        let head = format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            status,
+            response.len()
+        );
+        let stream = reader.get_mut();
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(response.as_bytes()).await?;
+    }
+}
+
+/// The status line and JSON body the mock server answers with.
+fn respond(method: &str, path: &str) -> (&'static str, Value) {
+    let deployed = path
+        .strip_prefix("/v2/repo/deploy/")
+        .and_then(|id| id.parse::<u64>().ok());
+    match (method, deployed) {
+        ("POST", Some(id)) => (
+            "200 OK",
+            json!({"status": "success", "data": {"id": id, "version": 1}}),
+        ),
+        _ => (
+            "404 Not Found",
+            json!({"status": "error", "message": format!("{} {} is not mocked", method, path)}),
+        ),
+    }
+}
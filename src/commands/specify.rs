@@ -2,16 +2,65 @@
 //!
 //! Check specification status and manage spec certs.
 
-use crate::config::ProjectConfig;
+use crate::commands::run::preflight;
+use crate::config::{resolve_source_roots, ProjectConfig};
+use crate::exit::{fail, ExitContext, ExitKind};
+use crate::freshness::{self, Artifact};
+use crate::metrics;
+use crate::output;
+use crate::paths::relative_arg;
+use crate::picker::{self, PickItem};
+use crate::structure::annotations;
+use crate::structure::certs::{Cert, SpecSource};
+use crate::structure::probe_output::{self, load_probe_map};
+use crate::structure::schema::{load_map, save_map};
 use crate::structure::{
-    cleanup_intermediate_files, create_cert, display_menu, get_existing_certs, run_command,
-    CommandConfig, ExternalTool, ATOMIZE_INTERMEDIATE_FILES,
+    cleanup_intermediate_files, display_menu, run_command, CertStore, CommandConfig, ExternalTool,
+    SpecEntry, StubEntry, ATOMIZE_INTERMEDIATE_FILES,
 };
 use anyhow::{bail, Context, Result};
-use serde_json::Value;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
 
+/// How a function's current spec compares with the one its cert recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SpecHashStatus {
+    Match,
+    Changed,
+    /// The cert has no spec hash: it predates them or can't be parsed.
+    Unrecorded,
+    /// The spec's source could not be read.
+    Unreadable,
+}
+
+/// Spec status of one stub, as reported by `specify --check-only --json`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SpecStatus {
+    /// stubs.json key.
+    pub stub: String,
+    pub code_name: Option<String>,
+    pub display_name: Option<String>,
+    /// Whether probe-verus found a spec (the stub has spec-text).
+    pub specified: bool,
+    pub certified: bool,
+    pub cert_timestamp: Option<DateTime<Utc>>,
+    /// Absent without a cert.
+    pub spec_hash: Option<SpecHashStatus>,
+}
+
+#[derive(Debug, Serialize)]
+struct CheckReport {
+    functions: Vec<SpecStatus>,
+    /// Specified functions without a cert.
+    uncertified: usize,
+}
+
 /// Run the specify subcommand.
 ///
 /// Flow:
@@ -22,45 +71,101 @@ use std::path::{Path, PathBuf};
 /// 5. Display menu and create certs for selected functions
 /// 6. Update specified status in stubs based on certification
 /// 7. Write updated stubs back to stubs.json
-pub async fn handle_specify(project_root: PathBuf, no_probe: bool, check_only: bool) -> Result<()> {
+///
+/// With `json`, progress goes to stderr and `--check-only` prints a
+/// [`SpecStatus`] for every stub to stdout. With `approve_from`, step 5
+/// certifies the rows of that CSV instead of showing the menu.
+pub async fn handle_specify(
+    project_root: PathBuf,
+    no_probe: bool,
+    check_only: bool,
+    interactive: bool,
+    auto: bool,
+    approve_from: Option<PathBuf>,
+    json: bool,
+) -> Result<()> {
     let project_root = project_root
         .canonicalize()
         .context("Failed to resolve project root")?;
-    ProjectConfig::init(&project_root)?;
-    let config = ProjectConfig::global().unwrap();
+    let config = ProjectConfig::open(&project_root)?;
+    config.require_full_mode("specify")?;
+    if check_only {
+        freshness::warn_stale(&config, &[Artifact::Stubs]);
+    } else {
+        preflight(&config, &[Artifact::Stubs], auto, no_probe).await?;
+    }
     let stubs_path = config.stubs_path();
     let atoms_path = config.atoms_path();
-    let certs_dir = config.certs_specify_dir();
+    let cert_store = config.spec_certs();
     let cmd_config = config.command_config();
     let auto_validate = config.auto_validate_specs;
 
     // Load stubs from stubs.json
     let mut stubs_data = read_stubs_json(&stubs_path)?;
-    println!("Loaded {} stubs from stubs.json", stubs_data.len());
+    metrics::count("stubs", stubs_data.len());
+    progress(
+        json,
+        format!("Loaded {} stubs from stubs.json", stubs_data.len()),
+    );
 
     // Run probe-verus specify or load from existing file
     let specs_path = config.verilib_path().join("specs.json");
-    let specs_data = if no_probe {
-        load_specs_from_file(&specs_path)?
-    } else {
-        run_probe_specify(&project_root, &specs_path, &atoms_path, &cmd_config)?
+    let specs_data = {
+        let _stage = metrics::stage("specs");
+        if no_probe {
+            load_specs_from_file(&specs_path, json)?
+        } else {
+            let specs =
+                run_probe_specify(&project_root, &specs_path, &atoms_path, &cmd_config, json)?;
+            freshness::record(&config, Artifact::Specs)?;
+            specs
+        }
     };
+    metrics::count("specs", specs_data.len());
 
     // Enrich stubs with spec-text (only for functions where specified=true)
-    incorporate_spec_text(&mut stubs_data, &specs_data);
+    incorporate_spec_text(&mut stubs_data, &specs_data, json);
 
     // Find stubs with spec-text that are not yet certified
-    let existing_certs = get_existing_certs(&certs_dir)?;
-    println!("Found {} existing certs", existing_certs.len());
-    let uncertified = find_uncertified_functions(&stubs_data, &existing_certs);
+    let existing_certs = cert_store.names()?;
+    progress(
+        json,
+        format!("Found {} existing certs", existing_certs.len()),
+    );
+    let uncertified = find_uncertified_functions(&stubs_data, &existing_certs, json);
+    metrics::count("uncertified", uncertified.len());
 
     // If check_only, verify all stubs with specs have certs
+    let source_roots = resolve_source_roots(&project_root, &config.source_roots);
+    let spec_source = SpecSource {
+        project_root: &project_root,
+        source_roots: &source_roots,
+    };
     if check_only {
-        return check_all_certified(&uncertified);
+        if json {
+            let report = CheckReport {
+                functions: spec_statuses(&stubs_data, cert_store.as_ref(), &spec_source)?,
+                uncertified: uncertified.len(),
+            };
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        return check_all_certified(&uncertified, json).exit_kind(ExitKind::CheckFailed);
     }
 
     // Display menu and create certs for selected functions
-    let newly_certified = collect_certifications(&uncertified, &certs_dir, auto_validate)?;
+    let newly_certified = match approve_from {
+        Some(csv_path) => {
+            let approved = read_approved_csv(&csv_path, &stubs_data, &uncertified)?;
+            create_certs(&approved, cert_store.as_ref(), &spec_source)?
+        }
+        None => collect_certifications(
+            &uncertified,
+            cert_store.as_ref(),
+            auto_validate,
+            interactive,
+            &spec_source,
+        )?,
+    };
 
     // Update specified status based on all certified functions
     let all_certified: HashSet<String> = existing_certs.union(&newly_certified).cloned().collect();
@@ -75,9 +180,9 @@ pub async fn handle_specify(project_root: PathBuf, no_probe: bool, check_only: b
 
 /// Check if all stubs with specs have certs.
 /// Returns Ok if all are certified, error with list of uncertified stubs otherwise.
-fn check_all_certified(uncertified: &HashMap<String, Value>) -> Result<()> {
+fn check_all_certified(uncertified: &HashMap<String, StubEntry>, json: bool) -> Result<()> {
     if uncertified.is_empty() {
-        println!("All stubs with specs have certs.");
+        progress(json, "All stubs with specs have certs.");
         return Ok(());
     }
 
@@ -90,14 +195,8 @@ fn check_all_certified(uncertified: &HashMap<String, Value>) -> Result<()> {
     uncertified_list.sort_by(|a, b| a.0.cmp(b.0));
 
     for (stub_path, stub) in &uncertified_list {
-        let code_name = stub
-            .get("code-name")
-            .and_then(|v| v.as_str())
-            .unwrap_or("?");
-        let display_name = stub
-            .get("display-name")
-            .and_then(|v| v.as_str())
-            .unwrap_or("?");
+        let code_name = stub.code_name.as_deref().unwrap_or("?");
+        let display_name = stub.display_name.as_deref().unwrap_or("?");
         eprintln!("  {}: {} ({})", stub_path, display_name, code_name);
     }
 
@@ -107,29 +206,79 @@ fn check_all_certified(uncertified: &HashMap<String, Value>) -> Result<()> {
     );
 }
 
+/// Spec and cert status of every stub, sorted by stubs.json key.
+pub fn spec_statuses(
+    stubs_data: &HashMap<String, StubEntry>,
+    cert_store: &dyn CertStore,
+    spec_source: &SpecSource,
+) -> Result<Vec<SpecStatus>> {
+    let entries = cert_store.entries()?;
+    let certs: HashMap<&str, Option<&Cert>> = entries
+        .iter()
+        .filter_map(|entry| Some((entry.code_name.as_deref()?, entry.cert.as_ref())))
+        .collect();
+    let mut statuses: Vec<SpecStatus> = stubs_data
+        .iter()
+        .map(|(key, stub)| {
+            let cert = stub
+                .code_name
+                .as_deref()
+                .and_then(|name| certs.get(name).copied());
+            let spec_hash = cert.map(|cert| {
+                match (
+                    cert.and_then(|c| c.spec_hash.as_deref()),
+                    spec_source.hash(stub),
+                ) {
+                    (None, _) => SpecHashStatus::Unrecorded,
+                    (Some(_), None) => SpecHashStatus::Unreadable,
+                    (Some(certified), Some(current)) if certified == current => {
+                        SpecHashStatus::Match
+                    }
+                    (Some(_), Some(_)) => SpecHashStatus::Changed,
+                }
+            });
+            SpecStatus {
+                stub: key.clone(),
+                code_name: stub.code_name.clone(),
+                display_name: stub.display_name.clone(),
+                specified: stub.spec_text.is_some(),
+                certified: cert.is_some(),
+                cert_timestamp: cert.flatten().map(|cert| cert.timestamp),
+                spec_hash,
+            }
+        })
+        .collect();
+    statuses.sort_by(|a, b| a.stub.cmp(&b.stub));
+    Ok(statuses)
+}
+
 /// Find stubs with spec-text that are not yet certified.
 fn find_uncertified_functions(
-    stubs_data: &HashMap<String, Value>,
+    stubs_data: &HashMap<String, StubEntry>,
     existing_certs: &HashSet<String>,
-) -> HashMap<String, Value> {
+    json: bool,
+) -> HashMap<String, StubEntry> {
     // Find stubs which have "spec-text" field
-    let stubs_with_specs: HashMap<String, Value> = stubs_data
+    let stubs_with_specs: HashMap<String, StubEntry> = stubs_data
         .iter()
-        .filter(|(_, stub)| stub.get("spec-text").is_some())
+        .filter(|(_, stub)| stub.spec_text.is_some())
         .map(|(k, v)| (k.clone(), v.clone()))
         .collect();
-    println!("\nFound {} stubs with spec-text", stubs_with_specs.len());
+    progress(
+        json,
+        format!("\nFound {} stubs with spec-text", stubs_with_specs.len()),
+    );
 
     // Filter out existing certs (by code-name)
-    let uncertified: HashMap<String, Value> = stubs_with_specs
+    let uncertified: HashMap<String, StubEntry> = stubs_with_specs
         .into_iter()
-        .filter(|(_, stub)| {
-            let code_name = stub.get("code-name").and_then(|v| v.as_str()).unwrap_or("");
-            !existing_certs.contains(code_name)
-        })
+        .filter(|(_, stub)| !existing_certs.contains(stub.code_name.as_deref().unwrap_or("")))
         .collect();
 
-    println!("Found {} stubs needing certification", uncertified.len());
+    progress(
+        json,
+        format!("Found {} stubs needing certification", uncertified.len()),
+    );
 
     uncertified
 }
@@ -137,15 +286,15 @@ fn find_uncertified_functions(
 /// Display menu for uncertified functions and create certs for selected ones.
 /// Returns the set of newly certified code-names.
 fn collect_certifications(
-    uncertified: &HashMap<String, Value>,
-    certs_dir: &Path,
+    uncertified: &HashMap<String, StubEntry>,
+    cert_store: &dyn CertStore,
     auto_validate: bool,
+    interactive: bool,
+    spec_source: &SpecSource,
 ) -> Result<HashSet<String>> {
-    let mut newly_certified = HashSet::new();
-
     if uncertified.is_empty() {
         println!("\nAll functions with specs in structure are already validated!");
-        return Ok(newly_certified);
+        return Ok(HashSet::new());
     }
 
     if auto_validate {
@@ -160,35 +309,49 @@ fn collect_certifications(
         );
     }
 
-    let mut uncertified_list: Vec<(String, Value)> = uncertified
+    let mut uncertified_list: Vec<(String, StubEntry)> = uncertified
         .iter()
         .map(|(k, v)| (k.clone(), v.clone()))
         .collect();
     uncertified_list.sort_by(|a, b| a.0.cmp(&b.0));
 
+    let terminal = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+    if interactive && !auto_validate && !terminal {
+        output::warn("--interactive needs a terminal; using the numbered menu");
+    }
+
     let selected_indices: Vec<usize> = if auto_validate {
         (0..uncertified_list.len()).collect()
+    } else if interactive && terminal {
+        let notes = annotations::load(&spec_source.project_root.join(".verilib"));
+        let items: Vec<PickItem> = uncertified_list
+            .iter()
+            .map(|(_stub_path, stub)| {
+                let mut preview = spec_source
+                    .text(stub)
+                    .unwrap_or_else(|| "(spec source not readable)".to_string());
+                if let Some(notes) = stub.code_name.as_ref().and_then(|name| notes.get(name)) {
+                    preview = format!("{}\n\n{}", annotations::format(notes), preview);
+                }
+                PickItem {
+                    label: format!(
+                        "{} ({})",
+                        stub.display_name.as_deref().unwrap_or("?"),
+                        stub.code_path.as_deref().unwrap_or("?")
+                    ),
+                    preview,
+                }
+            })
+            .collect();
+        picker::pick("Functions with specs but no certification", &items)?
     } else {
         display_menu(&uncertified_list, |i, _stub_path, stub| {
-            let display_name = stub
-                .get("display-name")
-                .and_then(|v| v.as_str())
-                .unwrap_or("?");
-            let code_path = stub
-                .get("code-path")
-                .and_then(|v| v.as_str())
-                .unwrap_or("?");
-            let spec_text = stub.get("spec-text");
-            let lines_start = spec_text
-                .and_then(|v| v.get("lines-start"))
-                .and_then(|v| v.as_u64())
-                .map(|l| l.to_string())
-                .unwrap_or_else(|| "?".to_string());
-            let lines_end = spec_text
-                .and_then(|v| v.get("lines-end"))
-                .and_then(|v| v.as_u64())
-                .map(|l| l.to_string())
-                .unwrap_or_else(|| "?".to_string());
+            let display_name = stub.display_name.as_deref().unwrap_or("?");
+            let code_path = stub.code_path.as_deref().unwrap_or("?");
+            let (lines_start, lines_end) = match &stub.spec_text {
+                Some(range) => (range.lines_start.to_string(), range.lines_end.to_string()),
+                None => ("?".to_string(), "?".to_string()),
+            };
 
             format!(
                 "  [{}] {} ({}#L{}-L{})",
@@ -197,51 +360,146 @@ fn collect_certifications(
         })?
     };
 
-    if selected_indices.is_empty() {
+    let selected: Vec<&StubEntry> = selected_indices
+        .iter()
+        .map(|&idx| &uncertified_list[idx].1)
+        .collect();
+    create_certs(&selected, cert_store, spec_source)
+}
+
+/// Create a cert for each of `selected`. Returns their code-names.
+fn create_certs(
+    selected: &[&StubEntry],
+    cert_store: &dyn CertStore,
+    spec_source: &SpecSource,
+) -> Result<HashSet<String>> {
+    let mut newly_certified = HashSet::new();
+    if selected.is_empty() {
         println!("\nNo functions selected.");
         return Ok(newly_certified);
     }
 
-    println!(
-        "\nCreating certs for {} functions...",
-        selected_indices.len()
-    );
+    println!("\nCreating certs for {} functions...", selected.len());
 
-    for idx in &selected_indices {
-        let (_stub_path, stub) = &uncertified_list[*idx];
-        let code_name = stub.get("code-name").and_then(|v| v.as_str()).unwrap_or("");
+    for stub in selected {
+        let code_name = stub.code_name.as_deref().unwrap_or("");
         newly_certified.insert(code_name.to_string());
-        let cert_path = create_cert(certs_dir, code_name)?;
-        println!(
-            "  Created: {}",
-            cert_path.file_name().unwrap_or_default().to_string_lossy()
-        );
+        cert_store.put(
+            code_name,
+            &Cert::new(spec_source.hash(stub), spec_source.code_hash(stub)),
+        )?;
+        println!("  Certified: {}", code_name);
     }
 
     println!(
-        "\nCreated {} cert files in {}",
-        selected_indices.len(),
-        certs_dir.display()
+        "\nCreated {} certs in {}",
+        selected.len(),
+        cert_store.path().display()
     );
 
     Ok(newly_certified)
 }
 
+/// The uncertified stubs approved in a review CSV, in row order.
+///
+/// The CSV has a header row and a `code-name` column, a `path` column (the
+/// stubs.json key, `file` in `convert --to-csv`), or both; a row's
+/// code-name is used when set. Rows of functions that are already certified
+/// are skipped. A row that matches no stub, or a stub without a spec, fails
+/// the whole import before any cert is written.
+fn read_approved_csv<'a>(
+    csv_path: &Path,
+    stubs: &HashMap<String, StubEntry>,
+    uncertified: &'a HashMap<String, StubEntry>,
+) -> Result<Vec<&'a StubEntry>> {
+    let mut csv = csv::Reader::from_path(csv_path)
+        .with_context(|| format!("Failed to read {}", csv_path.display()))?;
+    let headers = csv.headers()?.clone();
+    let column = |names: &[&str]| {
+        headers
+            .iter()
+            .position(|header| names.contains(&header.trim()))
+    };
+    let name_column = column(&["code-name"]);
+    let path_column = column(&["path", "file"]);
+    if name_column.is_none() && path_column.is_none() {
+        bail!("{} has no 'code-name' or 'path' column", csv_path.display());
+    }
+    let by_name: HashMap<&str, &str> = stubs
+        .iter()
+        .filter_map(|(key, stub)| Some((stub.code_name.as_deref()?, key.as_str())))
+        .collect();
+
+    let mut approved = Vec::new();
+    let mut errors = Vec::new();
+    let mut seen = HashSet::new();
+    for (i, record) in csv.records().enumerate() {
+        // Row 1 is the header.
+        let row = i + 2;
+        let record = record?;
+        let cell = |column: Option<usize>| {
+            column
+                .and_then(|index| record.get(index))
+                .map(str::trim)
+                .filter(|cell| !cell.is_empty())
+        };
+        let key = match (cell(name_column), cell(path_column)) {
+            (Some(name), _) => by_name.get(name).copied().ok_or(name),
+            (None, Some(path)) => stubs
+                .get_key_value(path)
+                .map(|(key, _)| key.as_str())
+                .ok_or(path),
+            (None, None) => {
+                errors.push(format!("row {}: no code-name or path", row));
+                continue;
+            }
+        };
+        let key = match key {
+            Ok(key) => key,
+            Err(value) => {
+                errors.push(format!("row {}: '{}' matches no function", row, value));
+                continue;
+            }
+        };
+        if !seen.insert(key) {
+            continue;
+        }
+        match uncertified.get(key) {
+            Some(stub) => approved.push(stub),
+            None if stubs[key].spec_text.is_some() => {
+                output::warn(format!("row {}: '{}' is already certified", row, key));
+            }
+            None => errors.push(format!("row {}: '{}' has no spec", row, key)),
+        }
+    }
+
+    if !errors.is_empty() {
+        bail!(
+            "{} rows of {} don't match an uncertified spec; no certs were created:\n  {}",
+            errors.len(),
+            csv_path.display(),
+            errors.join("\n  ")
+        );
+    }
+    Ok(approved)
+}
+
 /// Load specs from an existing specs.json file.
-fn load_specs_from_file(specs_path: &Path) -> Result<HashMap<String, Value>> {
+fn load_specs_from_file(specs_path: &Path, json: bool) -> Result<HashMap<String, SpecEntry>> {
     if !specs_path.exists() {
-        bail!(
+        fail!(
+            MissingPrerequisite,
             "specs.json not found at {}. Run without --no-probe first to generate it.",
             specs_path.display()
         );
     }
 
-    println!("Loading specs from {}...", specs_path.display());
-    let content = std::fs::read_to_string(specs_path)
-        .with_context(|| format!("Failed to read {}", specs_path.display()))?;
-    let specs: HashMap<String, Value> = serde_json::from_str(&content)
-        .with_context(|| format!("Failed to parse {}", specs_path.display()))?;
-    println!("Loaded {} specs", specs.len());
+    progress(
+        json,
+        format!("Loading specs from {}...", specs_path.display()),
+    );
+    let specs: HashMap<String, SpecEntry> = load_probe_map(specs_path)?;
+    progress(json, format!("Loaded {} specs", specs.len()));
     Ok(specs)
 }
 
@@ -251,14 +509,18 @@ fn run_probe_specify(
     specs_path: &Path,
     atoms_path: &Path,
     config: &CommandConfig,
-) -> Result<HashMap<String, Value>> {
+    json: bool,
+) -> Result<HashMap<String, SpecEntry>> {
     if let Some(parent) = specs_path.parent() {
         std::fs::create_dir_all(parent)?;
     }
 
-    println!(
-        "Running probe-verus specify on {}...",
-        project_root.display()
+    progress(
+        json,
+        format!(
+            "Running probe-verus specify on {}...",
+            project_root.display()
+        ),
     );
 
     let output = run_command(
@@ -267,17 +529,9 @@ fn run_probe_specify(
             "specify",
             ".",
             "-o",
-            specs_path
-                .strip_prefix(project_root)
-                .unwrap_or(specs_path)
-                .to_str()
-                .unwrap(),
+            relative_arg(specs_path, project_root)?,
             "-a",
-            atoms_path
-                .strip_prefix(project_root)
-                .unwrap_or(atoms_path)
-                .to_str()
-                .unwrap(),
+            relative_arg(atoms_path, project_root)?,
         ],
         Some(project_root),
         config,
@@ -289,32 +543,31 @@ fn run_probe_specify(
         if !stderr.is_empty() {
             eprintln!("{}", stderr);
         }
-        cleanup_intermediate_files(project_root, ATOMIZE_INTERMEDIATE_FILES);
-        bail!("probe-verus specify failed");
+        cleanup_intermediate_files(project_root, ATOMIZE_INTERMEDIATE_FILES, config, true);
+        fail!(
+            ExternalTool,
+            "probe-verus specify failed{}",
+            output.log_hint()
+        );
     }
 
-    println!("Specs saved to {}", specs_path.display());
+    probe_output::canonicalize(specs_path)?;
+    progress(json, format!("Specs saved to {}", specs_path.display()));
 
-    let content = std::fs::read_to_string(specs_path)?;
-    let specs: HashMap<String, Value> = serde_json::from_str(&content)?;
-    Ok(specs)
+    load_probe_map(specs_path)
 }
 
 /// Update stubs_data with specification statuses based on certified names.
 fn update_stubs_specification_status(
-    stubs_data: &mut HashMap<String, Value>,
+    stubs_data: &mut HashMap<String, StubEntry>,
     certified_names: &HashSet<String>,
 ) {
     for entry in stubs_data.values_mut() {
-        if let Some(obj) = entry.as_object_mut() {
-            let specified = obj
-                .get("code-name")
-                .and_then(|v| v.as_str())
-                .map(|name| certified_names.contains(name))
-                .unwrap_or(false);
-
-            obj.insert("specified".to_string(), Value::Bool(specified));
-        }
+        let specified = entry
+            .code_name
+            .as_deref()
+            .is_some_and(|name| certified_names.contains(name));
+        entry.specified = Some(specified);
     }
 
     println!(
@@ -324,20 +577,17 @@ fn update_stubs_specification_status(
 }
 
 /// Read stubs.json into a HashMap.
-fn read_stubs_json(stubs_path: &Path) -> Result<HashMap<String, Value>> {
+fn read_stubs_json(stubs_path: &Path) -> Result<HashMap<String, StubEntry>> {
     if !stubs_path.exists() {
         return Ok(HashMap::new());
     }
 
-    let content = std::fs::read_to_string(stubs_path)?;
-    let stubs: HashMap<String, Value> = serde_json::from_str(&content)?;
-    Ok(stubs)
+    load_map(stubs_path)
 }
 
 /// Write stubs_data to stubs.json.
-fn write_stubs_json(stubs_path: &Path, stubs_data: &HashMap<String, Value>) -> Result<()> {
-    let content = serde_json::to_string_pretty(stubs_data)?;
-    std::fs::write(stubs_path, content)?;
+fn write_stubs_json(stubs_path: &Path, stubs_data: &HashMap<String, StubEntry>) -> Result<()> {
+    save_map(stubs_path, stubs_data)?;
     println!("Wrote stubs to {}", stubs_path.display());
     Ok(())
 }
@@ -346,29 +596,32 @@ fn write_stubs_json(stubs_path: &Path, stubs_data: &HashMap<String, Value>) -> R
 /// For each stub with a code-name, look up code-name in specs_data
 /// and add "spec-text" field if specified is true.
 fn incorporate_spec_text(
-    stubs_data: &mut HashMap<String, Value>,
-    specs_data: &HashMap<String, Value>,
+    stubs_data: &mut HashMap<String, StubEntry>,
+    specs_data: &HashMap<String, SpecEntry>,
+    json: bool,
 ) {
     let mut count = 0;
     for stub in stubs_data.values_mut() {
-        if let Some(obj) = stub.as_object_mut() {
-            let code_name = obj.get("code-name").and_then(|v| v.as_str()).unwrap_or("");
-
-            if let Some(spec_info) = specs_data.get(code_name) {
-                // Only add spec-text if specified is true
-                let is_specified = spec_info
-                    .get("specified")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(false);
-
-                if is_specified {
-                    if let Some(spec_text) = spec_info.get("spec-text") {
-                        obj.insert("spec-text".to_string(), spec_text.clone());
-                        count += 1;
-                    }
+        let code_name = stub.code_name.as_deref().unwrap_or("");
+
+        if let Some(spec_info) = specs_data.get(code_name) {
+            // Only add spec-text if specified is true
+            if spec_info.specified == Some(true) {
+                if let Some(spec_text) = &spec_info.spec_text {
+                    stub.spec_text = Some(spec_text.clone());
+                    count += 1;
                 }
             }
         }
     }
-    println!("Incorporated spec-text for {} stubs", count);
+    progress(json, format!("Incorporated spec-text for {} stubs", count));
+}
+
+/// Print a progress line, to stderr when stdout carries JSON.
+fn progress(json: bool, message: impl Display) {
+    if json {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
 }
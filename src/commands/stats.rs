@@ -0,0 +1,323 @@
+//! Stats subcommand implementation.
+//!
+//! Summarize atoms.json (and, for full projects, stubs.json): how much code
+//! was atomized, how it is spread over files and modules, and how densely the
+//! atoms depend on each other; with `--by-owner`, also the stub counts per
+//! owner. With `--since`, report instead how the specified and verified counts
+//! of each module changed since an earlier stubs.json (see
+//! [`super::stats_diff`]).
+
+use super::stats_diff;
+use crate::config::ProjectConfig;
+use crate::exit::fail;
+use crate::freshness::{self, Artifact};
+use crate::output::{self, Table};
+use crate::structure::probe_output::load_probe_map;
+use crate::structure::schema::load_map;
+use crate::structure::{Atom, StubEntry};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct AtomStats {
+    atoms: usize,
+    files: usize,
+    /// Atoms per code-module; atoms without one are under `""`.
+    modules: BTreeMap<String, usize>,
+    /// Every listed dependency, including ones outside atoms.json.
+    dependencies: usize,
+    /// Dependencies on other atoms in atoms.json.
+    internal_dependencies: usize,
+    /// Atoms no other atom depends on.
+    roots: usize,
+    /// Atoms that depend on no other atom.
+    leaves: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct StubStats {
+    stubs: usize,
+    /// Stubs matched to an atom by `atomize`.
+    enriched: usize,
+    specified: usize,
+    verified: usize,
+    /// Counts per owner with `--by-owner`; stubs without one are under `""`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owners: Option<BTreeMap<String, OwnerStats>>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct OwnerStats {
+    stubs: usize,
+    specified: usize,
+    verified: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct Stats {
+    atoms: AtomStats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stubs: Option<StubStats>,
+}
+
+/// Read atoms.json, failing with a hint to run `atomize` if it is missing.
+pub fn read_atoms(atoms_path: &Path) -> Result<HashMap<String, Atom>> {
+    if !atoms_path.exists() {
+        fail!(
+            MissingPrerequisite,
+            "atoms.json not found at {}. Run 'verilib-cli atomize' first.",
+            atoms_path.display()
+        );
+    }
+    load_probe_map(atoms_path)
+}
+
+/// Run the stats subcommand.
+pub async fn handle_stats(
+    project_root: PathBuf,
+    atoms_only: bool,
+    since: Option<String>,
+    markdown: bool,
+    by_owner: bool,
+    json: bool,
+) -> Result<()> {
+    let project_root = project_root
+        .canonicalize()
+        .context("Failed to resolve project root")?;
+    let config = ProjectConfig::open(&project_root)?;
+    freshness::warn_stale(&config, &Artifact::ALL);
+
+    if let Some(since) = since {
+        let stubs_path = config.stubs_path();
+        if !stubs_path.exists() {
+            fail!(
+                MissingPrerequisite,
+                "stubs.json not found at {}. Run 'verilib-cli atomize' first.",
+                stubs_path.display()
+            );
+        }
+        let current: HashMap<String, StubEntry> = load_map(&stubs_path)?;
+        let (before, description) = stats_diff::load_baseline(&project_root, &current, &since)?;
+        let progress = stats_diff::progress(&before, &current, description);
+        if json {
+            println!("{}", serde_json::to_string_pretty(&progress)?);
+        } else {
+            stats_diff::print_progress(&progress, markdown);
+        }
+        return Ok(());
+    }
+
+    let atoms = atom_stats(&read_atoms(&config.atoms_path())?);
+    let stubs_path = config.stubs_path();
+    let stubs = if atoms_only || config.is_atoms_only() || !stubs_path.exists() {
+        if by_owner {
+            fail!(
+                MissingPrerequisite,
+                "--by-owner needs stubs.json at {}. Run 'verilib-cli atomize' first.",
+                stubs_path.display()
+            );
+        }
+        None
+    } else {
+        Some(stub_stats(&load_map(&stubs_path)?, by_owner))
+    };
+    let stats = Stats { atoms, stubs };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+    print_stats(&stats);
+    Ok(())
+}
+
+fn atom_stats(atoms: &HashMap<String, Atom>) -> AtomStats {
+    let mut modules = BTreeMap::new();
+    let mut files = HashSet::new();
+    let mut depended_on = HashSet::new();
+    let mut dependencies = 0;
+    let mut internal_dependencies = 0;
+    let mut leaves = 0;
+    for (name, atom) in atoms {
+        *modules
+            .entry(atom.code_module.clone().unwrap_or_default())
+            .or_insert(0) += 1;
+        files.extend(atom.code_path.as_deref());
+        dependencies += atom.dependencies.len();
+        let internal: Vec<&String> = atom
+            .dependencies
+            .iter()
+            .filter(|dep| *dep != name && atoms.contains_key(*dep))
+            .collect();
+        if internal.is_empty() {
+            leaves += 1;
+        }
+        internal_dependencies += internal.len();
+        depended_on.extend(internal);
+    }
+
+    AtomStats {
+        atoms: atoms.len(),
+        files: files.len(),
+        modules,
+        dependencies,
+        internal_dependencies,
+        roots: atoms.len() - depended_on.len(),
+        leaves,
+    }
+}
+
+fn stub_stats(stubs: &HashMap<String, StubEntry>, by_owner: bool) -> StubStats {
+    let count = |flag: fn(&StubEntry) -> bool| stubs.values().filter(|stub| flag(stub)).count();
+    StubStats {
+        stubs: stubs.len(),
+        enriched: count(|stub| stub.code_name.is_some()),
+        specified: count(|stub| stub.specified == Some(true)),
+        verified: count(|stub| stub.verified == Some(true)),
+        owners: by_owner.then(|| owner_stats(stubs)),
+    }
+}
+
+/// Stub counts per owner, as assigned by `atomize` from the `owners` rules.
+fn owner_stats(stubs: &HashMap<String, StubEntry>) -> BTreeMap<String, OwnerStats> {
+    let mut owners: BTreeMap<String, OwnerStats> = BTreeMap::new();
+    for stub in stubs.values() {
+        let owner = owners
+            .entry(stub.owner.clone().unwrap_or_default())
+            .or_default();
+        owner.stubs += 1;
+        owner.specified += usize::from(stub.specified == Some(true));
+        owner.verified += usize::from(stub.verified == Some(true));
+    }
+    owners
+}
+
+fn print_stats(stats: &Stats) {
+    let atoms = &stats.atoms;
+    output::heading("Atoms");
+    println!("  Atoms:         {}", atoms.atoms);
+    println!("  Files:         {}", atoms.files);
+    println!("  Modules:       {}", atoms.modules.len());
+    println!(
+        "  Dependencies:  {} ({} between atoms)",
+        atoms.dependencies, atoms.internal_dependencies
+    );
+    println!("  Roots:         {} (no atom depends on them)", atoms.roots);
+    println!("  Leaves:        {} (depend on no atom)", atoms.leaves);
+
+    if !atoms.modules.is_empty() {
+        println!();
+        let mut modules: Vec<(&String, &usize)> = atoms.modules.iter().collect();
+        modules.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let mut table = Table::new(["MODULE", "ATOMS"]);
+        for (module, count) in modules {
+            let module = if module.is_empty() { "-" } else { module };
+            table.row([module.to_string(), count.to_string()]);
+        }
+        table.print();
+    }
+
+    if let Some(stubs) = &stats.stubs {
+        println!();
+        output::heading("Stubs");
+        println!("  Stubs:         {}", stubs.stubs);
+        println!("  Enriched:      {}", stubs.enriched);
+        println!("  Specified:     {}", stubs.specified);
+        println!("  Verified:      {}", stubs.verified);
+
+        if let Some(owners) = &stubs.owners {
+            println!();
+            let mut table = Table::new(["OWNER", "STUBS", "SPECIFIED", "VERIFIED"]);
+            for (owner, counts) in owners {
+                let owner = if owner.is_empty() { "-" } else { owner };
+                table.row([
+                    owner.to_string(),
+                    counts.stubs.to_string(),
+                    counts.specified.to_string(),
+                    counts.verified.to_string(),
+                ]);
+            }
+            table.print();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atom(module: &str, path: &str, dependencies: &[&str]) -> Atom {
+        Atom {
+            code_module: Some(module.to_string()),
+            code_path: Some(path.to_string()),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            ..Atom::default()
+        }
+    }
+
+    #[test]
+    fn test_atom_stats_counts_internal_edges() {
+        let atoms = HashMap::from([
+            ("a".to_string(), atom("m", "src/m.rs", &["b", "vstd::seq"])),
+            ("b".to_string(), atom("m", "src/m.rs", &["c"])),
+            ("c".to_string(), atom("n", "src/n.rs", &["c"])),
+        ]);
+
+        let stats = atom_stats(&atoms);
+
+        assert_eq!(stats.atoms, 3);
+        assert_eq!(stats.files, 2);
+        assert_eq!(
+            stats.modules,
+            BTreeMap::from([("m".to_string(), 2), ("n".to_string(), 1)])
+        );
+        assert_eq!(stats.dependencies, 4);
+        assert_eq!(stats.internal_dependencies, 2);
+        assert_eq!(stats.roots, 1);
+        assert_eq!(stats.leaves, 1);
+    }
+
+    #[test]
+    fn test_owner_stats_groups_stubs_by_owner() {
+        let stub = |owner: Option<&str>, specified: bool, verified: bool| StubEntry {
+            owner: owner.map(str::to_string),
+            specified: Some(specified),
+            verified: Some(verified),
+            ..StubEntry::default()
+        };
+        let stubs = HashMap::from([
+            ("a.md".to_string(), stub(Some("alice"), true, true)),
+            ("b.md".to_string(), stub(Some("alice"), true, false)),
+            ("c.md".to_string(), stub(None, false, false)),
+        ]);
+
+        let owners = stub_stats(&stubs, true).owners.unwrap();
+
+        assert_eq!(
+            owners,
+            BTreeMap::from([
+                (
+                    "alice".to_string(),
+                    OwnerStats {
+                        stubs: 2,
+                        specified: 2,
+                        verified: 1
+                    }
+                ),
+                (
+                    String::new(),
+                    OwnerStats {
+                        stubs: 1,
+                        specified: 0,
+                        verified: 0
+                    }
+                ),
+            ])
+        );
+        assert!(stub_stats(&stubs, false).owners.is_none());
+    }
+}
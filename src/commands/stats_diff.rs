@@ -0,0 +1,340 @@
+//! Progress since an earlier point, for `stats --since`.
+//!
+//! The earlier stubs come from the first of these that `--since` names:
+//!
+//! - a path: a stubs.json file, a `.verilib` snapshot directory (or a project
+//!   containing `.verilib`), or an archive written by `pack`;
+//! - a date (`YYYY-MM-DD`): the last commit before it, read as a git ref;
+//! - a git ref: its `.verilib/stubs.json` if that was committed. stubs.json is
+//!   usually ignored, so otherwise the current stubs are marked specified and
+//!   verified from the ref's spec certs and verified.json.
+
+use crate::commands::pack::read_packed_file;
+use crate::git::git;
+use crate::output::{self, Table};
+use crate::structure::cert_store::CertRecord;
+use crate::structure::certs::decode_name;
+use crate::structure::schema::{load_map, parse_map};
+use crate::structure::verified::VerifiedCode;
+use crate::structure::StubEntry;
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Counts {
+    pub stubs: usize,
+    pub specified: usize,
+    pub verified: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ModuleProgress {
+    /// `code-module`, or `""` for stubs without one.
+    pub module: String,
+    pub before: Counts,
+    pub after: Counts,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Progress {
+    /// What the earlier stubs were read from.
+    pub since: String,
+    /// Every module in either set of stubs, by name.
+    pub modules: Vec<ModuleProgress>,
+    pub total: ModuleProgress,
+}
+
+/// Read the stubs `since` names; see the module docs.
+pub fn load_baseline(
+    project_root: &Path,
+    current: &HashMap<String, StubEntry>,
+    since: &str,
+) -> Result<(HashMap<String, StubEntry>, String)> {
+    let path = Path::new(since);
+    if path.exists() {
+        return Ok((load_path(path)?, since.to_string()));
+    }
+
+    let commit = match NaiveDate::parse_from_str(since, "%Y-%m-%d") {
+        Ok(date) => {
+            let before = format!("--before={}T00:00:00", date);
+            let output = git(project_root, &["rev-list", "-1", &before, "HEAD"])?;
+            let commit = output.trim().to_string();
+            if commit.is_empty() {
+                bail!("No commit before {}.", date);
+            }
+            commit
+        }
+        Err(_) => {
+            let spec = format!("{}^{{commit}}", since);
+            let output = git(project_root, &["rev-parse", "--verify", "--quiet", &spec])
+                .with_context(|| {
+                    format!(
+                        "'{}' is not a stubs.json, snapshot, archive, date or git ref",
+                        since
+                    )
+                })?;
+            output.trim().to_string()
+        }
+    };
+    let short = &commit[..commit.len().min(10)];
+    let description = format!("{} ({})", since, short);
+    Ok((load_commit(project_root, current, &commit)?, description))
+}
+
+fn load_path(path: &Path) -> Result<HashMap<String, StubEntry>> {
+    if path.is_dir() {
+        for candidate in [path.join("stubs.json"), path.join(".verilib/stubs.json")] {
+            if candidate.exists() {
+                return load_map(&candidate);
+            }
+        }
+        bail!("No stubs.json in {}", path.display());
+    }
+    if path.to_string_lossy().ends_with(".tar.zst") {
+        let Some(contents) = read_packed_file(path, "stubs.json")? else {
+            bail!("{} has no .verilib/stubs.json", path.display());
+        };
+        return parse_map(&String::from_utf8(contents)?)
+            .with_context(|| format!("Failed to parse stubs.json in {}", path.display()));
+    }
+    load_map(path)
+}
+
+/// The stubs as of `commit`: its stubs.json, or else `current` marked from
+/// the certs and verified.json it had.
+fn load_commit(
+    project_root: &Path,
+    current: &HashMap<String, StubEntry>,
+    commit: &str,
+) -> Result<HashMap<String, StubEntry>> {
+    if let Some(stubs) = git_show(project_root, commit, ".verilib/stubs.json")? {
+        return parse_map(&stubs).context("Failed to parse stubs.json from git");
+    }
+
+    let mut certified: HashSet<String> = HashSet::new();
+    let listing = git(
+        project_root,
+        &[
+            "ls-tree",
+            "--name-only",
+            commit,
+            "--",
+            ".verilib/certs/specs/",
+        ],
+    )?;
+    for line in listing.lines() {
+        let file = line.rsplit('/').next().unwrap_or(line);
+        certified.extend(file.strip_suffix(".json").and_then(decode_name));
+    }
+    if let Some(jsonl) = git_show(project_root, commit, ".verilib/certs/specs.jsonl")? {
+        certified.extend(
+            jsonl
+                .lines()
+                .filter_map(|line| serde_json::from_str::<CertRecord>(line).ok())
+                .map(|record| record.code_name),
+        );
+    }
+
+    let verified: HashSet<String> = match git_show(project_root, commit, ".verilib/verified.json")?
+    {
+        Some(content) => parse_map::<VerifiedCode>(&content)
+            .context("Failed to parse verified.json from git")?
+            .into_keys()
+            .collect(),
+        None => {
+            output::warn(
+                "verified.json is not in that commit; its verified counts are shown as 0.",
+            );
+            HashSet::new()
+        }
+    };
+
+    Ok(current
+        .iter()
+        .map(|(file, stub)| {
+            let mut stub = stub.clone();
+            let code_name = stub.code_name.as_deref().unwrap_or_default();
+            stub.specified = Some(certified.contains(code_name));
+            stub.verified = Some(verified.contains(code_name));
+            (file.clone(), stub)
+        })
+        .collect())
+}
+
+/// `path` (relative to `project_root`) as of `commit`, or `None` if it wasn't there.
+fn git_show(project_root: &Path, commit: &str, path: &str) -> Result<Option<String>> {
+    let spec = format!("{}:./{}", commit, path);
+    Ok(git(project_root, &["show", &spec]).ok())
+}
+
+fn per_module(stubs: &HashMap<String, StubEntry>) -> BTreeMap<String, Counts> {
+    let mut modules: BTreeMap<String, Counts> = BTreeMap::new();
+    for stub in stubs.values() {
+        let counts = modules
+            .entry(stub.code_module.clone().unwrap_or_default())
+            .or_default();
+        counts.stubs += 1;
+        counts.specified += usize::from(stub.specified == Some(true));
+        counts.verified += usize::from(stub.verified == Some(true));
+    }
+    modules
+}
+
+/// Compare `before` with `after`, module by module.
+pub fn progress(
+    before: &HashMap<String, StubEntry>,
+    after: &HashMap<String, StubEntry>,
+    since: String,
+) -> Progress {
+    let (before, after) = (per_module(before), per_module(after));
+    let names: std::collections::BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    let modules: Vec<ModuleProgress> = names
+        .into_iter()
+        .map(|module| ModuleProgress {
+            module: module.clone(),
+            before: before.get(module).copied().unwrap_or_default(),
+            after: after.get(module).copied().unwrap_or_default(),
+        })
+        .collect();
+
+    let sum = |counts: &mut Counts, other: &Counts| {
+        counts.stubs += other.stubs;
+        counts.specified += other.specified;
+        counts.verified += other.verified;
+    };
+    let mut total = ModuleProgress {
+        module: String::new(),
+        before: Counts::default(),
+        after: Counts::default(),
+    };
+    for module in &modules {
+        sum(&mut total.before, &module.before);
+        sum(&mut total.after, &module.after);
+    }
+    Progress {
+        since,
+        modules,
+        total,
+    }
+}
+
+/// `before -> after (+delta)`, or just the count when unchanged.
+fn change(before: usize, after: usize) -> String {
+    if before == after {
+        return after.to_string();
+    }
+    let delta = after as i64 - before as i64;
+    format!("{} -> {} ({:+})", before, after, delta)
+}
+
+fn cells(progress: &ModuleProgress) -> [String; 2] {
+    [
+        change(progress.before.specified, progress.after.specified),
+        change(progress.before.verified, progress.after.verified),
+    ]
+}
+
+/// Print the modules whose counts changed, and the total, as a table or as
+/// Markdown for status updates.
+pub fn print_progress(progress: &Progress, markdown: bool) {
+    let changed: Vec<&ModuleProgress> = progress
+        .modules
+        .iter()
+        .filter(|m| m.before != m.after)
+        .collect();
+    let unchanged = progress.modules.len() - changed.len();
+    let name = |module: &str| {
+        if module.is_empty() {
+            "-".to_string()
+        } else {
+            module.to_string()
+        }
+    };
+
+    if markdown {
+        println!("### Verification progress since {}\n", progress.since);
+        println!("| Module | Specified | Verified |");
+        println!("|--------|-----------|----------|");
+        for module in &changed {
+            let [specified, verified] = cells(module);
+            println!(
+                "| `{}` | {} | {} |",
+                name(&module.module),
+                specified,
+                verified
+            );
+        }
+        let [specified, verified] = cells(&progress.total);
+        println!("| **Total** | **{}** | **{}** |", specified, verified);
+        if unchanged > 0 {
+            println!("\nUnchanged modules: {}", unchanged);
+        }
+        return;
+    }
+
+    output::heading(format!("Progress since {}", progress.since));
+    let mut table = Table::new(["MODULE", "SPECIFIED", "VERIFIED"]);
+    for module in &changed {
+        let [specified, verified] = cells(module);
+        table.row([name(&module.module), specified, verified]);
+    }
+    let [specified, verified] = cells(&progress.total);
+    table.row(["TOTAL".to_string(), specified, verified]);
+    table.print();
+    if unchanged > 0 {
+        println!("Unchanged modules: {}", unchanged);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stub(module: &str, specified: bool, verified: bool) -> StubEntry {
+        StubEntry {
+            code_module: Some(module.to_string()),
+            specified: Some(specified),
+            verified: Some(verified),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_progress_per_module() {
+        let before = HashMap::from([
+            ("a.md".to_string(), stub("m", true, false)),
+            ("b.md".to_string(), stub("m", false, false)),
+            ("c.md".to_string(), stub("n", true, true)),
+        ]);
+        let after = HashMap::from([
+            ("a.md".to_string(), stub("m", true, true)),
+            ("b.md".to_string(), stub("m", true, false)),
+            ("c.md".to_string(), stub("n", true, true)),
+            ("d.md".to_string(), stub("o", false, false)),
+        ]);
+
+        let progress = progress(&before, &after, "last sprint".to_string());
+        let modules: Vec<(&str, [String; 2])> = progress
+            .modules
+            .iter()
+            .map(|m| (m.module.as_str(), cells(m)))
+            .collect();
+        assert_eq!(
+            modules,
+            vec![
+                ("m", ["1 -> 2 (+1)".to_string(), "0 -> 1 (+1)".to_string()]),
+                ("n", ["1".to_string(), "1".to_string()]),
+                ("o", ["0".to_string(), "0".to_string()]),
+            ]
+        );
+        assert_eq!(progress.modules[2].before.stubs, 0);
+        assert_eq!(
+            cells(&progress.total),
+            ["2 -> 3 (+1)".to_string(), "1 -> 2 (+1)".to_string()]
+        );
+    }
+}
@@ -1,10 +1,46 @@
 use anyhow::{Context, Result};
+use regex::Regex;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
 
+use crate::commands::auth_check::stored_api_key;
+use crate::commands::deploy::{job_suffix, last_deploy};
+use crate::config::{resolve_base_url, ProjectConfig};
+use crate::constants::init_required_msg;
+use crate::download::{decrypt_tree, download_repo, fetch_atomization_status, TreeNode};
+use crate::encryption::load_identity;
+use crate::exit::{ExitContext, ExitKind};
+use crate::output::{self, Table};
+use crate::paths::slash;
 use crate::storage::{get_credential_storage, get_platform_info};
 
-pub async fn handle_status() -> Result<()> {
+pub async fn handle_status(
+    remote: bool,
+    porcelain: bool,
+    base_url: Option<String>,
+    debug: bool,
+) -> Result<()> {
+    if remote {
+        return handle_remote_status(porcelain, base_url, debug).await;
+    }
+
     let platform_info = get_platform_info();
 
+    if porcelain {
+        let auth = if get_stored_api_key().is_ok() {
+            "stored"
+        } else {
+            "missing"
+        };
+        output::porcelain(["auth", auth]);
+        output::porcelain(["platform", platform_info.as_str()]);
+        return Ok(());
+    }
+
     match get_stored_api_key() {
         Ok(key) => {
             let masked_key = format!("{}***", if key.len() > 4 { &key[..4] } else { &key });
@@ -30,3 +66,350 @@ pub fn get_stored_api_key() -> Result<String> {
         .get_password()
         .context("Failed to retrieve API key from storage")
 }
+
+/// How one atom differs between the local checkout and the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum AtomDrift {
+    /// Only on the server; pull to get it.
+    RemoteOnly,
+    /// Only in the local checkout; deploy to publish it.
+    LocalOnly,
+    /// Edited on the server since the last pull.
+    RemoteChanged,
+    /// Edited locally since the last pull.
+    LocalChanged,
+    /// Edited on both sides, or no stored fingerprint to tell which side changed.
+    Diverged,
+}
+
+impl AtomDrift {
+    /// Stable name used in `--porcelain` output.
+    fn code(&self) -> &'static str {
+        match self {
+            AtomDrift::RemoteOnly => "remote-only",
+            AtomDrift::LocalOnly => "local-only",
+            AtomDrift::RemoteChanged => "remote-changed",
+            AtomDrift::LocalChanged => "local-changed",
+            AtomDrift::Diverged => "diverged",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            AtomDrift::RemoteOnly => "only on server",
+            AtomDrift::LocalOnly => "only local",
+            AtomDrift::RemoteChanged => "changed on server",
+            AtomDrift::LocalChanged => "changed locally",
+            AtomDrift::Diverged => "changed on both sides",
+        }
+    }
+}
+
+/// Fingerprints of one local atom file.
+struct LocalAtom {
+    /// SHA-256 of the current .atom.verilib content.
+    current: String,
+    /// Fingerprint recorded in the .meta.verilib file at the last pull, if any.
+    stored: Option<String>,
+}
+
+/// How the local checkout stands against the server, as `status --remote`
+/// reports it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SyncState {
+    ServerAhead,
+    LocalAhead,
+    Diverged,
+    InSync,
+}
+
+impl SyncState {
+    /// Stable name used in `--porcelain` output.
+    fn code(&self) -> &'static str {
+        match self {
+            SyncState::ServerAhead => "server-ahead",
+            SyncState::LocalAhead => "local-ahead",
+            SyncState::Diverged => "diverged",
+            SyncState::InSync => "in-sync",
+        }
+    }
+
+    fn message(&self) -> &'static str {
+        match self {
+            SyncState::ServerAhead => "Server is ahead: it has atoms the local checkout lacks.",
+            SyncState::LocalAhead => "Local checkout is ahead: it has changes the server lacks.",
+            SyncState::Diverged => "Local checkout and server have diverged: both have changes.",
+            SyncState::InSync => "Local checkout and server are in sync.",
+        }
+    }
+}
+
+/// Compare the local repository tree against the server and report how they differ.
+async fn handle_remote_status(
+    porcelain: bool,
+    base_url: Option<String>,
+    debug: bool,
+) -> Result<()> {
+    let project_root = PathBuf::from(".");
+    let config = ProjectConfig::load(&project_root)?;
+    let repo = config
+        .repo
+        .ok_or_else(|| anyhow::anyhow!(init_required_msg()))
+        .exit_kind(ExitKind::MissingPrerequisite)?;
+    let url = resolve_base_url(base_url.as_deref(), Some(&repo));
+    let api_key = stored_api_key()?;
+
+    let atomization = fetch_atomization_status(&repo.id, &url, &api_key).await?;
+    let mut remote = download_repo(&repo.id, &url, &api_key, debug).await?;
+    if porcelain {
+        output::porcelain(["repo", repo.id.as_str(), url.as_str()]);
+        output::porcelain(["atomization", atomization.status_id.as_str()]);
+        if let Some(updated_at) = &remote.data.repo.updated_at {
+            output::porcelain(["updated", updated_at.as_str()]);
+        }
+    } else {
+        println!("Repository: {} ({})", repo.id, url);
+        println!("Atomization status: {}", atomization.description());
+        if let Some(updated_at) = &remote.data.repo.updated_at {
+            println!("Last server update: {}", updated_at);
+        }
+    }
+    // Until the server has re-snippetized a deploy with changes, its atoms
+    // are the ones from before the deploy.
+    let pending = last_deploy(&project_root.join(".verilib"), &repo.id)
+        .filter(|deploy| deploy.has_changes && !atomization.is_complete());
+    if let Some(deploy) = pending {
+        output::warn(format!(
+            "the server is still snippetizing the deploy of {}{}, so its atoms may be outdated. \
+             Run 'verilib-cli atomization-status --wait' first.",
+            deploy.timestamp.to_rfc3339(),
+            job_suffix(deploy.job_id.as_ref())
+        ));
+    }
+    if config.encryption_recipient.is_some() {
+        match load_identity() {
+            Ok(identity) => {
+                decrypt_tree(&mut remote.data.tree, &identity)?;
+            }
+            Err(e) => output::warn(format!("cannot decrypt server atoms: {}", e)),
+        }
+    }
+
+    let mut remote_atoms = HashMap::new();
+    collect_remote_fingerprints(&remote.data.tree, &mut remote_atoms);
+    let local_atoms = collect_local_fingerprints(&project_root.join(".verilib"))?;
+
+    let drift = compare_atoms(&local_atoms, &remote_atoms);
+    let mut counts: BTreeMap<AtomDrift, usize> = BTreeMap::new();
+    for kind in drift.values() {
+        *counts.entry(*kind).or_default() += 1;
+    }
+    let state = sync_state(&counts);
+
+    if porcelain {
+        output::porcelain([
+            "atoms",
+            &remote_atoms.len().to_string(),
+            &local_atoms.len().to_string(),
+        ]);
+        for (identifier, kind) in &drift {
+            output::porcelain(["atom", kind.code(), identifier.as_str()]);
+        }
+        output::porcelain(["sync", state.code()]);
+        return Ok(());
+    }
+
+    println!("Atoms on server: {}", remote_atoms.len());
+    println!("Atoms locally:   {}", local_atoms.len());
+    if !counts.is_empty() {
+        let mut table = Table::new(["Drift", "Atoms"]);
+        for (kind, count) in &counts {
+            table.row([kind.label().to_string(), count.to_string()]);
+        }
+        table.print();
+    }
+    if debug {
+        for (identifier, kind) in &drift {
+            println!("Debug: {} ({})", identifier, kind.label());
+        }
+    }
+
+    println!("{}", state.message());
+    Ok(())
+}
+
+/// Fingerprint every atom in the server tree, keyed by identifier path.
+fn collect_remote_fingerprints(nodes: &[TreeNode], out: &mut HashMap<String, String>) {
+    for node in nodes {
+        if !node.snippets.is_empty() {
+            let mut snippets: Vec<_> = node.snippets.iter().collect();
+            snippets.sort_by_key(|s| s.sortorder);
+            let content: String = snippets.iter().map(|s| s.text.as_str()).collect();
+            out.insert(
+                node.path.trim_start_matches('/').to_string(),
+                fingerprint(&content),
+            );
+        }
+        collect_remote_fingerprints(&node.children, out);
+    }
+}
+
+/// Fingerprint every .atom.verilib file under `verilib_dir`, keyed by identifier path.
+fn collect_local_fingerprints(verilib_dir: &Path) -> Result<HashMap<String, LocalAtom>> {
+    let index_prefix = Regex::new(r"\[\d*\]\s-\s").unwrap();
+    let mut atoms = HashMap::new();
+
+    for entry in WalkDir::new(verilib_dir).into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let relative = slash(path.strip_prefix(verilib_dir).unwrap_or(path))?;
+        let Some(stem) = relative.strip_suffix(".atom.verilib") else {
+            continue;
+        };
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let meta_path = path.with_file_name(
+            path.file_name()
+                .unwrap()
+                .to_string_lossy()
+                .replace(".atom.verilib", ".meta.verilib"),
+        );
+        let stored = fs::read_to_string(&meta_path)
+            .ok()
+            .and_then(|m| serde_json::from_str::<Value>(&m).ok())
+            .and_then(|m| m.get("fingerprint")?.as_str().map(str::to_string));
+
+        atoms.insert(
+            index_prefix.replace(stem, "").to_string(),
+            LocalAtom {
+                current: fingerprint(&content),
+                stored,
+            },
+        );
+    }
+
+    Ok(atoms)
+}
+
+fn fingerprint(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// Classify every atom that is not identical on both sides.
+fn compare_atoms(
+    local: &HashMap<String, LocalAtom>,
+    remote: &HashMap<String, String>,
+) -> BTreeMap<String, AtomDrift> {
+    let mut drift = BTreeMap::new();
+
+    for (identifier, remote_fp) in remote {
+        let kind = match local.get(identifier) {
+            None => AtomDrift::RemoteOnly,
+            Some(atom) if &atom.current == remote_fp => continue,
+            Some(atom) => match atom.stored.as_deref() {
+                Some(stored) if stored == atom.current => AtomDrift::RemoteChanged,
+                Some(stored) if stored == remote_fp => AtomDrift::LocalChanged,
+                _ => AtomDrift::Diverged,
+            },
+        };
+        drift.insert(identifier.clone(), kind);
+    }
+    for identifier in local.keys() {
+        if !remote.contains_key(identifier) {
+            drift.insert(identifier.clone(), AtomDrift::LocalOnly);
+        }
+    }
+
+    drift
+}
+
+fn sync_state(counts: &BTreeMap<AtomDrift, usize>) -> SyncState {
+    let has = |kind| counts.contains_key(&kind);
+    let server = has(AtomDrift::RemoteOnly) || has(AtomDrift::RemoteChanged);
+    let local = has(AtomDrift::LocalOnly) || has(AtomDrift::LocalChanged);
+
+    if has(AtomDrift::Diverged) || (server && local) {
+        SyncState::Diverged
+    } else if server {
+        SyncState::ServerAhead
+    } else if local {
+        SyncState::LocalAhead
+    } else {
+        SyncState::InSync
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(current: &str, stored: Option<&str>) -> LocalAtom {
+        LocalAtom {
+            current: current.to_string(),
+            stored: stored.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_compare_atoms_uses_stored_fingerprint_as_base() {
+        let local_atoms = HashMap::from([
+            ("same".to_string(), local("a", Some("a"))),
+            ("remote-edit".to_string(), local("a", Some("a"))),
+            ("local-edit".to_string(), local("b", Some("a"))),
+            ("both-edit".to_string(), local("b", Some("a"))),
+            ("no-base".to_string(), local("b", None)),
+            ("new-local".to_string(), local("a", None)),
+        ]);
+        let remote_atoms = HashMap::from([
+            ("same".to_string(), "a".to_string()),
+            ("remote-edit".to_string(), "c".to_string()),
+            ("local-edit".to_string(), "a".to_string()),
+            ("both-edit".to_string(), "c".to_string()),
+            ("no-base".to_string(), "c".to_string()),
+            ("new-remote".to_string(), "a".to_string()),
+        ]);
+
+        let drift = compare_atoms(&local_atoms, &remote_atoms);
+        let expected = BTreeMap::from([
+            ("both-edit".to_string(), AtomDrift::Diverged),
+            ("local-edit".to_string(), AtomDrift::LocalChanged),
+            ("new-local".to_string(), AtomDrift::LocalOnly),
+            ("new-remote".to_string(), AtomDrift::RemoteOnly),
+            ("no-base".to_string(), AtomDrift::Diverged),
+            ("remote-edit".to_string(), AtomDrift::RemoteChanged),
+        ]);
+        assert_eq!(drift, expected);
+    }
+
+    #[test]
+    fn test_sync_state() {
+        let state =
+            |kinds: &[AtomDrift]| sync_state(&kinds.iter().map(|&kind| (kind, 1)).collect());
+        assert_eq!(state(&[]), SyncState::InSync);
+        assert_eq!(state(&[AtomDrift::RemoteOnly]), SyncState::ServerAhead);
+        assert_eq!(state(&[AtomDrift::LocalChanged]), SyncState::LocalAhead);
+        assert_eq!(
+            state(&[AtomDrift::RemoteChanged, AtomDrift::LocalOnly]),
+            SyncState::Diverged
+        );
+        assert_eq!(state(&[AtomDrift::Diverged]), SyncState::Diverged);
+    }
+
+    #[test]
+    fn test_collect_local_fingerprints_strips_index_prefix() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("[3] - f().atom.verilib"), "body").unwrap();
+        fs::write(
+            src.join("[3] - f().meta.verilib"),
+            r#"{ "fingerprint": "abc" }"#,
+        )
+        .unwrap();
+
+        let atoms = collect_local_fingerprints(dir.path()).unwrap();
+        let atom = &atoms["src/f()"];
+        assert_eq!(atom.current, fingerprint("body"));
+        assert_eq!(atom.stored.as_deref(), Some("abc"));
+    }
+}
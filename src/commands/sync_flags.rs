@@ -0,0 +1,383 @@
+//! Sync-flags subcommand implementation.
+//!
+//! The server records whether each atom is specified, and so does the local
+//! checkout in its `.meta.verilib` files (and stubs.json). The two drift when
+//! one side is edited. `sync-flags` fetches the server tree and lists every
+//! atom whose flag differs. `--pull` writes the server's flags locally,
+//! `--push` deploys the local ones, and `--interactive` asks per atom. Every
+//! flag it changes is appended to the flag log (see [`flag_log`]).
+
+use anyhow::{Context, Result};
+use dialoguer::Select;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::commands::auth_check::checked_api_key;
+use crate::commands::deploy::{atom_identifier, deploy_with_key, DeployOptions};
+use crate::commands::flag_log::{self, FlagChange, FlagSide};
+use crate::config::{resolve_base_url, ProjectConfig};
+use crate::constants::init_required_msg;
+use crate::download::{download_repo, TreeNode};
+use crate::exit::{ExitContext, ExitKind};
+use crate::output::{self, Table};
+use crate::structure::schema::{load_map, save_map};
+use crate::structure::StubEntry;
+
+/// Which side's flag wins where they differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncDirection {
+    /// Take the server's flags.
+    Pull,
+    /// Deploy the local flags.
+    Push,
+    /// Ask for each atom.
+    Interactive,
+}
+
+/// The `specified` flag of one local atom.
+#[derive(Debug, Clone)]
+struct LocalFlag {
+    meta_path: PathBuf,
+    specified: bool,
+    code_name: Option<String>,
+}
+
+/// An atom whose `specified` flag differs between the checkout and the server.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct FlagDiff {
+    identifier: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code_name: Option<String>,
+    local: bool,
+    server: bool,
+}
+
+/// Compare `specified` flags with the server and, given a direction, bring
+/// the two sides in line.
+pub async fn handle_sync_flags(
+    direction: Option<SyncDirection>,
+    base_url: Option<String>,
+    dry_run: bool,
+    json: bool,
+    debug: bool,
+) -> Result<()> {
+    let project_root = PathBuf::from(".");
+    let verilib_path = project_root.join(".verilib");
+    let config = ProjectConfig::load(&project_root)?;
+    let repo = config
+        .repo
+        .ok_or_else(|| anyhow::anyhow!(init_required_msg()))
+        .exit_kind(ExitKind::MissingPrerequisite)?;
+    let url = resolve_base_url(base_url.as_deref(), Some(&repo));
+    let api_key = checked_api_key(&url, &repo.id).await?;
+
+    let remote = download_repo(&repo.id, &url, &api_key, debug).await?;
+    let mut remote_flags = HashMap::new();
+    collect_remote_flags(&remote.data.tree, &mut remote_flags);
+    let local_flags = collect_local_flags(&verilib_path)?;
+    let diffs = compare_flags(&local_flags, &remote_flags);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diffs)?);
+    } else if diffs.is_empty() {
+        println!(
+            "Specified flags match the server for all {} atoms.",
+            local_flags.len()
+        );
+    } else {
+        let mut table = Table::new(["Atom", "Local", "Server"]);
+        for diff in &diffs {
+            table.row([
+                diff.identifier.clone(),
+                diff.local.to_string(),
+                diff.server.to_string(),
+            ]);
+        }
+        table.print();
+        println!("{} atoms have a different specified flag.", diffs.len());
+    }
+
+    let Some(direction) = direction else {
+        if !diffs.is_empty() && !json {
+            println!("Run with --pull, --push or --interactive to resolve them.");
+        }
+        return Ok(());
+    };
+    if diffs.is_empty() || dry_run {
+        return Ok(());
+    }
+
+    let mut pull = Vec::new();
+    let mut push = Vec::new();
+    for diff in diffs {
+        let take_server = match direction {
+            SyncDirection::Pull => true,
+            SyncDirection::Push => false,
+            SyncDirection::Interactive => ask(&diff)?,
+        };
+        if take_server {
+            pull.push(diff);
+        } else {
+            push.push(diff);
+        }
+    }
+
+    let mut log = Vec::new();
+    if !pull.is_empty() {
+        log.extend(pull_flags(&verilib_path, &pull, &local_flags)?);
+        flag_log::append(&verilib_path, &log)?;
+        output::success(format!("took the server's flag for {} atoms", pull.len()));
+    }
+    if !push.is_empty() {
+        let options = DeployOptions {
+            url: base_url,
+            message: Some(format!("sync-flags: {} specified flags", push.len())),
+            ..Default::default()
+        };
+        deploy_with_key(options, &api_key, debug).await?;
+        let pushed: Vec<FlagChange> = push
+            .iter()
+            .map(|diff| {
+                let file = local_flags
+                    .get(&diff.identifier)
+                    .map(|l| l.meta_path.as_path());
+                change(diff, file, diff.server, diff.local, FlagSide::Server)
+            })
+            .collect();
+        flag_log::append(&verilib_path, &pushed)?;
+        log.extend(pushed);
+        output::success(format!("deployed the local flag for {} atoms", push.len()));
+    }
+    if !json {
+        for entry in &log {
+            println!(
+                "  {} ({}): specified {} -> {}",
+                entry.identifier.as_deref().unwrap_or_default(),
+                match entry.side {
+                    Some(FlagSide::Server) => "server",
+                    _ => "local",
+                },
+                entry.from,
+                entry.to
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Whether to take the server's flag for `diff`.
+fn ask(diff: &FlagDiff) -> Result<bool> {
+    let items = [
+        format!("Take the server's flag ({})", diff.server),
+        format!("Keep the local flag ({}) and deploy it", diff.local),
+    ];
+    let selection = Select::new()
+        .with_prompt(format!("specified differs for {}", diff.identifier))
+        .items(&items)
+        .default(0)
+        .interact()
+        .context("Failed to get a choice")?;
+    Ok(selection == 0)
+}
+
+/// The `specified` flag of every server node, keyed by identifier path.
+fn collect_remote_flags(nodes: &[TreeNode], out: &mut HashMap<String, bool>) {
+    for node in nodes {
+        out.insert(
+            node.path.trim_start_matches('/').to_string(),
+            node.specified,
+        );
+        collect_remote_flags(&node.children, out);
+    }
+}
+
+/// The `specified` flag of every .meta.verilib file under `verilib_dir`,
+/// keyed by the identifier of its atom.
+fn collect_local_flags(verilib_dir: &Path) -> Result<HashMap<String, LocalFlag>> {
+    let mut flags = HashMap::new();
+    for entry in WalkDir::new(verilib_dir).into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy();
+        let Some(stem) = file_name.strip_suffix(".meta.verilib") else {
+            continue;
+        };
+        let atom_path = path.with_file_name(format!("{}.atom.verilib", stem));
+        let identifier = atom_identifier(verilib_dir, &atom_path)?;
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let meta: Value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        flags.insert(
+            identifier,
+            LocalFlag {
+                meta_path: path.to_path_buf(),
+                specified: meta["specified"].as_bool().unwrap_or_default(),
+                code_name: meta["code_name"]
+                    .as_str()
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string),
+            },
+        );
+    }
+    Ok(flags)
+}
+
+/// Atoms on both sides whose flags differ, sorted by identifier.
+fn compare_flags(
+    local: &HashMap<String, LocalFlag>,
+    remote: &HashMap<String, bool>,
+) -> Vec<FlagDiff> {
+    let diffs: BTreeMap<_, _> = local
+        .iter()
+        .filter_map(|(identifier, flag)| {
+            let server = *remote.get(identifier)?;
+            (server != flag.specified).then(|| {
+                (
+                    identifier.clone(),
+                    FlagDiff {
+                        identifier: identifier.clone(),
+                        code_name: flag.code_name.clone(),
+                        local: flag.specified,
+                        server,
+                    },
+                )
+            })
+        })
+        .collect();
+    diffs.into_values().collect()
+}
+
+/// Write the server's flag of each diff into its meta file and into the
+/// stubs.json entries with the same code-name.
+fn pull_flags(
+    verilib_dir: &Path,
+    diffs: &[FlagDiff],
+    local: &HashMap<String, LocalFlag>,
+) -> Result<Vec<FlagChange>> {
+    let mut changes = Vec::new();
+    for diff in diffs {
+        let meta_path = &local[&diff.identifier].meta_path;
+        let content = fs::read_to_string(meta_path)
+            .with_context(|| format!("Failed to read {}", meta_path.display()))?;
+        let mut meta: Value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", meta_path.display()))?;
+        meta["specified"] = Value::Bool(diff.server);
+        fs::write(meta_path, serde_json::to_string_pretty(&meta)?)
+            .with_context(|| format!("Failed to write {}", meta_path.display()))?;
+        changes.push(change(
+            diff,
+            Some(meta_path),
+            diff.local,
+            diff.server,
+            FlagSide::Local,
+        ));
+    }
+
+    let stubs_path = verilib_dir.join("stubs.json");
+    if stubs_path.exists() {
+        let mut stubs: HashMap<String, StubEntry> = load_map(&stubs_path)?;
+        let pulled: HashMap<&str, bool> = diffs
+            .iter()
+            .filter_map(|diff| Some((diff.code_name.as_deref()?, diff.server)))
+            .collect();
+        let mut updated = false;
+        for stub in stubs.values_mut() {
+            let Some(&specified) = stub.code_name.as_deref().and_then(|n| pulled.get(n)) else {
+                continue;
+            };
+            if stub.specified != Some(specified) {
+                stub.specified = Some(specified);
+                updated = true;
+            }
+        }
+        if updated {
+            save_map(&stubs_path, &stubs)?;
+        }
+    }
+    Ok(changes)
+}
+
+fn change(
+    diff: &FlagDiff,
+    meta_path: Option<&Path>,
+    from: bool,
+    to: bool,
+    side: FlagSide,
+) -> FlagChange {
+    FlagChange {
+        file: meta_path.map(|path| path.display().to_string()),
+        identifier: Some(diff.identifier.clone()),
+        code_name: diff.code_name.clone(),
+        side: Some(side),
+        ..FlagChange::new("sync-flags", "specified", from, to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::flag_log::FLAG_LOG;
+
+    #[test]
+    fn test_pull_updates_meta_stubs_and_log() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let verilib = dir.path();
+        let src = verilib.join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("[1] - f().atom.verilib"), "fn f() {}").unwrap();
+        fs::write(
+            src.join("[1] - f().meta.verilib"),
+            r#"{ "specified": false, "code_name": "probe:f()", "status_id": 2 }"#,
+        )
+        .unwrap();
+        fs::write(src.join("g().atom.verilib"), "fn g() {}").unwrap();
+        fs::write(src.join("g().meta.verilib"), r#"{ "specified": true }"#).unwrap();
+        fs::write(
+            verilib.join("stubs.json"),
+            r#"{ "src/f().md": { "code-name": "probe:f()", "specified": false } }"#,
+        )
+        .unwrap();
+
+        let local = collect_local_flags(verilib).unwrap();
+        let remote = HashMap::from([
+            ("src/f()".to_string(), true),
+            ("src/g()".to_string(), true),
+            ("src/only-remote()".to_string(), true),
+        ]);
+        let diffs = compare_flags(&local, &remote);
+        assert_eq!(
+            diffs,
+            [FlagDiff {
+                identifier: "src/f()".to_string(),
+                code_name: Some("probe:f()".to_string()),
+                local: false,
+                server: true,
+            }]
+        );
+
+        let changes = pull_flags(verilib, &diffs, &local).unwrap();
+        flag_log::append(verilib, &changes).unwrap();
+
+        let meta: Value =
+            serde_json::from_str(&fs::read_to_string(src.join("[1] - f().meta.verilib")).unwrap())
+                .unwrap();
+        assert_eq!(meta["specified"], true);
+        assert_eq!(meta["status_id"], 2);
+        let stubs: HashMap<String, StubEntry> = load_map(&verilib.join("stubs.json")).unwrap();
+        assert_eq!(stubs["src/f().md"].specified, Some(true));
+
+        let log = fs::read_to_string(verilib.join(FLAG_LOG)).unwrap();
+        let entry: FlagChange = serde_json::from_str(log.trim()).unwrap();
+        assert_eq!(
+            (entry.from, entry.to, entry.side),
+            (Value::Bool(false), Value::Bool(true), Some(FlagSide::Local))
+        );
+        assert!(compare_flags(&collect_local_flags(verilib).unwrap(), &remote).is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+//! Project templates, for `create --template`.
+//!
+//! A template is a directory, or a git repository holding one, with any of:
+//!
+//! - `config.json`: settings merged into `.verilib/config.json`
+//! - `.verilibignore`: copied to the project root
+//! - `spec-templates/`: copied to `.verilib/spec-templates/`
+//! - `hooks/`: copied to `.verilib/hooks/`
+//!
+//! so that an organization's verification projects start from the same
+//! docker image, auto-validation and ignore rules.
+
+use crate::config::ProjectConfig;
+use crate::output;
+use crate::walk::IGNORE_FILE;
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::TempDir;
+
+/// Config keys that belong to one project and are never taken from a template.
+const PROJECT_KEYS: [&str; 2] = ["repo", "subdir-repos"];
+
+/// Directories copied from a template into `.verilib`.
+const TEMPLATE_DIRS: [&str; 2] = ["spec-templates", "hooks"];
+
+/// A fetched template. A cloned one is removed when dropped.
+pub struct Template {
+    dir: PathBuf,
+    /// The temporary directory a git template was cloned into.
+    _clone: Option<TempDir>,
+}
+
+impl Template {
+    /// Use the template directory at `source`, or clone it if `source` is a
+    /// git URL. `URL#REF` clones a branch or tag.
+    pub fn fetch(source: &str) -> Result<Self> {
+        if !is_git_url(source) {
+            let dir = PathBuf::from(source);
+            if !dir.is_dir() {
+                bail!("Template {} is not a directory or a git URL", source);
+            }
+            return Ok(Self { dir, _clone: None });
+        }
+
+        let (url, reference) = match source.rsplit_once('#') {
+            Some((url, reference)) => (url, Some(reference)),
+            None => (source, None),
+        };
+        let clone = tempfile::Builder::new()
+            .prefix("verilib-template-")
+            .tempdir()
+            .context("Failed to create a directory to clone the template into")?;
+        println!("Cloning template {}...", source);
+        let mut command = Command::new("git");
+        command.args(["clone", "--quiet", "--depth", "1"]);
+        if let Some(reference) = reference {
+            command.arg(format!("--branch={}", reference));
+        }
+        let output = command
+            .arg("--")
+            .arg(url)
+            .arg(clone.path())
+            .output()
+            .context("Failed to run git clone")?;
+        if !output.status.success() {
+            bail!(
+                "Failed to clone template {}: {}",
+                source,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(Self {
+            dir: clone.path().to_path_buf(),
+            _clone: Some(clone),
+        })
+    }
+
+    /// Merge the template's config.json into `config`. Returns the keys it
+    /// set.
+    pub fn apply_config(&self, config: &mut ProjectConfig) -> Result<Vec<String>> {
+        let path = self.dir.join("config.json");
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let Value::Object(template) = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?
+        else {
+            bail!("{} is not a JSON object", path.display());
+        };
+
+        let mut merged = serde_json::to_value(&*config)?;
+        let mut applied = Vec::new();
+        for (key, value) in template {
+            if PROJECT_KEYS.contains(&key.as_str()) {
+                output::warn(format!("ignoring '{}' in the template's config.json", key));
+                continue;
+            }
+            merged[&key] = value;
+            applied.push(key);
+        }
+        let project_root = std::mem::take(&mut config.project_root);
+        *config = serde_json::from_value(merged)
+            .with_context(|| format!("Invalid settings in {}", path.display()))?;
+        config.project_root = project_root;
+        applied.sort();
+        Ok(applied)
+    }
+
+    /// Copy `.verilibignore`, spec templates and hooks into the project.
+    /// Returns the paths written, relative to `project_root`.
+    pub fn seed(&self, project_root: &Path) -> Result<Vec<String>> {
+        let mut seeded = Vec::new();
+        let ignore = self.dir.join(IGNORE_FILE);
+        if ignore.is_file() {
+            let target = project_root.join(IGNORE_FILE);
+            if target.exists() {
+                output::warn(format!(
+                    "file already exists, overwriting: {}",
+                    target.display()
+                ));
+            }
+            std::fs::copy(&ignore, &target)
+                .with_context(|| format!("Failed to write {}", target.display()))?;
+            seeded.push(IGNORE_FILE.to_string());
+        }
+        for name in TEMPLATE_DIRS {
+            let source = self.dir.join(name);
+            if source.is_dir() {
+                copy_dir(&source, &project_root.join(".verilib").join(name))?;
+                seeded.push(format!(".verilib/{}", name));
+            }
+        }
+        Ok(seeded)
+    }
+}
+
+/// Whether a template source names a git repository rather than a directory.
+fn is_git_url(source: &str) -> bool {
+    ["https://", "http://", "ssh://", "git://", "git@", "file://"]
+        .iter()
+        .any(|scheme| source.starts_with(scheme))
+        || source.split('#').next().unwrap_or(source).ends_with(".git")
+}
+
+/// Copy the files under `from` to `to`, replacing files that exist.
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    for entry in walkdir::WalkDir::new(from)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        let relative = entry.path().strip_prefix(from)?;
+        let target = to.join(relative);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)
+                .with_context(|| format!("Failed to create {}", target.display()))?;
+        } else if entry.file_type().is_file() {
+            std::fs::copy(entry.path(), &target)
+                .with_context(|| format!("Failed to write {}", target.display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_template_config_and_files_seed_a_project() {
+        let template = tempfile::TempDir::new().unwrap();
+        let project = tempfile::TempDir::new().unwrap();
+        let t = template.path();
+        fs::write(
+            t.join("config.json"),
+            r#"{"docker-image": "org/verus:1", "auto-validate-specs": true, "repo": {"id": "9"}}"#,
+        )
+        .unwrap();
+        fs::write(t.join(IGNORE_FILE), "generated/\n").unwrap();
+        fs::create_dir_all(t.join("hooks")).unwrap();
+        fs::write(t.join("hooks/pre-deploy.sh"), "true\n").unwrap();
+
+        let template = Template::fetch(t.to_str().unwrap()).unwrap();
+        let mut config = ProjectConfig {
+            project_root: project.path().to_path_buf(),
+            ..Default::default()
+        };
+        let applied = template.apply_config(&mut config).unwrap();
+        assert_eq!(applied, ["auto-validate-specs", "docker-image"]);
+        assert_eq!(config.docker_image, "org/verus:1");
+        assert!(config.auto_validate_specs);
+        assert!(config.repo.is_none());
+        assert_eq!(config.project_root, project.path());
+
+        let seeded = template.seed(project.path()).unwrap();
+        assert_eq!(seeded, [IGNORE_FILE, ".verilib/hooks"]);
+        assert!(project.path().join(".verilib/hooks/pre-deploy.sh").exists());
+
+        assert!(is_git_url("https://github.com/org/template"));
+        assert!(is_git_url("../template.git#v2"));
+        assert!(!is_git_url("../template"));
+    }
+
+    #[test]
+    fn test_git_template_is_cloned_into_a_private_temp_dir() {
+        let root = tempfile::TempDir::new().unwrap();
+        let repo = root.path().join("template.git");
+        fs::create_dir_all(&repo).unwrap();
+        fs::write(
+            repo.join("config.json"),
+            r#"{"docker-image": "org/verus:2"}"#,
+        )
+        .unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .args(["-c", "user.name=t", "-c", "user.email=t@t"])
+                .args(args)
+                .current_dir(&repo)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?}", args);
+        };
+        git(&["init", "--quiet"]);
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "template"]);
+
+        let template = Template::fetch(repo.to_str().unwrap()).unwrap();
+        let clone = template.dir.clone();
+        assert_ne!(clone, repo);
+        let mut config = ProjectConfig::default();
+        template.apply_config(&mut config).unwrap();
+        assert_eq!(config.docker_image, "org/verus:2");
+        drop(template);
+        assert!(!clone.exists());
+    }
+}
@@ -1,6 +1,9 @@
-#![allow(dead_code)] // WIP: deploy types not yet wired into CLI — see https://github.com/Beneficial-AI-Foundation/verilib-cli/issues/36
-
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::structure::ReviewStatus;
 
 #[derive(Debug)]
 pub struct Language {
@@ -70,11 +73,84 @@ pub const TYPES: &[(u32, &str)] = &[
     (8, "Math"),
 ];
 
-#[derive(Debug, Serialize)]
+/// Who can see a repository on the server.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    Private,
+    Public,
+}
+
+impl Visibility {
+    pub const ALL: [Visibility; 2] = [Visibility::Private, Visibility::Public];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Visibility::Private => "private",
+            Visibility::Public => "public",
+        }
+    }
+}
+
+/// Kinds of snippet an atom is split into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum SnippetKind {
+    Doc,
+    Code,
+    Spec,
+    Proof,
+}
+
+/// The server's snippet `type_id` for each kind.
+pub const SNIPPET_TYPES: &[(SnippetKind, u32)] = &[
+    (SnippetKind::Doc, 1),
+    (SnippetKind::Code, 2),
+    (SnippetKind::Spec, 3),
+    (SnippetKind::Proof, 4),
+];
+
+impl SnippetKind {
+    pub fn type_id(self) -> u32 {
+        SNIPPET_TYPES
+            .iter()
+            .find(|(kind, _)| *kind == self)
+            .map(|(_, id)| *id)
+            .expect("every snippet kind has a type_id")
+    }
+
+    pub fn from_type_id(type_id: u32) -> Option<Self> {
+        SNIPPET_TYPES
+            .iter()
+            .find(|(_, id)| *id == type_id)
+            .map(|(kind, _)| *kind)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SnippetKind::Doc => "doc",
+            SnippetKind::Code => "code",
+            SnippetKind::Spec => "spec",
+            SnippetKind::Proof => "proof",
+        }
+    }
+}
+
+/// One typed piece of an atom, as given to `api create-file`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SnippetInput {
+    #[serde(rename = "type")]
+    pub kind: SnippetKind,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct DeployNode {
     pub identifier: String,
     pub content: String,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub dependencies: Vec<String>,
     pub code_name: String,
     pub children: Vec<DeployNode>,
@@ -82,10 +158,47 @@ pub struct DeployNode {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub status_id: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub snippets: Option<serde_json::Value>,
+    pub snippets: Option<Value>,
     pub specified: bool,
     #[serde(default)]
     pub disabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub review_status: Option<ReviewStatus>,
+}
+
+/// Body of the deploy request.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct DeployPayload {
+    pub tree: Vec<DeployNode>,
+    /// `layout.json` of each atom folder, keyed by its path under `.verilib`.
+    pub layouts: BTreeMap<String, Value>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub has_changes: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language_id: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_id: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub type_id: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verifierversion_id: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo_id: Option<String>,
+    /// Release note describing what changed in this deployment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Team or organization that owns a new repository.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<Visibility>,
+    /// Topic tags of a new repository.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub topics: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -106,7 +219,21 @@ pub struct DeployResponse {
     pub data: DeployData,
 }
 
+/// A node the server rejected, from `data.errors` of a deploy response.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct NodeError {
+    /// The node's `identifier` as sent in the tree.
+    pub identifier: String,
+    pub message: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct DeployData {
     pub id: u64,
+    /// Version the server assigned to this deployment, if it reports one.
+    #[serde(default)]
+    pub version: Option<Value>,
+    /// Snippetization job started for the changes, if the server reports one.
+    #[serde(default)]
+    pub job_id: Option<Value>,
 }
@@ -0,0 +1,226 @@
+//! Verify-certs subcommand implementation.
+//!
+//! Audit spec certs against the current stubs and sources: every cert file
+//! name must decode to the code-name of a stub that still has a spec, and the
+//! spec must hash to what was certified. Problem certs can be removed
+//! (`--clean`) or queued for re-certification (`--requeue`).
+
+use crate::config::{resolve_source_roots, ProjectConfig};
+use crate::exit::fail;
+use crate::output::{self, Table};
+use crate::structure::cert_store::StoredCert;
+use crate::structure::certs::SpecSource;
+use crate::structure::schema::{load_map, save_map};
+use crate::structure::StubEntry;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// What is wrong with a cert.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CertProblem {
+    /// The cert's code-name can't be read, or it is not a cert.
+    Malformed,
+    /// No stub has the cert's code-name.
+    NoStub,
+    /// The stub no longer has a spec.
+    NoSpec,
+    /// The spec changed after it was certified.
+    SpecChanged,
+}
+
+impl CertProblem {
+    fn as_str(self) -> &'static str {
+        match self {
+            CertProblem::Malformed => "malformed",
+            CertProblem::NoStub => "no-stub",
+            CertProblem::NoSpec => "no-spec",
+            CertProblem::SpecChanged => "spec-changed",
+        }
+    }
+
+    /// Whether `--requeue` (rather than `--clean`) handles this problem.
+    fn is_requeued(self) -> bool {
+        self == CertProblem::SpecChanged
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CertFinding {
+    /// Cert file name, or `specs.jsonl:<line>` with the jsonl store.
+    pub file: String,
+    #[serde(rename = "code-name", skip_serializing_if = "Option::is_none")]
+    pub code_name: Option<String>,
+    pub problem: CertProblem,
+    /// Index into the store's entries.
+    #[serde(skip)]
+    entry: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct AuditReport<'a> {
+    findings: &'a [CertFinding],
+    /// Certs without a spec hash, or whose spec source could not be read.
+    unchecked: usize,
+}
+
+/// Run the verify-certs subcommand.
+pub async fn handle_verify_certs(
+    project_root: PathBuf,
+    clean: bool,
+    requeue: bool,
+    json: bool,
+) -> Result<()> {
+    let project_root = project_root
+        .canonicalize()
+        .context("Failed to resolve project root")?;
+    let config = ProjectConfig::open(&project_root)?;
+
+    let stubs_path = config.stubs_path();
+    if !stubs_path.exists() {
+        fail!(
+            MissingPrerequisite,
+            "{} not found. Run 'verilib-cli atomize' first.",
+            stubs_path.display()
+        );
+    }
+    let mut stubs: HashMap<String, StubEntry> = load_map(&stubs_path)?;
+    let source_roots = resolve_source_roots(&project_root, &config.source_roots);
+    let spec_source = SpecSource {
+        project_root: &project_root,
+        source_roots: &source_roots,
+    };
+
+    let cert_store = config.spec_certs();
+    let entries = cert_store.entries()?;
+    let (findings, unchecked) = audit(&entries, &stubs, &spec_source);
+
+    if json {
+        let report = AuditReport {
+            findings: &findings,
+            unchecked,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_findings(&findings, unchecked);
+    }
+
+    let mut remaining = 0;
+    let mut requeued = 0;
+    let mut fixed = Vec::new();
+    for finding in &findings {
+        let fix = if finding.problem.is_requeued() {
+            requeue
+        } else {
+            clean
+        };
+        if !fix {
+            remaining += 1;
+            continue;
+        }
+        fixed.push(&entries[finding.entry]);
+        if finding.problem.is_requeued() {
+            let code_name = finding.code_name.as_deref();
+            for stub in stubs
+                .values_mut()
+                .filter(|s| s.code_name.as_deref() == code_name)
+            {
+                stub.specified = Some(false);
+            }
+            requeued += 1;
+        }
+    }
+    cert_store.remove(&fixed)?;
+    if requeued > 0 {
+        save_map(&stubs_path, &stubs)?;
+    }
+
+    if !fixed.is_empty() && !json {
+        println!(
+            "Removed {} certs; {} queued for re-certification by 'specify'.",
+            fixed.len(),
+            requeued
+        );
+    }
+    if remaining > 0 {
+        fail!(
+            CheckFailed,
+            "{} cert problems found. Re-run with --clean to remove certs of missing functions or specs, \
+             or --requeue to re-certify changed specs.",
+            remaining
+        );
+    }
+    Ok(())
+}
+
+/// Check every stored cert. Returns the problems, in store order, and the
+/// number of certs whose spec could not be compared.
+fn audit(
+    entries: &[StoredCert],
+    stubs: &HashMap<String, StubEntry>,
+    spec_source: &SpecSource,
+) -> (Vec<CertFinding>, usize) {
+    let mut findings = Vec::new();
+    let mut unchecked = 0;
+
+    let by_code_name: HashMap<&str, &StubEntry> = stubs
+        .values()
+        .filter_map(|stub| Some((stub.code_name.as_deref()?, stub)))
+        .collect();
+
+    for (index, entry) in entries.iter().enumerate() {
+        let finding = |problem| CertFinding {
+            file: entry.location.clone(),
+            code_name: entry.code_name.clone(),
+            problem,
+            entry: index,
+        };
+
+        let (Some(code_name), Some(cert)) = (&entry.code_name, &entry.cert) else {
+            findings.push(finding(CertProblem::Malformed));
+            continue;
+        };
+        let Some(stub) = by_code_name.get(code_name.as_str()) else {
+            findings.push(finding(CertProblem::NoStub));
+            continue;
+        };
+        if stub.spec_text.is_none() {
+            findings.push(finding(CertProblem::NoSpec));
+            continue;
+        }
+        match (&cert.spec_hash, spec_source.hash(stub)) {
+            (Some(certified), Some(current)) if *certified != current => {
+                findings.push(finding(CertProblem::SpecChanged));
+            }
+            (Some(_), Some(_)) => {}
+            _ => unchecked += 1,
+        }
+    }
+
+    (findings, unchecked)
+}
+
+fn print_findings(findings: &[CertFinding], unchecked: usize) {
+    if findings.is_empty() {
+        output::success("All certs match their stubs and specs.");
+    } else {
+        let mut table = Table::new(["Problem", "Code-name", "Cert"]);
+        for finding in findings {
+            table.row([
+                finding.problem.as_str(),
+                finding.code_name.as_deref().unwrap_or("-"),
+                finding.file.as_str(),
+            ]);
+        }
+        table.print();
+    }
+    if unchecked > 0 {
+        output::warn(format!(
+            "{} certs have no spec hash or an unreadable source and were not compared; \
+             re-certify them with 'specify' to record one.",
+            unchecked
+        ));
+    }
+}
@@ -0,0 +1,211 @@
+//! PR-scoped verification, for `verify --modules-from-diff`.
+//!
+//! The lines a git diff range changes are matched against the code-text of
+//! the atoms in atoms.json. The modules of the changed atoms are verified,
+//! together with the modules of the atoms that depend on them, directly or
+//! through up to `depth` atoms.
+
+use crate::config::{source_file, SourceRoot};
+use crate::git::git;
+use crate::structure::Atom;
+use anyhow::Result;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Changed line ranges of the new side of the diff, per file.
+pub type ChangedLines = HashMap<PathBuf, Vec<(u32, u32)>>;
+
+/// The modules a diff range affects.
+#[derive(Debug, Default, PartialEq)]
+pub struct DiffScope {
+    /// Modules with changed atoms.
+    pub changed: BTreeSet<String>,
+    /// Other modules with atoms that depend on changed ones.
+    pub dependents: BTreeSet<String>,
+    /// Every other module in atoms.json.
+    pub skipped: BTreeSet<String>,
+}
+
+impl DiffScope {
+    /// The modules to verify.
+    pub fn modules(&self) -> BTreeSet<&str> {
+        self.changed
+            .iter()
+            .chain(&self.dependents)
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// The lines `range` (anything `git diff` accepts, e.g. `origin/main...HEAD`)
+/// changes, keyed by absolute path.
+pub fn changed_lines(project_root: &Path, range: &str) -> Result<ChangedLines> {
+    let toplevel = git(project_root, &["rev-parse", "--show-toplevel"])?;
+    let toplevel = PathBuf::from(toplevel.trim());
+    let diff = git(
+        project_root,
+        &["diff", "--unified=0", "--no-color", "--no-ext-diff", range],
+    )?;
+    Ok(parse_diff(&diff)
+        .into_iter()
+        .map(|(path, ranges)| (toplevel.join(path), ranges))
+        .collect())
+}
+
+/// Changed line ranges per file (relative to the repository) in a
+/// `--unified=0` diff. A hunk that only deletes lines counts as changing the
+/// line after the deletion.
+fn parse_diff(diff: &str) -> HashMap<String, Vec<(u32, u32)>> {
+    let mut changed: HashMap<String, Vec<(u32, u32)>> = HashMap::new();
+    let mut file: Option<String> = None;
+    for line in diff.lines() {
+        if let Some(path) = line.strip_prefix("+++ ") {
+            file = path.strip_prefix("b/").map(str::to_string);
+            continue;
+        }
+        let (Some(file), Some(hunk)) = (&file, line.strip_prefix("@@ ")) else {
+            continue;
+        };
+        // @@ -old[,count] +new[,count] @@
+        let Some(new) = hunk.split_whitespace().find_map(|s| s.strip_prefix('+')) else {
+            continue;
+        };
+        let mut parts = new.split(',');
+        let start: u32 = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+        let count: u32 = parts.next().and_then(|s| s.parse().ok()).unwrap_or(1);
+        let (start, end) = if count == 0 {
+            (start + 1, start + 1)
+        } else {
+            (start, start + count - 1)
+        };
+        changed.entry(file.clone()).or_default().push((start, end));
+    }
+    changed
+}
+
+/// The modules whose atoms overlap `changed`, and the modules of atoms that
+/// depend on those atoms through at most `depth` steps.
+pub fn affected_modules(
+    atoms: &HashMap<String, Atom>,
+    changed: &ChangedLines,
+    project_root: &Path,
+    source_roots: &[SourceRoot],
+    depth: usize,
+) -> DiffScope {
+    let touched: HashSet<&str> = atoms
+        .iter()
+        .filter(|(_, atom)| {
+            let (Some(code_path), Some(text)) = (&atom.code_path, &atom.code_text) else {
+                return false;
+            };
+            let path = source_file(project_root, source_roots, code_path);
+            changed.get(&path).is_some_and(|ranges| {
+                ranges
+                    .iter()
+                    .any(|&(start, end)| start <= text.lines_end && text.lines_start <= end)
+            })
+        })
+        .map(|(name, _)| name.as_str())
+        .collect();
+
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (name, atom) in atoms {
+        for dependency in &atom.dependencies {
+            dependents.entry(dependency).or_default().push(name);
+        }
+    }
+    let mut reached: HashSet<&str> = touched.clone();
+    let mut frontier: Vec<&str> = touched.iter().copied().collect();
+    for _ in 0..depth {
+        frontier = frontier
+            .iter()
+            .flat_map(|name| dependents.get(name).into_iter().flatten().copied())
+            .filter(|name| reached.insert(name))
+            .collect();
+    }
+
+    let module = |name: &str| atoms[name].code_module.clone();
+    let changed: BTreeSet<String> = touched.iter().filter_map(|n| module(n)).collect();
+    let dependents: BTreeSet<String> = reached
+        .iter()
+        .filter_map(|n| module(n))
+        .filter(|m| !changed.contains(m))
+        .collect();
+    let skipped = atoms
+        .values()
+        .filter_map(|atom| atom.code_module.clone())
+        .filter(|m| !changed.contains(m) && !dependents.contains(m))
+        .collect();
+    DiffScope {
+        changed,
+        dependents,
+        skipped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::schema::LineRange;
+
+    #[test]
+    fn test_parse_diff() {
+        let diff = "diff --git a/src/a.rs b/src/a.rs\n\
+                    --- a/src/a.rs\n\
+                    +++ b/src/a.rs\n\
+                    @@ -3 +3 @@ fn a() {\n\
+                    -x\n\
+                    +y\n\
+                    @@ -10,2 +9,0 @@\n\
+                    -gone\n\
+                    -gone\n\
+                    @@ -20,0 +19,3 @@\n\
+                    +new\n\
+                    --- a/src/old.rs\n\
+                    +++ /dev/null\n\
+                    @@ -1,5 +0,0 @@\n";
+        let changed = parse_diff(diff);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed["src/a.rs"], vec![(3, 3), (10, 10), (19, 21)]);
+    }
+
+    #[test]
+    fn test_affected_modules_follow_dependents_up_to_depth() {
+        let dir = Path::new("/project");
+        let atom = |module: &str, line: u32, dependencies: &[&str]| Atom {
+            code_module: Some(module.to_string()),
+            code_path: Some(format!("src/{}.rs", module)),
+            code_text: Some(LineRange {
+                lines_start: line,
+                lines_end: line + 5,
+                ..Default::default()
+            }),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            ..Default::default()
+        };
+        let atoms = HashMap::from([
+            ("a".to_string(), atom("core", 1, &[])),
+            ("b".to_string(), atom("api", 1, &["a"])),
+            ("c".to_string(), atom("cli", 1, &["b"])),
+            ("d".to_string(), atom("docs", 1, &[])),
+        ]);
+        let changed = ChangedLines::from([(dir.join("src/core.rs"), vec![(4, 4)])]);
+
+        let scope = affected_modules(&atoms, &changed, dir, &[], 1);
+        assert_eq!(scope.modules(), BTreeSet::from(["api", "core"]));
+        assert_eq!(scope.skipped, BTreeSet::from(["cli".into(), "docs".into()]));
+
+        let scope = affected_modules(&atoms, &changed, dir, &[], 0);
+        assert_eq!(scope.modules(), BTreeSet::from(["core"]));
+        let scope = affected_modules(&atoms, &changed, dir, &[], 5);
+        assert_eq!(scope.modules(), BTreeSet::from(["api", "cli", "core"]));
+
+        let outside = ChangedLines::from([(dir.join("src/core.rs"), vec![(40, 50)])]);
+        assert_eq!(
+            affected_modules(&atoms, &outside, dir, &[], 1)
+                .modules()
+                .len(),
+            0
+        );
+    }
+}
@@ -0,0 +1,354 @@
+//! Importing verification results from Verus, for `verify --import`.
+//!
+//! Teams that run Verus in their own CI can hand its machine-readable output
+//! to verilib instead of running probe-verus. The file may hold the object
+//! `verus --output-json` prints, the diagnostics of
+//! `--error-format=json` (bare or wrapped in cargo's `compiler-message`), or
+//! both, one after the other.
+//!
+//! A function is verified unless its `function-breakdown` entry (printed
+//! with `--time`) failed or an error's primary span falls inside its
+//! code-text. Breakdown entries are matched to stubs by code-name, errors by
+//! code-path and lines.
+
+use crate::structure::{ProofResult, StubEntry};
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// What a Verus run reported.
+#[derive(Debug, Default)]
+pub struct VerusReport {
+    /// Per-function results from `function-breakdown`: name and success.
+    pub functions: Vec<(String, bool)>,
+    /// Primary spans of error diagnostics: file and line.
+    pub errors: Vec<(String, u32)>,
+    /// `verification-results.errors`, if the summary object was present.
+    pub error_count: Option<u64>,
+    /// Whether the run covered the whole crate, not one module or function.
+    pub entire_crate: bool,
+    /// Verus stopped before verifying anything.
+    pub vir_error: bool,
+}
+
+/// Results imported for stubs, keyed by code-name.
+#[derive(Debug, Default)]
+pub struct Import {
+    pub proofs: HashMap<String, ProofResult>,
+    /// Whether only some functions were verified, so stubs missing from
+    /// `proofs` keep their status.
+    pub partial: bool,
+    /// Breakdown entries that matched no stub, or several.
+    pub unmatched: Vec<String>,
+}
+
+/// Read the Verus output at `path`.
+pub fn load_report(path: &Path) -> Result<VerusReport> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    parse_report(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Parse a stream of Verus JSON values.
+fn parse_report(content: &str) -> Result<VerusReport> {
+    let mut report = VerusReport {
+        entire_crate: true,
+        ..Default::default()
+    };
+    let mut found = false;
+    for value in serde_json::Deserializer::from_str(content).into_iter::<Value>() {
+        let value = value.context("Not Verus JSON output")?;
+        let value = match value.get("reason").and_then(Value::as_str) {
+            Some("compiler-message") => value["message"].clone(),
+            _ => value,
+        };
+
+        if let Some(results) = value.get("verification-results") {
+            found = true;
+            report.error_count = results.get("errors").and_then(Value::as_u64);
+            report.vir_error = results
+                .get("encountered-vir-error")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+            report.entire_crate = results
+                .get("is-verifying-entire-crate")
+                .and_then(Value::as_bool)
+                .unwrap_or(true);
+        }
+        if value.get("level").and_then(Value::as_str) == Some("error") {
+            found = true;
+            let spans = value.get("spans").and_then(Value::as_array).into_iter();
+            report.errors.extend(spans.flatten().filter_map(|span| {
+                if span.get("is_primary").and_then(Value::as_bool) != Some(true) {
+                    return None;
+                }
+                let file = span.get("file_name")?.as_str()?;
+                let line = span.get("line_start")?.as_u64()?;
+                Some((file.to_string(), line as u32))
+            }));
+        }
+        let before = report.functions.len();
+        collect_breakdown(&value, &mut report.functions);
+        found |= report.functions.len() > before;
+    }
+    if !found {
+        bail!(
+            "No Verus results found; expected the output of 'verus --output-json' or \
+             diagnostics from '--error-format=json'"
+        );
+    }
+    Ok(report)
+}
+
+/// Every `function-breakdown` entry anywhere in `value`.
+fn collect_breakdown(value: &Value, functions: &mut Vec<(String, bool)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                if key == "function-breakdown" {
+                    functions.extend(child.as_array().into_iter().flatten().filter_map(|entry| {
+                        let name = entry.get("function")?.as_str()?;
+                        let success = entry.get("success")?.as_bool()?;
+                        Some((name.to_string(), success))
+                    }));
+                } else {
+                    collect_breakdown(child, functions);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_breakdown(item, functions);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The proofs `report` gives the stubs.
+pub fn import(report: &VerusReport, stubs: &HashMap<String, StubEntry>) -> Result<Import> {
+    if report.vir_error {
+        bail!("Verus stopped before verifying (encountered-vir-error); nothing to import");
+    }
+
+    let by_path: Vec<(&str, Vec<&str>)> = stubs
+        .values()
+        .filter_map(|stub| Some((stub.code_name.as_deref()?, stub_path(stub)?)))
+        .collect();
+    let mut breakdown: HashMap<&str, bool> = HashMap::new();
+    let mut unmatched = Vec::new();
+    for (function, success) in &report.functions {
+        match match_function(function, &by_path) {
+            Some(code_name) => {
+                let verified = breakdown.entry(code_name).or_insert(true);
+                *verified &= success;
+            }
+            None => unmatched.push(function.clone()),
+        }
+    }
+
+    let mut proofs = HashMap::new();
+    let mut failed = 0;
+    for stub in stubs.values() {
+        let Some(code_name) = stub.code_name.as_deref() else {
+            continue;
+        };
+        let has_error = match (&stub.code_path, &stub.code_text) {
+            (Some(code_path), Some(text)) => report.errors.iter().any(|(file, line)| {
+                same_file(file, code_path) && (text.lines_start..=text.lines_end).contains(line)
+            }),
+            _ => false,
+        };
+        let verified = match breakdown.get(code_name) {
+            Some(&success) => success && !has_error,
+            None if has_error => false,
+            // Only a whole-crate run vouches for functions it doesn't name.
+            None if report.entire_crate && stub.code_text.is_some() => true,
+            None => continue,
+        };
+        if !verified {
+            failed += 1;
+        }
+        proofs.insert(
+            code_name.to_string(),
+            ProofResult {
+                code_path: stub.code_path.clone(),
+                code_line: stub.code_text.as_ref().map(|text| text.lines_start),
+                verified,
+                status: Some(if verified { "success" } else { "failure" }.to_string()),
+                ..Default::default()
+            },
+        );
+    }
+
+    if failed == 0 && report.error_count.is_some_and(|count| count > 0) {
+        bail!(
+            "Verus reported {} errors, but none of them could be attributed to a function; \
+             run Verus with --time and --error-format=json and import its stdout and stderr together",
+            report.error_count.unwrap_or_default()
+        );
+    }
+    unmatched.sort();
+    unmatched.dedup();
+    Ok(Import {
+        proofs,
+        partial: !report.entire_crate,
+        unmatched,
+    })
+}
+
+/// A stub's module path and name, e.g. `["module", "func_a"]` for
+/// `probe:crate/1.0.0/module/func_a()`; from its module and display-name
+/// when the code-name has another form.
+fn stub_path(stub: &StubEntry) -> Option<Vec<&str>> {
+    let from_code_name = stub
+        .code_name
+        .as_deref()
+        .and_then(|name| name.strip_prefix("probe:"))
+        .map(|rest| {
+            rest.split('/')
+                .skip(2)
+                .flat_map(|segment| segment.split(['#', '.']))
+                .map(|segment| segment.trim_end_matches("()"))
+                .filter(|segment| !segment.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .filter(|segments| !segments.is_empty());
+    from_code_name.or_else(|| {
+        let name = stub.display_name.as_deref()?;
+        let mut segments: Vec<&str> = stub
+            .code_module
+            .as_deref()
+            .map(|module| module.split("::").collect())
+            .unwrap_or_default();
+        segments.push(name);
+        Some(segments)
+    })
+}
+
+/// The code-name of the stub sharing the longest path suffix with a Verus
+/// function name such as `crate::module::func_a`, if exactly one does.
+fn match_function<'a>(function: &str, stubs: &[(&'a str, Vec<&str>)]) -> Option<&'a str> {
+    let segments: Vec<&str> = function.split("::").collect();
+    let suffix = |path: &[&str]| {
+        path.iter()
+            .rev()
+            .zip(segments.iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count()
+    };
+    let mut best = 0;
+    let mut matches = Vec::new();
+    for (code_name, path) in stubs {
+        let length = suffix(path);
+        if length == 0 || length < best {
+            continue;
+        }
+        if length > best {
+            best = length;
+            matches.clear();
+        }
+        matches.push(*code_name);
+    }
+    match matches.as_slice() {
+        [code_name] => Some(code_name),
+        _ => None,
+    }
+}
+
+/// Whether a diagnostic's file (relative to where Verus ran) is a stub's
+/// code-path (relative to its source root).
+fn same_file(file: &str, code_path: &str) -> bool {
+    let file = file.replace('\\', "/");
+    let file = file.trim_start_matches("./");
+    file == code_path
+        || file.ends_with(&format!("/{}", code_path))
+        || code_path.ends_with(&format!("/{}", file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn stubs() -> HashMap<String, StubEntry> {
+        serde_json::from_value(json!({
+            "a.md": {
+                "code-name": "probe:demo/0.1.0/module/func_a()",
+                "code-path": "src/module.rs",
+                "code-text": { "lines-start": 10, "lines-end": 20 }
+            },
+            "b.md": {
+                "code-name": "probe:demo/0.1.0/module/func_b()",
+                "code-path": "src/module.rs",
+                "code-text": { "lines-start": 25, "lines-end": 35 }
+            },
+            "c.md": {
+                "code-name": "probe:demo/0.1.0/other/func_c()",
+                "code-path": "src/other.rs",
+                "code-text": { "lines-start": 5, "lines-end": 15 }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_breakdown_and_diagnostics_map_to_stubs() {
+        let output = json!({
+            "verification-results": {
+                "encountered-vir-error": false,
+                "success": false,
+                "errors": 1,
+                "is-verifying-entire-crate": true
+            },
+            "times-ms": { "smt": { "smt-run-module-times": [{
+                "module": "module",
+                "function-breakdown": [
+                    { "function": "demo::module::func_a", "time": 3, "success": true },
+                    { "function": "demo::gone::func_z", "time": 1, "success": true }
+                ]
+            }]}}
+        })
+        .to_string()
+            + "\n" + &json!({
+            "reason": "compiler-message",
+            "message": {
+                "level": "error",
+                "message": "postcondition not satisfied",
+                "spans": [{ "file_name": "./src/other.rs", "line_start": 7, "is_primary": true }]
+            }
+        })
+        .to_string();
+
+        let report = parse_report(&output).unwrap();
+        let import = import(&report, &stubs()).unwrap();
+        let status = |name: &str| import.proofs[name].status.as_deref();
+        assert_eq!(status("probe:demo/0.1.0/module/func_a()"), Some("success"));
+        assert_eq!(status("probe:demo/0.1.0/module/func_b()"), Some("success"));
+        assert_eq!(status("probe:demo/0.1.0/other/func_c()"), Some("failure"));
+        assert_eq!(import.unmatched, ["demo::gone::func_z"]);
+        assert!(!import.partial);
+    }
+
+    #[test]
+    fn test_unattributed_errors_and_partial_runs() {
+        let summary = |entire: bool, errors: u64| {
+            json!({ "verification-results": {
+                "errors": errors,
+                "is-verifying-entire-crate": entire
+            }})
+            .to_string()
+        };
+        let report = parse_report(&summary(true, 2)).unwrap();
+        assert!(import(&report, &stubs()).is_err());
+
+        let report = parse_report(&summary(false, 0)).unwrap();
+        let import = import(&report, &stubs()).unwrap();
+        assert!(import.partial);
+        assert!(import.proofs.is_empty());
+
+        assert!(parse_report("{\"unrelated\": 1}").is_err());
+        assert!(parse_report("error: not json").is_err());
+    }
+}
@@ -0,0 +1,95 @@
+//! Profile comparison, for `verify --matrix`.
+//!
+//! Each profile's results are kept in `.verilib/proofs.<profile>.json`, next
+//! to proofs.json, so a matrix run leaves the default results alone and
+//! `--no-probe` can compare the stored ones again. The comparison lists the
+//! functions whose outcome is not the same under every profile.
+
+use crate::structure::ProofResult;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Outcome shown for a function a profile has no result for.
+pub const MISSING: &str = "-";
+
+/// Where the results of `profile` are stored.
+pub fn proofs_path(verilib_path: &Path, profile: &str) -> PathBuf {
+    verilib_path.join(format!("proofs.{}.json", profile))
+}
+
+/// `verified`, the failure status probe-verus reported, or [`MISSING`].
+pub fn outcome(proof: Option<&ProofResult>) -> &str {
+    match proof {
+        Some(proof) if proof.verified => "verified",
+        Some(proof) => proof.status.as_deref().unwrap_or("failure"),
+        None => MISSING,
+    }
+}
+
+/// Verified and total result counts of one profile.
+pub fn counts(proofs: &HashMap<String, ProofResult>) -> (usize, usize) {
+    let verified = proofs.values().filter(|proof| proof.verified).count();
+    (verified, proofs.len())
+}
+
+/// The code-names whose outcome differs between the profiles' results, with
+/// the outcome under each profile, in the order given. Sorted by code-name.
+pub fn differences(results: &[HashMap<String, ProofResult>]) -> Vec<(&str, Vec<&str>)> {
+    let names: BTreeSet<&str> = results
+        .iter()
+        .flat_map(|proofs| proofs.keys().map(String::as_str))
+        .collect();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let outcomes: Vec<&str> = results
+                .iter()
+                .map(|proofs| outcome(proofs.get(name)))
+                .collect();
+            let differs = outcomes.iter().any(|o| *o != outcomes[0]);
+            differs.then_some((name, outcomes))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proof(verified: bool, status: Option<&str>) -> ProofResult {
+        ProofResult {
+            verified,
+            status: status.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_differences_lists_only_disagreements() {
+        let quick = HashMap::from([
+            ("a".to_string(), proof(true, None)),
+            ("b".to_string(), proof(false, Some("timeout"))),
+            ("c".to_string(), proof(false, None)),
+        ]);
+        let full = HashMap::from([
+            ("a".to_string(), proof(true, None)),
+            ("b".to_string(), proof(true, None)),
+            ("c".to_string(), proof(false, Some("failure"))),
+            ("d".to_string(), proof(true, None)),
+        ]);
+        let results = [quick, full];
+
+        assert_eq!(
+            differences(&results),
+            vec![
+                ("b", vec!["timeout", "verified"]),
+                ("d", vec![MISSING, "verified"]),
+            ]
+        );
+        assert_eq!(counts(&results[0]), (1, 3));
+        assert_eq!(
+            proofs_path(Path::new(".verilib"), "quick"),
+            Path::new(".verilib/proofs.quick.json")
+        );
+    }
+}
@@ -1,23 +1,46 @@
 use anyhow::{Context, Result};
+use clap::ValueEnum;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
-use std::sync::OnceLock;
 
-use crate::constants::DEFAULT_DOCKER_IMAGE;
-use crate::executor::{CommandConfig, ExecutionMode};
-
-static GLOBAL_CONFIG: OnceLock<ProjectConfig> = OnceLock::new();
+use crate::constants::{BASE_URL_ENV, DEFAULT_BASE_URL, DEFAULT_DOCKER_IMAGE};
+use crate::download::{RateLimitConfig, ServerPolicy};
+use crate::executor::{CommandConfig, DockerMounts, ExecutionMode};
+use crate::exit::{fail, ExitContext, ExitKind};
+use crate::metrics;
+use crate::notify::NotifyPolicy;
+use crate::output;
+use crate::structure::{CertStore, CertStoreKind, FrontmatterFormat, LayoutKind, OwnerRule};
 
 /// Configuration for the repository stored in .verilib/config.json
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 pub struct RepoConfig {
     pub id: String,
     pub url: String,
     pub is_admin: bool,
 }
 
+/// The server URL, from the first of: the `--base-url` flag, `VERILIB_BASE_URL`,
+/// the repository's URL in config.json, and [`DEFAULT_BASE_URL`].
+pub fn resolve_base_url(flag: Option<&str>, repo: Option<&RepoConfig>) -> String {
+    let env = std::env::var(BASE_URL_ENV).ok();
+    base_url_from(flag, env.as_deref(), repo)
+}
+
+fn base_url_from(flag: Option<&str>, env: Option<&str>, repo: Option<&RepoConfig>) -> String {
+    [flag, env, repo.map(|repo| repo.url.as_str())]
+        .into_iter()
+        .flatten()
+        .map(|url| url.trim().trim_end_matches('/'))
+        .find(|url| !url.is_empty())
+        .unwrap_or(DEFAULT_BASE_URL)
+        .to_string()
+}
+
 /// Global configuration for the project stored in .verilib/config.json
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ProjectConfig {
     /// Resolved at runtime from the CLI argument, not persisted to disk.
     #[serde(skip)]
@@ -26,17 +49,281 @@ pub struct ProjectConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub repo: Option<RepoConfig>,
 
+    /// Server repositories for subtrees deployed with `deploy --subdir`, keyed by
+    /// the subdir relative to `.verilib`.
+    #[serde(
+        default,
+        rename = "subdir-repos",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub subdir_repos: BTreeMap<String, RepoConfig>,
+
+    /// `atoms-only` for code-intelligence projects without Verus; full when unset.
+    #[serde(
+        default,
+        rename = "project-mode",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub project_mode: Option<ProjectMode>,
+
     #[serde(rename = "structure-root", skip_serializing_if = "Option::is_none")]
     pub structure_root: Option<String>,
 
+    /// Naming scheme for structure files; the path-mirroring layout when unset.
+    #[serde(
+        default,
+        rename = "structure-layout",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub structure_layout: Option<LayoutKind>,
+
+    /// Storage backend for spec certs; one file per cert when unset.
+    #[serde(
+        default,
+        rename = "cert-store",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cert_store: Option<CertStoreKind>,
+
+    /// Format of new structure file frontmatter; YAML when unset.
+    #[serde(
+        default,
+        rename = "frontmatter-format",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub frontmatter_format: Option<FrontmatterFormat>,
+
     #[serde(default, rename = "execution-mode")]
     pub execution_mode: ExecutionMode,
 
     #[serde(default = "default_docker_image", rename = "docker-image")]
     pub docker_image: String,
 
+    /// What of the project the Docker container can write; the whole project when unset.
+    #[serde(
+        default,
+        rename = "docker-mounts",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub docker_mounts: Option<DockerMounts>,
+
+    /// Keep probe-verus intermediate files under `.verilib/intermediates/`.
+    #[serde(
+        default,
+        rename = "keep-intermediates",
+        skip_serializing_if = "is_false"
+    )]
+    pub keep_intermediates: bool,
+
     #[serde(default, rename = "auto-validate-specs")]
     pub auto_validate_specs: bool,
+
+    /// Refuse mutating `api` operations, e.g. for reviewer checkouts.
+    #[serde(default, rename = "read-only", skip_serializing_if = "is_false")]
+    pub read_only: bool,
+
+    /// Ordered `glob -> owner` rules; the last matching rule wins.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub owners: Vec<OwnerRule>,
+
+    /// age public key that atom contents are encrypted to on deploy.
+    #[serde(
+        default,
+        rename = "encryption-recipient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub encryption_recipient: Option<String>,
+
+    /// Client-side throttling of API requests; defaults apply when unset.
+    #[serde(
+        default,
+        rename = "rate-limit",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub rate_limit: Option<RateLimitConfig>,
+
+    /// Largest atom file `deploy` uploads, and what it does with larger ones.
+    #[serde(
+        default,
+        rename = "max-atom-size",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_atom_size: Option<AtomSizeLimit>,
+
+    /// What `deploy` does when an atom looks like it contains a credential;
+    /// `warn` when unset.
+    #[serde(
+        default,
+        rename = "secret-scan",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub secret_scan: Option<SecretScan>,
+
+    /// Hosts API requests may go to and TLS keys they must present.
+    #[serde(
+        default,
+        rename = "server-policy",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub server_policy: Option<ServerPolicy>,
+
+    /// How many lines a stub's code-line may drift from its atom's start.
+    #[serde(
+        default,
+        rename = "line-tolerance",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub line_tolerance: Option<u32>,
+
+    /// Extra source directories outside the cargo root, relative to the project root
+    /// (e.g. `../vendored-lib`). Their files get code-paths prefixed with the root's label.
+    #[serde(
+        default,
+        rename = "source-roots",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub source_roots: Vec<String>,
+
+    /// Language for user-facing messages (e.g. `es`); `VERILIB_LANG` overrides it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+
+    /// Named probe-verus argument sets selectable with `verify --profile`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, VerifyProfile>,
+
+    /// Record per-command timings in `.verilib/metrics.jsonl`; `VERILIB_METRICS` overrides it.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub metrics: bool,
+
+    /// When to ring the bell and show a desktop notification as a long command finishes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify: Option<NotifyPolicy>,
+
+    /// Stages `run` runs when `--stages` is not given.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pipeline: Vec<PipelineStage>,
+
+    /// Flags inserted before the command-line arguments of a command, e.g.
+    /// `"atomize": ["--update-stubs"]`; skipped with `--no-defaults`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub defaults: BTreeMap<String, Vec<String>>,
+}
+
+/// Which pipeline a project uses (`project-mode` in config.json).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProjectMode {
+    /// Structure files, specs, certs and verification with probe-verus.
+    Full,
+    /// Only atoms.json, for `stats`, `graph` and `export`.
+    AtomsOnly,
+}
+
+/// A stage of the pipeline `run` drives, in pipeline order.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    ValueEnum,
+    JsonSchema,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum PipelineStage {
+    Create,
+    Atomize,
+    Specify,
+    Verify,
+}
+
+impl PipelineStage {
+    /// Stages run when neither `--stages` nor `pipeline` in config.json names any.
+    pub const DEFAULT: [PipelineStage; 3] = [
+        PipelineStage::Atomize,
+        PipelineStage::Specify,
+        PipelineStage::Verify,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PipelineStage::Create => "create",
+            PipelineStage::Atomize => "atomize",
+            PipelineStage::Specify => "specify",
+            PipelineStage::Verify => "verify",
+        }
+    }
+}
+
+/// A named verifier configuration, e.g. `profiles.quick.args = ["--rlimit", "10"]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct VerifyProfile {
+    /// Extra arguments appended to `probe-verus verify`.
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// `max-atom-size` in config.json.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct AtomSizeLimit {
+    /// Size in bytes above which an atom file is oversized.
+    pub bytes: u64,
+    #[serde(default)]
+    pub oversized: OversizedAtoms,
+}
+
+/// What `deploy` does with an atom file larger than `max-atom-size`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum OversizedAtoms {
+    /// Stop before anything is uploaded.
+    #[default]
+    Error,
+    /// Leave the atom out of the upload, with a warning.
+    Skip,
+}
+
+/// What `deploy` does with atoms that look like they contain a secret
+/// (`secret-scan` in config.json).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum SecretScan {
+    /// List them and upload anyway.
+    #[default]
+    Warn,
+    /// List them and stop before anything is uploaded.
+    Block,
+    /// Don't scan.
+    Off,
+}
+
+/// A resolved extra source root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceRoot {
+    /// Prefix for code-paths inside this root: the configured path without `.`/`..`
+    /// components, so `../vendored-lib` becomes `vendored-lib`.
+    pub label: String,
+    /// Configured path, relative to the project root, as passed to probe-verus.
+    pub relative: String,
+    /// Canonical absolute path of the root.
+    pub path: PathBuf,
+}
+
+impl SourceRoot {
+    /// Prefix a code-path relative to this root with the root's label.
+    pub fn prefix(&self, code_path: &str) -> String {
+        format!("{}/{}", self.label, code_path.trim_start_matches("./"))
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
 }
 
 fn default_docker_image() -> String {
@@ -48,28 +335,61 @@ impl Default for ProjectConfig {
         Self {
             project_root: PathBuf::new(),
             repo: None,
+            subdir_repos: BTreeMap::new(),
+            project_mode: None,
             structure_root: None,
+            structure_layout: None,
+            cert_store: None,
+            frontmatter_format: None,
             execution_mode: ExecutionMode::Local,
             docker_image: default_docker_image(),
+            docker_mounts: None,
+            keep_intermediates: false,
             auto_validate_specs: false,
+            read_only: false,
+            owners: Vec::new(),
+            encryption_recipient: None,
+            rate_limit: None,
+            max_atom_size: None,
+            secret_scan: None,
+            server_policy: None,
+            line_tolerance: None,
+            source_roots: Vec::new(),
+            locale: None,
+            profiles: BTreeMap::new(),
+            metrics: false,
+            notify: None,
+            pipeline: Vec::new(),
+            defaults: BTreeMap::new(),
         }
     }
 }
 
 impl ProjectConfig {
-    /// Initialize the global config from a project root. Safe to call multiple times.
-    pub fn init(project_root: &Path) -> Result<()> {
-        if GLOBAL_CONFIG.get().is_some() {
-            return Ok(());
-        }
+    /// Load the config of the project at `project_root`, with `project_root` set
+    /// so the path helpers resolve against it.
+    pub fn open(project_root: &Path) -> Result<Self> {
         let mut config = Self::load(project_root)?;
         config.project_root = project_root.to_path_buf();
-        let _ = GLOBAL_CONFIG.set(config);
-        Ok(())
+        metrics::set_project(project_root);
+        Ok(config)
     }
 
-    pub fn global() -> Option<&'static Self> {
-        GLOBAL_CONFIG.get()
+    pub fn is_atoms_only(&self) -> bool {
+        self.project_mode == Some(ProjectMode::AtomsOnly)
+    }
+
+    /// Fail for a Verus-only `command` in an atoms-only project.
+    pub fn require_full_mode(&self, command: &str) -> Result<()> {
+        if self.is_atoms_only() {
+            fail!(
+                MissingPrerequisite,
+                "'{}' needs Verus, but this project is in atoms-only mode (project-mode in .verilib/config.json). \
+                 Use 'stats', 'graph' or 'export', or set project-mode to \"full\".",
+                command
+            );
+        }
+        Ok(())
     }
 
     pub fn command_config(&self) -> CommandConfig {
@@ -90,6 +410,26 @@ impl ProjectConfig {
         CommandConfig {
             execution_mode: mode,
             docker_image,
+            docker_mounts: self.docker_mounts.unwrap_or_default(),
+            keep_intermediates: self.keep_intermediates,
+        }
+    }
+
+    /// The server repository for `subdir`, or the whole project's when `None`.
+    pub fn repo_for(&self, subdir: Option<&str>) -> Option<&RepoConfig> {
+        match subdir {
+            Some(subdir) => self.subdir_repos.get(subdir),
+            None => self.repo.as_ref(),
+        }
+    }
+
+    /// Record the server repository for `subdir` (or the whole project).
+    pub fn set_repo_for(&mut self, subdir: Option<&str>, repo: RepoConfig) {
+        match subdir {
+            Some(subdir) => {
+                self.subdir_repos.insert(subdir.to_string(), repo);
+            }
+            None => self.repo = Some(repo),
         }
     }
 
@@ -105,14 +445,31 @@ impl ProjectConfig {
         self.verilib_path().join("atoms.json")
     }
 
+    pub fn verified_path(&self) -> PathBuf {
+        self.verilib_path().join("verified.json")
+    }
+
     pub fn certs_specify_dir(&self) -> PathBuf {
         self.verilib_path().join("certs").join("specs")
     }
 
+    /// The configured store of spec certs.
+    pub fn spec_certs(&self) -> Box<dyn CertStore> {
+        self.cert_store
+            .unwrap_or_default()
+            .open(self.certs_specify_dir())
+    }
+
     pub fn structure_root_path(&self) -> Result<PathBuf> {
-        let root = self.structure_root.as_deref().ok_or_else(|| {
-            anyhow::anyhow!("No 'structure-root' in config.json. Run 'verilib-cli create' first.")
-        })?;
+        let root = self
+            .structure_root
+            .as_deref()
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No 'structure-root' in config.json. Run 'verilib-cli create' first."
+                )
+            })
+            .exit_kind(ExitKind::MissingPrerequisite)?;
         Ok(self.project_root.join(root))
     }
 
@@ -139,8 +496,125 @@ impl ProjectConfig {
 
         let content = serde_json::to_string_pretty(self).context("Failed to serialize config")?;
 
-        std::fs::write(&config_path, content).context("Failed to write config.json")?;
+        crate::interrupt::write_atomic(&config_path, content)?;
 
         Ok(config_path)
     }
 }
+
+/// Locate a code-path on disk; paths under a source root start with the root's label.
+pub fn source_file(project_root: &Path, source_roots: &[SourceRoot], code_path: &str) -> PathBuf {
+    source_roots
+        .iter()
+        .find_map(|root| {
+            code_path
+                .strip_prefix(&root.label)
+                .and_then(|rest| rest.strip_prefix('/'))
+                .map(|rest| root.path.join(rest))
+        })
+        .unwrap_or_else(|| project_root.join(code_path))
+}
+
+/// The nearest directory from `start` up that has a `.verilib`, as a path
+/// relative to `start` (`.`, `..`, `../..`, ...), the way git finds `.git`.
+pub fn find_project_root(start: &Path) -> Option<PathBuf> {
+    let levels = start
+        .ancestors()
+        .position(|dir| dir.join(".verilib").is_dir())?;
+    if levels == 0 {
+        return Some(PathBuf::from("."));
+    }
+    Some((0..levels).map(|_| "..").collect())
+}
+
+/// Resolve configured source roots relative to `project_root`.
+pub fn resolve_source_roots(project_root: &Path, roots: &[String]) -> Vec<SourceRoot> {
+    roots
+        .iter()
+        .filter_map(|relative| {
+            let path = match project_root.join(relative).canonicalize() {
+                Ok(p) => p,
+                Err(_) => {
+                    output::warn(format!("source root '{}' not found, ignoring", relative));
+                    return None;
+                }
+            };
+            let label = Path::new(relative)
+                .components()
+                .filter_map(|c| match c {
+                    std::path::Component::Normal(s) => Some(s.to_string_lossy()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("/");
+            if label.is_empty() {
+                output::warn(format!("source root '{}' has no name, ignoring", relative));
+                return None;
+            }
+            Some(SourceRoot {
+                label,
+                relative: relative.clone(),
+                path,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_url_precedence() {
+        let repo = RepoConfig {
+            url: "https://saved.example/".to_string(),
+            ..RepoConfig::default()
+        };
+        let flag = Some("https://flag.example");
+        let env = Some("https://env.example");
+
+        assert_eq!(
+            base_url_from(flag, env, Some(&repo)),
+            "https://flag.example"
+        );
+        assert_eq!(base_url_from(None, env, Some(&repo)), "https://env.example");
+        assert_eq!(
+            base_url_from(None, Some(""), Some(&repo)),
+            "https://saved.example"
+        );
+        assert_eq!(base_url_from(None, None, None), DEFAULT_BASE_URL);
+    }
+
+    #[test]
+    fn test_find_project_root_walks_up() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let nested = dir.path().join("src/submodule");
+        std::fs::create_dir_all(&nested).unwrap();
+        assert_eq!(find_project_root(&nested), None);
+
+        std::fs::create_dir(dir.path().join(".verilib")).unwrap();
+        assert_eq!(find_project_root(dir.path()), Some(PathBuf::from(".")));
+        assert_eq!(find_project_root(&nested), Some(PathBuf::from("../..")));
+    }
+
+    #[test]
+    fn test_open_is_per_project() {
+        let dirs = [
+            tempfile::TempDir::new().unwrap(),
+            tempfile::TempDir::new().unwrap(),
+        ];
+        for (dir, layout) in dirs.iter().zip([LayoutKind::Path, LayoutKind::Flat]) {
+            let config = ProjectConfig {
+                structure_layout: Some(layout),
+                ..ProjectConfig::default()
+            };
+            config.save(dir.path()).unwrap();
+        }
+
+        for (dir, layout) in dirs.iter().zip([LayoutKind::Path, LayoutKind::Flat]) {
+            let config = ProjectConfig::open(dir.path()).unwrap();
+            assert_eq!(config.structure_layout, Some(layout));
+            assert_eq!(config.stubs_path(), dir.path().join(".verilib/stubs.json"));
+        }
+    }
+}
@@ -1,18 +1,20 @@
+use crate::i18n::t;
+
 pub const DEFAULT_BASE_URL: &str = "https://verilib.org";
 
+/// Overrides the server URL when `--base-url` is not given.
+pub const BASE_URL_ENV: &str = "VERILIB_BASE_URL";
+
 // CLI binary name - could also get this from env!("CARGO_PKG_NAME")
 pub const CLI_NAME: &str = env!("CARGO_PKG_NAME");
 
 // Dynamic error message generators
 pub fn auth_required_msg() -> String {
-    format!("No API key found. Please run '{} auth' first", CLI_NAME)
+    t!("auth-required", cli = CLI_NAME)
 }
 
 pub fn init_required_msg() -> String {
-    format!(
-        "Project not initialized. Please run '{} init <repo_id>' first",
-        CLI_NAME
-    )
+    t!("init-required", cli = CLI_NAME)
 }
 
 pub const DEFAULT_DOCKER_IMAGE: &str = "ghcr.io/beneficial-ai-foundation/verilib-cli:latest";
@@ -0,0 +1,93 @@
+//! Default flags per command, from `defaults` in `~/.verilib_config.json` and
+//! `.verilib/config.json`.
+//!
+//! `"defaults": {"atomize": ["--update-stubs"]}` inserts the flags right after
+//! the subcommand, before the arguments given on the command line, which
+//! override them. User defaults come first, then the project's.
+//! `--no-defaults` skips both.
+
+use crate::config::{find_project_root, ProjectConfig};
+use crate::user_config::UserConfig;
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// `args` with the configured defaults of the command `matches` parsed, or
+/// `None` when it has none.
+pub fn apply(args: &[OsString], matches: &ArgMatches) -> Option<Vec<OsString>> {
+    if matches.get_flag("no_defaults") {
+        return None;
+    }
+    let mut path = Vec::new();
+    let mut leaf = matches;
+    while let Some((name, sub)) = leaf.subcommand() {
+        path.push(name);
+        leaf = sub;
+    }
+    let command = path.join(" ");
+    let argument = leaf
+        .try_get_one::<PathBuf>("project_root")
+        .ok()
+        .flatten()
+        .filter(|_| leaf.value_source("project_root") == Some(ValueSource::CommandLine));
+    let project_root = argument
+        .or_else(|| matches.get_one::<PathBuf>("project_dir"))
+        .cloned()
+        .or_else(|| find_project_root(&std::env::current_dir().ok()?))
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    let mut defaults = Vec::new();
+    if let Ok(config) = UserConfig::load() {
+        defaults.extend(config.defaults.get(&command).cloned().unwrap_or_default());
+    }
+    if let Ok(config) = ProjectConfig::load(&project_root) {
+        defaults.extend(config.defaults.get(&command).cloned().unwrap_or_default());
+    }
+    insert(args, &path, &defaults)
+}
+
+/// `args` with `defaults` after the last name of the subcommand `path`.
+fn insert(args: &[OsString], path: &[&str], defaults: &[String]) -> Option<Vec<OsString>> {
+    if defaults.is_empty() {
+        return None;
+    }
+    let mut position = 0;
+    for name in path {
+        position += args[position + 1..].iter().position(|arg| arg == name)? + 1;
+    }
+    let mut with_defaults = args[..=position].to_vec();
+    with_defaults.extend(defaults.iter().map(OsString::from));
+    with_defaults.extend_from_slice(&args[position + 1..]);
+    Some(with_defaults)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_after_subcommand_path() {
+        let args: Vec<OsString> = ["verilib-cli", "--json", "functions", "list", "--owner", "a"]
+            .into_iter()
+            .map(OsString::from)
+            .collect();
+        let defaults = vec!["--owner".to_string(), "b".to_string()];
+
+        assert_eq!(
+            insert(&args, &["functions", "list"], &defaults).unwrap(),
+            [
+                "verilib-cli",
+                "--json",
+                "functions",
+                "list",
+                "--owner",
+                "b",
+                "--owner",
+                "a"
+            ]
+        );
+        assert!(insert(&args, &["functions", "list"], &[]).is_none());
+        assert!(insert(&args, &["verify"], &defaults).is_none());
+    }
+}
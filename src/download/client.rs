@@ -1,14 +1,18 @@
 #![allow(dead_code)] // WIP: not yet wired into CLI — see https://github.com/Beneficial-AI-Foundation/verilib-cli/issues/36
 
 use anyhow::{Context, Result};
-use reqwest::Client;
 use std::fs;
 use std::io::{self, Write};
-use std::time::Duration;
+use std::path::Path;
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 
-use super::error::handle_api_error;
-use super::types::{AtomizationStatusResponse, DownloadResponse};
+use super::error::{api_error, ApiError, ApiOperation};
+use super::http::ApiClient;
+use super::types::{AtomizationStatusResponse, DownloadData, DownloadResponse, TreeNode};
+use crate::encryption::decrypt_content;
+use crate::paths::{check_segment, join_inside};
+use age::x25519::Identity;
 
 pub async fn download_repo(
     repo_id: &str,
@@ -18,18 +22,14 @@ pub async fn download_repo(
 ) -> Result<DownloadResponse> {
     let endpoint = format!("{}/v2/repo/download/{}", base_url, repo_id);
 
-    let client = Client::new();
+    let client = ApiClient::for_project(api_key)?;
     let response = client
-        .get(&endpoint)
-        .header("Authorization", format!("ApiKey {}", api_key))
-        .header("Accept", "application/json")
-        .send()
+        .send(client.get(&endpoint).header("Accept", "application/json"))
         .await
         .context("Failed to send request to API")?;
 
     if !response.status().is_success() {
-        let error_msg = handle_api_error(response).await?;
-        anyhow::bail!(error_msg);
+        return Err(api_error(response, ApiOperation::Pull).await);
     }
 
     let response_text = response
@@ -47,56 +47,148 @@ pub async fn download_repo(
 
     let download_data: DownloadResponse =
         serde_json::from_str(&response_text).context("Failed to parse JSON response")?;
+    check_paths(&download_data.data).context("Server sent a tree with an unsafe path")?;
 
     Ok(download_data)
 }
 
-pub async fn wait_for_atomization(repo_id: &str, base_url: &str, api_key: &str) -> Result<()> {
+/// Reject a download whose node identifiers, node paths or layout keys could
+/// resolve outside `.verilib` once materialized as files.
+fn check_paths(data: &DownloadData) -> Result<()> {
+    fn check_nodes(nodes: &[TreeNode]) -> Result<()> {
+        for node in nodes {
+            check_segment(&node.identifier)
+                .with_context(|| format!("node {} identifier", node.id))?;
+            join_inside(
+                Path::new(""),
+                node.path.strip_prefix('/').unwrap_or(&node.path),
+            )
+            .with_context(|| format!("node {} path", node.id))?;
+            check_nodes(&node.children)?;
+        }
+        Ok(())
+    }
+
+    check_nodes(&data.tree)?;
+    for (folder, layout) in &data.layouts {
+        join_inside(Path::new(""), folder.strip_prefix('/').unwrap_or(folder))
+            .context("layout folder")?;
+        for node in &layout.nodes {
+            check_segment(&node.identifier)
+                .with_context(|| format!("layout node in '{}'", folder))?;
+        }
+    }
+    Ok(())
+}
+
+/// Decrypt snippet texts of every node that was encrypted on deploy.
+/// Returns the number of snippets that were decrypted.
+pub fn decrypt_tree(nodes: &mut [TreeNode], identity: &Identity) -> Result<usize> {
+    let mut count = 0;
+    for node in nodes {
+        for snippet in &mut node.snippets {
+            let decrypted = decrypt_content(identity, &snippet.text)
+                .with_context(|| format!("Failed to decrypt snippet of {}", node.identifier))?;
+            if decrypted != snippet.text {
+                snippet.text = decrypted;
+                count += 1;
+            }
+        }
+        count += decrypt_tree(&mut node.children, identity)?;
+    }
+    Ok(count)
+}
+
+/// Query the atomization status endpoint once.
+pub async fn fetch_atomization_status(
+    repo_id: &str,
+    base_url: &str,
+    api_key: &str,
+) -> Result<AtomizationStatusResponse> {
     let endpoint = format!("{}/api/atomization-status?id={}", base_url, repo_id);
-    let client = Client::new();
+
+    let client = ApiClient::for_project(api_key)?;
+    let response = client
+        .send(client.get(&endpoint).header("Accept", "application/json"))
+        .await
+        .context("Failed to send atomization status request")?;
+
+    if !response.status().is_success() {
+        return Err(api_error(response, ApiOperation::AtomizationStatus).await);
+    }
+
+    let response_text = response
+        .text()
+        .await
+        .context("Failed to read response body")?;
+
+    serde_json::from_str(&response_text).context("Failed to parse atomization status response")
+}
+
+/// Whether a failed request is worth repeating: the server could not be
+/// reached, rate limited it, or failed. A rejected key, a missing repository
+/// or an unreadable answer will not change by asking again.
+fn is_transient(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<ApiError>()
+            .map_or(cause.is::<reqwest::Error>(), ApiError::is_transient)
+    })
+}
+
+/// Poll the atomization status until it completes, printing each status
+/// transition. Transient request failures are retried and any other ends
+/// the wait; `timeout` bounds the total wait.
+pub async fn wait_for_atomization(
+    repo_id: &str,
+    base_url: &str,
+    api_key: &str,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let started = Instant::now();
+    let mut last_status: Option<String> = None;
 
     print!("Waiting for atomization");
-    io::stdout().flush().unwrap();
+    let _ = io::stdout().flush();
 
     loop {
         sleep(Duration::from_secs(2)).await;
 
-        print!(".");
-        io::stdout().flush().unwrap();
-
-        let response = match client
-            .get(&endpoint)
-            .header("Authorization", format!("ApiKey {}", api_key))
-            .header("Accept", "application/json")
-            .send()
-            .await
-        {
-            Ok(resp) => resp,
-            Err(_) => {
-                continue;
+        if let Some(limit) = timeout {
+            if started.elapsed() >= limit {
+                println!();
+                anyhow::bail!(
+                    "Timed out after {}s waiting for atomization (last status: {})",
+                    limit.as_secs(),
+                    last_status.as_deref().unwrap_or("unknown")
+                );
             }
-        };
-
-        if !response.status().is_success() {
-            continue;
         }
 
-        let response_text = match response.text().await {
-            Ok(text) => text,
-            Err(_) => {
-                continue;
-            }
-        };
-
-        let status_response: AtomizationStatusResponse = match serde_json::from_str(&response_text)
-        {
+        let status_response = match fetch_atomization_status(repo_id, base_url, api_key).await {
             Ok(data) => data,
-            Err(_) => {
+            Err(e) if is_transient(&e) => {
+                print!(".");
+                let _ = io::stdout().flush();
                 continue;
             }
+            Err(e) => {
+                println!();
+                return Err(e);
+            }
         };
 
-        if status_response.status_id == "2" {
+        let description = status_response.description();
+        if last_status.as_deref() != Some(description.as_str()) {
+            println!();
+            print!("Atomization status: {}", description);
+            last_status = Some(description);
+        } else {
+            print!(".");
+        }
+        let _ = io::stdout().flush();
+
+        if status_response.is_complete() {
             println!();
             break;
         }
@@ -104,3 +196,72 @@ pub async fn wait_for_atomization(repo_id: &str, base_url: &str, api_key: &str)
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::download::api_error_from_parts;
+    use serde_json::json;
+
+    fn data(tree: serde_json::Value, layouts: serde_json::Value) -> DownloadData {
+        serde_json::from_value(json!({
+            "repo": { "id": "1" },
+            "tree": tree,
+            "layouts": layouts,
+        }))
+        .unwrap()
+    }
+
+    fn node(identifier: &str, path: &str, children: serde_json::Value) -> serde_json::Value {
+        json!({
+            "id": 1, "parent_id": null, "identifier": identifier, "index": 0,
+            "statement_type": "fn", "status_id": 0, "specified": false,
+            "path": path, "snippets": [], "children": children,
+        })
+    }
+
+    #[test]
+    fn test_only_transient_errors_are_retried() {
+        let api = |status, body| {
+            api_error_from_parts(status, body, None, ApiOperation::AtomizationStatus)
+        };
+        assert!(is_transient(&api(503, "")));
+        assert!(is_transient(&api(429, "")));
+        assert!(is_transient(&api(502, "").context("Failed to poll")));
+        assert!(!is_transient(&api(401, "revoked")));
+        assert!(!is_transient(&api(404, "")));
+        let parse = serde_json::from_str::<AtomizationStatusResponse>("nope")
+            .context("Failed to parse atomization status response")
+            .unwrap_err();
+        assert!(!is_transient(&parse));
+    }
+
+    #[test]
+    fn test_adversarial_trees_are_rejected() {
+        let safe = node("src", "/src", json!([node("add", "/src/add", json!([]))]));
+        assert!(check_paths(&data(json!([safe.clone()]), json!([]))).is_ok());
+
+        let adversarial = [
+            node("..", "/..", json!([])),
+            node("src", "/src", json!([node("x", "/src/../../x", json!([]))])),
+            node("src", "/src", json!([node("..\\x", "/src/x", json!([]))])),
+            node("aux", "/aux", json!([])),
+            node("x", "//etc/passwd", json!([])),
+        ];
+        for tree in adversarial {
+            assert!(
+                check_paths(&data(json!([tree.clone()]), json!([]))).is_err(),
+                "{}",
+                tree
+            );
+        }
+
+        let layout = json!({ "nodes": [], "fx": 0, "fy": 0 });
+        assert!(check_paths(&data(
+            json!([safe.clone()]),
+            json!({ "src": layout.clone() })
+        ))
+        .is_ok());
+        assert!(check_paths(&data(json!([safe]), json!({ "../src": layout }))).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+#![allow(dead_code)] // WIP: the server has no endpoint for per-node content yet
+
+//! Content stage of a download.
+//!
+//! For a server that serves node contents separately from the tree, they
+//! are fetched after the tree is parsed, a bounded number at a time, each
+//! retried on its own, with one progress bar over all of them. Nothing is
+//! written here: the caller materializes the tree once every text is in.
+//!
+//! Requests carry the API key, so content is only ever fetched from the
+//! origin of the server URL; any other URL is refused before it is sent.
+
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::Url;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio::time::sleep;
+
+use super::error::{api_error, ApiOperation};
+use super::http::ApiClient;
+use crate::output;
+
+/// Contents fetched at the same time.
+const DOWNLOAD_CONCURRENCY: usize = 8;
+
+/// Retries of a content request that failed or was refused. 429 responses
+/// are retried by [`ApiClient`] itself.
+const CONTENT_RETRIES: u32 = 3;
+
+/// Wait before the first retry of a request, doubled for each further one.
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Fetch the content at each of `locations`, given as `(label, url)` with
+/// the label naming the node in errors. URLs are relative to `base_url` or
+/// absolute on its origin. Texts are returned in the order of `locations`.
+pub async fn fetch_contents(
+    client: &ApiClient,
+    base_url: &str,
+    locations: Vec<(String, String)>,
+) -> Result<Vec<String>> {
+    let (labels, urls): (Vec<String>, Vec<String>) = locations.into_iter().unzip();
+    let urls = urls
+        .iter()
+        .zip(&labels)
+        .map(|(url, label)| resolve_url(base_url, url).with_context(|| label.clone()))
+        .collect::<Result<Vec<Url>>>()?;
+
+    let client = client.clone();
+    let texts = fetch_all(
+        urls,
+        DOWNLOAD_CONCURRENCY,
+        CONTENT_RETRIES,
+        RETRY_BACKOFF,
+        move |url| {
+            let client = client.clone();
+            async move { fetch_text(&client, url).await }
+        },
+    )
+    .await;
+
+    let mut failures = Vec::new();
+    let mut contents = Vec::new();
+    for (label, text) in labels.into_iter().zip(texts) {
+        match text {
+            Ok(text) => contents.push(text),
+            Err(e) => failures.push(format!("{}: {:#}", label, e)),
+        }
+    }
+    if !failures.is_empty() {
+        bail!(
+            "Failed to fetch {} node contents:\n  {}",
+            failures.len(),
+            failures.join("\n  ")
+        );
+    }
+    Ok(contents)
+}
+
+/// `url` resolved against `base_url`, refused unless it is on the same
+/// origin (scheme, host and port), so the API key never goes elsewhere.
+fn resolve_url(base_url: &str, url: &str) -> Result<Url> {
+    let base = Url::parse(&format!("{}/", base_url.trim_end_matches('/')))
+        .with_context(|| format!("Invalid server URL '{}'", base_url))?;
+    let resolved = base
+        .join(url)
+        .with_context(|| format!("Invalid content URL '{}'", url))?;
+    if resolved.origin() != base.origin() {
+        bail!(
+            "Refusing to fetch content from {}: not on the server's origin {}",
+            resolved,
+            base.origin().ascii_serialization()
+        );
+    }
+    Ok(resolved)
+}
+
+async fn fetch_text(client: &ApiClient, url: Url) -> Result<String> {
+    let response = client
+        .send(client.get(url.as_str()))
+        .await
+        .with_context(|| format!("Failed to request {}", url))?;
+    if !response.status().is_success() {
+        return Err(api_error(response, ApiOperation::Pull).await);
+    }
+    response.text().await.context("Failed to read content body")
+}
+
+/// Run `fetch` on every URL, at most `concurrency` at once, retrying each up
+/// to `retries` times, waiting `backoff` and then twice as long each time.
+/// Results are in the order of `urls`.
+async fn fetch_all<T, F, Fut>(
+    urls: Vec<T>,
+    concurrency: usize,
+    retries: u32,
+    backoff: Duration,
+    fetch: F,
+) -> Vec<Result<String>>
+where
+    T: Clone + Send + 'static,
+    F: Fn(T) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = Result<String>> + Send,
+{
+    let progress = output::progress_bar(urls.len() as u64, "Downloading contents");
+    let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+    for (index, url) in urls.into_iter().enumerate() {
+        let permits = Arc::clone(&permits);
+        let fetch = fetch.clone();
+        let progress = progress.clone();
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let mut attempt = 0;
+            let result = loop {
+                match fetch(url.clone()).await {
+                    Ok(text) => break Ok(text),
+                    Err(_) if attempt < retries => {
+                        sleep(backoff * (1 << attempt.min(5))).await;
+                        attempt += 1;
+                    }
+                    Err(e) => break Err(e),
+                }
+            };
+            progress.inc(1);
+            (index, result)
+        });
+    }
+
+    let mut results: Vec<Option<Result<String>>> = Vec::new();
+    results.resize_with(tasks.len(), || None);
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((index, result)) => results[index] = Some(result),
+            Err(e) => progress.println(format!("content download task failed: {}", e)),
+        }
+    }
+    progress.finish_and_clear();
+    results
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| Err(anyhow!("content download task failed"))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_fetch_all_bounds_concurrency_and_retries() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let attempts: Arc<Mutex<HashMap<String, u32>>> = Arc::default();
+        let fetch = {
+            let (in_flight, peak, attempts) = (in_flight.clone(), peak.clone(), attempts.clone());
+            move |url: String| {
+                let (in_flight, peak, attempts) =
+                    (in_flight.clone(), peak.clone(), attempts.clone());
+                async move {
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    sleep(Duration::from_millis(5)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    let attempt = {
+                        let mut attempts = attempts.lock().unwrap();
+                        let attempt = attempts.entry(url.clone()).or_default();
+                        *attempt += 1;
+                        *attempt
+                    };
+                    match url.as_str() {
+                        "flaky" if attempt == 1 => anyhow::bail!("connection reset"),
+                        "broken" => anyhow::bail!("503"),
+                        _ => Ok(format!("text of {}", url)),
+                    }
+                }
+            }
+        };
+
+        let mut urls: Vec<String> = (0..10).map(|i| format!("node{}", i)).collect();
+        urls.push("flaky".to_string());
+        urls.push("broken".to_string());
+        let results = fetch_all(urls, 3, 2, Duration::from_millis(1), fetch).await;
+
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+        assert_eq!(results[0].as_ref().unwrap(), "text of node0");
+        assert_eq!(results[10].as_ref().unwrap(), "text of flaky");
+        assert!(results[11].is_err());
+        assert_eq!(attempts.lock().unwrap()["broken"], 3);
+    }
+
+    #[test]
+    fn test_resolve_url_stays_on_origin() {
+        let resolve = |url| resolve_url("https://verilib.org/", url).map(|u| u.to_string());
+        assert_eq!(
+            resolve("/v2/content/7").unwrap(),
+            "https://verilib.org/v2/content/7"
+        );
+        assert_eq!(
+            resolve("https://verilib.org/v2/content/7").unwrap(),
+            "https://verilib.org/v2/content/7"
+        );
+        for foreign in [
+            "https://cdn.example/7",
+            "//cdn.example/7",
+            "http://verilib.org/v2/content/7",
+            "https://verilib.org:8443/v2/content/7",
+        ] {
+            assert!(resolve(foreign).is_err(), "{}", foreign);
+        }
+    }
+
+    /// A content URL on another origin is refused before anything, API key
+    /// included, is sent; one on the server's origin is fetched.
+    #[tokio::test]
+    async fn test_no_credentials_sent_cross_origin() {
+        use crate::download::RateLimitConfig;
+        use crate::download::ServerPolicy;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requests: Arc<Mutex<Vec<String>>> = Arc::default();
+        let recorded = Arc::clone(&requests);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buffer = vec![0; 4096];
+                let n = stream.read(&mut buffer).await.unwrap_or(0);
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&buffer[..n]).to_string());
+                let _ = stream
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok",
+                    )
+                    .await;
+            }
+        });
+
+        let client = ApiClient::new(
+            "secret-key",
+            RateLimitConfig::default(),
+            ServerPolicy::default(),
+        )
+        .unwrap();
+        let foreign = format!("http://127.0.0.1:{}/content/1", port);
+        let error = fetch_contents(
+            &client,
+            &format!("http://localhost:{}", port),
+            vec![("node".to_string(), foreign)],
+        )
+        .await
+        .unwrap_err();
+        assert!(format!("{:#}", error).contains("Refusing"), "{:#}", error);
+        assert!(requests.lock().unwrap().is_empty());
+
+        let texts = fetch_contents(
+            &client,
+            &format!("http://127.0.0.1:{}", port),
+            vec![("node".to_string(), "/content/1".to_string())],
+        )
+        .await
+        .unwrap();
+        assert_eq!(texts, ["ok"]);
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0]
+            .to_ascii_lowercase()
+            .contains("authorization: apikey secret-key"));
+    }
+}
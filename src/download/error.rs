@@ -1,6 +1,12 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use reqwest::header::RETRY_AFTER;
 use reqwest::Response;
 use serde::Deserialize;
+use std::fmt;
+use std::time::Duration;
+
+use crate::constants::CLI_NAME;
 
 #[derive(Deserialize, Debug)]
 struct ApiErrorResponse {
@@ -14,32 +20,276 @@ struct ApiErrorData {
     message: String,
 }
 
-pub async fn handle_api_error(response: Response) -> Result<String> {
-    let status = response.status();
-
-    let response_text = match response.text().await {
-        Ok(text) => text,
-        Err(_) => return Ok(format!("API request failed with status: {}", status)),
-    };
-
-    if let Ok(error_response) = serde_json::from_str::<ApiErrorResponse>(&response_text) {
-        if error_response.error {
-            return Ok(format!(
-                "API error ({}): {}",
-                error_response.data.code, error_response.data.message
-            ));
+/// The command that issued a failing request, used to tailor guidance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiOperation {
+    Init,
+    Pull,
+    Deploy,
+    Reclone,
+    AtomizationStatus,
+}
+
+/// A failed API request, classified by server error code or HTTP status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    Validation(String),
+    RateLimited {
+        message: String,
+        retry_after: Option<Duration>,
+    },
+    Server {
+        status: u16,
+        message: String,
+    },
+    Other {
+        status: u16,
+        message: String,
+    },
+}
+
+impl ApiError {
+    /// Classify an error from its HTTP status, response body, and optional
+    /// `Retry-After` header. A structured `{error, data: {code, message}}`
+    /// body takes precedence over the HTTP status.
+    pub fn from_parts(status: u16, body: &str, retry_after: Option<&str>) -> Self {
+        let (code, message) = match serde_json::from_str::<ApiErrorResponse>(body) {
+            Ok(parsed) if parsed.error => (parsed.data.code, parsed.data.message),
+            _ if body.trim().is_empty() => (status, "Unable to read error response".to_string()),
+            _ => (status, body.trim().to_string()),
+        };
+
+        match code {
+            401 => ApiError::Unauthorized(message),
+            403 => ApiError::Forbidden(message),
+            404 => ApiError::NotFound(message),
+            409 => ApiError::Conflict(message),
+            422 => ApiError::Validation(message),
+            429 => ApiError::RateLimited {
+                message,
+                retry_after: retry_after.and_then(parse_retry_after),
+            },
+            500..=599 => ApiError::Server {
+                status: code,
+                message,
+            },
+            _ => ApiError::Other {
+                status: code,
+                message,
+            },
+        }
+    }
+
+    pub async fn from_response(response: Response) -> Self {
+        let status = response.status().as_u16();
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let body = response.text().await.unwrap_or_default();
+        Self::from_parts(status, &body, retry_after.as_deref())
+    }
+
+    pub fn status(&self) -> u16 {
+        match self {
+            ApiError::Unauthorized(_) => 401,
+            ApiError::Forbidden(_) => 403,
+            ApiError::NotFound(_) => 404,
+            ApiError::Conflict(_) => 409,
+            ApiError::Validation(_) => 422,
+            ApiError::RateLimited { .. } => 429,
+            ApiError::Server { status, .. } | ApiError::Other { status, .. } => *status,
         }
     }
 
-    if !response_text.is_empty() {
-        Ok(format!(
-            "API request failed with status: {} - {}",
-            status, response_text
-        ))
-    } else {
-        Ok(format!(
-            "API request failed with status: {} - Unable to read error response",
-            status
-        ))
+    pub fn message(&self) -> &str {
+        match self {
+            ApiError::Unauthorized(m)
+            | ApiError::Forbidden(m)
+            | ApiError::NotFound(m)
+            | ApiError::Conflict(m)
+            | ApiError::Validation(m) => m,
+            ApiError::RateLimited { message, .. }
+            | ApiError::Server { message, .. }
+            | ApiError::Other { message, .. } => message,
+        }
+    }
+
+    /// Whether retrying the same request later may succeed: rate limits and
+    /// server errors.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, ApiError::RateLimited { .. } | ApiError::Server { .. })
+    }
+
+    /// Suggest what the user should do next, given the failing operation.
+    pub fn guidance(&self, op: ApiOperation) -> Option<String> {
+        match (self, op) {
+            (ApiError::Unauthorized(_), _) => Some(format!(
+                "Your API key was rejected. Run '{} auth' to store a new one.",
+                CLI_NAME
+            )),
+            (ApiError::Forbidden(_), ApiOperation::Deploy) => {
+                Some("Your account cannot deploy to this repository.".to_string())
+            }
+            (ApiError::Forbidden(_), _) => {
+                Some("Your account does not have access to this repository.".to_string())
+            }
+            (ApiError::NotFound(_), ApiOperation::Init) => Some(
+                "Repository ID not found. Check --id, or omit it to create a new repository."
+                    .to_string(),
+            ),
+            (ApiError::NotFound(_), _) => Some(
+                "Repository ID not found. Check the 'repo' entry in .verilib/config.json."
+                    .to_string(),
+            ),
+            (ApiError::Conflict(_), ApiOperation::Deploy) => Some(format!(
+                "The server has changes you don't have. Run '{} status --remote' to see what \
+                 differs.",
+                CLI_NAME
+            )),
+            (ApiError::Conflict(_), ApiOperation::Reclone) => {
+                Some("A reclone is already in progress. Try again later.".to_string())
+            }
+            (ApiError::Conflict(_), _) => None,
+            (ApiError::Validation(_), _) => {
+                Some("The server rejected the request data; see the details below.".to_string())
+            }
+            (ApiError::RateLimited { retry_after, .. }, _) => Some(match retry_after {
+                Some(d) => format!("Rate limited. Retry after {}s.", d.as_secs()),
+                None => "Rate limited. Wait a moment and retry.".to_string(),
+            }),
+            (ApiError::Server { .. }, _) => {
+                Some("The server encountered an error. Try again later.".to_string())
+            }
+            (ApiError::Other { .. }, _) => None,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "API error ({}): {}", self.status(), self.message())
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Parse a `Retry-After` header given either as seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    let delta = date.with_timezone(&Utc) - Utc::now();
+    Some(delta.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Turn a failed response into an error carrying the typed [`ApiError`] and
+/// operation-specific guidance.
+pub async fn api_error(response: Response, op: ApiOperation) -> anyhow::Error {
+    guided(ApiError::from_response(response).await, op)
+}
+
+/// [`api_error`] for a response whose body has already been read.
+pub fn api_error_from_parts(
+    status: u16,
+    body: &str,
+    retry_after: Option<&str>,
+    op: ApiOperation,
+) -> anyhow::Error {
+    guided(ApiError::from_parts(status, body, retry_after), op)
+}
+
+fn guided(error: ApiError, op: ApiOperation) -> anyhow::Error {
+    match error.guidance(op) {
+        Some(hint) => anyhow::Error::new(error).context(hint),
+        None => anyhow::Error::new(error),
+    }
+}
+
+pub async fn handle_api_error(response: Response) -> Result<String> {
+    Ok(ApiError::from_response(response).await.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_structured_body_code_wins_over_status() {
+        let body = r#"{"error": true, "data": {"code": 404, "message": "Repo missing"}}"#;
+        let err = ApiError::from_parts(400, body, None);
+        assert_eq!(err, ApiError::NotFound("Repo missing".to_string()));
+        assert!(err
+            .guidance(ApiOperation::Init)
+            .unwrap()
+            .contains("Repository ID not found"));
+    }
+
+    #[test]
+    fn test_rate_limit_reads_retry_after_seconds() {
+        let err = ApiError::from_parts(429, "slow down", Some("30"));
+        assert_eq!(
+            err,
+            ApiError::RateLimited {
+                message: "slow down".to_string(),
+                retry_after: Some(Duration::from_secs(30)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_empty_body_falls_back_to_status() {
+        let err = ApiError::from_parts(503, "", None);
+        assert_eq!(err.status(), 503);
+        assert!(matches!(err, ApiError::Server { .. }));
+    }
+
+    /// Guidance only sends users to commands this CLI has.
+    #[test]
+    fn test_guidance_names_existing_commands() {
+        use clap::CommandFactory;
+        let cli = crate::cli::Cli::command();
+        let prefix = format!("'{} ", CLI_NAME);
+        let errors = [
+            ApiError::Unauthorized(String::new()),
+            ApiError::Forbidden(String::new()),
+            ApiError::NotFound(String::new()),
+            ApiError::Conflict(String::new()),
+            ApiError::Validation(String::new()),
+        ];
+        let ops = [
+            ApiOperation::Init,
+            ApiOperation::Pull,
+            ApiOperation::Deploy,
+            ApiOperation::Reclone,
+            ApiOperation::AtomizationStatus,
+        ];
+        for err in &errors {
+            for op in ops {
+                let Some(guidance) = err.guidance(op) else {
+                    continue;
+                };
+                for (at, _) in guidance.match_indices(&prefix) {
+                    let command = guidance[at + prefix.len()..]
+                        .split([' ', '\''])
+                        .next()
+                        .unwrap();
+                    assert!(
+                        cli.find_subcommand(command).is_some(),
+                        "{:?}/{:?} names unknown command '{}'",
+                        err,
+                        op,
+                        command
+                    );
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,279 @@
+//! Shared HTTP layer for Verilib API requests.
+//!
+//! Every request goes through a client-side token-bucket rate limiter and is
+//! retried with backoff when the server answers 429 Too Many Requests.
+//! Requests, redirects included, are checked against the project's
+//! [`ServerPolicy`] before they are sent.
+
+use anyhow::{Context, Result};
+use reqwest::{redirect, Client, RequestBuilder, Response, StatusCode};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+
+use super::error::ApiError;
+use super::policy::ServerPolicy;
+use crate::config::ProjectConfig;
+
+/// Rate limit settings, stored as `rate-limit` in `.verilib/config.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct RateLimitConfig {
+    /// Sustained requests per second; must be positive.
+    #[serde(default = "default_rps", deserialize_with = "positive_rps")]
+    pub rps: f64,
+    /// Requests that may be sent back-to-back before throttling starts.
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+    /// Retries after a 429 response before giving up.
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+}
+
+fn default_rps() -> f64 {
+    5.0
+}
+
+fn positive_rps<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let rps = f64::deserialize(deserializer)?;
+    if rps.is_finite() && rps > 0.0 {
+        Ok(rps)
+    } else {
+        Err(serde::de::Error::custom(format!(
+            "rate-limit rps must be a positive number, got {}",
+            rps
+        )))
+    }
+}
+
+fn default_burst() -> u32 {
+    10
+}
+
+fn default_max_retries() -> u32 {
+    5
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            rps: default_rps(),
+            burst: default_burst(),
+            max_retries: default_max_retries(),
+        }
+    }
+}
+
+/// Longest wait before retrying a 429, whatever `Retry-After` asks for.
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(60);
+
+/// Process-wide bucket so that every client queues behind the same limit.
+static BUCKET: OnceLock<Arc<Mutex<TokenBucket>>> = OnceLock::new();
+
+/// Token bucket state. Kept separate from the async limiter so the refill
+/// arithmetic can be tested without a runtime.
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    fn new(burst: u32, now: Instant) -> Self {
+        Self {
+            tokens: burst.max(1) as f64,
+            last: now,
+        }
+    }
+
+    /// Take one token, returning how long the caller must wait first. A
+    /// rate that is not a positive number never throttles.
+    fn take(&mut self, now: Instant, rps: f64, burst: u32) -> Duration {
+        if !(rps.is_finite() && rps > 0.0) {
+            return Duration::ZERO;
+        }
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rps).min(burst.max(1) as f64);
+        self.last = now;
+        self.tokens -= 1.0;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / rps)
+        }
+    }
+}
+
+/// API client that rate limits and retries requests.
+#[derive(Clone)]
+pub struct ApiClient {
+    client: Client,
+    api_key: String,
+    config: RateLimitConfig,
+    policy: ServerPolicy,
+    bucket: Arc<Mutex<TokenBucket>>,
+}
+
+impl ApiClient {
+    pub fn new(api_key: &str, config: RateLimitConfig, policy: ServerPolicy) -> Result<Self> {
+        let bucket = BUCKET
+            .get_or_init(|| Arc::new(Mutex::new(TokenBucket::new(config.burst, Instant::now()))))
+            .clone();
+        let redirects = policy.clone();
+        let mut builder = Client::builder().redirect(redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= 10 {
+                attempt.error("too many redirects")
+            } else if let Err(e) = redirects.check_url(attempt.url()) {
+                attempt.error(format!("{:#}", e))
+            } else {
+                attempt.follow()
+            }
+        }));
+        if let Some(tls) = policy.tls_config()? {
+            builder = builder.use_preconfigured_tls(tls);
+        }
+        Ok(Self {
+            client: builder.build().context("Failed to create HTTP client")?,
+            api_key: api_key.to_string(),
+            config,
+            policy,
+            bucket,
+        })
+    }
+
+    /// Build a client using the rate limit settings and server policy of the
+    /// project in the current directory, falling back to defaults.
+    pub fn for_project(api_key: &str) -> Result<Self> {
+        let config = ProjectConfig::load(Path::new("."))?;
+        Self::new(
+            api_key,
+            config.rate_limit.unwrap_or_default(),
+            config.server_policy.unwrap_or_default(),
+        )
+    }
+
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.authorize(self.client.get(url))
+    }
+
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.authorize(self.client.post(url))
+    }
+
+    fn authorize(&self, builder: RequestBuilder) -> RequestBuilder {
+        builder.header("Authorization", format!("ApiKey {}", self.api_key))
+    }
+
+    async fn throttle(&self) {
+        let wait = {
+            let mut bucket = self.bucket.lock().await;
+            bucket.take(Instant::now(), self.config.rps, self.config.burst)
+        };
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+    }
+
+    /// Send a request, waiting for the rate limiter and retrying 429
+    /// responses using `Retry-After` or exponential backoff, waiting at most
+    /// [`MAX_RETRY_WAIT`] each time. A request to a
+    /// host the server policy doesn't allow fails before anything is sent.
+    pub async fn send(&self, builder: RequestBuilder) -> Result<Response> {
+        self.send_with(|| {
+            builder
+                .try_clone()
+                .context("Request body cannot be retried")
+        })
+        .await
+    }
+
+    /// [`send`](Self::send) with a request built anew for every attempt,
+    /// for bodies that are streamed and cannot be cloned.
+    pub async fn send_with<F>(&self, build: F) -> Result<Response>
+    where
+        F: Fn() -> Result<RequestBuilder>,
+    {
+        let mut attempt = 0;
+        loop {
+            let request = build()?.build()?;
+            self.policy.check_url(request.url())?;
+
+            self.throttle().await;
+            let response = self.client.execute(request).await?;
+
+            if response.status() != StatusCode::TOO_MANY_REQUESTS
+                || attempt >= self.config.max_retries
+            {
+                return Ok(response);
+            }
+
+            let backoff = match ApiError::from_response(response).await {
+                ApiError::RateLimited {
+                    retry_after: Some(d),
+                    ..
+                } => d,
+                _ => Duration::from_secs(1 << attempt.min(6)),
+            }
+            .min(MAX_RETRY_WAIT);
+            attempt += 1;
+            eprintln!(
+                "Rate limited by server, retrying in {}s (attempt {}/{})...",
+                backoff.as_secs(),
+                attempt,
+                self.config.max_retries
+            );
+            sleep(backoff).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_allows_burst_then_throttles() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2, start);
+
+        assert_eq!(bucket.take(start, 4.0, 2), Duration::ZERO);
+        assert_eq!(bucket.take(start, 4.0, 2), Duration::ZERO);
+        assert_eq!(bucket.take(start, 4.0, 2), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_zero_rps_is_rejected_and_never_panics() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(1, start);
+        for rps in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(bucket.take(start, rps, 1), Duration::ZERO);
+        }
+
+        assert!(serde_json::from_str::<RateLimitConfig>(r#"{"rps": 0}"#).is_err());
+        assert!(serde_json::from_str::<RateLimitConfig>(r#"{"rps": -2.5}"#).is_err());
+        assert_eq!(
+            serde_json::from_str::<RateLimitConfig>(r#"{"rps": 0.5}"#)
+                .unwrap()
+                .rps,
+            0.5
+        );
+    }
+
+    #[test]
+    fn test_bucket_refills_up_to_burst() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2, start);
+        bucket.take(start, 1.0, 2);
+        bucket.take(start, 1.0, 2);
+
+        let later = start + Duration::from_secs(60);
+        assert_eq!(bucket.take(later, 1.0, 2), Duration::ZERO);
+        assert_eq!(bucket.take(later, 1.0, 2), Duration::ZERO);
+        assert!(bucket.take(later, 1.0, 2) > Duration::ZERO);
+    }
+}
@@ -1,5 +1,12 @@
 mod client;
+mod content;
 mod error;
+mod http;
+mod policy;
 mod types;
 
-pub use error::handle_api_error;
+pub use client::{decrypt_tree, download_repo, fetch_atomization_status, wait_for_atomization};
+pub use error::{api_error, api_error_from_parts, handle_api_error, ApiError, ApiOperation};
+pub use http::{ApiClient, RateLimitConfig};
+pub use policy::ServerPolicy;
+pub use types::{Layout, TreeNode};
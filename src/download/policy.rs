@@ -0,0 +1,282 @@
+//! Server host allowlist and TLS key pinning.
+//!
+//! With `server-policy` in `.verilib/config.json`, the API client refuses to
+//! send a request (and with it the API key) to a host outside
+//! `allowed-hosts`, and accepts a TLS connection only when the server's
+//! certificate chain contains a public key listed in `pinned-keys`. Pins use
+//! curl's `--pinnedpubkey` format: `sha256//` followed by the base64 SHA-256
+//! of the DER-encoded SubjectPublicKeyInfo.
+
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use reqwest::Url;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::{ring, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, SubjectPublicKeyInfoDer, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use webpki::EndEntityCert;
+
+use crate::exit::{fail, ExitContext, ExitKind};
+
+/// Where API requests may go, stored as `server-policy` in
+/// `.verilib/config.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct ServerPolicy {
+    /// Hosts requests may be sent to, e.g. `verilib.example.com`. A leading
+    /// `*.` also matches every subdomain. Any host is allowed when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_hosts: Vec<String>,
+    /// `sha256//<base64>` hashes of public keys, one of which the server's
+    /// certificate chain must contain. No pinning when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pinned_keys: Vec<String>,
+}
+
+impl ServerPolicy {
+    /// Fail unless a request to `url` is allowed.
+    pub fn check_url(&self, url: &Url) -> Result<()> {
+        let host = url.host_str().unwrap_or_default();
+        if !self.allowed_hosts.is_empty()
+            && !self
+                .allowed_hosts
+                .iter()
+                .any(|pattern| host_matches(pattern, host))
+        {
+            fail!(
+                Network,
+                "Refusing to send a request to '{}': it is not in allowed-hosts ({}) in .verilib/config.json. \
+                 Check --base-url and VERILIB_BASE_URL.",
+                host,
+                self.allowed_hosts.join(", ")
+            );
+        }
+        if !self.pinned_keys.is_empty() && url.scheme() != "https" {
+            fail!(
+                Network,
+                "Refusing to send a request to '{}' over {}: pinned-keys in .verilib/config.json requires https.",
+                host,
+                url.scheme()
+            );
+        }
+        Ok(())
+    }
+
+    /// The TLS configuration that enforces `pinned-keys`, if there are any.
+    pub fn tls_config(&self) -> Result<Option<ClientConfig>> {
+        if self.pinned_keys.is_empty() {
+            return Ok(None);
+        }
+        let pins = self
+            .pinned_keys
+            .iter()
+            .map(|pin| parse_pin(pin))
+            .collect::<Result<Vec<_>>>()
+            .exit_kind(ExitKind::MissingPrerequisite)?;
+
+        let provider = Arc::new(ring::default_provider());
+        let roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let inner = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+            .build()
+            .context("Failed to set up certificate verification")?;
+        let config = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .context("Failed to set up TLS")?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(PinnedVerifier {
+                inner,
+                provider,
+                pins,
+            }))
+            .with_no_client_auth();
+        Ok(Some(config))
+    }
+}
+
+/// `host` is `pattern`, or a subdomain of it when `pattern` starts with `*.`.
+fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim().to_ascii_lowercase();
+    let host = host.to_ascii_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.')),
+        None => host == pattern,
+    }
+}
+
+fn parse_pin(pin: &str) -> Result<[u8; 32]> {
+    let Some(encoded) = pin.trim().strip_prefix("sha256//") else {
+        bail!(
+            "Invalid pinned-keys entry '{}': expected sha256//<base64>",
+            pin
+        );
+    };
+    base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .ok()
+        .and_then(|hash| hash.try_into().ok())
+        .with_context(|| {
+            format!(
+                "Invalid pinned-keys entry '{}': not a base64 SHA-256 hash",
+                pin
+            )
+        })
+}
+
+/// Verifies the chain as usual, then requires a pinned key in it.
+#[derive(Debug)]
+struct PinnedVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    provider: Arc<CryptoProvider>,
+    pins: Vec<[u8; 32]>,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+        let pinned = std::iter::once(end_entity)
+            .chain(intermediates)
+            .filter_map(subject_public_key_info)
+            .any(|spki| self.pins.contains(&Sha256::digest(spki.as_ref()).into()));
+        if pinned {
+            Ok(verified)
+        } else {
+            Err(rustls::Error::General(
+                "the server's certificate matches none of pinned-keys in .verilib/config.json"
+                    .to_string(),
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// The DER-encoded SubjectPublicKeyInfo of an X.509 certificate, or `None`
+/// if webpki cannot parse it.
+fn subject_public_key_info(cert: &CertificateDer<'_>) -> Option<SubjectPublicKeyInfoDer<'static>> {
+    EndEntityCert::try_from(cert)
+        .ok()
+        .map(|cert| cert.subject_public_key_info())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowed_hosts_and_wildcards() {
+        let policy = ServerPolicy {
+            allowed_hosts: vec!["verilib.org".to_string(), "*.corp.example".to_string()],
+            ..Default::default()
+        };
+        let check = |url: &str| policy.check_url(&Url::parse(url).unwrap());
+        assert!(check("https://verilib.org/v2/repo").is_ok());
+        assert!(check("https://VERILIB.org:8443/").is_ok());
+        assert!(check("https://api.corp.example/").is_ok());
+        assert!(check("https://corp.example/").is_err());
+        assert!(check("https://evilcorp.example/").is_err());
+
+        let error = check("https://verilib.org.evil.test/").unwrap_err();
+        assert_eq!(crate::exit::code(&error), 4);
+        assert!(
+            format!("{:#}", error).contains("not in allowed-hosts (verilib.org, *.corp.example)")
+        );
+    }
+
+    #[test]
+    fn test_pins_require_https_and_valid_hashes() {
+        let pin = format!(
+            "sha256//{}",
+            base64::engine::general_purpose::STANDARD.encode([7u8; 32])
+        );
+        assert_eq!(parse_pin(&pin).unwrap(), [7u8; 32]);
+        assert!(parse_pin("sha256//c2hvcnQ=").is_err());
+        assert!(parse_pin("md5//AAAA").is_err());
+
+        let policy = ServerPolicy {
+            pinned_keys: vec![pin],
+            ..Default::default()
+        };
+        assert!(policy
+            .check_url(&Url::parse("http://localhost:8000/").unwrap())
+            .is_err());
+        assert!(policy.tls_config().unwrap().is_some());
+        assert!(ServerPolicy::default().tls_config().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_subject_public_key_info_matches_curl_pin() {
+        // Self-signed P-256 certificate; the pin is what
+        // `openssl x509 -pubkey | openssl pkey -pubin -outform der | sha256` gives.
+        let cert = base64::engine::general_purpose::STANDARD
+            .decode(
+                "MIIBhjCCASugAwIBAgIUfa4IVH4LZJ/8vCBXN+OjKKOREoowCgYIKoZIzj0EAwIwFzEVMBMGA1UEAwwM\
+                 dmVyaWxpYi50ZXN0MCAXDTI2MTAxNzAyMzE0N1oYDzIxMjYwOTIzMDIzMTQ3WjAXMRUwEwYDVQQDDAx2\
+                 ZXJpbGliLnRlc3QwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAARi7dbklDpSO4g4pyvoXDVYblCp50V2\
+                 UMzb1NnuMjBU73Bo7Y39svb/0h0sJNdb4bjuT/QS1f9QiziBYdjZvVHNo1MwUTAdBgNVHQ4EFgQUh8cO\
+                 mW78M+MDSN+6RN4QwLxClR4wHwYDVR0jBBgwFoAUh8cOmW78M+MDSN+6RN4QwLxClR4wDwYDVR0TAQH/\
+                 BAUwAwEB/zAKBggqhkjOPQQDAgNJADBGAiEA5TZlw75d6kd51JCJDLlKMe5KFGKT591P4GffmD/OtXsC\
+                 IQCc1rtrgDYlNS6ijC0PLbBZzGzHxl+I+bOQaJH6fuyrvA==",
+            )
+            .unwrap();
+        let pin = parse_pin("sha256//gXrpTF/ytOp+xAfbJZ0JSg1iOu4P2bkFE/mYXZn+BVc=").unwrap();
+
+        let spki = subject_public_key_info(&CertificateDer::from(cert.as_slice())).unwrap();
+        assert_eq!(spki.as_ref()[0], 0x30);
+        assert_eq!(<[u8; 32]>::from(Sha256::digest(spki.as_ref())), pin);
+
+        let truncated = CertificateDer::from(&cert[..40]);
+        assert!(subject_public_key_info(&truncated).is_none());
+    }
+}
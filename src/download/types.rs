@@ -1,7 +1,11 @@
 #![allow(dead_code)] // WIP: not yet wired into CLI — see https://github.com/Beneficial-AI-Foundation/verilib-cli/issues/36
 
+use anyhow::{Context, Result};
 use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
+
+use crate::structure::ReviewStatus;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TreeNode {
@@ -22,6 +26,8 @@ pub struct TreeNode {
     pub code_name: String,
     #[serde(default)]
     pub disabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review_status: Option<ReviewStatus>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -36,23 +42,63 @@ pub struct DownloadResponse {
     pub data: DownloadData,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// A child's position in its folder's graph. Coordinates are absent until
+/// the server has placed the node.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct LayoutNode {
     pub identifier: String,
     #[serde(default)]
     pub id: Option<String>,
-    pub fx: f64,
-    pub fy: f64,
+    #[serde(default)]
+    pub fx: Option<f64>,
+    #[serde(default)]
+    pub fy: Option<f64>,
     pub path: String,
+    /// Fields this version doesn't know, kept so layouts round-trip.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+impl LayoutNode {
+    pub fn has_coordinates(&self) -> bool {
+        self.fx.is_some_and(f64::is_finite) && self.fy.is_some_and(f64::is_finite)
+    }
+}
+
+/// The contents of a folder's `layout.verilib`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Layout {
     pub nodes: Vec<LayoutNode>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub zoom: Option<serde_json::Value>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub repositioned: Option<bool>,
+    /// Fields this version doesn't know, kept so layouts round-trip.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Layout {
+    pub const FILE: &'static str = "layout.verilib";
+
+    /// Read the layout of `folder`, if it has one.
+    pub fn read(folder: &Path) -> Result<Option<Layout>> {
+        let path = folder.join(Self::FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let layout = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid layout in {}", path.display()))?;
+        Ok(Some(layout))
+    }
+
+    /// Write the whole layout, zoom and unknown fields included, to `folder`.
+    pub fn write(&self, folder: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)? + "\n";
+        crate::interrupt::write_atomic(&folder.join(Self::FILE), content)
+    }
 }
 
 fn deserialize_layouts<'de, D>(deserializer: D) -> Result<HashMap<String, Layout>, D::Error>
@@ -88,9 +134,27 @@ pub struct DownloadData {
 pub struct RepoInfo {
     #[allow(dead_code)]
     pub id: String,
+    /// When the server tree last changed, if the server reports it.
+    #[serde(default)]
+    pub updated_at: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct AtomizationStatusResponse {
     pub status_id: String,
 }
+
+impl AtomizationStatusResponse {
+    pub fn is_complete(&self) -> bool {
+        self.status_id == "2"
+    }
+
+    /// Human-readable status used when reporting transitions.
+    pub fn description(&self) -> String {
+        if self.is_complete() {
+            "complete".to_string()
+        } else {
+            format!("in progress (status {})", self.status_id)
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Client-side encryption of atom contents.
+//!
+//! When `encryption-recipient` is set in `.verilib/config.json`, deploy
+//! encrypts atom contents and snippet texts to that age (X25519) public key.
+//! Identifiers and metadata are sent in the clear. The matching identity is
+//! stored in the user's home directory and used to decrypt on pull.
+
+use crate::exit::{ExitContext, ExitKind};
+use age::secrecy::ExposeSecret;
+use age::x25519::{Identity, Recipient};
+use anyhow::{Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+const IDENTITY_FILE_NAME: &str = ".verilib_identity";
+
+/// First line of an ASCII-armored age payload.
+pub const ARMOR_HEADER: &str = "-----BEGIN AGE ENCRYPTED FILE-----";
+
+/// Location of the local identity (private key) file.
+pub fn identity_path() -> Result<PathBuf> {
+    let home_dir = dirs::home_dir().context("Failed to get home directory")?;
+    Ok(home_dir.join(IDENTITY_FILE_NAME))
+}
+
+/// Generate a new identity, write it to the identity file with owner-only
+/// permissions, and return the public recipient string.
+pub fn generate_identity(force: bool) -> Result<(PathBuf, String)> {
+    let path = identity_path()?;
+    let recipient = write_identity(&path, force)?;
+    Ok((path, recipient))
+}
+
+/// Write a new identity to `path`. On unix the file is created owner-only,
+/// so the secret key is never readable by others, even briefly; an existing
+/// file is only replaced with `force`.
+fn write_identity(path: &Path, force: bool) -> Result<String> {
+    let identity = Identity::generate();
+    let recipient = identity.to_public().to_string();
+    let content = format!(
+        "# public key: {}\n{}\n",
+        recipient,
+        identity.to_string().expose_secret()
+    );
+
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path).map_err(|e| {
+        if e.kind() == io::ErrorKind::AlreadyExists {
+            anyhow::anyhow!(
+                "Identity file already exists at {}. Use --force to overwrite it.",
+                path.display()
+            )
+        } else {
+            anyhow::Error::new(e).context("Failed to create identity file")
+        }
+    })?;
+
+    // `mode` only applies to new files; a replaced one keeps its old mode.
+    #[cfg(unix)]
+    file.set_permissions(fs::Permissions::from_mode(0o600))
+        .context("Failed to set identity file permissions")?;
+
+    file.write_all(content.as_bytes())
+        .context("Failed to write identity file")?;
+    Ok(recipient)
+}
+
+/// Load the identity from the identity file, skipping comment lines.
+pub fn load_identity() -> Result<Identity> {
+    let path = identity_path()?;
+    let content = fs::read_to_string(&path)
+        .with_context(|| {
+            format!(
+                "No identity found at {}. Run 'verilib-cli keys generate' first.",
+                path.display()
+            )
+        })
+        .exit_kind(ExitKind::MissingPrerequisite)?;
+
+    let line = content
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'))
+        .ok_or_else(|| anyhow::anyhow!("Identity file {} is empty", path.display()))?;
+
+    Identity::from_str(line)
+        .map_err(|e| anyhow::anyhow!("Invalid identity in {}: {}", path.display(), e))
+}
+
+/// Parse an `age1...` public key.
+pub fn parse_recipient(recipient: &str) -> Result<Recipient> {
+    Recipient::from_str(recipient.trim())
+        .map_err(|e| anyhow::anyhow!("Invalid recipient '{}': {}", recipient, e))
+}
+
+pub fn is_encrypted(content: &str) -> bool {
+    content.trim_start().starts_with(ARMOR_HEADER)
+}
+
+/// Encrypt text to the recipient as an ASCII-armored age payload.
+pub fn encrypt_content(recipient: &Recipient, plaintext: &str) -> Result<String> {
+    age::encrypt_and_armor(recipient, plaintext.as_bytes()).context("Failed to encrypt content")
+}
+
+/// Decrypt an armored payload. Content that is not encrypted is returned
+/// unchanged so that mixed trees can be pulled.
+pub fn decrypt_content(identity: &Identity, content: &str) -> Result<String> {
+    if !is_encrypted(content) {
+        return Ok(content.to_string());
+    }
+
+    let plaintext =
+        age::decrypt(identity, content.as_bytes()).context("Failed to decrypt content")?;
+    String::from_utf8(plaintext).context("Decrypted content is not valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let identity = Identity::generate();
+        let recipient = identity.to_public();
+
+        let encrypted = encrypt_content(&recipient, "fn secret() {}").unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("secret"));
+
+        let decrypted = decrypt_content(&identity, &encrypted).unwrap();
+        assert_eq!(decrypted, "fn secret() {}");
+    }
+
+    #[test]
+    fn test_decrypt_passes_through_plaintext() {
+        let identity = Identity::generate();
+        assert_eq!(
+            decrypt_content(&identity, "fn open() {}").unwrap(),
+            "fn open() {}"
+        );
+    }
+
+    #[test]
+    fn test_parse_recipient_rejects_garbage() {
+        assert!(parse_recipient("not-a-key").is_err());
+        let valid = Identity::generate().to_public().to_string();
+        assert!(parse_recipient(&valid).is_ok());
+    }
+
+    #[test]
+    fn test_identity_file_is_private_and_not_overwritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(IDENTITY_FILE_NAME);
+
+        let recipient = write_identity(&path, false).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains(&recipient));
+        assert!(write_identity(&path, false).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+
+        #[cfg(unix)]
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        let replaced = write_identity(&path, true).unwrap();
+        assert_ne!(replaced, recipient);
+        #[cfg(unix)]
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+    }
+}
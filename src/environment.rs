@@ -0,0 +1,226 @@
+//! Capture of the toolchain and platform a verification ran under.
+//!
+//! `verify --capture-env` writes a baseline; `verify --check-env` compares the
+//! current environment against one, so verification results that differ
+//! between machines can be traced to a tool version or setting.
+
+use crate::executor::{CommandConfig, ExecutionMode};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+
+/// Tools whose `--version` is recorded in local mode.
+const TOOLS: &[&str] = &["rustc", "cargo", "verus", "probe-verus"];
+
+/// Environment variables that change how Rust code is built or verified.
+const ENV_VARS: &[&str] = &[
+    "RUSTFLAGS",
+    "RUSTDOCFLAGS",
+    "RUSTC_WRAPPER",
+    "RUSTUP_TOOLCHAIN",
+    "RUST_MIN_STACK",
+    "CARGO_BUILD_TARGET",
+    "CARGO_ENCODED_RUSTFLAGS",
+];
+
+/// Prefixes of further variables recorded in full.
+const ENV_PREFIXES: &[&str] = &["VERUS_", "PROBE_VERUS_"];
+
+/// A captured environment (`env.json`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Environment {
+    pub captured_at: DateTime<Utc>,
+    pub verilib_cli: String,
+    pub os: String,
+    pub arch: String,
+    pub execution_mode: ExecutionMode,
+    /// First line of each tool's `--version`, `null` when it is not installed.
+    /// Empty in Docker mode, where the image digest pins the tools.
+    #[serde(default)]
+    pub tools: BTreeMap<String, Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docker_image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docker_digest: Option<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+/// A field whose value differs from the baseline; `None` means absent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub field: String,
+    pub baseline: Option<String>,
+    pub current: Option<String>,
+}
+
+impl Environment {
+    /// Record the environment probe-verus runs in under `config`.
+    pub fn capture(config: &CommandConfig) -> Self {
+        let docker = config.execution_mode == ExecutionMode::Docker;
+        let tools = if docker {
+            BTreeMap::new()
+        } else {
+            TOOLS
+                .iter()
+                .map(|tool| (tool.to_string(), tool_version(tool)))
+                .collect()
+        };
+        let env = std::env::vars()
+            .filter(|(name, _)| {
+                ENV_VARS.contains(&name.as_str())
+                    || ENV_PREFIXES.iter().any(|p| name.starts_with(p))
+            })
+            .collect();
+
+        Environment {
+            captured_at: Utc::now(),
+            verilib_cli: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            execution_mode: config.execution_mode.clone(),
+            tools,
+            docker_image: docker.then(|| config.docker_image.clone()),
+            docker_digest: docker.then(|| image_digest(&config.docker_image)).flatten(),
+            env,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Fields that differ from `baseline`, sorted by name. The capture time is ignored.
+    pub fn diverges_from(&self, baseline: &Environment) -> Vec<Divergence> {
+        let (baseline, current) = (baseline.fields(), self.fields());
+        let mut names: Vec<&String> = baseline.keys().chain(current.keys()).collect();
+        names.sort();
+        names.dedup();
+
+        names
+            .into_iter()
+            .filter(|name| baseline.get(*name) != current.get(*name))
+            .map(|name| Divergence {
+                field: name.clone(),
+                baseline: baseline.get(name).cloned(),
+                current: current.get(name).cloned(),
+            })
+            .collect()
+    }
+
+    /// Flat `field -> value` view used for comparison.
+    fn fields(&self) -> BTreeMap<String, String> {
+        let mut fields = BTreeMap::from([
+            ("verilib-cli".to_string(), self.verilib_cli.clone()),
+            ("os".to_string(), self.os.clone()),
+            ("arch".to_string(), self.arch.clone()),
+            (
+                "execution-mode".to_string(),
+                serde_json::to_value(&self.execution_mode)
+                    .ok()
+                    .and_then(|v| v.as_str().map(str::to_string))
+                    .unwrap_or_default(),
+            ),
+        ]);
+        for (tool, version) in &self.tools {
+            if let Some(version) = version {
+                fields.insert(format!("tools.{}", tool), version.clone());
+            }
+        }
+        if let Some(image) = &self.docker_image {
+            fields.insert("docker-image".to_string(), image.clone());
+        }
+        if let Some(digest) = &self.docker_digest {
+            fields.insert("docker-digest".to_string(), digest.clone());
+        }
+        for (name, value) in &self.env {
+            fields.insert(format!("env.{}", name), value.clone());
+        }
+        fields
+    }
+}
+
+/// First line of `<tool> --version`, or `None` if the tool can't be run.
+fn tool_version(tool: &str) -> Option<String> {
+    let output = Command::new(tool).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.lines().next().map(|line| line.trim().to_string())
+}
+
+/// Content digest of a local Docker image.
+fn image_digest(image: &str) -> Option<String> {
+    let output = Command::new("docker")
+        .args(["image", "inspect", "--format", "{{.Id}}", image])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn environment() -> Environment {
+        Environment {
+            captured_at: Utc::now(),
+            verilib_cli: "0.2.1".to_string(),
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            execution_mode: ExecutionMode::Local,
+            tools: BTreeMap::from([
+                ("rustc".to_string(), Some("rustc 1.80.0".to_string())),
+                ("verus".to_string(), None),
+            ]),
+            docker_image: None,
+            docker_digest: None,
+            env: BTreeMap::from([("RUSTFLAGS".to_string(), "-Dwarnings".to_string())]),
+        }
+    }
+
+    #[test]
+    fn test_divergences_ignore_capture_time() {
+        let baseline = environment();
+        let mut current = environment();
+        current.captured_at = baseline.captured_at + chrono::Duration::hours(1);
+        assert!(current.diverges_from(&baseline).is_empty());
+
+        current
+            .tools
+            .insert("rustc".to_string(), Some("rustc 1.81.0".to_string()));
+        current
+            .tools
+            .insert("verus".to_string(), Some("verus 0.1".to_string()));
+        current.env.clear();
+        let divergence = |field: &str, baseline: Option<&str>, current: Option<&str>| Divergence {
+            field: field.to_string(),
+            baseline: baseline.map(str::to_string),
+            current: current.map(str::to_string),
+        };
+        assert_eq!(
+            current.diverges_from(&baseline),
+            vec![
+                divergence("env.RUSTFLAGS", Some("-Dwarnings"), None),
+                divergence("tools.rustc", Some("rustc 1.80.0"), Some("rustc 1.81.0")),
+                divergence("tools.verus", None, Some("verus 0.1")),
+            ]
+        );
+    }
+}
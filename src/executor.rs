@@ -1,14 +1,28 @@
 use crate::constants::{
     DEFAULT_DOCKER_IMAGE, PROBE_VERUS_MIN_VERSION, PROBE_VERUS_TESTED_MAX_VERSION,
 };
+use crate::exit::fail;
+use crate::interrupt;
+use crate::metrics;
+use crate::output;
 use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
-use std::process::{Command, Output};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::time::{Duration, Instant};
 
 pub const PROBE_REPO_URL: &str = "https://github.com/Beneficial-AI-Foundation/probe-verus";
 
+/// Per-invocation tool logs live here, relative to the working directory's `.verilib`.
+const TOOL_LOG_DIR: &str = "logs";
+
+/// Number of tool logs kept; older ones are deleted after each invocation.
+const TOOL_LOG_RETENTION: usize = 50;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ExternalTool {
     /// The `probe-verus` CLI tool.
@@ -23,7 +37,7 @@ impl ExternalTool {
     }
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ExecutionMode {
     #[default]
@@ -31,12 +45,30 @@ pub enum ExecutionMode {
     Docker,
 }
 
+/// What of the project the Docker container can write (`docker-mounts` in
+/// config.json).
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum DockerMounts {
+    /// The whole project root, read-write.
+    #[default]
+    Project,
+    /// The project root read-only; `.verilib` and the scratch directories
+    /// the probe-verus subcommand writes (`data`, `target`) read-write.
+    LeastPrivilege,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandConfig {
     #[serde(default)]
     pub execution_mode: ExecutionMode,
     #[serde(default = "default_docker_image")]
     pub docker_image: String,
+    #[serde(default)]
+    pub docker_mounts: DockerMounts,
+    /// Keep probe-verus intermediate files instead of deleting them.
+    #[serde(default)]
+    pub keep_intermediates: bool,
 }
 
 fn default_docker_image() -> String {
@@ -48,6 +80,8 @@ impl Default for CommandConfig {
         Self {
             execution_mode: ExecutionMode::Local,
             docker_image: default_docker_image(),
+            docker_mounts: DockerMounts::default(),
+            keep_intermediates: false,
         }
     }
 }
@@ -59,7 +93,7 @@ pub fn check_tool_available(tool: &ExternalTool, config: &CommandConfig) -> Resu
                 eprintln!("Error: Docker is not installed or not in PATH.");
                 eprintln!("Docker is required for execution mode 'docker'.");
                 eprintln!("Please install Docker: https://docs.docker.com/get-docker/");
-                bail!("docker not installed");
+                fail!(MissingPrerequisite, "docker not installed");
             }
         }
         ExecutionMode::Local => match tool {
@@ -75,7 +109,7 @@ pub fn check_tool_available(tool: &ExternalTool, config: &CommandConfig) -> Resu
                     eprintln!("  git clone {}", PROBE_REPO_URL);
                     eprintln!("  cd probe-verus");
                     eprintln!("  cargo install --path .");
-                    bail!("probe-verus not installed");
+                    fail!(MissingPrerequisite, "probe-verus not installed");
                 }
                 check_probe_verus_version()?;
             }
@@ -84,7 +118,8 @@ pub fn check_tool_available(tool: &ExternalTool, config: &CommandConfig) -> Resu
     Ok(())
 }
 
-fn check_probe_verus_version() -> Result<()> {
+/// The version `probe-verus --version` reports.
+pub fn probe_verus_version() -> Result<Version> {
     let output = Command::new("probe-verus")
         .arg("--version")
         .output()
@@ -94,15 +129,18 @@ fn check_probe_verus_version() -> Result<()> {
     let stderr = String::from_utf8_lossy(&output.stderr);
     let raw = format!("{}{}", stdout, stderr);
 
-    let version = raw
-        .split_whitespace()
+    raw.split_whitespace()
         .find_map(|token| Version::parse(token).ok())
         .ok_or_else(|| {
             anyhow::anyhow!(
                 "Could not parse a semver version from 'probe-verus --version' output: {}",
                 raw.trim()
             )
-        })?;
+        })
+}
+
+fn check_probe_verus_version() -> Result<()> {
+    let version = probe_verus_version()?;
 
     let min_req = VersionReq::parse(PROBE_VERUS_MIN_VERSION)
         .expect("PROBE_VERUS_MIN_VERSION is a valid semver requirement");
@@ -121,7 +159,8 @@ fn check_probe_verus_version() -> Result<()> {
         eprintln!("  git clone {}", PROBE_REPO_URL);
         eprintln!("  cd probe-verus");
         eprintln!("  cargo install --path .");
-        bail!(
+        fail!(
+            MissingPrerequisite,
             "probe-verus {} is below the minimum required version ({})",
             version,
             PROBE_VERUS_MIN_VERSION
@@ -129,10 +168,10 @@ fn check_probe_verus_version() -> Result<()> {
     }
 
     if !tested_max_req.matches(&version) {
-        eprintln!(
-            "Warning: probe-verus {} has not been tested with this version of verilib-cli (tested up to {}).",
+        output::warn(format!(
+            "probe-verus {} has not been tested with this version of verilib-cli (tested up to {}).",
             version, PROBE_VERUS_TESTED_MAX_VERSION
-        );
+        ));
         eprintln!("  It may work, but you could encounter unexpected behaviour.");
         eprintln!(
             "  Consider filing an issue at {} if you hit problems.",
@@ -143,18 +182,141 @@ fn check_probe_verus_version() -> Result<()> {
     Ok(())
 }
 
+/// Output of an external tool, plus where its full log was saved.
+#[derive(Debug)]
+pub struct ToolOutput {
+    pub output: Output,
+    /// `.verilib/logs/<timestamp>-<tool>.log`, when the working directory has a `.verilib`.
+    pub log_path: Option<PathBuf>,
+}
+
+impl ToolOutput {
+    /// ` (full log: <path>)`, for appending to error messages; empty without a log.
+    pub fn log_hint(&self) -> String {
+        self.log_path
+            .as_ref()
+            .map(|p| format!(" (full log: {})", p.display()))
+            .unwrap_or_default()
+    }
+}
+
+impl Deref for ToolOutput {
+    type Target = Output;
+
+    fn deref(&self) -> &Output {
+        &self.output
+    }
+}
+
 pub fn run_command(
     tool: &ExternalTool,
     args: &[&str],
     cwd: Option<&Path>,
     config: &CommandConfig,
-) -> Result<Output> {
+) -> Result<ToolOutput> {
     check_tool_available(tool, config)?;
     let program = tool.binary_name();
-    match config.execution_mode {
+    let started = Utc::now();
+    let timer = Instant::now();
+    let output = match config.execution_mode {
         ExecutionMode::Local => run_local(program, args, cwd),
-        ExecutionMode::Docker => run_docker(program, args, cwd, &config.docker_image),
+        ExecutionMode::Docker => run_docker(program, args, cwd, config),
+    }?;
+
+    let log_dir = cwd
+        .unwrap_or(Path::new("."))
+        .join(".verilib")
+        .join(TOOL_LOG_DIR);
+    let invocation = Invocation {
+        program,
+        args,
+        config,
+        started,
+        elapsed: timer.elapsed(),
+    };
+    let subcommand = args
+        .first()
+        .map(|arg| format!(" {}", arg))
+        .unwrap_or_default();
+    metrics::subprocess(
+        format!("{}{}", program, subcommand),
+        invocation.elapsed,
+        output.status.code(),
+    );
+    // Logging is best effort; a read-only checkout must not fail the command.
+    let log_path = if log_dir.parent().is_some_and(Path::exists) {
+        match write_tool_log(&log_dir, &invocation, &output) {
+            Ok(path) => Some(path),
+            Err(e) => {
+                output::warn(format!("could not write {} log: {:#}", program, e));
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    Ok(ToolOutput { output, log_path })
+}
+
+/// What was run, for the log header.
+struct Invocation<'a> {
+    program: &'a str,
+    args: &'a [&'a str],
+    config: &'a CommandConfig,
+    started: DateTime<Utc>,
+    elapsed: Duration,
+}
+
+/// Write one invocation's command line, timing, and full output, then rotate old logs.
+fn write_tool_log(log_dir: &Path, invocation: &Invocation, output: &Output) -> Result<PathBuf> {
+    std::fs::create_dir_all(log_dir)
+        .with_context(|| format!("Failed to create {}", log_dir.display()))?;
+
+    let stamp = invocation.started.format("%Y%m%dT%H%M%S%3fZ");
+    let mut path = log_dir.join(format!("{}-{}.log", stamp, invocation.program));
+    let mut n = 1;
+    while path.exists() {
+        path = log_dir.join(format!("{}-{}-{}.log", stamp, invocation.program, n));
+        n += 1;
+    }
+
+    let mode = match invocation.config.execution_mode {
+        ExecutionMode::Local => "local".to_string(),
+        ExecutionMode::Docker => format!("docker ({})", invocation.config.docker_image),
+    };
+    let content = format!(
+        "command: {} {}\nmode: {}\nstarted: {}\nduration: {:.3}s\nexit status: {}\n\n--- stdout ---\n{}\n--- stderr ---\n{}\n",
+        invocation.program,
+        invocation.args.join(" "),
+        mode,
+        invocation.started.to_rfc3339(),
+        invocation.elapsed.as_secs_f64(),
+        output.status,
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr),
+    );
+    std::fs::write(&path, content)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    rotate_tool_logs(log_dir, TOOL_LOG_RETENTION)?;
+    Ok(path)
+}
+
+/// Delete all but the `keep` newest logs; names start with a timestamp, so they sort by age.
+fn rotate_tool_logs(log_dir: &Path, keep: usize) -> Result<()> {
+    let mut logs: Vec<PathBuf> = std::fs::read_dir(log_dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "log"))
+        .collect();
+    logs.sort();
+
+    let excess = logs.len().saturating_sub(keep);
+    for old in &logs[..excess] {
+        std::fs::remove_file(old).with_context(|| format!("Failed to remove {}", old.display()))?;
     }
+    Ok(())
 }
 
 fn run_local(program: &str, args: &[&str], cwd: Option<&Path>) -> Result<Output> {
@@ -165,23 +327,42 @@ fn run_local(program: &str, args: &[&str], cwd: Option<&Path>) -> Result<Output>
         cmd.current_dir(dir);
     }
 
-    let output = cmd
-        .output()
-        .context(format!("Failed to run local command: {}", program))?;
+    let child =
+        spawn_captured(&mut cmd).context(format!("Failed to run local command: {}", program))?;
+    wait_tracked(child, None)
+}
+
+/// Start `cmd` with its output captured and no stdin, as `Command::output` does.
+fn spawn_captured(cmd: &mut Command) -> std::io::Result<Child> {
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+}
+
+/// Wait for `child`, stopping it (and `container`) if Ctrl+C is pressed meanwhile.
+fn wait_tracked(child: Child, container: Option<&str>) -> Result<Output> {
+    let _tracked = interrupt::track(&child, container);
+    let output = child.wait_with_output()?;
+    if interrupt::requested() {
+        bail!("Interrupted");
+    }
     Ok(output)
 }
 
-fn ensure_image_pulled(image: &str) -> Result<()> {
-    let status = Command::new("docker")
+/// Whether Docker has `image` locally.
+pub fn image_present(image: &str) -> bool {
+    Command::new("docker")
         .args(["image", "inspect", image])
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::null())
-        .status();
+        .status()
+        .is_ok_and(|status| status.success())
+}
 
-    if let Ok(status) = status {
-        if status.success() {
-            return Ok(());
-        }
+fn ensure_image_pulled(image: &str) -> Result<()> {
+    if image_present(image) {
+        return Ok(());
     }
 
     println!("Docker image {} not found locally. Pulling...", image);
@@ -192,20 +373,55 @@ fn ensure_image_pulled(image: &str) -> Result<()> {
         .context(format!("Failed to pull docker image {}", image))?;
 
     if !status.success() {
-        anyhow::bail!("Failed to pull docker image {}", image);
+        fail!(ExternalTool, "Failed to pull docker image {}", image);
     }
 
     Ok(())
 }
 
-fn run_docker(program: &str, args: &[&str], cwd: Option<&Path>, image: &str) -> Result<Output> {
+/// Project directories, besides `.verilib`, that a probe-verus subcommand
+/// writes: SCIP indexes and verification output go to `data`, the cargo
+/// build of `verify` to `target`.
+fn scratch_dirs(args: &[&str]) -> &'static [&'static str] {
+    match args.first() {
+        Some(&"atomize") | Some(&"specify") => &["data"],
+        Some(&"verify") => &["data", "target"],
+        _ => &[],
+    }
+}
+
+/// The `-v` bind mounts of the project at `/workspace`. With
+/// [`DockerMounts::LeastPrivilege`], the writable directories are created
+/// first, since Docker would create missing ones as root.
+fn volume_args(host_cwd: &Path, policy: DockerMounts, args: &[&str]) -> Result<Vec<String>> {
+    let host = crate::paths::utf8(host_cwd)?;
+    if policy == DockerMounts::Project {
+        return Ok(vec![format!("{}:/workspace:rw", host)]);
+    }
+
+    let mut volumes = vec![format!("{}:/workspace:ro", host)];
+    for dir in std::iter::once(&".verilib").chain(scratch_dirs(args)) {
+        let path = host_cwd.join(dir);
+        std::fs::create_dir_all(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        volumes.push(format!("{}/{}:/workspace/{}:rw", host, dir, dir));
+    }
+    Ok(volumes)
+}
+
+fn run_docker(
+    program: &str,
+    args: &[&str],
+    cwd: Option<&Path>,
+    config: &CommandConfig,
+) -> Result<Output> {
+    let image = config.docker_image.as_str();
     ensure_image_pulled(image)?;
 
     let host_cwd = cwd.map(|p| p.to_path_buf()).unwrap_or_else(|| {
         std::env::current_dir().unwrap_or_else(|_| Path::new(".").to_path_buf())
     });
-
-    let host_cwd_str = host_cwd.to_string_lossy();
+    let volumes = volume_args(&host_cwd, config.docker_mounts, args)?;
 
     #[cfg(unix)]
     let user_arg = {
@@ -217,20 +433,23 @@ fn run_docker(program: &str, args: &[&str], cwd: Option<&Path>, image: &str) ->
     #[cfg(not(unix))]
     let user_arg = "1000:1000".to_string();
 
+    let container = interrupt::container_name();
     let mut docker_args = vec![
         "run",
         "--rm",
+        "--name",
+        &container,
         "--platform",
         "linux/amd64",
         "--entrypoint",
         program,
         "-u",
         &user_arg,
-        "-v",
     ];
 
-    let mount_arg = format!("{}:/workspace:rw", host_cwd_str);
-    docker_args.push(&mount_arg);
+    for volume in &volumes {
+        docker_args.extend(["-v", volume]);
+    }
 
     docker_args.extend_from_slice(&[
         "--tmpfs",
@@ -245,10 +464,67 @@ fn run_docker(program: &str, args: &[&str], cwd: Option<&Path>, image: &str) ->
 
     docker_args.extend_from_slice(args);
 
-    let output = Command::new("docker")
-        .args(&docker_args)
-        .output()
+    let child = spawn_captured(Command::new("docker").args(&docker_args))
         .context(format!("Failed to run docker command with image {}", image))?;
+    wait_tracked(child, Some(&container))
+}
 
-    Ok(output)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_least_privilege_mounts_only_writable_dirs() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().to_str().unwrap();
+
+        assert_eq!(
+            volume_args(dir.path(), DockerMounts::Project, &["verify"]).unwrap(),
+            [format!("{}:/workspace:rw", root)]
+        );
+        assert!(!dir.path().join(".verilib").exists());
+
+        assert_eq!(
+            volume_args(dir.path(), DockerMounts::LeastPrivilege, &["stubify"]).unwrap(),
+            [
+                format!("{}:/workspace:ro", root),
+                format!("{}/.verilib:/workspace/.verilib:rw", root),
+            ]
+        );
+        let volumes = volume_args(dir.path(), DockerMounts::LeastPrivilege, &["verify"]).unwrap();
+        assert_eq!(volumes.len(), 4);
+        assert_eq!(volumes[3], format!("{}/target:/workspace/target:rw", root));
+        assert!(dir.path().join("data").is_dir());
+        assert!(dir.path().join("target").is_dir());
+    }
+
+    #[test]
+    fn test_rotation_keeps_newest_logs() {
+        let dir = TempDir::new().unwrap();
+        for stamp in [
+            "20260101T000000000Z",
+            "20260102T000000000Z",
+            "20260103T000000000Z",
+        ] {
+            std::fs::write(dir.path().join(format!("{}-probe-verus.log", stamp)), "").unwrap();
+        }
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        rotate_tool_logs(dir.path(), 2).unwrap();
+
+        let mut left: Vec<String> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+        assert_eq!(
+            left,
+            vec![
+                "20260102T000000000Z-probe-verus.log",
+                "20260103T000000000Z-probe-verus.log",
+                "notes.txt"
+            ]
+        );
+    }
 }
@@ -0,0 +1,188 @@
+//! Exit codes.
+//!
+//! A failure CI may want to branch on is tagged with an [`ExitKind`] where
+//! it is raised; API and HTTP errors are recognized by type. Anything else
+//! exits 1, and an interrupted run exits
+//! [`EXIT_INTERRUPTED`](crate::interrupt::EXIT_INTERRUPTED). Each command's
+//! `--help` lists the codes it can exit with.
+
+use crate::download::ApiError;
+use clap::Command;
+use std::fmt;
+
+/// Why a command failed, for the exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitKind {
+    /// A `--check`-style command found problems, e.g. failing proofs.
+    CheckFailed,
+    /// A file, setting or tool the command needs is missing or out of date.
+    MissingPrerequisite,
+    /// The server could not be reached, or rejected the request or the API key.
+    Network,
+    /// probe-verus, Docker or another external tool failed.
+    ExternalTool,
+}
+
+impl ExitKind {
+    const ALL: [ExitKind; 4] = [
+        ExitKind::CheckFailed,
+        ExitKind::MissingPrerequisite,
+        ExitKind::Network,
+        ExitKind::ExternalTool,
+    ];
+
+    pub fn code(self) -> i32 {
+        match self {
+            ExitKind::CheckFailed => 2,
+            ExitKind::MissingPrerequisite => 3,
+            ExitKind::Network => 4,
+            ExitKind::ExternalTool => 5,
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            ExitKind::CheckFailed => "check failed, or invalid arguments",
+            ExitKind::MissingPrerequisite => "missing or stale prerequisite",
+            ExitKind::Network => "network or authentication error",
+            ExitKind::ExternalTool => "external tool failed",
+        }
+    }
+}
+
+/// An error tagged with its [`ExitKind`]. It displays as the error it wraps,
+/// context included, so tagging never changes what the user sees.
+#[derive(Debug)]
+struct Tagged {
+    kind: ExitKind,
+    error: anyhow::Error,
+}
+
+impl fmt::Display for Tagged {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#}", self.error)
+    }
+}
+
+impl std::error::Error for Tagged {}
+
+/// Tag `error` with `kind`.
+pub fn tag(kind: ExitKind, error: anyhow::Error) -> anyhow::Error {
+    anyhow::Error::new(Tagged { kind, error })
+}
+
+/// `bail!` with an [`ExitKind`]: `fail!(CheckFailed, "{} problems", n)`.
+macro_rules! fail {
+    ($kind:ident, $($arg:tt)+) => {
+        return Err($crate::exit::tag(
+            $crate::exit::ExitKind::$kind,
+            anyhow::anyhow!($($arg)+),
+        ))
+    };
+}
+pub(crate) use fail;
+
+/// Tag the error of a `Result` with an [`ExitKind`].
+pub trait ExitContext<T> {
+    fn exit_kind(self, kind: ExitKind) -> anyhow::Result<T>;
+}
+
+impl<T, E: Into<anyhow::Error>> ExitContext<T> for Result<T, E> {
+    fn exit_kind(self, kind: ExitKind) -> anyhow::Result<T> {
+        self.map_err(|e| tag(kind, e.into()))
+    }
+}
+
+/// The exit code for `error`: the outermost tag, else 4 for API and HTTP
+/// errors, else 1.
+pub fn code(error: &anyhow::Error) -> i32 {
+    for cause in error.chain() {
+        if let Some(tagged) = cause.downcast_ref::<Tagged>() {
+            return tagged.kind.code();
+        }
+        if cause.is::<ApiError>() || cause.is::<reqwest::Error>() {
+            return ExitKind::Network.code();
+        }
+    }
+    1
+}
+
+/// The codes `command` can exit with besides 0, 1 and 130.
+fn contract(command: &str) -> &'static [ExitKind] {
+    use ExitKind::*;
+    match command {
+        "atomize" | "specify" | "verify" => &[CheckFailed, MissingPrerequisite, ExternalTool],
+        "create" | "run" => &[MissingPrerequisite, ExternalTool],
+        "verify-certs" | "drift" | "doctor" | "layouts" => &[CheckFailed, MissingPrerequisite],
+        "api" => &[CheckFailed, MissingPrerequisite],
+        "init" | "deploy" | "pull" | "reclone" | "status" | "atomization-status" => {
+            &[MissingPrerequisite, Network]
+        }
+        _ => &[MissingPrerequisite],
+    }
+}
+
+/// Help text listing the exit codes of `command`, or of every command when
+/// `None`.
+fn help(command: Option<&str>) -> String {
+    let kinds: Vec<ExitKind> = match command {
+        Some(command) => contract(command).to_vec(),
+        None => ExitKind::ALL.to_vec(),
+    };
+    let mut lines = vec![
+        "Exit codes:".to_string(),
+        "  0    success".to_string(),
+        "  1    other error".to_string(),
+    ];
+    // clap exits 2 on a usage error, so every command can.
+    if !kinds.contains(&ExitKind::CheckFailed) {
+        lines.push("  2    invalid arguments".to_string());
+    }
+    for kind in kinds {
+        lines.push(format!("  {:<4} {}", kind.code(), kind.description()));
+    }
+    lines.push(format!(
+        "  {:<4} interrupted",
+        crate::interrupt::EXIT_INTERRUPTED
+    ));
+    lines.join("\n")
+}
+
+/// `cli` with each command's exit codes at the end of its `--help`.
+pub fn document(cli: Command) -> Command {
+    cli.after_long_help(help(None)).mut_subcommands(|sub| {
+        let text = help(Some(sub.get_name()));
+        sub.after_long_help(text.clone())
+            .mut_subcommands(|nested| nested.after_long_help(text.clone()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Context};
+
+    #[test]
+    fn test_code_follows_tags_and_error_types() {
+        assert_eq!(code(&anyhow!("boom")), 1);
+
+        let tagged: anyhow::Result<()> =
+            Err(anyhow!("2 functions failed")).exit_kind(ExitKind::CheckFailed);
+        let error = tagged.context("verify").unwrap_err();
+        assert_eq!(code(&error), 2);
+        assert_eq!(format!("{:#}", error), "verify: 2 functions failed");
+
+        let api = anyhow::Error::new(ApiError::Unauthorized("bad key".to_string()));
+        assert_eq!(code(&api.context("deploy failed")), 4);
+    }
+
+    #[test]
+    fn test_help_lists_command_contract() {
+        let text = help(Some("verify"));
+        assert!(text.contains("  2    check failed"));
+        assert!(text.contains("  5    external tool failed"));
+        assert!(!text.contains("network"));
+        assert!(help(None).contains("  4    network"));
+        assert!(help(Some("stats")).contains("  2    invalid arguments"));
+    }
+}
@@ -0,0 +1,262 @@
+//! Freshness of the generated artifacts.
+//!
+//! `atomize` builds stubs.json from the structure files; `specify` and
+//! `verify` build specs.json and proofs.json from atoms.json. When one of
+//! them succeeds, the fingerprint of its input is recorded in
+//! `.verilib/state.json`. Later commands compare it with the input on disk
+//! to name the stage that has to run again.
+//!
+//! A fingerprint is a stamp of paths, sizes and modification times plus a
+//! hash of the contents. The contents are only compared when the stamp
+//! differs, so a checkout that touches files without changing them leaves
+//! the artifacts fresh. Artifacts written before tracking began have no
+//! record and count as fresh.
+
+use crate::config::{PipelineStage, ProjectConfig};
+use crate::i18n::t;
+use crate::output;
+use crate::walk;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Input fingerprints per artifact, under `.verilib`.
+const STATE_FILE: &str = "state.json";
+
+/// A file a stage writes from an earlier stage's output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Artifact {
+    Stubs,
+    Specs,
+    Proofs,
+}
+
+impl Artifact {
+    pub const ALL: [Artifact; 3] = [Artifact::Stubs, Artifact::Specs, Artifact::Proofs];
+
+    pub fn file(self) -> &'static str {
+        match self {
+            Artifact::Stubs => "stubs.json",
+            Artifact::Specs => "specs.json",
+            Artifact::Proofs => "proofs.json",
+        }
+    }
+
+    /// The stage that writes it.
+    pub fn stage(self) -> PipelineStage {
+        match self {
+            Artifact::Stubs => PipelineStage::Atomize,
+            Artifact::Specs => PipelineStage::Specify,
+            Artifact::Proofs => PipelineStage::Verify,
+        }
+    }
+
+    fn input(self) -> Input {
+        match self {
+            Artifact::Stubs => Input::Structure,
+            Artifact::Specs | Artifact::Proofs => Input::Atoms,
+        }
+    }
+
+    /// "stubs.json is older than the structure files; ..." for `message`.
+    pub fn message(self, message: &str) -> String {
+        t!(
+            message,
+            artifact = self.file(),
+            input = self.input().description(),
+            stage = self.stage().name()
+        )
+    }
+}
+
+/// What an artifact is built from.
+#[derive(Debug, Clone, Copy)]
+enum Input {
+    Structure,
+    Atoms,
+}
+
+impl Input {
+    fn key(self) -> &'static str {
+        match self {
+            Input::Structure => "structure",
+            Input::Atoms => "atoms.json",
+        }
+    }
+
+    fn description(self) -> String {
+        match self {
+            Input::Structure => t!("freshness-structure"),
+            Input::Atoms => "atoms.json".to_string(),
+        }
+    }
+
+    fn files(self, config: &ProjectConfig) -> Vec<PathBuf> {
+        match self {
+            Input::Structure => match config.structure_root_path() {
+                Ok(root) if root.is_dir() => walk::verilib_files(&root)
+                    .filter(|path| path.extension().is_some_and(|e| e == "md"))
+                    .collect(),
+                _ => Vec::new(),
+            },
+            Input::Atoms => vec![config.atoms_path()],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Fingerprint {
+    /// Over paths, sizes and modification times.
+    stamp: String,
+    /// Over paths and contents.
+    sha256: String,
+}
+
+impl Fingerprint {
+    fn of(config: &ProjectConfig, input: Input) -> Fingerprint {
+        let files = input.files(config);
+        Fingerprint {
+            stamp: stamp(&config.project_root, &files),
+            sha256: content_hash(&config.project_root, &files),
+        }
+    }
+
+    /// Whether `input` still has this fingerprint, hashing its contents only
+    /// when the stamp changed.
+    fn matches(&self, config: &ProjectConfig, input: Input) -> bool {
+        let files = input.files(config);
+        self.stamp == stamp(&config.project_root, &files)
+            || self.sha256 == content_hash(&config.project_root, &files)
+    }
+}
+
+type State = BTreeMap<String, BTreeMap<String, Fingerprint>>;
+
+/// Record that `artifact` was just built from its current input.
+pub fn record(config: &ProjectConfig, artifact: Artifact) -> Result<()> {
+    let path = config.verilib_path().join(STATE_FILE);
+    let mut state = read_state(&path);
+    let input = artifact.input();
+    state.insert(
+        artifact.file().to_string(),
+        BTreeMap::from([(input.key().to_string(), Fingerprint::of(config, input))]),
+    );
+    let content = serde_json::to_string_pretty(&state)? + "\n";
+    crate::interrupt::write_atomic(&path, content)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The `artifacts` whose input changed since they were built.
+pub fn stale(config: &ProjectConfig, artifacts: &[Artifact]) -> Vec<Artifact> {
+    let state = read_state(&config.verilib_path().join(STATE_FILE));
+    artifacts
+        .iter()
+        .copied()
+        .filter(|artifact| config.verilib_path().join(artifact.file()).exists())
+        .filter(|artifact| {
+            let input = artifact.input();
+            state
+                .get(artifact.file())
+                .and_then(|inputs| inputs.get(input.key()))
+                .is_some_and(|recorded| !recorded.matches(config, input))
+        })
+        .collect()
+}
+
+/// Warn about each of `artifacts` that is stale, for commands that only
+/// read them.
+pub fn warn_stale(config: &ProjectConfig, artifacts: &[Artifact]) {
+    for artifact in stale(config, artifacts) {
+        output::warn(artifact.message("freshness-stale"));
+    }
+}
+
+/// Stamp of `files`: their paths relative to `base`, sizes and modification
+/// times. Missing files are left out.
+pub fn stamp(base: &Path, files: &[PathBuf]) -> String {
+    let mut hasher = Sha256::new();
+    for file in files {
+        let Ok(metadata) = std::fs::metadata(file) else {
+            continue;
+        };
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        let path = file.strip_prefix(base).unwrap_or(file);
+        hasher.update(format!(
+            "{}\t{}\t{}\n",
+            path.display(),
+            metadata.len(),
+            modified.as_nanos()
+        ));
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+fn content_hash(base: &Path, files: &[PathBuf]) -> String {
+    let mut hasher = Sha256::new();
+    for file in files {
+        let Ok(content) = std::fs::read(file) else {
+            continue;
+        };
+        let path = file.strip_prefix(base).unwrap_or(file);
+        hasher.update(format!("{}\t{}\n", path.display(), content.len()));
+        hasher.update(&content);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+fn read_state(path: &Path) -> State {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_stale_after_content_change_only() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().to_path_buf();
+        let structure = root.join(".verilib/structure");
+        fs::create_dir_all(&structure).unwrap();
+        fs::write(structure.join("f.md"), "---\ncode-line: 1\n---\n").unwrap();
+        fs::write(root.join(".verilib/stubs.json"), "{}").unwrap();
+        let config = ProjectConfig {
+            project_root: root.clone(),
+            structure_root: Some(".verilib/structure".to_string()),
+            ..Default::default()
+        };
+
+        // Untracked artifacts count as fresh.
+        assert!(stale(&config, &Artifact::ALL).is_empty());
+        record(&config, Artifact::Stubs).unwrap();
+        assert!(stale(&config, &Artifact::ALL).is_empty());
+
+        // Touching without changing the contents keeps it fresh.
+        let file = fs::File::options()
+            .write(true)
+            .open(structure.join("f.md"))
+            .unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        assert!(stale(&config, &Artifact::ALL).is_empty());
+
+        fs::write(structure.join("f.md"), "---\ncode-line: 2\n---\n").unwrap();
+        assert_eq!(stale(&config, &Artifact::ALL), [Artifact::Stubs]);
+        assert_eq!(
+            Artifact::Stubs.message("freshness-stale"),
+            "stubs.json is older than the structure files; run 'verilib-cli atomize' to update it."
+        );
+    }
+}
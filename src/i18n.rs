@@ -0,0 +1,206 @@
+//! Localized user-facing messages.
+//!
+//! Messages live in Fluent catalogs under `locales/` and are compiled into the
+//! binary. The locale is taken from `VERILIB_LANG`, then `locale` in
+//! `.verilib/config.json`, then the usual `LC_ALL`/`LC_MESSAGES`/`LANG`
+//! variables. Messages missing from a catalog fall back to English.
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use std::path::Path;
+use std::sync::OnceLock;
+use unic_langid::LanguageIdentifier;
+
+use crate::config::ProjectConfig;
+
+pub use fluent_bundle::FluentValue;
+
+/// Supported locales and their catalogs; the first entry is the fallback.
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("es", include_str!("../locales/es.ftl")),
+];
+
+/// Environment variable that overrides every other locale source.
+pub const LOCALE_ENV: &str = "VERILIB_LANG";
+
+struct Catalog {
+    selected: Option<FluentBundle<FluentResource>>,
+    fallback: FluentBundle<FluentResource>,
+}
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+fn bundle(lang: &str, source: &str) -> FluentBundle<FluentResource> {
+    let langid: LanguageIdentifier = lang.parse().expect("catalog language id is valid");
+    let resource = FluentResource::try_new(source.to_string())
+        .unwrap_or_else(|(_, errors)| panic!("invalid {} catalog: {:?}", lang, errors));
+    let mut bundle = FluentBundle::new_concurrent(vec![langid]);
+    // Unicode isolation marks show up as garbage in many terminals.
+    bundle.set_use_isolating(false);
+    bundle
+        .add_resource(resource)
+        .unwrap_or_else(|errors| panic!("duplicate messages in {} catalog: {:?}", lang, errors));
+    bundle
+}
+
+/// Reduce a locale string such as `es_ES.UTF-8` to a supported language code.
+fn supported_language(locale: &str) -> Option<&'static str> {
+    let lang = locale
+        .split(['_', '-', '.', '@'])
+        .next()?
+        .to_ascii_lowercase();
+    CATALOGS
+        .iter()
+        .map(|(code, _)| *code)
+        .find(|code| *code == lang)
+}
+
+/// Pick the locale from the environment and project config.
+fn detect_language() -> &'static str {
+    let configured = ProjectConfig::load(Path::new("."))
+        .ok()
+        .and_then(|c| c.locale);
+    let candidates = std::iter::once(std::env::var(LOCALE_ENV).ok())
+        .chain(std::iter::once(configured))
+        .chain(
+            ["LC_ALL", "LC_MESSAGES", "LANG"]
+                .iter()
+                .map(|var| std::env::var(var).ok()),
+        );
+    choose_language(candidates)
+}
+
+/// The first non-empty candidate decides the language, even if unsupported.
+fn choose_language(candidates: impl IntoIterator<Item = Option<String>>) -> &'static str {
+    candidates
+        .into_iter()
+        .flatten()
+        .find(|value| !value.is_empty())
+        .and_then(|locale| supported_language(&locale))
+        .unwrap_or(CATALOGS[0].0)
+}
+
+fn catalog() -> &'static Catalog {
+    CATALOG.get_or_init(|| {
+        let (fallback_lang, fallback_source) = CATALOGS[0];
+        let lang = detect_language();
+        let selected = CATALOGS
+            .iter()
+            .find(|(code, _)| *code == lang && *code != fallback_lang)
+            .map(|(code, source)| bundle(code, source));
+        Catalog {
+            selected,
+            fallback: bundle(fallback_lang, fallback_source),
+        }
+    })
+}
+
+fn format(bundle: &FluentBundle<FluentResource>, id: &str, args: &FluentArgs) -> Option<String> {
+    let pattern = bundle.get_message(id)?.value()?;
+    let mut errors = Vec::new();
+    Some(
+        bundle
+            .format_pattern(pattern, Some(args), &mut errors)
+            .into_owned(),
+    )
+}
+
+/// Look up message `id` in the active locale, formatted with `args`.
+/// Unknown ids are returned verbatim so that a missing message is visible
+/// rather than fatal.
+pub fn message(id: &str, args: &[(&str, FluentValue)]) -> String {
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args {
+        fluent_args.set(*name, value.clone());
+    }
+
+    let catalog = catalog();
+    catalog
+        .selected
+        .as_ref()
+        .and_then(|bundle| format(bundle, id, &fluent_args))
+        .or_else(|| format(&catalog.fallback, id, &fluent_args))
+        .unwrap_or_else(|| id.to_string())
+}
+
+/// Format a localized message: `t!("auth-stored")` or
+/// `t!("verify-all-passed", count = checked)`.
+macro_rules! t {
+    ($id:expr) => {
+        $crate::i18n::message($id, &[])
+    };
+    ($id:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::message(
+            $id,
+            &[$((stringify!($name), $crate::i18n::FluentValue::from($value))),+],
+        )
+    };
+}
+pub(crate) use t;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supported_language_parses_posix_locales() {
+        assert_eq!(supported_language("es_ES.UTF-8"), Some("es"));
+        assert_eq!(supported_language("EN"), Some("en"));
+        assert_eq!(supported_language("C.UTF-8"), None);
+    }
+
+    #[test]
+    fn test_choose_language_precedence() {
+        let some = |s: &str| Some(s.to_string());
+        // VERILIB_LANG, then config.json, then POSIX variables.
+        assert_eq!(
+            choose_language([some("en"), some("es"), some("es_ES")]),
+            "en"
+        );
+        assert_eq!(choose_language([None, some("es"), some("en_US")]), "es");
+        assert_eq!(choose_language([None, None, some("es_ES.UTF-8")]), "es");
+        assert_eq!(choose_language([some(""), None, some("fr_FR")]), "en");
+    }
+
+    #[test]
+    fn test_spanish_catalog_is_used_when_selected() {
+        let es = bundle("es", CATALOGS[1].1);
+        let mut args = FluentArgs::new();
+        args.set("count", 2);
+        assert_eq!(
+            format(&es, "verify-failed", &args).as_deref(),
+            Some("2 stubs fallaron la verificación. Ejecuta 'verify' para actualizar el estado.")
+        );
+    }
+
+    #[test]
+    fn test_catalogs_define_the_same_messages() {
+        let ids = |source: &str| -> Vec<String> {
+            let mut ids: Vec<String> = source
+                .lines()
+                .filter_map(|line| line.split_once(" ="))
+                .map(|(id, _)| id.trim().to_string())
+                .filter(|id| !id.starts_with('#') && !id.is_empty() && !id.starts_with(' '))
+                .collect();
+            ids.sort();
+            ids
+        };
+        let (_, english) = CATALOGS[0];
+        for (code, source) in &CATALOGS[1..] {
+            bundle(code, source);
+            assert_eq!(ids(source), ids(english), "{} catalog is out of sync", code);
+        }
+    }
+
+    #[test]
+    fn test_message_formats_arguments() {
+        let en = bundle("en", CATALOGS[0].1);
+        let mut args = FluentArgs::new();
+        args.set("count", 3);
+        assert_eq!(
+            format(&en, "verify-all-passed", &args).as_deref(),
+            Some("All 3 stubs passed verification.")
+        );
+    }
+}
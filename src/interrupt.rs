@@ -0,0 +1,202 @@
+//! Ctrl+C handling.
+//!
+//! The first Ctrl+C stops the running probe-verus process (or the docker
+//! container running it), so the command fails through its normal error path:
+//! nothing further is written, the history entry records the interruption,
+//! and the process exits with [`EXIT_INTERRUPTED`]. When no tool is running,
+//! or on a second Ctrl+C, the process exits right away. Files written with
+//! [`write_atomic`], [`write_atomic_from`] or [`write_atomic_with`] are
+//! replaced in one rename, so an interrupt leaves either the old or the new
+//! contents, never a truncated file.
+
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Exit code after an interrupt (128 + SIGINT, as shells report it).
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static RUNNING: Mutex<Vec<Running>> = Mutex::new(Vec::new());
+static PENDING_WRITES: Mutex<Option<HashSet<PathBuf>>> = Mutex::new(None);
+static CONTAINERS: AtomicUsize = AtomicUsize::new(0);
+
+/// A tool being run, as needed to stop it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Running {
+    Process(u32),
+    Container(String),
+}
+
+/// Listen for Ctrl+C for the rest of the process. Call from within the runtime.
+pub fn install() {
+    tokio::spawn(async {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            on_interrupt();
+        }
+    });
+}
+
+fn on_interrupt() {
+    let again = INTERRUPTED.swap(true, Ordering::SeqCst);
+    let running = std::mem::take(&mut *RUNNING.lock().unwrap_or_else(|e| e.into_inner()));
+    if again || running.is_empty() {
+        discard_pending_writes();
+        eprintln!();
+        std::process::exit(EXIT_INTERRUPTED);
+    }
+
+    eprintln!("\nInterrupted; stopping probe-verus (press Ctrl+C again to exit now)...");
+    for tool in running {
+        stop(&tool);
+    }
+}
+
+/// Whether Ctrl+C has been pressed.
+pub fn requested() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// A name for the next docker container, so it can be stopped by name.
+pub fn container_name() -> String {
+    format!(
+        "verilib-{}-{}",
+        std::process::id(),
+        CONTAINERS.fetch_add(1, Ordering::SeqCst)
+    )
+}
+
+/// Stops `child` (and the docker container `container`, if any) on Ctrl+C
+/// while the guard is alive.
+pub struct Tracked(Vec<Running>);
+
+pub fn track(child: &Child, container: Option<&str>) -> Tracked {
+    let mut tools = vec![Running::Process(child.id())];
+    tools.extend(container.map(|name| Running::Container(name.to_string())));
+    RUNNING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .extend(tools.iter().cloned());
+    Tracked(tools)
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        RUNNING
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|tool| !self.0.contains(tool));
+    }
+}
+
+fn stop(tool: &Running) {
+    match tool {
+        // The docker CLI exits once its container is gone.
+        Running::Container(name) => {
+            let _ = Command::new("docker")
+                .args(["kill", name])
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status();
+        }
+        #[cfg(unix)]
+        Running::Process(pid) => {
+            // SAFETY: kill(2) has no memory-safety preconditions.
+            unsafe {
+                libc::kill(*pid as libc::pid_t, libc::SIGTERM);
+            }
+        }
+        #[cfg(not(unix))]
+        Running::Process(pid) => {
+            let _ = Command::new("taskkill")
+                .args(["/F", "/PID", &pid.to_string()])
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status();
+        }
+    }
+}
+
+/// Replace `path` with `contents` via a temporary file in the same directory.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    replace_via_temp(path, |temp| Ok(std::fs::write(temp, contents)?))
+}
+
+/// Replace `path` with everything `reader` yields, streamed through a
+/// temporary file in the same directory. Returns the bytes written.
+pub fn write_atomic_from(path: &Path, mut reader: impl Read) -> Result<u64> {
+    replace_via_temp(path, |temp| {
+        let mut file = std::fs::File::create(temp)?;
+        Ok(std::io::copy(&mut reader, &mut file)?)
+    })
+}
+
+/// Replace `path` with what `write` writes to a temporary file in the same
+/// directory, for output produced through a writer.
+pub fn write_atomic_with<T>(
+    path: &Path,
+    write: impl FnOnce(&mut std::fs::File) -> Result<T>,
+) -> Result<T> {
+    replace_via_temp(path, |temp| write(&mut std::fs::File::create(temp)?))
+}
+
+fn replace_via_temp<T>(path: &Path, write: impl FnOnce(&Path) -> Result<T>) -> Result<T> {
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("Not a file path: {}", path.display()))?;
+    let temp = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    pending_writes(|pending| pending.insert(temp.clone()));
+    let result = write(&temp)
+        .and_then(|written| {
+            std::fs::rename(&temp, path)?;
+            Ok(written)
+        })
+        .with_context(|| format!("Failed to write {}", path.display()));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    pending_writes(|pending| pending.remove(&temp));
+    result
+}
+
+fn pending_writes<T>(f: impl FnOnce(&mut HashSet<PathBuf>) -> T) -> T {
+    let mut pending = PENDING_WRITES.lock().unwrap_or_else(|e| e.into_inner());
+    f(pending.get_or_insert_with(HashSet::new))
+}
+
+/// Remove temporary files of writes cut short by exiting.
+fn discard_pending_writes() {
+    pending_writes(|pending| {
+        for temp in pending.drain() {
+            let _ = std::fs::remove_file(temp);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic_replaces_without_leftovers() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("stubs.json");
+        std::fs::write(&path, "old").unwrap();
+
+        write_atomic(&path, "new").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        assert!(write_atomic(&dir.path().join("missing/x.json"), "x").is_err());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -1,41 +1,721 @@
+use anyhow::Context;
 use anyhow::Result;
-use clap::Parser;
+use clap::error::{ContextKind, ContextValue};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 mod cli;
 mod commands;
 mod config;
 mod constants;
+mod defaults;
 mod download;
+mod encryption;
+mod environment;
 mod executor;
+mod exit;
+mod freshness;
+mod git;
+mod i18n;
+mod interrupt;
+mod metrics;
+mod notify;
+mod output;
+mod paths;
+mod picker;
+mod query;
 mod storage;
 mod structure;
+mod user_config;
+mod walk;
 
-use cli::{Cli, Commands};
+use cli::{
+    ApiCommands, Cli, Commands, FlagLogCommands, FunctionsCommands, KeysCommands, LayoutsCommands,
+    MetricsCommands,
+};
+use commands::api::{handle_api, ApiSubcommand, MetaTarget};
+use commands::atomize::Analyzer;
+use commands::deploy::{DeployOptions, RepoOptions};
 use commands::{
-    handle_atomize, handle_auth, handle_create, handle_init, handle_reclone, handle_specify,
-    handle_status, handle_verify,
+    handle_atomization_status, handle_atomize, handle_auth, handle_code_locate, handle_convert,
+    handle_create, handle_deploy, handle_doctor, handle_drift, handle_export, handle_flag_log_show,
+    handle_functions_list, handle_generate_fixture, handle_graph, handle_grep, handle_history,
+    handle_init, handle_keys, handle_layouts_check, handle_metrics_show, handle_migrate_certs,
+    handle_migrate_frontmatter, handle_migrate_layout, handle_migrate_paths, handle_pack,
+    handle_prune, handle_reclone, handle_reindex, handle_run, handle_schema, handle_selftest,
+    handle_specify, handle_stats, handle_status, handle_sync_flags, handle_unpack, handle_verify,
+    handle_verify_certs, ExportFormat, ExportOptions, FixtureOptions, FunctionsListOptions,
+    GrepOptions, KeysSubcommand, RunOptions, SyncDirection, VerifyOptions,
 };
+use user_config::UserConfig;
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
+async fn main() {
+    metrics::start();
+    let matches = parse_args();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    output::init(cli.color);
+    if let Err(e) = locate_project(&mut cli, &matches) {
+        output::error(format!("{:#}", e));
+        std::process::exit(exit::code(&e));
+    }
+    storage::init(cli.fallback_file_storage);
+    structure::init_keep_intermediates(cli.keep_intermediates);
+    interrupt::install();
+    let scope = history_scope(&cli.command, cli.dry_run);
+    let name = command_name(&matches);
+
+    let started = Instant::now();
+    let outcome = run(cli).await;
+
+    if let Some((project_root, command, artifacts)) = scope {
+        commands::history::record(&project_root, command, artifacts, &outcome);
+    }
+    notify::finish(&metrics::project_root(), &name, &outcome, started.elapsed());
+    if !name.starts_with("metrics") {
+        metrics::finish(&name, &outcome);
+    }
+    if let Err(e) = outcome {
+        output::error(format!("{:#}", e));
+        if interrupt::requested() {
+            std::process::exit(interrupt::EXIT_INTERRUPTED);
+        }
+        std::process::exit(exit::code(&e));
+    }
+}
+
+/// Parse the command line, expanding a user alias (`aliases` in
+/// `~/.verilib_config.json`) where clap found an unknown subcommand, then
+/// adding the command's configured default flags.
+fn parse_args() -> ArgMatches {
+    let args: Vec<OsString> = std::env::args_os().collect();
+    let (args, matches) = match exit::document(Cli::command()).try_get_matches_from(&args) {
+        Ok(matches) => (args, matches),
+        Err(error) => {
+            let expanded = match error.get(ContextKind::InvalidSubcommand) {
+                Some(ContextValue::String(name)) => UserConfig::load()
+                    .ok()
+                    .and_then(|config| config.expand_alias(&args, name)),
+                _ => None,
+            };
+            match expanded {
+                Some(args) => {
+                    let matches = exit::document(Cli::command()).get_matches_from(&args);
+                    (args, matches)
+                }
+                None => error.exit(),
+            }
+        }
+    };
+    let Some(args) = defaults::apply(&args, &matches) else {
+        return matches;
+    };
+    // Explicit arguments come after the defaults and replace them.
+    exit::document(Cli::command())
+        .args_override_self(true)
+        .try_get_matches_from(&args)
+        .unwrap_or_else(|e| {
+            let _ = e.print();
+            eprintln!("note: the command ran with its configured `defaults` flags; pass --no-defaults to skip them");
+            std::process::exit(e.exit_code());
+        })
+}
+
+/// Point the command at its project: `--project-root`, else, unless it
+/// creates a project, the nearest directory from the working directory up
+/// that has a `.verilib`. Commands with a project root argument get it there;
+/// the ones that work in the current directory run from it.
+fn locate_project(cli: &mut Cli, matches: &ArgMatches) -> Result<()> {
+    let creates_project = matches!(
+        cli.command,
+        Commands::Init { .. } | Commands::Create { .. } | Commands::Unpack { .. }
+    );
+    let root = match &cli.project_dir {
+        Some(root) => root.clone(),
+        None if creates_project => return Ok(()),
+        None => match config::find_project_root(&std::env::current_dir()?) {
+            Some(root) if root != Path::new(".") => root,
+            _ => return Ok(()),
+        },
+    };
+
+    let mut leaf = matches;
+    while let Some((_, sub)) = leaf.subcommand() {
+        leaf = sub;
+    }
+    let argument_given = leaf.try_contains_id("project_root").unwrap_or(false)
+        && leaf.value_source("project_root") == Some(ValueSource::CommandLine);
+    let explicit = cli.project_dir.is_some();
+    let runs_in_project = runs_in_project(&cli.command);
+    match project_root_mut(&mut cli.command) {
+        Some(_) if argument_given && explicit => {
+            anyhow::bail!("Give the project root as an argument or with --project-root, not both")
+        }
+        Some(_) if argument_given => {}
+        Some(project_root) => *project_root = root,
+        None if explicit || runs_in_project => {
+            let cwd = std::env::current_dir()?;
+            let absolute_root = cwd.join(&root).canonicalize().unwrap_or(cwd.join(&root));
+            anchor_paths(&mut cli.command, &cwd, &absolute_root);
+            std::env::set_current_dir(&root)
+                .with_context(|| format!("Failed to enter project root {}", root.display()))?
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+/// Before a command moves to the project root, make its file arguments
+/// absolute so they still name what the user meant from `cwd`. The reindex
+/// directory is relative to `.verilib`, unless given from inside it.
+fn anchor_paths(command: &mut Commands, cwd: &Path, root: &Path) {
+    let anchor = |path: &mut PathBuf| *path = cwd.join(&*path);
+    match command {
+        Commands::Deploy { changelog_file, .. } => changelog_file.iter_mut().for_each(anchor),
+        Commands::Reindex { dir, .. } => {
+            let target = cwd.join(&*dir);
+            let target = target.canonicalize().unwrap_or(target);
+            if let Ok(inside) = target.strip_prefix(root.join(".verilib")) {
+                *dir = Path::new(".verilib").join(inside);
+            }
+        }
+        Commands::Api {
+            command: ApiCommands::Batch { input },
+        } => anchor(input),
+        Commands::Api {
+            command:
+                ApiCommands::CreateFile {
+                    from_file,
+                    snippets,
+                    snippets_json,
+                    ..
+                },
+        } => {
+            from_file.iter_mut().for_each(anchor);
+            snippets.iter_mut().for_each(|(_, path)| anchor(path));
+            snippets_json.iter_mut().for_each(anchor);
+        }
+        _ => {}
+    }
+}
+
+/// The project root argument of `command`, if it takes one.
+fn project_root_mut(command: &mut Commands) -> Option<&mut PathBuf> {
+    match command {
+        Commands::Create { project_root, .. }
+        | Commands::MigrateLayout { project_root, .. }
+        | Commands::MigrateCerts { project_root, .. }
+        | Commands::MigrateFrontmatter { project_root, .. }
+        | Commands::MigratePaths { project_root, .. }
+        | Commands::Atomize { project_root, .. }
+        | Commands::Specify { project_root, .. }
+        | Commands::Export { project_root, .. }
+        | Commands::Pack { project_root, .. }
+        | Commands::Unpack { project_root, .. }
+        | Commands::Stats { project_root, .. }
+        | Commands::Graph { project_root, .. }
+        | Commands::VerifyCerts { project_root, .. }
+        | Commands::Drift { project_root, .. }
+        | Commands::Prune { project_root, .. }
+        | Commands::Convert { project_root, .. }
+        | Commands::Grep { project_root, .. }
+        | Commands::CodeLocate { project_root, .. }
+        | Commands::Doctor { project_root, .. }
+        | Commands::Verify { project_root, .. }
+        | Commands::Run { project_root, .. } => Some(project_root),
+        Commands::Functions {
+            command: FunctionsCommands::List { project_root, .. },
+        }
+        | Commands::Layouts {
+            command: LayoutsCommands::Check { project_root, .. },
+        }
+        | Commands::Metrics {
+            command: MetricsCommands::Show { project_root, .. },
+        } => Some(project_root),
+        _ => None,
+    }
+}
+
+/// Whether `command` works on the project in the current directory.
+fn runs_in_project(command: &Commands) -> bool {
+    matches!(
+        command,
+        Commands::Status { .. }
+            | Commands::Deploy { .. }
+            | Commands::Reclone
+            | Commands::SyncFlags { .. }
+            | Commands::Reindex { .. }
+            | Commands::AtomizationStatus { .. }
+            | Commands::History { .. }
+            | Commands::FlagLog { .. }
+            | Commands::Keys { .. }
+            | Commands::Api { .. }
+    )
+}
+
+/// The subcommand path, e.g. `atomize` or `keys generate`.
+fn command_name(matches: &ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut matches = matches;
+    while let Some((name, sub)) = matches.subcommand() {
+        names.push(name);
+        matches = sub;
+    }
+    names.join(" ")
+}
+
+/// Where a mutating command's history entry is recorded, the command's name,
+/// and the artifacts it touches. Read-only invocations return `None`.
+fn history_scope(
+    command: &Commands,
+    dry_run: bool,
+) -> Option<(PathBuf, &'static str, Vec<String>)> {
+    let here = PathBuf::from(".");
+    let paths = |paths: &[&str]| paths.iter().map(|p| p.to_string()).collect();
+
+    match command {
+        Commands::Init { .. } => Some((here, "init", paths(&[".verilib/config.json", ".verilib"]))),
+        Commands::Deploy { create_only, .. } => {
+            let mut artifacts = paths(&[".verilib/config.json", "server repository"]);
+            if !create_only {
+                artifacts.push(".verilib/deploys.jsonl".to_string());
+            }
+            Some((here, "deploy", artifacts))
+        }
+        Commands::Reclone => Some((here, "reclone", paths(&["server repository"]))),
+        Commands::SyncFlags {
+            pull,
+            push,
+            interactive,
+        } if (*pull || *push || *interactive) && !dry_run => Some((
+            here,
+            "sync-flags",
+            paths(&[".verilib", "server repository"]),
+        )),
+        Commands::Reindex { dir, .. } if !dry_run => {
+            Some((here, "reindex", vec![dir.display().to_string()]))
+        }
+        Commands::Keys { command } => match command {
+            KeysCommands::Generate { .. } => {
+                Some((here, "keys generate", paths(&["age identity"])))
+            }
+            KeysCommands::SetRecipient { .. } => {
+                Some((here, "keys set-recipient", paths(&[".verilib/config.json"])))
+            }
+            KeysCommands::UnsetRecipient => Some((
+                here,
+                "keys unset-recipient",
+                paths(&[".verilib/config.json"]),
+            )),
+            KeysCommands::Show => None,
+        },
+        Commands::Api { command } if !dry_run => match command {
+            ApiCommands::Set { .. } => Some((here, "api set", paths(&["meta files"]))),
+            ApiCommands::Batch { .. } => Some((here, "api batch", paths(&["meta files"]))),
+            ApiCommands::CreateFile { path, .. } => {
+                Some((here, "api create-file", vec![path.display().to_string()]))
+            }
+            ApiCommands::Validate { fix: true } => {
+                Some((here, "api validate", paths(&["meta files"])))
+            }
+            ApiCommands::Annotate {
+                file, add, remove, ..
+            } if !add.is_empty() || !remove.is_empty() => {
+                Some((here, "api annotate", vec![file.clone()]))
+            }
+            _ => None,
+        },
+        Commands::Create {
+            project_root,
+            root,
+            template,
+            ..
+        } => {
+            let structure = root
+                .as_ref()
+                .map(|r| r.display().to_string())
+                .unwrap_or_else(|| ".verilib/structure".to_string());
+            let mut artifacts = vec![
+                ".verilib/config.json".to_string(),
+                ".verilib/tracked_functions.csv".to_string(),
+                structure,
+            ];
+            if template.is_some() {
+                artifacts.extend(paths(&[
+                    ".verilibignore",
+                    ".verilib/spec-templates",
+                    ".verilib/hooks",
+                ]));
+            }
+            Some((project_root.clone(), "create", artifacts))
+        }
+        Commands::Atomize {
+            project_root,
+            check_only: false,
+            atoms_only,
+            update_stubs,
+            report_skips,
+            apply_renames,
+            ..
+        } => {
+            let mut artifacts = paths(&[".verilib/atoms.json"]);
+            if !atoms_only {
+                artifacts.push(".verilib/stubs.json".to_string());
+            }
+            if *update_stubs || *apply_renames {
+                artifacts.push("structure files".to_string());
+            }
+            if let Some(report) = report_skips {
+                artifacts.push(report.display().to_string());
+            }
+            Some((project_root.clone(), "atomize", artifacts))
+        }
+        Commands::Specify {
+            project_root,
+            check_only: false,
+            ..
+        } => Some((
+            project_root.clone(),
+            "specify",
+            paths(&[
+                ".verilib/specs.json",
+                ".verilib/stubs.json",
+                ".verilib/certs/specs",
+            ]),
+        )),
+        Commands::Verify {
+            project_root,
+            matrix,
+            no_probe,
+            ..
+        } if !matrix.is_empty() => (!*no_probe).then(|| {
+            (
+                project_root.clone(),
+                "verify",
+                matrix
+                    .iter()
+                    .map(|profile| format!(".verilib/proofs.{}.json", profile))
+                    .collect(),
+            )
+        }),
+        Commands::Verify {
+            project_root,
+            check_only: false,
+            ..
+        } => Some((
+            project_root.clone(),
+            "verify",
+            paths(&[
+                ".verilib/proofs.json",
+                ".verilib/stubs.json",
+                ".verilib/verified.json",
+            ]),
+        )),
+        Commands::Run { project_root, .. } => Some((
+            project_root.clone(),
+            "run",
+            paths(&[
+                ".verilib/atoms.json",
+                ".verilib/specs.json",
+                ".verilib/stubs.json",
+                ".verilib/proofs.json",
+                ".verilib/run.json",
+            ]),
+        )),
+        Commands::MigrateLayout { project_root, .. } if !dry_run => Some((
+            project_root.clone(),
+            "migrate-layout",
+            paths(&[
+                ".verilib/config.json",
+                ".verilib/stubs.json",
+                ".verilib/structure",
+            ]),
+        )),
+        Commands::MigrateCerts { project_root, .. } if !dry_run => Some((
+            project_root.clone(),
+            "migrate-certs",
+            paths(&[
+                ".verilib/config.json",
+                ".verilib/certs/specs",
+                ".verilib/certs/specs.jsonl",
+            ]),
+        )),
+        Commands::MigrateFrontmatter { project_root, .. } if !dry_run => Some((
+            project_root.clone(),
+            "migrate-frontmatter",
+            paths(&[".verilib/config.json", ".verilib/structure"]),
+        )),
+        Commands::MigratePaths { project_root } if !dry_run => Some((
+            project_root.clone(),
+            "migrate-paths",
+            paths(&["meta files"]),
+        )),
+        Commands::VerifyCerts {
+            project_root,
+            clean,
+            requeue,
+        } if *clean || *requeue => Some((
+            project_root.clone(),
+            "verify-certs",
+            paths(&[".verilib/certs/specs", ".verilib/stubs.json"]),
+        )),
+        Commands::Prune { project_root, .. } if !dry_run => Some((
+            project_root.clone(),
+            "prune",
+            paths(&[".verilib/stubs.json", ".verilib/certs/specs"]),
+        )),
+        Commands::Export {
+            project_root,
+            output,
+            scip,
+            ..
+        } => Some((
+            project_root.clone(),
+            "export",
+            output
+                .iter()
+                .chain(scip)
+                .map(|path| path.display().to_string())
+                .collect(),
+        )),
+        Commands::Pack {
+            project_root,
+            output,
+            ..
+        } if !dry_run => Some((
+            project_root.clone(),
+            "pack",
+            vec![output.display().to_string()],
+        )),
+        Commands::Unpack { project_root, .. } if !dry_run => {
+            Some((project_root.clone(), "unpack", paths(&[".verilib"])))
+        }
+        Commands::Convert {
+            project_root,
+            to_csv: Some(csv),
+            ..
+        } if csv.as_os_str() != "-" => Some((
+            project_root.clone(),
+            "convert",
+            vec![csv.display().to_string()],
+        )),
+        Commands::Convert {
+            project_root,
+            from_csv: Some(_),
+            ..
+        } if !dry_run => Some((
+            project_root.clone(),
+            "convert",
+            paths(&[".verilib/stubs.json", "structure files"]),
+        )),
+        Commands::Graph {
+            project_root,
+            output: Some(output),
+        } => Some((
+            project_root.clone(),
+            "graph",
+            vec![output.display().to_string()],
+        )),
+        Commands::GenerateFixture { output, .. } => Some((
+            output.clone(),
+            "generate-fixture",
+            vec![output.join(".verilib").display().to_string()],
+        )),
+        _ => None,
+    }
+}
 
+async fn run(cli: Cli) -> Result<()> {
     match cli.command {
         Commands::Auth => {
             handle_auth().await?;
         }
-        Commands::Status => {
-            handle_status().await?;
+        Commands::Status { remote, porcelain } => {
+            handle_status(remote, porcelain, cli.base_url, cli.debug).await?;
+        }
+        Commands::Init {
+            id,
+            force,
+            snapshot,
+            team,
+            visibility,
+            topics,
+        } => {
+            let repo = RepoOptions {
+                team,
+                visibility,
+                topics,
+            };
+            handle_init(id, cli.base_url, force, snapshot, repo, cli.debug).await?;
+        }
+        Commands::Deploy {
+            message,
+            changelog_file,
+            subdir,
+            team,
+            visibility,
+            topics,
+            create_only,
+        } => {
+            let options = DeployOptions {
+                url: cli.base_url,
+                subdir,
+                message,
+                changelog_file,
+                repo: RepoOptions {
+                    team,
+                    visibility,
+                    topics,
+                },
+                create_only,
+            };
+            handle_deploy(options, cli.debug).await?;
         }
-        Commands::Init { id, url } => {
-            handle_init(id, url, cli.debug).await?;
+        Commands::SyncFlags {
+            pull,
+            push,
+            interactive,
+        } => {
+            let direction = if pull {
+                Some(SyncDirection::Pull)
+            } else if push {
+                Some(SyncDirection::Push)
+            } else if interactive {
+                Some(SyncDirection::Interactive)
+            } else {
+                None
+            };
+            handle_sync_flags(direction, cli.base_url, cli.dry_run, cli.json, cli.debug).await?;
         }
         Commands::Reclone => {
-            handle_reclone(cli.debug).await?;
+            handle_reclone(cli.base_url, cli.debug).await?;
+        }
+        Commands::Reindex { dir, recursive } => {
+            handle_reindex(dir, recursive, cli.dry_run).await?;
+        }
+        Commands::History { limit } => {
+            handle_history(limit, cli.json).await?;
+        }
+        Commands::AtomizationStatus { wait, timeout } => {
+            handle_atomization_status(wait, timeout, cli.base_url, cli.debug).await?;
+        }
+        Commands::FlagLog { command } => match command {
+            FlagLogCommands::Show { file, limit } => {
+                handle_flag_log_show(file, limit, cli.json).await?;
+            }
+        },
+        Commands::Metrics { command } => match command {
+            MetricsCommands::Show {
+                project_root,
+                limit,
+            } => {
+                handle_metrics_show(project_root, limit, cli.json).await?;
+            }
+        },
+        Commands::Keys { command } => {
+            let subcommand = match command {
+                KeysCommands::Generate { force } => KeysSubcommand::Generate { force },
+                KeysCommands::Show => KeysSubcommand::Show,
+                KeysCommands::SetRecipient { recipient } => {
+                    KeysSubcommand::SetRecipient { recipient }
+                }
+                KeysCommands::UnsetRecipient => KeysSubcommand::UnsetRecipient,
+            };
+            handle_keys(subcommand).await?;
+        }
+        Commands::Api { command } => {
+            let subcommand = match command {
+                ApiCommands::Get {
+                    file,
+                    id,
+                    code_name,
+                } => ApiSubcommand::Get {
+                    target: meta_target(file, id, code_name),
+                },
+                ApiCommands::List {
+                    filter,
+                    review_status,
+                    sort,
+                    porcelain,
+                } => ApiSubcommand::List {
+                    filter,
+                    review_status,
+                    sort,
+                    porcelain,
+                },
+                ApiCommands::Set {
+                    file,
+                    id,
+                    code_name,
+                    specified,
+                    ignored,
+                    verified,
+                    review_status,
+                } => ApiSubcommand::Set {
+                    target: meta_target(file, id, code_name),
+                    specified,
+                    ignored,
+                    verified,
+                    review_status,
+                },
+                ApiCommands::Batch { input } => ApiSubcommand::Batch { input },
+                ApiCommands::CreateFile {
+                    path,
+                    content,
+                    from_file,
+                    disabled,
+                    specified,
+                    status_id,
+                    statement_type,
+                    code_name,
+                    snippets,
+                    snippets_json,
+                } => ApiSubcommand::CreateFile {
+                    path,
+                    content,
+                    from_file,
+                    disabled,
+                    specified,
+                    status_id,
+                    statement_type,
+                    code_name,
+                    snippet_files: snippets,
+                    snippets_json,
+                },
+                ApiCommands::Validate { fix } => ApiSubcommand::Validate { fix },
+                ApiCommands::Annotate {
+                    file, add, remove, ..
+                } => ApiSubcommand::Annotate {
+                    target: MetaTarget::File(file.into()),
+                    add,
+                    remove,
+                },
+            };
+            handle_api(subcommand, cli.json, cli.dry_run).await?;
         }
         // Structure commands (merged from verilib-structure)
-        Commands::Create { project_root, root } => {
-            handle_create(project_root, root).await?;
+        Commands::Create {
+            project_root,
+            root,
+            layout,
+            template,
+        } => {
+            handle_create(project_root, root, layout, template).await?;
+        }
+        Commands::MigrateLayout { project_root, to } => {
+            handle_migrate_layout(project_root, to, cli.dry_run).await?;
+        }
+        Commands::MigrateCerts { project_root, to } => {
+            handle_migrate_certs(project_root, to, cli.dry_run).await?;
+        }
+        Commands::MigrateFrontmatter { project_root, to } => {
+            handle_migrate_frontmatter(project_root, to, cli.dry_run).await?;
+        }
+        Commands::MigratePaths { project_root } => {
+            handle_migrate_paths(project_root, cli.dry_run).await?;
         }
         Commands::Atomize {
             project_root,
@@ -43,7 +723,12 @@ async fn main() -> Result<()> {
             no_probe,
             check_only,
             atoms_only,
+            analyzer,
             rust_analyzer,
+            from_scip,
+            report_skips,
+            line_tolerance,
+            apply_renames,
         } => {
             handle_atomize(
                 project_root,
@@ -51,7 +736,15 @@ async fn main() -> Result<()> {
                 no_probe,
                 check_only,
                 atoms_only,
-                rust_analyzer,
+                if rust_analyzer {
+                    Analyzer::Rust
+                } else {
+                    analyzer
+                },
+                from_scip,
+                report_skips,
+                line_tolerance,
+                apply_renames,
             )
             .await?;
         }
@@ -59,30 +752,248 @@ async fn main() -> Result<()> {
             project_root,
             no_probe,
             check_only,
+            interactive,
+            auto,
+            approve_from,
+        } => {
+            handle_specify(
+                project_root,
+                no_probe,
+                check_only,
+                interactive,
+                auto,
+                approve_from,
+                cli.json,
+            )
+            .await?;
+        }
+        Commands::Export {
+            project_root,
+            format,
+            output,
+            scip,
+            sign_key,
+            filter,
+        } => {
+            let options = match scip {
+                Some(scip) => ExportOptions {
+                    format: ExportFormat::Scip,
+                    output: scip,
+                    sign_key,
+                    filter,
+                },
+                None => ExportOptions {
+                    format,
+                    output: output.unwrap_or_default(),
+                    sign_key,
+                    filter,
+                },
+            };
+            handle_export(project_root, options).await?;
+        }
+        Commands::Pack {
+            project_root,
+            output,
+            exclude,
+        } => {
+            handle_pack(project_root, output, exclude, cli.dry_run, cli.json).await?;
+        }
+        Commands::Unpack {
+            archive,
+            project_root,
+            exclude,
+            force,
+        } => {
+            handle_unpack(archive, project_root, exclude, force, cli.dry_run, cli.json).await?;
+        }
+        Commands::Convert {
+            project_root,
+            to_csv,
+            from_csv,
+            yes,
+        } => {
+            handle_convert(project_root, to_csv, from_csv, yes, cli.dry_run, cli.json).await?;
+        }
+        Commands::Stats {
+            project_root,
+            atoms,
+            since,
+            markdown,
+            by_owner,
+        } => {
+            handle_stats(project_root, atoms, since, markdown, by_owner, cli.json).await?;
+        }
+        Commands::Graph {
+            project_root,
+            output,
+        } => {
+            handle_graph(project_root, output, cli.json).await?;
+        }
+        Commands::Drift {
+            project_root,
+            check,
+        } => {
+            handle_drift(project_root, check, cli.json).await?;
+        }
+        Commands::VerifyCerts {
+            project_root,
+            clean,
+            requeue,
+        } => {
+            handle_verify_certs(project_root, clean, requeue, cli.json).await?;
+        }
+        Commands::Prune { project_root, yes } => {
+            handle_prune(project_root, yes, cli.dry_run, cli.json).await?;
+        }
+        Commands::Grep {
+            pattern,
+            project_root,
+            ignore_case,
+            scope,
+            status,
+        } => {
+            let options = GrepOptions {
+                pattern,
+                ignore_case,
+                scope,
+                status,
+            };
+            handle_grep(project_root, options, cli.json).await?;
+        }
+        Commands::CodeLocate {
+            path,
+            line,
+            project_root,
+            line_tolerance,
         } => {
-            handle_specify(project_root, no_probe, check_only).await?;
+            let location = path.zip(line);
+            handle_code_locate(project_root, location, line_tolerance, cli.json).await?;
+        }
+        Commands::Functions { command } => match command {
+            FunctionsCommands::List {
+                project_root,
+                module,
+                path_glob,
+                specified,
+                verified,
+                failed,
+                filter,
+                sort,
+            } => {
+                let options = FunctionsListOptions {
+                    module,
+                    path_glob,
+                    specified,
+                    verified,
+                    failed,
+                    filter,
+                    sort,
+                };
+                handle_functions_list(project_root, options, cli.json).await?;
+            }
+        },
+        Commands::Layouts { command } => match command {
+            LayoutsCommands::Check { project_root } => {
+                handle_layouts_check(project_root, cli.json).await?;
+            }
+        },
+        Commands::Schema { artifact } => {
+            handle_schema(artifact).await?;
+        }
+        Commands::Doctor { project_root } => {
+            handle_doctor(project_root, cli.json).await?;
+        }
+        Commands::Selftest { keep } => {
+            handle_selftest(keep, cli.debug).await?;
+        }
+        Commands::GenerateFixture {
+            output,
+            atoms,
+            modules,
+            failure_rate,
+            seed,
+            force,
+        } => {
+            let options = FixtureOptions {
+                atoms,
+                modules,
+                failure_rate,
+                seed,
+            };
+            handle_generate_fixture(output, options, force).await?;
         }
         Commands::Verify {
             project_root,
             package,
             verify_only_module,
+            owner,
             no_probe,
             check_only,
+            profile,
+            function,
+            capture_env,
+            check_env,
+            no_cache,
+            expect_failures,
+            modules_from_diff,
+            diff_depth,
+            import,
+            matrix,
+            jobs,
+            auto,
         } => {
-            handle_verify(
-                project_root,
+            let options = VerifyOptions {
                 package,
                 verify_only_module,
+                owner,
                 no_probe,
                 check_only,
-            )
-            .await?;
+                profile,
+                function,
+                capture_env,
+                check_env,
+                no_cache,
+                expect_failures,
+                modules_from_diff,
+                diff_depth,
+                import,
+                matrix,
+                jobs,
+                auto,
+            };
+            handle_verify(project_root, options).await?;
+        }
+        Commands::Run {
+            project_root,
+            stages,
+            no_probe,
+            profile,
+            force,
+        } => {
+            let options = RunOptions {
+                stages,
+                no_probe,
+                profile,
+                force,
+            };
+            handle_run(project_root, options).await?;
         }
     }
 
     Ok(())
 }
 
+/// The meta file an `api` command addresses; clap requires exactly one of
+/// `--file`, `--id`, and `--code-name`.
+fn meta_target(file: Option<String>, id: Option<String>, code_name: Option<String>) -> MetaTarget {
+    match (file, id, code_name) {
+        (Some(file), _, _) => MetaTarget::File(file.into()),
+        (_, Some(id), _) => MetaTarget::Identifier(id),
+        (_, _, Some(code_name)) => MetaTarget::CodeName(code_name),
+        (None, None, None) => unreachable!("clap requires a meta file target"),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
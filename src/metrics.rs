@@ -0,0 +1,228 @@
+//! Opt-in local timing metrics.
+//!
+//! While a command runs it notes how long each stage and each external tool
+//! took and counts things like files read and cache hits. When metrics are
+//! enabled (`"metrics": true` in `.verilib/config.json`, or
+//! `VERILIB_METRICS=1`), [`finish`] appends the run as one JSON line to
+//! `.verilib/metrics.jsonl`. Nothing leaves the machine; `metrics show`
+//! summarizes the file.
+
+use crate::config::ProjectConfig;
+use crate::output;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Enables (`1`, `true`) or disables (`0`, `false`) metrics, overriding config.json.
+pub const METRICS_ENV: &str = "VERILIB_METRICS";
+
+const METRICS_FILE: &str = "metrics.jsonl";
+
+static RUN: Mutex<Option<Run>> = Mutex::new(None);
+
+/// What has been measured so far in this process.
+struct Run {
+    started: Instant,
+    project_root: Option<PathBuf>,
+    stages: Vec<Timing>,
+    subprocesses: Vec<Subprocess>,
+    counts: BTreeMap<String, u64>,
+}
+
+/// One recorded command run.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct MetricsEntry {
+    pub timestamp: DateTime<Utc>,
+    pub command: String,
+    pub args: Vec<String>,
+    pub result: String,
+    pub duration_ms: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stages: Vec<Timing>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subprocesses: Vec<Subprocess>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub counts: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Timing {
+    pub name: String,
+    pub duration_ms: u64,
+}
+
+/// One external tool invocation, e.g. `probe-verus verify`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Subprocess {
+    pub command: String,
+    pub duration_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+}
+
+fn with_run(f: impl FnOnce(&mut Run)) {
+    let mut run = RUN.lock().unwrap_or_else(|e| e.into_inner());
+    f(run.get_or_insert_with(|| Run {
+        started: Instant::now(),
+        project_root: None,
+        stages: Vec::new(),
+        subprocesses: Vec::new(),
+        counts: BTreeMap::new(),
+    }));
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+/// Start the clock for this command. Call once, first thing in `main`.
+pub fn start() {
+    with_run(|_| {});
+}
+
+/// Record the run in `project_root` rather than the working directory.
+/// The first project opened wins.
+pub fn set_project(project_root: &Path) {
+    with_run(|run| {
+        run.project_root
+            .get_or_insert_with(|| project_root.to_path_buf());
+    });
+}
+
+/// The project the command opened (see [`set_project`]), else the working
+/// directory.
+pub fn project_root() -> PathBuf {
+    let mut root = None;
+    with_run(|run| root = run.project_root.clone());
+    root.unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Times a pipeline stage until dropped.
+pub struct Stage {
+    name: &'static str,
+    started: Instant,
+}
+
+pub fn stage(name: &'static str) -> Stage {
+    Stage {
+        name,
+        started: Instant::now(),
+    }
+}
+
+impl Drop for Stage {
+    fn drop(&mut self) {
+        let duration_ms = millis(self.started.elapsed());
+        with_run(|run| {
+            run.stages.push(Timing {
+                name: self.name.to_string(),
+                duration_ms,
+            })
+        });
+    }
+}
+
+/// Add `n` to the counter `name` (e.g. `stubs`, `cache-hits`).
+pub fn count(name: &str, n: usize) {
+    with_run(|run| *run.counts.entry(name.to_string()).or_insert(0) += n as u64);
+}
+
+/// Record an external tool run that took `elapsed`.
+pub fn subprocess(command: String, elapsed: Duration, exit_code: Option<i32>) {
+    with_run(|run| {
+        run.subprocesses.push(Subprocess {
+            command,
+            duration_ms: millis(elapsed),
+            exit_code,
+        })
+    });
+}
+
+/// Whether runs in the project at `project_root` are recorded.
+fn enabled(project_root: &Path) -> bool {
+    match std::env::var(METRICS_ENV).ok().as_deref() {
+        Some("1" | "true") => true,
+        Some("0" | "false") => false,
+        _ => ProjectConfig::load(project_root).is_ok_and(|config| config.metrics),
+    }
+}
+
+pub fn metrics_path(project_root: &Path) -> PathBuf {
+    project_root.join(".verilib").join(METRICS_FILE)
+}
+
+/// Append this run to the project's metrics file, if metrics are enabled and
+/// the project has a `.verilib` directory. Failures to write are reported but
+/// never fail the command.
+pub fn finish(command: &str, outcome: &Result<()>) {
+    let Some(run) = RUN.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+        return;
+    };
+    let project_root = run.project_root.unwrap_or_else(|| PathBuf::from("."));
+    if !project_root.join(".verilib").is_dir() || !enabled(&project_root) {
+        return;
+    }
+
+    let entry = MetricsEntry {
+        timestamp: Utc::now(),
+        command: command.to_string(),
+        args: std::env::args().skip(1).collect(),
+        result: if outcome.is_ok() {
+            "success"
+        } else {
+            "failure"
+        }
+        .to_string(),
+        duration_ms: millis(run.started.elapsed()),
+        stages: run.stages,
+        subprocesses: run.subprocesses,
+        counts: run.counts,
+    };
+    if let Err(e) = append(&project_root, &entry) {
+        output::warn(format!("failed to record metrics: {:#}", e));
+    }
+}
+
+fn append(project_root: &Path, entry: &MetricsEntry) -> Result<()> {
+    let path = metrics_path(project_root);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// Read all recorded runs, oldest first. Malformed lines are skipped with a warning.
+pub fn read_metrics(project_root: &Path) -> Result<Vec<MetricsEntry>> {
+    let path = metrics_path(project_root);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(i, line)| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                output::warn(format!("{}:{}: {}", path.display(), i + 1, e));
+                None
+            }
+        })
+        .collect())
+}
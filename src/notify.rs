@@ -0,0 +1,104 @@
+//! Notifications when a long command finishes.
+//!
+//! Verification can run for half an hour. With `"notify"` set in
+//! `.verilib/config.json`, a command that ran for at least
+//! [`MIN_DURATION`] rings the terminal bell and shows a desktop
+//! notification when it finishes. Failing to notify never fails the command.
+
+use crate::config::ProjectConfig;
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Commands that finish sooner than this are not announced.
+pub const MIN_DURATION: Duration = Duration::from_secs(60);
+
+/// When to notify (`notify` in config.json).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotifyPolicy {
+    /// Only when the command failed.
+    OnFailure,
+    /// Whenever the command finished.
+    Always,
+    #[default]
+    Never,
+}
+
+impl NotifyPolicy {
+    fn applies(self, succeeded: bool) -> bool {
+        match self {
+            NotifyPolicy::Always => true,
+            NotifyPolicy::OnFailure => !succeeded,
+            NotifyPolicy::Never => false,
+        }
+    }
+}
+
+/// Announce that `command` finished after `elapsed`, if the project at
+/// `project_root` asks for it.
+pub fn finish(project_root: &Path, command: &str, outcome: &Result<()>, elapsed: Duration) {
+    if elapsed < MIN_DURATION {
+        return;
+    }
+    let policy = ProjectConfig::load(project_root)
+        .ok()
+        .and_then(|config| config.notify)
+        .unwrap_or_default();
+    if !policy.applies(outcome.is_ok()) {
+        return;
+    }
+
+    let mut stderr = std::io::stderr();
+    if stderr.is_terminal() {
+        let _ = stderr.write_all(b"\x07");
+        let _ = stderr.flush();
+    }
+
+    let (summary, body) = message(command, outcome, elapsed);
+    let _ = notify_rust::Notification::new()
+        .appname("verilib-cli")
+        .summary(&summary)
+        .body(&body)
+        .show();
+}
+
+/// Notification summary and body.
+fn message(command: &str, outcome: &Result<()>, elapsed: Duration) -> (String, String) {
+    let minutes = elapsed.as_secs() / 60;
+    let seconds = elapsed.as_secs() % 60;
+    match outcome {
+        Ok(()) => (
+            format!("verilib-cli {} finished", command),
+            format!("Succeeded after {}m {:02}s", minutes, seconds),
+        ),
+        Err(e) => (
+            format!("verilib-cli {} failed", command),
+            format!("Failed after {}m {:02}s: {:#}", minutes, seconds, e),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_and_message() {
+        assert!(NotifyPolicy::Always.applies(true));
+        assert!(!NotifyPolicy::OnFailure.applies(true));
+        assert!(NotifyPolicy::OnFailure.applies(false));
+        assert!(!NotifyPolicy::Never.applies(false));
+
+        let (summary, body) = message(
+            "verify",
+            &Err(anyhow::anyhow!("2 functions failed")),
+            Duration::from_secs(1865),
+        );
+        assert_eq!(summary, "verilib-cli verify failed");
+        assert_eq!(body, "Failed after 31m 05s: 2 functions failed");
+    }
+}
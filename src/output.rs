@@ -0,0 +1,208 @@
+//! Terminal output helpers: colored status prefixes, plain-text tables,
+//! `--porcelain` records, and progress bars.
+//!
+//! Colors are decided once from `--color` and the environment: `auto` colors
+//! only a terminal stdout, and honors `NO_COLOR` and `TERM=dumb`, so that CI
+//! logs stay plain.
+
+use clap::ValueEnum;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// When to color output (`--color`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+static COLORS: OnceLock<bool> = OnceLock::new();
+
+const BOLD: &str = "1";
+const RED: &str = "1;31";
+const GREEN: &str = "1;32";
+const YELLOW: &str = "1;33";
+
+/// Decide once whether to emit ANSI colors. Later calls are ignored.
+pub fn init(choice: ColorChoice) {
+    let _ = COLORS.set(resolve(
+        choice,
+        std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()),
+        std::env::var("TERM").is_ok_and(|t| t == "dumb"),
+        std::io::stdout().is_terminal(),
+    ));
+}
+
+fn resolve(choice: ColorChoice, no_color: bool, dumb_term: bool, is_terminal: bool) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => !no_color && !dumb_term && is_terminal,
+    }
+}
+
+fn colors_enabled() -> bool {
+    *COLORS.get().unwrap_or(&false)
+}
+
+fn paint(code: &str, text: &str) -> String {
+    if colors_enabled() {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Print `success: <message>` to stdout.
+pub fn success(message: impl Display) {
+    println!("{} {}", paint(GREEN, "success:"), message);
+}
+
+/// Print `warning: <message>` to stderr.
+pub fn warn(message: impl Display) {
+    eprintln!("{} {}", paint(YELLOW, "warning:"), message);
+}
+
+/// Print `error: <message>` to stderr.
+pub fn error(message: impl Display) {
+    eprintln!("{} {}", paint(RED, "error:"), message);
+}
+
+/// Print a section heading, bold when colors are enabled.
+pub fn heading(title: impl Display) {
+    println!("{}", paint(BOLD, &title.to_string()));
+}
+
+/// A left-aligned text table with a header row.
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new<S: Into<String>>(headers: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            headers: headers.into_iter().map(Into::into).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn row<S: Into<String>>(&mut self, cells: impl IntoIterator<Item = S>) {
+        self.rows.push(cells.into_iter().map(Into::into).collect());
+    }
+
+    /// Render with columns padded to their widest cell; trailing spaces are trimmed.
+    pub fn render(&self) -> String {
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.chars().count()).collect();
+        for row in &self.rows {
+            for (i, cell) in row.iter().enumerate() {
+                let width = cell.chars().count();
+                match widths.get_mut(i) {
+                    Some(w) => *w = (*w).max(width),
+                    None => widths.push(width),
+                }
+            }
+        }
+
+        let line = |cells: &[String]| {
+            cells
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| format!("{:<width$}", cell, width = widths[i]))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        };
+
+        let mut out = paint(BOLD, &line(&self.headers));
+        for row in &self.rows {
+            out.push('\n');
+            out.push_str(&line(row));
+        }
+        out
+    }
+
+    pub fn print(&self) {
+        println!("{}", self.render());
+    }
+}
+
+/// A progress bar over `len` items, drawn on stderr only when it is a terminal.
+pub fn progress_bar(len: u64, message: impl Into<Cow<'static, str>>) -> ProgressBar {
+    let style = ProgressStyle::with_template("{msg} [{bar:30}] {pos}/{len} ({elapsed})")
+        .expect("valid progress template")
+        .progress_chars("=> ");
+    ProgressBar::new(len)
+        .with_style(style)
+        .with_message(message)
+}
+
+/// A spinner for a step of unknown length, drawn like `progress_bar`.
+pub fn spinner(message: impl Into<Cow<'static, str>>) -> ProgressBar {
+    let spinner = ProgressBar::new_spinner().with_message(message);
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    spinner
+}
+
+/// One `--porcelain` record: fields joined by tabs, with `\`, tab, and newline
+/// inside a field escaped as `\\`, `\t`, and `\n`, so each record is one line.
+pub fn porcelain_line<S: AsRef<str>>(fields: impl IntoIterator<Item = S>) -> String {
+    fields
+        .into_iter()
+        .map(|field| {
+            field
+                .as_ref()
+                .replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+        })
+        .collect::<Vec<_>>()
+        .join("\t")
+}
+
+/// Print one `--porcelain` record to stdout. Never colored.
+pub fn porcelain<S: AsRef<str>>(fields: impl IntoIterator<Item = S>) {
+    println!("{}", porcelain_line(fields));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_porcelain_escapes_separators() {
+        assert_eq!(
+            porcelain_line(["atom", "remote-only", "a/b"]),
+            "atom\tremote-only\ta/b"
+        );
+        assert_eq!(
+            porcelain_line(["x\ty", "line\nbreak", "back\\slash"]),
+            "x\\ty\tline\\nbreak\tback\\\\slash"
+        );
+    }
+
+    #[test]
+    fn test_auto_colors_only_plain_terminals() {
+        assert!(resolve(ColorChoice::Auto, false, false, true));
+        assert!(!resolve(ColorChoice::Auto, true, false, true));
+        assert!(!resolve(ColorChoice::Auto, false, true, true));
+        assert!(!resolve(ColorChoice::Auto, false, false, false));
+        assert!(resolve(ColorChoice::Always, true, true, false));
+        assert!(!resolve(ColorChoice::Never, false, false, true));
+    }
+
+    #[test]
+    fn test_table_pads_columns() {
+        let mut table = Table::new(["Name", "Count"]);
+        table.row(["verified", "12"]);
+        table.row(["x", "3"]);
+        assert_eq!(table.render(), "Name      Count\nverified  12\nx         3");
+    }
+}
@@ -0,0 +1,159 @@
+//! UTF-8 conversion of paths, and checks on paths built from untrusted names.
+//!
+//! Paths end up as JSON keys and values (stubs.json, code-paths) and as
+//! probe-verus arguments, all of which must be UTF-8. Linux allows any bytes
+//! in file names, so conversions go through here and fail with an error
+//! naming the path instead of panicking or silently mangling it.
+//!
+//! Node identifiers and paths from the server, and atom paths given to
+//! `api`, become file names under `.verilib`. [`check_segment`] and
+//! [`join_inside`] reject any that could land outside it or that Windows
+//! cannot create.
+
+use anyhow::{anyhow, bail, Result};
+use std::path::{Component, Path, PathBuf};
+
+/// `path` as UTF-8.
+pub fn utf8(path: &Path) -> Result<&str> {
+    path.to_str()
+        .ok_or_else(|| anyhow!("Path is not valid UTF-8: {}", path.display()))
+}
+
+/// `path` relative to `base` (or unchanged if it is not under `base`), as
+/// UTF-8, for passing to a tool that runs in `base`.
+pub fn relative_arg<'a>(path: &'a Path, base: &Path) -> Result<&'a str> {
+    utf8(path.strip_prefix(base).unwrap_or(path))
+}
+
+/// Relative `path` as UTF-8 with `/` separators on every platform, for node
+/// identifiers and meta `path` fields. `.` components are dropped; a `..`
+/// component is an error, since the result would name a different file.
+pub fn slash(path: &Path) -> Result<String> {
+    utf8(path)?;
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.extend(part.to_str()),
+            Component::ParentDir => {
+                bail!("unsafe path '{}': '..' is not allowed", path.display())
+            }
+            _ => {}
+        }
+    }
+    Ok(parts.join("/"))
+}
+
+/// Device names Windows reserves in every directory, with any extension.
+const WINDOWS_RESERVED: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Check that `segment` is a plain file or directory name: not empty, `.` or
+/// `..`, without separators, drive or stream colons, or control characters,
+/// not a reserved Windows device name, and not ending in a dot or space
+/// (which Windows strips).
+pub fn check_segment(segment: &str) -> Result<()> {
+    if segment.is_empty() || segment == "." || segment == ".." {
+        bail!("'{}' is not a file name", segment);
+    }
+    if let Some(c) = segment
+        .chars()
+        .find(|c| matches!(c, '/' | '\\' | ':') || c.is_control())
+    {
+        bail!(
+            "'{}' contains the character {:?}",
+            segment.escape_debug(),
+            c
+        );
+    }
+    if segment.ends_with('.') || segment.ends_with(' ') {
+        bail!("'{}' ends with a dot or space", segment);
+    }
+    let stem = segment.split('.').next().unwrap_or(segment).trim_end();
+    if WINDOWS_RESERVED
+        .iter()
+        .any(|name| stem.eq_ignore_ascii_case(name))
+    {
+        bail!("'{}' is a reserved device name on Windows", segment);
+    }
+    Ok(())
+}
+
+/// `relative` (with `/` or `\` separators) joined onto `root`, failing for
+/// absolute paths, `..`, and segments [`check_segment`] rejects. Empty and
+/// `.` segments are dropped.
+pub fn join_inside(root: &Path, relative: &str) -> Result<PathBuf> {
+    if relative.starts_with(['/', '\\']) {
+        bail!("'{}' is an absolute path", relative);
+    }
+    let mut path = root.to_path_buf();
+    for segment in relative.split(['/', '\\']) {
+        if segment.is_empty() || segment == "." {
+            continue;
+        }
+        check_segment(segment).map_err(|e| anyhow!("unsafe path '{}': {}", relative, e))?;
+        path.push(segment);
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_untrusted_names_stay_inside_root() {
+        let root = Path::new(".verilib");
+        assert_eq!(
+            join_inside(root, "src/lib.rs/./add").unwrap(),
+            root.join("src").join("lib.rs").join("add")
+        );
+        assert_eq!(
+            join_inside(root, "src\\lib.rs").unwrap(),
+            root.join("src").join("lib.rs")
+        );
+
+        for unsafe_path in [
+            "../escape",
+            "src/../../escape",
+            "src\\..\\..\\escape",
+            "/etc/passwd",
+            "\\\\server\\share",
+            "C:\\Windows",
+            "src/con.txt",
+            "src/LPT1",
+            "src/trailing.",
+            "src/nul\u{0}byte",
+        ] {
+            assert!(join_inside(root, unsafe_path).is_err(), "{}", unsafe_path);
+        }
+        assert!(check_segment("[3] - add.atom.verilib").is_ok());
+        assert!(check_segment("console").is_ok());
+        assert!(check_segment("a/b").is_err());
+    }
+
+    #[test]
+    fn test_slash_joins_components_with_forward_slashes() {
+        let path = Path::new(".").join("src").join("lib.rs").join("add");
+        assert_eq!(slash(&path).unwrap(), "src/lib.rs/add");
+        assert!(slash(&Path::new("src").join("..").join("add")).is_err());
+    }
+}
+
+#[cfg(all(test, unix))]
+mod unix_tests {
+    use super::*;
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    #[test]
+    fn test_non_utf8_paths_are_errors() {
+        let base = Path::new(OsStr::from_bytes(b"/tmp/caf\xe9"));
+        let inside = base.join(".verilib/atoms.json");
+
+        assert_eq!(relative_arg(&inside, base).unwrap(), ".verilib/atoms.json");
+        let err = relative_arg(&inside, Path::new("/elsewhere")).unwrap_err();
+        assert!(err.to_string().contains("caf\u{fffd}"), "{}", err);
+    }
+}
@@ -0,0 +1,323 @@
+//! Full-screen multi-select list with search and a preview pane.
+//!
+//! Used by `specify --interactive`. Keys: arrows or `j`/`k` move, space
+//! toggles, `a` toggles every shown item, `/` searches, Enter asks for
+//! confirmation, and `q` or Esc cancels.
+
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::BTreeSet;
+
+/// One choice: a one-line label and the text shown beside the list.
+pub struct PickItem {
+    pub label: String,
+    pub preview: String,
+}
+
+/// Let the user choose among `items`; returns the chosen indices in order,
+/// or none if they cancel.
+pub fn pick(title: &str, items: &[PickItem]) -> Result<Vec<usize>> {
+    let mut terminal = ratatui::try_init()?;
+    let result = run(&mut terminal, title, items);
+    ratatui::restore();
+    result
+}
+
+fn run(terminal: &mut DefaultTerminal, title: &str, items: &[PickItem]) -> Result<Vec<usize>> {
+    let mut picker = Picker::new(items);
+    loop {
+        terminal.draw(|frame| draw(frame, title, &mut picker))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Release {
+                if let Some(done) = picker.handle(key) {
+                    return Ok(done);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Mode {
+    Browse,
+    Search,
+    Confirm,
+}
+
+struct Picker<'a> {
+    items: &'a [PickItem],
+    query: String,
+    /// Indices of the items matching `query`.
+    shown: Vec<usize>,
+    selected: BTreeSet<usize>,
+    list: ListState,
+    mode: Mode,
+}
+
+impl<'a> Picker<'a> {
+    fn new(items: &'a [PickItem]) -> Self {
+        let mut picker = Picker {
+            items,
+            query: String::new(),
+            shown: Vec::new(),
+            selected: BTreeSet::new(),
+            list: ListState::default(),
+            mode: Mode::Browse,
+        };
+        picker.refilter();
+        picker
+    }
+
+    /// Match items whose label contains every word of the query, ignoring case.
+    fn refilter(&mut self) {
+        let words: Vec<String> = self
+            .query
+            .to_lowercase()
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        self.shown = (0..self.items.len())
+            .filter(|&i| {
+                let label = self.items[i].label.to_lowercase();
+                words.iter().all(|word| label.contains(word.as_str()))
+            })
+            .collect();
+        self.list.select((!self.shown.is_empty()).then_some(0));
+    }
+
+    fn current(&self) -> Option<usize> {
+        self.list
+            .selected()
+            .and_then(|i| self.shown.get(i).copied())
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        if self.shown.is_empty() {
+            return;
+        }
+        let last = self.shown.len() as isize - 1;
+        let at = self.list.selected().unwrap_or(0) as isize;
+        self.list.select(Some((at + delta).clamp(0, last) as usize));
+    }
+
+    fn toggle_current(&mut self) {
+        if let Some(item) = self.current() {
+            if !self.selected.remove(&item) {
+                self.selected.insert(item);
+            }
+        }
+    }
+
+    /// Select every shown item, or deselect them if all are already selected.
+    fn toggle_shown(&mut self) {
+        if self.shown.iter().all(|i| self.selected.contains(i)) {
+            for i in &self.shown {
+                self.selected.remove(i);
+            }
+        } else {
+            self.selected.extend(self.shown.iter().copied());
+        }
+    }
+
+    /// Apply a key; `Some` with the final selection once the picker is done.
+    fn handle(&mut self, key: KeyEvent) -> Option<Vec<usize>> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Some(Vec::new());
+        }
+        match self.mode {
+            Mode::Search => match key.code {
+                KeyCode::Enter => self.mode = Mode::Browse,
+                KeyCode::Esc => {
+                    self.query.clear();
+                    self.refilter();
+                    self.mode = Mode::Browse;
+                }
+                KeyCode::Backspace => {
+                    self.query.pop();
+                    self.refilter();
+                }
+                KeyCode::Char(c) => {
+                    self.query.push(c);
+                    self.refilter();
+                }
+                _ => {}
+            },
+            Mode::Confirm => match key.code {
+                KeyCode::Char('y') | KeyCode::Enter => {
+                    return Some(self.selected.iter().copied().collect())
+                }
+                _ => self.mode = Mode::Browse,
+            },
+            Mode::Browse => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Some(Vec::new()),
+                KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+                KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+                KeyCode::PageUp => self.move_by(-10),
+                KeyCode::PageDown => self.move_by(10),
+                KeyCode::Home => self.move_by(isize::MIN / 2),
+                KeyCode::End => self.move_by(isize::MAX / 2),
+                KeyCode::Char(' ') => {
+                    self.toggle_current();
+                    self.move_by(1);
+                }
+                KeyCode::Char('a') => self.toggle_shown(),
+                KeyCode::Char('/') => self.mode = Mode::Search,
+                KeyCode::Enter if !self.selected.is_empty() => self.mode = Mode::Confirm,
+                _ => {}
+            },
+        }
+        None
+    }
+}
+
+fn draw(frame: &mut Frame, title: &str, picker: &mut Picker) {
+    let [main, status] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(1)])
+        .areas(frame.area());
+    let [list_area, preview_area] = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .areas(main);
+
+    let rows: Vec<ListItem> = picker
+        .shown
+        .iter()
+        .map(|&i| {
+            let mark = if picker.selected.contains(&i) {
+                "[x]"
+            } else {
+                "[ ]"
+            };
+            ListItem::new(format!("{} {}", mark, picker.items[i].label))
+        })
+        .collect();
+    let list = List::new(rows)
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            " {} ({}/{}) ",
+            title,
+            picker.shown.len(),
+            picker.items.len()
+        )))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, list_area, &mut picker.list);
+
+    let preview = picker
+        .current()
+        .map(|i| picker.items[i].preview.as_str())
+        .unwrap_or("");
+    frame.render_widget(
+        Paragraph::new(preview)
+            .block(Block::default().borders(Borders::ALL).title(" Spec "))
+            .wrap(Wrap { trim: false }),
+        preview_area,
+    );
+
+    let status_text = match picker.mode {
+        Mode::Search => format!("/{}", picker.query),
+        _ => format!(
+            "{} selected | space toggle  a all  / search  enter certify  q quit{}",
+            picker.selected.len(),
+            if picker.query.is_empty() {
+                String::new()
+            } else {
+                format!("  [filter: {}]", picker.query)
+            }
+        ),
+    };
+    frame.render_widget(Paragraph::new(status_text), status);
+
+    if picker.mode == Mode::Confirm {
+        let area = centered(frame.area(), 70, 60);
+        let mut lines = vec![
+            Line::from(format!("Certify {} functions?", picker.selected.len())),
+            Line::from(""),
+        ];
+        lines.extend(
+            picker
+                .selected
+                .iter()
+                .map(|&i| Line::from(format!("  {}", picker.items[i].label))),
+        );
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" y: certify, any other key: back "),
+            ),
+            area,
+        );
+    }
+}
+
+/// A `width`% by `height`% rectangle in the middle of `area`.
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let [_, middle, _] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - height) / 2),
+            Constraint::Percentage(height),
+            Constraint::Percentage((100 - height) / 2),
+        ])
+        .areas(area);
+    let [_, center, _] = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - width) / 2),
+            Constraint::Percentage(width),
+            Constraint::Percentage((100 - width) / 2),
+        ])
+        .areas(middle);
+    center
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_search_select_and_confirm() {
+        let items: Vec<PickItem> = ["alpha", "beta", "alphabet"]
+            .iter()
+            .map(|label| PickItem {
+                label: label.to_string(),
+                preview: String::new(),
+            })
+            .collect();
+        let mut picker = Picker::new(&items);
+
+        assert_eq!(picker.handle(key(KeyCode::Enter)), None);
+        assert_eq!(picker.mode, Mode::Browse, "nothing selected yet");
+
+        for code in [KeyCode::Char('/'), KeyCode::Char('A'), KeyCode::Char('l')] {
+            picker.handle(key(code));
+        }
+        picker.handle(key(KeyCode::Enter));
+        assert_eq!(picker.shown, vec![0, 2]);
+
+        picker.handle(key(KeyCode::Char('a')));
+        picker.handle(key(KeyCode::Char(' ')));
+        assert_eq!(picker.selected, BTreeSet::from([2]));
+
+        picker.handle(key(KeyCode::Enter));
+        assert_eq!(picker.mode, Mode::Confirm);
+        picker.handle(key(KeyCode::Char('n')));
+        assert_eq!(picker.mode, Mode::Browse);
+        picker.handle(key(KeyCode::Enter));
+        assert_eq!(picker.handle(key(KeyCode::Char('y'))), Some(vec![2]));
+
+        let mut picker = Picker::new(&items);
+        picker.handle(key(KeyCode::Char(' ')));
+        assert_eq!(picker.handle(key(KeyCode::Char('q'))), Some(Vec::new()));
+    }
+}
@@ -0,0 +1,453 @@
+//! Filter expressions for `api list --filter`, `functions list --filter` and
+//! `export --filter`.
+//!
+//! ```text
+//! specified && !verified && path:rsa/**
+//! (failed || review:draft) && module:crypto::rsa
+//! ```
+//!
+//! An expression combines terms with `&&`, `||`, `!` and parentheses, `!`
+//! binding tightest and `||` loosest. A term is a flag (`specified`,
+//! `verified`, `ignored`, `failed`) or `field:value`: `path:GLOB`,
+//! `name:GLOB`, `module:MODULE` (submodules match too), `owner:NAME` and
+//! `review:STATUS`. A path glob without a leading `/` may match from any
+//! directory down, so `rsa/**` matches `src/rsa/key.rs`. A term about
+//! something a subject does not record is false for it.
+
+use crate::structure::{ReviewStatus, StubEntry};
+use anyhow::{bail, Context, Result};
+use glob::Pattern;
+use std::fmt;
+use std::str::FromStr;
+
+/// Deepest nesting of `!` and parentheses an expression may have, so that
+/// parsing and evaluating it cannot exhaust the stack.
+const MAX_DEPTH: usize = 64;
+
+/// A flag a [`Subject`] may have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    Specified,
+    Verified,
+    Ignored,
+    /// The last verification failed.
+    Failed,
+}
+
+/// What filter expressions are evaluated against: a meta file or a stub.
+pub trait Subject {
+    /// `None` when the subject does not record the flag.
+    fn flag(&self, flag: Flag) -> Option<bool>;
+    fn path(&self) -> Option<&str>;
+    /// Names a `name:` glob is matched against, e.g. display and code name.
+    fn names(&self) -> Vec<&str>;
+    fn module(&self) -> Option<&str> {
+        None
+    }
+    fn owner(&self) -> Option<&str> {
+        None
+    }
+    fn review_status(&self) -> Option<ReviewStatus>;
+}
+
+impl Subject for StubEntry {
+    fn flag(&self, flag: Flag) -> Option<bool> {
+        Some(match flag {
+            Flag::Specified => self.specified == Some(true),
+            Flag::Verified => self.verified == Some(true),
+            Flag::Ignored => self.ignore_reason.is_some(),
+            Flag::Failed => self.status.as_deref() == Some("failure"),
+        })
+    }
+
+    fn path(&self) -> Option<&str> {
+        self.code_path.as_deref()
+    }
+
+    fn names(&self) -> Vec<&str> {
+        [&self.display_name, &self.code_name]
+            .into_iter()
+            .filter_map(|name| name.as_deref())
+            .collect()
+    }
+
+    fn module(&self) -> Option<&str> {
+        self.code_module.as_deref()
+    }
+
+    fn owner(&self) -> Option<&str> {
+        self.owner.as_deref()
+    }
+
+    fn review_status(&self) -> Option<ReviewStatus> {
+        self.review_status.as_deref()?.parse().ok()
+    }
+}
+
+/// A parsed filter expression.
+#[derive(Debug, Clone)]
+pub enum Query {
+    Flag(Flag),
+    Path(Pattern),
+    Name(Pattern),
+    Module(String),
+    Owner(String),
+    Review(ReviewStatus),
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+}
+
+impl Query {
+    pub fn matches(&self, subject: &impl Subject) -> bool {
+        match self {
+            Query::Flag(flag) => subject.flag(*flag) == Some(true),
+            Query::Path(glob) => subject.path().is_some_and(|path| path_matches(glob, path)),
+            Query::Name(glob) => subject.names().iter().any(|name| glob.matches(name)),
+            Query::Module(module) => subject.module().is_some_and(|m| {
+                m == module
+                    || m.strip_prefix(module.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            }),
+            Query::Owner(owner) => subject.owner() == Some(owner.as_str()),
+            Query::Review(status) => subject.review_status() == Some(*status),
+            Query::Not(query) => !query.matches(subject),
+            Query::And(left, right) => left.matches(subject) && right.matches(subject),
+            Query::Or(left, right) => left.matches(subject) || right.matches(subject),
+        }
+    }
+}
+
+/// Whether `glob` matches `path`, or, unless it starts with `/`, the part of
+/// `path` after any `/`.
+fn path_matches(glob: &Pattern, path: &str) -> bool {
+    let path = path.trim_start_matches("./");
+    if glob.as_str().starts_with('/') {
+        return glob.matches(&format!("/{}", path.trim_start_matches('/')));
+    }
+    glob.matches(path)
+        || path
+            .match_indices('/')
+            .any(|(i, _)| glob.matches(&path[i + 1..]))
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, QueryError> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            next: 0,
+            depth: 0,
+        };
+        let query = parser.or()?;
+        match parser.tokens.get(parser.next) {
+            None => Ok(query),
+            Some((column, token)) => Err(QueryError::at(
+                *column,
+                format!("unexpected {}", token.describe()),
+            )),
+        }
+    }
+}
+
+/// Why a filter expression does not parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    /// 1-based column of the problem; 0 for the end of the expression.
+    pub column: usize,
+    pub message: String,
+}
+
+impl QueryError {
+    fn at(column: usize, message: impl Into<String>) -> Self {
+        Self {
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.column == 0 {
+            write!(f, "{} at end of filter", self.message)
+        } else {
+            write!(f, "{} at column {} of filter", self.message, self.column)
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+    Term(String),
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::And => "'&&'".to_string(),
+            Token::Or => "'||'".to_string(),
+            Token::Not => "'!'".to_string(),
+            Token::Open => "'('".to_string(),
+            Token::Close => "')'".to_string(),
+            Token::Term(term) => format!("'{}'", term),
+        }
+    }
+}
+
+/// Split `s` into tokens with their 1-based columns. A term runs until
+/// whitespace, a parenthesis, `&&` or `||` outside `[...]`, so globs keep
+/// their `*`, `?` and character classes.
+fn tokenize(s: &str) -> std::result::Result<Vec<(usize, Token)>, QueryError> {
+    let chars: Vec<(usize, char)> = s.char_indices().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let (offset, c) = chars[i];
+        let column = i + 1;
+        let next = chars.get(i + 1).map(|&(_, c)| c);
+        let token = match (c, next) {
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ('&', Some('&')) | ('|', Some('|')) => {
+                i += 2;
+                tokens.push((column, if c == '&' { Token::And } else { Token::Or }));
+                continue;
+            }
+            ('&', _) | ('|', _) => {
+                return Err(QueryError::at(column, format!("expected '{0}{0}'", c)))
+            }
+            ('!', _) => Token::Not,
+            ('(', _) => Token::Open,
+            (')', _) => Token::Close,
+            _ => {
+                let start = offset;
+                let mut depth = 0usize;
+                while i < chars.len() {
+                    let (_, c) = chars[i];
+                    let next = chars.get(i + 1).map(|&(_, c)| c);
+                    match c {
+                        '[' => depth += 1,
+                        ']' => depth = depth.saturating_sub(1),
+                        _ if depth > 0 => {}
+                        '(' | ')' => break,
+                        '&' | '|' if next == Some(c) => break,
+                        c if c.is_whitespace() => break,
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                let end = chars.get(i).map_or(s.len(), |&(offset, _)| offset);
+                tokens.push((column, Token::Term(s[start..end].to_string())));
+                continue;
+            }
+        };
+        tokens.push((column, token));
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    /// `!` and parentheses open around the current token.
+    depth: usize,
+}
+
+impl Parser {
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.tokens.get(self.next).is_some_and(|(_, t)| t == token);
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> std::result::Result<Query, QueryError> {
+        let mut query = self.and()?;
+        while self.eat(&Token::Or) {
+            query = Query::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> std::result::Result<Query, QueryError> {
+        let mut query = self.unary()?;
+        while self.eat(&Token::And) {
+            query = Query::And(Box::new(query), Box::new(self.unary()?));
+        }
+        Ok(query)
+    }
+
+    fn unary(&mut self) -> std::result::Result<Query, QueryError> {
+        let Some((column, token)) = self.tokens.get(self.next).cloned() else {
+            return Err(QueryError::at(0, "expected a term"));
+        };
+        self.next += 1;
+        if matches!(token, Token::Not | Token::Open) {
+            if self.depth == MAX_DEPTH {
+                return Err(QueryError::at(
+                    column,
+                    format!("nested more than {} levels deep", MAX_DEPTH),
+                ));
+            }
+            self.depth += 1;
+        }
+        match token {
+            Token::Not => {
+                let query = self.unary()?;
+                self.depth -= 1;
+                Ok(Query::Not(Box::new(query)))
+            }
+            Token::Open => {
+                let query = self.or()?;
+                if !self.eat(&Token::Close) {
+                    let column = self.tokens.get(self.next).map_or(0, |(column, _)| *column);
+                    return Err(QueryError::at(column, "expected ')'"));
+                }
+                self.depth -= 1;
+                Ok(query)
+            }
+            Token::Term(term) => {
+                term_query(&term).map_err(|e| QueryError::at(column, format!("{:#}", e)))
+            }
+            other => Err(QueryError::at(
+                column,
+                format!("expected a term, found {}", other.describe()),
+            )),
+        }
+    }
+}
+
+fn term_query(term: &str) -> Result<Query> {
+    let Some((field, value)) = term.split_once(':') else {
+        return Ok(Query::Flag(match term {
+            "specified" => Flag::Specified,
+            "verified" => Flag::Verified,
+            "ignored" | "disabled" => Flag::Ignored,
+            "failed" => Flag::Failed,
+            _ => bail!(
+                "unknown flag '{}' (expected specified, verified, ignored, failed, or FIELD:VALUE)",
+                term
+            ),
+        }));
+    };
+    if value.is_empty() {
+        bail!("'{}' has no value", term);
+    }
+    let glob = || Pattern::new(value).with_context(|| format!("invalid glob '{}'", value));
+    Ok(match field {
+        "path" => Query::Path(glob()?),
+        "name" => Query::Name(glob()?),
+        "module" => Query::Module(value.to_string()),
+        "owner" => Query::Owner(value.to_string()),
+        "review" => Query::Review(value.parse()?),
+        _ => bail!(
+            "unknown field '{}' (expected path, name, module, owner, or review)",
+            field
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Fake {
+        specified: bool,
+        verified: bool,
+        path: &'static str,
+        module: Option<&'static str>,
+        review: Option<ReviewStatus>,
+    }
+
+    impl Subject for Fake {
+        fn flag(&self, flag: Flag) -> Option<bool> {
+            match flag {
+                Flag::Specified => Some(self.specified),
+                Flag::Verified => Some(self.verified),
+                Flag::Ignored | Flag::Failed => None,
+            }
+        }
+        fn path(&self) -> Option<&str> {
+            Some(self.path)
+        }
+        fn names(&self) -> Vec<&str> {
+            vec!["rsa::sign"]
+        }
+        fn module(&self) -> Option<&str> {
+            self.module
+        }
+        fn review_status(&self) -> Option<ReviewStatus> {
+            self.review
+        }
+    }
+
+    fn matches(query: &str, subject: &Fake) -> bool {
+        query.parse::<Query>().unwrap().matches(subject)
+    }
+
+    #[test]
+    fn test_evaluates_expressions() {
+        let sign = Fake {
+            specified: true,
+            path: "src/rsa/sign.rs",
+            module: Some("crypto::rsa::sign"),
+            review: Some(ReviewStatus::Draft),
+            ..Fake::default()
+        };
+        assert!(matches("specified && !verified && path:rsa/**", &sign));
+        assert!(matches("specified&&!verified&&path:rsa/**", &sign));
+        assert!(!matches("path:/rsa/**", &sign));
+        assert!(matches("path:/src/rsa/*.rs", &sign));
+        assert!(matches("module:crypto::rsa && name:rsa::*", &sign));
+        assert!(!matches("module:crypto::rs", &sign));
+        assert!(matches("verified || review:draft", &sign));
+        assert!(!matches("!(specified || verified)", &sign));
+        assert!(matches("verified || specified && review:draft", &sign));
+        assert!(!matches("failed || ignored", &sign));
+        assert!(matches("path:src/*/s[!a]gn.rs", &sign));
+    }
+
+    #[test]
+    fn test_reports_parse_errors() {
+        let error = |query: &str| query.parse::<Query>().unwrap_err().to_string();
+        assert_eq!(
+            error("specified && (verified"),
+            "expected ')' at end of filter"
+        );
+        assert_eq!(error("specified &"), "expected '&&' at column 11 of filter");
+        assert_eq!(
+            error("specified verified"),
+            "unexpected 'verified' at column 11 of filter"
+        );
+        assert!(error("colour:red").starts_with("unknown field 'colour'"));
+        assert!(error("verifed").contains("at column 1"));
+        assert_eq!(error(""), "expected a term at end of filter");
+        assert_eq!(error("é && ü &"), "expected '&&' at column 8 of filter");
+
+        let deep = format!("{}verified{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert_eq!(
+            error(&deep),
+            format!(
+                "nested more than {} levels deep at column 65 of filter",
+                MAX_DEPTH
+            )
+        );
+        assert!(error(&"!".repeat(100_000)).starts_with("nested more than"));
+        let nested = format!("{}verified{}", "(".repeat(MAX_DEPTH), ")".repeat(MAX_DEPTH));
+        assert!(nested.parse::<Query>().is_ok());
+    }
+}
@@ -1,7 +1,11 @@
 use crate::storage::file::FileStorage;
+#[cfg(unix)]
+use crate::storage::ssh_agent::SshAgentStorage;
 use crate::storage::types::{CredentialStorage, StorageType};
 use anyhow::Result;
 
+#[cfg(not(target_os = "linux"))]
+use crate::storage::fallback::FallbackStorage;
 #[cfg(not(target_os = "linux"))]
 use crate::storage::keyring::KeyringStorage;
 
@@ -9,16 +13,25 @@ pub struct CredentialStorageFactory;
 
 impl CredentialStorageFactory {
     pub fn create() -> Result<Box<dyn CredentialStorage>> {
-        Self::create_with_type(StorageType::from_env())
+        Self::create_with_type(StorageType::configured())
     }
 
     pub fn create_with_type(storage_type: StorageType) -> Result<Box<dyn CredentialStorage>> {
+        if storage_type == StorageType::SshAgent {
+            #[cfg(unix)]
+            return Ok(Box::new(SshAgentStorage::new()?));
+
+            #[cfg(not(unix))]
+            anyhow::bail!("SSH agent storage needs a Unix agent socket (SSH_AUTH_SOCK)");
+        }
         if storage_type.should_use_file_storage() {
             Ok(Box::new(FileStorage::new()?))
         } else {
             #[cfg(not(target_os = "linux"))]
             {
-                Ok(Box::new(KeyringStorage::new()?))
+                Ok(Box::new(FallbackStorage::new(Box::new(
+                    KeyringStorage::new()?,
+                ))))
             }
 
             #[cfg(target_os = "linux")]
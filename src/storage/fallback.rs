@@ -0,0 +1,172 @@
+//! Falling back to file storage when the keyring is locked or unavailable.
+//!
+//! A keyring operation that fails because the keyring can't be reached (not
+//! because no key is stored) offers the secure file storage instead: with
+//! `--fallback-file-storage` without asking, on a terminal after a prompt
+//! that can also remember the choice in the user config, and otherwise not at
+//! all, with a hint.
+
+use crate::output;
+use crate::storage::file::FileStorage;
+use crate::storage::types::{CredentialStorage, StorageType};
+use crate::user_config::UserConfig;
+use anyhow::Result;
+use dialoguer::Select;
+use std::cell::Cell;
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+static FALLBACK: OnceLock<bool> = OnceLock::new();
+
+/// Decide once whether an unavailable keyring falls back to file storage
+/// without asking (`--fallback-file-storage`). Later calls are ignored.
+pub fn init(fallback: bool) {
+    let _ = FALLBACK.set(fallback);
+}
+
+/// Whether `error` means the keyring is locked or can't be reached, rather
+/// than that it has no entry or rejected the value.
+pub fn is_unavailable(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<keyring::Error>(),
+            Some(keyring::Error::NoStorageAccess(_) | keyring::Error::PlatformFailure(_))
+        )
+    })
+}
+
+/// A keyring backend that switches to file storage for the rest of the
+/// session once the keyring is unavailable and the switch is accepted.
+// Linux always uses file storage, so the keyring is never wrapped there.
+#[cfg_attr(target_os = "linux", allow(dead_code))]
+pub struct FallbackStorage {
+    keyring: Box<dyn CredentialStorage>,
+    /// Whether file storage was accepted, once it was offered.
+    accepted: Cell<Option<bool>>,
+}
+
+#[cfg_attr(target_os = "linux", allow(dead_code))]
+impl FallbackStorage {
+    pub fn new(keyring: Box<dyn CredentialStorage>) -> Self {
+        Self {
+            keyring,
+            accepted: Cell::new(None),
+        }
+    }
+
+    fn run<T>(&self, operation: impl Fn(&dyn CredentialStorage) -> Result<T>) -> Result<T> {
+        if self.accepted.get() == Some(true) {
+            return operation(&FileStorage::new()?);
+        }
+        match operation(self.keyring.as_ref()) {
+            Err(e) if is_unavailable(&e) => {
+                let accepted = match self.accepted.get() {
+                    Some(accepted) => accepted,
+                    None => {
+                        let accepted = offer_file_storage(&e)?;
+                        self.accepted.set(Some(accepted));
+                        accepted
+                    }
+                };
+                if !accepted {
+                    return Err(e);
+                }
+                operation(&FileStorage::new()?)
+            }
+            result => result,
+        }
+    }
+}
+
+impl CredentialStorage for FallbackStorage {
+    fn set_password(&self, password: &str) -> Result<()> {
+        self.run(|storage| storage.set_password(password))
+    }
+
+    fn get_password(&self) -> Result<String> {
+        self.run(|storage| storage.get_password())
+    }
+
+    fn delete_password(&self) -> Result<()> {
+        self.run(|storage| storage.delete_password())
+    }
+}
+
+/// Ask whether to use file storage instead of the unavailable keyring.
+fn offer_file_storage(error: &anyhow::Error) -> Result<bool> {
+    output::warn(format!("The system keyring is unavailable: {:#}", error));
+    if FALLBACK.get() == Some(&true) {
+        eprintln!("Using the secure file storage (~/.verilib_credentials) for this session.");
+        return Ok(true);
+    }
+    if !std::io::stdin().is_terminal() {
+        eprintln!(
+            "Pass --fallback-file-storage, or set VERILIB_STORAGE=file, to use the secure file \
+             storage (~/.verilib_credentials) instead."
+        );
+        return Ok(false);
+    }
+
+    let choices = [
+        "Use file storage for this session",
+        "Use file storage from now on",
+        "Cancel",
+    ];
+    let selection = Select::new()
+        .with_prompt("Use the secure file storage (~/.verilib_credentials) instead?")
+        .items(&choices)
+        .default(0)
+        .interact()?;
+    match selection {
+        0 => Ok(true),
+        1 => {
+            let mut config = UserConfig::load()?;
+            config.credential_storage = Some(StorageType::File);
+            config.save()?;
+            eprintln!(
+                "Remembered in {}; VERILIB_STORAGE overrides it.",
+                UserConfig::path()?.display()
+            );
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    struct LockedKeyring;
+
+    impl CredentialStorage for LockedKeyring {
+        fn set_password(&self, _: &str) -> Result<()> {
+            Err(keyring::Error::NoStorageAccess("locked".into()))
+                .context("Failed to set password in keyring")
+        }
+
+        fn get_password(&self) -> Result<String> {
+            Err(keyring::Error::NoEntry).context("Failed to get password from keyring")
+        }
+
+        fn delete_password(&self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_only_an_unreachable_keyring_offers_file_storage() {
+        let storage = FallbackStorage::new(Box::new(LockedKeyring));
+
+        let missing = storage.get_password().unwrap_err();
+        assert!(!is_unavailable(&missing));
+        assert_eq!(storage.accepted.get(), None);
+
+        // As if the offer had been declined, so the test never prompts.
+        storage.accepted.set(Some(false));
+        let locked = storage.set_password("key").unwrap_err();
+        assert!(is_unavailable(&locked));
+        assert!(format!("{:#}", locked).contains("locked"));
+    }
+}
@@ -1,11 +1,15 @@
 mod factory;
+mod fallback;
 mod file;
+#[cfg(unix)]
+mod ssh_agent;
 mod types;
 
 #[cfg(not(target_os = "linux"))]
 mod keyring;
 
 pub use factory::CredentialStorageFactory;
+pub use fallback::init;
 pub use types::{CredentialStorage, StorageType};
 
 use anyhow::Result;
@@ -15,9 +19,11 @@ pub fn get_credential_storage() -> Result<Box<dyn CredentialStorage>> {
 }
 
 pub fn get_platform_info() -> String {
-    let storage_type = StorageType::from_env();
+    let storage_type = StorageType::configured();
 
-    let base_info = if storage_type.should_use_file_storage() {
+    let base_info = if storage_type == StorageType::SshAgent {
+        "SSH agent encrypted file (~/.verilib_credentials.ssh-agent)"
+    } else if storage_type.should_use_file_storage() {
         "Secure file storage (~/.verilib_credentials)"
     } else {
         #[cfg(target_os = "macos")]
@@ -32,21 +38,34 @@ pub fn get_platform_info() -> String {
         platform
     };
 
-    match storage_type {
-        StorageType::Auto => base_info.to_string(),
-        StorageType::File => format!("{} (forced via VERILIB_STORAGE=file)", base_info),
-        StorageType::Keyring => format!("{} (forced via VERILIB_STORAGE=keyring)", base_info),
+    match (StorageType::from_env(), storage_type) {
+        (None, StorageType::Auto) | (Some(StorageType::Auto), _) => base_info.to_string(),
+        (None, _) => format!("{} (remembered in ~/.verilib_config.json)", base_info),
+        (Some(StorageType::File), _) => {
+            format!("{} (forced via VERILIB_STORAGE=file)", base_info)
+        }
+        (Some(StorageType::Keyring), _) => {
+            format!("{} (forced via VERILIB_STORAGE=keyring)", base_info)
+        }
+        (Some(StorageType::SshAgent), _) => {
+            format!("{} (forced via VERILIB_STORAGE=ssh-agent)", base_info)
+        }
     }
 }
 
 pub fn print_platform_help() {
-    let storage_type = StorageType::from_env();
+    let storage_type = StorageType::configured();
 
     eprintln!("Storage configuration:");
     eprintln!("   • Current: {}", get_platform_info());
     eprintln!();
 
-    if storage_type.should_use_file_storage() {
+    if storage_type == StorageType::SshAgent {
+        eprintln!("SSH agent storage tips:");
+        eprintln!("   • SSH_AUTH_SOCK must point at a running or forwarded agent");
+        eprintln!("   • The agent must hold the Ed25519 or RSA key the API key was stored with");
+        eprintln!("   • Set VERILIB_SSH_KEY to a key's comment to choose among several keys");
+    } else if storage_type.should_use_file_storage() {
         eprintln!("File storage tips:");
         eprintln!("   • Credentials are stored in a secure file: ~/.verilib_credentials");
         eprintln!("   • File permissions are set to 0600 (owner read/write only)");
@@ -72,4 +91,8 @@ pub fn print_platform_help() {
     eprintln!("   • VERILIB_STORAGE=auto    (default, platform-specific)");
     eprintln!("   • VERILIB_STORAGE=keyring (force system keyring)");
     eprintln!("   • VERILIB_STORAGE=file    (force file storage, useful for testing)");
+    eprintln!("   • VERILIB_STORAGE=ssh-agent (encrypt with an SSH agent key, for shared hosts)");
+    eprintln!();
+    eprintln!("If the keyring is locked or unavailable, pass --fallback-file-storage to use file");
+    eprintln!("storage for one command, or choose to remember file storage when asked.");
 }
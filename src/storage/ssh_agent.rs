@@ -0,0 +1,408 @@
+//! API key storage gated by an SSH agent (`VERILIB_STORAGE=ssh-agent`).
+//!
+//! The key is kept in `~/.verilib_credentials.ssh-agent`, encrypted with a
+//! passphrase derived from the agent's signature over a fixed challenge. The
+//! file alone can't be decrypted: reading the key needs an agent holding the
+//! private key it was stored with, e.g. one forwarded to a shared CI host.
+//! Only Ed25519 and RSA keys are used, because their signatures are
+//! deterministic and so give the same passphrase on every run.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+use crate::storage::types::CredentialStorage;
+
+const FILE_NAME: &str = ".verilib_credentials.ssh-agent";
+
+/// Picks the agent key by its comment, e.g. `ci@build-host`.
+const KEY_ENV: &str = "VERILIB_SSH_KEY";
+
+/// What the agent signs; changing it makes stored keys unreadable.
+const CHALLENGE: &[u8] = b"verilib-cli credential storage v1";
+
+/// scrypt work factor of the passphrase encryption. The passphrase is a
+/// signature hash, not something guessable, so a low factor is enough.
+const WORK_FACTOR: u8 = 10;
+
+// SSH agent protocol message numbers (draft-miller-ssh-agent).
+const AGENT_FAILURE: u8 = 5;
+const AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const AGENT_IDENTITIES_ANSWER: u8 = 12;
+const AGENTC_SIGN_REQUEST: u8 = 13;
+const AGENT_SIGN_RESPONSE: u8 = 14;
+/// Sign RSA keys with rsa-sha2-256 rather than SHA-1.
+const AGENT_RSA_SHA2_256: u32 = 2;
+/// Longest reply accepted from the agent, OpenSSH's `AGENT_MAX_LEN`.
+const AGENT_MAX_LEN: usize = 256 * 1024;
+
+/// The encrypted API key and the agent key it was sealed with.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Sealed {
+    /// The public key blob, hex-encoded.
+    key: String,
+    comment: String,
+    /// ASCII-armored age payload.
+    ciphertext: String,
+}
+
+pub struct SshAgentStorage {
+    file_path: PathBuf,
+    socket: Option<PathBuf>,
+}
+
+impl SshAgentStorage {
+    pub fn new() -> Result<Self> {
+        let home_dir = dirs::home_dir().context("Failed to get home directory")?;
+        Ok(Self {
+            file_path: home_dir.join(FILE_NAME),
+            socket: std::env::var_os("SSH_AUTH_SOCK").map(PathBuf::from),
+        })
+    }
+
+    fn agent(&self) -> Result<Agent> {
+        let socket = self.socket.as_ref().ok_or_else(|| {
+            anyhow!(
+                "SSH_AUTH_SOCK is not set. Start ssh-agent and add a key with ssh-add, \
+                 or forward an agent, to use VERILIB_STORAGE=ssh-agent"
+            )
+        })?;
+        let stream = UnixStream::connect(socket).with_context(|| {
+            format!("Failed to connect to the SSH agent at {}", socket.display())
+        })?;
+        Ok(Agent { stream })
+    }
+}
+
+impl CredentialStorage for SshAgentStorage {
+    fn set_password(&self, password: &str) -> Result<()> {
+        let mut agent = self.agent()?;
+        let (key, comment) = choose_key(agent.identities()?, std::env::var(KEY_ENV).ok())?;
+        let passphrase = derive_passphrase(&agent.sign(&key, CHALLENGE)?);
+
+        let mut recipient = age::scrypt::Recipient::new(passphrase.into());
+        recipient.set_work_factor(WORK_FACTOR);
+        let ciphertext = age::encrypt_and_armor(&recipient, password.as_bytes())
+            .context("Failed to encrypt the API key")?;
+        let sealed = Sealed {
+            key: hex(&key),
+            comment,
+            ciphertext,
+        };
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&self.file_path)
+            .context("Failed to open credentials file for writing")?;
+        file.write_all(serde_json::to_string_pretty(&sealed)?.as_bytes())
+            .context("Failed to write the encrypted API key")?;
+        Ok(())
+    }
+
+    fn get_password(&self) -> Result<String> {
+        if !self.file_path.exists() {
+            bail!("No credentials file found");
+        }
+        let content =
+            fs::read_to_string(&self.file_path).context("Failed to read credentials file")?;
+        let sealed: Sealed =
+            serde_json::from_str(&content).context("Failed to parse credentials file")?;
+        let key = unhex(&sealed.key).context("Invalid key in credentials file")?;
+
+        let mut agent = self.agent()?;
+        if !agent.identities()?.iter().any(|(blob, _)| *blob == key) {
+            bail!(
+                "The SSH agent does not hold the key the API key was stored with ({}). \
+                 Add it with ssh-add, or run 'verilib-cli auth' again.",
+                sealed.comment
+            );
+        }
+        let passphrase = derive_passphrase(&agent.sign(&key, CHALLENGE)?);
+
+        let mut identity = age::scrypt::Identity::new(passphrase.into());
+        identity.set_max_work_factor(WORK_FACTOR);
+        let plaintext = age::decrypt(&identity, sealed.ciphertext.as_bytes())
+            .context("Failed to decrypt the API key with the SSH agent's signature")?;
+        String::from_utf8(plaintext).context("Decrypted API key is not valid UTF-8")
+    }
+
+    fn delete_password(&self) -> Result<()> {
+        if self.file_path.exists() {
+            fs::remove_file(&self.file_path).context("Failed to delete credentials file")?;
+        }
+        Ok(())
+    }
+}
+
+/// The first Ed25519 or RSA key, or the one whose comment is `wanted`.
+fn choose_key(
+    identities: Vec<(Vec<u8>, String)>,
+    wanted: Option<String>,
+) -> Result<(Vec<u8>, String)> {
+    let count = identities.len();
+    identities
+        .into_iter()
+        .filter(|(blob, _)| matches!(key_type(blob).as_deref(), Some("ssh-ed25519" | "ssh-rsa")))
+        .find(|(_, comment)| wanted.as_ref().is_none_or(|wanted| wanted == comment))
+        .ok_or_else(|| match wanted {
+            Some(wanted) => anyhow!(
+                "The SSH agent has no Ed25519 or RSA key with the comment '{}' ({} keys loaded)",
+                wanted,
+                count
+            ),
+            None => anyhow!(
+                "The SSH agent has no Ed25519 or RSA key ({} keys loaded). \
+                 Add one with ssh-add; ECDSA signatures are not deterministic.",
+                count
+            ),
+        })
+}
+
+fn derive_passphrase(signature: &[u8]) -> String {
+    hex(&Sha256::digest(signature))
+}
+
+/// The algorithm name a public key blob starts with.
+fn key_type(blob: &[u8]) -> Option<String> {
+    let name = Reader(blob).string().ok()?;
+    String::from_utf8(name.to_vec()).ok()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(text: &str) -> Result<Vec<u8>> {
+    text.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .filter(|digits| digits.len() == 2)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .context("not a pair of hex digits")
+        })
+        .collect()
+}
+
+/// A connection to an SSH agent.
+struct Agent {
+    stream: UnixStream,
+}
+
+impl Agent {
+    /// Public key blobs and comments of the keys the agent holds.
+    fn identities(&mut self) -> Result<Vec<(Vec<u8>, String)>> {
+        let body = self.request(AGENTC_REQUEST_IDENTITIES, &[], AGENT_IDENTITIES_ANSWER)?;
+        let mut reader = Reader(&body);
+        let count = reader.u32()?;
+        (0..count)
+            .map(|_| {
+                let blob = reader.string()?.to_vec();
+                let comment = String::from_utf8_lossy(reader.string()?).into_owned();
+                Ok((blob, comment))
+            })
+            .collect()
+    }
+
+    /// The agent's signature of `data` with `key`.
+    fn sign(&mut self, key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        let mut payload = Vec::new();
+        put_string(&mut payload, key);
+        put_string(&mut payload, data);
+        let flags = if key_type(key).as_deref() == Some("ssh-rsa") {
+            AGENT_RSA_SHA2_256
+        } else {
+            0
+        };
+        payload.extend_from_slice(&flags.to_be_bytes());
+        let body = self.request(AGENTC_SIGN_REQUEST, &payload, AGENT_SIGN_RESPONSE)?;
+        Ok(Reader(&body).string()?.to_vec())
+    }
+
+    /// Send one message and return the body of the `expected` reply.
+    fn request(&mut self, kind: u8, payload: &[u8], expected: u8) -> Result<Vec<u8>> {
+        let mut message = Vec::with_capacity(payload.len() + 5);
+        message.extend_from_slice(&(payload.len() as u32 + 1).to_be_bytes());
+        message.push(kind);
+        message.extend_from_slice(payload);
+        self.stream
+            .write_all(&message)
+            .context("Failed to write to the SSH agent")?;
+
+        let mut length = [0; 4];
+        self.stream
+            .read_exact(&mut length)
+            .context("Failed to read from the SSH agent")?;
+        let length = u32::from_be_bytes(length) as usize;
+        if length > AGENT_MAX_LEN {
+            bail!(
+                "The SSH agent sent a {} byte reply, more than the {} allowed",
+                length,
+                AGENT_MAX_LEN
+            );
+        }
+        let mut reply = vec![0; length];
+        self.stream
+            .read_exact(&mut reply)
+            .context("Failed to read from the SSH agent")?;
+        match reply.split_first() {
+            Some((&kind, body)) if kind == expected => Ok(body.to_vec()),
+            Some((&AGENT_FAILURE, _)) => bail!("The SSH agent refused the request"),
+            _ => bail!("Unexpected reply from the SSH agent"),
+        }
+    }
+}
+
+fn put_string(buf: &mut Vec<u8>, data: &[u8]) {
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buf.extend_from_slice(data);
+}
+
+/// Reads SSH wire-format integers and length-prefixed strings.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn u32(&mut self) -> Result<u32> {
+        if self.0.len() < 4 {
+            bail!("Truncated message from the SSH agent");
+        }
+        let (head, rest) = self.0.split_at(4);
+        self.0 = rest;
+        Ok(u32::from_be_bytes(head.try_into().expect("four bytes")))
+    }
+
+    fn string(&mut self) -> Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        if self.0.len() < len {
+            bail!("Truncated message from the SSH agent");
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+
+    fn blob(kind: &str, key: &[u8]) -> Vec<u8> {
+        let mut blob = Vec::new();
+        put_string(&mut blob, kind.as_bytes());
+        put_string(&mut blob, key);
+        blob
+    }
+
+    /// Serve the agent protocol for `keys`, signing with a hash of key and
+    /// data so signatures are deterministic per key.
+    fn fake_agent(socket: &std::path::Path, keys: Vec<(Vec<u8>, String)>) {
+        let listener = UnixListener::bind(socket).unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let keys = keys.clone();
+                std::thread::spawn(move || loop {
+                    let mut length = [0; 4];
+                    if stream.read_exact(&mut length).is_err() {
+                        return;
+                    }
+                    let mut message = vec![0; u32::from_be_bytes(length) as usize];
+                    stream.read_exact(&mut message).unwrap();
+                    let mut reply = Vec::new();
+                    match message[0] {
+                        AGENTC_REQUEST_IDENTITIES => {
+                            reply.push(AGENT_IDENTITIES_ANSWER);
+                            reply.extend_from_slice(&(keys.len() as u32).to_be_bytes());
+                            for (blob, comment) in &keys {
+                                put_string(&mut reply, blob);
+                                put_string(&mut reply, comment.as_bytes());
+                            }
+                        }
+                        AGENTC_SIGN_REQUEST => {
+                            let mut reader = Reader(&message[1..]);
+                            let key = reader.string().unwrap().to_vec();
+                            let data = reader.string().unwrap();
+                            if keys.iter().any(|(blob, _)| *blob == key) {
+                                reply.push(AGENT_SIGN_RESPONSE);
+                                let signature = Sha256::digest([key.as_slice(), data].concat());
+                                put_string(&mut reply, &signature);
+                            } else {
+                                reply.push(AGENT_FAILURE);
+                            }
+                        }
+                        _ => reply.push(AGENT_FAILURE),
+                    }
+                    let mut framed = (reply.len() as u32).to_be_bytes().to_vec();
+                    framed.extend_from_slice(&reply);
+                    stream.write_all(&framed).unwrap();
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn test_api_key_roundtrips_through_the_agent() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let ecdsa = (blob("ecdsa-sha2-nistp256", b"e"), "ecdsa".to_string());
+        let ed25519 = (blob("ssh-ed25519", b"one"), "ci@host".to_string());
+        let other = (blob("ssh-ed25519", b"two"), "other".to_string());
+        fake_agent(
+            &dir.path().join("agent"),
+            vec![ecdsa.clone(), ed25519.clone()],
+        );
+        fake_agent(&dir.path().join("other"), vec![ecdsa, other]);
+
+        let storage = |socket: &str| SshAgentStorage {
+            file_path: dir.path().join(FILE_NAME),
+            socket: Some(dir.path().join(socket)),
+        };
+        storage("agent").set_password("secret-key").unwrap();
+        let stored = fs::read_to_string(dir.path().join(FILE_NAME)).unwrap();
+        assert!(!stored.contains("secret-key"));
+        assert!(stored.contains("ci@host"));
+        assert_eq!(storage("agent").get_password().unwrap(), "secret-key");
+
+        let error = storage("other").get_password().unwrap_err();
+        assert!(format!("{:#}", error).contains("does not hold the key"));
+
+        storage("agent").delete_password().unwrap();
+        assert!(storage("agent").get_password().is_err());
+    }
+
+    #[test]
+    fn test_oversized_agent_reply_is_rejected() {
+        let (stream, mut agent) = UnixStream::pair().unwrap();
+        agent
+            .write_all(&(AGENT_MAX_LEN as u32 + 1).to_be_bytes())
+            .unwrap();
+        let error = Agent { stream }.identities().unwrap_err();
+        assert!(format!("{:#}", error).contains("more than the 262144 allowed"));
+    }
+
+    #[test]
+    fn test_choose_key_skips_ecdsa_and_honors_comment() {
+        let keys = vec![
+            (blob("ecdsa-sha2-nistp256", b"e"), "ecdsa".to_string()),
+            (blob("ssh-rsa", b"r"), "rsa".to_string()),
+            (blob("ssh-ed25519", b"d"), "ed".to_string()),
+        ];
+        assert_eq!(choose_key(keys.clone(), None).unwrap().1, "rsa");
+        assert_eq!(
+            choose_key(keys.clone(), Some("ed".to_string())).unwrap().1,
+            "ed"
+        );
+        assert!(choose_key(keys, Some("ecdsa".to_string())).is_err());
+        assert_eq!(unhex(&hex(b"\x00\xffab")).unwrap(), b"\x00\xffab");
+    }
+}
@@ -1,29 +1,50 @@
+use crate::user_config::UserConfig;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum StorageType {
     Auto,
     Keyring,
     File,
+    /// The API key encrypted with an SSH agent's signature; see `ssh_agent`.
+    #[serde(rename = "ssh-agent")]
+    SshAgent,
 }
 
 impl StorageType {
-    pub fn from_env() -> Self {
+    /// `VERILIB_STORAGE`, if set to a known backend.
+    pub fn from_env() -> Option<Self> {
         std::env::var("VERILIB_STORAGE")
             .ok()
             .and_then(|s| match s.to_lowercase().as_str() {
                 "keyring" => Some(StorageType::Keyring),
                 "file" => Some(StorageType::File),
+                "ssh-agent" => Some(StorageType::SshAgent),
                 "auto" => Some(StorageType::Auto),
                 _ => None,
             })
+    }
+
+    /// `VERILIB_STORAGE`, else the backend remembered in the user config,
+    /// else `Auto`.
+    pub fn configured() -> Self {
+        Self::from_env()
+            .or_else(Self::remembered)
             .unwrap_or(StorageType::Auto)
     }
 
+    /// The backend remembered in the user config after the keyring was
+    /// unavailable.
+    pub fn remembered() -> Option<Self> {
+        UserConfig::load().ok()?.credential_storage
+    }
+
     pub fn should_use_file_storage(self) -> bool {
         match self {
             StorageType::File => true,
-            StorageType::Keyring => false,
+            StorageType::Keyring | StorageType::SshAgent => false,
             StorageType::Auto => cfg!(target_os = "linux"),
         }
     }
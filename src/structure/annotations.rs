@@ -0,0 +1,88 @@
+//! Reviewer notes on atoms.
+//!
+//! Free-form notes ("spec too weak", "needs loop invariant") are stored as an
+//! `annotations` array of strings in `.meta.verilib` files, beside the spec
+//! rather than in it. `api annotate` edits them; the `specify --interactive`
+//! preview and `export` show them.
+
+use crate::walk;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The annotations of every meta file under `verilib_dir`, keyed by the
+/// meta file's `code_name`. Meta files without notes, or that can't be read,
+/// are left out.
+pub fn load(verilib_dir: &Path) -> HashMap<String, Vec<String>> {
+    let mut annotations: HashMap<String, Vec<String>> = HashMap::new();
+    if !verilib_dir.exists() {
+        return annotations;
+    }
+    for path in walk::verilib_files(verilib_dir) {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        if !file_name.ends_with(".meta.verilib") {
+            continue;
+        }
+        let Some(meta) = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        else {
+            continue;
+        };
+        let Some(code_name) = meta.get("code_name").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let notes = meta
+            .get("annotations")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|note| note.as_str().map(str::to_string));
+        annotations
+            .entry(code_name.to_string())
+            .or_default()
+            .extend(notes);
+    }
+    annotations.retain(|_, notes| !notes.is_empty());
+    annotations
+}
+
+/// `notes` as a bulleted list under a "Notes" heading.
+pub fn format(notes: &[String]) -> String {
+    let mut text = String::from("Notes:");
+    for note in notes {
+        text.push_str("\n- ");
+        text.push_str(note);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn test_load_keys_notes_by_code_name() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        let write = |name: &str, meta: serde_json::Value| {
+            fs::write(src.join(name), meta.to_string()).unwrap();
+        };
+        write(
+            "[0] - a.rs.meta.verilib",
+            json!({"code_name": "probe:a", "annotations": ["spec too weak"]}),
+        );
+        write("[1] - b.rs.meta.verilib", json!({"code_name": "probe:b"}));
+        write(
+            "[2] - c.rs.atom.verilib",
+            json!({"code_name": "probe:c", "annotations": ["ignored"]}),
+        );
+
+        let annotations = load(dir.path());
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations["probe:a"], ["spec too weak"]);
+        assert_eq!(format(&annotations["probe:a"]), "Notes:\n- spec too weak");
+    }
+}
@@ -0,0 +1,351 @@
+//! Storage backends for spec certs.
+//!
+//! The `directory` store keeps one percent-encoded JSON file per function in
+//! `.verilib/certs/specs/`. The `jsonl` store appends one line per cert to
+//! `.verilib/certs/specs.jsonl`, which suits filesystems that are slow with
+//! many small files and keeps git diffs to one line per cert. A later line for
+//! a code-name supersedes earlier ones; removing certs rewrites the file
+//! without their lines.
+
+use super::certs::{decode_name, encode_name, Cert};
+use crate::interrupt::write_atomic;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Configured backend (`cert-store` in config.json).
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum, JsonSchema,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum CertStoreKind {
+    /// One file per cert in `certs/specs/`.
+    #[default]
+    Directory,
+    /// All certs in `certs/specs.jsonl`, one per line.
+    Jsonl,
+}
+
+impl CertStoreKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CertStoreKind::Directory => "directory",
+            CertStoreKind::Jsonl => "jsonl",
+        }
+    }
+
+    /// The store of this kind for the certs directory `certs_dir`.
+    pub fn open(self, certs_dir: PathBuf) -> Box<dyn CertStore> {
+        match self {
+            CertStoreKind::Directory => Box::new(DirectoryStore { dir: certs_dir }),
+            CertStoreKind::Jsonl => Box::new(JsonlStore {
+                path: certs_dir.with_extension("jsonl"),
+            }),
+        }
+    }
+}
+
+/// Where a stored cert lives, for removing it.
+#[derive(Debug)]
+enum Slot {
+    File(PathBuf),
+    /// 0-based line of the jsonl file.
+    Line(usize),
+}
+
+/// A cert as found in a store.
+#[derive(Debug)]
+pub struct StoredCert {
+    /// A cert file name, or `specs.jsonl:<line>`.
+    pub location: String,
+    /// `None` if the code-name can't be read.
+    pub code_name: Option<String>,
+    /// `None` if the cert can't be parsed.
+    pub cert: Option<Cert>,
+    slot: Slot,
+}
+
+/// One line of the jsonl store.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct CertRecord {
+    pub code_name: String,
+    #[serde(flatten)]
+    pub cert: Cert,
+}
+
+/// Reads and writes spec certs.
+pub trait CertStore {
+    /// The directory or file holding the certs.
+    fn path(&self) -> &Path;
+
+    /// Every stored cert, including ones that can't be read.
+    fn entries(&self) -> Result<Vec<StoredCert>>;
+
+    /// Store `cert` for `code_name`, replacing any earlier cert.
+    fn put(&self, code_name: &str, cert: &Cert) -> Result<()>;
+
+    /// Remove `entries`, as returned by [`CertStore::entries`].
+    fn remove(&self, entries: &[&StoredCert]) -> Result<()>;
+
+    /// Code-names that have a cert.
+    fn names(&self) -> Result<HashSet<String>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .filter_map(|entry| entry.code_name)
+            .collect())
+    }
+
+    /// Remove the certs of `code_names`.
+    fn remove_names(&self, code_names: &HashSet<&str>) -> Result<()> {
+        let entries = self.entries()?;
+        let doomed: Vec<&StoredCert> = entries
+            .iter()
+            .filter(|entry| {
+                entry
+                    .code_name
+                    .as_deref()
+                    .is_some_and(|name| code_names.contains(name))
+            })
+            .collect();
+        self.remove(&doomed)
+    }
+}
+
+/// One JSON file per cert, named by the percent-encoded code-name.
+pub struct DirectoryStore {
+    dir: PathBuf,
+}
+
+impl DirectoryStore {
+    fn cert_path(&self, code_name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", encode_name(code_name)))
+    }
+}
+
+impl CertStore for DirectoryStore {
+    fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entries(&self) -> Result<Vec<StoredCert>> {
+        let mut entries = Vec::new();
+        if !self.dir.exists() {
+            return Ok(entries);
+        }
+        for entry in std::fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read {}", self.dir.display()))?
+        {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            entries.push(StoredCert {
+                location: path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+                code_name: path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .and_then(decode_name),
+                cert: Cert::load(&path).ok(),
+                slot: Slot::File(path),
+            });
+        }
+        entries.sort_by(|a, b| a.location.cmp(&b.location));
+        Ok(entries)
+    }
+
+    fn put(&self, code_name: &str, cert: &Cert) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let path = self.cert_path(code_name);
+        write_atomic(&path, serde_json::to_string_pretty(cert)?)
+    }
+
+    fn remove(&self, entries: &[&StoredCert]) -> Result<()> {
+        for entry in entries {
+            if let Slot::File(path) = &entry.slot {
+                std::fs::remove_file(path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// All certs in one append-only JSON Lines file of [`CertRecord`]s.
+pub struct JsonlStore {
+    path: PathBuf,
+}
+
+impl JsonlStore {
+    fn lines(&self) -> Result<Vec<String>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        Ok(content.lines().map(str::to_string).collect())
+    }
+}
+
+/// The code-name of a jsonl line, even if the rest of the record is invalid.
+fn record_name(line: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(line).ok()?;
+    Some(value.get("code-name")?.as_str()?.to_string())
+}
+
+impl CertStore for JsonlStore {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn entries(&self) -> Result<Vec<StoredCert>> {
+        let file_name = self
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let mut entries: Vec<StoredCert> = Vec::new();
+        let mut latest: HashMap<String, usize> = HashMap::new();
+        for (index, line) in self.lines()?.iter().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let code_name = record_name(line);
+            let entry = StoredCert {
+                location: format!("{}:{}", file_name, index + 1),
+                cert: serde_json::from_str::<CertRecord>(line)
+                    .ok()
+                    .map(|record| record.cert),
+                code_name: code_name.clone(),
+                slot: Slot::Line(index),
+            };
+            match code_name
+                .as_ref()
+                .and_then(|name| latest.get(name).copied())
+            {
+                Some(superseded) => entries[superseded] = entry,
+                None => {
+                    if let Some(name) = code_name {
+                        latest.insert(name, entries.len());
+                    }
+                    entries.push(entry);
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    fn put(&self, code_name: &str, cert: &Cert) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let record = CertRecord {
+            code_name: code_name.to_string(),
+            cert: cert.clone(),
+        };
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(&record)?)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    fn remove(&self, entries: &[&StoredCert]) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        // Earlier lines a removed cert superseded go with it.
+        let names: HashSet<&str> = entries
+            .iter()
+            .filter_map(|entry| entry.code_name.as_deref())
+            .collect();
+        let lines: HashSet<usize> = entries
+            .iter()
+            .filter_map(|entry| match entry.slot {
+                Slot::Line(index) => Some(index),
+                Slot::File(_) => None,
+            })
+            .collect();
+
+        let kept: Vec<String> = self
+            .lines()?
+            .into_iter()
+            .enumerate()
+            .filter(|(index, line)| {
+                !lines.contains(index)
+                    && record_name(line).is_none_or(|name| !names.contains(name.as_str()))
+            })
+            .map(|(_, line)| line)
+            .collect();
+        if kept.iter().all(|line| line.trim().is_empty()) {
+            return std::fs::remove_file(&self.path)
+                .with_context(|| format!("Failed to remove {}", self.path.display()));
+        }
+        write_atomic(&self.path, kept.join("\n") + "\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn cert(spec_hash: &str) -> Cert {
+        Cert {
+            timestamp: Utc::now(),
+            spec_hash: Some(spec_hash.to_string()),
+            code_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_jsonl_later_lines_supersede_and_remove_drops_all() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = CertStoreKind::Jsonl.open(dir.path().join("specs"));
+        store.put("probe:a()", &cert("old")).unwrap();
+        store.put("probe:b()", &cert("b")).unwrap();
+        store.put("probe:a()", &cert("new")).unwrap();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(store.path())
+            .unwrap()
+            .write_all(b"not json\n")
+            .unwrap();
+
+        let entries = store.entries().unwrap();
+        assert_eq!(entries.len(), 3);
+        let a = &entries[0];
+        assert_eq!(a.code_name.as_deref(), Some("probe:a()"));
+        assert_eq!(a.location, "specs.jsonl:3");
+        assert_eq!(a.cert.as_ref().unwrap().spec_hash.as_deref(), Some("new"));
+        assert!(entries[2].code_name.is_none());
+
+        store.remove(&[a, &entries[2]]).unwrap();
+        let names = store.names().unwrap();
+        assert_eq!(names, HashSet::from(["probe:b()".to_string()]));
+        assert_eq!(
+            std::fs::read_to_string(store.path())
+                .unwrap()
+                .lines()
+                .count(),
+            1
+        );
+
+        store.remove_names(&HashSet::from(["probe:b()"])).unwrap();
+        assert!(!store.path().exists());
+    }
+}
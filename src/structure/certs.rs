@@ -2,67 +2,129 @@
 //!
 //! Handles creation and lookup of specification certificates.
 
-use anyhow::Result;
+use super::schema::{LineRange, StubEntry};
+use crate::config::{source_file, SourceRoot};
+use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-use std::path::{Path, PathBuf};
+use sha2::{Digest, Sha256};
+use std::path::Path;
 
 /// Certificate data stored in cert files.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
 pub struct Cert {
     pub timestamp: DateTime<Utc>,
+    /// SHA-256 of the certified spec's source lines. Absent in certs created
+    /// before it was recorded, or when the source could not be read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spec_hash: Option<String>,
+    /// SHA-256 of the function's code lines when the spec was certified, for
+    /// `drift`. Absent in older certs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_hash: Option<String>,
 }
 
-/// Encode an identifier for use as a filename.
-///
-/// Uses URL percent-encoding to replace special characters like '/', ':', '#', etc.
-pub fn encode_name(name: &str) -> String {
-    utf8_percent_encode(name, NON_ALPHANUMERIC).to_string()
+impl Cert {
+    /// A cert issued now.
+    pub fn new(spec_hash: Option<String>, code_hash: Option<String>) -> Self {
+        Cert {
+            timestamp: Utc::now(),
+            spec_hash,
+            code_hash,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
 }
 
-/// Decode a filename back to an identifier.
-pub fn decode_name(encoded: &str) -> String {
-    percent_decode_str(encoded).decode_utf8_lossy().to_string()
+/// Reads the spec and code lines a stub's spec-text and code-text point at,
+/// to fingerprint them.
+pub struct SpecSource<'a> {
+    pub project_root: &'a Path,
+    pub source_roots: &'a [SourceRoot],
 }
 
-/// Get the set of identifiers that already have certs.
-pub fn get_existing_certs(certs_dir: &Path) -> Result<HashSet<String>> {
-    let mut existing = HashSet::new();
+impl SpecSource<'_> {
+    /// SHA-256 of the stub's spec lines, or `None` if it has no spec or the
+    /// source file can't be read.
+    pub fn hash(&self, stub: &StubEntry) -> Option<String> {
+        Some(hash_spec(&self.text(stub)?))
+    }
 
-    if !certs_dir.exists() {
-        return Ok(existing);
+    /// The stub's spec lines, or `None` if it has no spec or the source file
+    /// can't be read.
+    pub fn text(&self, stub: &StubEntry) -> Option<String> {
+        self.lines(stub, stub.spec_text.as_ref()?)
     }
 
-    for entry in std::fs::read_dir(certs_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        if path.extension().is_some_and(|ext| ext == "json") {
-            if let Some(stem) = path.file_stem() {
-                let encoded_name = stem.to_string_lossy();
-                let name = decode_name(&encoded_name);
-                existing.insert(name);
-            }
-        }
+    /// SHA-256 of the stub's code lines (signature, spec and body), or `None`
+    /// if it has no code-text or the source file can't be read.
+    pub fn code_hash(&self, stub: &StubEntry) -> Option<String> {
+        Some(hash_spec(&self.lines(stub, stub.code_text.as_ref()?)?))
     }
 
-    Ok(existing)
+    fn lines(&self, stub: &StubEntry, range: &LineRange) -> Option<String> {
+        let code_path = stub.code_path.as_deref()?;
+        let source =
+            std::fs::read_to_string(source_file(self.project_root, self.source_roots, code_path))
+                .ok()?;
+        Some(spec_lines(&source, range))
+    }
 }
 
-/// Create a cert file for a function.
-pub fn create_cert(certs_dir: &Path, name: &str) -> Result<PathBuf> {
-    std::fs::create_dir_all(certs_dir)?;
+/// The 1-based, inclusive `range` of lines of `source`.
+fn spec_lines(source: &str, range: &LineRange) -> String {
+    let start = range.lines_start.max(1) as usize;
+    let end = range.lines_end as usize;
+    source
+        .lines()
+        .skip(start - 1)
+        .take((end + 1).saturating_sub(start))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
 
-    let encoded_name = encode_name(name);
-    let cert_path = certs_dir.join(format!("{}.json", encoded_name));
+fn hash_spec(spec: &str) -> String {
+    format!("{:x}", Sha256::digest(spec.as_bytes()))
+}
 
-    let cert = Cert {
-        timestamp: Utc::now(),
-    };
+/// Encode an identifier for use as a filename.
+///
+/// Uses URL percent-encoding to replace special characters like '/', ':', '#', etc.
+pub fn encode_name(name: &str) -> String {
+    utf8_percent_encode(name, NON_ALPHANUMERIC).to_string()
+}
 
-    let content = serde_json::to_string_pretty(&cert)?;
-    std::fs::write(&cert_path, content)?;
+/// Decode a filename back to an identifier, if it percent-decodes to UTF-8
+/// text. Older certs leave some characters (such as `_`) unescaped, so the
+/// encoding is not required to match `encode_name`.
+pub fn decode_name(encoded: &str) -> Option<String> {
+    let name = percent_decode_str(encoded).decode_utf8().ok()?;
+    (!name.is_empty()).then(|| name.to_string())
+}
 
-    Ok(cert_path)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_name() {
+        assert_eq!(
+            decode_name("probe%3Ac%2Ff%28%29").as_deref(),
+            Some("probe:c/f()")
+        );
+        assert_eq!(
+            decode_name("probe%3Amod%2Ffunc_a%28%29").as_deref(),
+            Some("probe:mod/func_a()")
+        );
+        assert_eq!(decode_name("%FF"), None);
+    }
 }
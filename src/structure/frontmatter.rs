@@ -1,52 +1,141 @@
-//! YAML frontmatter parsing and writing for markdown files.
+//! Frontmatter parsing and writing for markdown files.
+//!
+//! Frontmatter is YAML between `---` lines or TOML between `+++` lines. The
+//! format is detected on read, and [`update`] keeps a file's format; new
+//! files are written in the configured `frontmatter-format`.
 
 use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::path::Path;
 
-/// Parse YAML frontmatter from a markdown file.
-pub fn parse(path: &Path) -> Result<HashMap<String, Value>> {
-    let content = std::fs::read_to_string(path)?;
-    let mut lines = content.lines();
+/// Configured format of new frontmatter (`frontmatter-format` in config.json).
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum, JsonSchema,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum FrontmatterFormat {
+    /// YAML between `---` lines.
+    #[default]
+    Yaml,
+    /// TOML between `+++` lines.
+    Toml,
+}
 
-    // Check for opening ---
-    match lines.next() {
-        Some("---") => {}
-        _ => bail!("No frontmatter found"),
+impl FrontmatterFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FrontmatterFormat::Yaml => "yaml",
+            FrontmatterFormat::Toml => "toml",
+        }
     }
 
-    // Collect frontmatter lines until closing ---
-    let mut yaml_lines = Vec::new();
+    fn delimiter(self) -> &'static str {
+        match self {
+            FrontmatterFormat::Yaml => "---",
+            FrontmatterFormat::Toml => "+++",
+        }
+    }
+}
+
+/// A markdown file split into its frontmatter format, frontmatter text, and
+/// body (without the blank lines after the frontmatter).
+fn split(content: &str) -> Result<(FrontmatterFormat, String, Option<&str>)> {
+    let mut lines = content.split_inclusive('\n');
+    let format = match lines.next().map(|line| line.trim_end_matches(['\r', '\n'])) {
+        Some("---") => FrontmatterFormat::Yaml,
+        Some("+++") => FrontmatterFormat::Toml,
+        _ => bail!("No frontmatter found"),
+    };
+
+    let mut frontmatter = Vec::new();
+    let mut rest = None;
+    let mut offset = content.split_inclusive('\n').next().map_or(0, str::len);
     for line in lines {
-        if line == "---" {
+        offset += line.len();
+        if line.trim_end_matches(['\r', '\n']) == format.delimiter() {
+            rest = Some(&content[offset..]);
             break;
         }
-        yaml_lines.push(line);
+        frontmatter.push(line.trim_end_matches(['\r', '\n']));
     }
 
-    let yaml_content = yaml_lines.join("\n");
-    let frontmatter: HashMap<String, Value> =
-        serde_yaml::from_str(&yaml_content).context("Failed to parse YAML frontmatter")?;
+    let body = rest
+        .map(|body| body.trim_start_matches(['\r', '\n']).trim_end_matches('\n'))
+        .filter(|body| !body.is_empty());
+    Ok((format, frontmatter.join("\n"), body))
+}
+
+/// Parse the frontmatter of a markdown file, in either format.
+pub fn parse(path: &Path) -> Result<HashMap<String, Value>> {
+    let content = std::fs::read_to_string(path)?;
+    let (format, frontmatter, _) = split(&content)?;
+    parse_text(format, &frontmatter)
+}
 
-    Ok(frontmatter)
+fn parse_text(format: FrontmatterFormat, text: &str) -> Result<HashMap<String, Value>> {
+    match format {
+        FrontmatterFormat::Yaml => {
+            serde_yaml::from_str(text).context("Failed to parse YAML frontmatter")
+        }
+        FrontmatterFormat::Toml => {
+            let table: toml::Table = text.parse().context("Failed to parse TOML frontmatter")?;
+            Ok(table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_json(value)))
+                .collect())
+        }
+    }
 }
 
-/// Write a markdown file with YAML frontmatter.
-pub fn write(path: &Path, metadata: &HashMap<String, Value>, body: Option<&str>) -> Result<()> {
+/// Datetimes become strings, as YAML frontmatter would have them.
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => Value::from(f),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(items) => Value::Array(items.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// Write a markdown file with frontmatter in `format`.
+pub fn write(
+    path: &Path,
+    metadata: &HashMap<String, Value>,
+    body: Option<&str>,
+    format: FrontmatterFormat,
+) -> Result<()> {
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent)?;
     }
 
     let mut content = String::new();
-    content.push_str("---\n");
+    content.push_str(format.delimiter());
+    content.push('\n');
 
-    for (key, value) in metadata {
-        let formatted = format_value(value)?;
-        content.push_str(&format!("{}: {}\n", key, formatted));
+    match format {
+        FrontmatterFormat::Yaml => {
+            for (key, value) in metadata {
+                let formatted = format_value(value)?;
+                content.push_str(&format!("{}: {}\n", key, formatted));
+            }
+        }
+        FrontmatterFormat::Toml => content.push_str(&format_toml(metadata)?),
     }
 
-    content.push_str("---\n");
+    content.push_str(format.delimiter());
+    content.push('\n');
     content.push('\n');
 
     if let Some(body_content) = body {
@@ -54,8 +143,55 @@ pub fn write(path: &Path, metadata: &HashMap<String, Value>, body: Option<&str>)
         content.push('\n');
     }
 
-    std::fs::write(path, content)?;
-    Ok(())
+    crate::interrupt::write_atomic(path, content)
+}
+
+/// Rewrite the frontmatter of a markdown file with `edit`, keeping its body
+/// and format.
+pub fn update(path: &Path, edit: impl FnOnce(&mut HashMap<String, Value>)) -> Result<()> {
+    let content = std::fs::read_to_string(path)?;
+    let (format, frontmatter, body) = split(&content)?;
+    let mut metadata = parse_text(format, &frontmatter)?;
+
+    edit(&mut metadata);
+    write(path, &metadata, body, format)
+}
+
+/// The frontmatter format of a markdown file.
+pub fn detect(path: &Path) -> Result<FrontmatterFormat> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(split(&content)?.0)
+}
+
+/// Rewrite a markdown file's frontmatter in `format`. Returns whether it
+/// changed; files already in `format` are left alone.
+pub fn convert(path: &Path, format: FrontmatterFormat) -> Result<bool> {
+    let content = std::fs::read_to_string(path)?;
+    let (from, frontmatter, body) = split(&content)?;
+    if from == format {
+        return Ok(false);
+    }
+    let metadata = parse_text(from, &frontmatter)?;
+    write(path, &metadata, body, format)?;
+    Ok(true)
+}
+
+/// Format metadata as TOML, with keys sorted. TOML has no null, so null
+/// values are left out, which reads back the same as absent keys.
+fn format_toml(metadata: &HashMap<String, Value>) -> Result<String> {
+    let mut table = toml::Table::new();
+    for (key, value) in metadata {
+        if value.is_null() {
+            continue;
+        }
+        if value.is_object() {
+            bail!("Nested objects are not supported in metadata");
+        }
+        let value = toml::Value::try_from(value)
+            .with_context(|| format!("Cannot write '{}' as TOML", key))?;
+        table.insert(key.clone(), value);
+    }
+    Ok(toml::to_string(&table)?)
 }
 
 /// Format a JSON value as a YAML scalar.
@@ -100,3 +236,60 @@ fn format_value(value: &Value) -> Result<String> {
         Value::Object(_) => bail!("Nested objects are not supported in metadata"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_formats_round_trip_and_keep_the_body() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("f.md");
+        let metadata = HashMap::from([
+            ("code-name".to_string(), json!("probe:c/f()")),
+            ("code-line".to_string(), json!(12)),
+            ("dependencies".to_string(), json!(["probe:c/g()"])),
+            ("owner".to_string(), Value::Null),
+        ]);
+        write(
+            &path,
+            &metadata,
+            Some("Body\n---\nafter a rule"),
+            FrontmatterFormat::Yaml,
+        )
+        .unwrap();
+
+        assert!(convert(&path, FrontmatterFormat::Toml).unwrap());
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(
+            content.starts_with("+++\ncode-line = 12\ncode-name = \"probe:c/f()\"\n"),
+            "{}",
+            content
+        );
+        assert!(
+            content.ends_with("+++\n\nBody\n---\nafter a rule\n"),
+            "{}",
+            content
+        );
+        assert!(!convert(&path, FrontmatterFormat::Toml).unwrap());
+        assert_eq!(detect(&path).unwrap(), FrontmatterFormat::Toml);
+
+        update(&path, |m| {
+            m.insert("owner".to_string(), json!("alice"));
+        })
+        .unwrap();
+        let parsed = parse(&path).unwrap();
+        assert_eq!(parsed["code-name"], "probe:c/f()");
+        assert_eq!(parsed["code-line"], 12);
+        assert_eq!(parsed["owner"], "alice");
+        assert!(std::fs::read_to_string(&path).unwrap().starts_with("+++\n"));
+
+        assert!(convert(&path, FrontmatterFormat::Yaml).unwrap());
+        assert_eq!(parse(&path).unwrap(), parsed);
+        assert_eq!(
+            split(&std::fs::read_to_string(&path).unwrap()).unwrap().2,
+            Some("Body\n---\nafter a rule")
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//! Naming schemes for structure .md files.
+//!
+//! The layout decides where `create` and `migrate-layout` put each function's
+//! file; `atomize` warns about files that don't follow it. Everything else keys
+//! stubs by the file's path relative to the structure root and reads
+//! code-path/code-line from the frontmatter, so specify and verify work with
+//! any layout.
+
+use super::schema::StubEntry;
+use clap::ValueEnum;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Hex digits of the code-path hash used by the flat layout.
+const FLAT_HASH_LEN: usize = 8;
+
+/// Decides the structure file path of a function.
+pub trait StructureLayout {
+    /// Path of the function's .md file, relative to the structure root.
+    fn file_path(&self, code_path: &str, function_name: &str) -> String;
+
+    /// Recover the function name from a file path produced by `file_path`.
+    fn function_name(&self, file_path: &str) -> Option<String> {
+        file_stem(file_path).map(str::to_string)
+    }
+}
+
+/// Configured layout (`structure-layout` in config.json).
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum, JsonSchema,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum LayoutKind {
+    /// `<code-path>/<function>.md`, mirroring the source tree.
+    #[default]
+    Path,
+    /// `<module>/<function>.md`, one folder per module (e.g. `parser.lexer`).
+    Module,
+    /// `<function>-<hash>.md`, all files directly under the structure root.
+    Flat,
+}
+
+impl LayoutKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LayoutKind::Path => "path",
+            LayoutKind::Module => "module",
+            LayoutKind::Flat => "flat",
+        }
+    }
+
+    pub fn layout(self) -> &'static dyn StructureLayout {
+        match self {
+            LayoutKind::Path => &PathMirrorLayout,
+            LayoutKind::Module => &ModuleLayout,
+            LayoutKind::Flat => &FlatLayout,
+        }
+    }
+}
+
+/// The original layout: the source file's path becomes a directory.
+pub struct PathMirrorLayout;
+
+impl StructureLayout for PathMirrorLayout {
+    fn file_path(&self, code_path: &str, function_name: &str) -> String {
+        format!("{}/{}.md", code_path, function_name)
+    }
+}
+
+/// One folder per Rust module, named by the dotted module path.
+pub struct ModuleLayout;
+
+impl StructureLayout for ModuleLayout {
+    fn file_path(&self, code_path: &str, function_name: &str) -> String {
+        format!("{}/{}.md", module_name(code_path), function_name)
+    }
+}
+
+/// No directories; a short hash of the code-path keeps same-named functions apart.
+pub struct FlatLayout;
+
+impl StructureLayout for FlatLayout {
+    fn file_path(&self, code_path: &str, function_name: &str) -> String {
+        let hash = format!("{:x}", Sha256::digest(code_path.as_bytes()));
+        format!("{}-{}.md", function_name, &hash[..FLAT_HASH_LEN])
+    }
+
+    fn function_name(&self, file_path: &str) -> Option<String> {
+        let stem = file_stem(file_path)?;
+        match stem.rsplit_once('-') {
+            Some((name, hash))
+                if hash.len() == FLAT_HASH_LEN && hash.chars().all(|c| c.is_ascii_hexdigit()) =>
+            {
+                Some(name.to_string())
+            }
+            _ => Some(stem.to_string()),
+        }
+    }
+}
+
+/// Where each stub's file belongs under `to`, for stubs keyed by paths from `from`.
+/// Returns `(current, target)` pairs, sorted, for files that are not already in place;
+/// stubs without a code-path or a recognizable file name are left alone.
+pub fn relocations(
+    stubs: &HashMap<String, StubEntry>,
+    from: &dyn StructureLayout,
+    to: &dyn StructureLayout,
+) -> Vec<(String, String)> {
+    let mut moves: Vec<(String, String)> = stubs
+        .iter()
+        .filter_map(|(file_path, stub)| {
+            let code_path = stub.code_path.as_deref()?;
+            let name = from.function_name(file_path)?;
+            let target = to.file_path(code_path, &name);
+            (target != *file_path).then(|| (file_path.clone(), target))
+        })
+        .collect();
+    moves.sort();
+    moves
+}
+
+/// Dotted module path of a source file: `src/parser/lexer.rs` becomes
+/// `parser.lexer`, and crate roots (`src/lib.rs`, `src/main.rs`) become `crate`.
+fn module_name(code_path: &str) -> String {
+    let mut segments: Vec<&str> = code_path
+        .trim_end_matches(".rs")
+        .split('/')
+        .filter(|s| !s.is_empty() && *s != "." && *s != "src")
+        .collect();
+    if matches!(segments.last(), Some(&"mod") | Some(&"lib") | Some(&"main")) {
+        segments.pop();
+    }
+    if segments.is_empty() {
+        "crate".to_string()
+    } else {
+        segments.join(".")
+    }
+}
+
+fn file_stem(file_path: &str) -> Option<&str> {
+    file_path.rsplit('/').next()?.strip_suffix(".md")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layouts_round_trip_function_names() {
+        for kind in [LayoutKind::Path, LayoutKind::Module, LayoutKind::Flat] {
+            let layout = kind.layout();
+            let path = layout.file_path("src/parser/lexer.rs", "Lexer.next_token");
+            assert_eq!(
+                layout.function_name(&path).as_deref(),
+                Some("Lexer.next_token"),
+                "{:?}: {}",
+                kind,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_module_and_flat_paths() {
+        assert_eq!(
+            ModuleLayout.file_path("src/parser/lexer.rs", "lex"),
+            "parser.lexer/lex.md"
+        );
+        assert_eq!(ModuleLayout.file_path("src/lib.rs", "run"), "crate/run.md");
+        assert_eq!(
+            ModuleLayout.file_path("src/parser/mod.rs", "parse"),
+            "parser/parse.md"
+        );
+
+        let a = FlatLayout.file_path("src/a.rs", "new");
+        let b = FlatLayout.file_path("src/b.rs", "new");
+        assert_ne!(a, b);
+        assert!(!a.contains('/'));
+    }
+
+    #[test]
+    fn test_relocations_skip_files_already_in_place() {
+        let stubs: HashMap<String, StubEntry> =
+            [("src/a.rs/f.md", "src/a.rs"), ("a/g.md", "src/a.rs")]
+                .into_iter()
+                .map(|(key, path)| {
+                    let stub = StubEntry {
+                        code_path: Some(path.to_string()),
+                        ..Default::default()
+                    };
+                    (key.to_string(), stub)
+                })
+                .collect();
+
+        assert_eq!(
+            relocations(&stubs, &PathMirrorLayout, &ModuleLayout),
+            vec![("src/a.rs/f.md".to_string(), "a/f.md".to_string())]
+        );
+    }
+}
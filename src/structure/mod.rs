@@ -1,15 +1,35 @@
 //! Structure management utilities for verilib-cli.
 //!
 //! This module provides utilities for managing verification structure files,
-//! including configuration, YAML frontmatter, certificates, and probe-verus integration.
+//! including configuration, frontmatter, certificates, and probe-verus integration.
 
+pub mod annotations;
+pub mod cert_store;
 pub mod certs;
 pub mod frontmatter;
+pub mod layout;
+pub mod owners;
+pub mod probe_output;
+pub mod proof_cache;
+pub mod review;
+pub mod schema;
+pub mod scip;
 pub mod utils;
+pub mod verified;
 
 pub use crate::constants::{ATOMIZE_INTERMEDIATE_FILES, VERIFY_INTERMEDIATE_FILES};
 pub use crate::executor::{CommandConfig, ExecutionMode, ExternalTool};
-pub use certs::{create_cert, get_existing_certs};
-pub use frontmatter::{parse as parse_frontmatter, write as write_frontmatter};
+pub use cert_store::{CertStore, CertStoreKind};
+pub use frontmatter::{
+    parse as parse_frontmatter, update as update_frontmatter, write as write_frontmatter,
+    FrontmatterFormat,
+};
+pub use layout::{LayoutKind, StructureLayout};
+pub use owners::{assign_owners, is_owned_by, OwnerRule};
+pub use review::ReviewStatus;
+pub use schema::{Atom, ProofResult, SpecEntry, StubEntry};
 pub use utils::create_gitignore;
-pub use utils::{cleanup_intermediate_files, display_menu, get_display_name, run_command};
+pub use utils::{
+    cleanup_intermediate_files, display_menu, get_display_name, init_keep_intermediates,
+    read_structure_files, run_command,
+};
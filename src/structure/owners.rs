@@ -0,0 +1,114 @@
+//! Ownership mapping from code paths to people or teams.
+//!
+//! Rules are read from the `owners` list in `.verilib/config.json`. Like a
+//! CODEOWNERS file, the last matching rule wins.
+
+use super::schema::StubEntry;
+use glob::Pattern;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A single `glob -> owner` rule.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct OwnerRule {
+    pub pattern: String,
+    pub owner: String,
+}
+
+/// Resolve the owner of a code-path. Invalid patterns never match.
+pub fn resolve_owner<'a>(rules: &'a [OwnerRule], code_path: &str) -> Option<&'a str> {
+    rules
+        .iter()
+        .rev()
+        .find(|rule| {
+            Pattern::new(&rule.pattern)
+                .map(|p| p.matches(code_path))
+                .unwrap_or(false)
+        })
+        .map(|rule| rule.owner.as_str())
+}
+
+/// Attach the resolved owner to every stub that has a code-path and no owner
+/// of its own (set in its structure file, e.g. by `convert --from-csv`).
+/// Returns the number of stubs that were assigned an owner.
+pub fn assign_owners(stubs: &mut HashMap<String, StubEntry>, rules: &[OwnerRule]) -> usize {
+    if rules.is_empty() {
+        return 0;
+    }
+
+    let mut assigned = 0;
+    for stub in stubs.values_mut().filter(|stub| stub.owner.is_none()) {
+        let owner = stub
+            .code_path
+            .as_deref()
+            .and_then(|p| resolve_owner(rules, p));
+
+        if let Some(owner) = owner {
+            stub.owner = Some(owner.to_string());
+            assigned += 1;
+        }
+    }
+    assigned
+}
+
+/// Whether a stub belongs to the given owner.
+pub fn is_owned_by(stub: &StubEntry, owner: &str) -> bool {
+    stub.owner.as_deref() == Some(owner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, owner: &str) -> OwnerRule {
+        OwnerRule {
+            pattern: pattern.to_string(),
+            owner: owner.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_last_matching_rule_wins() {
+        let rules = vec![
+            rule("src/**", "team-core"),
+            rule("src/crypto/*.rs", "alice"),
+        ];
+        assert_eq!(resolve_owner(&rules, "src/crypto/field.rs"), Some("alice"));
+        assert_eq!(resolve_owner(&rules, "src/lib.rs"), Some("team-core"));
+        assert_eq!(resolve_owner(&rules, "tests/it.rs"), None);
+    }
+
+    #[test]
+    fn test_assign_owners_skips_stubs_without_code_path() {
+        let mut stubs = HashMap::new();
+        stubs.insert(
+            "a.md".to_string(),
+            StubEntry {
+                code_path: Some("src/a.rs".to_string()),
+                ..Default::default()
+            },
+        );
+        stubs.insert("b.md".to_string(), StubEntry::default());
+
+        let assigned = assign_owners(&mut stubs, &[rule("src/*.rs", "bob")]);
+        assert_eq!(assigned, 1);
+        assert!(is_owned_by(&stubs["a.md"], "bob"));
+        assert!(stubs["b.md"].owner.is_none());
+    }
+
+    #[test]
+    fn test_assign_owners_keeps_explicit_owner() {
+        let mut stubs = HashMap::from([(
+            "a.md".to_string(),
+            StubEntry {
+                code_path: Some("src/a.rs".to_string()),
+                owner: Some("carol".to_string()),
+                ..Default::default()
+            },
+        )]);
+
+        assert_eq!(assign_owners(&mut stubs, &[rule("src/*.rs", "bob")]), 0);
+        assert!(is_owned_by(&stubs["a.md"], "carol"));
+    }
+}
@@ -0,0 +1,44 @@
+//! Adapter for probe-verus output from before 1.0, which spelled entry
+//! fields in snake_case (`display_name`, `code_text.lines_start`).
+
+use serde_json::{Map, Value};
+
+/// Fields whose snake_case spelling marks a legacy file.
+const LEGACY_FIELDS: &[&str] = &[
+    "display_name",
+    "code_module",
+    "code_path",
+    "code_line",
+    "code_text",
+    "spec_text",
+];
+
+/// Whether any entry uses a legacy field name.
+pub(super) fn detect(map: &Map<String, Value>) -> bool {
+    map.values().any(|entry| {
+        entry
+            .as_object()
+            .is_some_and(|fields| LEGACY_FIELDS.iter().any(|name| fields.contains_key(*name)))
+    })
+}
+
+/// Rename every field in every entry from snake_case to kebab-case. The
+/// code-names keying the map are left alone.
+pub(super) fn upgrade(map: Map<String, Value>) -> Map<String, Value> {
+    map.into_iter()
+        .map(|(code_name, entry)| (code_name, kebab_keys(entry)))
+        .collect()
+}
+
+fn kebab_keys(value: Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.replace('_', "-"), kebab_keys(value)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(kebab_keys).collect()),
+        other => other,
+    }
+}
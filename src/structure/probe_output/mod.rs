@@ -0,0 +1,162 @@
+//! Reading probe-verus output (atoms.json, specs.json, proofs.json) across
+//! output versions.
+//!
+//! The layout of a file is detected before any entry is deserialized, and an
+//! adapter converts it to the current one, a bare map from code-name to
+//! kebab-case entry:
+//!
+//! - [`legacy`]: the snake_case keys written before probe-verus 1.0.
+//! - unversioned: the current layout, read as is.
+//! - [`versioned`]: `{"schema-version": ..., "data": {...}}`, as written by
+//!   probe-verus releases that version their output.
+//!
+//! A schema-version outside [`versioned::SUPPORTED_SCHEMA_VERSIONS`] fails up front with
+//! the supported ranges, instead of with a serde error about some entry.
+
+mod legacy;
+mod versioned;
+
+use super::schema::parse_entries;
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+/// The detected layout of a probe-verus output file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputVersion {
+    Legacy,
+    Unversioned,
+    Versioned(String),
+}
+
+impl fmt::Display for OutputVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputVersion::Legacy => write!(f, "legacy (snake_case)"),
+            OutputVersion::Unversioned => write!(f, "unversioned"),
+            OutputVersion::Versioned(version) => write!(f, "schema-version {}", version),
+        }
+    }
+}
+
+/// Read a probe-verus output file into typed entries, whatever its version.
+pub fn load_probe_map<T: DeserializeOwned>(path: &Path) -> Result<HashMap<String, T>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    parse_probe_map(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Rewrite a probe-verus output file with its keys sorted, so that identical
+/// inputs give byte-identical files whatever order probe-verus wrote them in.
+/// The layout, versioned envelope included, is kept as is.
+pub fn canonicalize(path: &Path) -> Result<()> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let value: Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    crate::interrupt::write_atomic(path, serde_json::to_string_pretty(&value)?)
+}
+
+/// Parse probe-verus output; see [`load_probe_map`].
+pub fn parse_probe_map<T: DeserializeOwned>(content: &str) -> Result<HashMap<String, T>> {
+    let (version, entries) = normalize(serde_json::from_str(content)?)?;
+    parse_entries(entries).with_context(|| format!("read as {} probe-verus output", version))
+}
+
+/// Detect the version of `value` and convert it to the current layout.
+fn normalize(value: Value) -> Result<(OutputVersion, Map<String, Value>)> {
+    let Value::Object(map) = value else {
+        bail!("expected a JSON object of entries keyed by code-name");
+    };
+    if let Some(version) = versioned::version(&map)? {
+        let entries = versioned::entries(map, &version)?;
+        return Ok((OutputVersion::Versioned(version), entries));
+    }
+    if legacy::detect(&map) {
+        return Ok((OutputVersion::Legacy, legacy::upgrade(map)));
+    }
+    Ok((OutputVersion::Unversioned, map))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::versioned::SUPPORTED_SCHEMA_VERSIONS;
+    use super::*;
+    use crate::structure::{Atom, ProofResult};
+    use serde_json::json;
+
+    #[test]
+    fn test_all_versions_read_the_same() {
+        let current = json!({
+            "probe:c/f()": {
+                "display-name": "f",
+                "code-path": "src/c.rs",
+                "code-text": { "lines-start": 3, "lines-end": 9 }
+            }
+        });
+        let legacy = json!({
+            "probe:c/f()": {
+                "display_name": "f",
+                "code_path": "src/c.rs",
+                "code_text": { "lines_start": 3, "lines_end": 9 }
+            }
+        });
+        let versioned = json!({
+            "schema-version": "2.1",
+            "tool": { "name": "probe-verus" },
+            "data": current.clone()
+        });
+
+        let expected: HashMap<String, Atom> = parse_probe_map(&current.to_string()).unwrap();
+        assert_eq!(
+            expected["probe:c/f()"]
+                .code_text
+                .as_ref()
+                .unwrap()
+                .lines_end,
+            9
+        );
+        for other in [legacy, versioned] {
+            let atoms: HashMap<String, Atom> = parse_probe_map(&other.to_string()).unwrap();
+            assert_eq!(atoms, expected, "{}", other);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_version_names_supported_ranges() {
+        let err =
+            parse_probe_map::<ProofResult>(r#"{"schema-version": 7, "data": {}}"#).unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(
+            message.contains("unsupported probe-verus output version 7"),
+            "{}",
+            message
+        );
+        assert!(message.contains(SUPPORTED_SCHEMA_VERSIONS), "{}", message);
+    }
+
+    #[test]
+    fn test_canonicalize_sorts_keys_and_keeps_envelope() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("atoms.json");
+        std::fs::write(
+            &path,
+            r#"{"schema-version": "2.1", "data": {"probe:b/g()": {"code-path": "b.rs", "display-name": "g"}, "probe:a/f()": {"display-name": "f"}}}"#,
+        )
+        .unwrap();
+        canonicalize(&path).unwrap();
+        let first = std::fs::read_to_string(&path).unwrap();
+
+        assert!(first.find("\"data\"").unwrap() < first.find("\"schema-version\"").unwrap());
+        assert!(first.find("probe:a/f()").unwrap() < first.find("probe:b/g()").unwrap());
+        assert!(first.find("code-path").unwrap() < first.find("\"g\"").unwrap());
+        let atoms: HashMap<String, Atom> = load_probe_map(&path).unwrap();
+        assert_eq!(atoms.len(), 2);
+
+        canonicalize(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), first);
+    }
+}
@@ -0,0 +1,68 @@
+//! Adapter for versioned probe-verus output:
+//! `{"schema-version": "2.0", "data": {<code-name>: <entry>}, ...}`.
+//! Entries inside `data` use the current field names; the other top-level
+//! fields describe the run and are ignored.
+
+use crate::constants::{PROBE_VERUS_MIN_VERSION, PROBE_VERUS_TESTED_MAX_VERSION};
+use anyhow::{bail, Result};
+use serde_json::{Map, Value};
+
+/// The schema-version majors this build reads, as shown in errors.
+pub const SUPPORTED_SCHEMA_VERSIONS: &str = "1.x and 2.x";
+
+const SUPPORTED_MAJORS: std::ops::RangeInclusive<u64> = 1..=2;
+
+const VERSION_KEY: &str = "schema-version";
+const DATA_KEY: &str = "data";
+
+/// The schema-version of `map`, or `None` for unversioned output. Code-names
+/// never collide with the key, as they start with a scheme such as `probe:`.
+pub(super) fn version(map: &Map<String, Value>) -> Result<Option<String>> {
+    let Some(version) = map.get(VERSION_KEY) else {
+        return Ok(None);
+    };
+    let version = match version {
+        Value::String(text) => text.clone(),
+        Value::Number(number) => number.to_string(),
+        other => bail!(
+            "'{}' must be a string or number, got {}",
+            VERSION_KEY,
+            other
+        ),
+    };
+
+    let major = version
+        .split('.')
+        .next()
+        .and_then(|major| major.trim().parse::<u64>().ok());
+    if !major.is_some_and(|major| SUPPORTED_MAJORS.contains(&major)) {
+        bail!(
+            "unsupported probe-verus output version {}. This verilib-cli reads unversioned \
+             output, legacy snake_case output, and schema-version {}; it is tested with \
+             probe-verus {}, {}. Upgrade verilib-cli to read newer output, or use a \
+             probe-verus release in that range.",
+            version,
+            SUPPORTED_SCHEMA_VERSIONS,
+            PROBE_VERUS_MIN_VERSION,
+            PROBE_VERUS_TESTED_MAX_VERSION
+        );
+    }
+    Ok(Some(version))
+}
+
+/// The entries of a supported versioned file.
+pub(super) fn entries(mut map: Map<String, Value>, version: &str) -> Result<Map<String, Value>> {
+    match map.remove(DATA_KEY) {
+        Some(Value::Object(entries)) => Ok(entries),
+        Some(_) => bail!(
+            "'{}' of schema-version {} output must be an object",
+            DATA_KEY,
+            version
+        ),
+        None => bail!(
+            "schema-version {} output has no '{}' object",
+            version,
+            DATA_KEY
+        ),
+    }
+}
@@ -0,0 +1,275 @@
+//! Persistent cache of verification results, keyed by atom fingerprint.
+//!
+//! An atom's fingerprint hashes its source lines together with the
+//! fingerprints of everything it depends on, so editing a callee invalidates
+//! its callers. Each function's result is stored in
+//! `.verilib/cache/proofs/<key>.json`, where the key also covers the verify
+//! profile, so results from different verifier settings never mix.
+
+use crate::config::{source_file, SourceRoot, VerifyProfile};
+use crate::structure::{Atom, ProofResult};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// One cached result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct CacheEntry {
+    code_name: String,
+    cached_at: DateTime<Utc>,
+    /// Share of the probe-verus run that produced this result.
+    seconds: f64,
+    proof: ProofResult,
+}
+
+/// Hits and misses of one lookup, and the verification time the hits saved.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    /// Recorded time of the hits; zero unless every function hit, since
+    /// probe-verus has to run for the scope otherwise.
+    pub saved: Duration,
+}
+
+/// Cache keys of the atoms in `atoms.json`.
+pub struct ProofCache {
+    dir: PathBuf,
+    /// Code-name -> cache key; atoms whose source can't be read have none.
+    keys: HashMap<String, String>,
+}
+
+impl ProofCache {
+    pub fn new(
+        dir: PathBuf,
+        atoms: &HashMap<String, Atom>,
+        project_root: &Path,
+        source_roots: &[SourceRoot],
+        profile: Option<(&str, &VerifyProfile)>,
+    ) -> Self {
+        let mut profile_key = String::new();
+        if let Some((name, profile)) = profile {
+            profile_key = format!("{}\0{}", name, profile.args.join("\0"));
+        }
+        let keys = fingerprints(atoms, project_root, source_roots)
+            .into_iter()
+            .map(|(name, fingerprint)| {
+                let key = sha256(&format!("{}\0{}\0{}", name, fingerprint, profile_key));
+                (name, key)
+            })
+            .collect();
+        ProofCache { dir, keys }
+    }
+
+    /// The cached results for all of `code_names`, or `None` if any is missing.
+    pub fn lookup(
+        &self,
+        code_names: &[&str],
+    ) -> (Option<HashMap<String, ProofResult>>, CacheStats) {
+        let mut stats = CacheStats::default();
+        let mut found = HashMap::new();
+        let mut seconds = 0.0;
+        for name in code_names {
+            match self.read(name) {
+                Some(entry) => {
+                    stats.hits += 1;
+                    seconds += entry.seconds;
+                    found.insert(entry.code_name, entry.proof);
+                }
+                None => stats.misses += 1,
+            }
+        }
+        if stats.misses > 0 || code_names.is_empty() {
+            return (None, stats);
+        }
+        stats.saved = Duration::from_secs_f64(seconds);
+        (Some(found), stats)
+    }
+
+    /// Cache the results of `code_names` from a run that took `elapsed`.
+    pub fn store(
+        &self,
+        proofs: &HashMap<String, ProofResult>,
+        code_names: &[&str],
+        elapsed: Duration,
+    ) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let seconds = elapsed.as_secs_f64() / code_names.len().max(1) as f64;
+        for name in code_names {
+            let (Some(key), Some(proof)) = (self.keys.get(*name), proofs.get(*name)) else {
+                continue;
+            };
+            let entry = CacheEntry {
+                code_name: name.to_string(),
+                cached_at: Utc::now(),
+                seconds,
+                proof: proof.clone(),
+            };
+            let path = self.dir.join(format!("{}.json", key));
+            crate::interrupt::write_atomic(&path, serde_json::to_string_pretty(&entry)? + "\n")?;
+        }
+        Ok(())
+    }
+
+    fn read(&self, code_name: &str) -> Option<CacheEntry> {
+        let key = self.keys.get(code_name)?;
+        let content = std::fs::read_to_string(self.dir.join(format!("{}.json", key))).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&content).ok()?;
+        (entry.code_name == code_name).then_some(entry)
+    }
+}
+
+/// Fingerprint of every atom whose source, and whose dependencies' sources,
+/// can be read. Dependencies outside `atoms` (e.g. vstd) count by name only.
+fn fingerprints(
+    atoms: &HashMap<String, Atom>,
+    project_root: &Path,
+    source_roots: &[SourceRoot],
+) -> HashMap<String, String> {
+    let mut sources: HashMap<PathBuf, Option<String>> = HashMap::new();
+    let own: HashMap<&str, Option<String>> = atoms
+        .iter()
+        .map(|(name, atom)| {
+            let text = atom
+                .code_path
+                .as_deref()
+                .zip(atom.code_text.as_ref())
+                .and_then(|(code_path, range)| {
+                    let path = source_file(project_root, source_roots, code_path);
+                    let source = sources
+                        .entry(path.clone())
+                        .or_insert_with(|| std::fs::read_to_string(&path).ok())
+                        .as_deref()?;
+                    let start = range.lines_start.max(1) as usize;
+                    let end = range.lines_end as usize;
+                    let lines: Vec<&str> = source
+                        .lines()
+                        .skip(start - 1)
+                        .take((end + 1).saturating_sub(start))
+                        .collect();
+                    Some(sha256(&lines.join("\n")))
+                });
+            (name.as_str(), text)
+        })
+        .collect();
+
+    let mut memo = HashMap::new();
+    atoms
+        .keys()
+        .filter_map(|name| {
+            let (fingerprint, _) = fingerprint(name, atoms, &own, &mut memo, &mut Vec::new());
+            Some((name.clone(), fingerprint?))
+        })
+        .collect()
+}
+
+/// Hash of `name`'s own text and its dependencies' fingerprints, and whether a
+/// dependency cycle was cut below it. A cycle is cut at the atom already on
+/// `stack`, which then counts by name; such results depend on where the walk
+/// started, so only cycle-free results are memoized.
+fn fingerprint(
+    name: &str,
+    atoms: &HashMap<String, Atom>,
+    own: &HashMap<&str, Option<String>>,
+    memo: &mut HashMap<String, Option<String>>,
+    stack: &mut Vec<String>,
+) -> (Option<String>, bool) {
+    if let Some(done) = memo.get(name) {
+        return (done.clone(), false);
+    }
+    let Some(atom) = atoms.get(name) else {
+        return (Some(sha256(name)), false);
+    };
+    if stack.iter().any(|n| n == name) {
+        return (Some(sha256(name)), true);
+    }
+
+    stack.push(name.to_string());
+    let mut parts = vec![own.get(name).cloned().flatten()];
+    let mut cut = false;
+    let mut dependencies: Vec<&String> = atom.dependencies.iter().collect();
+    dependencies.sort();
+    for dependency in dependencies {
+        let (part, dependency_cut) = fingerprint(dependency, atoms, own, memo, stack);
+        parts.push(part);
+        cut |= dependency_cut;
+    }
+    stack.pop();
+
+    let result = parts
+        .into_iter()
+        .collect::<Option<Vec<String>>>()
+        .map(|parts| sha256(&parts.join("\0")));
+    if !cut {
+        memo.insert(name.to_string(), result.clone());
+    }
+    (result, cut)
+}
+
+fn sha256(text: &str) -> String {
+    format!("{:x}", Sha256::digest(text.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::schema::LineRange;
+
+    fn atom(path: &str, lines: (u32, u32), dependencies: &[&str]) -> Atom {
+        Atom {
+            display_name: None,
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            code_module: None,
+            code_path: Some(path.to_string()),
+            code_text: Some(LineRange {
+                lines_start: lines.0,
+                lines_end: lines.1,
+                ..Default::default()
+            }),
+            extra: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_covers_dependencies() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("lib.rs"),
+            "fn a() {}\nfn b() {}\nfn c() {}\n",
+        )
+        .unwrap();
+        let atoms = HashMap::from([
+            ("a".to_string(), atom("lib.rs", (1, 1), &["b", "vstd"])),
+            ("b".to_string(), atom("lib.rs", (2, 2), &[])),
+            ("c".to_string(), atom("lib.rs", (3, 3), &["d"])),
+            ("d".to_string(), atom("lib.rs", (3, 3), &["c"])),
+            ("gone".to_string(), atom("missing.rs", (1, 1), &[])),
+        ]);
+        let before = fingerprints(&atoms, dir.path(), &[]);
+        assert!(!before.contains_key("gone"));
+
+        std::fs::write(
+            dir.path().join("lib.rs"),
+            "fn a() {}\nfn b() { 1 }\nfn c() {}\n",
+        )
+        .unwrap();
+        let after = fingerprints(&atoms, dir.path(), &[]);
+        assert_ne!(
+            before["a"], after["a"],
+            "editing a callee changes its callers"
+        );
+        assert_ne!(before["b"], after["b"]);
+        assert_eq!(before["c"], after["c"]);
+        // Cycles must not make fingerprints depend on HashMap iteration order.
+        for _ in 0..10 {
+            let reordered: HashMap<_, _> = atoms.clone().into_iter().collect();
+            assert_eq!(after, fingerprints(&reordered, dir.path(), &[]));
+        }
+    }
+}
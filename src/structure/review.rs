@@ -0,0 +1,52 @@
+//! Spec review workflow states.
+//!
+//! A spec moves through `draft` -> `in-review` -> `approved`. The state is
+//! stored as `review-status` in structure frontmatter and stubs.json, and as
+//! `review_status` in `.meta.verilib` files.
+
+use anyhow::{bail, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Review state of a specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReviewStatus {
+    Draft,
+    InReview,
+    Approved,
+}
+
+impl ReviewStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReviewStatus::Draft => "draft",
+            ReviewStatus::InReview => "in-review",
+            ReviewStatus::Approved => "approved",
+        }
+    }
+}
+
+impl fmt::Display for ReviewStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ReviewStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "draft" => Ok(ReviewStatus::Draft),
+            "in-review" => Ok(ReviewStatus::InReview),
+            "approved" => Ok(ReviewStatus::Approved),
+            other => bail!(
+                "Invalid review status '{}'. Expected one of: draft, in-review, approved",
+                other
+            ),
+        }
+    }
+}
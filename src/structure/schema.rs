@@ -0,0 +1,197 @@
+//! Typed entries of the JSON files passed between pipeline stages.
+//!
+//! atoms.json, specs.json and proofs.json map a code-name to an entry, and
+//! stubs.json maps a structure file path to one. Fields the pipeline reads are
+//! typed, so a renamed or mistyped field fails at load time instead of being
+//! read as missing. Fields this version doesn't know about are kept in `extra`
+//! and written back unchanged.
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A 1-based, inclusive line range (`code-text`, `spec-text`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct LineRange {
+    pub lines_start: u32,
+    pub lines_end: u32,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl LineRange {
+    pub fn new(lines_start: u32, lines_end: u32) -> Self {
+        LineRange {
+            lines_start,
+            lines_end,
+            extra: Map::new(),
+        }
+    }
+}
+
+/// An atoms.json entry, produced by `probe-verus atomize` or a SCIP import.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct Atom {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_module: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_text: Option<LineRange>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A stubs.json entry: structure file frontmatter, enriched by `atomize` and
+/// updated by `specify` and `verify`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct StubEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_path: Option<String>,
+    /// First line of the function as written in the frontmatter.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_line: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_text: Option<LineRange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_module: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dependencies: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spec_text: Option<LineRange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub specified: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub review_status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub atom_override: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub match_confidence: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip_reason: Option<String>,
+    /// Why the function is left out of spec and proof work, set during triage.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore_reason: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl StubEntry {
+    /// Build a stub from parsed .md frontmatter.
+    pub fn from_frontmatter(frontmatter: HashMap<String, Value>) -> Result<Self> {
+        serde_json::from_value(Value::Object(frontmatter.into_iter().collect()))
+            .context("Invalid frontmatter")
+    }
+}
+
+/// A specs.json entry, produced by `probe-verus specify`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct SpecEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spec_text: Option<LineRange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub specified: Option<bool>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A proofs.json entry, produced by `probe-verus verify`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct ProofResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_line: Option<u32>,
+    #[serde(default)]
+    pub verified: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// The `verify --profile` that produced this result; absent for the default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verify_profile: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Read a JSON map of entries, naming the offending entry on schema errors.
+pub fn load_map<T: DeserializeOwned>(path: &Path) -> Result<HashMap<String, T>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    parse_map(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Parse a JSON map of entries; see [`load_map`].
+pub fn parse_map<T: DeserializeOwned>(content: &str) -> Result<HashMap<String, T>> {
+    parse_entries(serde_json::from_str(content)?)
+}
+
+/// Deserialize each entry of an already parsed map, naming the offending entry.
+pub fn parse_entries<T: DeserializeOwned>(raw: Map<String, Value>) -> Result<HashMap<String, T>> {
+    raw.into_iter()
+        .map(|(key, value)| {
+            let entry = serde_json::from_value(value)
+                .with_context(|| format!("entry '{}' does not match the schema", key))?;
+            Ok((key, entry))
+        })
+        .collect()
+}
+
+/// Write a JSON map of entries with keys sorted at every level, so that
+/// output is stable across runs.
+pub fn save_map<T: Serialize>(path: &Path, entries: &HashMap<String, T>) -> Result<()> {
+    let value = serde_json::to_value(entries)?;
+    crate::interrupt::write_atomic(path, serde_json::to_string_pretty(&value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_unknown_fields_round_trip() {
+        let raw = json!({
+            "code-name": "probe:c/f()",
+            "code-text": { "lines-start": 3, "lines-end": 9 },
+            "dependencies": [],
+            "future-field": { "nested": true }
+        });
+        let stub: StubEntry = serde_json::from_value(raw.clone()).unwrap();
+        assert_eq!(stub.code_name.as_deref(), Some("probe:c/f()"));
+        assert_eq!(stub.extra["future-field"], json!({ "nested": true }));
+        assert_eq!(serde_json::to_value(&stub).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_schema_errors_name_the_entry() {
+        let err = parse_map::<StubEntry>(r#"{"a.md": {"code-line": "ten"}}"#).unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(message.contains("'a.md'"), "{}", message);
+        assert!(message.contains("u32"), "{}", message);
+    }
+}
@@ -0,0 +1,440 @@
+//! SCIP index conversion.
+//!
+//! Converts the function definitions and references of a SCIP index into
+//! the atoms.json format produced by `probe-verus atomize`, so projects that
+//! already build a SCIP index can skip probe-verus. In the other direction,
+//! exports enriched stubs as a SCIP index whose symbols carry verification
+//! status, so code-intelligence tools can show it on hover.
+
+use super::annotations;
+use super::schema::{Atom, LineRange, StubEntry};
+use anyhow::{Context, Result};
+use protobuf::Message;
+use scip::symbol::{format_symbol, parse_symbol};
+use scip::types::descriptor::Suffix;
+use scip::types::symbol_information::Kind;
+use scip::types::{
+    Descriptor, Document, Index, Metadata, Occurrence, Package, Relationship, Symbol,
+    SymbolInformation, SymbolRole, TextEncoding, ToolInfo,
+};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+/// Scheme and package manager used for exported symbols. These match what
+/// rust-analyzer emits, so exported symbols line up with its indexes.
+const EXPORT_SCHEME: &str = "rust-analyzer";
+const EXPORT_MANAGER: &str = "cargo";
+
+/// Read a binary SCIP index from disk.
+pub fn load_index(path: &Path) -> Result<Index> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Index::parse_from_bytes(&bytes)
+        .with_context(|| format!("Failed to parse SCIP index {}", path.display()))
+}
+
+/// A function symbol split into the parts atoms.json needs.
+struct FunctionSymbol {
+    code_name: String,
+    code_module: String,
+    name: String,
+}
+
+/// Parse a global SCIP symbol whose last descriptor is a method/function.
+/// Returns `None` for locals and non-function symbols.
+fn parse_function_symbol(symbol: &str) -> Option<FunctionSymbol> {
+    let parsed = parse_symbol(symbol).ok()?;
+    let last = parsed.descriptors.last()?;
+    if last.suffix.enum_value() != Ok(Suffix::Method) {
+        return None;
+    }
+
+    let (crate_name, version) = parsed
+        .package
+        .as_ref()
+        .map(|p| (p.name.as_str(), p.version.as_str()))
+        .unwrap_or(("", ""));
+
+    let segments: Vec<String> = parsed
+        .descriptors
+        .iter()
+        .map(|d| match d.suffix.enum_value() {
+            Ok(Suffix::Method) => format!("{}()", d.name),
+            _ => d.name.clone(),
+        })
+        .collect();
+
+    let code_module = parsed
+        .descriptors
+        .iter()
+        .filter(|d| matches!(d.suffix.enum_value(), Ok(Suffix::Namespace)))
+        .map(|d| d.name.as_str())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    Some(FunctionSymbol {
+        code_name: format!("probe:{}/{}/{}", crate_name, version, segments.join("/")),
+        code_module,
+        name: last.name.clone(),
+    })
+}
+
+/// Start and end line (0-based, inclusive) of a SCIP range, which is either
+/// `[line, start_char, end_char]` or `[start_line, start_char, end_line, end_char]`.
+fn line_span(range: &[i32]) -> Option<(i32, i32)> {
+    match range {
+        [line, _, _] => Some((*line, *line)),
+        [start, _, end, _] => Some((*start, *end)),
+        _ => None,
+    }
+}
+
+struct Definition {
+    symbol: FunctionSymbol,
+    display_name: String,
+    start: i32,
+    end: i32,
+    dependencies: BTreeSet<String>,
+}
+
+fn document_definitions(document: &Document) -> Vec<Definition> {
+    let display_names: HashMap<&str, &str> = document
+        .symbols
+        .iter()
+        .filter(|s| !s.display_name.is_empty())
+        .map(|s| (s.symbol.as_str(), s.display_name.as_str()))
+        .collect();
+
+    let mut definitions: Vec<Definition> = document
+        .occurrences
+        .iter()
+        .filter(|o| o.symbol_roles & SymbolRole::Definition as i32 != 0)
+        .filter_map(|o| {
+            let symbol = parse_function_symbol(&o.symbol)?;
+            let range = if o.enclosing_range.is_empty() {
+                &o.range
+            } else {
+                &o.enclosing_range
+            };
+            let (start, end) = line_span(range)?;
+            let display_name = display_names
+                .get(o.symbol.as_str())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| symbol.name.clone());
+            Some(Definition {
+                symbol,
+                display_name,
+                start,
+                end,
+                dependencies: BTreeSet::new(),
+            })
+        })
+        .collect();
+
+    // Attribute each call to the innermost definition that encloses it.
+    for occurrence in &document.occurrences {
+        if occurrence.symbol_roles & SymbolRole::Definition as i32 != 0 {
+            continue;
+        }
+        let Some(callee) = parse_function_symbol(&occurrence.symbol) else {
+            continue;
+        };
+        let Some((line, _)) = line_span(&occurrence.range) else {
+            continue;
+        };
+        let caller = definitions
+            .iter_mut()
+            .filter(|d| d.start <= line && line <= d.end)
+            .min_by_key(|d| d.end - d.start);
+        if let Some(caller) = caller {
+            if caller.symbol.code_name != callee.code_name {
+                caller.dependencies.insert(callee.code_name);
+            }
+        }
+    }
+
+    definitions
+}
+
+/// Convert a SCIP index into atoms.json entries keyed by code-name.
+pub fn atoms_from_index(index: &Index) -> HashMap<String, Atom> {
+    let mut atoms = HashMap::new();
+
+    for document in &index.documents {
+        for def in document_definitions(document) {
+            let mut extra = serde_json::Map::new();
+            extra.insert("mode".to_string(), json!("exec"));
+            atoms.insert(
+                def.symbol.code_name.clone(),
+                Atom {
+                    display_name: Some(def.display_name),
+                    dependencies: def.dependencies.into_iter().collect(),
+                    code_module: Some(def.symbol.code_module),
+                    code_path: Some(document.relative_path.clone()),
+                    code_text: Some(LineRange::new((def.start + 1) as u32, (def.end + 1) as u32)),
+                    extra,
+                },
+            );
+        }
+    }
+
+    atoms
+}
+
+/// Convert a probe code-name (`probe:crate/version/path/func()`) into a
+/// global SCIP symbol string.
+fn symbol_from_code_name(code_name: &str) -> Option<String> {
+    let rest = code_name.strip_prefix("probe:")?;
+    let mut parts = rest.split('/');
+    let crate_name = parts.next()?;
+    let version = parts.next()?;
+    let segments: Vec<&str> = parts.collect();
+    let (last, modules) = segments.split_last()?;
+    let function = last.strip_suffix("()").unwrap_or(last);
+
+    let descriptor = |name: &str, suffix: Suffix| Descriptor {
+        name: name.to_string(),
+        suffix: suffix.into(),
+        ..Default::default()
+    };
+    let mut descriptors: Vec<Descriptor> = modules
+        .iter()
+        .map(|m| descriptor(m, Suffix::Namespace))
+        .collect();
+    descriptors.push(descriptor(function, Suffix::Method));
+
+    Some(format_symbol(Symbol {
+        scheme: EXPORT_SCHEME.to_string(),
+        package: Some(Package {
+            manager: EXPORT_MANAGER.to_string(),
+            name: crate_name.to_string(),
+            version: version.to_string(),
+            ..Default::default()
+        })
+        .into(),
+        descriptors,
+        ..Default::default()
+    }))
+}
+
+/// Hover text describing a stub's verification state and reviewer notes.
+fn stub_documentation(stub: &StubEntry, notes: Option<&Vec<String>>) -> String {
+    let mut lines = vec![
+        format!(
+            "**verilib**: {}",
+            if stub.verified == Some(true) {
+                "verified"
+            } else {
+                "not verified"
+            }
+        ),
+        format!(
+            "specified: {}",
+            if stub.specified == Some(true) {
+                "yes"
+            } else {
+                "no"
+            }
+        ),
+    ];
+    for (key, value) in [
+        ("status", &stub.status),
+        ("review-status", &stub.review_status),
+        ("owner", &stub.owner),
+    ] {
+        if let Some(value) = value {
+            lines.push(format!("{}: {}", key, value));
+        }
+    }
+    if let Some(notes) = notes {
+        lines.push(annotations::format(notes));
+    }
+    lines.join("\n\n")
+}
+
+/// Build a SCIP index from enriched stubs. Each stub with a code-name becomes
+/// a function definition spanning its `code-text` lines, and each dependency
+/// becomes a reference relationship, and `annotations` (keyed by code-name)
+/// are added to the hover text. Returns the index and the number of exported
+/// symbols.
+pub fn index_from_stubs(
+    stubs: &HashMap<String, StubEntry>,
+    annotations: &HashMap<String, Vec<String>>,
+    project_root: &Path,
+) -> (Index, usize) {
+    let mut documents: BTreeMap<String, Document> = BTreeMap::new();
+    let mut exported = 0;
+
+    let mut names: Vec<&String> = stubs.keys().collect();
+    names.sort();
+
+    for name in names {
+        let stub = &stubs[name];
+        let (Some(code_name), Some(code_path), Some(code_text)) =
+            (&stub.code_name, &stub.code_path, &stub.code_text)
+        else {
+            continue;
+        };
+        let Some(symbol) = symbol_from_code_name(code_name) else {
+            continue;
+        };
+        let line = |l: u32| l.saturating_sub(1) as i32;
+        let (start, end) = (line(code_text.lines_start), line(code_text.lines_end));
+
+        let relationships = stub
+            .dependencies
+            .iter()
+            .flatten()
+            .filter_map(|d| symbol_from_code_name(d))
+            .map(|dep| Relationship {
+                symbol: dep,
+                is_reference: true,
+                ..Default::default()
+            })
+            .collect();
+
+        let document = documents
+            .entry(code_path.to_string())
+            .or_insert_with(|| Document {
+                language: "rust".to_string(),
+                relative_path: code_path.to_string(),
+                ..Default::default()
+            });
+        document.occurrences.push(Occurrence {
+            symbol: symbol.clone(),
+            symbol_roles: SymbolRole::Definition as i32,
+            range: vec![start, 0, 0],
+            enclosing_range: vec![start, 0, end, 0],
+            ..Default::default()
+        });
+        document.symbols.push(SymbolInformation {
+            symbol,
+            display_name: stub.display_name.clone().unwrap_or_default(),
+            documentation: vec![stub_documentation(stub, annotations.get(code_name))],
+            relationships,
+            kind: Kind::Function.into(),
+            ..Default::default()
+        });
+        exported += 1;
+    }
+
+    let index = Index {
+        metadata: Some(Metadata {
+            tool_info: Some(ToolInfo {
+                name: env!("CARGO_PKG_NAME").to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                ..Default::default()
+            })
+            .into(),
+            project_root: format!("file://{}", project_root.display()),
+            text_document_encoding: TextEncoding::UTF8.into(),
+            ..Default::default()
+        })
+        .into(),
+        documents: documents.into_values().collect(),
+        ..Default::default()
+    };
+
+    (index, exported)
+}
+
+/// Write a SCIP index to disk.
+pub fn write_index(index: &Index, path: &Path) -> Result<()> {
+    let bytes = index
+        .write_to_bytes()
+        .context("Failed to encode SCIP index")?;
+    std::fs::write(path, bytes).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scip::types::Occurrence;
+
+    fn occurrence(symbol: &str, roles: i32, range: Vec<i32>, enclosing: Vec<i32>) -> Occurrence {
+        Occurrence {
+            symbol: symbol.to_string(),
+            symbol_roles: roles,
+            range,
+            enclosing_range: enclosing,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_definitions_and_calls_become_atoms() {
+        let func_a = "rust-analyzer cargo demo 0.1.0 module/func_a().";
+        let helper = "rust-analyzer cargo demo 0.1.0 module/helper().";
+        let def = SymbolRole::Definition as i32;
+
+        let document = Document {
+            relative_path: "src/module.rs".to_string(),
+            occurrences: vec![
+                occurrence(func_a, def, vec![9, 7, 13], vec![9, 0, 19, 1]),
+                occurrence(helper, 0, vec![12, 4, 10], vec![]),
+                occurrence(helper, def, vec![39, 7, 13], vec![39, 0, 44, 1]),
+                occurrence("local 3", def, vec![11, 8, 9], vec![]),
+            ],
+            ..Default::default()
+        };
+        let index = Index {
+            documents: vec![document],
+            ..Default::default()
+        };
+
+        let atoms = atoms_from_index(&index);
+        assert_eq!(atoms.len(), 2);
+
+        let a = serde_json::to_value(&atoms["probe:demo/0.1.0/module/func_a()"]).unwrap();
+        assert_eq!(a["display-name"], "func_a");
+        assert_eq!(a["code-module"], "module");
+        assert_eq!(a["code-path"], "src/module.rs");
+        assert_eq!(a["code-text"]["lines-start"], 10);
+        assert_eq!(a["code-text"]["lines-end"], 20);
+        assert_eq!(
+            a["dependencies"],
+            json!(["probe:demo/0.1.0/module/helper()"])
+        );
+    }
+
+    #[test]
+    fn test_exported_stubs_round_trip_to_atoms() {
+        let stubs: HashMap<String, StubEntry> = serde_json::from_value(json!({
+            "src/module.rs/func_a().md": {
+                "code-name": "probe:demo/0.1.0/module/func_a()",
+                "code-path": "src/module.rs",
+                "code-text": { "lines-start": 10, "lines-end": 20 },
+                "dependencies": ["probe:demo/0.1.0/module/helper()"],
+                "display-name": "func_a",
+                "verified": true
+            },
+            "src/missing.rs/orphan().md": { "code-path": "src/missing.rs" }
+        }))
+        .unwrap();
+
+        let annotations = HashMap::from([(
+            "probe:demo/0.1.0/module/func_a()".to_string(),
+            vec!["needs loop invariant".to_string()],
+        )]);
+        let (index, exported) = index_from_stubs(&stubs, &annotations, Path::new("/project"));
+        assert_eq!(exported, 1);
+
+        let info = &index.documents[0].symbols[0];
+        assert_eq!(info.relationships.len(), 1);
+        assert!(info.documentation[0].contains("verified"));
+        assert!(info.documentation[0].ends_with("Notes:\n- needs loop invariant"));
+
+        let bytes = index.write_to_bytes().unwrap();
+        let atoms = atoms_from_index(&Index::parse_from_bytes(&bytes).unwrap());
+        let a = serde_json::to_value(&atoms["probe:demo/0.1.0/module/func_a()"]).unwrap();
+        assert_eq!(a["code-text"]["lines-start"], 10);
+        assert_eq!(a["code-text"]["lines-end"], 20);
+    }
+
+    #[test]
+    fn test_non_function_symbols_are_ignored() {
+        assert!(parse_function_symbol("rust-analyzer cargo demo 0.1.0 module/Type#").is_none());
+        assert!(parse_function_symbol("local 1").is_none());
+    }
+}
@@ -1,16 +1,52 @@
 //! General utility functions for verilib structure.
 
-use crate::executor::{self as executor, CommandConfig, ExternalTool};
+use super::frontmatter;
+use super::schema::StubEntry;
+use crate::executor::{self as executor, CommandConfig, ExternalTool, ToolOutput};
+use crate::exit::fail;
+use crate::output;
+use crate::paths::relative_arg;
 use anyhow::{Context, Result};
-use serde_json::Value;
-use std::collections::HashSet;
+use chrono::Utc;
+use std::collections::{HashMap, HashSet};
 use std::io::{self, BufRead, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use walkdir::WalkDir;
+
+static KEEP_INTERMEDIATES: OnceLock<bool> = OnceLock::new();
+
+/// Decide once whether probe-verus intermediate files are kept
+/// (`--keep-intermediates`). Later calls are ignored.
+pub fn init_keep_intermediates(keep: bool) {
+    let _ = KEEP_INTERMEDIATES.set(keep);
+}
 
 /// Clean up generated intermediate files from probe-verus commands.
 ///
-/// Removes common intermediate files and the data directory if empty.
-pub fn cleanup_intermediate_files(project_root: &Path, files: &[&str]) {
+/// Removes common intermediate files and the data directory if empty. When
+/// intermediates are kept (`--keep-intermediates`, or `keep-intermediates`
+/// in config.json), the data directory is moved to
+/// `.verilib/intermediates/<timestamp>/` instead, and its new location is
+/// printed if the probe step `failed`.
+pub fn cleanup_intermediate_files(
+    project_root: &Path,
+    files: &[&str],
+    config: &CommandConfig,
+    failed: bool,
+) {
+    if config.keep_intermediates || KEEP_INTERMEDIATES.get() == Some(&true) {
+        match keep_intermediate_files(project_root) {
+            Ok(Some(dir)) if failed => eprintln!(
+                "Intermediate files kept in {}; attach them to a bug report.",
+                dir.display()
+            ),
+            Ok(_) => {}
+            Err(e) => output::warn(format!("Failed to keep intermediate files: {:#}", e)),
+        }
+        return;
+    }
+
     for file in files {
         let path = project_root.join(file);
         if path.exists() {
@@ -25,6 +61,28 @@ pub fn cleanup_intermediate_files(project_root: &Path, files: &[&str]) {
     }
 }
 
+/// Move the data directory under `.verilib/intermediates`, returning where
+/// it went, or `None` when there is none.
+fn keep_intermediate_files(project_root: &Path) -> Result<Option<PathBuf>> {
+    let data_dir = project_root.join("data");
+    if !data_dir.is_dir() {
+        return Ok(None);
+    }
+    let parent = project_root.join(".verilib").join("intermediates");
+    std::fs::create_dir_all(&parent)
+        .with_context(|| format!("Failed to create {}", parent.display()))?;
+    let stamp = Utc::now().format("%Y%m%dT%H%M%S%.3fZ").to_string();
+    let target = parent.join(stamp);
+    std::fs::rename(&data_dir, &target).with_context(|| {
+        format!(
+            "Failed to move {} to {}",
+            data_dir.display(),
+            target.display()
+        )
+    })?;
+    Ok(Some(target))
+}
+
 /// Run an external tool's command and return its output.
 ///
 /// Checks tool availability (and Docker availability when in Docker mode)
@@ -34,19 +92,17 @@ pub fn run_command(
     args: &[&str],
     cwd: Option<&Path>,
     config: &CommandConfig,
-) -> Result<std::process::Output> {
+) -> Result<ToolOutput> {
     executor::run_command(tool, args, cwd, config)
 }
 
 /// Display a multiple choice menu and get user selections.
-pub fn display_menu<F>(items: &[(String, Value)], format_item: F) -> Result<Vec<usize>>
+pub fn display_menu<T, F>(items: &[(String, T)], format_item: F) -> Result<Vec<usize>>
 where
-    F: Fn(usize, &str, &Value) -> String,
+    F: Fn(usize, &str, &T) -> String,
 {
     println!();
-    println!("{}", "=".repeat(60));
-    println!("Functions with specs but no certification:");
-    println!("{}", "=".repeat(60));
+    output::heading("Functions with specs but no certification:");
     println!();
 
     for (i, (name, info)) in items.iter().enumerate() {
@@ -54,8 +110,6 @@ where
         println!();
     }
 
-    println!("{}", "=".repeat(60));
-    println!();
     println!("Enter selection:");
     println!("  - Individual numbers: 1, 3, 5");
     println!("  - Ranges: 1-5");
@@ -91,17 +145,17 @@ where
                         }
                     }
                 } else {
-                    eprintln!("Warning: Invalid range '{}', skipping", part);
+                    output::warn(format!("invalid range '{}', skipping", part));
                 }
             }
         } else if let Ok(idx) = part.parse::<usize>() {
             if idx >= 1 && idx <= items.len() {
                 selected.insert(idx - 1);
             } else {
-                eprintln!("Warning: {} out of range, skipping", idx);
+                output::warn(format!("{} out of range, skipping", idx));
             }
         } else {
-            eprintln!("Warning: Invalid number '{}', skipping", part);
+            output::warn(format!("invalid number '{}', skipping", part));
         }
     }
 
@@ -110,6 +164,34 @@ where
     Ok(result)
 }
 
+/// Frontmatter of every .md file under the structure root, keyed by relative path.
+pub fn read_structure_files(structure_root: &Path) -> Result<HashMap<String, StubEntry>> {
+    if !structure_root.exists() {
+        fail!(
+            MissingPrerequisite,
+            "Structure directory not found at {}. Run 'verilib-cli create' first.",
+            structure_root.display()
+        );
+    }
+
+    let mut files = HashMap::new();
+    for entry in WalkDir::new(structure_root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("md") {
+            continue;
+        }
+        let rel_path = relative_arg(path, structure_root)?.to_string();
+        let stub = frontmatter::parse(path)
+            .and_then(StubEntry::from_frontmatter)
+            .with_context(|| format!("Failed to parse frontmatter of {}", rel_path))?;
+        files.insert(rel_path, stub);
+    }
+    Ok(files)
+}
+
 /// Get a display name from a full identifier (e.g., extract "func" from "probe:crate/mod#func()").
 pub fn get_display_name(name: &str) -> String {
     if let Some(pos) = name.rfind('#') {
@@ -124,9 +206,40 @@ pub fn create_gitignore(verilib_path: &Path) -> Result<()> {
     let gitignore_path = verilib_path.join(".gitignore");
     if !gitignore_path.exists() {
         let gitignore_content =
-            "# Generated by VeriLib (not tracked)\natoms.json\nspecs.json\nstubs.json\nproofs.json\n";
+            "# Generated by VeriLib (not tracked)\natoms.json\natoms.provenance.json\nspecs.json\nstubs.json\nproofs.json\nlogs/\ncache/\nmetrics.jsonl\ndeploy_errors.json\n";
         std::fs::write(&gitignore_path, gitignore_content).context("Failed to write .gitignore")?;
         println!("Created .verilib/.gitignore");
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cleanup_keeps_intermediates_when_configured() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("data")).unwrap();
+        std::fs::write(root.join("data/index.scip"), "scip").unwrap();
+        let keep = CommandConfig {
+            keep_intermediates: true,
+            ..CommandConfig::default()
+        };
+
+        cleanup_intermediate_files(root, &["data/index.scip"], &keep, true);
+        assert!(!root.join("data").exists());
+        let kept: Vec<PathBuf> = std::fs::read_dir(root.join(".verilib/intermediates"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(kept.len(), 1);
+        assert!(kept[0].join("index.scip").exists());
+
+        std::fs::create_dir_all(root.join("data")).unwrap();
+        std::fs::write(root.join("data/index.scip"), "scip").unwrap();
+        cleanup_intermediate_files(root, &["data/index.scip"], &CommandConfig::default(), false);
+        assert!(!root.join("data").exists());
+    }
+}
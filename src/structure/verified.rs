@@ -0,0 +1,110 @@
+//! Code hashes of functions as they were when they verified.
+//!
+//! `atomize` rebuilds stubs.json, so `verify` records these apart, in
+//! `.verilib/verified.json` keyed by code-name. `drift` compares them with
+//! the current source to find proofs that may be stale.
+
+use super::certs::SpecSource;
+use super::schema::{load_map, save_map, StubEntry};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// One verified.json entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct VerifiedCode {
+    /// When the function first verified with this code.
+    pub verified_at: DateTime<Utc>,
+    /// SHA-256 of the function's code lines.
+    pub code_hash: String,
+}
+
+/// Read verified.json; a missing file records nothing.
+pub fn load_verified(path: &Path) -> Result<HashMap<String, VerifiedCode>> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    load_map(path)
+}
+
+/// Update verified.json with the outcome of a verify run for `stubs`: the
+/// current code hash of each verified function, and no entry for the rest.
+/// An entry whose code is unchanged keeps its timestamp, so re-running
+/// verify does not rewrite the file.
+pub fn record_verified<'a>(
+    path: &Path,
+    stubs: impl IntoIterator<Item = &'a StubEntry>,
+    source: &SpecSource,
+) -> Result<()> {
+    let mut verified = load_verified(path)?;
+    let before = verified.clone();
+    for stub in stubs {
+        let Some(code_name) = stub.code_name.as_deref() else {
+            continue;
+        };
+        let code_hash = source
+            .code_hash(stub)
+            .filter(|_| stub.verified == Some(true));
+        match code_hash {
+            Some(code_hash) => {
+                if verified.get(code_name).map(|v| &v.code_hash) != Some(&code_hash) {
+                    verified.insert(
+                        code_name.to_string(),
+                        VerifiedCode {
+                            verified_at: Utc::now(),
+                            code_hash,
+                        },
+                    );
+                }
+            }
+            None => {
+                verified.remove(code_name);
+            }
+        }
+    }
+    if verified != before {
+        save_map(path, &verified)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::schema::LineRange;
+
+    #[test]
+    fn test_record_keeps_unchanged_and_drops_unverified() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+        let source = SpecSource {
+            project_root: dir.path(),
+            source_roots: &[],
+        };
+        let stub = |name: &str, line: u32, verified: bool| StubEntry {
+            code_name: Some(name.to_string()),
+            code_path: Some("lib.rs".to_string()),
+            code_text: Some(LineRange {
+                lines_start: line,
+                lines_end: line,
+                ..Default::default()
+            }),
+            verified: Some(verified),
+            ..Default::default()
+        };
+        let path = dir.path().join("verified.json");
+
+        record_verified(&path, &[stub("a", 1, true), stub("b", 2, true)], &source).unwrap();
+        let first = load_verified(&path).unwrap();
+        assert_eq!(first.len(), 2);
+
+        record_verified(&path, &[stub("a", 1, true), stub("b", 2, false)], &source).unwrap();
+        let second = load_verified(&path).unwrap();
+        assert_eq!(second.keys().collect::<Vec<_>>(), vec!["a"]);
+        assert_eq!(second["a"], first["a"]);
+    }
+}
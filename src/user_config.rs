@@ -0,0 +1,96 @@
+//! User-level settings, shared by every project.
+//!
+//! Stored next to the credentials file, in `~/.verilib_config.json`. Project
+//! settings live in `.verilib/config.json` instead (see [`crate::config`]).
+
+use crate::storage::StorageType;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+const FILE_NAME: &str = ".verilib_config.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct UserConfig {
+    /// Credential backend chosen when the keyring was unavailable; used
+    /// unless `VERILIB_STORAGE` is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_storage: Option<StorageType>,
+    /// Command aliases, e.g. `"ci": ["run", "--stages", "atomize,verify"]`.
+    /// Built-in commands take precedence.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, Vec<String>>,
+    /// Flags inserted before the command-line arguments of a command; the
+    /// project's `defaults` come after them.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub defaults: BTreeMap<String, Vec<String>>,
+}
+
+impl UserConfig {
+    pub fn path() -> Result<PathBuf> {
+        let home_dir = dirs::home_dir().context("Failed to get home directory")?;
+        Ok(home_dir.join(FILE_NAME))
+    }
+
+    /// The saved settings, or the defaults if none were saved.
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// `args` with the alias `name` replaced by its expansion, if it is one.
+    pub fn expand_alias(&self, args: &[OsString], name: &str) -> Option<Vec<OsString>> {
+        let expansion = self.aliases.get(name)?;
+        let position = args.iter().skip(1).position(|arg| arg == name)? + 1;
+        let mut expanded = args[..position].to_vec();
+        expanded.extend(expansion.iter().map(OsString::from));
+        expanded.extend_from_slice(&args[position + 1..]);
+        Some(expanded)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()?;
+        let content = serde_json::to_string_pretty(self)? + "\n";
+        crate::interrupt::write_atomic(&path, content)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_alias_in_place() {
+        let config = UserConfig {
+            aliases: BTreeMap::from([(
+                "ci".to_string(),
+                vec![
+                    "run".to_string(),
+                    "--stages".to_string(),
+                    "verify".to_string(),
+                ],
+            )]),
+            ..Default::default()
+        };
+        let args: Vec<OsString> = ["verilib-cli", "--json", "ci", "."]
+            .into_iter()
+            .map(OsString::from)
+            .collect();
+
+        assert_eq!(
+            config.expand_alias(&args, "ci").unwrap(),
+            ["verilib-cli", "--json", "run", "--stages", "verify", "."]
+        );
+        assert!(config.expand_alias(&args, "other").is_none());
+    }
+}
@@ -0,0 +1,139 @@
+//! Walking source trees and `.verilib`.
+//!
+//! Source walks skip what git would ignore (`.gitignore`, `.ignore`,
+//! `.git/info/exclude` and the global excludes file, also outside a git
+//! checkout), what `.verilibignore` lists in the same syntax, hidden files
+//! and directories, and the build and dependency
+//! directories in [`SKIP_DIRS`]. A monorepo's `target/` directories, wherever
+//! they are, are never entered.
+//!
+//! `.verilib` is walked in full: its own `.gitignore` lists generated files,
+//! like stubs.json, that commands still need to read.
+
+use ignore::{WalkBuilder, WalkState};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Directories never entered by source walks, even when not ignored.
+pub const SKIP_DIRS: &[&str] = &["target", "node_modules"];
+
+/// Ignore file, in `.gitignore` syntax, read by verilib only.
+pub const IGNORE_FILE: &str = ".verilibignore";
+
+fn source_walk(root: &Path) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .require_git(false)
+        .add_custom_ignore_filename(IGNORE_FILE)
+        .filter_entry(|entry| {
+            !entry.file_type().is_some_and(|t| t.is_dir())
+                || !SKIP_DIRS.contains(&entry.file_name().to_str().unwrap_or(""))
+        });
+    builder
+}
+
+/// Whether any file or directory under the source tree `root` satisfies
+/// `matches`. Entries are checked on several threads, and the walk stops at
+/// the first match.
+pub fn any_source_path(root: &Path, matches: impl Fn(&Path) -> bool + Sync) -> bool {
+    let found = AtomicBool::new(false);
+    source_walk(root).build_parallel().run(|| {
+        Box::new(|entry| {
+            let Ok(entry) = entry else {
+                return WalkState::Continue;
+            };
+            if entry.depth() > 0 && matches(entry.path()) {
+                found.store(true, Ordering::Relaxed);
+                return WalkState::Quit;
+            }
+            if found.load(Ordering::Relaxed) {
+                WalkState::Quit
+            } else {
+                WalkState::Continue
+            }
+        })
+    });
+    found.into_inner()
+}
+
+/// Every file under the source tree `root`, in sorted order.
+pub fn source_files(root: &Path) -> Vec<PathBuf> {
+    source_walk(root)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+/// Every file under `root`, a `.verilib` directory or part of one, in sorted
+/// order. Nothing is skipped.
+pub fn verilib_files(root: &Path) -> impl Iterator<Item = PathBuf> {
+    WalkBuilder::new(root)
+        .standard_filters(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .map(|entry| entry.into_path())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_source_walks_skip_ignored_and_build_directories() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        for path in [
+            "src/lib.rs",
+            "crates/a/target/debug/build.rs",
+            "crates/a/src/a.rs",
+            "web/node_modules/x/index.rs",
+            "generated/out.rs",
+            "vendored/v.rs",
+            ".hidden/h.rs",
+            ".verilib/.gitignore",
+            ".verilib/stubs.json",
+        ] {
+            fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+            fs::write(root.join(path), "").unwrap();
+        }
+        fs::write(root.join(".gitignore"), "generated/\n").unwrap();
+        fs::write(root.join(IGNORE_FILE), "vendored/\n").unwrap();
+        fs::write(root.join(".verilib/.gitignore"), "stubs.json\n").unwrap();
+
+        assert!(any_source_path(root, |p| p.ends_with("a.rs")));
+        for skipped in [
+            "build.rs",
+            "index.rs",
+            "out.rs",
+            "v.rs",
+            "h.rs",
+            "stubs.json",
+        ] {
+            assert!(
+                !any_source_path(root, |p| p.ends_with(skipped)),
+                "{}",
+                skipped
+            );
+        }
+
+        assert_eq!(
+            source_files(root),
+            vec![root.join("crates/a/src/a.rs"), root.join("src/lib.rs")]
+        );
+
+        let files: Vec<PathBuf> = verilib_files(&root.join(".verilib")).collect();
+        assert_eq!(
+            files,
+            vec![
+                root.join(".verilib/.gitignore"),
+                root.join(".verilib/stubs.json")
+            ]
+        );
+    }
+}
@@ -14,6 +14,23 @@ fn main() {
         return;
     }
 
+    // Simulate a long run: announce the start, then sleep.
+    if let Ok(secs) = env::var("MOCK_PROBE_SLEEP_SECS") {
+        if let Ok(marker) = env::var("MOCK_PROBE_STARTED") {
+            let _ = fs::write(marker, "");
+        }
+        std::thread::sleep(std::time::Duration::from_secs(secs.parse().unwrap_or(0)));
+    }
+
+    // Simulate verus-analyzer crashing: atomize only works with --rust-analyzer.
+    if subcommand == "atomize"
+        && env::var("MOCK_VERUS_ANALYZER_FAILS").is_ok()
+        && !args.iter().any(|arg| arg == "--rust-analyzer")
+    {
+        eprintln!("mock-probe-verus: verus-analyzer panicked");
+        process::exit(101);
+    }
+
     let output_path = args
         .windows(2)
         .find(|w| w[0] == "-o" || w[0] == "--output")